@@ -0,0 +1,86 @@
+//! Benchmarks for clients running commands in parallel, each on its own
+//! thread with its own connection, run through the dispatcher.
+//!
+//! Clients on different databases only share the keyspace lock, so they
+//! scale with the threads; clients on one database take turns on its lock.
+//!
+//! Run with `cargo bench --bench concurrency`.
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use miniredis_rs::connection::ConnCtx;
+use miniredis_rs::db::SharedState;
+use miniredis_rs::dispatch::{CommandTable, dispatch};
+
+/// Clients running at once.
+const CLIENTS: usize = 4;
+
+/// Distinct keys each client writes.
+const KEYS: usize = 100;
+
+fn args(args: &[&str]) -> Vec<Vec<u8>> {
+    args.iter().map(|a| a.as_bytes().to_vec()).collect()
+}
+
+/// Run `iters` SET/GET pairs on each of CLIENTS threads and return the
+/// wall time. Client `i` uses database `db(i)`.
+fn run_clients(
+    table: &CommandTable,
+    state: &Arc<SharedState>,
+    iters: u64,
+    db: fn(usize) -> usize,
+) -> Duration {
+    let start = Instant::now();
+    std::thread::scope(|s| {
+        for client in 0..CLIENTS {
+            s.spawn(move || {
+                let mut ctx = ConnCtx::new();
+                let select = args(&["SELECT", &db(client).to_string()]);
+                dispatch(table, state, &mut ctx, &select);
+                let keys: Vec<String> = (0..KEYS).map(|k| format!("c{client}:k{k}")).collect();
+                for i in 0..iters as usize {
+                    let key = &keys[i % KEYS];
+                    let set = args(&["SET", key, "value"]);
+                    black_box(dispatch(table, state, &mut ctx, &set));
+                    let get = args(&["GET", key]);
+                    black_box(dispatch(table, state, &mut ctx, &get));
+                }
+            });
+        }
+    });
+    start.elapsed()
+}
+
+fn bench_concurrency(c: &mut Criterion) {
+    let table = CommandTable::new();
+    let state = SharedState::new();
+
+    c.bench_function("set_get_separate_dbs", |bench| {
+        bench.iter_custom(|iters| run_clients(&table, &state, iters, |client| client))
+    });
+    c.bench_function("set_get_same_db", |bench| {
+        bench.iter_custom(|iters| run_clients(&table, &state, iters, |_| 0))
+    });
+    c.bench_function("get_same_db", |bench| {
+        bench.iter_custom(|iters| {
+            let start = Instant::now();
+            std::thread::scope(|s| {
+                for _ in 0..CLIENTS {
+                    s.spawn(|| {
+                        let mut ctx = ConnCtx::new();
+                        let get = args(&["GET", "shared"]);
+                        for _ in 0..iters {
+                            black_box(dispatch(&table, &state, &mut ctx, &get));
+                        }
+                    });
+                }
+            });
+            start.elapsed()
+        })
+    });
+}
+
+criterion_group!(benches, bench_concurrency);
+criterion_main!(benches);
@@ -0,0 +1,127 @@
+//! Benchmarks for the sorted set commands (`cmd/sorted_set.rs`), run
+//! through the dispatcher against a large set.
+//!
+//! Run with `cargo bench --bench sorted_set`.
+use std::hint::black_box;
+use std::sync::Arc;
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use miniredis_rs::connection::ConnCtx;
+use miniredis_rs::db::SharedState;
+use miniredis_rs::dispatch::{CommandTable, dispatch};
+
+/// Members in the benchmark set.
+const SIZE: usize = 100_000;
+
+struct Bench {
+    table: CommandTable,
+    state: Arc<SharedState>,
+    ctx: ConnCtx,
+}
+
+impl Bench {
+    /// A server with `zs` holding SIZE members. Member `m{i}` has score
+    /// `i`; `lex` holds the same members all with score 0.
+    fn new() -> Self {
+        let mut b = Bench {
+            table: CommandTable::new(),
+            state: SharedState::new(),
+            ctx: ConnCtx::new(),
+        };
+        for chunk in (0..SIZE).collect::<Vec<_>>().chunks(1000) {
+            let mut zs = vec!["ZADD".to_string(), "zs".to_string()];
+            let mut lex = vec!["ZADD".to_string(), "lex".to_string()];
+            for i in chunk {
+                zs.extend([i.to_string(), member(*i)]);
+                lex.extend(["0".to_string(), member(*i)]);
+            }
+            b.run(&zs.iter().map(String::as_str).collect::<Vec<_>>());
+            b.run(&lex.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        b
+    }
+
+    fn run(&mut self, args: &[&str]) {
+        let args: Vec<Vec<u8>> = args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        black_box(dispatch(&self.table, &self.state, &mut self.ctx, &args));
+    }
+}
+
+fn member(i: usize) -> String {
+    format!("m{i:08}")
+}
+
+fn bench_sorted_set(c: &mut Criterion) {
+    let mut b = Bench::new();
+    let mid = member(SIZE / 2);
+    let lex_min = format!("[{mid}");
+
+    c.bench_function("zadd_update", |bench| {
+        bench.iter(|| b.run(&["ZADD", "zs", "50000.5", &mid]))
+    });
+    c.bench_function("zincrby", |bench| {
+        bench.iter(|| b.run(&["ZINCRBY", "zs", "0", &mid]))
+    });
+    c.bench_function("zscore", |bench| {
+        bench.iter(|| b.run(&["ZSCORE", "zs", &mid]))
+    });
+    c.bench_function("zrank", |bench| {
+        bench.iter(|| b.run(&["ZRANK", "zs", &mid]))
+    });
+    c.bench_function("zrange_10", |bench| {
+        bench.iter(|| b.run(&["ZRANGE", "zs", "50000", "50009"]))
+    });
+    c.bench_function("zrevrange_10_withscores", |bench| {
+        bench.iter(|| b.run(&["ZREVRANGE", "zs", "50000", "50009", "WITHSCORES"]))
+    });
+    c.bench_function("zrangebyscore_limit_10", |bench| {
+        bench.iter(|| b.run(&["ZRANGEBYSCORE", "zs", "(50000", "+inf", "LIMIT", "0", "10"]))
+    });
+    c.bench_function("zrange_byscore_rev_10", |bench| {
+        bench.iter(|| {
+            b.run(&[
+                "ZRANGE", "zs", "60000", "50000", "BYSCORE", "REV", "LIMIT", "0", "10",
+            ])
+        })
+    });
+    c.bench_function("zrangebylex_limit_10", |bench| {
+        bench.iter(|| b.run(&["ZRANGEBYLEX", "lex", &lex_min, "+", "LIMIT", "0", "10"]))
+    });
+    c.bench_function("zcount", |bench| {
+        bench.iter(|| b.run(&["ZCOUNT", "zs", "25000", "75000"]))
+    });
+    c.bench_function("zlexcount", |bench| {
+        bench.iter(|| b.run(&["ZLEXCOUNT", "lex", &lex_min, "+"]))
+    });
+    c.bench_function("zpopmin_zadd", |bench| {
+        bench.iter(|| {
+            b.run(&["ZPOPMIN", "zs"]);
+            b.run(&["ZADD", "zs", "0", &member(0)]);
+        })
+    });
+    c.bench_function("zrem_zadd", |bench| {
+        bench.iter(|| {
+            b.run(&["ZREM", "zs", &mid]);
+            b.run(&["ZADD", "zs", "50000", &mid]);
+        })
+    });
+    c.bench_function("zunionstore_small", |bench| {
+        bench.iter_batched(
+            || {
+                let mut b = Bench {
+                    table: CommandTable::new(),
+                    state: SharedState::new(),
+                    ctx: ConnCtx::new(),
+                };
+                b.run(&["ZADD", "a", "1", "x", "2", "y", "3", "z"]);
+                b.run(&["ZADD", "b", "4", "y", "5", "w"]);
+                b
+            },
+            |mut b| b.run(&["ZUNIONSTORE", "dst", "2", "a", "b"]),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_sorted_set);
+criterion_main!(benches);
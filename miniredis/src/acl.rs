@@ -0,0 +1,881 @@
+//! Access control lists (ACL SETUSER and friends).
+//!
+//! Every connection runs as a user, "default" until it authenticates as
+//! someone else. A user has passwords, command rules (`+@read`, `-del`,
+//! `+config|get`), key patterns (`~cache:*`, `%R~stats:*`) and channel
+//! patterns (`&news.*`). Commands a user may not run fail with NOPERM and
+//! are recorded in the ACL LOG.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+use crate::keys::glob_match;
+use crate::keyspec::command_keys;
+
+/// ACL categories and the commands in them, separated by spaces. A
+/// `CMD|SUB` entry puts a subcommand in categories of its own; every
+/// command that isn't `fast` is `slow`.
+pub const CATEGORIES: &[(&str, &str)] = &[
+    (
+        "keyspace",
+        "DEL UNLINK EXISTS TYPE RENAME RENAMENX EXPIRE EXPIREAT PEXPIRE PEXPIREAT PERSIST TTL \
+         PTTL KEYS SCAN TOUCH RANDOMKEY OBJECT EXPIRETIME PEXPIRETIME COPY MOVE DUMP RESTORE \
+         DBSIZE FLUSHDB FLUSHALL SWAPDB",
+    ),
+    (
+        "read",
+        "EXISTS TYPE TTL PTTL KEYS SCAN TOUCH RANDOMKEY OBJECT EXPIRETIME PEXPIRETIME DUMP \
+         SORT_RO DBSIZE GET MGET STRLEN GETRANGE SUBSTR GETBIT BITCOUNT BITPOS BITFIELD_RO \
+         HGET HMGET HEXISTS HGETALL HKEYS HVALS HLEN HSTRLEN HSCAN HRANDFIELD HTTL HPTTL \
+         HEXPIRETIME HPEXPIRETIME LLEN LINDEX \
+         LRANGE LPOS SCARD SMEMBERS SISMEMBER SMISMEMBER SDIFF SINTER SINTERCARD SUNION \
+         SRANDMEMBER SSCAN ZCARD ZCOUNT ZSCORE ZMSCORE ZRANK ZREVRANK ZRANGE ZREVRANGE \
+         ZRANGEBYSCORE ZREVRANGEBYSCORE ZRANGEBYLEX ZREVRANGEBYLEX ZLEXCOUNT ZSCAN ZINTER \
+         ZUNION ZRANDMEMBER PFCOUNT GEODIST GEOPOS GEOSEARCH GEORADIUS_RO \
+         GEORADIUSBYMEMBER_RO XLEN XRANGE XREVRANGE XREAD XINFO XPENDING MEMORY JSON.GET \
+         JSON.MGET JSON.TYPE JSON.ARRLEN JSON.OBJKEYS BF.EXISTS BF.MEXISTS BF.INFO CF.EXISTS \
+         CMS.QUERY TOPK.LIST",
+    ),
+    (
+        "write",
+        "DEL UNLINK RENAME RENAMENX EXPIRE EXPIREAT PEXPIRE PEXPIREAT PERSIST COPY MOVE \
+         RESTORE SORT FLUSHDB FLUSHALL SWAPDB SET SETNX GETSET SETEX PSETEX MSET MSETNX INCR \
+         INCRBY INCRBYFLOAT DECR DECRBY APPEND SETRANGE GETDEL GETEX SETBIT BITOP BITFIELD \
+         HSET HSETNX HMSET HDEL HINCRBY HINCRBYFLOAT HEXPIRE HPEXPIRE HEXPIREAT HPEXPIREAT \
+         HPERSIST HGETDEL HGETEX HSETEX LPUSH RPUSH LPUSHX RPUSHX LPOP \
+         RPOP LSET LINSERT LREM LTRIM RPOPLPUSH LMOVE LMPOP BLPOP BRPOP BRPOPLPUSH BLMOVE \
+         BLMPOP SADD SREM SDIFFSTORE SINTERSTORE SUNIONSTORE SMOVE SPOP ZADD ZINCRBY ZREM \
+         ZREMRANGEBYRANK ZREMRANGEBYSCORE ZREMRANGEBYLEX ZUNIONSTORE ZINTERSTORE ZPOPMIN \
+         ZPOPMAX ZMPOP BZPOPMIN BZPOPMAX BZMPOP PFADD PFMERGE GEOADD GEORADIUS \
+         GEORADIUSBYMEMBER GEOSEARCHSTORE XADD XDEL XTRIM XGROUP XREADGROUP XACK XCLAIM \
+         XAUTOCLAIM JSON.SET JSON.DEL JSON.FORGET JSON.ARRAPPEND JSON.NUMINCRBY \
+         JSON.STRAPPEND BF.RESERVE BF.ADD BF.MADD CF.ADD CF.DEL CMS.INITBYDIM CMS.INCRBY \
+         TOPK.RESERVE TOPK.ADD",
+    ),
+    (
+        "string",
+        "GET SET SETNX GETSET SETEX PSETEX MGET MSET MSETNX INCR INCRBY INCRBYFLOAT DECR \
+         DECRBY STRLEN APPEND GETRANGE SUBSTR SETRANGE GETDEL GETEX",
+    ),
+    (
+        "bitmap",
+        "GETBIT SETBIT BITCOUNT BITOP BITPOS BITFIELD BITFIELD_RO",
+    ),
+    (
+        "hash",
+        "HSET HSETNX HMSET HGET HMGET HDEL HEXISTS HGETALL HKEYS HVALS HLEN HINCRBY \
+         HINCRBYFLOAT HSTRLEN HSCAN HRANDFIELD HEXPIRE HPEXPIRE HEXPIREAT HPEXPIREAT HTTL \
+         HPTTL HEXPIRETIME HPEXPIRETIME HPERSIST HGETDEL HGETEX HSETEX",
+    ),
+    (
+        "list",
+        "LPUSH RPUSH LPUSHX RPUSHX LPOP RPOP LLEN LINDEX LRANGE LSET LINSERT LREM LTRIM \
+         RPOPLPUSH LMOVE LPOS LMPOP BLPOP BRPOP BRPOPLPUSH BLMOVE BLMPOP SORT SORT_RO",
+    ),
+    (
+        "set",
+        "SADD SREM SCARD SMEMBERS SISMEMBER SMISMEMBER SDIFF SDIFFSTORE SINTER SINTERSTORE \
+         SINTERCARD SUNION SUNIONSTORE SMOVE SPOP SRANDMEMBER SSCAN SORT SORT_RO",
+    ),
+    (
+        "sortedset",
+        "ZADD ZCARD ZCOUNT ZINCRBY ZSCORE ZMSCORE ZRANK ZREVRANK ZREM ZRANGE ZREVRANGE \
+         ZRANGEBYSCORE ZREVRANGEBYSCORE ZRANGEBYLEX ZREVRANGEBYLEX ZLEXCOUNT ZREMRANGEBYRANK \
+         ZREMRANGEBYSCORE ZREMRANGEBYLEX ZUNIONSTORE ZINTERSTORE ZPOPMIN ZPOPMAX ZMPOP \
+         BZPOPMIN BZPOPMAX BZMPOP ZSCAN ZINTER ZUNION ZRANDMEMBER SORT SORT_RO",
+    ),
+    ("hyperloglog", "PFADD PFCOUNT PFMERGE"),
+    (
+        "geo",
+        "GEOADD GEODIST GEOPOS GEORADIUS GEORADIUS_RO GEORADIUSBYMEMBER GEORADIUSBYMEMBER_RO \
+         GEOSEARCH GEOSEARCHSTORE",
+    ),
+    (
+        "stream",
+        "XADD XLEN XRANGE XREVRANGE XREAD XINFO XDEL XTRIM XGROUP XREADGROUP XACK XPENDING \
+         XCLAIM XAUTOCLAIM",
+    ),
+    (
+        "json",
+        "JSON.SET JSON.GET JSON.DEL JSON.FORGET JSON.MGET JSON.TYPE JSON.ARRAPPEND \
+         JSON.ARRLEN JSON.NUMINCRBY JSON.STRAPPEND JSON.OBJKEYS",
+    ),
+    (
+        "bloom",
+        "BF.RESERVE BF.ADD BF.MADD BF.EXISTS BF.MEXISTS BF.INFO",
+    ),
+    ("cuckoo", "CF.ADD CF.EXISTS CF.DEL"),
+    ("cms", "CMS.INITBYDIM CMS.INCRBY CMS.QUERY"),
+    ("topk", "TOPK.RESERVE TOPK.ADD TOPK.LIST"),
+    (
+        "pubsub",
+        "PUBLISH PUBSUB SUBSCRIBE PSUBSCRIBE UNSUBSCRIBE PUNSUBSCRIBE SPUBLISH SSUBSCRIBE \
+         SUNSUBSCRIBE",
+    ),
+    (
+        "admin",
+        "CONFIG SAVE BGSAVE LASTSAVE DEBUG MINIREDIS.FASTFORWARD MINIREDIS.FAULT ACL|SETUSER \
+         ACL|GETUSER ACL|DELUSER ACL|LIST ACL|USERS ACL|LOG REPLICAOF SLAVEOF ROLE MONITOR \
+         SLOWLOG LATENCY",
+    ),
+    (
+        "fast",
+        "PING ECHO SELECT AUTH HELLO EXISTS TYPE EXPIRE EXPIREAT PEXPIRE PEXPIREAT PERSIST \
+         TTL PTTL TOUCH EXPIRETIME PEXPIRETIME RENAMENX UNLINK MOVE DBSIZE LASTSAVE TIME GET \
+         SETNX GETSET MGET INCR INCRBY INCRBYFLOAT DECR DECRBY STRLEN APPEND GETDEL GETEX \
+         GETBIT BITFIELD_RO HSET HSETNX HMSET HGET HMGET HDEL HEXISTS HLEN HSTRLEN HINCRBY \
+         HINCRBYFLOAT HEXPIRE HPEXPIRE HEXPIREAT HPEXPIREAT HTTL HPTTL HEXPIRETIME \
+         HPEXPIRETIME HPERSIST HGETDEL HGETEX HSETEX LPUSH RPUSH LPUSHX RPUSHX LPOP RPOP LLEN \
+         SADD SREM SCARD \
+         SISMEMBER SMISMEMBER SMOVE SPOP ZADD ZCARD ZCOUNT ZINCRBY ZSCORE ZMSCORE ZRANK \
+         ZREVRANK ZREM ZLEXCOUNT ZPOPMIN ZPOPMAX BZPOPMIN BZPOPMAX PFADD XADD XLEN XDEL XACK \
+         XCLAIM XAUTOCLAIM PUBLISH SPUBLISH MULTI DISCARD WATCH UNWATCH ASKING READONLY \
+         READWRITE ROLE",
+    ),
+    ("slow", ""),
+    (
+        "blocking",
+        "BLPOP BRPOP BRPOPLPUSH BLMOVE BLMPOP BZPOPMIN BZPOPMAX BZMPOP XREAD XREADGROUP",
+    ),
+    (
+        "dangerous",
+        "FLUSHDB FLUSHALL KEYS SWAPDB SORT RESTORE CONFIG SAVE BGSAVE LASTSAVE DEBUG \
+         MINIREDIS.FASTFORWARD MINIREDIS.FAULT INFO ACL|SETUSER ACL|GETUSER ACL|DELUSER \
+         ACL|LIST ACL|USERS ACL|LOG REPLICAOF SLAVEOF ROLE MONITOR SLOWLOG LATENCY",
+    ),
+    (
+        "connection",
+        "PING ECHO QUIT SELECT AUTH HELLO CLIENT COMMAND WAIT ASKING READONLY READWRITE",
+    ),
+    ("transaction", "MULTI EXEC DISCARD WATCH UNWATCH"),
+    (
+        "scripting",
+        "EVAL EVAL_RO EVALSHA EVALSHA_RO SCRIPT FCALL FCALL_RO FUNCTION",
+    ),
+];
+
+/// Commands whose first argument is a subcommand. ACL rules and errors
+/// name these as `config|get`.
+const CONTAINER_COMMANDS: &[&str] = &[
+    "ACL", "CLIENT", "CLUSTER", "COMMAND", "CONFIG", "FUNCTION", "MEMORY", "OBJECT", "PUBSUB",
+    "SCRIPT", "XGROUP", "XINFO", "SLOWLOG", "LATENCY",
+];
+
+/// The name ACL errors and the ACL LOG use for a command: `get`, or
+/// `config|get` for a subcommand.
+pub fn full_command_name(cmd: &str, first_arg: Option<&[u8]>) -> String {
+    match first_arg {
+        Some(sub) if CONTAINER_COMMANDS.contains(&cmd) => format!(
+            "{}|{}",
+            cmd.to_lowercase(),
+            String::from_utf8_lossy(sub).to_lowercase()
+        ),
+        _ => cmd.to_lowercase(),
+    }
+}
+
+/// Whether `name` (uppercase) is in `category`. `name` is either a
+/// command or a `CMD|SUB` pair.
+pub fn in_category(name: &str, category: &str) -> bool {
+    let lists = |cmds: &str, name: &str| cmds.split(' ').any(|c| c == name);
+    let listed = |cat: &str, name: &str| {
+        CATEGORIES
+            .iter()
+            .any(|(c, cmds)| *c == cat && lists(cmds, name))
+    };
+    // A subcommand listed anywhere only has the categories it's listed in.
+    let name = match name.split_once('|') {
+        Some((cmd, _)) if !CATEGORIES.iter().any(|(_, cmds)| lists(cmds, name)) => cmd,
+        _ => name,
+    };
+    if category == "all" {
+        return true;
+    }
+    if category == "slow" {
+        return !listed("fast", name);
+    }
+    listed(category, name)
+}
+
+/// Most ACL LOG entries kept (`acllog-max-len`).
+const LOG_MAX_LEN: usize = 128;
+
+/// Why a command was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Denied {
+    /// The user may not run the command.
+    Command,
+    /// The user may not access this key.
+    Key(Vec<u8>),
+    /// The user may not access this channel.
+    Channel(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RuleTarget {
+    /// `@category`, including `@all`.
+    Category(String),
+    /// A command, uppercase.
+    Command(String),
+    /// A `CMD|SUB` pair, uppercase.
+    Subcommand(String, String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CommandRule {
+    allow: bool,
+    target: RuleTarget,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeyPattern {
+    pattern: Vec<u8>,
+    read: bool,
+    write: bool,
+}
+
+/// An ACL user.
+#[derive(Debug, Clone)]
+pub struct AclUser {
+    pub name: String,
+    pub enabled: bool,
+    pub nopass: bool,
+    /// SHA-256 hashes of the passwords, hex encoded.
+    passwords: Vec<String>,
+    /// Command rules, in the order given. The last rule matching a
+    /// command decides.
+    commands: Vec<CommandRule>,
+    keys: Vec<KeyPattern>,
+    channels: Vec<Vec<u8>>,
+}
+
+impl AclUser {
+    /// A new user: off, without passwords, commands, keys or channels.
+    pub fn new(name: &str) -> Self {
+        AclUser {
+            name: name.to_owned(),
+            enabled: false,
+            nopass: false,
+            passwords: Vec::new(),
+            commands: Vec::new(),
+            keys: Vec::new(),
+            channels: Vec::new(),
+        }
+    }
+
+    /// The default user: may do anything, without a password.
+    fn default_user() -> Self {
+        let mut user = Self::new("default");
+        for rule in ["on", "nopass", "~*", "&*", "+@all"] {
+            user.apply(rule.as_bytes(), |_| true).unwrap();
+        }
+        user
+    }
+
+    /// Whether `password` is one of the user's passwords.
+    pub fn check_password(&self, password: &str) -> bool {
+        self.nopass || self.passwords.contains(&sha256_hex(password.as_bytes()))
+    }
+
+    /// Apply a single ACL SETUSER rule. `known_command` says whether a
+    /// command name (uppercase) exists.
+    pub fn apply(
+        &mut self,
+        rule: &[u8],
+        known_command: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        let text = String::from_utf8_lossy(rule).to_string();
+        match text.to_lowercase().as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.apply(b"~*", known_command)?,
+            "resetkeys" => self.keys.clear(),
+            "allchannels" => self.apply(b"&*", known_command)?,
+            "resetchannels" => self.channels.clear(),
+            "allcommands" => self.apply(b"+@all", known_command)?,
+            "nocommands" => self.apply(b"-@all", known_command)?,
+            "reset" => {
+                *self = AclUser::new(&self.name);
+            }
+            _ => return self.apply_pattern(rule, &text, known_command),
+        }
+        Ok(())
+    }
+
+    fn apply_pattern(
+        &mut self,
+        rule: &[u8],
+        text: &str,
+        known_command: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        match rule.first() {
+            Some(b'>') => {
+                let hash = sha256_hex(&rule[1..]);
+                if !self.passwords.contains(&hash) {
+                    self.passwords.push(hash);
+                }
+                self.nopass = false;
+            }
+            Some(b'#') => {
+                let hash = &text[1..];
+                if hash.len() != 64 || !hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+                {
+                    return Err("The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters".into());
+                }
+                if !self.passwords.iter().any(|p| p == hash) {
+                    self.passwords.push(hash.to_owned());
+                }
+                self.nopass = false;
+            }
+            Some(b'<') | Some(b'!') => {
+                let hash = if rule[0] == b'<' {
+                    sha256_hex(&rule[1..])
+                } else {
+                    text[1..].to_owned()
+                };
+                let before = self.passwords.len();
+                self.passwords.retain(|p| *p != hash);
+                if self.passwords.len() == before {
+                    return Err(
+                        "The password you are trying to remove from the user does not exist".into(),
+                    );
+                }
+            }
+            Some(b'~') | Some(b'%') => {
+                let (read, write, pattern) = parse_key_pattern(rule)?;
+                if self
+                    .keys
+                    .iter()
+                    .any(|k| k.pattern == b"*" && k.read && k.write)
+                {
+                    return Err("Adding a pattern after the * pattern (or the 'allkeys' flag) is not valid and does not have any effect. Try 'resetkeys' to start with an empty list of patterns".into());
+                }
+                if pattern == b"*" && read && write {
+                    self.keys.clear();
+                }
+                self.keys.push(KeyPattern {
+                    pattern: pattern.to_vec(),
+                    read,
+                    write,
+                });
+            }
+            Some(b'&') => {
+                if self.channels.iter().any(|c| c == b"*") {
+                    return Err("Adding a pattern after the * pattern (or the 'allchannels' flag) is not valid and does not have any effect. Try 'resetchannels' to start with an empty list of channels".into());
+                }
+                if &rule[1..] == b"*" {
+                    self.channels.clear();
+                }
+                self.channels.push(rule[1..].to_vec());
+            }
+            Some(b'+') | Some(b'-') => {
+                let allow = rule[0] == b'+';
+                let name = text[1..].to_uppercase();
+                let target = if let Some(cat) = name.strip_prefix('@') {
+                    let cat = cat.to_lowercase();
+                    if cat != "all" && !CATEGORIES.iter().any(|(c, _)| *c == cat) {
+                        return Err("Unknown command or category name in ACL".into());
+                    }
+                    RuleTarget::Category(cat)
+                } else if let Some((cmd, sub)) = name.split_once('|') {
+                    if !known_command(cmd) || sub.is_empty() || sub.contains('|') {
+                        return Err("Unknown command or category name in ACL".into());
+                    }
+                    RuleTarget::Subcommand(cmd.to_owned(), sub.to_owned())
+                } else {
+                    if !known_command(&name) {
+                        return Err("Unknown command or category name in ACL".into());
+                    }
+                    RuleTarget::Command(name)
+                };
+                if target == RuleTarget::Category("all".into()) {
+                    self.commands.clear();
+                }
+                self.commands.retain(|r| r.target != target);
+                self.commands.push(CommandRule { allow, target });
+            }
+            _ => return Err("Syntax error".into()),
+        }
+        Ok(())
+    }
+
+    /// Whether the user may run `cmd` (uppercase) with `args`. Key and
+    /// channel arguments are checked too.
+    pub fn check(&self, cmd: &str, args: &[Vec<u8>]) -> Result<(), Denied> {
+        if !self.command_allowed(cmd, args.first()) {
+            return Err(Denied::Command);
+        }
+
+        for key in command_keys(cmd, args) {
+            let name = &args[key.index];
+            let ok = self.keys.iter().any(|k| {
+                (k.read || !key.read) && (k.write || !key.write) && glob_match(&k.pattern, name)
+            });
+            if !ok {
+                return Err(Denied::Key(name.clone()));
+            }
+        }
+
+        let channels: &[Vec<u8>] = match cmd {
+            "PUBLISH" | "SPUBLISH" => &args[..args.len().min(1)],
+            "SUBSCRIBE" | "PSUBSCRIBE" | "SSUBSCRIBE" => args,
+            _ => &[],
+        };
+        for channel in channels {
+            let ok = self.channels.iter().any(|c| {
+                if cmd == "PSUBSCRIBE" {
+                    // A pattern has to be one of the user's patterns.
+                    c == b"*" || c == channel
+                } else {
+                    glob_match(c, channel)
+                }
+            });
+            if !ok {
+                return Err(Denied::Channel(channel.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn command_allowed(&self, cmd: &str, first_arg: Option<&Vec<u8>>) -> bool {
+        let full = full_command_name(cmd, first_arg.map(|a| a.as_slice())).to_uppercase();
+        self.commands
+            .iter()
+            .rev()
+            .find(|r| match &r.target {
+                RuleTarget::Category(cat) => in_category(&full, cat),
+                RuleTarget::Command(c) => c == cmd,
+                RuleTarget::Subcommand(c, s) => {
+                    c == cmd && first_arg.is_some_and(|a| a.eq_ignore_ascii_case(s.as_bytes()))
+                }
+            })
+            .is_some_and(|r| r.allow)
+    }
+
+    /// The user's flags: `on` or `off`, and `nopass`.
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+
+    pub fn password_hashes(&self) -> &[String] {
+        &self.passwords
+    }
+
+    /// The command rules, like `-@all +@read -keys`.
+    pub fn describe_commands(&self) -> String {
+        let mut rules = Vec::new();
+        if !matches!(self.commands.first(), Some(r) if r.target == RuleTarget::Category("all".into()))
+        {
+            rules.push("-@all".to_owned());
+        }
+        for r in &self.commands {
+            let sign = if r.allow { '+' } else { '-' };
+            rules.push(match &r.target {
+                RuleTarget::Category(c) => format!("{sign}@{c}"),
+                RuleTarget::Command(c) => format!("{sign}{}", c.to_lowercase()),
+                RuleTarget::Subcommand(c, s) => {
+                    format!("{sign}{}|{}", c.to_lowercase(), s.to_lowercase())
+                }
+            });
+        }
+        rules.join(" ")
+    }
+
+    /// The key patterns, like `~cache:* %R~stats:*`.
+    pub fn describe_keys(&self) -> String {
+        self.keys
+            .iter()
+            .map(|k| {
+                let prefix = match (k.read, k.write) {
+                    (true, true) => "~",
+                    (true, false) => "%R~",
+                    _ => "%W~",
+                };
+                format!("{prefix}{}", String::from_utf8_lossy(&k.pattern))
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The channel patterns, like `&news.*`.
+    pub fn describe_channels(&self) -> String {
+        self.channels
+            .iter()
+            .map(|c| format!("&{}", String::from_utf8_lossy(c)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The user as ACL LIST shows it.
+    pub fn describe(&self) -> String {
+        let mut parts = vec![format!("user {}", self.name)];
+        parts.extend(self.flags().iter().map(|f| f.to_string()));
+        parts.extend(self.passwords.iter().map(|p| format!("#{p}")));
+        let keys = self.describe_keys();
+        if !keys.is_empty() {
+            parts.push(keys);
+        }
+        let channels = self.describe_channels();
+        parts.push(if channels.is_empty() {
+            "resetchannels".to_owned()
+        } else {
+            channels
+        });
+        parts.push(self.describe_commands());
+        parts.join(" ")
+    }
+}
+
+/// Parse `~pattern`, `%R~pattern`, `%W~pattern` or `%RW~pattern`.
+fn parse_key_pattern(rule: &[u8]) -> Result<(bool, bool, &[u8]), String> {
+    if rule[0] == b'~' {
+        return Ok((true, true, &rule[1..]));
+    }
+    let tilde = rule.iter().position(|&b| b == b'~').ok_or("Syntax error")?;
+    let (mut read, mut write) = (false, false);
+    for &b in &rule[1..tilde] {
+        match b.to_ascii_uppercase() {
+            b'R' => read = true,
+            b'W' => write = true,
+            _ => return Err("Syntax error".into()),
+        }
+    }
+    if !read && !write {
+        return Err("Syntax error".into());
+    }
+    Ok((read, write, &rule[tilde + 1..]))
+}
+
+/// An ACL LOG entry.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub count: u64,
+    /// "command", "key", "channel" or "auth".
+    pub reason: &'static str,
+    /// "toplevel", "multi" or "lua".
+    pub context: &'static str,
+    /// The command, key or channel that was refused.
+    pub object: String,
+    pub username: String,
+    pub client_info: String,
+    pub entry_id: u64,
+    pub created: SystemTime,
+    pub updated: SystemTime,
+}
+
+/// All users, and the ACL LOG.
+#[derive(Debug)]
+pub struct Acl {
+    users: BTreeMap<String, AclUser>,
+    /// Newest first.
+    log: VecDeque<LogEntry>,
+    next_entry_id: u64,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Acl {
+    pub fn new() -> Self {
+        let mut users = BTreeMap::new();
+        users.insert("default".to_owned(), AclUser::default_user());
+        Acl {
+            users,
+            log: VecDeque::new(),
+            next_entry_id: 0,
+        }
+    }
+
+    /// Whether new connections have to authenticate: the default user is
+    /// off or has a password.
+    pub fn auth_required(&self) -> bool {
+        self.users
+            .get("default")
+            .is_none_or(|u| !u.enabled || !u.nopass)
+    }
+
+    /// Whether `username` may log in with `password`.
+    pub fn authenticate(&self, username: &str, password: &str) -> bool {
+        self.users
+            .get(username)
+            .is_some_and(|u| u.enabled && u.check_password(password))
+    }
+
+    pub fn user(&self, name: &str) -> Option<&AclUser> {
+        self.users.get(name)
+    }
+
+    /// All users, by name.
+    pub fn users(&self) -> impl Iterator<Item = &AclUser> {
+        self.users.values()
+    }
+
+    /// ACL SETUSER: create or change a user. Either all rules apply, or
+    /// none do. The error is the message for the failing rule.
+    pub fn set_user(
+        &mut self,
+        name: &str,
+        rules: &[Vec<u8>],
+        known_command: impl Fn(&str) -> bool,
+    ) -> Result<(), String> {
+        let mut user = self
+            .users
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| AclUser::new(name));
+        for rule in rules {
+            user.apply(rule, &known_command).map_err(|e| {
+                format!(
+                    "ERR Error in ACL SETUSER modifier '{}': {e}",
+                    String::from_utf8_lossy(rule)
+                )
+            })?;
+        }
+        self.users.insert(name.to_owned(), user);
+        Ok(())
+    }
+
+    /// Replace all users, keeping the ACL LOG.
+    pub fn replace_users(&mut self, users: impl IntoIterator<Item = AclUser>) {
+        self.users = users.into_iter().map(|u| (u.name.clone(), u)).collect();
+    }
+
+    /// Delete a user. Returns whether it existed.
+    pub fn del_user(&mut self, name: &str) -> bool {
+        self.users.remove(name).is_some()
+    }
+
+    /// Add an entry to the ACL LOG. An entry for the same refusal in the
+    /// last minute is counted again instead.
+    pub fn log(
+        &mut self,
+        reason: &'static str,
+        context: &'static str,
+        object: String,
+        username: String,
+        client_info: String,
+        now: SystemTime,
+    ) {
+        let similar = self.log.iter().position(|e| {
+            e.reason == reason
+                && e.context == context
+                && e.object == object
+                && e.username == username
+                && now.duration_since(e.updated).unwrap_or_default() < Duration::from_secs(60)
+        });
+        if let Some(i) = similar {
+            let mut entry = self.log.remove(i).unwrap();
+            entry.count += 1;
+            entry.updated = now;
+            entry.client_info = client_info;
+            self.log.push_front(entry);
+            return;
+        }
+
+        self.log.push_front(LogEntry {
+            count: 1,
+            reason,
+            context,
+            object,
+            username,
+            client_info,
+            entry_id: self.next_entry_id,
+            created: now,
+            updated: now,
+        });
+        self.next_entry_id += 1;
+        self.log.truncate(LOG_MAX_LEN);
+    }
+
+    /// ACL LOG entries, newest first.
+    pub fn log_entries(&self) -> impl Iterator<Item = &LogEntry> {
+        self.log.iter()
+    }
+
+    pub fn reset_log(&mut self) {
+        self.log.clear();
+    }
+}
+
+/// SHA-256 of `data`, hex encoded, as ACL stores passwords.
+pub fn sha256_hex(data: &[u8]) -> String {
+    const K: [u32; 64] = [
+        0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
+        0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
+        0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
+        0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
+        0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
+        0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
+        0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
+        0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+        0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+        0xc67178f2,
+    ];
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (x, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *x = x.wrapping_add(v);
+        }
+    }
+
+    h.iter().map(|x| format!("{x:08x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(rules: &str) -> AclUser {
+        let mut u = AclUser::new("alice");
+        for r in rules.split(' ') {
+            u.apply(r.as_bytes(), |_| true).unwrap();
+        }
+        u
+    }
+
+    fn args(s: &str) -> Vec<Vec<u8>> {
+        s.split(' ').map(|a| a.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_command_rules() {
+        let u = user("on +@read -@dangerous +set +config|get ~*");
+        assert!(u.check("GET", &args("foo")).is_ok());
+        assert!(u.check("SET", &args("foo bar")).is_ok());
+        assert_eq!(u.check("DEL", &args("foo")), Err(Denied::Command));
+        assert_eq!(u.check("KEYS", &args("*")), Err(Denied::Command));
+        assert!(u.check("CONFIG", &args("GET maxmemory")).is_ok());
+        assert_eq!(
+            u.check("CONFIG", &args("SET maxmemory 1")),
+            Err(Denied::Command)
+        );
+        assert_eq!(
+            u.describe_commands(),
+            "-@all +@read -@dangerous +set +config|get"
+        );
+
+        let u = user("+@all -flushall");
+        assert_eq!(u.describe_commands(), "+@all -flushall");
+        assert_eq!(u.check("FLUSHALL", &[]), Err(Denied::Command));
+    }
+
+    #[test]
+    fn test_key_and_channel_patterns() {
+        let u = user("+@all ~app:* %R~ro:* %W~wo:* &news.*");
+        assert!(u.check("GET", &args("app:1")).is_ok());
+        assert!(u.check("GET", &args("ro:1")).is_ok());
+        assert_eq!(
+            u.check("SET", &args("ro:1 v")),
+            Err(Denied::Key(b"ro:1".to_vec()))
+        );
+        assert!(u.check("SET", &args("wo:1 v")).is_ok());
+        assert_eq!(
+            u.check("GET", &args("wo:1")),
+            Err(Denied::Key(b"wo:1".to_vec()))
+        );
+        assert_eq!(
+            u.check("MGET", &args("app:1 other")),
+            Err(Denied::Key(b"other".to_vec()))
+        );
+        assert!(u.check("PUBLISH", &args("news.tech hi")).is_ok());
+        assert_eq!(
+            u.check("PUBLISH", &args("sport hi")),
+            Err(Denied::Channel(b"sport".to_vec()))
+        );
+        assert!(u.check("PSUBSCRIBE", &args("news.*")).is_ok());
+        assert!(u.check("PSUBSCRIBE", &args("news.t*")).is_err());
+        assert_eq!(u.describe_keys(), "~app:* %R~ro:* %W~wo:*");
+    }
+
+    #[test]
+    fn test_set_user() {
+        let mut acl = Acl::new();
+        assert!(!acl.auth_required());
+        acl.set_user("bob", &args("on >pw1 >pw2 <pw1"), |_| true)
+            .unwrap();
+        assert!(acl.authenticate("bob", "pw2"));
+        assert!(!acl.authenticate("bob", "pw1"));
+
+        // Rules apply all or nothing.
+        let err = acl
+            .set_user("bob", &args("off +@nosuch"), |_| true)
+            .unwrap_err();
+        assert!(err.contains("'+@nosuch'"));
+        assert!(acl.user("bob").unwrap().enabled);
+
+        acl.set_user("default", &args(">secret"), |_| true).unwrap();
+        assert!(acl.auth_required());
+    }
+}
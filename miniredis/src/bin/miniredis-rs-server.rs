@@ -12,16 +12,23 @@
 //!   tls-cert-file <p>  – server certificate path
 //!   tls-key-file <p>   – server private key path
 //!   tls-ca-cert-file <p> – CA / client certificate path
+//!   dir <path>         – directory of the RDB file (default .)
+//!   dbfilename <name>  – enable RDB persistence, see below
 //!   appendonly …       – silently ignored
 //!   cluster-enabled …  – silently ignored
 //!   cluster-config-file … – silently ignored
 //!
+//! With `dbfilename` (or `--dbfilename <path>` on the command line) the
+//! data is loaded from that RDB file on start, if it exists, and saved to it
+//! on exit. SAVE and BGSAVE write to it too.
+//!
 //! Once ready, the actual listening port is printed to stdout as a single line:
 //!   PORT=<n>
 //!
 //! The process exits cleanly on SIGTERM or SIGINT.
 
 use std::io::{self, BufRead};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use miniredis_rs::Miniredis;
@@ -76,6 +83,17 @@ async fn main() {
     let mut tls_cert = String::new();
     let mut tls_key = String::new();
     let mut tls_ca_cert = String::new();
+    let mut dir: Option<String> = None;
+    let mut dbfilename: Option<String> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dbfilename" {
+            dbfilename = Some(args.next().expect("--dbfilename requires a path"));
+        } else if let Some(path) = arg.strip_prefix("--dbfilename=") {
+            dbfilename = Some(path.to_string());
+        }
+    }
 
     // Read config from stdin
     let stdin = io::stdin();
@@ -131,6 +149,14 @@ async fn main() {
                 if parts.len() > 1 => {
                     tls_ca_cert = parts[1].to_string();
                 }
+            "dir"
+                if parts.len() > 1 => {
+                    dir = Some(parts[1].to_string());
+                }
+            "dbfilename"
+                if parts.len() > 1 && dbfilename.is_none() => {
+                    dbfilename = Some(parts[1].to_string());
+                }
             // Silently ignore everything else
             _ => {}
         }
@@ -162,6 +188,27 @@ async fn main() {
         m.require_user_auth(user, pw);
     }
 
+    // RDB persistence
+    let rdb_path = dbfilename.map(|name| {
+        let path = match &dir {
+            Some(dir) => PathBuf::from(dir).join(name),
+            None => PathBuf::from(name),
+        };
+        {
+            let mut inner = m.shared_state().lock();
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                inner.dir = parent.to_string_lossy().into_owned();
+            }
+            if let Some(file) = path.file_name() {
+                inner.dbfilename = file.to_string_lossy().into_owned();
+            }
+        }
+        if path.exists() {
+            m.load_from(&path).expect("load RDB file");
+        }
+        path
+    });
+
     // Print the port – the Go test harness reads this as readiness signal.
     println!("PORT={}", m.port());
 
@@ -173,5 +220,10 @@ async fn main() {
         _ = sigint.recv() => {}
     }
 
+    if let Some(path) = &rdb_path
+        && let Err(e) = m.save_to(path)
+    {
+        eprintln!("failed to save {}: {}", path.display(), e);
+    }
     m.close().await;
 }
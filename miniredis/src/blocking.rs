@@ -0,0 +1,98 @@
+//! Queue of clients blocked in BLPOP, BZPOPMIN and friends.
+//!
+//! Redis serves blocked clients in the order they blocked: when a key gets
+//! data, the client that has waited longest for that key gets it first.
+//! Here every blocked client wakes up on `SharedState::notify` and races
+//! for the lock, so before taking from a key a client checks that nobody
+//! who blocked earlier is waiting for it.
+
+use crate::types::KeyType;
+
+#[derive(Debug)]
+struct Waiter {
+    id: u64,
+    db: usize,
+    keys: Vec<Vec<u8>>,
+    /// The type of value the client waits for. A client blocked in BLPOP
+    /// doesn't hold up a BZPOPMIN on the same key.
+    kind: KeyType,
+}
+
+/// Blocked clients, in the order they blocked.
+#[derive(Debug, Default)]
+pub struct BlockedClients {
+    waiters: Vec<Waiter>,
+    next_id: u64,
+}
+
+impl BlockedClients {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a client waiting for a `kind` value in one of `keys`. Returns
+    /// the id to pass to `unblock` and `may_serve`.
+    pub fn block(&mut self, db: usize, keys: &[Vec<u8>], kind: KeyType) -> u64 {
+        self.next_id += 1;
+        self.waiters.push(Waiter {
+            id: self.next_id,
+            db,
+            keys: keys.to_vec(),
+            kind,
+        });
+        self.next_id
+    }
+
+    pub fn unblock(&mut self, id: u64) {
+        self.waiters.retain(|w| w.id != id);
+    }
+
+    /// Whether the client `id` may take a `kind` value from `key`: it is
+    /// the longest waiting client for it, or nobody waits for it. Use
+    /// `None` for a client that isn't blocked (yet).
+    pub fn may_serve(&self, id: Option<u64>, db: usize, key: &[u8], kind: KeyType) -> bool {
+        self.waiters
+            .iter()
+            .find(|w| w.db == db && w.kind == kind && w.keys.iter().any(|k| k == key))
+            .is_none_or(|w| Some(w.id) == id)
+    }
+
+    /// Number of blocked clients.
+    pub fn len(&self) -> usize {
+        self.waiters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.waiters.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_may_serve() {
+        let mut b = BlockedClients::new();
+        let keys = |ks: &[&str]| ks.iter().map(|k| k.as_bytes().to_vec()).collect::<Vec<_>>();
+        assert!(b.may_serve(None, 0, b"a", KeyType::List));
+
+        let first = b.block(0, &keys(&["a", "b"]), KeyType::List);
+        let second = b.block(0, &keys(&["b", "c"]), KeyType::List);
+        assert_eq!(b.len(), 2);
+
+        assert!(b.may_serve(Some(first), 0, b"b", KeyType::List));
+        assert!(!b.may_serve(Some(second), 0, b"b", KeyType::List));
+        assert!(b.may_serve(Some(second), 0, b"c", KeyType::List));
+        assert!(!b.may_serve(None, 0, b"a", KeyType::List));
+
+        // Other databases and types don't wait in the same line.
+        assert!(b.may_serve(None, 1, b"a", KeyType::List));
+        assert!(b.may_serve(None, 0, b"a", KeyType::SortedSet));
+
+        b.unblock(first);
+        assert!(b.may_serve(Some(second), 0, b"b", KeyType::List));
+        b.unblock(second);
+        assert!(b.is_empty());
+    }
+}
@@ -0,0 +1,904 @@
+//! Probabilistic data structures of the RedisBloom module: scalable Bloom
+//! filters, Cuckoo filters, Count-Min sketches and Top-K.
+//!
+//! Items are hashed with MurmurHash64A and MurmurHash2, like RedisBloom
+//! does, so false positive rates and estimates behave the same. The memory
+//! layout is miniredis-rs' own, and so is the payload [`Sketch::encode`]
+//! makes for RDB snapshots and DUMP: RedisBloom can't load it.
+use rand::Rng;
+
+use crate::types::KeyType;
+
+/// MurmurHash64A.
+pub fn murmur64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// MurmurHash2, 32 bit.
+pub fn murmur2(data: &[u8], seed: u32) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let mut h = seed ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &b) in tail.iter().enumerate() {
+            h ^= (b as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+/// A value of one of the RedisBloom types.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sketch {
+    Bloom(BloomFilter),
+    Cuckoo(CuckooFilter),
+    CountMin(CountMinSketch),
+    TopK(TopK),
+}
+
+impl Sketch {
+    pub fn key_type(&self) -> KeyType {
+        match self {
+            Sketch::Bloom(_) => KeyType::Bloom,
+            Sketch::Cuckoo(_) => KeyType::Cuckoo,
+            Sketch::CountMin(_) => KeyType::CountMinSketch,
+            Sketch::TopK(_) => KeyType::TopK,
+        }
+    }
+
+    /// Memory used, in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Sketch::Bloom(bf) => bf.size(),
+            Sketch::Cuckoo(cf) => cf.size(),
+            Sketch::CountMin(cms) => cms.counters.len() * 4 + 32,
+            Sketch::TopK(topk) => {
+                topk.buckets.len() * 8
+                    + topk.heap.iter().map(|h| h.item.len() + 16).sum::<usize>()
+                    + 48
+            }
+        }
+    }
+
+    /// The value as bytes, for RDB snapshots and DUMP payloads. Numbers
+    /// are little-endian; the first byte is the type.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Sketch::Bloom(bf) => {
+                out.push(0);
+                out.extend_from_slice(&bf.error_rate.to_le_bytes());
+                out.extend_from_slice(&bf.expansion.unwrap_or(0).to_le_bytes());
+                put_len(&mut out, bf.layers.len());
+                for layer in &bf.layers {
+                    out.extend_from_slice(&layer.capacity.to_le_bytes());
+                    out.extend_from_slice(&layer.hashes.to_le_bytes());
+                    out.extend_from_slice(&layer.items.to_le_bytes());
+                    put_len(&mut out, layer.bits.len());
+                    for word in &layer.bits {
+                        out.extend_from_slice(&word.to_le_bytes());
+                    }
+                }
+            }
+            Sketch::Cuckoo(cf) => {
+                out.push(1);
+                put_len(&mut out, cf.bucket_size);
+                out.extend_from_slice(&cf.max_iterations.to_le_bytes());
+                out.extend_from_slice(&cf.expansion.to_le_bytes());
+                out.extend_from_slice(&cf.items.to_le_bytes());
+                out.extend_from_slice(&cf.deleted.to_le_bytes());
+                put_len(&mut out, cf.tables.len());
+                for table in &cf.tables {
+                    out.extend_from_slice(&table.num_buckets.to_le_bytes());
+                    put_len(&mut out, table.slots.len());
+                    out.extend_from_slice(&table.slots);
+                }
+            }
+            Sketch::CountMin(cms) => {
+                out.push(2);
+                put_len(&mut out, cms.width);
+                put_len(&mut out, cms.depth);
+                out.extend_from_slice(&cms.count.to_le_bytes());
+                for counter in &cms.counters {
+                    out.extend_from_slice(&counter.to_le_bytes());
+                }
+            }
+            Sketch::TopK(topk) => {
+                out.push(3);
+                put_len(&mut out, topk.k);
+                put_len(&mut out, topk.width);
+                put_len(&mut out, topk.depth);
+                out.extend_from_slice(&topk.decay.to_le_bytes());
+                for (fp, count) in &topk.buckets {
+                    out.extend_from_slice(&fp.to_le_bytes());
+                    out.extend_from_slice(&count.to_le_bytes());
+                }
+                put_len(&mut out, topk.heap.len());
+                for entry in &topk.heap {
+                    out.extend_from_slice(&entry.fingerprint.to_le_bytes());
+                    out.extend_from_slice(&entry.count.to_le_bytes());
+                    put_len(&mut out, entry.item.len());
+                    out.extend_from_slice(&entry.item);
+                }
+            }
+        }
+        out
+    }
+
+    /// Parse what [`Sketch::encode`] made. None if it's malformed, or
+    /// describes a value the commands could panic on.
+    pub fn decode(data: &[u8]) -> Option<Sketch> {
+        let mut r = Bytes(data);
+        let sketch = match r.take(1)?[0] {
+            0 => {
+                let error_rate = r.f64()?;
+                let expansion = Some(r.u32()?).filter(|&e| e > 0);
+                let layers = (0..r.len(1)?)
+                    .map(|_| {
+                        let capacity = r.u64()?;
+                        let hashes = r.u32()?;
+                        let items = r.u64()?;
+                        let bits = (0..r.len(8)?)
+                            .map(|_| r.u64())
+                            .collect::<Option<Vec<_>>>()?;
+                        Some(BloomLayer {
+                            capacity,
+                            hashes,
+                            bits,
+                            items,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if layers.is_empty() || layers.iter().any(|l| l.bits.is_empty()) {
+                    return None;
+                }
+                Sketch::Bloom(BloomFilter {
+                    layers,
+                    error_rate,
+                    expansion,
+                })
+            }
+            1 => {
+                let bucket_size = r.len(0)?;
+                let max_iterations = r.u32()?;
+                let expansion = r.u64()?;
+                let items = r.u64()?;
+                let deleted = r.u64()?;
+                let tables = (0..r.len(1)?)
+                    .map(|_| {
+                        let num_buckets = r.u64()?;
+                        let n = r.len(1)?;
+                        let slots = r.take(n)?.to_vec();
+                        Some(CuckooTable { num_buckets, slots })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if bucket_size == 0
+                    || tables.is_empty()
+                    || tables.iter().any(|t| {
+                        t.num_buckets == 0
+                            || (t.num_buckets as usize).checked_mul(bucket_size)
+                                != Some(t.slots.len())
+                    })
+                {
+                    return None;
+                }
+                Sketch::Cuckoo(CuckooFilter {
+                    tables,
+                    bucket_size,
+                    max_iterations,
+                    expansion,
+                    items,
+                    deleted,
+                })
+            }
+            2 => {
+                let width = r.len(0)?;
+                let depth = r.len(0)?;
+                let count = r.u64()?;
+                if width == 0 {
+                    return None;
+                }
+                let n = width.checked_mul(depth)?;
+                r.fits(n, 4)?;
+                let counters = (0..n).map(|_| r.u32()).collect::<Option<Vec<_>>>()?;
+                Sketch::CountMin(CountMinSketch {
+                    width,
+                    depth,
+                    counters,
+                    count,
+                })
+            }
+            3 => {
+                let k = r.len(0)?;
+                let width = r.len(0)?;
+                let depth = r.len(0)?;
+                let decay = r.f64()?;
+                if width == 0 {
+                    return None;
+                }
+                let n = width.checked_mul(depth)?;
+                r.fits(n, 8)?;
+                let buckets = (0..n)
+                    .map(|_| Some((r.u32()?, r.u32()?)))
+                    .collect::<Option<Vec<_>>>()?;
+                let heap = (0..r.len(9)?)
+                    .map(|_| {
+                        let fingerprint = r.u32()?;
+                        let count = r.u32()?;
+                        let n = r.len(1)?;
+                        let item = r.take(n)?.to_vec();
+                        Some(TopKItem {
+                            item,
+                            fingerprint,
+                            count,
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                if heap.len() > k {
+                    return None;
+                }
+                Sketch::TopK(TopK {
+                    k,
+                    width,
+                    depth,
+                    decay,
+                    buckets,
+                    heap,
+                })
+            }
+            _ => return None,
+        };
+        r.0.is_empty().then_some(sketch)
+    }
+}
+
+fn put_len(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u64).to_le_bytes());
+}
+
+/// What's left of a payload being decoded.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        Some(f64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    /// Some if what's left can hold `n` items of at least `size` bytes,
+    /// so a bad length can't make us allocate a lot.
+    fn fits(&self, n: usize, size: usize) -> Option<()> {
+        (n.checked_mul(size)? <= self.0.len()).then_some(())
+    }
+
+    /// A length, followed by items of at least `size` bytes.
+    fn len(&mut self, size: usize) -> Option<usize> {
+        let n = usize::try_from(self.u64()?).ok()?;
+        self.fits(n, size)?;
+        Some(n)
+    }
+}
+
+// ── Bloom filters ────────────────────────────────────────────────────
+
+/// Each filter added to a scalable Bloom filter gets a tighter error rate,
+/// so the rate of the whole chain stays below the one asked for.
+const ERROR_TIGHTENING_RATIO: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq)]
+struct BloomLayer {
+    capacity: u64,
+    hashes: u32,
+    bits: Vec<u64>,
+    items: u64,
+}
+
+impl BloomLayer {
+    fn new(capacity: u64, error_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bits_per_entry = -error_rate.ln() / (ln2 * ln2);
+        let bits = (capacity as f64 * bits_per_entry).ceil() as u64;
+        BloomLayer {
+            capacity,
+            hashes: (ln2 * bits_per_entry).ceil() as u32,
+            bits: vec![0; bits.div_ceil(64).max(1) as usize],
+            items: 0,
+        }
+    }
+
+    /// The bit positions of an item, by double hashing.
+    fn positions(&self, hash: (u64, u64)) -> impl Iterator<Item = usize> {
+        let nbits = self.bits.len() as u64 * 64;
+        (0..self.hashes as u64)
+            .map(move |i| (hash.0.wrapping_add(i.wrapping_mul(hash.1)) % nbits) as usize)
+    }
+
+    fn contains(&self, hash: (u64, u64)) -> bool {
+        self.positions(hash)
+            .all(|p| self.bits[p / 64] & (1 << (p % 64)) != 0)
+    }
+
+    fn insert(&mut self, hash: (u64, u64)) {
+        let positions: Vec<usize> = self.positions(hash).collect();
+        for p in positions {
+            self.bits[p / 64] |= 1 << (p % 64);
+        }
+        self.items += 1;
+    }
+}
+
+/// A scalable Bloom filter: when the last filter of the chain is full,
+/// a new one, `expansion` times bigger, is added.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BloomFilter {
+    layers: Vec<BloomLayer>,
+    error_rate: f64,
+    /// None for a non-scaling filter.
+    expansion: Option<u32>,
+}
+
+impl BloomFilter {
+    /// What BF.ADD creates filters with.
+    pub const DEFAULT_ERROR_RATE: f64 = 0.01;
+    pub const DEFAULT_CAPACITY: u64 = 100;
+    pub const DEFAULT_EXPANSION: u32 = 2;
+
+    pub fn new(error_rate: f64, capacity: u64, expansion: Option<u32>) -> Self {
+        let error_rate = error_rate * ERROR_TIGHTENING_RATIO;
+        BloomFilter {
+            layers: vec![BloomLayer::new(capacity, error_rate)],
+            error_rate,
+            expansion,
+        }
+    }
+
+    fn hash(item: &[u8]) -> (u64, u64) {
+        let h = murmur64a(item, 0xc6a4_a793_5bd1_e995);
+        (h, murmur64a(item, h))
+    }
+
+    /// Add an item. Returns false if it may have been added before.
+    pub fn add(&mut self, item: &[u8]) -> Result<bool, &'static str> {
+        let hash = Self::hash(item);
+        if self.layers.iter().any(|l| l.contains(hash)) {
+            return Ok(false);
+        }
+        let last = self.layers.last().unwrap();
+        if last.items >= last.capacity {
+            let Some(expansion) = self.expansion else {
+                return Err("ERR non scaling filter is full");
+            };
+            let capacity = last.capacity * expansion as u64;
+            self.error_rate *= ERROR_TIGHTENING_RATIO;
+            self.layers.push(BloomLayer::new(capacity, self.error_rate));
+        }
+        self.layers.last_mut().unwrap().insert(hash);
+        Ok(true)
+    }
+
+    /// Whether an item may have been added.
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let hash = Self::hash(item);
+        self.layers.iter().any(|l| l.contains(hash))
+    }
+
+    /// The number of items the filters of the chain hold before the next
+    /// one is added.
+    pub fn capacity(&self) -> u64 {
+        self.layers.iter().map(|l| l.capacity).sum()
+    }
+
+    pub fn size(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|l| l.bits.len() * 8 + 40)
+            .sum::<usize>()
+            + 32
+    }
+
+    pub fn filters(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn items(&self) -> u64 {
+        self.layers.iter().map(|l| l.items).sum()
+    }
+
+    pub fn expansion(&self) -> Option<u32> {
+        self.expansion
+    }
+}
+
+// ── Cuckoo filters ───────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+struct CuckooTable {
+    num_buckets: u64,
+    /// `bucket_size` fingerprints per bucket; 0 is an empty slot.
+    slots: Vec<u8>,
+}
+
+/// A Cuckoo filter with 8 bit fingerprints. Unlike a Bloom filter it can
+/// delete items, and an item added twice is in it twice.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuckooFilter {
+    tables: Vec<CuckooTable>,
+    bucket_size: usize,
+    max_iterations: u32,
+    expansion: u64,
+    items: u64,
+    deleted: u64,
+}
+
+impl CuckooFilter {
+    /// What CF.ADD creates filters with.
+    pub const DEFAULT_CAPACITY: u64 = 1024;
+    pub const DEFAULT_BUCKET_SIZE: usize = 2;
+    pub const DEFAULT_MAX_ITERATIONS: u32 = 20;
+    pub const DEFAULT_EXPANSION: u64 = 1;
+
+    pub fn new(capacity: u64, bucket_size: usize, max_iterations: u32, expansion: u64) -> Self {
+        let mut cf = CuckooFilter {
+            tables: Vec::new(),
+            bucket_size,
+            max_iterations,
+            expansion,
+            items: 0,
+            deleted: 0,
+        };
+        let num_buckets = (capacity / bucket_size as u64).max(1).next_power_of_two();
+        cf.add_table(num_buckets);
+        cf
+    }
+
+    fn add_table(&mut self, num_buckets: u64) {
+        self.tables.push(CuckooTable {
+            num_buckets,
+            slots: vec![0; num_buckets as usize * self.bucket_size],
+        });
+    }
+
+    /// The fingerprint of an item and the hash of its first bucket.
+    fn hash(item: &[u8]) -> (u8, u64) {
+        let h = murmur64a(item, 0);
+        ((h % 255 + 1) as u8, h)
+    }
+
+    /// The hash of the other bucket a fingerprint can be in. With a power
+    /// of two buckets it maps each of the two buckets to the other.
+    fn alt(h: u64, fp: u8) -> u64 {
+        h ^ (fp as u64).wrapping_mul(0x5bd1_e995)
+    }
+
+    fn bucket(&self, table: usize, h: u64) -> std::ops::Range<usize> {
+        let start = (h % self.tables[table].num_buckets) as usize * self.bucket_size;
+        start..start + self.bucket_size
+    }
+
+    fn find_slot(&self, table: usize, h: u64, fp: u8) -> Option<usize> {
+        self.bucket(table, h)
+            .find(|&i| self.tables[table].slots[i] == fp)
+    }
+
+    /// Add an item, moving other fingerprints around when both its buckets
+    /// are full, and adding a filter when that fails too.
+    pub fn add(&mut self, item: &[u8], rng: &mut impl Rng) -> Result<(), &'static str> {
+        let (fp, h) = Self::hash(item);
+        let h2 = Self::alt(h, fp);
+        for t in 0..self.tables.len() {
+            if let Some(slot) = self.find_slot(t, h, 0).or_else(|| self.find_slot(t, h2, 0)) {
+                self.tables[t].slots[slot] = fp;
+                self.items += 1;
+                return Ok(());
+            }
+        }
+        let last = self.tables.len() - 1;
+        if self.relocate(last, h, fp, rng) {
+            self.items += 1;
+            return Ok(());
+        }
+        if self.expansion == 0 {
+            return Err("Filter is full");
+        }
+        let num_buckets = (self.tables[last].num_buckets * self.expansion).next_power_of_two();
+        self.add_table(num_buckets);
+        let slot = self.find_slot(last + 1, h, 0).unwrap();
+        self.tables[last + 1].slots[slot] = fp;
+        self.items += 1;
+        Ok(())
+    }
+
+    /// Cuckoo insertion into a full bucket: swap the fingerprint with a
+    /// random one there, and move that one to its other bucket. Nothing
+    /// changes if that doesn't end in a free slot.
+    fn relocate(&mut self, table: usize, h: u64, fp: u8, rng: &mut impl Rng) -> bool {
+        let mut slots = self.tables[table].slots.clone();
+        let (mut h, mut fp) = (h, fp);
+        for _ in 0..self.max_iterations {
+            let bucket = self.bucket(table, h);
+            let victim = bucket.start + rng.random_range(0..self.bucket_size);
+            std::mem::swap(&mut fp, &mut slots[victim]);
+            h = Self::alt(h % self.tables[table].num_buckets, fp);
+            if let Some(free) = self.bucket(table, h).find(|&i| slots[i] == 0) {
+                slots[free] = fp;
+                self.tables[table].slots = slots;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether an item may have been added.
+    pub fn contains(&self, item: &[u8]) -> bool {
+        let (fp, h) = Self::hash(item);
+        let h2 = Self::alt(h, fp);
+        (0..self.tables.len())
+            .any(|t| self.find_slot(t, h, fp).is_some() || self.find_slot(t, h2, fp).is_some())
+    }
+
+    /// Remove one occurrence of an item. Returns false if it isn't there.
+    pub fn delete(&mut self, item: &[u8]) -> bool {
+        let (fp, h) = Self::hash(item);
+        let h2 = Self::alt(h, fp);
+        for t in (0..self.tables.len()).rev() {
+            if let Some(slot) = self
+                .find_slot(t, h, fp)
+                .or_else(|| self.find_slot(t, h2, fp))
+            {
+                self.tables[t].slots[slot] = 0;
+                self.items -= 1;
+                self.deleted += 1;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub fn size(&self) -> usize {
+        self.tables
+            .iter()
+            .map(|t| t.slots.len() + 16)
+            .sum::<usize>()
+            + 48
+    }
+
+    pub fn items(&self) -> u64 {
+        self.items
+    }
+}
+
+// ── Count-Min sketches ───────────────────────────────────────────────
+
+/// A Count-Min sketch: `depth` rows of `width` counters. An item's count
+/// is the smallest of its counters, one per row, so it's never too low.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountMinSketch {
+    pub width: usize,
+    pub depth: usize,
+    counters: Vec<u32>,
+    /// Sum of all increments.
+    pub count: u64,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        CountMinSketch {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+            count: 0,
+        }
+    }
+
+    fn counter_indexes(&self, item: &[u8]) -> impl Iterator<Item = usize> {
+        (0..self.depth)
+            .map(move |row| row * self.width + murmur2(item, row as u32) as usize % self.width)
+    }
+
+    /// Add `by` to an item's count and return the new count.
+    pub fn incr_by(&mut self, item: &[u8], by: u32) -> Result<u32, &'static str> {
+        let indexes: Vec<usize> = self.counter_indexes(item).collect();
+        if indexes
+            .iter()
+            .any(|&i| self.counters[i].checked_add(by).is_none())
+        {
+            return Err("CMS: INCRBY overflow");
+        }
+        for &i in &indexes {
+            self.counters[i] += by;
+        }
+        self.count += by as u64;
+        Ok(self.query(item))
+    }
+
+    /// The count of an item.
+    pub fn query(&self, item: &[u8]) -> u32 {
+        self.counter_indexes(item)
+            .map(|i| self.counters[i])
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+// ── Top-K ────────────────────────────────────────────────────────────
+
+/// Seed of the fingerprint hash of Top-K items.
+const TOPK_FINGERPRINT_SEED: u32 = 1919;
+
+#[derive(Debug, Clone, PartialEq)]
+struct TopKItem {
+    item: Vec<u8>,
+    fingerprint: u32,
+    count: u32,
+}
+
+/// The HeavyKeeper algorithm: counters like a Count-Min sketch, which
+/// decay when another item hashes to them, and the `k` items with the
+/// highest counts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopK {
+    pub k: usize,
+    pub width: usize,
+    pub depth: usize,
+    pub decay: f64,
+    /// Fingerprint and count, `depth` rows of `width`.
+    buckets: Vec<(u32, u32)>,
+    heap: Vec<TopKItem>,
+}
+
+impl TopK {
+    /// What TOPK.RESERVE uses when only `topk` is given.
+    pub const DEFAULT_WIDTH: usize = 8;
+    pub const DEFAULT_DEPTH: usize = 7;
+    pub const DEFAULT_DECAY: f64 = 0.9;
+
+    pub fn new(k: usize, width: usize, depth: usize, decay: f64) -> Self {
+        TopK {
+            k,
+            width,
+            depth,
+            decay,
+            buckets: vec![(0, 0); width * depth],
+            heap: Vec::with_capacity(k),
+        }
+    }
+
+    /// Count an item. Returns the item it pushed out of the top `k`.
+    pub fn add(&mut self, item: &[u8], rng: &mut impl Rng) -> Option<Vec<u8>> {
+        let fingerprint = murmur2(item, TOPK_FINGERPRINT_SEED);
+        let mut max = 0;
+        for row in 0..self.depth {
+            let i = row * self.width + murmur2(item, row as u32) as usize % self.width;
+            let (fp, count) = &mut self.buckets[i];
+            if *count == 0 {
+                *fp = fingerprint;
+            } else if *fp != fingerprint {
+                // Another item's counter decays with probability decay^count.
+                if rng.random::<f64>() >= self.decay.powi(*count as i32) {
+                    continue;
+                }
+                *count -= 1;
+                if *count > 0 {
+                    continue;
+                }
+                *fp = fingerprint;
+            }
+            *count += 1;
+            max = max.max(*count);
+        }
+
+        if let Some(entry) = self
+            .heap
+            .iter_mut()
+            .find(|e| e.fingerprint == fingerprint && e.item == item)
+        {
+            entry.count = entry.count.max(max);
+            return None;
+        }
+        let entry = TopKItem {
+            item: item.to_vec(),
+            fingerprint,
+            count: max,
+        };
+        if self.heap.len() < self.k {
+            if max > 0 {
+                self.heap.push(entry);
+            }
+            return None;
+        }
+        let (min, _) = self.heap.iter().enumerate().min_by_key(|(_, e)| e.count)?;
+        if max <= self.heap[min].count {
+            return None;
+        }
+        Some(std::mem::replace(&mut self.heap[min], entry).item)
+    }
+
+    /// The top items and their counts, highest count first.
+    pub fn list(&self) -> Vec<(&[u8], u32)> {
+        let mut items: Vec<(&[u8], u32)> = self
+            .heap
+            .iter()
+            .map(|e| (e.item.as_slice(), e.count))
+            .collect();
+        items.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_murmur() {
+        assert_eq!(murmur2(b"", 0), 0);
+        assert_ne!(murmur2(b"a", 0), murmur2(b"a", 1));
+        assert_ne!(murmur64a(b"abcdefghi", 0), murmur64a(b"abcdefgh", 0));
+    }
+
+    #[test]
+    fn test_bloom_scaling() {
+        let mut bf = BloomFilter::new(0.01, 10, Some(2));
+        for i in 0..100 {
+            bf.add(format!("item{i}").as_bytes()).unwrap();
+        }
+        assert!((0..100).all(|i| bf.contains(format!("item{i}").as_bytes())));
+        assert!(bf.filters() > 1);
+        assert!(bf.items() <= 100);
+        let false_positives = (0..1000)
+            .filter(|i| bf.contains(format!("other{i}").as_bytes()))
+            .count();
+        assert!(false_positives < 30, "{false_positives} false positives");
+
+        let mut fixed = BloomFilter::new(0.01, 2, None);
+        fixed.add(b"a").unwrap();
+        fixed.add(b"b").unwrap();
+        assert_eq!(fixed.add(b"c"), Err("ERR non scaling filter is full"));
+        assert_eq!(fixed.add(b"a"), Ok(false));
+    }
+
+    #[test]
+    fn test_cuckoo() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut cf = CuckooFilter::new(8, 2, 20, 1);
+        for i in 0..50 {
+            cf.add(format!("item{i}").as_bytes(), &mut rng).unwrap();
+        }
+        assert!((0..50).all(|i| cf.contains(format!("item{i}").as_bytes())));
+        assert_eq!(cf.items(), 50);
+
+        cf.add(b"item0", &mut rng).unwrap();
+        assert!(cf.delete(b"item0"));
+        assert!(cf.contains(b"item0"));
+        assert!(cf.delete(b"item0"));
+        assert!(!cf.delete(b"item0"));
+
+        let mut full = CuckooFilter::new(2, 2, 5, 0);
+        let added = (0..10)
+            .filter(|i| full.add(format!("item{i}").as_bytes(), &mut rng).is_ok())
+            .count();
+        assert!(added < 10);
+    }
+
+    #[test]
+    fn test_count_min() {
+        let mut cms = CountMinSketch::new(100, 4);
+        assert_eq!(cms.incr_by(b"a", 3), Ok(3));
+        assert_eq!(cms.incr_by(b"a", 2), Ok(5));
+        assert_eq!(cms.query(b"a"), 5);
+        assert_eq!(cms.query(b"b"), 0);
+        assert_eq!(cms.count, 5);
+        assert_eq!(cms.incr_by(b"a", u32::MAX), Err("CMS: INCRBY overflow"));
+        assert_eq!(cms.query(b"a"), 5);
+    }
+
+    #[test]
+    fn test_topk() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut topk = TopK::new(2, 50, 4, 0.9);
+        for _ in 0..10 {
+            topk.add(b"a", &mut rng);
+        }
+        for _ in 0..5 {
+            topk.add(b"b", &mut rng);
+        }
+        assert_eq!(topk.add(b"c", &mut rng), None);
+        assert_eq!(topk.list(), vec![(&b"a"[..], 10), (&b"b"[..], 5)]);
+        for _ in 0..4 {
+            assert_eq!(topk.add(b"c", &mut rng), None);
+        }
+        assert_eq!(topk.add(b"c", &mut rng), Some(b"b".to_vec()));
+        assert_eq!(topk.list(), vec![(&b"a"[..], 10), (&b"c"[..], 6)]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut bf = BloomFilter::new(0.01, 10, Some(2));
+        let mut cf = CuckooFilter::new(8, 2, 20, 1);
+        let mut cms = CountMinSketch::new(20, 3);
+        let mut topk = TopK::new(2, 8, 7, 0.9);
+        for i in 0..30 {
+            let item = format!("item{}", i % 12);
+            bf.add(item.as_bytes()).unwrap();
+            cf.add(item.as_bytes(), &mut rng).unwrap();
+            cms.incr_by(item.as_bytes(), 2).unwrap();
+            topk.add(item.as_bytes(), &mut rng);
+        }
+        cf.delete(b"item1");
+        assert!(bf.filters() > 1);
+
+        for sketch in [
+            Sketch::Bloom(bf),
+            Sketch::Bloom(BloomFilter::new(0.1, 5, None)),
+            Sketch::Cuckoo(cf),
+            Sketch::CountMin(cms),
+            Sketch::TopK(topk),
+        ] {
+            let data = sketch.encode();
+            assert_eq!(Sketch::decode(&data), Some(sketch));
+            assert_eq!(Sketch::decode(&data[..data.len() - 1]), None);
+        }
+
+        assert_eq!(Sketch::decode(&[]), None);
+        assert_eq!(Sketch::decode(&[4]), None);
+        let mut huge = vec![2];
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        huge.extend_from_slice(&u64::MAX.to_le_bytes());
+        huge.extend_from_slice(&0u64.to_le_bytes());
+        assert_eq!(Sketch::decode(&huge), None);
+    }
+}
@@ -0,0 +1,249 @@
+//! Checkpoints of the dataset, for `Miniredis::checkpoint` and `restore`.
+//!
+//! A checkpoint copies all databases, the cached scripts, the function
+//! libraries and the ACL users. TTLs are the time keys had left, like the
+//! databases keep them, so a restored key expires after the same time it
+//! had left at the checkpoint, whatever the mock time is by then.
+use std::collections::{BTreeSet, HashMap};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::acl::AclUser;
+use crate::cmd::functions::FunctionLibrary;
+use crate::db::{Isolation, RedisDB, SharedState};
+use crate::rdb;
+
+/// A copy of the dataset, taken by
+/// [`Miniredis::checkpoint()`](crate::Miniredis::checkpoint).
+#[derive(Debug)]
+pub struct Checkpoint {
+    dbs: Vec<RedisDB>,
+    scripts: HashMap<String, String>,
+    functions: HashMap<String, FunctionLibrary>,
+    users: Vec<AclUser>,
+}
+
+/// How a key differs from a checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The key didn't exist at the checkpoint.
+    Added,
+    /// The key existed at the checkpoint, and is gone now.
+    Removed,
+    /// The value, type or TTL is different.
+    Modified,
+}
+
+/// A key that differs from a checkpoint, see
+/// [`Miniredis::diff()`](crate::Miniredis::diff).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    pub db: usize,
+    pub key: Vec<u8>,
+    pub change: Change,
+}
+
+/// Copy the dataset.
+pub fn take(state: &SharedState) -> Checkpoint {
+    let _keyspace = state.lock_keyspace(Isolation::Read);
+    let data = state.lock_all();
+    Checkpoint {
+        dbs: data.dbs.iter().map(|db| copy_data(db)).collect(),
+        scripts: data.inner.scripts.clone(),
+        functions: data.inner.functions.clone(),
+        users: data.inner.acl.users().cloned().collect(),
+    }
+}
+
+/// Replace the dataset with the one of `checkpoint`. Changed keys get a
+/// new version, so WATCH sees them change.
+pub fn restore(state: &SharedState, checkpoint: &Checkpoint) {
+    {
+        let _keyspace = state.lock_keyspace(Isolation::Exclusive);
+        let mut data = state.lock_all();
+        for (db, saved) in data.dbs.iter_mut().zip(&checkpoint.dbs) {
+            restore_db(db, saved);
+        }
+        data.inner.scripts = checkpoint.scripts.clone();
+        data.inner.functions = checkpoint.functions.clone();
+        data.inner
+            .acl
+            .replace_users(checkpoint.users.iter().cloned());
+        drop(data);
+        state.publish_changes(None);
+    }
+    state.notify.notify_waiters();
+}
+
+/// The keys that differ from `checkpoint`, by database and key.
+pub fn diff(state: &SharedState, checkpoint: &Checkpoint) -> Vec<KeyChange> {
+    let _keyspace = state.lock_keyspace(Isolation::Read);
+    let data = state.lock_all();
+    let mut changes = Vec::new();
+    for (idx, (db, saved)) in data.dbs.iter().zip(&checkpoint.dbs).enumerate() {
+        changes.extend(
+            diff_db(saved, db)
+                .into_iter()
+                .map(|(key, change)| KeyChange {
+                    db: idx,
+                    key,
+                    change,
+                }),
+        );
+    }
+    changes
+}
+
+/// A database with the keys, values and TTLs of `db`, and nothing else.
+fn copy_data(db: &RedisDB) -> RedisDB {
+    RedisDB {
+        keys: db.keys.clone(),
+        string_keys: db.string_keys.clone(),
+        hash_keys: db.hash_keys.clone(),
+        list_keys: db.list_keys.clone(),
+        set_keys: db.set_keys.clone(),
+        sorted_set_keys: db.sorted_set_keys.clone(),
+        stream_keys: db.stream_keys.clone(),
+        hll_keys: db.hll_keys.clone(),
+        #[cfg(feature = "json")]
+        json_keys: db.json_keys.clone(),
+        #[cfg(feature = "bloom")]
+        bloom_keys: db.bloom_keys.clone(),
+        ttl: db.ttl.clone(),
+        hash_field_ttls: db.hash_field_ttls.clone(),
+        ..RedisDB::new()
+    }
+}
+
+fn restore_db(db: &mut RedisDB, saved: &RedisDB) {
+    let changed = diff_db(saved, db);
+    if changed.is_empty() {
+        return;
+    }
+    let restored = copy_data(saved);
+    db.keys = restored.keys;
+    db.string_keys = restored.string_keys;
+    db.hash_keys = restored.hash_keys;
+    db.list_keys = restored.list_keys;
+    db.set_keys = restored.set_keys;
+    db.sorted_set_keys = restored.sorted_set_keys;
+    db.stream_keys = restored.stream_keys;
+    db.hll_keys = restored.hll_keys;
+    #[cfg(feature = "json")]
+    {
+        db.json_keys = restored.json_keys;
+    }
+    #[cfg(feature = "bloom")]
+    {
+        db.bloom_keys = restored.bloom_keys;
+    }
+    db.ttl = restored.ttl;
+    db.hash_field_ttls = restored.hash_field_ttls;
+    db.lru.retain(|key, _| db.keys.contains_key(key));
+    db.access.retain(|key, _| db.keys.contains_key(key));
+    for (key, _) in changed {
+        db.resized(&key);
+        *db.key_version.entry(key.clone()).or_insert(0) += 1;
+        if db.tracking {
+            db.modified_keys.push((None, key));
+        }
+    }
+    db.dirty += 1;
+}
+
+/// The keys of `db` that differ from `saved`, sorted.
+fn diff_db(saved: &RedisDB, db: &RedisDB) -> Vec<(Vec<u8>, Change)> {
+    let keys: BTreeSet<&Vec<u8>> = saved.keys.keys().chain(db.keys.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let change = match (value(saved, key), value(db, key)) {
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                (Some(a), Some(b)) if a != b => Change::Modified,
+                _ => return None,
+            };
+            Some((key.clone(), change))
+        })
+        .collect()
+}
+
+/// The DUMP payload and the TTL of `key`. Hash field TTLs are encoded
+/// relative to the time passed in, so a fixed one keeps them comparable.
+fn value(db: &RedisDB, key: &[u8]) -> Option<(Vec<u8>, Option<Duration>)> {
+    Some((
+        rdb::dump_value(db, key, UNIX_EPOCH)?,
+        db.ttl.get(key).copied(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let state = SharedState::new();
+        let now = state.now();
+        state.db(0).string_set(b"a", b"1".to_vec(), now);
+        state.db(0).string_set(b"b", b"2".to_vec(), now);
+        state.db(1).list_rpush(b"l", &[b"x".to_vec()], now);
+        let checkpoint = take(&state);
+        assert!(diff(&state, &checkpoint).is_empty());
+
+        for i in 0..2 {
+            state.db(i).track_memory(true);
+        }
+        state.db(0).string_set(b"a", b"changed".to_vec(), now);
+        state.db(0).del(b"b");
+        state.db(1).set_add(b"s", &[b"m".to_vec()], now);
+        for i in 0..2 {
+            state.db(i).used_memory();
+        }
+        let change = |db, key: &[u8], change| KeyChange {
+            db,
+            key: key.to_vec(),
+            change,
+        };
+        assert_eq!(
+            diff(&state, &checkpoint),
+            vec![
+                change(0, b"a", Change::Modified),
+                change(0, b"b", Change::Removed),
+                change(1, b"s", Change::Added),
+            ]
+        );
+
+        let version = state.db(0).key_version.get(&b"a"[..]).copied();
+        restore(&state, &checkpoint);
+        assert!(diff(&state, &checkpoint).is_empty());
+        assert_eq!(state.db(0).string_get(b"a"), Some(&b"1".to_vec()));
+        assert_ne!(state.db(0).key_version.get(&b"a"[..]).copied(), version);
+        assert!(!state.db(1).keys.contains_key(&b"s"[..]));
+        // The cached key sizes match the restored keys.
+        for i in 0..2 {
+            let mut db = state.db(i);
+            let cached = db.used_memory();
+            db.track_memory(true);
+            assert_eq!(db.used_memory(), cached);
+        }
+    }
+
+    #[test]
+    fn test_diff_ttl() {
+        let state = SharedState::new();
+        let now = state.now();
+        state.db(0).string_set(b"k", b"v".to_vec(), now);
+        state
+            .db(0)
+            .ttl
+            .insert(b"k".to_vec(), Duration::from_secs(10));
+        let checkpoint = take(&state);
+
+        state.fast_forward(Duration::from_secs(1));
+        assert_eq!(diff(&state, &checkpoint)[0].change, Change::Modified);
+        restore(&state, &checkpoint);
+        assert_eq!(
+            state.db(0).ttl.get(&b"k"[..]),
+            Some(&Duration::from_secs(10))
+        );
+    }
+}
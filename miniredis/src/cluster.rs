@@ -0,0 +1,318 @@
+//! Cluster mode: hash slots, slot ownership and [`MiniredisCluster`].
+//!
+//! Every node of a cluster is a normal [`Miniredis`] server that shares a
+//! [`Topology`] with the other nodes. Before a command runs, dispatch
+//! checks that the node serves the slot of its keys, and answers with a
+//! MOVED, ASK, CROSSSLOT or TRYAGAIN error if it doesn't.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use crate::Miniredis;
+use crate::Result;
+
+/// Number of hash slots.
+pub const CLUSTER_SLOTS: usize = 16384;
+
+/// The CRC16 (XMODEM) of `data`, as used for hash slots.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// The hash slot of `key`. If the key contains a non-empty `{...}` hash
+/// tag only the tag is hashed, so `{user1}:name` and `{user1}:email` end
+/// up in the same slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = match key.iter().position(|&b| b == b'{') {
+        Some(open) => match key[open + 1..].iter().position(|&b| b == b'}') {
+            Some(len) if len > 0 => &key[open + 1..open + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+    crc16(hashed) % CLUSTER_SLOTS as u16
+}
+
+/// A node of the cluster.
+#[derive(Debug, Clone)]
+pub struct ClusterNode {
+    /// The 40 character node ID.
+    pub id: String,
+    pub addr: SocketAddr,
+}
+
+impl ClusterNode {
+    fn new(addr: SocketAddr) -> Self {
+        let mut id = crate::acl::sha256_hex(addr.to_string().as_bytes());
+        id.truncate(40);
+        ClusterNode { id, addr }
+    }
+}
+
+/// Which node owns which slots, and the slots being migrated.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    pub nodes: Vec<ClusterNode>,
+    /// Owning node of every slot.
+    owners: Vec<usize>,
+    /// Slots being migrated: slot -> node importing it.
+    migrating: HashMap<u16, usize>,
+}
+
+impl Topology {
+    /// Divide the slots evenly over `nodes`, in order, the way
+    /// `redis-cli --cluster create` does.
+    pub fn new(nodes: Vec<ClusterNode>) -> Self {
+        let n = nodes.len();
+        let mut owners = vec![0; CLUSTER_SLOTS];
+        for i in 1..n {
+            let start = (i * CLUSTER_SLOTS + n / 2) / n;
+            owners[start..].fill(i);
+        }
+        Topology {
+            nodes,
+            owners,
+            migrating: HashMap::new(),
+        }
+    }
+
+    /// What a server not started by [`MiniredisCluster`] reports: a single
+    /// node serving every slot.
+    pub fn standalone() -> Self {
+        Topology::new(vec![ClusterNode {
+            id: "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca".to_string(),
+            addr: "127.0.0.1:6379".parse().unwrap(),
+        }])
+    }
+
+    /// The node owning `slot`.
+    pub fn owner(&self, slot: u16) -> usize {
+        self.owners[slot as usize]
+    }
+
+    /// The node importing `slot`, if it's being migrated.
+    pub fn importing_node(&self, slot: u16) -> Option<usize> {
+        self.migrating.get(&slot).copied()
+    }
+
+    /// Slots being migrated, with the node importing them.
+    pub fn migrations(&self) -> impl Iterator<Item = (u16, usize)> + '_ {
+        self.migrating.iter().map(|(&slot, &to)| (slot, to))
+    }
+
+    /// The slot ranges owned by `node`, as inclusive (start, end) pairs.
+    pub fn ranges(&self, node: usize) -> Vec<(u16, u16)> {
+        self.all_ranges()
+            .into_iter()
+            .filter(|&(_, _, owner)| owner == node)
+            .map(|(start, end, _)| (start, end))
+            .collect()
+    }
+
+    /// All slot ranges, in slot order, with their owner.
+    pub fn all_ranges(&self) -> Vec<(u16, u16, usize)> {
+        let mut ranges: Vec<(u16, u16, usize)> = Vec::new();
+        for (slot, &owner) in self.owners.iter().enumerate() {
+            match ranges.last_mut() {
+                Some((_, end, o)) if *o == owner => *end = slot as u16,
+                _ => ranges.push((slot as u16, slot as u16, owner)),
+            }
+        }
+        ranges
+    }
+}
+
+/// The cluster as seen by one of its nodes.
+#[derive(Debug, Clone)]
+pub struct ClusterHandle {
+    pub topology: Arc<Mutex<Topology>>,
+    /// Index of this node in the topology.
+    pub myself: usize,
+}
+
+/// A cluster of in-process miniredis nodes, for testing cluster-aware
+/// clients.
+///
+/// ```rust
+/// # async fn example() {
+/// let c = miniredis_rs::MiniredisCluster::run(3).await.unwrap();
+/// // Seed a cluster client with c.redis_urls()
+/// c.set("foo", "bar");
+/// assert_eq!(c.node_for_key("foo").get("foo"), Some("bar".to_string()));
+/// c.close().await;
+/// # }
+/// ```
+pub struct MiniredisCluster {
+    nodes: Vec<Miniredis>,
+    topology: Arc<Mutex<Topology>>,
+}
+
+impl MiniredisCluster {
+    /// Start a cluster of `nodes` masters on random ports, with the hash
+    /// slots divided evenly over them.
+    pub async fn run(nodes: usize) -> Result<Self> {
+        assert!(
+            (1..=CLUSTER_SLOTS).contains(&nodes),
+            "a cluster needs 1-16384 nodes"
+        );
+        let mut servers = Vec::with_capacity(nodes);
+        for _ in 0..nodes {
+            servers.push(Miniredis::run().await?);
+        }
+        let topology = Topology::new(servers.iter().map(|m| ClusterNode::new(m.addr())).collect());
+        let topology = Arc::new(Mutex::new(topology));
+        for (i, m) in servers.iter().enumerate() {
+            let handle = ClusterHandle {
+                topology: Arc::clone(&topology),
+                myself: i,
+            };
+            m.state
+                .cluster
+                .set(handle)
+                .expect("node is already in a cluster");
+        }
+        Ok(MiniredisCluster {
+            nodes: servers,
+            topology,
+        })
+    }
+
+    /// Shut down all nodes.
+    pub async fn close(&self) {
+        for m in &self.nodes {
+            m.close().await;
+        }
+    }
+
+    /// All nodes, in topology order.
+    pub fn nodes(&self) -> &[Miniredis] {
+        &self.nodes
+    }
+
+    /// A single node.
+    pub fn node(&self, i: usize) -> &Miniredis {
+        &self.nodes[i]
+    }
+
+    /// `redis://host:port` URLs of all nodes, to seed a cluster client.
+    pub fn redis_urls(&self) -> Vec<String> {
+        self.nodes.iter().map(|m| m.redis_url()).collect()
+    }
+
+    /// The index of the node owning `slot`.
+    pub fn slot_owner(&self, slot: u16) -> usize {
+        self.topology.lock().unwrap().owner(slot)
+    }
+
+    /// The node owning the slot of `key`.
+    pub fn node_for_key(&self, key: &str) -> &Miniredis {
+        &self.nodes[self.slot_owner(key_slot(key.as_bytes()))]
+    }
+
+    /// Get a string key from the node owning it.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.node_for_key(key).get(key)
+    }
+
+    /// Set a string key on the node owning it.
+    pub fn set(&self, key: &str, value: &str) {
+        self.node_for_key(key).set(key, value)
+    }
+
+    /// Start migrating `slot` to node `to`. Until
+    /// [`finish_migration()`](Self::finish_migration) the owner still
+    /// serves the keys it has, and sends clients an ASK redirect for the
+    /// ones it doesn't.
+    pub fn begin_migration(&self, slot: u16, to: usize) {
+        assert!((slot as usize) < CLUSTER_SLOTS, "slot must be 0-16383");
+        assert!(to < self.nodes.len(), "no such node");
+        let mut topology = self.topology.lock().unwrap();
+        assert!(topology.owner(slot) != to, "node already owns the slot");
+        topology.migrating.insert(slot, to);
+    }
+
+    /// Move the keys left in a migrating slot to the importing node, and
+    /// make that node the owner. Clients still talking to the old owner
+    /// get a MOVED redirect from now on.
+    pub fn finish_migration(&self, slot: u16) {
+        let mut topology = self.topology.lock().unwrap();
+        let to = topology
+            .migrating
+            .remove(&slot)
+            .expect("slot is not being migrated");
+        let from = topology.owner(slot);
+        topology.owners[slot as usize] = to;
+
+        let src = &self.nodes[from].state;
+        let dst = &self.nodes[to].state;
+        let now = dst.now();
+        for (src_db, dst_db) in src.dbs.iter().zip(&dst.dbs) {
+            let mut src_db = src_db.lock().unwrap();
+            let mut dst_db = dst_db.lock().unwrap();
+            let keys: Vec<Vec<u8>> = src_db
+                .keys
+                .keys()
+                .filter(|k| key_slot(k) == slot)
+                .cloned()
+                .collect();
+            for key in keys {
+                src_db.move_key(&key, &mut dst_db, now);
+            }
+        }
+        self.nodes[to].state.notify.notify_waiters();
+    }
+
+    /// Move `slot` and its keys to node `to` in one go.
+    pub fn migrate_slot(&self, slot: u16, to: usize) {
+        self.begin_migration(slot, to);
+        self.finish_migration(slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"{test_key}"), 15118);
+        assert_eq!(key_slot(b"{user1}:name"), key_slot(b"user1"));
+        assert_eq!(key_slot(b"{user1}:name"), key_slot(b"{user1}:email"));
+        // Empty or unclosed tags hash the whole key.
+        assert_eq!(key_slot(b"{}foo"), crc16(b"{}foo") % 16384);
+        assert_eq!(key_slot(b"{foo"), crc16(b"{foo") % 16384);
+        // Only the first tag counts.
+        assert_eq!(key_slot(b"{a}{b}"), key_slot(b"a"));
+    }
+
+    #[test]
+    fn test_topology_ranges() {
+        let nodes = (0..3)
+            .map(|i| ClusterNode::new(format!("127.0.0.1:{}", 7000 + i).parse().unwrap()))
+            .collect();
+        let mut t = Topology::new(nodes);
+        assert_eq!(t.ranges(0), [(0, 5460)]);
+        assert_eq!(t.ranges(1), [(5461, 10922)]);
+        assert_eq!(t.ranges(2), [(10923, 16383)]);
+        assert_eq!(t.nodes[0].id.len(), 40);
+
+        t.owners[100] = 2;
+        assert_eq!(t.ranges(0), [(0, 99), (101, 5460)]);
+        assert_eq!(t.ranges(2), [(100, 100), (10923, 16383)]);
+        assert_eq!(t.all_ranges().len(), 5);
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use crate::acl::{AclUser, CATEGORIES, in_category};
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::dispatch::{CommandTable, MSG_INVALID_INT, MSG_INVALID_RANGE, err_wrong_number};
+use crate::frame::Frame;
+
+pub fn register(table: &mut CommandTable) {
+    table.add("ACL", cmd_acl, false, -2);
+}
+
+/// ACL SETUSER|GETUSER|DELUSER|LIST|USERS|WHOAMI|CAT|LOG ...
+fn cmd_acl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let args = &args[1..];
+    let arity_ok = match subcmd.as_str() {
+        "SETUSER" | "DELUSER" => !args.is_empty(),
+        "GETUSER" => args.len() == 1,
+        "LIST" | "USERS" | "WHOAMI" => args.is_empty(),
+        "CAT" | "LOG" => args.len() <= 1,
+        _ => {
+            return Frame::error(format!(
+                "ERR unknown subcommand '{}'. Try ACL HELP.",
+                subcmd.to_lowercase()
+            ));
+        }
+    };
+    if !arity_ok {
+        return Frame::error(err_wrong_number(&format!("acl|{}", subcmd.to_lowercase())));
+    }
+
+    match subcmd.as_str() {
+        "SETUSER" => acl_setuser(state, args),
+        "GETUSER" => acl_getuser(state, args),
+        "DELUSER" => acl_deluser(state, args),
+        "LIST" => {
+            let inner = state.lock();
+            Frame::Array(
+                inner
+                    .acl
+                    .users()
+                    .map(|u| Frame::Bulk(u.describe().into()))
+                    .collect(),
+            )
+        }
+        "USERS" => {
+            let inner = state.lock();
+            Frame::Array(
+                inner
+                    .acl
+                    .users()
+                    .map(|u| Frame::Bulk(u.name.clone().into()))
+                    .collect(),
+            )
+        }
+        "WHOAMI" => Frame::Bulk(ctx.user.clone().into()),
+        "CAT" => acl_cat(state, args),
+        _ => acl_log(state, args),
+    }
+}
+
+/// ACL SETUSER username [rule [rule ...]]
+fn acl_setuser(state: &Arc<SharedState>, args: &[Vec<u8>]) -> Frame {
+    let name = String::from_utf8_lossy(&args[0]).to_string();
+    let table = state.command_table.get().unwrap();
+    let mut inner = state.lock();
+    match inner
+        .acl
+        .set_user(&name, &args[1..], |cmd| table.is_command(cmd))
+    {
+        Ok(()) => Frame::ok(),
+        Err(e) => Frame::error(e),
+    }
+}
+
+/// ACL GETUSER username
+fn acl_getuser(state: &Arc<SharedState>, args: &[Vec<u8>]) -> Frame {
+    let name = String::from_utf8_lossy(&args[0]);
+    let inner = state.lock();
+    let Some(user) = inner.acl.user(&name) else {
+        return Frame::Null;
+    };
+    getuser_frame(user)
+}
+
+fn getuser_frame(user: &AclUser) -> Frame {
+    Frame::Map(vec![
+        (
+            Frame::bulk_string("flags"),
+            Frame::Array(user.flags().iter().map(|f| Frame::bulk_string(f)).collect()),
+        ),
+        (
+            Frame::bulk_string("passwords"),
+            Frame::Array(
+                user.password_hashes()
+                    .iter()
+                    .map(|p| Frame::bulk_string(p))
+                    .collect(),
+            ),
+        ),
+        (
+            Frame::bulk_string("commands"),
+            Frame::Bulk(user.describe_commands().into()),
+        ),
+        (
+            Frame::bulk_string("keys"),
+            Frame::Bulk(user.describe_keys().into()),
+        ),
+        (
+            Frame::bulk_string("channels"),
+            Frame::Bulk(user.describe_channels().into()),
+        ),
+        (Frame::bulk_string("selectors"), Frame::Array(vec![])),
+    ])
+}
+
+/// ACL DELUSER username [username ...]
+fn acl_deluser(state: &Arc<SharedState>, args: &[Vec<u8>]) -> Frame {
+    let mut inner = state.lock();
+    if args.iter().any(|a| a.as_slice() == b"default") {
+        return Frame::error("ERR The 'default' user cannot be removed");
+    }
+    let mut count = 0;
+    for name in args {
+        if inner.acl.del_user(&String::from_utf8_lossy(name)) {
+            count += 1;
+        }
+    }
+    Frame::Integer(count)
+}
+
+/// ACL CAT [category]
+fn acl_cat(state: &Arc<SharedState>, args: &[Vec<u8>]) -> Frame {
+    let Some(category) = args.first() else {
+        return Frame::Array(
+            CATEGORIES
+                .iter()
+                .map(|(c, _)| Frame::bulk_string(c))
+                .collect(),
+        );
+    };
+    let category = String::from_utf8_lossy(category).to_lowercase();
+    if !CATEGORIES.iter().any(|(c, _)| *c == category) {
+        return Frame::error(format!("ERR Unknown category '{category}'"));
+    }
+
+    let table = state.command_table.get().unwrap();
+    let mut names: Vec<&str> = table.names().chain(["EXEC"]).collect();
+    // Subcommands with categories of their own.
+    names.extend(
+        CATEGORIES
+            .iter()
+            .flat_map(|(_, cmds)| cmds.split(' '))
+            .filter(|c| c.contains('|')),
+    );
+    names.sort_unstable();
+    names.dedup();
+    Frame::Array(
+        names
+            .into_iter()
+            .filter(|name| in_category(name, &category))
+            .map(|name| Frame::Bulk(name.to_lowercase().into()))
+            .collect(),
+    )
+}
+
+/// ACL LOG [count | RESET]
+fn acl_log(state: &Arc<SharedState>, args: &[Vec<u8>]) -> Frame {
+    let mut inner = state.lock();
+    let mut count = usize::MAX;
+    if let Some(arg) = args.first() {
+        if arg.eq_ignore_ascii_case(b"reset") {
+            inner.acl.reset_log();
+            return Frame::ok();
+        }
+        count = match String::from_utf8_lossy(arg).parse::<i64>() {
+            Ok(n) if n >= 0 => n as usize,
+            Ok(_) => return Frame::error(MSG_INVALID_RANGE),
+            Err(_) => return Frame::error(MSG_INVALID_INT),
+        };
+    }
+
+    let now = state.now();
+    let millis = |t: std::time::SystemTime| {
+        t.duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64
+    };
+    Frame::Array(
+        inner
+            .acl
+            .log_entries()
+            .take(count)
+            .map(|e| {
+                let age = now.duration_since(e.created).unwrap_or_default();
+                Frame::Map(vec![
+                    (Frame::bulk_string("count"), Frame::Integer(e.count as i64)),
+                    (Frame::bulk_string("reason"), Frame::bulk_string(e.reason)),
+                    (Frame::bulk_string("context"), Frame::bulk_string(e.context)),
+                    (
+                        Frame::bulk_string("object"),
+                        Frame::Bulk(e.object.clone().into()),
+                    ),
+                    (
+                        Frame::bulk_string("username"),
+                        Frame::Bulk(e.username.clone().into()),
+                    ),
+                    (
+                        Frame::bulk_string("age-seconds"),
+                        Frame::Double(age.as_secs_f64()),
+                    ),
+                    (
+                        Frame::bulk_string("client-info"),
+                        Frame::Bulk(e.client_info.clone().into()),
+                    ),
+                    (
+                        Frame::bulk_string("entry-id"),
+                        Frame::Integer(e.entry_id as i64),
+                    ),
+                    (
+                        Frame::bulk_string("timestamp-created"),
+                        Frame::Integer(millis(e.created)),
+                    ),
+                    (
+                        Frame::bulk_string("timestamp-last-updated"),
+                        Frame::Integer(millis(e.updated)),
+                    ),
+                ])
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,521 @@
+use std::sync::Arc;
+
+use crate::bloom::{BloomFilter, CountMinSketch, CuckooFilter, Sketch, TopK};
+use crate::cmd::{parse_float, parse_int};
+use crate::connection::ConnCtx;
+use crate::db::{RedisDB, SharedState};
+use crate::dispatch::{CommandTable, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE, err_wrong_number};
+use crate::frame::Frame;
+use crate::types::KeyType;
+
+pub fn register(table: &mut CommandTable) {
+    table.add("BF.RESERVE", cmd_bf_reserve, false, -4);
+    table.add("BF.ADD", cmd_bf_add, false, 3);
+    table.add("BF.MADD", cmd_bf_madd, false, -3);
+    table.add("BF.EXISTS", cmd_bf_exists, true, 3);
+    table.add("BF.MEXISTS", cmd_bf_mexists, true, -3);
+    table.add("BF.INFO", cmd_bf_info, true, -2);
+    table.add("CF.ADD", cmd_cf_add, false, 3);
+    table.add("CF.EXISTS", cmd_cf_exists, true, 3);
+    table.add("CF.DEL", cmd_cf_del, false, 3);
+    table.add("CMS.INITBYDIM", cmd_cms_initbydim, false, 4);
+    table.add("CMS.INCRBY", cmd_cms_incrby, false, -4);
+    table.add("CMS.QUERY", cmd_cms_query, true, -3);
+    table.add("TOPK.RESERVE", cmd_topk_reserve, false, -3);
+    table.add("TOPK.ADD", cmd_topk_add, false, -3);
+    table.add("TOPK.LIST", cmd_topk_list, true, -2);
+}
+
+const MSG_CMS_NO_KEY: &str = "CMS: key does not exist";
+const MSG_TOPK_NO_KEY: &str = "TopK: key does not exist";
+
+/// The value at `key` if it has type `t`, or WRONGTYPE if the key holds
+/// another type.
+fn sketch<'a>(db: &'a RedisDB, key: &[u8], t: KeyType) -> Result<Option<&'a Sketch>, Frame> {
+    match db.key_type(key) {
+        None => Ok(None),
+        Some(kt) if kt == t => Ok(db.bloom_keys.get(key)),
+        Some(_) => Err(Frame::error(MSG_WRONG_TYPE)),
+    }
+}
+
+fn sketch_mut<'a>(
+    db: &'a mut RedisDB,
+    key: &[u8],
+    t: KeyType,
+) -> Result<Option<&'a mut Sketch>, Frame> {
+    match db.key_type(key) {
+        None => Ok(None),
+        Some(kt) if kt == t => Ok(db.bloom_keys.get_mut(key)),
+        Some(_) => Err(Frame::error(MSG_WRONG_TYPE)),
+    }
+}
+
+/// The value at `key`, created with `new` when the key doesn't exist.
+fn sketch_or_insert<'a>(
+    db: &'a mut RedisDB,
+    key: &[u8],
+    t: KeyType,
+    new: impl FnOnce() -> Sketch,
+) -> Result<&'a mut Sketch, Frame> {
+    match db.key_type(key) {
+        None => {
+            db.add_key(key, t);
+            db.bloom_keys.insert(key.to_vec(), new());
+        }
+        Some(kt) if kt == t => {}
+        Some(_) => return Err(Frame::error(MSG_WRONG_TYPE)),
+    }
+    Ok(db.bloom_keys.get_mut(key).expect("key has a value"))
+}
+
+/// Create a new value at `key`, or fail with `exists` if the key exists.
+fn reserve(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    key: &[u8],
+    sketch: Sketch,
+    exists: &str,
+) -> Frame {
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    if db.keys.contains_key(key) {
+        return Frame::error(exists);
+    }
+    db.add_key(key, sketch.key_type());
+    db.bloom_keys.insert(key.to_vec(), sketch);
+    db.incr_version(key, now);
+    Frame::ok()
+}
+
+fn new_bloom() -> Sketch {
+    Sketch::Bloom(BloomFilter::new(
+        BloomFilter::DEFAULT_ERROR_RATE,
+        BloomFilter::DEFAULT_CAPACITY,
+        Some(BloomFilter::DEFAULT_EXPANSION),
+    ))
+}
+
+fn new_cuckoo() -> Sketch {
+    Sketch::Cuckoo(CuckooFilter::new(
+        CuckooFilter::DEFAULT_CAPACITY,
+        CuckooFilter::DEFAULT_BUCKET_SIZE,
+        CuckooFilter::DEFAULT_MAX_ITERATIONS,
+        CuckooFilter::DEFAULT_EXPANSION,
+    ))
+}
+
+// ── Bloom filters ────────────────────────────────────────────────────
+
+/// BF.RESERVE key error_rate capacity [EXPANSION expansion] [NONSCALING]
+fn cmd_bf_reserve(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let error_rate = match parse_float(&args[1]) {
+        Some(e) if e.is_nan() => return Frame::error("ERR bad error rate"),
+        Some(e) if e <= 0.0 || e >= 1.0 => {
+            return Frame::error("ERR (0 < error rate range < 1)");
+        }
+        Some(e) => e,
+        None => return Frame::error("ERR bad error rate"),
+    };
+    let capacity = match parse_int(&args[2]) {
+        Some(c) if c <= 0 => return Frame::error("ERR (capacity should be larger than 0)"),
+        Some(c) => c as u64,
+        None => return Frame::error("ERR bad capacity"),
+    };
+    let mut expansion = None;
+    let mut nonscaling = false;
+    let mut i = 3;
+    while i < args.len() {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        match opt.as_str() {
+            "NONSCALING" => nonscaling = true,
+            "EXPANSION" => {
+                let Some(arg) = args.get(i + 1) else {
+                    return Frame::error(err_wrong_number("bf.reserve"));
+                };
+                expansion = match parse_int(arg) {
+                    Some(n) if n < 1 => {
+                        return Frame::error("ERR expansion should be greater or equal to 1");
+                    }
+                    Some(n) => Some(n.min(u32::MAX as i64) as u32),
+                    None => return Frame::error("ERR bad expansion"),
+                };
+                i += 1;
+            }
+            _ => return Frame::error(MSG_SYNTAX_ERROR),
+        }
+        i += 1;
+    }
+    if nonscaling && expansion.is_some() {
+        return Frame::error("ERR Nonscaling filters cannot expand");
+    }
+    let expansion = if nonscaling {
+        None
+    } else {
+        Some(expansion.unwrap_or(BloomFilter::DEFAULT_EXPANSION))
+    };
+
+    let bf = BloomFilter::new(error_rate, capacity, expansion);
+    reserve(state, ctx, &args[0], Sketch::Bloom(bf), "ERR item exists")
+}
+
+/// Add items to the Bloom filter at `key`, creating it if needed. The
+/// result of each item is 1 if it was added, 0 if it may have been added
+/// before, or an error.
+fn bf_add(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    key: &[u8],
+    items: &[Vec<u8>],
+) -> Result<Vec<Frame>, Frame> {
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    let Sketch::Bloom(bf) = sketch_or_insert(&mut db, key, KeyType::Bloom, new_bloom)? else {
+        unreachable!()
+    };
+    let results: Vec<Frame> = items
+        .iter()
+        .map(|item| match bf.add(item) {
+            Ok(added) => Frame::Integer(added as i64),
+            Err(e) => Frame::error(e),
+        })
+        .collect();
+    db.incr_version(key, now);
+    Ok(results)
+}
+
+/// BF.ADD key item
+fn cmd_bf_add(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    match bf_add(state, ctx, &args[0], &args[1..]) {
+        Ok(mut results) => results.remove(0),
+        Err(e) => e,
+    }
+}
+
+/// BF.MADD key item [item ...]
+fn cmd_bf_madd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    match bf_add(state, ctx, &args[0], &args[1..]) {
+        Ok(results) => Frame::Array(results),
+        Err(e) => e,
+    }
+}
+
+/// Whether each item may have been added to the Bloom filter at `key`.
+fn bf_exists(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    key: &[u8],
+    items: &[Vec<u8>],
+) -> Result<Vec<Frame>, Frame> {
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    let bf = match sketch(&db, key, KeyType::Bloom)? {
+        Some(Sketch::Bloom(bf)) => Some(bf),
+        _ => None,
+    };
+    Ok(items
+        .iter()
+        .map(|item| Frame::Integer(bf.is_some_and(|bf| bf.contains(item)) as i64))
+        .collect())
+}
+
+/// BF.EXISTS key item
+fn cmd_bf_exists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    match bf_exists(state, ctx, &args[0], &args[1..]) {
+        Ok(mut results) => results.remove(0),
+        Err(e) => e,
+    }
+}
+
+/// BF.MEXISTS key item [item ...]
+fn cmd_bf_mexists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    match bf_exists(state, ctx, &args[0], &args[1..]) {
+        Ok(results) => Frame::Array(results),
+        Err(e) => e,
+    }
+}
+
+/// BF.INFO key [CAPACITY | SIZE | FILTERS | ITEMS | EXPANSION]
+fn cmd_bf_info(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len() > 2 {
+        return Frame::error(err_wrong_number("bf.info"));
+    }
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    let bf = match sketch(&db, key, KeyType::Bloom) {
+        Ok(Some(Sketch::Bloom(bf))) => bf,
+        Ok(_) => return Frame::error("ERR not found"),
+        Err(e) => return e,
+    };
+    let fields = [
+        ("CAPACITY", "Capacity", Frame::Integer(bf.capacity() as i64)),
+        ("SIZE", "Size", Frame::Integer(bf.size() as i64)),
+        (
+            "FILTERS",
+            "Number of filters",
+            Frame::Integer(bf.filters() as i64),
+        ),
+        (
+            "ITEMS",
+            "Number of items inserted",
+            Frame::Integer(bf.items() as i64),
+        ),
+        (
+            "EXPANSION",
+            "Expansion rate",
+            bf.expansion()
+                .map_or(Frame::Null, |e| Frame::Integer(e as i64)),
+        ),
+    ];
+    match args.get(1) {
+        None => Frame::Array(
+            fields
+                .into_iter()
+                .flat_map(|(_, name, value)| [Frame::bulk_string(name), value])
+                .collect(),
+        ),
+        Some(arg) => {
+            let opt = String::from_utf8_lossy(arg).to_uppercase();
+            match fields.into_iter().find(|(field, _, _)| *field == opt) {
+                Some((_, _, value)) => Frame::Array(vec![value]),
+                None => Frame::error("Invalid information value"),
+            }
+        }
+    }
+}
+
+// ── Cuckoo filters ───────────────────────────────────────────────────
+
+/// CF.ADD key item
+fn cmd_cf_add(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    let cf = match sketch_or_insert(&mut db, key, KeyType::Cuckoo, new_cuckoo) {
+        Ok(Sketch::Cuckoo(cf)) => cf,
+        Ok(_) => unreachable!(),
+        Err(e) => return e,
+    };
+    if let Err(e) = cf.add(&args[1], &mut state.lock().rng) {
+        return Frame::error(e);
+    }
+    db.incr_version(key, now);
+    Frame::Integer(1)
+}
+
+/// CF.EXISTS key item
+fn cmd_cf_exists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    match sketch(&db, key, KeyType::Cuckoo) {
+        Ok(Some(Sketch::Cuckoo(cf))) => Frame::Integer(cf.contains(&args[1]) as i64),
+        Ok(_) => Frame::Integer(0),
+        Err(e) => e,
+    }
+}
+
+/// CF.DEL key item
+fn cmd_cf_del(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    let cf = match sketch_mut(&mut db, key, KeyType::Cuckoo) {
+        Ok(Some(Sketch::Cuckoo(cf))) => cf,
+        Ok(_) => return Frame::error("Not found"),
+        Err(e) => return e,
+    };
+    if !cf.delete(&args[1]) {
+        return Frame::Integer(0);
+    }
+    db.incr_version(key, now);
+    Frame::Integer(1)
+}
+
+// ── Count-Min sketches ───────────────────────────────────────────────
+
+/// CMS.INITBYDIM key width depth
+fn cmd_cms_initbydim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let width = match parse_int(&args[1]) {
+        Some(w) if w >= 1 && w <= u32::MAX as i64 => w as usize,
+        _ => return Frame::error("CMS: invalid width"),
+    };
+    let depth = match parse_int(&args[2]) {
+        Some(d) if d >= 1 && d <= u32::MAX as i64 => d as usize,
+        _ => return Frame::error("CMS: invalid depth"),
+    };
+    if width
+        .checked_mul(depth)
+        .is_none_or(|n| n > u32::MAX as usize)
+    {
+        return Frame::error("CMS: invalid width");
+    }
+    let cms = CountMinSketch::new(width, depth);
+    reserve(
+        state,
+        ctx,
+        &args[0],
+        Sketch::CountMin(cms),
+        "CMS: key already exists",
+    )
+}
+
+/// CMS.INCRBY key item increment [item increment ...]
+fn cmd_cms_incrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len().is_multiple_of(2) {
+        return Frame::error(err_wrong_number("cms.incrby"));
+    }
+    let key = &args[0];
+    let mut increments = Vec::new();
+    for pair in args[1..].chunks_exact(2) {
+        match parse_int(&pair[1]) {
+            Some(n) if n >= 0 => increments.push((&pair[0], n.min(u32::MAX as i64) as u32)),
+            _ => return Frame::error("CMS: Cannot parse number"),
+        }
+    }
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    let cms = match sketch_mut(&mut db, key, KeyType::CountMinSketch) {
+        Ok(Some(Sketch::CountMin(cms))) => cms,
+        Ok(_) => return Frame::error(MSG_CMS_NO_KEY),
+        Err(e) => return e,
+    };
+    let mut results = Vec::with_capacity(increments.len());
+    let mut err = None;
+    for (item, by) in increments {
+        match cms.incr_by(item, by) {
+            Ok(count) => results.push(Frame::Integer(count as i64)),
+            Err(e) => {
+                err = Some(e);
+                break;
+            }
+        }
+    }
+    if !results.is_empty() {
+        db.incr_version(key, now);
+    }
+    match err {
+        Some(e) => Frame::error(e),
+        None => Frame::Array(results),
+    }
+}
+
+/// CMS.QUERY key item [item ...]
+fn cmd_cms_query(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    match sketch(&db, key, KeyType::CountMinSketch) {
+        Ok(Some(Sketch::CountMin(cms))) => Frame::Array(
+            args[1..]
+                .iter()
+                .map(|item| Frame::Integer(cms.query(item) as i64))
+                .collect(),
+        ),
+        Ok(_) => Frame::error(MSG_CMS_NO_KEY),
+        Err(e) => e,
+    }
+}
+
+// ── Top-K ────────────────────────────────────────────────────────────
+
+/// TOPK.RESERVE key topk [width depth decay]
+fn cmd_topk_reserve(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len() != 2 && args.len() != 5 {
+        return Frame::error(err_wrong_number("topk.reserve"));
+    }
+    let k = match parse_int(&args[1]) {
+        Some(k) if k >= 1 && k <= u32::MAX as i64 => k as usize,
+        _ => return Frame::error("TopK: invalid k"),
+    };
+    let (mut width, mut depth, mut decay) = (
+        TopK::DEFAULT_WIDTH,
+        TopK::DEFAULT_DEPTH,
+        TopK::DEFAULT_DECAY,
+    );
+    if args.len() == 5 {
+        width = match parse_int(&args[2]) {
+            Some(w) if w >= 1 && w <= u32::MAX as i64 => w as usize,
+            _ => return Frame::error("TopK: invalid width"),
+        };
+        depth = match parse_int(&args[3]) {
+            Some(d) if d >= 1 && d <= u32::MAX as i64 => d as usize,
+            _ => return Frame::error("TopK: invalid depth"),
+        };
+        decay = match parse_float(&args[4]) {
+            Some(d) if d > 0.0 && d <= 1.0 => d,
+            _ => return Frame::error("TopK: invalid decay value. must be '<= 1' & '> 0'"),
+        };
+    }
+    if width
+        .checked_mul(depth)
+        .is_none_or(|n| n > u32::MAX as usize)
+    {
+        return Frame::error("TopK: invalid width");
+    }
+    let topk = TopK::new(k, width, depth, decay);
+    reserve(
+        state,
+        ctx,
+        &args[0],
+        Sketch::TopK(topk),
+        "TopK: key already exists",
+    )
+}
+
+/// TOPK.ADD key item [item ...]
+fn cmd_topk_add(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    let topk = match sketch_mut(&mut db, key, KeyType::TopK) {
+        Ok(Some(Sketch::TopK(topk))) => topk,
+        Ok(_) => return Frame::error(MSG_TOPK_NO_KEY),
+        Err(e) => return e,
+    };
+    let mut inner = state.lock();
+    let results = args[1..]
+        .iter()
+        .map(|item| match topk.add(item, &mut inner.rng) {
+            Some(expelled) => Frame::bulk(expelled),
+            None => Frame::Null,
+        })
+        .collect();
+    drop(inner);
+    db.incr_version(key, now);
+    Frame::Array(results)
+}
+
+/// TOPK.LIST key [WITHCOUNT]
+fn cmd_topk_list(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let with_count = match args.get(1..).unwrap_or_default() {
+        [] => false,
+        [opt] if opt.eq_ignore_ascii_case(b"WITHCOUNT") => true,
+        [_] => return Frame::error(MSG_SYNTAX_ERROR),
+        _ => return Frame::error(err_wrong_number("topk.list")),
+    };
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    let topk = match sketch(&db, key, KeyType::TopK) {
+        Ok(Some(Sketch::TopK(topk))) => topk,
+        Ok(_) => return Frame::error(MSG_TOPK_NO_KEY),
+        Err(e) => return e,
+    };
+    let mut results = Vec::new();
+    for (item, count) in topk.list() {
+        results.push(Frame::bulk(item.to_vec()));
+        if with_count {
+            results.push(Frame::Integer(count as i64));
+        }
+    }
+    Frame::Array(results)
+}
@@ -2,14 +2,15 @@ use std::sync::Arc;
 
 use crate::connection::ConnCtx;
 use crate::db::SharedState;
-use crate::dispatch::CommandTable;
+use crate::dispatch::{CommandTable, MSG_INVALID_INT, MSG_SYNTAX_ERROR, err_wrong_number};
 use crate::frame::Frame;
+use crate::tracking::{TrackingOpts, check_prefixes};
 
 pub fn register(table: &mut CommandTable) {
     table.add("CLIENT", cmd_client, false, -2);
 }
 
-fn cmd_client(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+fn cmd_client(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
     match subcmd.as_str() {
         "SETNAME" => {
@@ -34,9 +35,145 @@ fn cmd_client(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 None => Frame::Null,
             }
         }
+        "ID" => {
+            if args.len() != 1 {
+                return Frame::error(err_wrong_number("client|id"));
+            }
+            Frame::Integer(ctx.client_id as i64)
+        }
+        "TRACKING" => client_tracking(state, ctx, &args[1..]),
+        "CACHING" => client_caching(state, ctx, &args[1..]),
+        "GETREDIR" => {
+            if args.len() != 1 {
+                return Frame::error(err_wrong_number("client|getredir"));
+            }
+            let table = state.tracking.lock().unwrap();
+            match table.opts(ctx.client_id) {
+                Some(opts) => Frame::Integer(opts.redirect.map_or(0, |id| id as i64)),
+                None => Frame::Integer(-1),
+            }
+        }
         _ => Frame::error(format!(
             "ERR unknown subcommand '{}'. Try CLIENT HELP.",
             subcmd.to_lowercase()
         )),
     }
 }
+
+/// CLIENT TRACKING ON|OFF [REDIRECT id] [PREFIX prefix ...] [BCAST] [OPTIN] [OPTOUT] [NOLOOP]
+fn client_tracking(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.is_empty() {
+        return Frame::error(err_wrong_number("client|tracking"));
+    }
+    let on = match String::from_utf8_lossy(&args[0]).to_uppercase().as_str() {
+        "ON" => true,
+        "OFF" => false,
+        _ => return Frame::error(MSG_SYNTAX_ERROR),
+    };
+
+    let mut opts = TrackingOpts::default();
+    let mut i = 1;
+    while i < args.len() {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        match opt.as_str() {
+            "REDIRECT" if i + 1 < args.len() => {
+                match String::from_utf8_lossy(&args[i + 1]).parse::<u64>() {
+                    Ok(id) => opts.redirect = Some(id),
+                    Err(_) => return Frame::error(MSG_INVALID_INT),
+                }
+                i += 1;
+            }
+            "PREFIX" if i + 1 < args.len() => {
+                opts.prefixes.push(args[i + 1].clone());
+                i += 1;
+            }
+            "BCAST" => opts.bcast = true,
+            "OPTIN" => opts.optin = true,
+            "OPTOUT" => opts.optout = true,
+            "NOLOOP" => opts.noloop = true,
+            _ => return Frame::error(MSG_SYNTAX_ERROR),
+        }
+        i += 1;
+    }
+
+    if !on {
+        state.tracking.lock().unwrap().set_opts(ctx.client_id, None);
+        ctx.tracking_caching = None;
+        state.update_tracking();
+        return Frame::ok();
+    }
+
+    if !opts.bcast && !opts.prefixes.is_empty() {
+        return Frame::error("ERR PREFIX option requires BCAST mode to be enabled");
+    }
+    if opts.bcast && (opts.optin || opts.optout) {
+        return Frame::error("ERR OPTIN and OPTOUT are not compatible with BCAST");
+    }
+    if opts.optin && opts.optout {
+        return Frame::error("ERR You can't use both OPTIN and OPTOUT");
+    }
+
+    {
+        let mut table = state.tracking.lock().unwrap();
+        if let Some(id) = opts.redirect
+            && id != ctx.client_id
+            && !table.exists(id)
+        {
+            return Frame::error("ERR The client ID you want redirect to does not exist");
+        }
+        if let Some(old) = table.opts(ctx.client_id) {
+            if old.bcast != opts.bcast {
+                return Frame::error(
+                    "ERR You can't switch BCAST mode on/off before disabling tracking for this client, and then re-enabling it with a different mode.",
+                );
+            }
+            if old.optin != opts.optin || old.optout != opts.optout {
+                return Frame::error(
+                    "ERR You can't switch OPTIN/OPTOUT mode before disabling tracking for this client, and then re-enabling it with a different mode.",
+                );
+            }
+            // Turning tracking on again adds to the existing prefixes.
+            let mut prefixes = old.prefixes.clone();
+            prefixes.append(&mut opts.prefixes);
+            opts.prefixes = prefixes;
+        }
+        if let Err(e) = check_prefixes(&opts.prefixes) {
+            return Frame::error(e);
+        }
+        table.set_opts(ctx.client_id, Some(opts));
+    }
+    state.update_tracking();
+    Frame::ok()
+}
+
+/// CLIENT CACHING YES|NO
+fn client_caching(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len() != 1 {
+        return Frame::error(err_wrong_number("client|caching"));
+    }
+    let (optin, optout) = match state.tracking.lock().unwrap().opts(ctx.client_id) {
+        Some(opts) => (opts.optin, opts.optout),
+        None => (false, false),
+    };
+    if !optin && !optout {
+        return Frame::error(
+            "ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled",
+        );
+    }
+    match String::from_utf8_lossy(&args[0]).to_uppercase().as_str() {
+        "YES" if optin => ctx.tracking_caching = Some(true),
+        "YES" => {
+            return Frame::error(
+                "ERR CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode.",
+            );
+        }
+        "NO" if optout => ctx.tracking_caching = Some(false),
+        "NO" => {
+            return Frame::error(
+                "ERR CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode.",
+            );
+        }
+        _ => return Frame::error(MSG_SYNTAX_ERROR),
+    }
+    Frame::ok()
+}
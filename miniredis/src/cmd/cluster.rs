@@ -1,73 +1,206 @@
 use std::sync::Arc;
 
+use crate::cluster::{CLUSTER_SLOTS, Topology, key_slot};
 use crate::connection::ConnCtx;
 use crate::db::SharedState;
-use crate::dispatch::CommandTable;
+use crate::dispatch::{CommandTable, MSG_INVALID_INT, err_wrong_number};
 use crate::frame::Frame;
 
 pub fn register(table: &mut CommandTable) {
     table.add("CLUSTER", cmd_cluster, true, -2);
+    table.add("ASKING", cmd_asking, true, 1);
+    table.add("READONLY", cmd_readonly, true, 1);
+    table.add("READWRITE", cmd_readonly, true, 1);
 }
 
-fn cmd_cluster(_state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+/// The cluster layout and this node's index in it. A server that isn't
+/// part of a `MiniredisCluster` reports a single node with every slot.
+fn topology(state: &Arc<SharedState>) -> (Topology, usize) {
+    match state.cluster.get() {
+        Some(cluster) => (cluster.topology.lock().unwrap().clone(), cluster.myself),
+        None => (Topology::standalone(), 0),
+    }
+}
+
+fn cmd_cluster(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let args = &args[1..];
+    let arity_ok = match subcmd.as_str() {
+        "SLOTS" | "NODES" | "SHARDS" | "MYID" => args.is_empty(),
+        "KEYSLOT" | "COUNTKEYSINSLOT" => args.len() == 1,
+        "GETKEYSINSLOT" => args.len() == 2,
+        _ => {
+            return Frame::error(format!(
+                "ERR unknown subcommand '{}'. Try CLUSTER HELP.",
+                subcmd.to_lowercase()
+            ));
+        }
+    };
+    if !arity_ok {
+        return Frame::error(err_wrong_number(&format!(
+            "cluster|{}",
+            subcmd.to_lowercase()
+        )));
+    }
+
     match subcmd.as_str() {
-        "SLOTS" => {
-            // Single-node cluster: one slot range 0-16383
-            Frame::Array(vec![Frame::Array(vec![
-                Frame::Integer(0),
-                Frame::Integer(16383),
-                Frame::Array(vec![
-                    Frame::Bulk("127.0.0.1".into()),
-                    Frame::Integer(6379),
-                    Frame::Bulk(
-                        "09dbe9720cda62f7865eabc5fd8857c5d2678366".into(),
-                    ),
-                ]),
-            ])])
+        "SLOTS" => cluster_slots(state),
+        "NODES" => cluster_nodes(state),
+        "SHARDS" => cluster_shards(state),
+        "MYID" => {
+            let (topology, myself) = topology(state);
+            Frame::Bulk(topology.nodes[myself].id.clone().into())
+        }
+        "KEYSLOT" => Frame::Integer(key_slot(&args[0]) as i64),
+        _ => {
+            let Some(slot) = String::from_utf8_lossy(&args[0])
+                .parse::<usize>()
+                .ok()
+                .filter(|&s| s < CLUSTER_SLOTS)
+            else {
+                return Frame::error("ERR Invalid slot");
+            };
+            let keys = state
+                .db(ctx.selected_db)
+                .all_keys()
+                .into_iter()
+                .filter(|k| key_slot(k) as usize == slot);
+            if subcmd == "COUNTKEYSINSLOT" {
+                return Frame::Integer(keys.count() as i64);
+            }
+            let count = match String::from_utf8_lossy(&args[1]).parse::<i64>() {
+                Ok(n) if n >= 0 => n as usize,
+                Ok(_) => return Frame::error("ERR Invalid number of keys"),
+                Err(_) => return Frame::error(MSG_INVALID_INT),
+            };
+            Frame::Array(keys.take(count).map(|k| Frame::Bulk(k.into())).collect())
         }
-        "KEYSLOT" => {
-            if args.len() != 2 {
-                return Frame::error(
-                    "ERR wrong number of arguments for 'cluster|keyslot' command",
-                );
+    }
+}
+
+/// CLUSTER SLOTS
+fn cluster_slots(state: &Arc<SharedState>) -> Frame {
+    let (topology, _) = topology(state);
+    Frame::Array(
+        topology
+            .all_ranges()
+            .into_iter()
+            .map(|(start, end, owner)| {
+                let node = &topology.nodes[owner];
+                Frame::Array(vec![
+                    Frame::Integer(start as i64),
+                    Frame::Integer(end as i64),
+                    Frame::Array(vec![
+                        Frame::Bulk(node.addr.ip().to_string().into()),
+                        Frame::Integer(node.addr.port() as i64),
+                        Frame::Bulk(node.id.clone().into()),
+                    ]),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// CLUSTER NODES
+fn cluster_nodes(state: &Arc<SharedState>) -> Frame {
+    let (topology, myself) = topology(state);
+    let mut out = String::new();
+    for (i, node) in topology.nodes.iter().enumerate() {
+        let flags = if i == myself {
+            "myself,master"
+        } else {
+            "master"
+        };
+        out.push_str(&format!(
+            "{} {}@{} {} - 0 0 {} connected",
+            node.id,
+            node.addr,
+            node.addr.port(),
+            flags,
+            i + 1
+        ));
+        for (start, end) in topology.ranges(i) {
+            if start == end {
+                out.push_str(&format!(" {start}"));
+            } else {
+                out.push_str(&format!(" {start}-{end}"));
             }
-            // Simplified: always return 163
-            Frame::Integer(163)
         }
-        "NODES" => {
-            Frame::Bulk(
-                "e7d1eecce10fd6bb5eb35b9f99a514335d9ba9ca 127.0.0.1:6379@6379 myself,master - 0 0 1 connected 0-16383\n"
-                    .into(),
-            )
+        // Only a node's own line shows the slots it's migrating or
+        // importing.
+        if i == myself {
+            let mut migrations: Vec<(u16, usize)> = topology.migrations().collect();
+            migrations.sort_unstable();
+            for (slot, to) in migrations {
+                let from = topology.owner(slot);
+                if from == myself {
+                    out.push_str(&format!(" [{slot}->-{}]", topology.nodes[to].id));
+                } else if to == myself {
+                    out.push_str(&format!(" [{slot}-<-{}]", topology.nodes[from].id));
+                }
+            }
         }
-        "SHARDS" => {
-            // Simplified shard info as a flat array
-            Frame::Array(vec![Frame::Array(vec![
-                Frame::Bulk("slots".into()),
-                Frame::Array(vec![Frame::Integer(0), Frame::Integer(16383)]),
-                Frame::Bulk("nodes".into()),
-                Frame::Array(vec![Frame::Array(vec![
-                    Frame::Bulk("id".into()),
-                    Frame::Bulk(
-                        "13f84e686106847b76671957dd348fde540a77bb".into(),
+        out.push('\n');
+    }
+    Frame::Bulk(out.into())
+}
+
+/// CLUSTER SHARDS
+fn cluster_shards(state: &Arc<SharedState>) -> Frame {
+    let (topology, _) = topology(state);
+    Frame::Array(
+        topology
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let slots = topology
+                    .ranges(i)
+                    .into_iter()
+                    .flat_map(|(start, end)| [start, end])
+                    .map(|s| Frame::Integer(s as i64))
+                    .collect();
+                let ip = node.addr.ip().to_string();
+                Frame::Map(vec![
+                    (Frame::bulk_string("slots"), Frame::Array(slots)),
+                    (
+                        Frame::bulk_string("nodes"),
+                        Frame::Array(vec![Frame::Map(vec![
+                            (
+                                Frame::bulk_string("id"),
+                                Frame::Bulk(node.id.clone().into()),
+                            ),
+                            (
+                                Frame::bulk_string("port"),
+                                Frame::Integer(node.addr.port() as i64),
+                            ),
+                            (Frame::bulk_string("ip"), Frame::Bulk(ip.clone().into())),
+                            (Frame::bulk_string("endpoint"), Frame::Bulk(ip.into())),
+                            (Frame::bulk_string("role"), Frame::bulk_string("master")),
+                            (Frame::bulk_string("replication-offset"), Frame::Integer(0)),
+                            (Frame::bulk_string("health"), Frame::bulk_string("online")),
+                        ])]),
                     ),
-                    Frame::Bulk("ip".into()),
-                    Frame::Bulk("127.0.0.1".into()),
-                    Frame::Bulk("port".into()),
-                    Frame::Integer(6379),
-                    Frame::Bulk("role".into()),
-                    Frame::Bulk("master".into()),
-                    Frame::Bulk("replication-offset".into()),
-                    Frame::Integer(0),
-                    Frame::Bulk("health".into()),
-                    Frame::Bulk("online".into()),
-                ])]),
-            ])])
-        }
-        _ => Frame::error(format!(
-            "ERR unknown subcommand '{}'. Try CLUSTER HELP.",
-            subcmd.to_lowercase()
-        )),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// ASKING
+fn cmd_asking(state: &Arc<SharedState>, ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    if state.cluster.get().is_none() {
+        return Frame::error("ERR This instance has cluster support disabled");
+    }
+    ctx.asking = true;
+    Frame::ok()
+}
+
+/// READONLY and READWRITE. There are no replicas, so these don't change
+/// anything.
+fn cmd_readonly(state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    if state.cluster.get().is_none() {
+        return Frame::error("ERR This instance has cluster support disabled");
     }
+    Frame::ok()
 }
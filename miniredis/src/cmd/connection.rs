@@ -1,9 +1,10 @@
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{Inner, SharedState};
 use crate::dispatch::{
-    CommandTable, MSG_DB_INDEX_OUT_OF_RANGE, MSG_SYNTAX_ERROR, err_wrong_number,
+    CommandTable, MSG_DB_INDEX_OUT_OF_RANGE, MSG_SYNTAX_ERROR, client_info, err_wrong_number,
 };
 use crate::frame::Frame;
 
@@ -36,7 +37,7 @@ fn cmd_quit(_state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) ->
 }
 
 /// SELECT db
-fn cmd_select(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+fn cmd_select(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let db_str = match std::str::from_utf8(&args[0]) {
         Ok(s) => s,
         Err(_) => return Frame::error(crate::dispatch::MSG_INVALID_INT),
@@ -50,6 +51,9 @@ fn cmd_select(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     if !(0..16).contains(&db) {
         return Frame::error(MSG_DB_INDEX_OUT_OF_RANGE);
     }
+    if db != 0 && state.cluster.get().is_some() {
+        return Frame::error("ERR SELECT is not allowed in cluster mode");
+    }
 
     ctx.selected_db = db as usize;
     Frame::ok()
@@ -73,21 +77,42 @@ fn cmd_auth(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         )
     };
 
-    let inner = state.lock();
+    let mut inner = state.lock();
 
-    if inner.passwords.is_empty() && username == "default" {
+    if username == "default" && inner.acl.user("default").is_some_and(|u| u.nopass) {
         return Frame::error(
             "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
         );
     }
 
-    match inner.passwords.get(&username) {
-        Some(pw) if pw == &password => {
-            ctx.authenticated = true;
-            Frame::ok()
-        }
-        _ => Frame::error("WRONGPASS invalid username-password pair"),
+    match authenticate(&mut inner, ctx, &username, &password, state.now()) {
+        Ok(()) => Frame::ok(),
+        Err(e) => e,
+    }
+}
+
+/// Log in as `username`, or log the failure to the ACL LOG.
+fn authenticate(
+    inner: &mut Inner,
+    ctx: &mut ConnCtx,
+    username: &str,
+    password: &str,
+    now: SystemTime,
+) -> Result<(), Frame> {
+    if inner.acl.authenticate(username, password) {
+        ctx.authenticated = true;
+        ctx.user = username.to_string();
+        return Ok(());
     }
+    inner.acl.log(
+        "auth",
+        "toplevel",
+        "AUTH".to_string(),
+        username.to_string(),
+        client_info(ctx),
+        now,
+    );
+    Err(Frame::error("WRONGPASS invalid username-password pair"))
 }
 
 /// HELLO protover [AUTH username password] [SETNAME clientname]
@@ -152,19 +177,12 @@ fn cmd_hello(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     }
 
     // Check authentication if AUTH was provided
-    let inner = state.lock();
-    if inner.passwords.is_empty() && username == "default" {
+    let mut inner = state.lock();
+    if username == "default" && inner.acl.user("default").is_some_and(|u| u.nopass) {
         check_auth = false;
     }
-    if check_auth {
-        match inner.passwords.get(&username) {
-            Some(pw) if pw == &password => {
-                ctx.authenticated = true;
-            }
-            _ => {
-                return Frame::error("WRONGPASS invalid username-password pair");
-            }
-        }
+    if check_auth && let Err(e) = authenticate(&mut inner, ctx, &username, &password, state.now()) {
+        return e;
     }
 
     // Set RESP3 mode if version is 3
@@ -0,0 +1,759 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+
+use mlua::prelude::*;
+
+use crate::cmd::scripting::{
+    KeysAndArgs, call_script, lua_string_array, lua_to_frame, msg_not_from_scripts, new_sandbox,
+    new_script_env, sanitize_lua_error, script_runtime_error, split_numkeys,
+};
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::dispatch::{CommandTable, MSG_READONLY, err_wrong_number};
+use crate::frame::Frame;
+use crate::rdb::{OPCODE_FUNCTION2, RDB_VERSION, crc64, read_len, write_len};
+use crate::replication::rejects_writes;
+use crate::script::MSG_FUNCTION_KILLED;
+
+pub fn register(table: &mut CommandTable) {
+    table.add("FCALL", cmd_fcall, false, -3);
+    table.add("FCALL_RO", cmd_fcall_ro, true, -3);
+    table.add("FUNCTION", cmd_function, false, -2);
+}
+
+const MSG_FUNCTION_NOT_FOUND: &str = "ERR Function not found";
+const MSG_LIBRARY_NOT_FOUND: &str = "ERR Library not found";
+const MSG_RO_WITH_WRITE_FLAG: &str =
+    "ERR Can not execute a script with write flag using *_ro command.";
+const MSG_BAD_PAYLOAD: &str = "ERR payload version or checksum are wrong";
+
+/// Flags accepted by `redis.register_function`.
+const KNOWN_FLAGS: &[&str] = &[
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// Lua registry key for the table of registered callbacks (name -> function).
+const CALLBACKS_KEY: &str = "miniredis_function_callbacks";
+
+/// A function library registered with FUNCTION LOAD.
+#[derive(Clone, Debug)]
+pub struct FunctionLibrary {
+    pub name: String,
+    /// The full library source, including the `#!lua` header line.
+    pub code: String,
+    /// The functions the library registered, in registration order.
+    pub functions: Vec<FunctionMeta>,
+}
+
+/// A single function registered with `redis.register_function`.
+#[derive(Clone, Debug)]
+pub struct FunctionMeta {
+    pub name: String,
+    pub description: Option<String>,
+    pub flags: Vec<String>,
+}
+
+impl FunctionMeta {
+    /// True if the function declared the `no-writes` flag.
+    pub fn no_writes(&self) -> bool {
+        self.flags.iter().any(|f| f == "no-writes")
+    }
+}
+
+/// Validate a library or function name: `[A-Za-z0-9_]+`.
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Parse the `#!lua name=<library>` header. Returns the library name and the
+/// code without the header (the header line is blanked so that line numbers
+/// in error messages still match).
+fn parse_header(code: &str) -> Result<(String, String), String> {
+    let Some(rest) = code.strip_prefix("#!") else {
+        return Err("ERR Missing library metadata".to_string());
+    };
+    let (header, body) = match rest.find('\n') {
+        Some(pos) => (&rest[..pos], &rest[pos..]),
+        None => (rest, ""),
+    };
+
+    let mut parts = header.split(' ').filter(|p| !p.is_empty());
+    let engine = parts.next().unwrap_or("");
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(format!("ERR Engine '{}' not found", engine));
+    }
+
+    let mut name = None;
+    for part in parts {
+        match part.strip_prefix("name=") {
+            Some(n) => {
+                if name.is_some() {
+                    return Err(
+                        "ERR Invalid metadata value, name argument was given multiple times"
+                            .to_string(),
+                    );
+                }
+                name = Some(n.to_string());
+            }
+            None => return Err(format!("ERR Invalid metadata value given: {}", part)),
+        }
+    }
+
+    let Some(name) = name else {
+        return Err("ERR Library name was not given".to_string());
+    };
+    if !valid_name(&name) {
+        return Err("ERR Library names can only contain letters, numbers, or underscores(_) and must be at least one character long".to_string());
+    }
+    Ok((name, body.to_string()))
+}
+
+/// Parse the arguments of `redis.register_function`, either positional
+/// `(name, callback)` or a single table of named arguments.
+fn parse_register_args(args: LuaMultiValue) -> LuaResult<(String, LuaFunction, FunctionMeta)> {
+    let err = |msg: &str| LuaError::RuntimeError(msg.to_string());
+    let args: Vec<LuaValue> = args.into_iter().collect();
+
+    let (name, callback, flags, description) = match args.len() {
+        1 => {
+            let LuaValue::Table(tbl) = &args[0] else {
+                return Err(err(
+                    "calling redis.register_function with a single argument is only applicable to Lua table (representing named arguments).",
+                ));
+            };
+            let mut name = None;
+            let mut callback = None;
+            let mut flags = None;
+            let mut description = None;
+            for pair in tbl.clone().pairs::<LuaValue, LuaValue>() {
+                let (k, v) = pair?;
+                let key = match &k {
+                    LuaValue::String(s) => s.to_str()?.to_string(),
+                    _ => {
+                        return Err(err(
+                            "named argument key given to redis.register_function is not a string",
+                        ));
+                    }
+                };
+                match key.as_str() {
+                    "function_name" => match v {
+                        LuaValue::String(s) => name = Some(s.to_str()?.to_string()),
+                        _ => {
+                            return Err(err(
+                                "function_name argument given to redis.register_function must be a string",
+                            ));
+                        }
+                    },
+                    "callback" => match v {
+                        LuaValue::Function(f) => callback = Some(f),
+                        _ => {
+                            return Err(err(
+                                "callback argument given to redis.register_function must be a function",
+                            ));
+                        }
+                    },
+                    "flags" => match v {
+                        LuaValue::Table(t) => flags = Some(t),
+                        _ => {
+                            return Err(err(
+                                "flags argument to redis.register_function must be a table representing function flags",
+                            ));
+                        }
+                    },
+                    "description" => match v {
+                        LuaValue::String(s) => description = Some(s.to_str()?.to_string()),
+                        _ => {
+                            return Err(err(
+                                "description argument given to redis.register_function must be a string",
+                            ));
+                        }
+                    },
+                    _ => return Err(err("unknown argument given to redis.register_function")),
+                }
+            }
+            let Some(name) = name else {
+                return Err(err(
+                    "redis.register_function must get a function name argument",
+                ));
+            };
+            let Some(callback) = callback else {
+                return Err(err("redis.register_function must get a callback argument"));
+            };
+            (name, callback, flags, description)
+        }
+        2 => {
+            let mut args = args.into_iter();
+            let name = match args.next() {
+                Some(LuaValue::String(s)) => s.to_str()?.to_string(),
+                _ => {
+                    return Err(err(
+                        "first argument to redis.register_function must be a string",
+                    ));
+                }
+            };
+            let callback = match args.next() {
+                Some(LuaValue::Function(f)) => f,
+                _ => {
+                    return Err(err(
+                        "second argument to redis.register_function must be a function",
+                    ));
+                }
+            };
+            (name, callback, None, None)
+        }
+        _ => return Err(err("wrong number of arguments to redis.register_function")),
+    };
+
+    if !valid_name(&name) {
+        return Err(err(
+            "Function names can only contain letters, numbers, or underscores(_) and must be at least one character long",
+        ));
+    }
+
+    let mut flag_names = Vec::new();
+    if let Some(flags) = flags {
+        for flag in flags.sequence_values::<LuaValue>() {
+            let flag = match flag? {
+                LuaValue::String(s) => s.to_str()?.to_string(),
+                _ => return Err(err("unknown flag given")),
+            };
+            if !KNOWN_FLAGS.contains(&flag.as_str()) {
+                return Err(err("unknown flag given"));
+            }
+            if !flag_names.contains(&flag) {
+                flag_names.push(flag);
+            }
+        }
+    }
+
+    let meta = FunctionMeta {
+        name: name.clone(),
+        description,
+        flags: flag_names,
+    };
+    Ok((name, callback, meta))
+}
+
+/// Install `redis.register_function` into the given `redis` table. Callbacks
+/// are kept in a Lua registry table, their metadata is pushed to `registered`.
+fn install_register_function(
+    lua: &Lua,
+    redis_table: &LuaTable,
+    registered: Arc<Mutex<Vec<FunctionMeta>>>,
+) -> LuaResult<()> {
+    lua.set_named_registry_value(CALLBACKS_KEY, lua.create_table()?)?;
+    let register_fn = lua.create_function(move |lua_ctx, args: LuaMultiValue| {
+        let (name, callback, meta) = parse_register_args(args)?;
+        let mut registered = registered.lock().unwrap();
+        if registered.iter().any(|f| f.name == name) {
+            return Err(LuaError::RuntimeError(
+                "Function already exists in the library".to_string(),
+            ));
+        }
+        let callbacks: LuaTable = lua_ctx.named_registry_value(CALLBACKS_KEY)?;
+        callbacks.set(name, callback)?;
+        registered.push(meta);
+        Ok(())
+    })?;
+    redis_table.set("register_function", register_fn)
+}
+
+/// Compile and run a library's code in a load-time environment, where only
+/// `redis.register_function` and `redis.log` are available. Returns the
+/// library name and the functions it registered.
+fn load_library(code: &str) -> Result<(String, Vec<FunctionMeta>), String> {
+    let (name, body) = parse_header(code)?;
+
+    let lua = new_sandbox()?;
+    let registered = Arc::new(Mutex::new(Vec::new()));
+    {
+        let redis_table = lua.create_table().map_err(|e| e.to_string())?;
+        install_register_function(&lua, &redis_table, Arc::clone(&registered))
+            .map_err(|e| e.to_string())?;
+        let log_fn = lua
+            .create_function(|_, _: LuaMultiValue| Ok(()))
+            .map_err(|e| e.to_string())?;
+        redis_table.set("log", log_fn).map_err(|e| e.to_string())?;
+        for (level, n) in [
+            ("LOG_DEBUG", 0),
+            ("LOG_VERBOSE", 1),
+            ("LOG_NOTICE", 2),
+            ("LOG_WARNING", 3),
+        ] {
+            redis_table.set(level, n).map_err(|e| e.to_string())?;
+        }
+        lua.globals()
+            .set("redis", redis_table)
+            .map_err(|e| e.to_string())?;
+    }
+
+    let func = lua.load(body.as_str()).into_function().map_err(|e| {
+        format!(
+            "ERR Error compiling function: {}",
+            sanitize_lua_error(&e.to_string())
+        )
+    })?;
+    let _: () = func.call(()).map_err(|e| {
+        format!(
+            "ERR Error registering functions: {}",
+            sanitize_lua_error(&e.to_string())
+        )
+    })?;
+
+    let functions = std::mem::take(&mut *registered.lock().unwrap());
+    if functions.is_empty() {
+        return Err("ERR No functions registered".to_string());
+    }
+    Ok((name, functions))
+}
+
+/// Compile a library, e.g. one read from an RDB file, without installing it.
+pub(crate) fn compile_library(code: &str) -> Result<FunctionLibrary, String> {
+    let (name, functions) = load_library(code)?;
+    Ok(FunctionLibrary {
+        name,
+        code: code.to_string(),
+        functions,
+    })
+}
+
+/// Add a library to the registry. Fails if the library exists (unless
+/// `replace`) or if one of its functions is already defined by another library.
+fn install_library(state: &Arc<SharedState>, code: &str, replace: bool) -> Result<String, String> {
+    let (name, functions) = load_library(code)?;
+
+    let mut inner = state.lock();
+    if inner.functions.contains_key(&name) && !replace {
+        return Err(format!("ERR Library '{}' already exists", name));
+    }
+    for lib in inner.functions.values() {
+        if lib.name == name {
+            continue;
+        }
+        if let Some(f) = lib
+            .functions
+            .iter()
+            .find(|f| functions.iter().any(|n| n.name == f.name))
+        {
+            return Err(format!("ERR Function {} already exists", f.name));
+        }
+    }
+    inner.functions.insert(
+        name.clone(),
+        FunctionLibrary {
+            name: name.clone(),
+            code: code.to_string(),
+            functions,
+        },
+    );
+    Ok(name)
+}
+
+/// Run a registered function: re-create the library in a full script
+/// environment and call the registered callback with (keys, args), until
+/// it returns or `killed` is set.
+fn run_function(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    name: &str,
+    code: &str,
+    read_only: bool,
+    (keys, argv): KeysAndArgs,
+    killed: Arc<AtomicBool>,
+) -> Result<Frame, String> {
+    let (_, body) = parse_header(code)?;
+
+    let lua = new_script_env(state, ctx, name, read_only)?;
+    let redis_table: LuaTable = lua.globals().get("redis").map_err(|e| e.to_string())?;
+    install_register_function(&lua, &redis_table, Arc::new(Mutex::new(Vec::new())))
+        .map_err(|e| e.to_string())?;
+
+    lua.load(body.as_str())
+        .exec()
+        .map_err(|e| script_runtime_error(&e, "@user_function"))?;
+
+    let callbacks: LuaTable = lua
+        .named_registry_value(CALLBACKS_KEY)
+        .map_err(|e| e.to_string())?;
+    let callback: LuaFunction = callbacks
+        .get(name)
+        .map_err(|_| MSG_FUNCTION_NOT_FOUND.to_string())?;
+
+    let keys_table = lua_string_array(&lua, keys)?;
+    let argv_table = lua_string_array(&lua, argv)?;
+    let args = (keys_table, argv_table)
+        .into_lua_multi(&lua)
+        .map_err(|e| e.to_string())?;
+    let result = call_script(&lua, callback, args, killed, MSG_FUNCTION_KILLED)
+        .map_err(|e| script_runtime_error(&e, "@user_function"))?;
+
+    Ok(lua_to_frame(result))
+}
+
+// ── FUNCTION DUMP/RESTORE payload ───────────────────────────────────
+
+/// Serialize all libraries in the FUNCTION DUMP format: one
+/// `FUNCTION2` opcode + code string per library, then the RDB version and a
+/// CRC64 checksum.
+fn dump_payload(libraries: &[&FunctionLibrary]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for lib in libraries {
+        out.push(OPCODE_FUNCTION2);
+        write_len(&mut out, lib.code.len() as u64);
+        out.extend_from_slice(lib.code.as_bytes());
+    }
+    out.extend_from_slice(&RDB_VERSION.to_le_bytes());
+    let crc = crc64(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// Parse a FUNCTION DUMP payload into library sources.
+fn parse_payload(payload: &[u8]) -> Result<Vec<String>, String> {
+    if payload.len() < 10 {
+        return Err(MSG_BAD_PAYLOAD.to_string());
+    }
+    let (body, footer) = payload.split_at(payload.len() - 10);
+    let version = u16::from_le_bytes([footer[0], footer[1]]);
+    let crc = u64::from_le_bytes(footer[2..].try_into().unwrap());
+    if version > RDB_VERSION + 1 || crc64(&payload[..payload.len() - 8]) != crc {
+        return Err(MSG_BAD_PAYLOAD.to_string());
+    }
+
+    let mut codes = Vec::new();
+    let mut pos = 0;
+    while pos < body.len() {
+        if body[pos] != OPCODE_FUNCTION2 {
+            return Err("ERR given type is not a function".to_string());
+        }
+        pos += 1;
+        let len = read_len(body, &mut pos).ok_or_else(|| MSG_BAD_PAYLOAD.to_string())?;
+        let code = body
+            .get(pos..pos + len)
+            .ok_or_else(|| MSG_BAD_PAYLOAD.to_string())?;
+        pos += len;
+        codes.push(String::from_utf8_lossy(code).into_owned());
+    }
+    Ok(codes)
+}
+
+// ── Command handlers ────────────────────────────────────────────────
+
+/// FCALL function numkeys [key ...] [arg ...]
+fn cmd_fcall(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    fcall_shared(state, ctx, args, false)
+}
+
+/// FCALL_RO function numkeys [key ...] [arg ...]
+fn cmd_fcall_ro(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    fcall_shared(state, ctx, args, true)
+}
+
+fn fcall_shared(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+    read_only: bool,
+) -> Frame {
+    if ctx.nested {
+        return Frame::error(msg_not_from_scripts(
+            ctx.nested_sha.as_deref().unwrap_or(""),
+        ));
+    }
+
+    let name = String::from_utf8_lossy(&args[0]).into_owned();
+    let found = {
+        let inner = state.lock();
+        inner.functions.values().find_map(|lib| {
+            lib.functions
+                .iter()
+                .find(|f| f.name == name)
+                .map(|f| (lib.code.clone(), f.clone()))
+        })
+    };
+    let Some((code, meta)) = found else {
+        return Frame::error(MSG_FUNCTION_NOT_FOUND);
+    };
+
+    if read_only && !meta.no_writes() {
+        return Frame::error(MSG_RO_WITH_WRITE_FLAG);
+    }
+    if !meta.no_writes() && rejects_writes(state, ctx) {
+        return Frame::error(MSG_READONLY);
+    }
+
+    let split = match split_numkeys(&args[1..]) {
+        Ok(split) => split,
+        Err(msg) => return Frame::error(msg),
+    };
+
+    let command_name: &[u8] = if read_only { b"FCALL_RO" } else { b"FCALL" };
+    let command: Vec<Vec<u8>> = std::iter::once(command_name.to_vec())
+        .chain(args.iter().cloned())
+        .collect();
+    let guard = crate::script::start(state, ctx.client_id, &name, &command, true);
+    match run_function(
+        state,
+        ctx,
+        &name,
+        &code,
+        read_only || meta.no_writes(),
+        split,
+        guard.killed(),
+    ) {
+        Ok(frame) => frame,
+        Err(msg) => Frame::error(msg),
+    }
+}
+
+/// FUNCTION LOAD|LIST|DELETE|DUMP|RESTORE|FLUSH|STATS|KILL
+fn cmd_function(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if ctx.nested {
+        return Frame::error(msg_not_from_scripts(
+            ctx.nested_sha.as_deref().unwrap_or(""),
+        ));
+    }
+
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let sub_args = &args[1..];
+
+    match subcmd.as_str() {
+        "LOAD" => {
+            let (replace, code) = match sub_args {
+                [code] => (false, code),
+                [opt, code] if opt.eq_ignore_ascii_case(b"REPLACE") => (true, code),
+                [opt, _] => {
+                    return Frame::error(format!(
+                        "ERR Unknown option given: {}",
+                        String::from_utf8_lossy(opt)
+                    ));
+                }
+                _ => return Frame::error(err_wrong_number("function|load")),
+            };
+            let code = String::from_utf8_lossy(code).into_owned();
+            match install_library(state, &code, replace) {
+                Ok(name) => Frame::Bulk(name.into()),
+                Err(msg) => Frame::error(msg),
+            }
+        }
+        "LIST" => {
+            let mut with_code = false;
+            let mut pattern = None;
+            let mut i = 0;
+            while i < sub_args.len() {
+                let opt = String::from_utf8_lossy(&sub_args[i]).to_uppercase();
+                match opt.as_str() {
+                    "WITHCODE" => with_code = true,
+                    "LIBRARYNAME" => {
+                        i += 1;
+                        let Some(p) = sub_args.get(i) else {
+                            return Frame::error("ERR library name argument was not given");
+                        };
+                        pattern = Some(String::from_utf8_lossy(p).into_owned());
+                    }
+                    _ => {
+                        return Frame::error(format!(
+                            "ERR Unknown argument {}",
+                            String::from_utf8_lossy(&sub_args[i])
+                        ));
+                    }
+                }
+                i += 1;
+            }
+
+            let inner = state.lock();
+            let mut libs: Vec<&FunctionLibrary> = inner
+                .functions
+                .values()
+                .filter(|lib| match &pattern {
+                    Some(p) => crate::keys::glob_match(p, &lib.name),
+                    None => true,
+                })
+                .collect();
+            libs.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let frames = libs
+                .into_iter()
+                .map(|lib| {
+                    let functions = lib
+                        .functions
+                        .iter()
+                        .map(|f| {
+                            Frame::Map(vec![
+                                (Frame::bulk_string("name"), Frame::bulk_string(&f.name)),
+                                (
+                                    Frame::bulk_string("description"),
+                                    match &f.description {
+                                        Some(d) => Frame::bulk_string(d),
+                                        None => Frame::Null,
+                                    },
+                                ),
+                                (
+                                    Frame::bulk_string("flags"),
+                                    Frame::Set(
+                                        f.flags.iter().map(|fl| Frame::bulk_string(fl)).collect(),
+                                    ),
+                                ),
+                            ])
+                        })
+                        .collect();
+                    let mut fields = vec![
+                        (
+                            Frame::bulk_string("library_name"),
+                            Frame::bulk_string(&lib.name),
+                        ),
+                        (Frame::bulk_string("engine"), Frame::bulk_string("LUA")),
+                        (Frame::bulk_string("functions"), Frame::Array(functions)),
+                    ];
+                    if with_code {
+                        fields.push((
+                            Frame::bulk_string("library_code"),
+                            Frame::bulk_string(&lib.code),
+                        ));
+                    }
+                    Frame::Map(fields)
+                })
+                .collect();
+            Frame::Array(frames)
+        }
+        "DELETE" => {
+            if sub_args.len() != 1 {
+                return Frame::error(err_wrong_number("function|delete"));
+            }
+            let name = String::from_utf8_lossy(&sub_args[0]);
+            let mut inner = state.lock();
+            match inner.functions.remove(name.as_ref()) {
+                Some(_) => Frame::ok(),
+                None => Frame::error(MSG_LIBRARY_NOT_FOUND),
+            }
+        }
+        "DUMP" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number("function|dump"));
+            }
+            let inner = state.lock();
+            let mut libs: Vec<&FunctionLibrary> = inner.functions.values().collect();
+            libs.sort_by(|a, b| a.name.cmp(&b.name));
+            Frame::Bulk(dump_payload(&libs).into())
+        }
+        "RESTORE" => {
+            if sub_args.is_empty() || sub_args.len() > 2 {
+                return Frame::error(err_wrong_number("function|restore"));
+            }
+            let policy = match sub_args.get(1) {
+                Some(p) => String::from_utf8_lossy(p).to_uppercase(),
+                None => "APPEND".to_string(),
+            };
+            if !["FLUSH", "APPEND", "REPLACE"].contains(&policy.as_str()) {
+                return Frame::error(
+                    "ERR Wrong restore policy given, value should be either FLUSH, APPEND or REPLACE.",
+                );
+            }
+            let codes = match parse_payload(&sub_args[0]) {
+                Ok(codes) => codes,
+                Err(msg) => return Frame::error(msg),
+            };
+
+            // Load everything into a scratch registry first so that a failing
+            // library leaves the current one untouched.
+            let mut libraries = Vec::with_capacity(codes.len());
+            for code in codes {
+                match load_library(&code) {
+                    Ok((name, functions)) => libraries.push(FunctionLibrary {
+                        name,
+                        code,
+                        functions,
+                    }),
+                    Err(msg) => return Frame::error(msg),
+                }
+            }
+
+            let mut inner = state.lock();
+            let mut registry = if policy == "FLUSH" {
+                Default::default()
+            } else {
+                inner.functions.clone()
+            };
+            for lib in libraries {
+                if registry.contains_key(&lib.name) && policy != "REPLACE" {
+                    return Frame::error(format!("ERR Library {} already exists", lib.name));
+                }
+                registry.remove(&lib.name);
+                for other in registry.values() {
+                    if let Some(f) = other
+                        .functions
+                        .iter()
+                        .find(|f| lib.functions.iter().any(|n| n.name == f.name))
+                    {
+                        return Frame::error(format!("ERR Function {} already exists", f.name));
+                    }
+                }
+                registry.insert(lib.name.clone(), lib);
+            }
+            inner.functions = registry;
+            Frame::ok()
+        }
+        "FLUSH" => {
+            if sub_args.len() > 1 {
+                return Frame::error(err_wrong_number("function|flush"));
+            }
+            if let Some(opt) = sub_args.first() {
+                let opt = String::from_utf8_lossy(opt).to_uppercase();
+                if opt != "SYNC" && opt != "ASYNC" {
+                    return Frame::error("ERR FUNCTION FLUSH only supports SYNC|ASYNC option");
+                }
+            }
+            let mut inner = state.lock();
+            inner.functions.clear();
+            Frame::ok()
+        }
+        "STATS" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number("function|stats"));
+            }
+            let inner = state.lock();
+            let libraries = inner.functions.len() as i64;
+            let functions = inner
+                .functions
+                .values()
+                .map(|lib| lib.functions.len() as i64)
+                .sum();
+            Frame::Map(vec![
+                (
+                    Frame::bulk_string("running_script"),
+                    crate::script::running_function(state),
+                ),
+                (
+                    Frame::bulk_string("engines"),
+                    Frame::Map(vec![(
+                        Frame::bulk_string("LUA"),
+                        Frame::Map(vec![
+                            (
+                                Frame::bulk_string("libraries_count"),
+                                Frame::Integer(libraries),
+                            ),
+                            (
+                                Frame::bulk_string("functions_count"),
+                                Frame::Integer(functions),
+                            ),
+                        ]),
+                    )]),
+                ),
+            ])
+        }
+        "KILL" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number("function|kill"));
+            }
+            crate::script::kill(state, true)
+        }
+        _ => Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try FUNCTION HELP.",
+            subcmd.to_lowercase()
+        )),
+    }
+}
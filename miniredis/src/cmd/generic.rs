@@ -3,15 +3,18 @@ use std::time::Duration;
 
 use rand::Rng;
 
-use super::parse_int;
+use super::{parse_float, parse_int};
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{RedisDB, SharedState};
 use crate::dispatch::{
     CommandTable, MSG_DB_INDEX_OUT_OF_RANGE, MSG_INVALID_CURSOR, MSG_INVALID_INT,
-    MSG_KEY_NOT_FOUND, MSG_SYNTAX_ERROR, MSG_TIMEOUT_NEGATIVE, err_wrong_number,
+    MSG_KEY_NOT_FOUND, MSG_SORT_SCORE, MSG_SYNTAX_ERROR, MSG_TIMEOUT_NEGATIVE, MSG_WRONG_TYPE,
+    err_wrong_number,
 };
 use crate::frame::Frame;
-use crate::types::KeyType;
+use crate::pubsub::{NOTIFY_GENERIC, NOTIFY_LIST};
+use crate::rdb;
+use crate::types::{Direction, KeyType};
 
 pub fn register(table: &mut CommandTable) {
     table.add("DEL", cmd_del, false, -2);
@@ -39,18 +42,20 @@ pub fn register(table: &mut CommandTable) {
     table.add("MOVE", cmd_move, false, 3);
     table.add("DUMP", cmd_dump, true, 2);
     table.add("RESTORE", cmd_restore, false, -4);
+    table.add("SORT", cmd_sort, false, -2);
+    table.add("SORT_RO", cmd_sort_ro, true, -2);
 }
 
 /// DEL key [key ...]
 fn cmd_del(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
     let mut count = 0i64;
 
     for arg in args {
-        let key = String::from_utf8_lossy(arg);
-        db.check_ttl(&key);
-        if db.del(&key) {
+        let key = arg;
+        db.check_ttl(key);
+        if db.del(key) {
+            db.notify(NOTIFY_GENERIC, "del", key);
             count += 1;
         }
     }
@@ -60,15 +65,14 @@ fn cmd_del(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
 
 /// EXISTS key [key ...]
 fn cmd_exists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     let mut count = 0i64;
 
     for arg in args {
-        let key = String::from_utf8_lossy(arg);
-        db.check_ttl(&key);
-        if db.exists(&key, now) {
+        let key = arg;
+        db.check_ttl(key);
+        if db.exists(key, now) {
             count += 1;
         }
     }
@@ -78,12 +82,11 @@ fn cmd_exists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// TYPE key
 fn cmd_type(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    let t = match db.key_type(&key) {
+    let t = match db.key_type(key) {
         Some(t) => t.as_str(),
         None => "none",
     };
@@ -92,29 +95,29 @@ fn cmd_type(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// RENAME key newkey
 fn cmd_rename(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let from = String::from_utf8_lossy(&args[0]).into_owned();
-    let to = String::from_utf8_lossy(&args[1]).into_owned();
+    let from = args[0].clone();
+    let to = args[1].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if !db.keys.contains_key(&from) {
         return Frame::error(MSG_KEY_NOT_FOUND);
     }
 
     db.rename(&from, &to, now);
+    db.notify(NOTIFY_GENERIC, "rename_from", &from);
+    db.notify(NOTIFY_GENERIC, "rename_to", &to);
     Frame::ok()
 }
 
 /// RENAMENX key newkey
 fn cmd_renamenx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let from = String::from_utf8_lossy(&args[0]).into_owned();
-    let to = String::from_utf8_lossy(&args[1]).into_owned();
+    let from = args[0].clone();
+    let to = args[1].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if !db.keys.contains_key(&from) {
         return Frame::error(MSG_KEY_NOT_FOUND);
@@ -125,6 +128,8 @@ fn cmd_renamenx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
     }
 
     db.rename(&from, &to, now);
+    db.notify(NOTIFY_GENERIC, "rename_from", &from);
+    db.notify(NOTIFY_GENERIC, "rename_to", &to);
     Frame::Integer(1)
 }
 
@@ -178,7 +183,7 @@ fn expire_impl(
     args: &[Vec<u8>],
     to_duration: impl Fn(i64, std::time::SystemTime) -> Duration,
 ) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let value: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -209,9 +214,8 @@ fn expire_impl(
         return Frame::error("ERR GT and LT options at the same time are not compatible");
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -251,21 +255,25 @@ fn expire_impl(
         }
     }
 
+    // A TTL in the past deletes the key right away.
+    if new_ttl.is_zero() {
+        db.del(&key);
+        db.notify(NOTIFY_GENERIC, "del", &key);
+        return Frame::Integer(1);
+    }
+
     db.ttl.insert(key.clone(), new_ttl);
     db.incr_version(&key, now);
-
-    // Check if key already expired
-    db.check_ttl(&key);
+    db.notify(NOTIFY_GENERIC, "expire", &key);
 
     Frame::Integer(1)
 }
 
 /// PERSIST key
 fn cmd_persist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if !db.keys.contains_key(&key) {
         return Frame::Integer(0);
@@ -273,6 +281,7 @@ fn cmd_persist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
     if db.ttl.remove(&key).is_some() {
         db.incr_version(&key, now);
+        db.notify(NOTIFY_GENERIC, "persist", &key);
         Frame::Integer(1)
     } else {
         Frame::Integer(0)
@@ -281,16 +290,15 @@ fn cmd_persist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// TTL key
 fn cmd_ttl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(-2);
     }
 
-    match db.ttl.get(key.as_ref()) {
+    match db.ttl.get(key.as_slice()) {
         Some(ttl) => Frame::Integer(ttl.as_secs() as i64),
         None => Frame::Integer(-1),
     }
@@ -298,16 +306,15 @@ fn cmd_ttl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
 
 /// PTTL key
 fn cmd_pttl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(-2);
     }
 
-    match db.ttl.get(key.as_ref()) {
+    match db.ttl.get(key.as_slice()) {
         Some(ttl) => Frame::Integer(ttl.as_millis() as i64),
         None => Frame::Integer(-1),
     }
@@ -315,12 +322,11 @@ fn cmd_pttl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// KEYS pattern
 fn cmd_keys(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let pattern = String::from_utf8_lossy(&args[0]);
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let pattern = &args[0];
+    let db = state.db(ctx.selected_db);
 
     let all_keys = db.all_keys();
-    let matched = match_keys(&all_keys, &pattern);
+    let matched = match_keys(&all_keys, pattern);
 
     Frame::Array(matched.into_iter().map(|k| Frame::Bulk(k.into())).collect())
 }
@@ -337,8 +343,7 @@ fn cmd_scan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         Err(e) => return e,
     };
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     let mut all_keys = db.all_keys();
 
@@ -371,13 +376,12 @@ fn cmd_scan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// TOUCH key [key ...]
 fn cmd_touch(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
     let mut count = 0i64;
 
     for arg in args {
-        let key = String::from_utf8_lossy(arg);
-        if db.keys.contains_key(key.as_ref()) {
+        let key = arg;
+        if db.keys.contains_key(key.as_slice()) {
             count += 1;
         }
     }
@@ -385,40 +389,49 @@ fn cmd_touch(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     Frame::Integer(count)
 }
 
-/// WAIT numreplicas timeout — always returns 0 (standalone)
-fn cmd_wait(_state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let _replicas: i64 = match parse_int(&args[0]) {
+/// WAIT numreplicas timeout
+///
+/// This doesn't block: it's what WAIT does inside MULTI or a script. The
+/// connection loop handles WAIT from clients.
+fn cmd_wait(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if let Err(err) = parse_wait(state, args) {
+        return err;
+    }
+    Frame::Integer(crate::replication::acked_replicas(state, ctx.repl_offset))
+}
+
+/// Parse the numreplicas and timeout (ms) of WAIT.
+pub fn parse_wait(state: &SharedState, args: &[Vec<u8>]) -> Result<(i64, i64), Frame> {
+    let replicas: i64 = match parse_int(&args[0]) {
         Some(n) if n >= 0 => n,
-        _ => return Frame::error(MSG_INVALID_INT),
+        _ => return Err(Frame::error(MSG_INVALID_INT)),
     };
     let timeout: i64 = match parse_int(&args[1]) {
         Some(n) => n,
-        None => return Frame::error(MSG_INVALID_INT),
+        None => return Err(Frame::error(MSG_INVALID_INT)),
     };
     if timeout < 0 {
-        return Frame::error(MSG_TIMEOUT_NEGATIVE);
+        return Err(Frame::error(MSG_TIMEOUT_NEGATIVE));
     }
-
-    Frame::Integer(0)
+    if state.replication.lock().unwrap().is_replica() {
+        return Err(Frame::error(
+            "ERR WAIT cannot be used with replica instances. Please also note that since Redis 4.0 if a replica is configured to be writable (which is not the default) writes to replicas are just local and are not propagated.",
+        ));
+    }
+    Ok((replicas, timeout))
 }
 
 /// RANDOMKEY
 fn cmd_randomkey(state: &Arc<SharedState>, ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
-    let mut inner = state.lock();
-    let key_count = inner.db(ctx.selected_db).keys.len();
+    let db = state.db(ctx.selected_db);
+    let key_count = db.keys.len();
 
     if key_count == 0 {
         return Frame::Null;
     }
 
-    let idx = inner.rng.random_range(0..key_count);
-    let key = inner
-        .db(ctx.selected_db)
-        .keys
-        .keys()
-        .nth(idx)
-        .unwrap()
-        .clone();
+    let idx = state.lock().rng.random_range(0..key_count);
+    let key = db.keys.keys().nth(idx).unwrap().clone();
     Frame::Bulk(key.into())
 }
 
@@ -431,11 +444,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error(err_wrong_number("object|encoding"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let mut inner = state.lock();
-            let db = inner.db_mut(ctx.selected_db);
-            db.check_ttl(&key);
-            if !db.keys.contains_key(key.as_ref()) {
+            let key = &args[1];
+            let mut db = state.db(ctx.selected_db);
+            db.check_ttl(key);
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::error(MSG_KEY_NOT_FOUND);
             }
             // Stub: always return "raw"
@@ -445,11 +457,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error(err_wrong_number("object|idletime"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let mut inner = state.lock();
-            let db = inner.db_mut(ctx.selected_db);
-            db.check_ttl(&key);
-            if !db.keys.contains_key(key.as_ref()) {
+            let key = &args[1];
+            let mut db = state.db(ctx.selected_db);
+            db.check_ttl(key);
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::error(MSG_KEY_NOT_FOUND);
             }
             Frame::Integer(0)
@@ -458,11 +469,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error(err_wrong_number("object|refcount"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let mut inner = state.lock();
-            let db = inner.db_mut(ctx.selected_db);
-            db.check_ttl(&key);
-            if !db.keys.contains_key(key.as_ref()) {
+            let key = &args[1];
+            let mut db = state.db(ctx.selected_db);
+            db.check_ttl(key);
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::error(MSG_KEY_NOT_FOUND);
             }
             Frame::Integer(1)
@@ -471,11 +481,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error(err_wrong_number("object|freq"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let mut inner = state.lock();
-            let db = inner.db_mut(ctx.selected_db);
-            db.check_ttl(&key);
-            if !db.keys.contains_key(key.as_ref()) {
+            let key = &args[1];
+            let mut db = state.db(ctx.selected_db);
+            db.check_ttl(key);
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::error(MSG_KEY_NOT_FOUND);
             }
             Frame::Integer(0)
@@ -489,17 +498,16 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// EXPIRETIME key
 fn cmd_expiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(-2);
     }
 
-    match db.ttl.get(key.as_ref()) {
+    match db.ttl.get(key.as_slice()) {
         Some(ttl) => {
             let expire_at = now + *ttl;
             let secs = expire_at
@@ -514,17 +522,16 @@ fn cmd_expiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
 
 /// PEXPIRETIME key
 fn cmd_pexpiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(-2);
     }
 
-    match db.ttl.get(key.as_ref()) {
+    match db.ttl.get(key.as_slice()) {
         Some(ttl) => {
             let expire_at = now + *ttl;
             let ms = expire_at
@@ -539,8 +546,8 @@ fn cmd_pexpiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
 
 /// COPY source destination [DB db] [REPLACE]
 fn cmd_copy(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
+    let src = args[0].clone();
+    let dst = args[1].clone();
     let mut dest_db = ctx.selected_db;
     let mut replace = false;
 
@@ -565,120 +572,136 @@ fn cmd_copy(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         i += 1;
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
 
     // COPY to self on same DB: error (checked before key existence)
     if ctx.selected_db == dest_db && src == dst {
         return Frame::error("ERR source and destination objects are the same");
     }
 
-    // Check source exists
-    {
-        let src_db = inner.db_mut(ctx.selected_db);
-        src_db.check_ttl(&src);
-        if !src_db.keys.contains_key(&src) {
+    if ctx.selected_db == dest_db {
+        let mut db = state.db(dest_db);
+        db.check_ttl(&src);
+        if !db.keys.contains_key(&src) {
             return Frame::Integer(0);
         }
-    }
-
-    // Check destination
-    {
-        let dst_db = inner.db_mut(dest_db);
-        dst_db.check_ttl(&dst);
-        if dst_db.keys.contains_key(&dst) && !replace {
+        db.check_ttl(&dst);
+        if db.keys.contains_key(&dst) && !replace {
             return Frame::Integer(0);
         }
         if replace {
-            dst_db.del(&dst);
+            db.del(&dst);
         }
+        db.copy_key(&src, &dst, now);
+        db.notify(NOTIFY_GENERIC, "copy_to", &dst);
+        return Frame::Integer(1);
     }
+    let (mut src_db, mut dst_db) = state.db_pair(ctx.selected_db, dest_db);
 
-    if ctx.selected_db == dest_db {
-        // Same DB: use copy_key
-        let db = inner.db_mut(ctx.selected_db);
-        db.copy_key(&src, &dst, now);
-    } else {
-        // Cross-DB copy: manually clone data
-        let key_type = *inner.db(ctx.selected_db).keys.get(&src).unwrap();
-        let ttl = inner.db(ctx.selected_db).ttl.get(&src).copied();
-
-        match key_type {
-            KeyType::String => {
-                let val = inner.db(ctx.selected_db).string_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner.db_mut(dest_db).string_set(&dst, v, now);
-                }
+    // Check source exists
+    src_db.check_ttl(&src);
+    if !src_db.keys.contains_key(&src) {
+        return Frame::Integer(0);
+    }
+
+    // Check destination
+    dst_db.check_ttl(&dst);
+    if dst_db.keys.contains_key(&dst) && !replace {
+        return Frame::Integer(0);
+    }
+    if replace {
+        dst_db.del(&dst);
+    }
+
+    // Cross-DB copy: manually clone data
+    let key_type = *src_db.keys.get(&src).unwrap();
+    let ttl = src_db.ttl.get(&src).copied();
+
+    match key_type {
+        KeyType::String => {
+            let val = src_db.string_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.string_set(&dst, v, now);
             }
-            KeyType::Hash => {
-                let val = inner.db(ctx.selected_db).hash_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner
-                        .db_mut(dest_db)
-                        .keys
-                        .insert(dst.clone(), KeyType::Hash);
-                    inner.db_mut(dest_db).hash_keys.insert(dst.clone(), v);
-                    inner.db_mut(dest_db).incr_version(&dst, now);
-                }
+        }
+        KeyType::Hash => {
+            let val = src_db.hash_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, KeyType::Hash);
+                dst_db.hash_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
             }
-            KeyType::List => {
-                let val = inner.db(ctx.selected_db).list_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner
-                        .db_mut(dest_db)
-                        .keys
-                        .insert(dst.clone(), KeyType::List);
-                    inner.db_mut(dest_db).list_keys.insert(dst.clone(), v);
-                    inner.db_mut(dest_db).incr_version(&dst, now);
-                }
+        }
+        KeyType::List => {
+            let val = src_db.list_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, KeyType::List);
+                dst_db.list_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
             }
-            KeyType::Set => {
-                let val = inner.db(ctx.selected_db).set_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner.db_mut(dest_db).set_set(&dst, v, now);
-                }
+        }
+        KeyType::Set => {
+            let val = src_db.set_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.set_set(&dst, v, now);
             }
-            KeyType::SortedSet => {
-                let val = inner.db(ctx.selected_db).sorted_set_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner.db_mut(dest_db).sset_set(&dst, v, now);
-                }
+        }
+        KeyType::SortedSet => {
+            let val = src_db.sorted_set_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.sset_set(&dst, v, now);
             }
-            KeyType::Stream => {
-                let val = inner.db(ctx.selected_db).stream_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner
-                        .db_mut(dest_db)
-                        .keys
-                        .insert(dst.clone(), KeyType::Stream);
-                    inner.db_mut(dest_db).stream_keys.insert(dst.clone(), v);
-                    inner.db_mut(dest_db).incr_version(&dst, now);
-                }
+        }
+        KeyType::Stream => {
+            let val = src_db.stream_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, KeyType::Stream);
+                dst_db.stream_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
             }
-            KeyType::HyperLogLog => {
-                let val = inner.db(ctx.selected_db).hll_keys.get(&src).cloned();
-                if let Some(v) = val {
-                    inner
-                        .db_mut(dest_db)
-                        .keys
-                        .insert(dst.clone(), KeyType::HyperLogLog);
-                    inner.db_mut(dest_db).hll_keys.insert(dst.clone(), v);
-                    inner.db_mut(dest_db).incr_version(&dst, now);
-                }
+        }
+        KeyType::HyperLogLog => {
+            let val = src_db.hll_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, KeyType::HyperLogLog);
+                dst_db.hll_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
             }
         }
-
-        if let Some(ttl) = ttl {
-            inner.db_mut(dest_db).ttl.insert(dst, ttl);
+        #[cfg(feature = "json")]
+        KeyType::Json => {
+            let val = src_db.json_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, KeyType::Json);
+                dst_db.json_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
+            }
+        }
+        #[cfg(feature = "bloom")]
+        KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+            let val = src_db.bloom_keys.get(&src).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&dst, v.key_type());
+                dst_db.bloom_keys.insert(dst.clone(), v);
+                dst_db.incr_version(&dst, now);
+            }
         }
     }
 
+    if let Some(ttl) = ttl {
+        dst_db.ttl.insert(dst.clone(), ttl);
+    }
+    if let Some(field_ttls) = src_db.hash_field_ttls.get(&src).cloned() {
+        dst_db.hash_field_ttls.insert(dst.clone(), field_ttls);
+    }
+
+    dst_db.notify(NOTIFY_GENERIC, "copy_to", &dst);
     Frame::Integer(1)
 }
 
 /// MOVE key db
 fn cmd_move(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let target_db = match parse_int(&args[1]) {
         Some(n) if (0..16).contains(&n) => n as usize,
         _ => return Frame::error(MSG_DB_INDEX_OUT_OF_RANGE),
@@ -688,129 +711,137 @@ fn cmd_move(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error("ERR source and destination objects are the same");
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
+    let (mut src_db, mut dst_db) = state.db_pair(ctx.selected_db, target_db);
 
     // Check source exists
-    {
-        let src_db = inner.db_mut(ctx.selected_db);
-        src_db.check_ttl(&key);
-        if !src_db.keys.contains_key(&key) {
-            return Frame::Integer(0);
-        }
+    src_db.check_ttl(&key);
+    if !src_db.keys.contains_key(&key) {
+        return Frame::Integer(0);
     }
 
     // Check target doesn't have the key
-    {
-        let dst_db = inner.db_mut(target_db);
-        dst_db.check_ttl(&key);
-        if dst_db.keys.contains_key(&key) {
-            return Frame::Integer(0);
-        }
+    dst_db.check_ttl(&key);
+    if dst_db.keys.contains_key(&key) {
+        return Frame::Integer(0);
     }
 
     // Copy to target, then delete from source
-    let key_type = *inner.db(ctx.selected_db).keys.get(&key).unwrap();
-    let ttl = inner.db(ctx.selected_db).ttl.get(&key).copied();
+    let key_type = *src_db.keys.get(&key).unwrap();
+    let ttl = src_db.ttl.get(&key).copied();
 
     match key_type {
         KeyType::String => {
-            let val = inner.db(ctx.selected_db).string_keys.get(&key).cloned();
+            let val = src_db.string_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner.db_mut(target_db).string_set(&key, v, now);
+                dst_db.string_set(&key, v, now);
             }
         }
         KeyType::Hash => {
-            let val = inner.db(ctx.selected_db).hash_keys.get(&key).cloned();
+            let val = src_db.hash_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner
-                    .db_mut(target_db)
-                    .keys
-                    .insert(key.clone(), KeyType::Hash);
-                inner.db_mut(target_db).hash_keys.insert(key.clone(), v);
-                inner.db_mut(target_db).incr_version(&key, now);
+                dst_db.add_key(&key, KeyType::Hash);
+                dst_db.hash_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
             }
         }
         KeyType::List => {
-            let val = inner.db(ctx.selected_db).list_keys.get(&key).cloned();
+            let val = src_db.list_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner
-                    .db_mut(target_db)
-                    .keys
-                    .insert(key.clone(), KeyType::List);
-                inner.db_mut(target_db).list_keys.insert(key.clone(), v);
-                inner.db_mut(target_db).incr_version(&key, now);
+                dst_db.add_key(&key, KeyType::List);
+                dst_db.list_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
             }
         }
         KeyType::Set => {
-            let val = inner.db(ctx.selected_db).set_keys.get(&key).cloned();
+            let val = src_db.set_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner.db_mut(target_db).set_set(&key, v, now);
+                dst_db.set_set(&key, v, now);
             }
         }
         KeyType::SortedSet => {
-            let val = inner.db(ctx.selected_db).sorted_set_keys.get(&key).cloned();
+            let val = src_db.sorted_set_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner.db_mut(target_db).sset_set(&key, v, now);
+                dst_db.sset_set(&key, v, now);
             }
         }
         KeyType::Stream => {
-            let val = inner.db(ctx.selected_db).stream_keys.get(&key).cloned();
+            let val = src_db.stream_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner
-                    .db_mut(target_db)
-                    .keys
-                    .insert(key.clone(), KeyType::Stream);
-                inner.db_mut(target_db).stream_keys.insert(key.clone(), v);
-                inner.db_mut(target_db).incr_version(&key, now);
+                dst_db.add_key(&key, KeyType::Stream);
+                dst_db.stream_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
             }
         }
         KeyType::HyperLogLog => {
-            let val = inner.db(ctx.selected_db).hll_keys.get(&key).cloned();
+            let val = src_db.hll_keys.get(&key).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&key, KeyType::HyperLogLog);
+                dst_db.hll_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
+            }
+        }
+        #[cfg(feature = "json")]
+        KeyType::Json => {
+            let val = src_db.json_keys.get(&key).cloned();
+            if let Some(v) = val {
+                dst_db.add_key(&key, KeyType::Json);
+                dst_db.json_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
+            }
+        }
+        #[cfg(feature = "bloom")]
+        KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+            let val = src_db.bloom_keys.get(&key).cloned();
             if let Some(v) = val {
-                inner
-                    .db_mut(target_db)
-                    .keys
-                    .insert(key.clone(), KeyType::HyperLogLog);
-                inner.db_mut(target_db).hll_keys.insert(key.clone(), v);
-                inner.db_mut(target_db).incr_version(&key, now);
+                dst_db.add_key(&key, v.key_type());
+                dst_db.bloom_keys.insert(key.clone(), v);
+                dst_db.incr_version(&key, now);
             }
         }
     }
 
     if let Some(ttl) = ttl {
-        inner.db_mut(target_db).ttl.insert(key.clone(), ttl);
+        dst_db.ttl.insert(key.clone(), ttl);
+    }
+    if let Some(field_ttls) = src_db.hash_field_ttls.get(&key).cloned() {
+        dst_db.hash_field_ttls.insert(key.clone(), field_ttls);
     }
 
     // Delete from source
-    inner.db_mut(ctx.selected_db).del(&key);
+    src_db.del(&key);
+    src_db.notify(NOTIFY_GENERIC, "move_from", &key);
+    dst_db.notify(NOTIFY_GENERIC, "move_to", &key);
     Frame::Integer(1)
 }
 
-/// DUMP key — stub: returns raw string value or null
+/// DUMP key — strings dump as their raw value, everything else as a
+/// Redis compatible payload
 fn cmd_dump(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Null;
     }
 
-    // Stub: only dump string values
-    match db.key_type(&key) {
-        Some(KeyType::String) => match db.string_get(&key) {
+    match db.key_type(key) {
+        Some(KeyType::String) => match db.string_get(key) {
             Some(val) => Frame::Bulk(val.clone().into()),
             None => Frame::Null,
         },
-        _ => Frame::Null,
+        _ => match rdb::dump_value(&db, key, now) {
+            Some(payload) => Frame::Bulk(payload.into()),
+            None => Frame::Null,
+        },
     }
 }
 
 /// RESTORE key ttl serialized-value [REPLACE]
 fn cmd_restore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let ttl_ms: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -825,33 +856,259 @@ fn cmd_restore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         }
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
-    if db.keys.contains_key(&key) {
-        if !replace {
-            return Frame::error("BUSYKEY Target key name already exists.");
+    if db.keys.contains_key(&key) && !replace {
+        return Frame::error("BUSYKEY Target key name already exists.");
+    }
+
+    if rdb::is_dump_payload(&value) {
+        match rdb::restore_value(&mut db, &key, &value, now) {
+            Ok(true) => db.incr_version(&key, now),
+            Ok(false) => return Frame::ok(),
+            Err(_) => return Frame::error("ERR Bad data format"),
         }
+    } else {
+        // Anything else is taken to be a raw string, as DUMP returns them
         db.del(&key);
+        db.string_set(&key, value, now);
     }
 
-    // Stub: store as string value
-    db.string_set(&key, value, now);
-
     if ttl_ms > 0 {
-        db.ttl.insert(key, Duration::from_millis(ttl_ms as u64));
+        db.ttl
+            .insert(key.clone(), Duration::from_millis(ttl_ms as u64));
     }
+    db.notify(NOTIFY_GENERIC, "restore", &key);
 
     Frame::ok()
 }
 
+// ── SORT ─────────────────────────────────────────────────────────────
+
+/// SORT key [BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]]
+/// [ASC|DESC] [ALPHA] [STORE destination]
+fn cmd_sort(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    sort_impl(state, ctx, args, false)
+}
+
+/// SORT_RO key [BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]]
+/// [ASC|DESC] [ALPHA]
+fn cmd_sort_ro(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    sort_impl(state, ctx, args, true)
+}
+
+#[derive(Default)]
+struct SortOpts {
+    by: Option<Vec<u8>>,
+    offset: i64,
+    count: i64,
+    gets: Vec<Vec<u8>>,
+    desc: bool,
+    alpha: bool,
+    store: Option<Vec<u8>>,
+}
+
+fn parse_sort_opts(args: &[Vec<u8>], read_only: bool) -> Result<SortOpts, Frame> {
+    let mut opts = SortOpts {
+        count: -1,
+        ..Default::default()
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        let left = args.len() - i - 1;
+        match opt.as_str() {
+            "ASC" => opts.desc = false,
+            "DESC" => opts.desc = true,
+            "ALPHA" => opts.alpha = true,
+            "LIMIT" if left >= 2 => {
+                match (parse_int(&args[i + 1]), parse_int(&args[i + 2])) {
+                    (Some(offset), Some(count)) => {
+                        opts.offset = offset;
+                        opts.count = count;
+                    }
+                    _ => return Err(Frame::error(MSG_INVALID_INT)),
+                }
+                i += 2;
+            }
+            "BY" if left >= 1 => {
+                opts.by = Some(args[i + 1].clone());
+                i += 1;
+            }
+            "GET" if left >= 1 => {
+                opts.gets.push(args[i + 1].clone());
+                i += 1;
+            }
+            "STORE" if left >= 1 && !read_only => {
+                opts.store = Some(args[i + 1].clone());
+                i += 1;
+            }
+            _ => return Err(Frame::error(MSG_SYNTAX_ERROR)),
+        }
+        i += 1;
+    }
+    Ok(opts)
+}
+
+struct SortItem {
+    elem: Vec<u8>,
+    /// What ALPHA compares: the BY value, or the element itself.
+    by: Option<Vec<u8>>,
+    score: f64,
+}
+
+fn sort_impl(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+    read_only: bool,
+) -> Frame {
+    let key = &args[0];
+    let mut opts = match parse_sort_opts(&args[1..], read_only) {
+        Ok(o) => o,
+        Err(e) => return e,
+    };
+    // A BY pattern without a `*` can't point at a key per element, so
+    // there is nothing to sort by: keep the source order.
+    let mut dontsort = opts.by.as_ref().is_some_and(|p| !p.contains(&b'*'));
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+
+    let mut elems: Vec<Vec<u8>> = match db.key_type(key) {
+        None => Vec::new(),
+        Some(KeyType::List) => db.list_keys[key.as_slice()].iter().cloned().collect(),
+        Some(KeyType::Set) => {
+            // Sets have no order of their own. Like Redis, sort them
+            // anyway when storing, so the stored list is deterministic.
+            if dontsort && opts.store.is_some() {
+                dontsort = false;
+                opts.alpha = true;
+                opts.by = None;
+            }
+            db.set_keys[key.as_slice()].iter().cloned().collect()
+        }
+        Some(KeyType::SortedSet) => {
+            let dir = if dontsort && opts.desc {
+                Direction::Desc
+            } else {
+                Direction::Asc
+            };
+            db.sorted_set_keys[key.as_slice()]
+                .by_score(dir)
+                .into_iter()
+                .map(|e| e.member)
+                .collect()
+        }
+        Some(_) => return Frame::error(MSG_WRONG_TYPE),
+    };
+
+    if !dontsort {
+        let mut items = Vec::with_capacity(elems.len());
+        for elem in elems {
+            let by = match &opts.by {
+                Some(pattern) => sort_lookup(&mut db, pattern, &elem),
+                None => Some(elem.clone()),
+            };
+            let mut score = 0.0;
+            if !opts.alpha
+                && let Some(v) = &by
+            {
+                match parse_float(v) {
+                    Some(f) if !f.is_nan() => score = f,
+                    _ => return Frame::error(MSG_SORT_SCORE),
+                }
+            }
+            items.push(SortItem { elem, by, score });
+        }
+        items.sort_by(|a, b| {
+            let ord = if opts.alpha {
+                a.by.cmp(&b.by)
+            } else {
+                a.score
+                    .total_cmp(&b.score)
+                    .then_with(|| a.elem.cmp(&b.elem))
+            };
+            if opts.desc { ord.reverse() } else { ord }
+        });
+        elems = items.into_iter().map(|i| i.elem).collect();
+    }
+
+    let start = opts.offset.max(0) as usize;
+    let count = if opts.count < 0 {
+        usize::MAX
+    } else {
+        opts.count as usize
+    };
+    let mut values: Vec<Option<Vec<u8>>> = Vec::new();
+    for elem in elems.into_iter().skip(start).take(count) {
+        if opts.gets.is_empty() {
+            values.push(Some(elem));
+            continue;
+        }
+        for pattern in &opts.gets {
+            values.push(sort_lookup(&mut db, pattern, &elem));
+        }
+    }
+
+    let Some(dst) = opts.store else {
+        return Frame::Array(
+            values
+                .into_iter()
+                .map(|v| match v {
+                    Some(v) => Frame::Bulk(v.into()),
+                    None => Frame::Null,
+                })
+                .collect(),
+        );
+    };
+    db.check_ttl(&dst);
+    let existed = db.del(&dst);
+    if values.is_empty() {
+        if existed {
+            db.notify(NOTIFY_GENERIC, "del", &dst);
+        }
+        return Frame::Integer(0);
+    }
+    let values: Vec<Vec<u8>> = values.into_iter().map(Option::unwrap_or_default).collect();
+    let len = db.list_rpush(&dst, &values, now);
+    db.notify(NOTIFY_LIST, "sortstore", &dst);
+    Frame::Integer(len)
+}
+
+/// The value a BY or GET pattern points at for `elem`. `#` is the element
+/// itself. Otherwise the first `*` is replaced by the element to get a
+/// key name, and a `->field` suffix reads that hash field instead of a
+/// string value.
+fn sort_lookup(db: &mut RedisDB, pattern: &[u8], elem: &[u8]) -> Option<Vec<u8>> {
+    if pattern == b"#" {
+        return Some(elem.to_vec());
+    }
+    let star = pattern.iter().position(|&b| b == b'*')?;
+    let rest = &pattern[star + 1..];
+    let (suffix, field) = match rest.windows(2).position(|w| w == b"->") {
+        Some(i) if i + 2 < rest.len() => (&rest[..i], Some(&rest[i + 2..])),
+        _ => (rest, None),
+    };
+    let mut key = pattern[..star].to_vec();
+    key.extend_from_slice(elem);
+    key.extend_from_slice(suffix);
+
+    db.check_ttl(&key);
+    match field {
+        Some(field) => db.hash_get(&key, field).cloned(),
+        None => db.string_get(&key).cloned(),
+    }
+}
+
 // ── Pattern matching ─────────────────────────────────────────────────
 
 /// Match keys against a glob-style pattern (like Redis KEYS/SCAN).
-fn match_keys(keys: &[String], pattern: &str) -> Vec<String> {
-    if pattern == "*" {
+fn match_keys(keys: &[Vec<u8>], pattern: &[u8]) -> Vec<Vec<u8>> {
+    if pattern == b"*" {
         return keys.to_vec();
     }
 
@@ -3,11 +3,13 @@ use std::sync::Arc;
 use crate::connection::ConnCtx;
 use crate::db::SharedState;
 use crate::dispatch::{
-    CommandTable, MSG_INVALID_INT, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE, err_wrong_number,
+    CommandTable, MSG_INVALID_FLOAT, MSG_INVALID_INT, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE,
+    err_wrong_number,
 };
 use crate::frame::Frame;
-use crate::geo::{from_geohash, haversine_distance, parse_unit, to_geohash};
-use crate::types::{Direction, KeyType};
+use crate::geo::{box_distance, from_geohash, haversine_distance, parse_unit, to_geohash};
+use crate::pubsub::{NOTIFY_GENERIC, NOTIFY_ZSET};
+use crate::types::{Direction, KeyType, SortedSet};
 
 const MSG_UNSUPPORTED_UNIT: &str = "ERR unsupported unit provided. please use M, KM, FT, MI";
 
@@ -19,11 +21,13 @@ pub fn register(table: &mut CommandTable) {
     table.add("GEORADIUS_RO", cmd_georadius_ro, true, -6);
     table.add("GEORADIUSBYMEMBER", cmd_georadiusbymember, false, -5);
     table.add("GEORADIUSBYMEMBER_RO", cmd_georadiusbymember_ro, true, -5);
+    table.add("GEOSEARCH", cmd_geosearch, true, -7);
+    table.add("GEOSEARCHSTORE", cmd_geosearchstore, false, -8);
 }
 
 /// GEOADD key longitude latitude member [longitude latitude member ...]
 fn cmd_geoadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = to_str(&args[0]);
+    let key = args[0].clone();
     let triplets = &args[1..];
 
     if !triplets.len().is_multiple_of(3) {
@@ -35,7 +39,7 @@ fn cmd_geoadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     while i + 2 < triplets.len() {
         let raw_long = to_str(&triplets[i]);
         let raw_lat = to_str(&triplets[i + 1]);
-        let name = to_str(&triplets[i + 2]);
+        let name = triplets[i + 2].clone();
         i += 3;
 
         let longitude: f64 = match raw_long.parse() {
@@ -60,9 +64,8 @@ fn cmd_geoadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         entries.push((name, score));
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::SortedSet
@@ -76,15 +79,16 @@ fn cmd_geoadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             added += 1;
         }
     }
+    db.notify(NOTIFY_ZSET, "geoadd", &key);
 
     Frame::Integer(added)
 }
 
 /// GEODIST key member1 member2 [unit]
 fn cmd_geodist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = to_str(&args[0]);
-    let from = to_str(&args[1]);
-    let to = to_str(&args[2]);
+    let key = args[0].clone();
+    let from = args[1].clone();
+    let to = args[2].clone();
     let remaining = &args[3..];
 
     let unit = if !remaining.is_empty() {
@@ -102,8 +106,7 @@ fn cmd_geodist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         None => return Frame::error(MSG_UNSUPPORTED_UNIT),
     };
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     if !db.keys.contains_key(&key) {
         return Frame::Null;
@@ -130,11 +133,10 @@ fn cmd_geodist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// GEOPOS key member [member ...]
 fn cmd_geopos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = to_str(&args[0]);
+    let key = args[0].clone();
     let members = &args[1..];
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::SortedSet
@@ -144,8 +146,8 @@ fn cmd_geopos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
     let mut results = Vec::with_capacity(members.len());
     for member_arg in members {
-        let member = to_str(member_arg);
-        match db.sset_score(&key, &member) {
+        let member = member_arg;
+        match db.sset_score(&key, member) {
             Some(score) => {
                 let (lng, lat) = from_geohash(score as u64);
                 results.push(Frame::Array(vec![
@@ -165,7 +167,7 @@ fn cmd_geopos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 // ── Shared radius search types and helpers ──────────────────────────
 
 struct GeoMatch {
-    name: String,
+    name: Vec<u8>,
     score: f64,
     distance: f64,
     longitude: f64,
@@ -182,22 +184,30 @@ enum SortDir {
 struct RadiusOpts {
     with_dist: bool,
     with_coord: bool,
+    with_hash: bool,
     direction: SortDir,
     count: usize,
-    store_key: Option<String>,
-    storedist_key: Option<String>,
+    /// COUNT ANY: take the first `count` matches found, not the nearest.
+    any: bool,
+    store_key: Option<Vec<u8>>,
+    storedist_key: Option<Vec<u8>>,
+}
+
+/// The area to search, in meters.
+enum Shape {
+    Radius(f64),
+    Box { width: f64, height: f64 },
 }
 
-fn within_radius(
+fn within_shape(
     state: &Arc<SharedState>,
     db_idx: usize,
-    key: &str,
+    key: &[u8],
     longitude: f64,
     latitude: f64,
-    radius_meters: f64,
+    shape: &Shape,
 ) -> Vec<GeoMatch> {
-    let inner = state.lock();
-    let db = inner.db(db_idx);
+    let db = state.db(db_idx);
 
     let ss = match db.sorted_set_keys.get(key) {
         Some(ss) => ss,
@@ -208,8 +218,14 @@ fn within_radius(
     let mut matches = Vec::new();
     for el in &elems {
         let (el_lng, el_lat) = from_geohash(el.score as u64);
-        let d = haversine_distance(latitude, longitude, el_lat, el_lng);
-        if d <= radius_meters {
+        let distance = match *shape {
+            Shape::Radius(radius) => Some(haversine_distance(latitude, longitude, el_lat, el_lng))
+                .filter(|d| *d <= radius),
+            Shape::Box { width, height } => {
+                box_distance(latitude, longitude, width, height, el_lat, el_lng)
+            }
+        };
+        if let Some(d) = distance {
             matches.push(GeoMatch {
                 name: el.member.clone(),
                 score: el.score,
@@ -226,8 +242,10 @@ fn parse_radius_opts(args: &[Vec<u8>], read_only: bool) -> Result<RadiusOpts, Fr
     let mut opts = RadiusOpts {
         with_dist: false,
         with_coord: false,
+        with_hash: false,
         direction: SortDir::Unsorted,
         count: 0,
+        any: false,
         store_key: None,
         storedist_key: None,
     };
@@ -262,7 +280,7 @@ fn parse_radius_opts(args: &[Vec<u8>], read_only: bool) -> Result<RadiusOpts, Fr
                 if i >= args.len() {
                     return Err(Frame::error(MSG_SYNTAX_ERROR));
                 }
-                opts.store_key = Some(to_str(&args[i]));
+                opts.store_key = Some(args[i].clone());
             }
             "STOREDIST" => {
                 if read_only {
@@ -272,7 +290,7 @@ fn parse_radius_opts(args: &[Vec<u8>], read_only: bool) -> Result<RadiusOpts, Fr
                 if i >= args.len() {
                     return Err(Frame::error(MSG_SYNTAX_ERROR));
                 }
-                opts.storedist_key = Some(to_str(&args[i]));
+                opts.storedist_key = Some(args[i].clone());
             }
             _ => return Err(Frame::error(MSG_SYNTAX_ERROR)),
         }
@@ -285,14 +303,17 @@ fn parse_radius_opts(args: &[Vec<u8>], read_only: bool) -> Result<RadiusOpts, Fr
 fn format_radius_results(matches: &[GeoMatch], opts: &RadiusOpts, to_meter: f64) -> Frame {
     let mut frames = Vec::with_capacity(matches.len());
     for m in matches {
-        if !opts.with_dist && !opts.with_coord {
-            frames.push(Frame::bulk_string(&m.name));
+        if !opts.with_dist && !opts.with_coord && !opts.with_hash {
+            frames.push(Frame::Bulk(m.name.clone().into()));
         } else {
             let mut inner = Vec::new();
-            inner.push(Frame::bulk_string(&m.name));
+            inner.push(Frame::Bulk(m.name.clone().into()));
             if opts.with_dist {
                 inner.push(Frame::Bulk(format!("{:.4}", m.distance / to_meter).into()));
             }
+            if opts.with_hash {
+                inner.push(Frame::Integer(m.score as i64));
+            }
             if opts.with_coord {
                 inner.push(Frame::Array(vec![
                     Frame::Bulk(format!("{:.6}", m.longitude).into()),
@@ -306,6 +327,10 @@ fn format_radius_results(matches: &[GeoMatch], opts: &RadiusOpts, to_meter: f64)
 }
 
 fn apply_sort_and_count(matches: &mut Vec<GeoMatch>, opts: &RadiusOpts) {
+    // With ANY the first matches found are kept, and only those sorted.
+    if opts.any {
+        matches.truncate(opts.count);
+    }
     if opts.direction != SortDir::Unsorted {
         matches.sort_by(|a, b| {
             if opts.direction == SortDir::Desc {
@@ -324,6 +349,33 @@ fn apply_sort_and_count(matches: &mut Vec<GeoMatch>, opts: &RadiusOpts) {
     }
 }
 
+/// Replace `key` with a sorted set of the matches, scored by `score`.
+/// `event` is the keyspace event to fire.
+fn store_matches(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    key: &[u8],
+    matches: &[GeoMatch],
+    event: &'static str,
+    score: impl Fn(&GeoMatch) -> f64,
+) -> Frame {
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    if matches.is_empty() {
+        if db.del(key) {
+            db.notify(NOTIFY_GENERIC, "del", key);
+        }
+    } else {
+        let mut ss = SortedSet::new();
+        for m in matches {
+            ss.set(score(m), &m.name);
+        }
+        db.sset_set(key, ss, now);
+        db.notify(NOTIFY_ZSET, event, key);
+    }
+    Frame::Integer(matches.len() as i64)
+}
+
 // ── GEORADIUS / GEORADIUS_RO ────────────────────────────────────────
 
 fn cmd_georadius_impl(
@@ -333,7 +385,7 @@ fn cmd_georadius_impl(
     read_only: bool,
     cmd_name: &str,
 ) -> Frame {
-    let key = to_str(&args[0]);
+    let key = args[0].clone();
 
     let longitude: f64 = match to_str(&args[1]).parse() {
         Ok(v) => v,
@@ -366,38 +418,28 @@ fn cmd_georadius_impl(
         );
     }
 
-    let mut matches = within_radius(
+    let mut matches = within_shape(
         state,
         ctx.selected_db,
         &key,
         longitude,
         latitude,
-        radius * to_meter,
+        &Shape::Radius(radius * to_meter),
     );
     apply_sort_and_count(&mut matches, &opts);
 
     // Handle STORE
     if let Some(ref store_key) = opts.store_key {
-        let mut inner = state.lock();
-        let now = inner.effective_now();
-        let db = inner.db_mut(ctx.selected_db);
-        db.del(store_key);
-        for m in &matches {
-            db.sset_add(store_key, m.score, &m.name, now);
-        }
-        return Frame::Integer(matches.len() as i64);
+        return store_matches(state, ctx, store_key, &matches, "georadiusstore", |m| {
+            m.score
+        });
     }
 
     // Handle STOREDIST
     if let Some(ref storedist_key) = opts.storedist_key {
-        let mut inner = state.lock();
-        let now = inner.effective_now();
-        let db = inner.db_mut(ctx.selected_db);
-        db.del(storedist_key);
-        for m in &matches {
-            db.sset_add(storedist_key, m.distance / to_meter, &m.name, now);
-        }
-        return Frame::Integer(matches.len() as i64);
+        return store_matches(state, ctx, storedist_key, &matches, "georadiusstore", |m| {
+            m.distance / to_meter
+        });
     }
 
     format_radius_results(&matches, &opts, to_meter)
@@ -420,8 +462,8 @@ fn cmd_georadiusbymember_impl(
     read_only: bool,
     cmd_name: &str,
 ) -> Frame {
-    let key = to_str(&args[0]);
-    let member = to_str(&args[1]);
+    let key = args[0].clone();
+    let member = args[1].clone();
 
     let radius: f64 = match to_str(&args[2]).parse() {
         Ok(v) if v >= 0.0 => v,
@@ -448,8 +490,7 @@ fn cmd_georadiusbymember_impl(
 
     // Look up the member's coordinates
     {
-        let inner = state.lock();
-        let db = inner.db(ctx.selected_db);
+        let db = state.db(ctx.selected_db);
 
         if !db.keys.contains_key(&key) {
             return Frame::Null;
@@ -461,40 +502,35 @@ fn cmd_georadiusbymember_impl(
         match db.sset_score(&key, &member) {
             Some(score) => {
                 let (longitude, latitude) = from_geohash(score as u64);
-                drop(inner);
+                drop(db);
 
-                let mut matches = within_radius(
+                let mut matches = within_shape(
                     state,
                     ctx.selected_db,
                     &key,
                     longitude,
                     latitude,
-                    radius * to_meter,
+                    &Shape::Radius(radius * to_meter),
                 );
                 apply_sort_and_count(&mut matches, &opts);
 
                 // Handle STORE
                 if let Some(ref store_key) = opts.store_key {
-                    let mut inner = state.lock();
-                    let now = inner.effective_now();
-                    let db = inner.db_mut(ctx.selected_db);
-                    db.del(store_key);
-                    for m in &matches {
-                        db.sset_add(store_key, m.score, &m.name, now);
-                    }
-                    return Frame::Integer(matches.len() as i64);
+                    return store_matches(state, ctx, store_key, &matches, "georadiusstore", |m| {
+                        m.score
+                    });
                 }
 
                 // Handle STOREDIST
                 if let Some(ref storedist_key) = opts.storedist_key {
-                    let mut inner = state.lock();
-                    let now = inner.effective_now();
-                    let db = inner.db_mut(ctx.selected_db);
-                    db.del(storedist_key);
-                    for m in &matches {
-                        db.sset_add(storedist_key, m.distance / to_meter, &m.name, now);
-                    }
-                    return Frame::Integer(matches.len() as i64);
+                    return store_matches(
+                        state,
+                        ctx,
+                        storedist_key,
+                        &matches,
+                        "georadiusstore",
+                        |m| m.distance / to_meter,
+                    );
                 }
 
                 format_radius_results(&matches, &opts, to_meter)
@@ -516,6 +552,243 @@ fn cmd_georadiusbymember_ro(
     cmd_georadiusbymember_impl(state, ctx, args, true, "georadiusbymember_ro")
 }
 
+// ── GEOSEARCH / GEOSEARCHSTORE ──────────────────────────────────────
+
+enum Origin {
+    Member(Vec<u8>),
+    LonLat(f64, f64),
+}
+
+struct SearchOpts {
+    origin: Origin,
+    shape: Shape,
+    to_meter: f64,
+    opts: RadiusOpts,
+    store_dist: bool,
+}
+
+/// Parse `FROMMEMBER|FROMLONLAT BYRADIUS|BYBOX [options]`. `store` is
+/// set for GEOSEARCHSTORE, which takes STOREDIST but no WITH* options.
+fn parse_search_opts(args: &[Vec<u8>], store: bool, cmd_name: &str) -> Result<SearchOpts, Frame> {
+    let float_arg = |i: usize, msg: &str| -> Result<f64, Frame> {
+        let arg = args.get(i).ok_or_else(|| Frame::error(MSG_SYNTAX_ERROR))?;
+        to_str(arg).parse::<f64>().map_err(|_| Frame::error(msg))
+    };
+    let unit_arg = |i: usize| -> Result<f64, Frame> {
+        let arg = args.get(i).ok_or_else(|| Frame::error(MSG_SYNTAX_ERROR))?;
+        parse_unit(&to_str(arg)).ok_or_else(|| Frame::error(MSG_UNSUPPORTED_UNIT))
+    };
+
+    let mut origin = None;
+    let mut shape = None;
+    let mut to_meter = 1.0;
+    let mut store_dist = false;
+    let mut opts = RadiusOpts {
+        with_dist: false,
+        with_coord: false,
+        with_hash: false,
+        direction: SortDir::Unsorted,
+        count: 0,
+        any: false,
+        store_key: None,
+        storedist_key: None,
+    };
+    let msg_from =
+        format!("ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {cmd_name}");
+    let msg_by = format!("ERR exactly one of BYRADIUS and BYBOX can be specified for {cmd_name}");
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = to_str(&args[i]).to_uppercase();
+        match arg.as_str() {
+            "FROMMEMBER" => {
+                if origin.is_some() {
+                    return Err(Frame::error(msg_from));
+                }
+                let member = args
+                    .get(i + 1)
+                    .ok_or_else(|| Frame::error(MSG_SYNTAX_ERROR))?;
+                origin = Some(Origin::Member(member.clone()));
+                i += 1;
+            }
+            "FROMLONLAT" => {
+                if origin.is_some() {
+                    return Err(Frame::error(msg_from));
+                }
+                let longitude = float_arg(i + 1, MSG_INVALID_FLOAT)?;
+                let latitude = float_arg(i + 2, MSG_INVALID_FLOAT)?;
+                if !(-85.05112878..=85.05112878).contains(&latitude)
+                    || !(-180.0..=180.0).contains(&longitude)
+                {
+                    return Err(Frame::error(format!(
+                        "ERR invalid longitude,latitude pair {:.6},{:.6}",
+                        longitude, latitude
+                    )));
+                }
+                origin = Some(Origin::LonLat(longitude, latitude));
+                i += 2;
+            }
+            "BYRADIUS" => {
+                if shape.is_some() {
+                    return Err(Frame::error(msg_by));
+                }
+                let radius = float_arg(i + 1, "ERR need numeric radius")?;
+                if radius < 0.0 {
+                    return Err(Frame::error("ERR radius cannot be negative"));
+                }
+                to_meter = unit_arg(i + 2)?;
+                shape = Some(Shape::Radius(radius * to_meter));
+                i += 2;
+            }
+            "BYBOX" => {
+                if shape.is_some() {
+                    return Err(Frame::error(msg_by));
+                }
+                let width = float_arg(i + 1, "ERR need numeric width")?;
+                let height = float_arg(i + 2, "ERR need numeric height")?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(Frame::error("ERR height or width cannot be negative"));
+                }
+                to_meter = unit_arg(i + 3)?;
+                shape = Some(Shape::Box {
+                    width: width * to_meter,
+                    height: height * to_meter,
+                });
+                i += 3;
+            }
+            "ASC" => opts.direction = SortDir::Asc,
+            "DESC" => opts.direction = SortDir::Desc,
+            "COUNT" => {
+                let n = args
+                    .get(i + 1)
+                    .ok_or_else(|| Frame::error(MSG_SYNTAX_ERROR))?;
+                let n: i64 = to_str(n)
+                    .parse()
+                    .map_err(|_| Frame::error(MSG_INVALID_INT))?;
+                if n <= 0 {
+                    return Err(Frame::error("ERR COUNT must be > 0"));
+                }
+                opts.count = n as usize;
+                i += 1;
+                if args
+                    .get(i + 1)
+                    .is_some_and(|a| a.eq_ignore_ascii_case(b"ANY"))
+                {
+                    opts.any = true;
+                    i += 1;
+                }
+            }
+            "WITHDIST" => opts.with_dist = true,
+            "WITHCOORD" => opts.with_coord = true,
+            "WITHHASH" => opts.with_hash = true,
+            "STOREDIST" if store => store_dist = true,
+            _ => return Err(Frame::error(MSG_SYNTAX_ERROR)),
+        }
+        i += 1;
+    }
+
+    if store && (opts.with_dist || opts.with_coord || opts.with_hash) {
+        return Err(Frame::error(format!(
+            "ERR STORE option in {cmd_name} is not compatible with WITHDIST, WITHHASH and WITHCOORDS options"
+        )));
+    }
+    let Some(origin) = origin else {
+        return Err(Frame::error(msg_from));
+    };
+    let Some(shape) = shape else {
+        return Err(Frame::error(msg_by));
+    };
+    // Without ANY, COUNT returns the nearest matches.
+    if opts.count > 0 && !opts.any && opts.direction == SortDir::Unsorted {
+        opts.direction = SortDir::Asc;
+    }
+
+    Ok(SearchOpts {
+        origin,
+        shape,
+        to_meter,
+        opts,
+        store_dist,
+    })
+}
+
+/// GEOSEARCH key FROMMEMBER member|FROMLONLAT longitude latitude
+/// BYRADIUS radius unit|BYBOX width height unit [ASC|DESC]
+/// [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]
+fn cmd_geosearch(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    geosearch_impl(state, ctx, &args[0], &args[1..], None)
+}
+
+/// GEOSEARCHSTORE destination source <GEOSEARCH options> [STOREDIST]
+fn cmd_geosearchstore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    geosearch_impl(state, ctx, &args[1], &args[2..], Some(&args[0]))
+}
+
+fn geosearch_impl(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    key: &[u8],
+    args: &[Vec<u8>],
+    store_key: Option<&[u8]>,
+) -> Frame {
+    let cmd_name = if store_key.is_some() {
+        "GEOSEARCHSTORE"
+    } else {
+        "GEOSEARCH"
+    };
+
+    if state
+        .db(ctx.selected_db)
+        .keys
+        .get(key)
+        .is_some_and(|t| *t != KeyType::SortedSet)
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
+    let search = match parse_search_opts(args, store_key.is_some(), cmd_name) {
+        Ok(s) => s,
+        Err(e) => return e,
+    };
+
+    let (longitude, latitude) = {
+        let db = state.db(ctx.selected_db);
+        if !db.keys.contains_key(key) {
+            drop(db);
+            return match store_key {
+                Some(dst) => store_matches(state, ctx, dst, &[], "geosearchstore", |m| m.score),
+                None => Frame::Array(vec![]),
+            };
+        }
+        match &search.origin {
+            Origin::LonLat(longitude, latitude) => (*longitude, *latitude),
+            Origin::Member(member) => match db.sset_score(key, member) {
+                Some(score) => from_geohash(score as u64),
+                None => return Frame::error("ERR could not decode requested zset member"),
+            },
+        }
+    };
+
+    let mut matches = within_shape(
+        state,
+        ctx.selected_db,
+        key,
+        longitude,
+        latitude,
+        &search.shape,
+    );
+    apply_sort_and_count(&mut matches, &search.opts);
+
+    match store_key {
+        Some(dst) if search.store_dist => {
+            store_matches(state, ctx, dst, &matches, "geosearchstore", |m| {
+                m.distance / search.to_meter
+            })
+        }
+        Some(dst) => store_matches(state, ctx, dst, &matches, "geosearchstore", |m| m.score),
+        None => format_radius_results(&matches, &search.opts, search.to_meter),
+    }
+}
+
 // ── Helpers ─────────────────────────────────────────────────────────
 
 fn to_str(bytes: &[u8]) -> String {
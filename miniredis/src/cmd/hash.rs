@@ -1,16 +1,18 @@
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use rand::Rng;
 use rand::seq::SliceRandom;
 
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{RedisDB, SharedState};
 use crate::dispatch::{
-    CommandTable, MSG_GT_AND_LT, MSG_INT_OVERFLOW, MSG_INVALID_CURSOR, MSG_INVALID_FLOAT,
-    MSG_INVALID_INT, MSG_NUM_FIELDS_INVALID, MSG_NUM_FIELDS_PARAMETER, MSG_NX_AND_XX_GT_LT,
-    MSG_SYNTAX_ERROR, MSG_WRONG_TYPE, err_wrong_number,
+    CommandTable, MSG_FIELDS_MISSING, MSG_GT_AND_LT, MSG_INT_OVERFLOW, MSG_INVALID_CURSOR,
+    MSG_INVALID_FLOAT, MSG_INVALID_INT, MSG_NUM_FIELDS_INVALID, MSG_NUM_FIELDS_PARAMETER,
+    MSG_NX_AND_XX_GT_LT, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE, err_wrong_number,
 };
 use crate::frame::Frame;
+use crate::pubsub::NOTIFY_HASH;
 use crate::types::KeyType;
 
 use super::parse_int;
@@ -33,6 +35,17 @@ pub fn register(table: &mut CommandTable) {
     table.add("HSCAN", cmd_hscan, true, -3);
     table.add("HRANDFIELD", cmd_hrandfield, true, -2);
     table.add("HEXPIRE", cmd_hexpire, false, -6);
+    table.add("HPEXPIRE", cmd_hpexpire, false, -6);
+    table.add("HEXPIREAT", cmd_hexpireat, false, -6);
+    table.add("HPEXPIREAT", cmd_hpexpireat, false, -6);
+    table.add("HTTL", cmd_httl, true, -5);
+    table.add("HPTTL", cmd_hpttl, true, -5);
+    table.add("HEXPIRETIME", cmd_hexpiretime, true, -5);
+    table.add("HPEXPIRETIME", cmd_hpexpiretime, true, -5);
+    table.add("HPERSIST", cmd_hpersist, false, -5);
+    table.add("HGETDEL", cmd_hgetdel, false, -5);
+    table.add("HGETEX", cmd_hgetex, false, -5);
+    table.add("HSETEX", cmd_hsetex, false, -6);
 }
 
 /// HSET key field value [field value ...]
@@ -41,10 +54,9 @@ fn cmd_hset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(err_wrong_number("hset"));
     }
 
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -53,24 +65,24 @@ fn cmd_hset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let pairs: Vec<(String, Vec<u8>)> = args[1..]
+    let pairs: Vec<(Vec<u8>, Vec<u8>)> = args[1..]
         .chunks_exact(2)
-        .map(|c| (String::from_utf8_lossy(&c[0]).into_owned(), c[1].clone()))
+        .map(|c| (c[0].clone(), c[1].clone()))
         .collect();
 
     let added = db.hash_set(&key, &pairs, now);
+    db.notify(NOTIFY_HASH, "hset", &key);
     Frame::Integer(added)
 }
 
 /// HSETNX key field value
 fn cmd_hsetnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let field = String::from_utf8_lossy(&args[1]).into_owned();
+    let key = args[0].clone();
+    let field = args[1].clone();
     let value = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -87,6 +99,7 @@ fn cmd_hsetnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
 
     db.hash_set(&key, &[(field, value)], now);
+    db.notify(NOTIFY_HASH, "hset", &key);
     Frame::Integer(1)
 }
 
@@ -96,10 +109,9 @@ fn cmd_hmset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         return Frame::error(err_wrong_number("hmset"));
     }
 
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -108,31 +120,31 @@ fn cmd_hmset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let pairs: Vec<(String, Vec<u8>)> = args[1..]
+    let pairs: Vec<(Vec<u8>, Vec<u8>)> = args[1..]
         .chunks_exact(2)
-        .map(|c| (String::from_utf8_lossy(&c[0]).into_owned(), c[1].clone()))
+        .map(|c| (c[0].clone(), c[1].clone()))
         .collect();
 
     db.hash_set(&key, &pairs, now);
+    db.notify(NOTIFY_HASH, "hset", &key);
     Frame::ok()
 }
 
 /// HGET key field
 fn cmd_hget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let field = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let field = &args[1];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.hash_get(&key, &field) {
+    match db.hash_get(key, field) {
         Some(val) => Frame::Bulk(val.clone().into()),
         None => Frame::Null,
     }
@@ -140,12 +152,11 @@ fn cmd_hget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// HMGET key field [field ...]
 fn cmd_hmget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
@@ -153,8 +164,8 @@ fn cmd_hmget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
     let mut results = Vec::with_capacity(args.len() - 1);
     for arg in &args[1..] {
-        let field = String::from_utf8_lossy(arg);
-        match db.hash_get(&key, &field) {
+        let field = arg;
+        match db.hash_get(key, field) {
             Some(val) => results.push(Frame::Bulk(val.clone().into())),
             None => results.push(Frame::Null),
         }
@@ -165,10 +176,9 @@ fn cmd_hmget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
 /// HDEL key field [field ...]
 fn cmd_hdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -181,31 +191,31 @@ fn cmd_hdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::Integer(0);
     }
 
-    let fields: Vec<String> = args[1..]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let fields: Vec<Vec<u8>> = args[1..].to_vec();
 
     let count = db.hash_del(&key, &fields, now);
+    if count > 0 {
+        db.notify(NOTIFY_HASH, "hdel", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(count)
 }
 
 /// HEXISTS key field
 fn cmd_hexists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let field = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let field = &args[1];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.hash_get(&key, &field) {
+    match db.hash_get(key, field) {
         Some(_) => Frame::Integer(1),
         None => Frame::Integer(0),
     }
@@ -213,23 +223,22 @@ fn cmd_hexists(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// HGETALL key
 fn cmd_hgetall(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let fields = db.hash_fields(&key);
+    let fields = db.hash_fields(key);
 
     if ctx.resp3 {
         let mut pairs = Vec::with_capacity(fields.len());
         for field in &fields {
-            let val = db.hash_get(&key, field).cloned().unwrap_or_default();
+            let val = db.hash_get(key, field).cloned().unwrap_or_default();
             pairs.push((Frame::Bulk(field.clone().into()), Frame::Bulk(val.into())));
         }
         Frame::Map(pairs)
@@ -237,7 +246,7 @@ fn cmd_hgetall(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         let mut result = Vec::with_capacity(fields.len() * 2);
         for field in &fields {
             result.push(Frame::Bulk(field.clone().into()));
-            if let Some(val) = db.hash_get(&key, field) {
+            if let Some(val) = db.hash_get(key, field) {
                 result.push(Frame::Bulk(val.clone().into()));
             }
         }
@@ -247,67 +256,67 @@ fn cmd_hgetall(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// HKEYS key
 fn cmd_hkeys(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let fields = db.hash_fields(&key);
+    let fields = db.hash_fields(key);
     Frame::Array(fields.into_iter().map(|f| Frame::Bulk(f.into())).collect())
 }
 
 /// HVALS key
 fn cmd_hvals(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let values = db.hash_values(&key);
+    let values = db.hash_values(key);
     Frame::Array(values.into_iter().map(|v| Frame::Bulk(v.into())).collect())
 }
 
 /// HLEN key
 fn cmd_hlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let len = db.hash_keys.get(key.as_ref()).map(|h| h.len()).unwrap_or(0);
+    let len = db
+        .hash_keys
+        .get(key.as_slice())
+        .map(|h| h.len())
+        .unwrap_or(0);
     Frame::Integer(len as i64)
 }
 
 /// HINCRBY key field increment
 fn cmd_hincrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let field = String::from_utf8_lossy(&args[1]).into_owned();
+    let key = args[0].clone();
+    let field = args[1].clone();
     let delta: i64 = match String::from_utf8_lossy(&args[2]).parse() {
         Ok(n) => n,
         Err(_) => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -331,14 +340,15 @@ fn cmd_hincrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         }
     };
 
-    db.hash_set(&key, &[(field, new_val.to_string().into_bytes())], now);
+    db.hash_set_keep_ttl(&key, &[(field, new_val.to_string().into_bytes())], now);
+    db.notify(NOTIFY_HASH, "hincrby", &key);
     Frame::Integer(new_val)
 }
 
 /// HINCRBYFLOAT key field increment
 fn cmd_hincrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let field = String::from_utf8_lossy(&args[1]).into_owned();
+    let key = args[0].clone();
+    let field = args[1].clone();
     let delta_str = String::from_utf8_lossy(&args[2]).into_owned();
 
     // Validate by parsing as f64
@@ -350,9 +360,8 @@ fn cmd_hincrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>
         return Frame::error(MSG_INVALID_FLOAT);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -373,26 +382,26 @@ fn cmd_hincrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>
     };
 
     let formatted = crate::cmd::string::decimal_add_format(&current_str, &delta_str);
-    db.hash_set(&key, &[(field, formatted.as_bytes().to_vec())], now);
+    db.hash_set_keep_ttl(&key, &[(field, formatted.as_bytes().to_vec())], now);
+    db.notify(NOTIFY_HASH, "hincrbyfloat", &key);
     Frame::Bulk(formatted.into_bytes().into())
 }
 
 /// HSTRLEN key field
 fn cmd_hstrlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let field = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let field = &args[1];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.hash_get(&key, &field) {
+    match db.hash_get(key, field) {
         Some(val) => Frame::Integer(val.len() as i64),
         None => Frame::Integer(0),
     }
@@ -400,7 +409,7 @@ fn cmd_hstrlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// HSCAN key cursor [MATCH pattern] [COUNT count]
 fn cmd_hscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let _cursor: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_CURSOR),
@@ -411,16 +420,15 @@ fn cmd_hscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         Err(e) => return e,
     };
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let mut fields = db.hash_fields(&key);
+    let mut fields = db.hash_fields(key);
 
     if let Some(ref pat) = opts.pattern {
         fields = crate::keys::match_keys_vec(&fields, pat);
@@ -429,7 +437,7 @@ fn cmd_hscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     let mut result = Vec::new();
     for field in &fields {
         result.push(Frame::Bulk(field.clone().into()));
-        let val = db.hash_get(&key, field).cloned().unwrap_or_default();
+        let val = db.hash_get(key, field).cloned().unwrap_or_default();
         result.push(Frame::Bulk(val.into()));
     }
 
@@ -442,7 +450,7 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         return Frame::error(err_wrong_number("hrandfield"));
     }
 
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let mut count: i64 = 0;
     let mut with_count = false;
     let mut with_values = false;
@@ -465,11 +473,10 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return if with_count {
             Frame::Array(vec![])
         } else {
@@ -477,13 +484,13 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         };
     }
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let mut fields = db.hash_fields(&key);
+    let mut fields = db.hash_fields(key);
     if fields.is_empty() {
         return if with_count {
             Frame::Array(vec![])
@@ -492,17 +499,17 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         };
     }
 
-    // Collect values before shuffling (avoids borrow issues with inner.rng)
-    let field_values: std::collections::HashMap<String, Vec<u8>> = fields
+    // Collect values before shuffling
+    let field_values: std::collections::HashMap<Vec<u8>, Vec<u8>> = fields
         .iter()
-        .map(|f| (f.clone(), db.hash_get(&key, f).cloned().unwrap_or_default()))
+        .map(|f| (f.clone(), db.hash_get(key, f).cloned().unwrap_or_default()))
         .collect();
 
     if count < 0 {
         let abs_count = (-count) as usize;
         let mut result = Vec::new();
         for _ in 0..abs_count {
-            let idx = inner.rng.random_range(0..fields.len());
+            let idx = state.lock().rng.random_range(0..fields.len());
             result.push(Frame::Bulk(fields[idx].clone().into()));
             if with_values {
                 let val = field_values.get(&fields[idx]).cloned().unwrap_or_default();
@@ -512,7 +519,7 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         return Frame::Array(result);
     }
 
-    fields.shuffle(&mut inner.rng);
+    fields.shuffle(&mut state.lock().rng);
     let take = (count as usize).min(fields.len());
 
     if !with_count {
@@ -530,69 +537,65 @@ fn cmd_hrandfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
     Frame::Array(result)
 }
 
+// ── Field expiration ────────────────────────────────────────────────
+
 /// HEXPIRE key seconds [NX|XX|GT|LT] FIELDS numfields field [field ...]
 fn cmd_hexpire(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let ttl_secs: i64 = match parse_int(&args[1]) {
+    hexpire_impl(state, ctx, args, "EX")
+}
+
+/// HPEXPIRE key milliseconds [NX|XX|GT|LT] FIELDS numfields field [field ...]
+fn cmd_hpexpire(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    hexpire_impl(state, ctx, args, "PX")
+}
+
+/// HEXPIREAT key unix-time-seconds [NX|XX|GT|LT] FIELDS numfields field [field ...]
+fn cmd_hexpireat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    hexpire_impl(state, ctx, args, "EXAT")
+}
+
+/// HPEXPIREAT key unix-time-milliseconds [NX|XX|GT|LT] FIELDS numfields field [field ...]
+fn cmd_hpexpireat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    hexpire_impl(state, ctx, args, "PXAT")
+}
+
+/// Set the TTL of hash fields. Replies per field: -2 if there's no such
+/// field, 0 if the NX/XX/GT/LT condition isn't met, 1 if the TTL was set,
+/// and 2 if the field was deleted because the time is already past.
+fn hexpire_impl(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+    unit: &str,
+) -> Frame {
+    let key = args[0].clone();
+    let value: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
     };
+    if value < 0 {
+        return Frame::error("ERR invalid expire time, must be >= 0");
+    }
 
     let mut nx = false;
     let mut xx = false;
     let mut gt = false;
     let mut lt = false;
-    let mut fields: Vec<String> = Vec::new();
-
     let mut i = 2;
-    while i < args.len() {
-        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
-        match opt.as_str() {
-            "NX" => {
-                nx = true;
-                i += 1;
-            }
-            "XX" => {
-                xx = true;
-                i += 1;
-            }
-            "GT" => {
-                gt = true;
-                i += 1;
-            }
-            "LT" => {
-                lt = true;
-                i += 1;
-            }
-            "FIELDS" => {
-                i += 1;
-                if i >= args.len() {
-                    return Frame::error(MSG_NUM_FIELDS_INVALID);
-                }
-                let num_fields: i64 = match parse_int(&args[i]) {
-                    Some(n) => n,
-                    None => return Frame::error(MSG_NUM_FIELDS_INVALID),
-                };
-                if num_fields <= 0 {
-                    return Frame::error(MSG_NUM_FIELDS_INVALID);
-                }
-                i += 1;
-                let num_fields = num_fields as usize;
-                if i + num_fields > args.len() {
-                    return Frame::error(MSG_NUM_FIELDS_PARAMETER);
-                }
-                for j in 0..num_fields {
-                    fields.push(String::from_utf8_lossy(&args[i + j]).into_owned());
-                }
-                i += num_fields;
-            }
-            _ => {
-                return Frame::error(
-                    "ERR Mandatory argument FIELDS is missing or not at the right position",
-                );
-            }
+    while i < args.len() && !args[i].eq_ignore_ascii_case(b"FIELDS") {
+        match String::from_utf8_lossy(&args[i]).to_uppercase().as_str() {
+            "NX" => nx = true,
+            "XX" => xx = true,
+            "GT" => gt = true,
+            "LT" => lt = true,
+            _ => return Frame::error(MSG_FIELDS_MISSING),
         }
+        i += 1;
     }
+    let fields = match parse_fields(&args[i..], 1) {
+        Ok(fields) => fields,
+        Err(err) => return err,
+    };
 
     if gt && lt {
         return Frame::error(MSG_GT_AND_LT);
@@ -601,75 +604,415 @@ fn cmd_hexpire(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         return Frame::error(MSG_NX_AND_XX_GT_LT);
     }
 
-    if fields.is_empty() {
-        return Frame::error(
-            "ERR Mandatory argument FIELDS is missing or not at the right position",
-        );
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(&key);
+
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::Hash
+    {
+        return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let new_ttl = expiry_ttl(unit, value, now);
+    let mut updated = false;
+    let mut deleted = false;
+    let mut results = Vec::with_capacity(fields.len());
+    for field in &fields {
+        if db.hash_get(&key, field).is_none() {
+            results.push(Frame::Integer(-2));
+            continue;
+        }
 
-    // Key doesn't exist: return -2 for all fields
-    if !db.keys.contains_key(&key) {
-        return Frame::Array(fields.iter().map(|_| Frame::Integer(-2)).collect());
+        // A field without a TTL lives forever: GT never applies, LT
+        // always does.
+        let skip = match db.hash_field_ttl(&key, field) {
+            Some(ttl) => nx || (gt && new_ttl <= ttl) || (lt && new_ttl >= ttl),
+            None => xx || gt,
+        };
+        if skip {
+            results.push(Frame::Integer(0));
+            continue;
+        }
+
+        db.set_hash_field_ttl(&key, field, new_ttl, now);
+        if new_ttl.is_zero() {
+            deleted = true;
+            results.push(Frame::Integer(2));
+        } else {
+            updated = true;
+            results.push(Frame::Integer(1));
+        }
     }
 
-    if let Some(t) = db.key_type(&key)
+    if updated {
+        db.incr_version(&key, now);
+        db.notify(NOTIFY_HASH, "hexpire", &key);
+    }
+    if deleted {
+        db.notify(NOTIFY_HASH, "hexpired", &key);
+        db.notify_if_deleted(&key);
+    }
+    Frame::Array(results)
+}
+
+/// HTTL key FIELDS numfields field [field ...]
+fn cmd_httl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    field_ttl_impl(state, ctx, args, |ttl, _| ttl.as_secs() as i64)
+}
+
+/// HPTTL key FIELDS numfields field [field ...]
+fn cmd_hpttl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    field_ttl_impl(state, ctx, args, |ttl, _| ttl.as_millis() as i64)
+}
+
+/// HEXPIRETIME key FIELDS numfields field [field ...]
+fn cmd_hexpiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    field_ttl_impl(state, ctx, args, |ttl, now| {
+        (now + ttl)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+    })
+}
+
+/// HPEXPIRETIME key FIELDS numfields field [field ...]
+fn cmd_hpexpiretime(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    field_ttl_impl(state, ctx, args, |ttl, now| {
+        (now + ttl)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64
+    })
+}
+
+/// Reply per field with -2 if there's no such field, -1 if it has no
+/// TTL, and otherwise with `f` of its TTL.
+fn field_ttl_impl(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+    f: impl Fn(Duration, SystemTime) -> i64,
+) -> Frame {
+    let key = &args[0];
+    let fields = match parse_fields(&args[1..], 1) {
+        Ok(fields) => fields,
+        Err(err) => return err,
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Hash
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let new_ttl = std::time::Duration::from_secs(ttl_secs as u64);
+    let results = fields
+        .iter()
+        .map(|field| {
+            if db.hash_get(key, field).is_none() {
+                return Frame::Integer(-2);
+            }
+            match db.hash_field_ttl(key, field) {
+                Some(ttl) => Frame::Integer(f(ttl, now)),
+                None => Frame::Integer(-1),
+            }
+        })
+        .collect();
+    Frame::Array(results)
+}
 
-    let field_ttls = db.hash_field_ttls.entry(key.clone()).or_default();
+/// HPERSIST key FIELDS numfields field [field ...]
+fn cmd_hpersist(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = args[0].clone();
+    let fields = match parse_fields(&args[1..], 1) {
+        Ok(fields) => fields,
+        Err(err) => return err,
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(&key);
+
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::Hash
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
 
+    let mut persisted = false;
     let mut results = Vec::with_capacity(fields.len());
     for field in &fields {
-        // Check field exists in hash
-        let field_exists = db
-            .hash_keys
-            .get(&key)
-            .is_some_and(|h| h.contains_key(field));
-        if !field_exists {
+        if db.hash_get(&key, field).is_none() {
             results.push(Frame::Integer(-2));
-            continue;
+        } else if db.hash_persist_field(&key, field) {
+            persisted = true;
+            results.push(Frame::Integer(1));
+        } else {
+            results.push(Frame::Integer(-1));
         }
+    }
 
-        let current_ttl = field_ttls.get(field).copied();
-        let has_ttl = current_ttl.is_some();
+    if persisted {
+        db.incr_version(&key, now);
+        db.notify(NOTIFY_HASH, "hpersist", &key);
+    }
+    Frame::Array(results)
+}
 
-        // NX: set only when field has no expiration
-        if nx && has_ttl {
-            results.push(Frame::Integer(0));
-            continue;
+/// HGETDEL key FIELDS numfields field [field ...]
+fn cmd_hgetdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = args[0].clone();
+    let fields = match parse_fields(&args[1..], 1) {
+        Ok(fields) => fields,
+        Err(err) => return err,
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(&key);
+
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::Hash
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
+
+    let values = hash_values_of(&db, &key, &fields);
+    if db.hash_del(&key, &fields, now) > 0 {
+        db.notify(NOTIFY_HASH, "hdel", &key);
+        db.notify_if_deleted(&key);
+    }
+    Frame::Array(values)
+}
+
+/// What HGETEX and HSETEX do with the TTL of the fields.
+enum Expiry {
+    /// Leave it as it is.
+    Keep,
+    /// Remove it (HGETEX PERSIST).
+    Persist,
+    /// EX, PX, EXAT or PXAT, with its argument.
+    Set(String, i64),
+}
+
+/// Parse the EX/PX/EXAT/PXAT option at `args[*i]`, if there is one.
+fn parse_expiry(args: &[Vec<u8>], i: &mut usize, cmd: &str) -> Result<Option<Expiry>, Frame> {
+    let opt = String::from_utf8_lossy(&args[*i]).to_uppercase();
+    if !["EX", "PX", "EXAT", "PXAT"].contains(&opt.as_str()) {
+        return Ok(None);
+    }
+    let value = match args.get(*i + 1) {
+        Some(arg) => match parse_int(arg) {
+            Some(n) => n,
+            None => return Err(Frame::error(MSG_INVALID_INT)),
+        },
+        None => return Err(Frame::error(MSG_SYNTAX_ERROR)),
+    };
+    if value <= 0 {
+        return Err(Frame::error(format!(
+            "ERR invalid expire time in '{}' command",
+            cmd
+        )));
+    }
+    *i += 2;
+    Ok(Some(Expiry::Set(opt, value)))
+}
+
+/// HGETEX key [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+/// PXAT unix-time-milliseconds | PERSIST] FIELDS numfields field [field ...]
+fn cmd_hgetex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = args[0].clone();
+    let mut expiry = Expiry::Keep;
+    let mut i = 1;
+    while i < args.len() && !args[i].eq_ignore_ascii_case(b"FIELDS") {
+        let set = !matches!(expiry, Expiry::Keep);
+        match parse_expiry(args, &mut i, "hgetex") {
+            Ok(Some(_)) if set => return Frame::error(MSG_SYNTAX_ERROR),
+            Ok(Some(e)) => expiry = e,
+            Ok(None) if !set && args[i].eq_ignore_ascii_case(b"PERSIST") => {
+                expiry = Expiry::Persist;
+                i += 1;
+            }
+            Ok(None) => return Frame::error(MSG_SYNTAX_ERROR),
+            Err(err) => return err,
         }
+    }
+    let fields = match parse_fields(&args[i..], 1) {
+        Ok(fields) => fields,
+        Err(err) => return err,
+    };
 
-        // XX: set only when field has existing expiration
-        if xx && !has_ttl {
-            results.push(Frame::Integer(0));
-            continue;
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(&key);
+
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::Hash
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
+
+    let values = hash_values_of(&db, &key, &fields);
+    let existing: Vec<&Vec<u8>> = fields
+        .iter()
+        .filter(|field| db.hash_get(&key, field).is_some())
+        .collect();
+    match expiry {
+        Expiry::Keep => {}
+        Expiry::Persist => {
+            let mut persisted = false;
+            for field in existing {
+                persisted |= db.hash_persist_field(&key, field);
+            }
+            if persisted {
+                db.incr_version(&key, now);
+                db.notify(NOTIFY_HASH, "hpersist", &key);
+            }
         }
+        Expiry::Set(unit, value) if !existing.is_empty() => {
+            let ttl = expiry_ttl(&unit, value, now);
+            for field in existing {
+                db.set_hash_field_ttl(&key, field, ttl, now);
+            }
+            if ttl.is_zero() {
+                db.notify(NOTIFY_HASH, "hexpired", &key);
+                db.notify_if_deleted(&key);
+            } else {
+                db.incr_version(&key, now);
+                db.notify(NOTIFY_HASH, "hexpire", &key);
+            }
+        }
+        Expiry::Set(..) => {}
+    }
+    Frame::Array(values)
+}
 
-        // GT: set only when new TTL > current TTL
-        if gt && (!has_ttl || new_ttl <= current_ttl.unwrap()) {
-            results.push(Frame::Integer(0));
-            continue;
+/// HSETEX key [FNX | FXX] [EX seconds | PX milliseconds |
+/// EXAT unix-time-seconds | PXAT unix-time-milliseconds | KEEPTTL]
+/// FIELDS numfields field value [field value ...]
+fn cmd_hsetex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = args[0].clone();
+    let mut fnx = false;
+    let mut fxx = false;
+    let mut expiry: Option<Expiry> = None;
+    let mut i = 1;
+    while i < args.len() && !args[i].eq_ignore_ascii_case(b"FIELDS") {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        match opt.as_str() {
+            "FNX" | "FXX" if !fnx && !fxx => {
+                fnx = opt == "FNX";
+                fxx = opt == "FXX";
+                i += 1;
+            }
+            "KEEPTTL" if expiry.is_none() => {
+                expiry = Some(Expiry::Keep);
+                i += 1;
+            }
+            _ => match parse_expiry(args, &mut i, "hsetex") {
+                Ok(Some(e)) if expiry.is_none() => expiry = Some(e),
+                Ok(_) => return Frame::error(MSG_SYNTAX_ERROR),
+                Err(err) => return err,
+            },
         }
+    }
+    let flat = match parse_fields(&args[i..], 2) {
+        Ok(flat) => flat,
+        Err(err) => return err,
+    };
+    let pairs: Vec<(Vec<u8>, Vec<u8>)> = flat
+        .chunks_exact(2)
+        .map(|c| (c[0].clone(), c[1].clone()))
+        .collect();
 
-        // LT: set only when new TTL < current TTL (and field has expiration)
-        if lt && has_ttl && new_ttl >= current_ttl.unwrap() {
-            results.push(Frame::Integer(0));
-            continue;
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(&key);
+
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::Hash
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
+
+    // FNX: none of the fields may exist; FXX: all of them must.
+    let exists = |field: &Vec<u8>| db.hash_get(&key, field).is_some();
+    if (fnx && pairs.iter().any(|(f, _)| exists(f)))
+        || (fxx && !pairs.iter().all(|(f, _)| exists(f)))
+    {
+        return Frame::Integer(0);
+    }
+
+    match expiry {
+        Some(Expiry::Keep) => {
+            db.hash_set_keep_ttl(&key, &pairs, now);
+        }
+        _ => {
+            db.hash_set(&key, &pairs, now);
+        }
+    }
+    db.notify(NOTIFY_HASH, "hset", &key);
+
+    if let Some(Expiry::Set(unit, value)) = expiry {
+        let ttl = expiry_ttl(&unit, value, now);
+        for (field, _) in &pairs {
+            db.set_hash_field_ttl(&key, field, ttl, now);
+        }
+        if ttl.is_zero() {
+            db.notify(NOTIFY_HASH, "hexpired", &key);
+            db.notify_if_deleted(&key);
+        } else {
+            db.notify(NOTIFY_HASH, "hexpire", &key);
         }
+    }
+    Frame::Integer(1)
+}
 
-        field_ttls.insert(field.clone(), new_ttl);
-        results.push(Frame::Integer(1));
+/// Parse `FIELDS numfields field [field ...]`, which must be all of
+/// `args`. With `per_field` 2 every field is followed by its value.
+fn parse_fields(args: &[Vec<u8>], per_field: usize) -> Result<Vec<Vec<u8>>, Frame> {
+    if !args
+        .first()
+        .is_some_and(|a| a.eq_ignore_ascii_case(b"FIELDS"))
+    {
+        return Err(Frame::error(MSG_FIELDS_MISSING));
+    }
+    let num_fields = match args.get(1).and_then(|a| parse_int(a)) {
+        Some(n) if n > 0 => n as usize,
+        _ => return Err(Frame::error(MSG_NUM_FIELDS_INVALID)),
+    };
+    if num_fields.checked_mul(per_field) != Some(args.len() - 2) {
+        return Err(Frame::error(MSG_NUM_FIELDS_PARAMETER));
     }
+    Ok(args[2..].to_vec())
+}
 
-    db.incr_version(&key, now);
-    Frame::Array(results)
+/// The values of `fields`, nil for missing ones.
+fn hash_values_of(db: &RedisDB, key: &[u8], fields: &[Vec<u8>]) -> Vec<Frame> {
+    fields
+        .iter()
+        .map(|field| match db.hash_get(key, field) {
+            Some(val) => Frame::Bulk(val.clone().into()),
+            None => Frame::Null,
+        })
+        .collect()
+}
+
+/// The TTL an EX, PX, EXAT or PXAT `value` stands for; zero if that time
+/// has passed.
+fn expiry_ttl(unit: &str, value: i64, now: SystemTime) -> Duration {
+    let value = value as u64;
+    match unit {
+        "EX" => Duration::from_secs(value),
+        "PX" => Duration::from_millis(value),
+        "EXAT" => (UNIX_EPOCH + Duration::from_secs(value))
+            .duration_since(now)
+            .unwrap_or_default(),
+        _ => (UNIX_EPOCH + Duration::from_millis(value))
+            .duration_since(now)
+            .unwrap_or_default(),
+    }
 }
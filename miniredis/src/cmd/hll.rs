@@ -4,6 +4,7 @@ use crate::connection::ConnCtx;
 use crate::db::SharedState;
 use crate::dispatch::{CommandTable, MSG_NOT_VALID_HLL_VALUE, err_wrong_number};
 use crate::frame::Frame;
+use crate::pubsub::NOTIFY_STRING;
 use crate::types::KeyType;
 
 pub fn register(table: &mut CommandTable) {
@@ -18,15 +19,11 @@ fn cmd_pfadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         return Frame::error(err_wrong_number("pfadd"));
     }
 
-    let key = String::from_utf8_lossy(&args[0]).to_string();
-    let items: Vec<&str> = args[1..]
-        .iter()
-        .map(|a| std::str::from_utf8(a).unwrap_or(""))
-        .collect();
+    let key = args[0].clone();
+    let items: Vec<&[u8]> = args[1..].iter().map(|a| a.as_slice()).collect();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     // Check type if key already exists
     if let Some(kt) = db.keys.get(&key)
@@ -36,18 +33,17 @@ fn cmd_pfadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     }
 
     let altered = db.hll_add(&key, &items, now);
+    if altered > 0 {
+        db.notify(NOTIFY_STRING, "pfadd", &key);
+    }
     Frame::Integer(altered)
 }
 
 /// PFCOUNT key [key ...]
 fn cmd_pfcount(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let keys: Vec<&str> = args
-        .iter()
-        .map(|a| std::str::from_utf8(a).unwrap_or(""))
-        .collect();
+    let keys: Vec<&[u8]> = args.iter().map(|a| a.as_slice()).collect();
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     match db.hll_count(&keys) {
         Ok(count) => Frame::Integer(count),
@@ -57,17 +53,16 @@ fn cmd_pfcount(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// PFMERGE destkey [sourcekey ...]
 fn cmd_pfmerge(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let keys: Vec<&str> = args
-        .iter()
-        .map(|a| std::str::from_utf8(a).unwrap_or(""))
-        .collect();
+    let keys: Vec<&[u8]> = args.iter().map(|a| a.as_slice()).collect();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     match db.hll_merge(&keys, now) {
-        Ok(()) => Frame::ok(),
+        Ok(()) => {
+            db.notify(NOTIFY_STRING, "pfadd", keys[0]);
+            Frame::ok()
+        }
         Err(msg) => Frame::error(msg),
     }
 }
@@ -0,0 +1,445 @@
+use std::sync::Arc;
+
+use serde_json::{Number, Value};
+
+use crate::connection::ConnCtx;
+use crate::db::{RedisDB, SharedState};
+use crate::dispatch::{
+    CommandTable, MSG_INT_OVERFLOW, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE, err_wrong_number,
+};
+use crate::frame::Frame;
+use crate::json::{self, Format, Path};
+use crate::types::KeyType;
+
+pub fn register(table: &mut CommandTable) {
+    table.add("JSON.SET", cmd_json_set, false, -4);
+    table.add("JSON.GET", cmd_json_get, true, -2);
+    table.add("JSON.DEL", cmd_json_del, false, -2);
+    table.add("JSON.FORGET", cmd_json_del, false, -2);
+    table.add("JSON.MGET", cmd_json_mget, true, -3);
+    table.add("JSON.TYPE", cmd_json_type, true, -2);
+    table.add("JSON.ARRAPPEND", cmd_json_arrappend, false, -4);
+    table.add("JSON.ARRLEN", cmd_json_arrlen, true, -2);
+    table.add("JSON.NUMINCRBY", cmd_json_numincrby, false, 4);
+    table.add("JSON.STRAPPEND", cmd_json_strappend, false, -3);
+    table.add("JSON.OBJKEYS", cmd_json_objkeys, true, -2);
+}
+
+const MSG_NO_KEY: &str = "ERR could not perform this operation on a key that doesn't exist";
+
+/// The document at `key`, or WRONGTYPE if the key holds another type.
+fn json_doc<'a>(db: &'a RedisDB, key: &[u8]) -> Result<Option<&'a Value>, Frame> {
+    match db.key_type(key) {
+        None => Ok(None),
+        Some(KeyType::Json) => Ok(db.json_keys.get(key)),
+        Some(_) => Err(Frame::error(MSG_WRONG_TYPE)),
+    }
+}
+
+fn parse_path(arg: &[u8]) -> Result<Path, Frame> {
+    Path::parse(&String::from_utf8_lossy(arg)).map_err(Frame::error)
+}
+
+/// The path argument at `i`, or the legacy root when there isn't one.
+fn path_arg(args: &[Vec<u8>], i: usize) -> Result<Path, Frame> {
+    args.get(i).map_or(Ok(Path::root()), |arg| parse_path(arg))
+}
+
+fn parse_json(arg: &[u8]) -> Result<Value, Frame> {
+    serde_json::from_slice(arg).map_err(|e| Frame::error(format!("ERR {e}")))
+}
+
+fn wrong_type(expected: &str, found: &Value) -> Frame {
+    Frame::error(format!(
+        "WRONGTYPE wrong type of path value - expected {} but found {}",
+        expected,
+        json::type_name(found)
+    ))
+}
+
+/// JSON.SET key path value [NX | XX]
+fn cmd_json_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let path = match parse_path(&args[1]) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let value = match parse_json(&args[2]) {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let (nx, xx) = match args.get(3..).unwrap_or_default() {
+        [] => (false, false),
+        [opt] if opt.eq_ignore_ascii_case(b"NX") => (true, false),
+        [opt] if opt.eq_ignore_ascii_case(b"XX") => (false, true),
+        _ => return Frame::error(MSG_SYNTAX_ERROR),
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    if let Err(e) = json_doc(&db, key) {
+        return e;
+    }
+
+    let Some(doc) = db.json_keys.get_mut(key) else {
+        if !path.is_root() {
+            return Frame::error("ERR new objects must be created at the root");
+        }
+        if xx {
+            return Frame::Null;
+        }
+        db.add_key(key, KeyType::Json);
+        db.json_keys.insert(key.clone(), value);
+        db.incr_version(key, now);
+        return Frame::ok();
+    };
+    if !json::set(doc, &path, value, nx, xx) {
+        return Frame::Null;
+    }
+    db.incr_version(key, now);
+    Frame::ok()
+}
+
+/// JSON.GET key [INDENT indent] [NEWLINE newline] [SPACE space] [path ...]
+fn cmd_json_get(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let key = &args[0];
+    let mut format = Format::default();
+    let mut paths = Vec::new();
+    let mut i = 1;
+    while i < args.len() {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        let field = match opt.as_str() {
+            "INDENT" => Some(&mut format.indent),
+            "NEWLINE" => Some(&mut format.newline),
+            "SPACE" => Some(&mut format.space),
+            _ => None,
+        };
+        match (field, args.get(i + 1)) {
+            (Some(field), Some(value)) => {
+                *field = String::from_utf8_lossy(value).into_owned();
+                i += 1;
+            }
+            (Some(_), None) => return Frame::error(MSG_SYNTAX_ERROR),
+            (None, _) => match parse_path(&args[i]) {
+                Ok(p) => paths.push(p),
+                Err(e) => return e,
+            },
+        }
+        i += 1;
+    }
+
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    let doc = match json_doc(&db, key) {
+        Ok(Some(doc)) => doc,
+        Ok(None) => return Frame::Null,
+        Err(e) => return e,
+    };
+    let result = match paths.as_slice() {
+        [] => Ok(doc.clone()),
+        [path] => path.query(doc),
+        paths => json::query_all(doc, paths),
+    };
+    match result {
+        Ok(value) => Frame::bulk(format.format(&value)),
+        Err(e) => Frame::error(e),
+    }
+}
+
+/// JSON.DEL key [path]
+fn cmd_json_del(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len() > 2 {
+        return Frame::error(err_wrong_number("json.del"));
+    }
+    let key = &args[0];
+    let path = match path_arg(args, 1) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    if let Err(e) = json_doc(&db, key) {
+        return e;
+    }
+    if !db.json_keys.contains_key(key) {
+        return Frame::Integer(0);
+    }
+    if path.is_root() {
+        db.del(key);
+        return Frame::Integer(1);
+    }
+    let doc = db.json_keys.get_mut(key).unwrap();
+    let removed = json::delete(doc, &path);
+    if removed > 0 {
+        db.incr_version(key, now);
+    }
+    Frame::Integer(removed as i64)
+}
+
+/// JSON.MGET key [key ...] path
+fn cmd_json_mget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let (path, keys) = args.split_last().unwrap();
+    let path = match parse_path(path) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+
+    let mut db = state.db(ctx.selected_db);
+    let mut out = Vec::with_capacity(keys.len());
+    for key in keys {
+        db.check_ttl_read(key);
+        let value = match json_doc(&db, key) {
+            Ok(Some(doc)) => path.query(doc).ok(),
+            _ => None,
+        };
+        out.push(value.map_or(Frame::Null, |v| Frame::bulk(v.to_string())));
+    }
+    Frame::Array(out)
+}
+
+/// Reply for the values of a read-only command: an array with a reply
+/// per value for JSONPath, the reply for the value of a legacy path.
+/// `reply` returns None for a value of the wrong type, which is null for
+/// JSONPath and an error for a legacy path.
+fn inspect(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    args: &[Vec<u8>],
+    expected: &str,
+    reply: impl Fn(&Value) -> Option<Frame>,
+) -> Frame {
+    if args.len() > 2 {
+        return Frame::error(MSG_SYNTAX_ERROR);
+    }
+    let key = &args[0];
+    let path = match path_arg(args, 1) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
+    let doc = match json_doc(&db, key) {
+        Ok(Some(doc)) => doc,
+        Ok(None) => return Frame::Null,
+        Err(e) => return e,
+    };
+    let values = path.values(doc);
+    if !path.legacy {
+        return Frame::Array(
+            values
+                .into_iter()
+                .map(|v| reply(v).unwrap_or(Frame::Null))
+                .collect(),
+        );
+    }
+    match values.first() {
+        Some(v) => reply(v).unwrap_or_else(|| wrong_type(expected, v)),
+        None => Frame::error(path.missing()),
+    }
+}
+
+/// JSON.TYPE key [path]
+fn cmd_json_type(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    inspect(state, ctx, args, "", |v| {
+        Some(Frame::bulk_string(json::type_name(v)))
+    })
+}
+
+/// JSON.ARRLEN key [path]
+fn cmd_json_arrlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    inspect(state, ctx, args, "array", |v| {
+        Some(Frame::Integer(v.as_array()?.len() as i64))
+    })
+}
+
+/// JSON.OBJKEYS key [path]
+fn cmd_json_objkeys(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    inspect(state, ctx, args, "object", |v| {
+        Some(Frame::Array(
+            v.as_object()?
+                .keys()
+                .map(|k| Frame::bulk_string(k))
+                .collect(),
+        ))
+    })
+}
+
+/// Apply `update` to the values `path` selects in the document at `key`,
+/// for the commands that modify values of one type. Returns a result per
+/// value, None for values of the wrong type. With a legacy path, it's an
+/// error when the path selects nothing or a value of the wrong type, and
+/// nothing is modified then.
+fn update(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    key: &[u8],
+    path: &Path,
+    expected: &str,
+    fits: fn(&Value) -> bool,
+    mut update: impl FnMut(&mut Value) -> Result<Value, Frame>,
+) -> Result<Vec<Option<Value>>, Frame> {
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
+    json_doc(&db, key)?;
+    let Some(doc) = db.json_keys.get_mut(key) else {
+        return Err(Frame::error(MSG_NO_KEY));
+    };
+    let found = path.find(doc);
+    if path.legacy {
+        if found.is_empty() {
+            return Err(Frame::error(path.missing()));
+        }
+        for loc in &found {
+            if let Some(v) = json::get(doc, loc).filter(|v| !fits(v)) {
+                return Err(wrong_type(expected, v));
+            }
+        }
+    }
+    let mut results = Vec::with_capacity(found.len());
+    for loc in &found {
+        results.push(match json::get_mut(doc, loc) {
+            Some(v) if fits(v) => Some(update(v)?),
+            _ => None,
+        });
+    }
+    if results.iter().any(Option::is_some) {
+        db.incr_version(key, now);
+    }
+    Ok(results)
+}
+
+/// Reply with integer results: an array for JSONPath, the last result for
+/// a legacy path.
+fn integer_results(path: &Path, results: Vec<Option<Value>>) -> Frame {
+    let mut frames: Vec<Frame> = results
+        .into_iter()
+        .map(|r| {
+            r.and_then(|v| v.as_i64())
+                .map_or(Frame::Null, Frame::Integer)
+        })
+        .collect();
+    if path.legacy {
+        return frames.pop().unwrap_or(Frame::Null);
+    }
+    Frame::Array(frames)
+}
+
+/// JSON.ARRAPPEND key path value [value ...]
+fn cmd_json_arrappend(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let path = match parse_path(&args[1]) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let values = match args[2..]
+        .iter()
+        .map(|a| parse_json(a))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(v) => v,
+        Err(e) => return e,
+    };
+    let results = update(state, ctx, &args[0], &path, "array", Value::is_array, |v| {
+        let arr = v.as_array_mut().unwrap();
+        arr.extend(values.iter().cloned());
+        Ok(Value::from(arr.len()))
+    });
+    match results {
+        Ok(results) => integer_results(&path, results),
+        Err(e) => e,
+    }
+}
+
+/// JSON.STRAPPEND key [path] value
+fn cmd_json_strappend(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.len() > 3 {
+        return Frame::error(err_wrong_number("json.strappend"));
+    }
+    let path = match args.len() {
+        3 => parse_path(&args[1]),
+        _ => Ok(Path::root()),
+    };
+    let path = match path {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let suffix = match parse_json(args.last().unwrap()) {
+        Ok(Value::String(s)) => s,
+        Ok(v) => return wrong_type("string", &v),
+        Err(e) => return e,
+    };
+    let results = update(
+        state,
+        ctx,
+        &args[0],
+        &path,
+        "string",
+        Value::is_string,
+        |v| {
+            let Value::String(s) = v else { unreachable!() };
+            s.push_str(&suffix);
+            Ok(Value::from(s.len()))
+        },
+    );
+    match results {
+        Ok(results) => integer_results(&path, results),
+        Err(e) => e,
+    }
+}
+
+/// JSON.NUMINCRBY key path value
+fn cmd_json_numincrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let path = match parse_path(&args[1]) {
+        Ok(p) => p,
+        Err(e) => return e,
+    };
+    let by = match parse_json(&args[2]) {
+        Ok(Value::Number(n)) => n,
+        Ok(v) => return wrong_type("number", &v),
+        Err(e) => return e,
+    };
+    let results = update(
+        state,
+        ctx,
+        &args[0],
+        &path,
+        "number",
+        Value::is_number,
+        |v| {
+            let Value::Number(n) = v else { unreachable!() };
+            *n = add_numbers(n, &by)?;
+            Ok(v.clone())
+        },
+    );
+    match results {
+        Ok(results) if path.legacy => Frame::bulk(
+            results
+                .last()
+                .cloned()
+                .flatten()
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        Ok(results) => Frame::bulk(
+            Value::Array(results.into_iter().map(Option::unwrap_or_default).collect()).to_string(),
+        ),
+        Err(e) => e,
+    }
+}
+
+/// Integer addition when both numbers are integers, floating point
+/// otherwise.
+fn add_numbers(a: &Number, b: &Number) -> Result<Number, Frame> {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a
+            .checked_add(b)
+            .map(Number::from)
+            .ok_or_else(|| Frame::error(MSG_INT_OVERFLOW));
+    }
+    let sum = a.as_f64().unwrap_or(0.0) + b.as_f64().unwrap_or(0.0);
+    Number::from_f64(sum).ok_or_else(|| Frame::error("ERR result is not a number"))
+}
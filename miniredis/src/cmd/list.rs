@@ -1,14 +1,16 @@
 use std::sync::Arc;
 
-use super::parse_int;
+use std::time::SystemTime;
+
+use super::{parse_int, parse_mpop, parse_timeout};
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{RedisDB, SharedState};
 use crate::dispatch::{
-    CommandTable, MSG_INVALID_INT, MSG_INVALID_TIMEOUT, MSG_KEY_NOT_FOUND, MSG_OUT_OF_RANGE,
-    MSG_SYNTAX_ERROR, MSG_TIMEOUT_IS_OUT_OF_RANGE, MSG_TIMEOUT_NEGATIVE, MSG_WRONG_TYPE,
-    err_wrong_number,
+    CommandTable, MSG_INVALID_INT, MSG_KEY_NOT_FOUND, MSG_OUT_OF_RANGE, MSG_SYNTAX_ERROR,
+    MSG_WRONG_TYPE, err_wrong_number,
 };
 use crate::frame::Frame;
+use crate::pubsub::NOTIFY_LIST;
 use crate::types::KeyType;
 
 pub fn register(table: &mut CommandTable) {
@@ -28,11 +30,13 @@ pub fn register(table: &mut CommandTable) {
     table.add("RPOPLPUSH", cmd_rpoplpush, false, 3);
     table.add("LMOVE", cmd_lmove, false, 5);
     table.add("LPOS", cmd_lpos, true, -3);
+    table.add("LMPOP", cmd_lmpop, false, -4);
     // Blocking commands: registered for MULTI/EXEC queueing (non-blocking attempt)
     table.add("BLPOP", cmd_blpop, false, -3);
     table.add("BRPOP", cmd_brpop, false, -3);
     table.add("BRPOPLPUSH", cmd_brpoplpush, false, 4);
     table.add("BLMOVE", cmd_blmove, false, 6);
+    table.add("BLMPOP", cmd_blmpop, false, -5);
 }
 
 /// LPUSH key element [element ...]
@@ -62,10 +66,9 @@ fn cmd_xpush(
     left: bool,
     only_existing: bool,
 ) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -85,6 +88,7 @@ fn cmd_xpush(
     } else {
         db.list_rpush(&key, &values, now)
     };
+    db.notify(NOTIFY_LIST, if left { "lpush" } else { "rpush" }, &key);
 
     Frame::Integer(len)
 }
@@ -106,7 +110,7 @@ fn cmd_xpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], left:
         return Frame::error(err_wrong_number(cmd_name));
     }
 
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let count = if args.len() > 1 {
         match parse_int(&args[1]) {
             Some(n) if n < 0 => return Frame::error(MSG_OUT_OF_RANGE),
@@ -117,9 +121,8 @@ fn cmd_xpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], left:
         None
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -136,7 +139,7 @@ fn cmd_xpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], left:
         };
     }
 
-    match count {
+    let response = match count {
         Some(n) => {
             let mut results = Vec::new();
             for _ in 0..n {
@@ -150,6 +153,10 @@ fn cmd_xpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], left:
                     None => break,
                 }
             }
+            // COUNT 0 pops nothing and emits no event.
+            if results.is_empty() {
+                return Frame::Array(results);
+            }
             Frame::Array(results)
         }
         None => {
@@ -160,32 +167,38 @@ fn cmd_xpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], left:
             };
             match val {
                 Some(v) => Frame::Bulk(v.into()),
-                None => Frame::Null,
+                None => return Frame::Null,
             }
         }
-    }
+    };
+    db.notify(NOTIFY_LIST, cmd_name, &key);
+    db.notify_if_deleted(&key);
+    response
 }
 
 /// LLEN key
 fn cmd_llen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::List
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let len = db.list_keys.get(key.as_ref()).map(|l| l.len()).unwrap_or(0);
+    let len = db
+        .list_keys
+        .get(key.as_slice())
+        .map(|l| l.len())
+        .unwrap_or(0);
     Frame::Integer(len as i64)
 }
 
 /// LINDEX key index
 fn cmd_lindex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     // Reject "-0" (Go miniredis compat)
     if args[1] == b"-0" {
         return Frame::error(MSG_INVALID_INT);
@@ -195,17 +208,16 @@ fn cmd_lindex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         None => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::List
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let list = match db.list_keys.get(key.as_ref()) {
+    let list = match db.list_keys.get(key.as_slice()) {
         Some(l) => l,
         None => return Frame::Null,
     };
@@ -224,7 +236,7 @@ fn cmd_lindex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// LRANGE key start stop
 fn cmd_lrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let start: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -234,17 +246,16 @@ fn cmd_lrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         None => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::List
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let list = match db.list_keys.get(key.as_ref()) {
+    let list = match db.list_keys.get(key.as_slice()) {
         Some(l) => l,
         None => return Frame::Array(vec![]),
     };
@@ -264,16 +275,15 @@ fn cmd_lrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// LSET key index element
 fn cmd_lset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let index: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
     };
     let value = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -302,12 +312,13 @@ fn cmd_lset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
     list[idx as usize] = value;
     db.incr_version(&key, now);
+    db.notify(NOTIFY_LIST, "lset", &key);
     Frame::ok()
 }
 
 /// LINSERT key BEFORE|AFTER pivot element
 fn cmd_linsert(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let position = String::from_utf8_lossy(&args[1]).to_uppercase();
     let before = match position.as_str() {
         "BEFORE" => true,
@@ -317,9 +328,8 @@ fn cmd_linsert(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     let pivot = &args[2];
     let value = args[3].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -345,6 +355,7 @@ fn cmd_linsert(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             list.insert(insert_at, value);
             let new_len = list.len() as i64;
             db.incr_version(&key, now);
+            db.notify(NOTIFY_LIST, "linsert", &key);
             Frame::Integer(new_len)
         }
         None => Frame::Integer(-1),
@@ -353,16 +364,15 @@ fn cmd_linsert(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// LREM key count element
 fn cmd_lrem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let count: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
     };
     let element = &args[2];
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -411,13 +421,17 @@ fn cmd_lrem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
     } else {
         db.incr_version(&key, now);
     }
+    if removed > 0 {
+        db.notify(NOTIFY_LIST, "lrem", &key);
+        db.notify_if_deleted(&key);
+    }
 
     Frame::Integer(removed)
 }
 
 /// LTRIM key start stop
 fn cmd_ltrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let start: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -427,9 +441,8 @@ fn cmd_ltrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         None => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -452,6 +465,8 @@ fn cmd_ltrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
     if rs > re || rs >= len {
         db.del(&key);
+        db.notify(NOTIFY_LIST, "ltrim", &key);
+        db.notify_if_deleted(&key);
         return Frame::ok();
     }
 
@@ -468,125 +483,93 @@ fn cmd_ltrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         db.list_keys.insert(key.clone(), trimmed);
         db.incr_version(&key, now);
     }
+    db.notify(NOTIFY_LIST, "ltrim", &key);
+    db.notify_if_deleted(&key);
 
     Frame::ok()
 }
 
 /// RPOPLPUSH source destination
 fn cmd_rpoplpush(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
-
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&src);
-    db.check_ttl(&dst);
-
-    // Type checks
-    if let Some(t) = db.key_type(&src)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
-    }
-    if let Some(t) = db.key_type(&dst)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
-    }
-
-    // Save TTL when src == dst so we can restore it after pop+push cycle
-    let saved_ttl = if src == dst {
-        db.ttl.get(&src).cloned()
-    } else {
-        None
-    };
-
-    let val = match db.list_rpop(&src, now) {
-        Some(v) => v,
-        None => return Frame::Null,
-    };
-
-    db.list_lpush(&dst, std::slice::from_ref(&val), now);
-
-    // Restore TTL if src == dst (pop may have deleted the key and its TTL)
-    if let Some(ttl) = saved_ttl {
-        db.ttl.insert(dst.clone(), ttl);
-    }
-
-    Frame::Bulk(val.into())
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    list_move(&mut db, &args[0], &args[1], false, true, now).unwrap_or(Frame::Null)
 }
 
 /// LMOVE source destination LEFT|RIGHT LEFT|RIGHT
 fn cmd_lmove(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
-    let src_dir = String::from_utf8_lossy(&args[2]).to_uppercase();
-    let dst_dir = String::from_utf8_lossy(&args[3]).to_uppercase();
-
-    let pop_left = match src_dir.as_str() {
+    let pop_left = match String::from_utf8_lossy(&args[2]).to_uppercase().as_str() {
         "LEFT" => true,
         "RIGHT" => false,
         _ => return Frame::error(MSG_SYNTAX_ERROR),
     };
-    let push_left = match dst_dir.as_str() {
+    let push_left = match String::from_utf8_lossy(&args[3]).to_uppercase().as_str() {
         "LEFT" => true,
         "RIGHT" => false,
         _ => return Frame::error(MSG_SYNTAX_ERROR),
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&src);
-    db.check_ttl(&dst);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    list_move(&mut db, &args[0], &args[1], pop_left, push_left, now).unwrap_or(Frame::Null)
+}
 
-    if let Some(t) = db.key_type(&src)
+/// Move an element from the head or tail of `src` to the head or tail of
+/// `dst`, as LMOVE does. Returns None if `src` is empty.
+pub(crate) fn list_move(
+    db: &mut RedisDB,
+    src: &[u8],
+    dst: &[u8],
+    pop_left: bool,
+    push_left: bool,
+    now: SystemTime,
+) -> Option<Frame> {
+    db.check_ttl(src);
+    db.check_ttl(dst);
+
+    if let Some(t) = db.key_type(src)
         && t != KeyType::List
     {
-        return Frame::error(MSG_WRONG_TYPE);
+        return Some(Frame::error(MSG_WRONG_TYPE));
     }
-    if let Some(t) = db.key_type(&dst)
+    if let Some(t) = db.key_type(dst)
         && t != KeyType::List
     {
-        return Frame::error(MSG_WRONG_TYPE);
+        return Some(Frame::error(MSG_WRONG_TYPE));
     }
 
     // Save TTL when src == dst so we can restore it after pop+push cycle
     let saved_ttl = if src == dst {
-        db.ttl.get(&src).cloned()
+        db.ttl.get(src).cloned()
     } else {
         None
     };
 
     let val = if pop_left {
-        db.list_lpop(&src, now)
+        db.list_lpop(src, now)
     } else {
-        db.list_rpop(&src, now)
-    };
-
-    match val {
-        Some(v) => {
-            if push_left {
-                db.list_lpush(&dst, std::slice::from_ref(&v), now);
-            } else {
-                db.list_rpush(&dst, std::slice::from_ref(&v), now);
-            }
-            // Restore TTL if src == dst (pop may have deleted the key and its TTL)
-            if let Some(ttl) = saved_ttl {
-                db.ttl.insert(dst.clone(), ttl);
-            }
-            Frame::Bulk(v.into())
-        }
-        None => Frame::Null,
+        db.list_rpop(src, now)
+    }?;
+    db.notify(NOTIFY_LIST, if pop_left { "lpop" } else { "rpop" }, src);
+    db.notify_if_deleted(src);
+    if push_left {
+        db.list_lpush(dst, std::slice::from_ref(&val), now);
+    } else {
+        db.list_rpush(dst, std::slice::from_ref(&val), now);
+    }
+    db.notify(NOTIFY_LIST, if push_left { "lpush" } else { "rpush" }, dst);
+    // Restore TTL if src == dst (pop may have deleted the key and its TTL)
+    if let Some(ttl) = saved_ttl {
+        db.ttl.insert(dst.to_vec(), ttl);
     }
+    Some(Frame::Bulk(val.into()))
 }
 
 // ── Utility ──────────────────────────────────────────────────────────
 
 /// LPOS key element [RANK rank] [COUNT count] [MAXLEN maxlen]
 fn cmd_lpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let element = &args[1];
     let mut rank: i64 = 1;
     let mut count: Option<i64> = None;
@@ -638,9 +621,8 @@ fn cmd_lpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         i += 1;
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(&key);
 
     if let Some(t) = db.key_type(&key)
         && t != KeyType::List
@@ -716,18 +698,17 @@ fn cmd_lpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 /// BLPOP key [key ...] timeout — non-blocking attempt (for MULTI/EXEC)
 pub fn cmd_blpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     // Last arg is timeout — validate it
-    if let Some(err) = validate_timeout(&args[args.len() - 1]) {
+    if let Err(err) = parse_timeout(&args[args.len() - 1]) {
         return err;
     }
 
     // Last arg is timeout, keys are all but last
     let keys = &args[..args.len() - 1];
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     for key_bytes in keys {
-        let key = String::from_utf8_lossy(key_bytes).into_owned();
-        let db = inner.db_mut(ctx.selected_db);
+        let key = key_bytes.to_vec();
         db.check_ttl(&key);
 
         if let Some(t) = db.key_type(&key)
@@ -737,6 +718,8 @@ pub fn cmd_blpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         }
 
         if let Some(val) = db.list_lpop(&key, now) {
+            db.notify(NOTIFY_LIST, "lpop", &key);
+            db.notify_if_deleted(&key);
             return Frame::Array(vec![Frame::Bulk(key.into()), Frame::Bulk(val.into())]);
         }
     }
@@ -747,17 +730,16 @@ pub fn cmd_blpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
 /// BRPOP key [key ...] timeout — non-blocking attempt (for MULTI/EXEC)
 pub fn cmd_brpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     // Last arg is timeout — validate it
-    if let Some(err) = validate_timeout(&args[args.len() - 1]) {
+    if let Err(err) = parse_timeout(&args[args.len() - 1]) {
         return err;
     }
 
     let keys = &args[..args.len() - 1];
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     for key_bytes in keys {
-        let key = String::from_utf8_lossy(key_bytes).into_owned();
-        let db = inner.db_mut(ctx.selected_db);
+        let key = key_bytes.to_vec();
         db.check_ttl(&key);
 
         if let Some(t) = db.key_type(&key)
@@ -767,6 +749,8 @@ pub fn cmd_brpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         }
 
         if let Some(val) = db.list_rpop(&key, now) {
+            db.notify(NOTIFY_LIST, "rpop", &key);
+            db.notify_if_deleted(&key);
             return Frame::Array(vec![Frame::Bulk(key.into()), Frame::Bulk(val.into())]);
         }
     }
@@ -776,107 +760,99 @@ pub fn cmd_brpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
 
 /// BRPOPLPUSH source destination timeout — non-blocking attempt
 pub fn cmd_brpoplpush(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    // Last arg is timeout — validate it
-    if let Some(err) = validate_timeout(&args[2]) {
+    if let Err(err) = parse_timeout(&args[2]) {
         return err;
     }
 
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
-
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&src);
-    db.check_ttl(&dst);
-
-    if let Some(t) = db.key_type(&src)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
-    }
-    if let Some(t) = db.key_type(&dst)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
-    }
-
-    match db.list_rpop(&src, now) {
-        Some(val) => {
-            db.list_lpush(&dst, std::slice::from_ref(&val), now);
-            Frame::Bulk(val.into())
-        }
-        None => Frame::Null,
-    }
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    list_move(&mut db, &args[0], &args[1], false, true, now).unwrap_or(Frame::Null)
 }
 
 /// BLMOVE source destination LEFT|RIGHT LEFT|RIGHT timeout — non-blocking attempt
 pub fn cmd_blmove(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
-    let src_dir = String::from_utf8_lossy(&args[2]).to_uppercase();
-    let dst_dir = String::from_utf8_lossy(&args[3]).to_uppercase();
-
-    let pop_left = match src_dir.as_str() {
+    let pop_left = match String::from_utf8_lossy(&args[2]).to_uppercase().as_str() {
         "LEFT" => true,
         "RIGHT" => false,
         _ => return Frame::error(MSG_SYNTAX_ERROR),
     };
-    let push_left = match dst_dir.as_str() {
+    let push_left = match String::from_utf8_lossy(&args[3]).to_uppercase().as_str() {
         "LEFT" => true,
         "RIGHT" => false,
         _ => return Frame::error(MSG_SYNTAX_ERROR),
     };
 
-    if let Some(err) = validate_timeout(&args[4]) {
+    if let Err(err) = parse_timeout(&args[4]) {
         return err;
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&src);
-    db.check_ttl(&dst);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    list_move(&mut db, &args[0], &args[1], pop_left, push_left, now).unwrap_or(Frame::Null)
+}
 
-    if let Some(t) = db.key_type(&src)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
-    }
-    if let Some(t) = db.key_type(&dst)
-        && t != KeyType::List
-    {
-        return Frame::error(MSG_WRONG_TYPE);
+/// BLMPOP timeout numkeys key [key ...] LEFT|RIGHT [COUNT count] —
+/// non-blocking attempt
+pub fn cmd_blmpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if let Err(err) = parse_timeout(&args[0]) {
+        return err;
     }
+    cmd_lmpop(state, ctx, &args[1..])
+}
 
-    // Save TTL when src == dst so we can restore it after pop+push cycle
-    let saved_ttl = if src == dst {
-        db.ttl.get(&src).cloned()
-    } else {
-        None
+/// LMPOP numkeys key [key ...] LEFT|RIGHT [COUNT count]
+fn cmd_lmpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let opts = match parse_mpop(args, ["LEFT", "RIGHT"]) {
+        Ok(opts) => opts,
+        Err(err) => return err,
     };
 
-    let val = if pop_left {
-        db.list_lpop(&src, now)
-    } else {
-        db.list_rpop(&src, now)
-    };
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    for key in &opts.keys {
+        db.check_ttl(key);
+        if let Some(t) = db.key_type(key)
+            && t != KeyType::List
+        {
+            return Frame::error(MSG_WRONG_TYPE);
+        }
+        if let Some(frame) = list_mpop(&mut db, key, opts.first, opts.count, now) {
+            return frame;
+        }
+    }
+    Frame::NullArray
+}
 
-    match val {
-        Some(v) => {
-            if push_left {
-                db.list_lpush(&dst, std::slice::from_ref(&v), now);
-            } else {
-                db.list_rpush(&dst, std::slice::from_ref(&v), now);
-            }
-            // Restore TTL if src == dst
-            if let Some(ttl) = saved_ttl {
-                db.ttl.insert(dst.clone(), ttl);
-            }
-            Frame::Bulk(v.into())
+/// Pop up to `count` elements from one end of a list, as LMPOP does:
+/// `[key, [element ...]]`. Returns None if the list is empty.
+pub(crate) fn list_mpop(
+    db: &mut RedisDB,
+    key: &[u8],
+    left: bool,
+    count: usize,
+    now: SystemTime,
+) -> Option<Frame> {
+    let mut popped = Vec::new();
+    while popped.len() < count {
+        let val = if left {
+            db.list_lpop(key, now)
+        } else {
+            db.list_rpop(key, now)
+        };
+        match val {
+            Some(v) => popped.push(Frame::Bulk(v.into())),
+            None => break,
         }
-        None => Frame::Null,
     }
+    if popped.is_empty() {
+        return None;
+    }
+    db.notify(NOTIFY_LIST, if left { "lpop" } else { "rpop" }, key);
+    db.notify_if_deleted(key);
+    Some(Frame::Array(vec![
+        Frame::Bulk(key.to_vec().into()),
+        Frame::Array(popped),
+    ]))
 }
 
 // ── Utility ──────────────────────────────────────────────────────────
@@ -901,18 +877,3 @@ fn redis_range(start: i64, end: i64, len: i64) -> (i64, i64) {
 
     (s, e)
 }
-
-/// Validate a blocking command timeout argument.
-/// Returns Some(Frame) with error if invalid, None if OK.
-fn validate_timeout(arg: &[u8]) -> Option<Frame> {
-    let s = String::from_utf8_lossy(arg);
-    let s_lower = s.to_lowercase();
-    if s_lower == "inf" || s_lower == "+inf" || s_lower == "-inf" {
-        return Some(Frame::error(MSG_TIMEOUT_IS_OUT_OF_RANGE));
-    }
-    match s.parse::<f64>() {
-        Ok(t) if t < 0.0 => Some(Frame::error(MSG_TIMEOUT_NEGATIVE)),
-        Ok(_) => None,
-        Err(_) => Some(Frame::error(MSG_INVALID_TIMEOUT)),
-    }
-}
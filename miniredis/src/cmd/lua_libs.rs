@@ -0,0 +1,730 @@
+//! The `bit`, `struct` and `cmsgpack` libraries Redis makes available to
+//! Lua scripts, following LuaBitOp, Roberto Ierusalimschy's struct library
+//! and lua-cmsgpack.
+use mlua::Variadic;
+use mlua::prelude::*;
+
+/// Install `bit`, `struct` and `cmsgpack` as globals.
+pub(crate) fn install(lua: &Lua) -> LuaResult<()> {
+    lua.globals().set("bit", bit_library(lua)?)?;
+    lua.globals().set("struct", struct_library(lua)?)?;
+    lua.globals().set("cmsgpack", cmsgpack_library(lua)?)?;
+    Ok(())
+}
+
+fn runtime_error<T>(msg: impl Into<String>) -> LuaResult<T> {
+    Err(LuaError::RuntimeError(msg.into()))
+}
+
+/// The number in a Lua value, with strings converted as Lua does.
+fn to_number(value: &LuaValue) -> Option<f64> {
+    match value {
+        LuaValue::Integer(n) => Some(*n as f64),
+        LuaValue::Number(n) => Some(*n),
+        LuaValue::String(s) => String::from_utf8_lossy(&s.as_bytes()).trim().parse().ok(),
+        _ => None,
+    }
+}
+
+// ── bit ─────────────────────────────────────────────────────────────
+
+/// Normalize a number to a signed 32 bit integer, as LuaBitOp does.
+fn tobit(n: f64) -> i32 {
+    n.round_ties_even().rem_euclid(4294967296.0) as u64 as u32 as i32
+}
+
+fn bit_library(lua: &Lua) -> LuaResult<LuaTable> {
+    let bit = lua.create_table()?;
+    bit.set("tobit", lua.create_function(|_, x: f64| Ok(tobit(x)))?)?;
+    bit.set(
+        "tohex",
+        lua.create_function(|_, (x, n): (f64, Option<i64>)| {
+            let n = n.unwrap_or(8);
+            let digits = n.unsigned_abs().min(8) as usize;
+            let hex = if n < 0 {
+                format!("{:08X}", tobit(x) as u32)
+            } else {
+                format!("{:08x}", tobit(x) as u32)
+            };
+            Ok(hex[8 - digits..].to_string())
+        })?,
+    )?;
+    bit.set("bnot", lua.create_function(|_, x: f64| Ok(!tobit(x)))?)?;
+
+    let fold = |op: fn(i32, i32) -> i32| {
+        lua.create_function(move |_, (x, rest): (f64, Variadic<f64>)| {
+            Ok(rest.iter().fold(tobit(x), |acc, &y| op(acc, tobit(y))))
+        })
+    };
+    bit.set("band", fold(|a, b| a & b)?)?;
+    bit.set("bor", fold(|a, b| a | b)?)?;
+    bit.set("bxor", fold(|a, b| a ^ b)?)?;
+
+    let shift = |op: fn(i32, u32) -> i32| {
+        lua.create_function(move |_, (x, n): (f64, f64)| Ok(op(tobit(x), tobit(n) as u32 & 31)))
+    };
+    bit.set("lshift", shift(|x, n| ((x as u32) << n) as i32)?)?;
+    bit.set("rshift", shift(|x, n| ((x as u32) >> n) as i32)?)?;
+    bit.set("arshift", shift(|x, n| x >> n)?)?;
+    bit.set("rol", shift(|x, n| x.rotate_left(n))?)?;
+    bit.set("ror", shift(|x, n| x.rotate_right(n))?)?;
+    bit.set(
+        "bswap",
+        lua.create_function(|_, x: f64| Ok(tobit(x).swap_bytes()))?,
+    )?;
+    Ok(bit)
+}
+
+// ── struct ──────────────────────────────────────────────────────────
+
+/// Largest integer size `i` and `I` take.
+const STRUCT_MAX_INT_SIZE: usize = 32;
+/// Alignment `!` without a number sets.
+const STRUCT_MAX_ALIGN: usize = 8;
+
+/// One option of a struct format string.
+enum StructOpt {
+    LittleEndian,
+    BigEndian,
+    Align(usize),
+    /// `x`: a zero byte.
+    Pad,
+    Int {
+        size: usize,
+        signed: bool,
+    },
+    Float,
+    Double,
+    /// `s`: a zero-terminated string.
+    ZeroString,
+    /// `cN`: N characters; with N = 0 the length comes from the string
+    /// (pack) or the previous value (unpack).
+    Chars(usize),
+}
+
+impl StructOpt {
+    fn size(&self) -> usize {
+        match self {
+            StructOpt::Pad => 1,
+            StructOpt::Int { size, .. } => *size,
+            StructOpt::Float => 4,
+            StructOpt::Double => 8,
+            StructOpt::Chars(n) => *n,
+            _ => 0,
+        }
+    }
+
+    /// Padding needed before this option at offset `len`.
+    fn padding(&self, len: usize, max_align: usize) -> usize {
+        let align = match self {
+            StructOpt::Int { .. } | StructOpt::Float | StructOpt::Double => {
+                self.size().min(max_align)
+            }
+            _ => return 0,
+        };
+        if align <= 1 {
+            return 0;
+        }
+        (align - (len & (align - 1))) & (align - 1)
+    }
+}
+
+fn parse_struct_format(fmt: &[u8]) -> LuaResult<Vec<StructOpt>> {
+    let mut opts = Vec::new();
+    let mut i = 0;
+    // The number after an option, if any.
+    let number = |i: &mut usize, default: usize| -> usize {
+        let start = *i;
+        while *i < fmt.len() && fmt[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        std::str::from_utf8(&fmt[start..*i])
+            .ok()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default)
+    };
+    while i < fmt.len() {
+        let opt = fmt[i];
+        i += 1;
+        opts.push(match opt {
+            b' ' => continue,
+            b'<' | b'=' => StructOpt::LittleEndian,
+            b'>' => StructOpt::BigEndian,
+            b'!' => {
+                let align = number(&mut i, STRUCT_MAX_ALIGN);
+                if !align.is_power_of_two() {
+                    return runtime_error(format!("alignment {} is not a power of 2", align));
+                }
+                StructOpt::Align(align)
+            }
+            b'x' => StructOpt::Pad,
+            b'b' | b'B' => StructOpt::Int {
+                size: 1,
+                signed: opt == b'b',
+            },
+            b'h' | b'H' => StructOpt::Int {
+                size: 2,
+                signed: opt == b'h',
+            },
+            b'l' | b'L' | b'T' => StructOpt::Int {
+                size: 8,
+                signed: opt == b'l',
+            },
+            b'i' | b'I' => {
+                let size = number(&mut i, 4);
+                if size > STRUCT_MAX_INT_SIZE {
+                    return runtime_error(format!(
+                        "integral size {} is larger than limit of {}",
+                        size, STRUCT_MAX_INT_SIZE
+                    ));
+                }
+                StructOpt::Int {
+                    size,
+                    signed: opt == b'i',
+                }
+            }
+            b'f' => StructOpt::Float,
+            b'd' => StructOpt::Double,
+            b's' => StructOpt::ZeroString,
+            b'c' => StructOpt::Chars(number(&mut i, 1)),
+            _ => return runtime_error(format!("invalid format option '{}'", opt as char)),
+        });
+    }
+    Ok(opts)
+}
+
+/// Append `bytes` (least significant first) in the given byte order.
+fn put_bytes(out: &mut Vec<u8>, mut bytes: Vec<u8>, big_endian: bool) {
+    if big_endian {
+        bytes.reverse();
+    }
+    out.extend_from_slice(&bytes);
+}
+
+/// Read `n` bytes at `pos`, least significant first.
+fn get_bytes(data: &[u8], pos: usize, n: usize, big_endian: bool) -> LuaResult<Vec<u8>> {
+    let Some(raw) = data.get(pos..pos + n) else {
+        return runtime_error("bad argument #2 to 'unpack' (data string too short)");
+    };
+    let mut bytes = raw.to_vec();
+    if big_endian {
+        bytes.reverse();
+    }
+    Ok(bytes)
+}
+
+fn struct_pack(lua: &Lua, fmt: &[u8], args: Vec<LuaValue>) -> LuaResult<LuaString> {
+    let mut out = Vec::new();
+    let mut big_endian = false;
+    let mut max_align = 1;
+    let mut args = args.into_iter();
+    let mut arg_no = 1;
+    let mut next_arg = || {
+        arg_no += 1;
+        (arg_no, args.next().unwrap_or(LuaValue::Nil))
+    };
+    for opt in parse_struct_format(fmt)? {
+        out.resize(out.len() + opt.padding(out.len(), max_align), 0);
+        match opt {
+            StructOpt::LittleEndian => big_endian = false,
+            StructOpt::BigEndian => big_endian = true,
+            StructOpt::Align(n) => max_align = n,
+            StructOpt::Pad => out.push(0),
+            StructOpt::Int { size, .. } => {
+                let (n, arg) = next_arg();
+                let Some(value) = to_number(&arg) else {
+                    return runtime_error(format!(
+                        "bad argument #{} to 'pack' (number expected)",
+                        n
+                    ));
+                };
+                let value = if value < 0.0 {
+                    value as i64 as u64
+                } else {
+                    value as u64
+                };
+                let bytes = (0..size)
+                    .map(|i| if i < 8 { (value >> (8 * i)) as u8 } else { 0 })
+                    .collect();
+                put_bytes(&mut out, bytes, big_endian);
+            }
+            StructOpt::Float | StructOpt::Double => {
+                let (n, arg) = next_arg();
+                let Some(value) = to_number(&arg) else {
+                    return runtime_error(format!(
+                        "bad argument #{} to 'pack' (number expected)",
+                        n
+                    ));
+                };
+                let bytes = match opt {
+                    StructOpt::Float => (value as f32).to_le_bytes().to_vec(),
+                    _ => value.to_le_bytes().to_vec(),
+                };
+                put_bytes(&mut out, bytes, big_endian);
+            }
+            StructOpt::ZeroString | StructOpt::Chars(_) => {
+                let (n, arg) = next_arg();
+                let s = match &arg {
+                    LuaValue::String(s) => s.as_bytes().to_vec(),
+                    LuaValue::Integer(_) | LuaValue::Number(_) => {
+                        to_number(&arg).unwrap_or_default().to_string().into_bytes()
+                    }
+                    _ => {
+                        return runtime_error(format!(
+                            "bad argument #{} to 'pack' (string expected)",
+                            n
+                        ));
+                    }
+                };
+                match opt {
+                    StructOpt::ZeroString => {
+                        if s.contains(&0) {
+                            return runtime_error(format!(
+                                "bad argument #{} to 'pack' (string contains zeros)",
+                                n
+                            ));
+                        }
+                        out.extend_from_slice(&s);
+                        out.push(0);
+                    }
+                    _ => {
+                        let size = if opt.size() == 0 { s.len() } else { opt.size() };
+                        if s.len() < size {
+                            return runtime_error(format!(
+                                "bad argument #{} to 'pack' (string too short)",
+                                n
+                            ));
+                        }
+                        out.extend_from_slice(&s[..size]);
+                    }
+                }
+            }
+        }
+    }
+    lua.create_string(&out)
+}
+
+fn struct_unpack(
+    lua: &Lua,
+    fmt: &[u8],
+    data: &[u8],
+    init: Option<i64>,
+) -> LuaResult<LuaMultiValue> {
+    let mut pos = match init.unwrap_or(1) {
+        n if n >= 1 => n as usize - 1,
+        _ => return runtime_error("bad argument #3 to 'unpack' (offset must be 1 or greater)"),
+    };
+    let mut values: Vec<LuaValue> = Vec::new();
+    let mut big_endian = false;
+    let mut max_align = 1;
+    for opt in parse_struct_format(fmt)? {
+        pos += opt.padding(pos, max_align);
+        match opt {
+            StructOpt::LittleEndian => big_endian = false,
+            StructOpt::BigEndian => big_endian = true,
+            StructOpt::Align(n) => max_align = n,
+            StructOpt::Pad => {
+                get_bytes(data, pos, 1, false)?;
+                pos += 1;
+            }
+            StructOpt::Int { size, signed } => {
+                let bytes = get_bytes(data, pos, size, big_endian)?;
+                let mut value = bytes
+                    .iter()
+                    .take(8)
+                    .rev()
+                    .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+                if signed && size < 8 {
+                    let mask = 1u64 << (size * 8 - 1);
+                    value = (value ^ mask).wrapping_sub(mask);
+                }
+                let value = if signed {
+                    value as i64 as f64
+                } else {
+                    value as f64
+                };
+                values.push(LuaValue::Number(value));
+                pos += size;
+            }
+            StructOpt::Float => {
+                let bytes = get_bytes(data, pos, 4, big_endian)?;
+                let value = f32::from_le_bytes(bytes.try_into().unwrap());
+                values.push(LuaValue::Number(value as f64));
+                pos += 4;
+            }
+            StructOpt::Double => {
+                let bytes = get_bytes(data, pos, 8, big_endian)?;
+                values.push(LuaValue::Number(f64::from_le_bytes(
+                    bytes.try_into().unwrap(),
+                )));
+                pos += 8;
+            }
+            StructOpt::ZeroString => {
+                let Some(len) = data
+                    .get(pos..)
+                    .and_then(|rest| rest.iter().position(|&b| b == 0))
+                else {
+                    return runtime_error("unfinished string in data");
+                };
+                values.push(LuaValue::String(lua.create_string(&data[pos..pos + len])?));
+                pos += len + 1;
+            }
+            StructOpt::Chars(n) => {
+                let size = if n > 0 {
+                    n
+                } else {
+                    match values.pop().as_ref().and_then(to_number) {
+                        Some(len) => len as usize,
+                        None => return runtime_error("format 'c0' needs a previous size"),
+                    }
+                };
+                let bytes = get_bytes(data, pos, size, false)?;
+                values.push(LuaValue::String(lua.create_string(&bytes)?));
+                pos += size;
+            }
+        }
+    }
+    values.push(LuaValue::Integer(pos as i64 + 1));
+    Ok(values.into_iter().collect())
+}
+
+fn struct_library(lua: &Lua) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.set(
+        "pack",
+        lua.create_function(|lua, (fmt, args): (LuaString, Variadic<LuaValue>)| {
+            struct_pack(lua, &fmt.as_bytes(), args.into_iter().collect())
+        })?,
+    )?;
+    table.set(
+        "unpack",
+        lua.create_function(
+            |lua, (fmt, data, init): (LuaString, LuaString, Option<i64>)| {
+                struct_unpack(lua, &fmt.as_bytes(), &data.as_bytes(), init)
+            },
+        )?,
+    )?;
+    table.set(
+        "size",
+        lua.create_function(|_, fmt: LuaString| {
+            let mut size = 0;
+            let mut max_align = 1;
+            for opt in parse_struct_format(&fmt.as_bytes())? {
+                size += opt.padding(size, max_align);
+                match opt {
+                    StructOpt::Align(n) => max_align = n,
+                    StructOpt::ZeroString | StructOpt::Chars(0) => {
+                        return runtime_error("options 'c0' - 's' have undefined sizes");
+                    }
+                    _ => size += opt.size(),
+                }
+            }
+            Ok(size)
+        })?,
+    )?;
+    Ok(table)
+}
+
+// ── cmsgpack ────────────────────────────────────────────────────────
+
+/// Tables nested deeper than this are packed as nil.
+const MSGPACK_MAX_NESTING: usize = 16;
+
+fn msgpack_int(out: &mut Vec<u8>, n: i64) {
+    if n >= 0 {
+        match n {
+            0..=0x7f => out.push(n as u8),
+            0x80..=0xff => out.extend_from_slice(&[0xcc, n as u8]),
+            0x100..=0xffff => {
+                out.push(0xcd);
+                out.extend_from_slice(&(n as u16).to_be_bytes());
+            }
+            0x1_0000..=0xffff_ffff => {
+                out.push(0xce);
+                out.extend_from_slice(&(n as u32).to_be_bytes());
+            }
+            _ => {
+                out.push(0xcf);
+                out.extend_from_slice(&(n as u64).to_be_bytes());
+            }
+        }
+    } else if n >= -32 {
+        out.push(n as i8 as u8);
+    } else if n >= i8::MIN as i64 {
+        out.extend_from_slice(&[0xd0, n as i8 as u8]);
+    } else if n >= i16::MIN as i64 {
+        out.push(0xd1);
+        out.extend_from_slice(&(n as i16).to_be_bytes());
+    } else if n >= i32::MIN as i64 {
+        out.push(0xd2);
+        out.extend_from_slice(&(n as i32).to_be_bytes());
+    } else {
+        out.push(0xd3);
+        out.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+/// Write a length header: the fix variant for short lengths, then the
+/// 16 and 32 bit ones.
+fn msgpack_len(out: &mut Vec<u8>, len: usize, fix: (u8, usize), long: (u8, u8)) {
+    if len < fix.1 {
+        out.push(fix.0 | len as u8);
+    } else if len <= 0xffff {
+        out.push(long.0);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(long.1);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+}
+
+fn msgpack_encode(out: &mut Vec<u8>, value: &LuaValue, depth: usize) -> LuaResult<()> {
+    match value {
+        LuaValue::Boolean(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        LuaValue::Integer(n) => msgpack_int(out, *n),
+        LuaValue::Number(n) => {
+            if n.fract() == 0.0 && *n >= i64::MIN as f64 && *n < i64::MAX as f64 {
+                msgpack_int(out, *n as i64);
+            } else if (*n as f32) as f64 == *n {
+                out.push(0xca);
+                out.extend_from_slice(&(*n as f32).to_be_bytes());
+            } else {
+                out.push(0xcb);
+                out.extend_from_slice(&n.to_be_bytes());
+            }
+        }
+        LuaValue::String(s) => {
+            let bytes = s.as_bytes().to_vec();
+            if bytes.len() < 32 {
+                out.push(0xa0 | bytes.len() as u8);
+            } else if bytes.len() <= 0xff {
+                out.extend_from_slice(&[0xd9, bytes.len() as u8]);
+            } else {
+                msgpack_len(out, bytes.len(), (0xa0, 0), (0xda, 0xdb));
+            }
+            out.extend_from_slice(&bytes);
+        }
+        LuaValue::Table(t) if depth < MSGPACK_MAX_NESTING => {
+            let pairs: Vec<(LuaValue, LuaValue)> = t
+                .clone()
+                .pairs::<LuaValue, LuaValue>()
+                .collect::<LuaResult<_>>()?;
+            // An array has the keys 1 to n, and nothing else.
+            let mut max = 0.0;
+            let is_array = pairs.iter().all(|(k, _)| match to_index(k) {
+                Some(i) => {
+                    max = f64::max(max, i);
+                    true
+                }
+                None => false,
+            }) && max as usize == pairs.len();
+            if is_array {
+                msgpack_len(out, pairs.len(), (0x90, 16), (0xdc, 0xdd));
+                for i in 1..=pairs.len() {
+                    msgpack_encode(out, &t.raw_get(i)?, depth + 1)?;
+                }
+            } else {
+                msgpack_len(out, pairs.len(), (0x80, 16), (0xde, 0xdf));
+                for (k, v) in &pairs {
+                    msgpack_encode(out, k, depth + 1)?;
+                    msgpack_encode(out, v, depth + 1)?;
+                }
+            }
+        }
+        _ => out.push(0xc0),
+    }
+    Ok(())
+}
+
+/// A positive whole number table key.
+fn to_index(key: &LuaValue) -> Option<f64> {
+    match key {
+        LuaValue::Integer(n) if *n > 0 => Some(*n as f64),
+        LuaValue::Number(n) if *n > 0.0 && n.fract() == 0.0 => Some(*n),
+        _ => None,
+    }
+}
+
+fn msgpack_missing_bytes<T>() -> LuaResult<T> {
+    runtime_error("Missing bytes in input.")
+}
+
+/// Take `n` bytes at `pos`.
+fn msgpack_take<'a>(data: &'a [u8], pos: &mut usize, n: usize) -> LuaResult<&'a [u8]> {
+    let Some(bytes) = data.get(*pos..*pos + n) else {
+        return msgpack_missing_bytes();
+    };
+    *pos += n;
+    Ok(bytes)
+}
+
+/// A big-endian unsigned integer of `n` bytes.
+fn msgpack_uint(data: &[u8], pos: &mut usize, n: usize) -> LuaResult<u64> {
+    Ok(msgpack_take(data, pos, n)?
+        .iter()
+        .fold(0, |acc, &b| (acc << 8) | b as u64))
+}
+
+fn msgpack_decode(lua: &Lua, data: &[u8], pos: &mut usize) -> LuaResult<LuaValue> {
+    let tag = msgpack_take(data, pos, 1)?[0];
+    let int = |n: i64| LuaValue::Integer(n);
+    Ok(match tag {
+        0x00..=0x7f => int(tag as i64),
+        0x80..=0x8f => msgpack_map(lua, data, pos, (tag & 0x0f) as usize)?,
+        0x90..=0x9f => msgpack_array(lua, data, pos, (tag & 0x0f) as usize)?,
+        0xa0..=0xbf => msgpack_str(lua, data, pos, (tag & 0x1f) as usize)?,
+        0xc0 => LuaValue::Nil,
+        0xc2 => LuaValue::Boolean(false),
+        0xc3 => LuaValue::Boolean(true),
+        0xc4 | 0xd9 => {
+            let len = msgpack_uint(data, pos, 1)? as usize;
+            msgpack_str(lua, data, pos, len)?
+        }
+        0xc5 | 0xda => {
+            let len = msgpack_uint(data, pos, 2)? as usize;
+            msgpack_str(lua, data, pos, len)?
+        }
+        0xc6 | 0xdb => {
+            let len = msgpack_uint(data, pos, 4)? as usize;
+            msgpack_str(lua, data, pos, len)?
+        }
+        0xca => {
+            let bits = msgpack_uint(data, pos, 4)? as u32;
+            LuaValue::Number(f32::from_bits(bits) as f64)
+        }
+        0xcb => LuaValue::Number(f64::from_bits(msgpack_uint(data, pos, 8)?)),
+        0xcc => int(msgpack_uint(data, pos, 1)? as i64),
+        0xcd => int(msgpack_uint(data, pos, 2)? as i64),
+        0xce => int(msgpack_uint(data, pos, 4)? as i64),
+        0xcf => match msgpack_uint(data, pos, 8)? {
+            n if n > i64::MAX as u64 => LuaValue::Number(n as f64),
+            n => int(n as i64),
+        },
+        0xd0 => int(msgpack_uint(data, pos, 1)? as i8 as i64),
+        0xd1 => int(msgpack_uint(data, pos, 2)? as i16 as i64),
+        0xd2 => int(msgpack_uint(data, pos, 4)? as i32 as i64),
+        0xd3 => int(msgpack_uint(data, pos, 8)? as i64),
+        0xdc => {
+            let len = msgpack_uint(data, pos, 2)? as usize;
+            msgpack_array(lua, data, pos, len)?
+        }
+        0xdd => {
+            let len = msgpack_uint(data, pos, 4)? as usize;
+            msgpack_array(lua, data, pos, len)?
+        }
+        0xde => {
+            let len = msgpack_uint(data, pos, 2)? as usize;
+            msgpack_map(lua, data, pos, len)?
+        }
+        0xdf => {
+            let len = msgpack_uint(data, pos, 4)? as usize;
+            msgpack_map(lua, data, pos, len)?
+        }
+        0xe0..=0xff => int(tag as i8 as i64),
+        _ => return runtime_error("Bad data format in input."),
+    })
+}
+
+fn msgpack_str(lua: &Lua, data: &[u8], pos: &mut usize, len: usize) -> LuaResult<LuaValue> {
+    Ok(LuaValue::String(
+        lua.create_string(msgpack_take(data, pos, len)?)?,
+    ))
+}
+
+fn msgpack_array(lua: &Lua, data: &[u8], pos: &mut usize, len: usize) -> LuaResult<LuaValue> {
+    let table = lua.create_table()?;
+    for i in 1..=len {
+        table.raw_set(i, msgpack_decode(lua, data, pos)?)?;
+    }
+    Ok(LuaValue::Table(table))
+}
+
+fn msgpack_map(lua: &Lua, data: &[u8], pos: &mut usize, len: usize) -> LuaResult<LuaValue> {
+    let table = lua.create_table()?;
+    for _ in 0..len {
+        let key = msgpack_decode(lua, data, pos)?;
+        let value = msgpack_decode(lua, data, pos)?;
+        if !matches!(key, LuaValue::Nil) {
+            table.raw_set(key, value)?;
+        }
+    }
+    Ok(LuaValue::Table(table))
+}
+
+/// Decode up to `limit` objects (all of them for 0) starting at byte
+/// `offset`. With `with_offset`, the values are preceded by the offset to
+/// continue from, or -1 at the end of the input.
+fn msgpack_unpack(
+    lua: &Lua,
+    data: &[u8],
+    limit: i64,
+    offset: i64,
+    with_offset: bool,
+) -> LuaResult<LuaMultiValue> {
+    if offset < 0 || limit < 0 {
+        return runtime_error(format!(
+            "Invalid request to unpack with offset of {} and limit of {}.",
+            offset, limit
+        ));
+    }
+    if offset as usize > data.len() {
+        return runtime_error(format!(
+            "Start offset {} greater than input length {}.",
+            offset,
+            data.len()
+        ));
+    }
+    let limit = if limit == 0 {
+        usize::MAX
+    } else {
+        limit as usize
+    };
+    let mut pos = offset as usize;
+    let mut values = Vec::new();
+    while pos < data.len() && values.len() < limit {
+        values.push(msgpack_decode(lua, data, &mut pos)?);
+    }
+    if with_offset {
+        let next = if pos == data.len() { -1 } else { pos as i64 };
+        values.insert(0, LuaValue::Integer(next));
+    }
+    Ok(values.into_iter().collect())
+}
+
+fn cmsgpack_library(lua: &Lua) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+    table.set(
+        "pack",
+        lua.create_function(|lua, args: Variadic<LuaValue>| {
+            if args.is_empty() {
+                return runtime_error("MessagePack pack needs input.");
+            }
+            let mut out = Vec::new();
+            for arg in args.iter() {
+                msgpack_encode(&mut out, arg, 0)?;
+            }
+            lua.create_string(&out)
+        })?,
+    )?;
+    table.set(
+        "unpack",
+        lua.create_function(|lua, data: LuaString| {
+            msgpack_unpack(lua, &data.as_bytes(), 0, 0, false)
+        })?,
+    )?;
+    table.set(
+        "unpack_one",
+        lua.create_function(|lua, (data, offset): (LuaString, Option<i64>)| {
+            msgpack_unpack(lua, &data.as_bytes(), 1, offset.unwrap_or(0), true)
+        })?,
+    )?;
+    table.set(
+        "unpack_limit",
+        lua.create_function(
+            |lua, (data, limit, offset): (LuaString, i64, Option<i64>)| {
+                msgpack_unpack(lua, &data.as_bytes(), limit, offset.unwrap_or(0), true)
+            },
+        )?,
+    )?;
+    Ok(table)
+}
@@ -3,16 +3,24 @@
 // Each module implements a category of Redis commands.
 // Commands are registered in the dispatch table (src/dispatch.rs).
 
+pub mod acl; // ACL SETUSER, GETUSER, LIST, LOG, etc.
+#[cfg(feature = "bloom")]
+pub mod bloom; // BF.ADD, CF.ADD, CMS.INCRBY, TOPK.ADD, etc.
 pub mod client; // CLIENT SETNAME/GETNAME
-pub mod cluster; // CLUSTER SLOTS/KEYSLOT/NODES/SHARDS (mocked)
+pub mod cluster; // CLUSTER SLOTS/KEYSLOT/NODES/SHARDS, ASKING
 pub mod connection; // PING, ECHO, QUIT, SELECT, AUTH, HELLO
+pub mod functions; // FUNCTION, FCALL, FCALL_RO
 pub mod generic; // DEL, EXISTS, EXPIRE, TTL, KEYS, SCAN, etc.
 pub mod geo; // GEOADD, GEODIST, GEOPOS, GEORADIUS, etc.
 pub mod hash; // HSET, HGET, HDEL, HGETALL, etc.
 pub mod hll; // PFADD, PFCOUNT, PFMERGE
+#[cfg(feature = "json")]
+pub mod json; // JSON.SET, JSON.GET, JSON.DEL, etc.
 pub mod list; // LPUSH, RPUSH, LPOP, RPOP, BLPOP, etc.
+pub(crate) mod lua_libs; // bit, struct and cmsgpack for scripts
 pub mod object;
 pub mod pubsub; // SUBSCRIBE, PUBLISH, PSUBSCRIBE, etc.
+pub mod replication; // REPLICAOF, SLAVEOF, ROLE
 pub mod scripting; // EVAL, EVALSHA, SCRIPT
 pub mod server; // DBSIZE, FLUSHDB, INFO, TIME, etc.
 pub mod set; // SADD, SREM, SMEMBERS, SINTER, etc.
@@ -34,11 +42,75 @@ pub(crate) fn parse_float(bytes: &[u8]) -> Option<f64> {
     }
 }
 
-use crate::dispatch::{MSG_INVALID_INT, MSG_SYNTAX_ERROR};
+use crate::dispatch::{
+    MSG_COUNT_NOT_POSITIVE, MSG_INVALID_INT, MSG_INVALID_TIMEOUT, MSG_NUMKEYS_NOT_POSITIVE,
+    MSG_SYNTAX_ERROR, MSG_TIMEOUT_IS_OUT_OF_RANGE, MSG_TIMEOUT_NEGATIVE,
+};
 use crate::frame::Frame;
 
+/// Parse the timeout of a blocking command, in seconds. 0 means no
+/// timeout.
+pub(crate) fn parse_timeout(arg: &[u8]) -> Result<f64, Frame> {
+    let s = String::from_utf8_lossy(arg);
+    let s_lower = s.to_lowercase();
+    if s_lower == "inf" || s_lower == "+inf" || s_lower == "-inf" {
+        return Err(Frame::error(MSG_TIMEOUT_IS_OUT_OF_RANGE));
+    }
+    match s.parse::<f64>() {
+        Ok(t) if t < 0.0 => Err(Frame::error(MSG_TIMEOUT_NEGATIVE)),
+        Ok(t) => Ok(t),
+        Err(_) => Err(Frame::error(MSG_INVALID_TIMEOUT)),
+    }
+}
+
+pub(crate) struct MpopOpts {
+    pub keys: Vec<Vec<u8>>,
+    /// True for the first of the two `where` words (LEFT, MIN).
+    pub first: bool,
+    pub count: usize,
+}
+
+/// Parse `numkeys key [key ...] <where> [COUNT count]`, the arguments of
+/// LMPOP and ZMPOP. `wheres` are the two accepted `where` words.
+pub(crate) fn parse_mpop(args: &[Vec<u8>], wheres: [&str; 2]) -> Result<MpopOpts, Frame> {
+    let numkeys = match args.first().and_then(|a| parse_int(a)) {
+        Some(n) if n > 0 => n as usize,
+        _ => return Err(Frame::error(MSG_NUMKEYS_NOT_POSITIVE)),
+    };
+    if numkeys >= args.len() - 1 {
+        return Err(Frame::error(MSG_SYNTAX_ERROR));
+    }
+    let keys = args[1..=numkeys].to_vec();
+    let first = match String::from_utf8_lossy(&args[numkeys + 1]).to_uppercase() {
+        w if w == wheres[0] => true,
+        w if w == wheres[1] => false,
+        _ => return Err(Frame::error(MSG_SYNTAX_ERROR)),
+    };
+
+    let mut count = None;
+    let mut rest = args[numkeys + 2..].iter();
+    while let Some(opt) = rest.next() {
+        if !opt.eq_ignore_ascii_case(b"COUNT") || count.is_some() {
+            return Err(Frame::error(MSG_SYNTAX_ERROR));
+        }
+        let Some(n) = rest.next() else {
+            return Err(Frame::error(MSG_SYNTAX_ERROR));
+        };
+        match parse_int(n) {
+            Some(n) if n > 0 => count = Some(n as usize),
+            _ => return Err(Frame::error(MSG_COUNT_NOT_POSITIVE)),
+        }
+    }
+
+    Ok(MpopOpts {
+        keys,
+        first,
+        count: count.unwrap_or(1),
+    })
+}
+
 pub(crate) struct ScanOpts {
-    pub pattern: Option<String>,
+    pub pattern: Option<Vec<u8>>,
     pub count: Option<i64>,
     pub type_filter: Option<String>,
 }
@@ -59,7 +131,7 @@ pub(crate) fn parse_scan_opts(args: &[Vec<u8>], allow_type: bool) -> Result<Scan
                 if i >= args.len() {
                     return Err(Frame::error(MSG_SYNTAX_ERROR));
                 }
-                pattern = Some(String::from_utf8_lossy(&args[i]).into_owned());
+                pattern = Some(args[i].clone());
             }
             "COUNT" => {
                 i += 1;
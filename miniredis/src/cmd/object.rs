@@ -16,11 +16,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error("ERR wrong number of arguments for 'object|encoding' command");
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            match db.keys.get(key.as_ref()) {
+            match db.keys.get(key.as_slice()) {
                 None => Frame::Null,
                 Some(kt) => {
                     let encoding = match kt {
@@ -31,6 +30,13 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                         crate::types::KeyType::SortedSet => "skiplist",
                         crate::types::KeyType::Stream => "stream",
                         crate::types::KeyType::HyperLogLog => "raw",
+                        #[cfg(feature = "json")]
+                        crate::types::KeyType::Json => "raw",
+                        #[cfg(feature = "bloom")]
+                        crate::types::KeyType::Bloom
+                        | crate::types::KeyType::Cuckoo
+                        | crate::types::KeyType::CountMinSketch
+                        | crate::types::KeyType::TopK => "raw",
                     };
                     Frame::Bulk(encoding.into())
                 }
@@ -40,11 +46,10 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error("ERR wrong number of arguments for 'object|refcount' command");
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            if !db.keys.contains_key(key.as_ref()) {
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::Null;
             }
             // Always return 1 (simplified)
@@ -54,31 +59,33 @@ fn cmd_object(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() != 2 {
                 return Frame::error("ERR wrong number of arguments for 'object|freq' command");
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            if !db.keys.contains_key(key.as_ref()) {
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::Null;
             }
-            // Always return 0 (simplified)
-            Frame::Integer(0)
+            // Access counts only matter to the LFU policies.
+            if !state.lock().maxmemory_policy.is_lfu() {
+                return Frame::Integer(0);
+            }
+            let freq = db.access.get(key.as_slice()).map_or(0, |a| a.freq);
+            Frame::Integer(freq as i64)
         }
         "IDLETIME" => {
             if args.len() != 2 {
                 return Frame::error("ERR wrong number of arguments for 'object|idletime' command");
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            if !db.keys.contains_key(key.as_ref()) {
+            if !db.keys.contains_key(key.as_slice()) {
                 return Frame::Null;
             }
 
-            match db.lru.get(key.as_ref()) {
+            match db.lru.get(key.as_slice()) {
                 Some(last_access) => {
-                    let now = inner.effective_now();
+                    let now = state.now();
                     let idle = now.duration_since(*last_access).unwrap_or_default();
                     Frame::Integer(idle.as_secs() as i64)
                 }
@@ -7,14 +7,17 @@ use crate::frame::Frame;
 
 pub fn register(table: &mut CommandTable) {
     table.add("PUBLISH", cmd_publish, false, 3);
+    table.add("SPUBLISH", cmd_spublish, false, 3);
     table.add("PUBSUB", cmd_pubsub, true, -2);
-    // SUBSCRIBE/PSUBSCRIBE/UNSUBSCRIBE/PUNSUBSCRIBE are normally handled in
-    // server.rs (outside dispatch). These are registered so they can be queued
-    // inside MULTI/EXEC.
+    // (P|S)SUBSCRIBE and (P|S)UNSUBSCRIBE are normally handled in server.rs
+    // (outside dispatch). These are registered so they can be queued inside
+    // MULTI/EXEC.
     table.add("SUBSCRIBE", cmd_subscribe, false, -2);
     table.add("PSUBSCRIBE", cmd_psubscribe, false, -2);
+    table.add("SSUBSCRIBE", cmd_ssubscribe, false, -2);
     table.add("UNSUBSCRIBE", cmd_unsubscribe, false, -1);
     table.add("PUNSUBSCRIBE", cmd_punsubscribe, false, -1);
+    table.add("SUNSUBSCRIBE", cmd_sunsubscribe, false, -1);
 }
 
 /// SUBSCRIBE channel [channel ...] — handler for MULTI/EXEC path.
@@ -60,6 +63,26 @@ fn cmd_psubscribe(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
     }
 }
 
+/// SSUBSCRIBE shardchannel [shardchannel ...] — handler for MULTI/EXEC path.
+fn cmd_ssubscribe(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let mut confirmations = Vec::new();
+    for arg in args {
+        let channel = String::from_utf8_lossy(arg).to_string();
+        ctx.pending_ssubscribe.push(channel.clone());
+        confirmations.push(Frame::Array(vec![
+            Frame::Bulk("ssubscribe".into()),
+            Frame::Bulk(channel.into()),
+            Frame::Integer(ctx.pending_ssubscribe.len() as i64),
+        ]));
+    }
+
+    if confirmations.len() == 1 {
+        confirmations.pop().unwrap()
+    } else {
+        Frame::Array(confirmations)
+    }
+}
+
 /// UNSUBSCRIBE [channel ...] — handler for MULTI/EXEC path.
 fn cmd_unsubscribe(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     if args.is_empty() {
@@ -124,6 +147,36 @@ fn cmd_punsubscribe(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8
     }
 }
 
+/// SUNSUBSCRIBE [shardchannel ...] — handler for MULTI/EXEC path.
+fn cmd_sunsubscribe(_state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if args.is_empty() {
+        // Unsubscribe from all pending shard channels
+        ctx.pending_ssubscribe.clear();
+        return Frame::Array(vec![
+            Frame::Bulk("sunsubscribe".into()),
+            Frame::Null,
+            Frame::Integer(0),
+        ]);
+    }
+
+    let mut confirmations = Vec::new();
+    for arg in args {
+        let channel = String::from_utf8_lossy(arg).to_string();
+        ctx.pending_ssubscribe.retain(|ch| *ch != channel);
+        confirmations.push(Frame::Array(vec![
+            Frame::Bulk("sunsubscribe".into()),
+            Frame::Bulk(channel.into()),
+            Frame::Integer(ctx.pending_ssubscribe.len() as i64),
+        ]));
+    }
+
+    if confirmations.len() == 1 {
+        confirmations.pop().unwrap()
+    } else {
+        Frame::Array(confirmations)
+    }
+}
+
 /// PUBLISH channel message
 fn cmd_publish(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let channel = String::from_utf8_lossy(&args[0]).to_string();
@@ -134,13 +187,25 @@ fn cmd_publish(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -
     Frame::Integer(count)
 }
 
-/// PUBSUB CHANNELS/NUMSUB/NUMPAT
+/// SPUBLISH shardchannel message
+fn cmd_spublish(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let channel = String::from_utf8_lossy(&args[0]).to_string();
+    let message = String::from_utf8_lossy(&args[1]).to_string();
+
+    let registry = state.pubsub.lock().unwrap();
+    Frame::Integer(registry.spublish(&channel, &message))
+}
+
+/// PUBSUB CHANNELS/NUMSUB/NUMPAT/SHARDCHANNELS/SHARDNUMSUB
 fn cmd_pubsub(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
     match subcmd.as_str() {
-        "CHANNELS" => {
+        "CHANNELS" | "SHARDCHANNELS" => {
             if args.len() > 2 {
-                return Frame::error(err_wrong_number("pubsub|channels"));
+                return Frame::error(err_wrong_number(&format!(
+                    "pubsub|{}",
+                    subcmd.to_lowercase()
+                )));
             }
             let pattern = if args.len() > 1 {
                 Some(String::from_utf8_lossy(&args[1]).to_string())
@@ -148,7 +213,11 @@ fn cmd_pubsub(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 None
             };
             let registry = state.pubsub.lock().unwrap();
-            let channels = registry.active_channels(pattern.as_deref());
+            let channels = if subcmd == "CHANNELS" {
+                registry.active_channels(pattern.as_deref())
+            } else {
+                registry.active_shard_channels(pattern.as_deref())
+            };
             Frame::Array(
                 channels
                     .into_iter()
@@ -156,12 +225,16 @@ fn cmd_pubsub(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                     .collect(),
             )
         }
-        "NUMSUB" => {
+        "NUMSUB" | "SHARDNUMSUB" => {
             let registry = state.pubsub.lock().unwrap();
             let mut result = Vec::new();
             for arg in &args[1..] {
                 let channel = String::from_utf8_lossy(arg).to_string();
-                let count = registry.numsub(&channel);
+                let count = if subcmd == "NUMSUB" {
+                    registry.numsub(&channel)
+                } else {
+                    registry.shard_numsub(&channel)
+                };
                 result.push(Frame::Bulk(channel.into()));
                 result.push(Frame::Integer(count));
             }
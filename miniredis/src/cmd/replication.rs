@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::dispatch::{CommandTable, MSG_INVALID_INT, MSG_SYNTAX_ERROR};
+use crate::frame::Frame;
+use crate::replication;
+
+pub fn register(table: &mut CommandTable) {
+    table.add("REPLICAOF", cmd_replicaof, false, 3);
+    table.add("SLAVEOF", cmd_replicaof, false, 3); // alias
+    table.add("ROLE", cmd_role, true, 1);
+}
+
+/// REPLICAOF host port | REPLICAOF NO ONE
+///
+/// The master has to be a `Miniredis` running in this process. Until it
+/// is, the replica keeps looking for it.
+fn cmd_replicaof(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if state.cluster.get().is_some() {
+        return Frame::error("ERR REPLICAOF not allowed in cluster mode.");
+    }
+    if args[0].eq_ignore_ascii_case(b"no") && args[1].eq_ignore_ascii_case(b"one") {
+        replication::promote(state);
+        return Frame::ok();
+    }
+
+    let host = String::from_utf8_lossy(&args[0]).to_string();
+    let port = match String::from_utf8_lossy(&args[1]).parse::<i64>() {
+        Ok(n) if (0..=65535).contains(&n) => n as u16,
+        Ok(_) => return Frame::error("ERR Invalid master port"),
+        Err(_) if args[0].eq_ignore_ascii_case(b"no") => return Frame::error(MSG_SYNTAX_ERROR),
+        Err(_) => return Frame::error(MSG_INVALID_INT),
+    };
+    if !replication::replicate(state, &host, port) {
+        return Frame::Simple("OK Already connected to specified master".into());
+    }
+    Frame::ok()
+}
+
+/// ROLE
+fn cmd_role(state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    replication::role(state)
+}
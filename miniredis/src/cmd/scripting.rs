@@ -1,15 +1,21 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
 
+use mlua::Variadic;
 use mlua::prelude::*;
 
+use crate::cmd::lua_libs;
 use crate::connection::ConnCtx;
 use crate::db::SharedState;
 use crate::dispatch::{
     CommandTable, MSG_INVALID_INT, MSG_INVALID_KEYS_NUMBER, MSG_NEGATIVE_KEYS_NUMBER,
-    MSG_NO_SCRIPT_FOUND, err_wrong_number,
+    MSG_NO_SCRIPT_FOUND, MSG_OOM, MSG_READONLY, check_acl, err_wrong_number,
 };
-use crate::frame::Frame;
+use crate::eviction::deny_oom;
+use crate::frame::{Frame, format_double};
+use crate::replication::rejects_writes;
+use crate::script::{LogLevel, MSG_SCRIPT_KILLED};
 
 pub fn register(table: &mut CommandTable) {
     table.add("EVAL", cmd_eval, false, -3);
@@ -32,7 +38,7 @@ fn sha1_hex(s: &str) -> String {
         .collect()
 }
 
-fn msg_not_from_scripts(sha: &str) -> String {
+pub(crate) fn msg_not_from_scripts(sha: &str) -> String {
     format!(
         "This Redis command is not allowed from script script: {}, &c",
         sha
@@ -50,7 +56,7 @@ fn err_lua_parse_error(err: &str) -> String {
 /// 1. Strip everything after the first `\n` (stack traces)
 /// 2. Strip mlua "runtime error: " prefix
 /// 3. Strip Lua source location prefix like `[string "..."]:N: `
-fn sanitize_lua_error(err: &str) -> String {
+pub(crate) fn sanitize_lua_error(err: &str) -> String {
     // Take only the first line
     let first_line = err.split('\n').next().unwrap_or(err);
     // Strip mlua "runtime error: " prefix
@@ -78,6 +84,7 @@ fn sanitize_lua_error(err: &str) -> String {
 
 /// Commands that are not allowed inside Lua scripts.
 const DISALLOWED_IN_SCRIPTS: &[&str] = &[
+    "MONITOR",
     "MULTI",
     "EXEC",
     "DISCARD",
@@ -86,6 +93,9 @@ const DISALLOWED_IN_SCRIPTS: &[&str] = &[
     "EVALSHA",
     "EVALSHA_RO",
     "SCRIPT",
+    "FCALL",
+    "FCALL_RO",
+    "FUNCTION",
     "AUTH",
     "WATCH",
     "UNWATCH",
@@ -97,9 +107,11 @@ const DISALLOWED_IN_SCRIPTS: &[&str] = &[
 
 // ── Frame <-> Lua value conversion ──────────────────────────────────
 
-/// Convert a Frame (Redis response) to a Lua value.
-fn frame_to_lua(lua: &Lua, frame: &Frame) -> LuaResult<LuaValue> {
+/// Convert a Frame (Redis response) to a Lua value, with the RESP2 or
+/// RESP3 rules (`redis.setresp()`).
+fn frame_to_lua(lua: &Lua, frame: &Frame, resp3: bool) -> LuaResult<LuaValue> {
     match frame {
+        Frame::Null | Frame::NullArray if resp3 => Ok(LuaValue::Nil),
         Frame::Null | Frame::NullArray => Ok(LuaValue::Boolean(false)),
         Frame::Integer(n) => Ok(LuaValue::Integer(*n)),
         Frame::Simple(s) => {
@@ -118,30 +130,54 @@ fn frame_to_lua(lua: &Lua, frame: &Frame) -> LuaResult<LuaValue> {
             tbl.set("err", msg.as_str())?;
             Ok(LuaValue::Table(tbl))
         }
+        Frame::Set(members) if resp3 => {
+            // {set={member=true, ...}}
+            let set = lua.create_table()?;
+            for member in members {
+                set.set(frame_to_lua(lua, member, resp3)?, true)?;
+            }
+            let tbl = lua.create_table()?;
+            tbl.set("set", set)?;
+            Ok(LuaValue::Table(tbl))
+        }
         Frame::Array(arr) | Frame::Set(arr) | Frame::Push(arr) => {
             let tbl = lua.create_table()?;
             for (i, item) in arr.iter().enumerate() {
-                let val = frame_to_lua(lua, item)?;
+                let val = frame_to_lua(lua, item, resp3)?;
                 tbl.set(i + 1, val)?;
             }
             Ok(LuaValue::Table(tbl))
         }
+        Frame::Map(pairs) if resp3 => {
+            // {map={field=value, ...}}
+            let map = lua.create_table()?;
+            for (k, v) in pairs {
+                map.set(frame_to_lua(lua, k, resp3)?, frame_to_lua(lua, v, resp3)?)?;
+            }
+            let tbl = lua.create_table()?;
+            tbl.set("map", map)?;
+            Ok(LuaValue::Table(tbl))
+        }
         Frame::Map(pairs) => {
+            // RESP2 flattens maps to field, value, field, value, ...
             let tbl = lua.create_table()?;
             for (i, (k, v)) in pairs.iter().enumerate() {
-                let kv_tbl = lua.create_table()?;
-                kv_tbl.set(1, frame_to_lua(lua, k)?)?;
-                kv_tbl.set(2, frame_to_lua(lua, v)?)?;
-                tbl.set(i + 1, kv_tbl)?;
+                tbl.set(2 * i + 1, frame_to_lua(lua, k, resp3)?)?;
+                tbl.set(2 * i + 2, frame_to_lua(lua, v, resp3)?)?;
             }
             Ok(LuaValue::Table(tbl))
         }
-        Frame::Double(f) => Ok(LuaValue::Number(*f)),
+        Frame::Double(f) if resp3 => {
+            let tbl = lua.create_table()?;
+            tbl.set("double", *f)?;
+            Ok(LuaValue::Table(tbl))
+        }
+        Frame::Double(f) => Ok(LuaValue::String(lua.create_string(format_double(*f))?)),
     }
 }
 
 /// Convert a Lua value to a Frame (Redis response).
-fn lua_to_frame(value: LuaValue) -> Frame {
+pub(crate) fn lua_to_frame(value: LuaValue) -> Frame {
     match value {
         LuaValue::Nil => Frame::Null,
         LuaValue::Boolean(b) => {
@@ -169,6 +205,29 @@ fn lua_to_frame(value: LuaValue) -> Frame {
                 let msg = String::from_utf8_lossy(&s.as_bytes()).to_string();
                 return Frame::Simple(msg);
             }
+            // RESP3 types: {double=n}, {map={...}} and {set={...}}
+            if let Ok(LuaValue::Number(n)) = tbl.get::<LuaValue>("double") {
+                return Frame::Double(n);
+            }
+            if let Ok(LuaValue::Integer(n)) = tbl.get::<LuaValue>("double") {
+                return Frame::Double(n as f64);
+            }
+            if let Ok(LuaValue::Table(map)) = tbl.get::<LuaValue>("map") {
+                return Frame::Map(
+                    map.pairs::<LuaValue, LuaValue>()
+                        .filter_map(Result::ok)
+                        .map(|(k, v)| (lua_to_frame(k), lua_to_frame(v)))
+                        .collect(),
+                );
+            }
+            if let Ok(LuaValue::Table(set)) = tbl.get::<LuaValue>("set") {
+                return Frame::Set(
+                    set.pairs::<LuaValue, LuaValue>()
+                        .filter_map(Result::ok)
+                        .map(|(member, _)| lua_to_frame(member))
+                        .collect(),
+                );
+            }
             // Numeric array
             let mut result = Vec::new();
             for i in 1.. {
@@ -499,16 +558,11 @@ fn json_parse_object(lua: &Lua, json: &str, pos: usize) -> LuaResult<(LuaValue,
 
 // ── Lua script execution ────────────────────────────────────────────
 
-/// Run a Lua script. Returns the response Frame.
-fn run_lua_script(
-    state: &Arc<SharedState>,
-    ctx: &mut ConnCtx,
-    sha: &str,
-    script: &str,
-    read_only: bool,
-    args: &[Vec<u8>],
-) -> Result<Frame, String> {
-    // Parse numkeys and split args into KEYS/ARGV
+/// KEYS and ARGV slices of a script invocation.
+pub(crate) type KeysAndArgs<'a> = (&'a [Vec<u8>], &'a [Vec<u8>]);
+
+/// Split `numkeys key [key ...] arg [arg ...]` into (KEYS, ARGV).
+pub(crate) fn split_numkeys(args: &[Vec<u8>]) -> Result<KeysAndArgs<'_>, String> {
     if args.is_empty() {
         return Err(MSG_INVALID_INT.to_string());
     }
@@ -524,12 +578,25 @@ fn run_lua_script(
     if numkeys > remaining.len() {
         return Err(MSG_INVALID_KEYS_NUMBER.to_string());
     }
+    Ok(remaining.split_at(numkeys))
+}
 
-    let keys = &remaining[..numkeys];
-    let argv = &remaining[numkeys..];
+/// Build a 1-based Lua array of byte strings (used for KEYS/ARGV).
+pub(crate) fn lua_string_array(lua: &Lua, items: &[Vec<u8>]) -> Result<LuaTable, String> {
+    let tbl = lua.create_table().map_err(|e| e.to_string())?;
+    for (i, item) in items.iter().enumerate() {
+        let s = lua
+            .create_string(item.as_slice())
+            .map_err(|e| e.to_string())?;
+        tbl.set(i + 1, s).map_err(|e| e.to_string())?;
+    }
+    Ok(tbl)
+}
 
-    // Create Lua state
+/// Create a fresh Lua state, sandboxed like Redis does.
+pub(crate) fn new_sandbox() -> Result<Lua, String> {
     let lua = Lua::new();
+    lua_libs::install(&lua).map_err(|e| e.to_string())?;
 
     // Set up global protection metatable to catch accesses to nonexistent globals.
     // This mimics Redis's behavior of erroring on undefined global variables.
@@ -568,25 +635,21 @@ fn run_lua_script(
     .exec()
     .map_err(|e| e.to_string())?;
 
-    // Set KEYS global
-    let keys_table = lua.create_table().map_err(|e| e.to_string())?;
-    for (i, k) in keys.iter().enumerate() {
-        let s = lua.create_string(k.as_slice()).map_err(|e| e.to_string())?;
-        keys_table.set(i + 1, s).map_err(|e| e.to_string())?;
-    }
-    lua.globals()
-        .set("KEYS", keys_table)
-        .map_err(|e| e.to_string())?;
+    Ok(lua)
+}
 
-    // Set ARGV global
-    let argv_table = lua.create_table().map_err(|e| e.to_string())?;
-    for (i, a) in argv.iter().enumerate() {
-        let s = lua.create_string(a.as_slice()).map_err(|e| e.to_string())?;
-        argv_table.set(i + 1, s).map_err(|e| e.to_string())?;
-    }
-    lua.globals()
-        .set("ARGV", argv_table)
-        .map_err(|e| e.to_string())?;
+/// Create a sandboxed Lua state with the `cjson` and `redis` modules installed.
+///
+/// `name` identifies the running code in error messages: the SHA for EVAL,
+/// the function name for FCALL. With `read_only` set, redis.call() rejects
+/// write commands.
+pub(crate) fn new_script_env(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    name: &str,
+    read_only: bool,
+) -> Result<Lua, String> {
+    let lua = new_sandbox()?;
 
     // Create cjson module
     let cjson_table = lua.create_table().map_err(|e| e.to_string())?;
@@ -641,20 +704,30 @@ fn run_lua_script(
     // Use an AtomicUsize to share selected_db between closures so that SELECT inside
     // a script persists for subsequent redis.call() invocations within the same script.
     let shared_selected_db = Arc::new(AtomicUsize::new(ctx.selected_db));
+    // The protocol redis.call() replies with, set by redis.setresp().
+    let resp3 = Arc::new(AtomicBool::new(false));
 
     // redis.call() and redis.pcall()
     let authenticated = ctx.authenticated;
+    let client_id = ctx.client_id;
     {
+        let user = ctx.user.clone();
+        let client_name = ctx.client_name.clone();
         let state_call = Arc::clone(state);
         let db_cell_call = Arc::clone(&shared_selected_db);
-        let sha_str = sha.to_string();
+        let resp3_call = Arc::clone(&resp3);
+        let sha_str = name.to_string();
         let call_fn = lua
             .create_function(move |lua_ctx, args: LuaMultiValue| {
                 redis_call_impl(
                     lua_ctx,
                     &state_call,
                     &db_cell_call,
+                    &resp3_call,
                     authenticated,
+                    &user,
+                    client_id,
+                    &client_name,
                     &sha_str,
                     true,
                     read_only,
@@ -667,16 +740,23 @@ fn run_lua_script(
             .map_err(|e| e.to_string())?;
     }
     {
+        let user = ctx.user.clone();
+        let client_name = ctx.client_name.clone();
         let state_pcall = Arc::clone(state);
         let db_cell_pcall = Arc::clone(&shared_selected_db);
-        let sha_str2 = sha.to_string();
+        let resp3_pcall = Arc::clone(&resp3);
+        let sha_str2 = name.to_string();
         let pcall_fn = lua
             .create_function(move |lua_ctx, args: LuaMultiValue| {
                 redis_call_impl(
                     lua_ctx,
                     &state_pcall,
                     &db_cell_pcall,
+                    &resp3_pcall,
                     authenticated,
+                    &user,
+                    client_id,
+                    &client_name,
                     &sha_str2,
                     false,
                     read_only,
@@ -706,6 +786,8 @@ fn run_lua_script(
                     ));
                 }
             };
+            // Error replies are a single line.
+            let s = s.replace(['\r', '\n'], " ");
             let parts: Vec<&str> = s.splitn(2, ' ').collect();
             let final_msg = if parts.len() == 2 {
                 let prefix = parts[0].strip_prefix('-').unwrap_or(parts[0]);
@@ -733,7 +815,9 @@ fn run_lua_script(
             }
             let arg = args.into_iter().next().unwrap();
             let msg = match arg {
-                LuaValue::String(s) => s,
+                LuaValue::String(s) => {
+                    String::from_utf8_lossy(&s.as_bytes()).replace(['\r', '\n'], " ")
+                }
                 _ => {
                     return Err(LuaError::RuntimeError(
                         "wrong number or type of arguments".to_string(),
@@ -749,10 +833,8 @@ fn run_lua_script(
         .set("status_reply", status_reply_fn)
         .map_err(|e| e.to_string())?;
 
-    // redis.log() - no-op
-    let log_fn = lua
-        .create_function(|_, (_level, _msg): (i32, LuaString)| Ok(()))
-        .map_err(|e| e.to_string())?;
+    // redis.log(level, message, ...)
+    let log_fn = log_function(&lua, state).map_err(|e| e.to_string())?;
     redis_table.set("log", log_fn).map_err(|e| e.to_string())?;
 
     // redis.sha1hex() - handle nil/non-string args (treat as empty string)
@@ -796,14 +878,15 @@ fn run_lua_script(
         .set("set_repl", set_repl_fn)
         .map_err(|e| e.to_string())?;
 
-    // redis.setresp() - validate arg (must be 2 or 3)
+    // redis.setresp() - the protocol of redis.call() replies (2 or 3)
     let setresp_fn = lua
-        .create_function(|_, version: i32| {
+        .create_function(move |_, version: i32| {
             if version != 2 && version != 3 {
                 return Err(LuaError::RuntimeError(
                     "RESP version must be 2 or 3.".to_string(),
                 ));
             }
+            resp3.store(version == 3, Ordering::Relaxed);
             Ok(LuaValue::Nil)
         })
         .map_err(|e| e.to_string())?;
@@ -838,6 +921,109 @@ fn run_lua_script(
         .set("redis", redis_table)
         .map_err(|e| e.to_string())?;
 
+    Ok(lua)
+}
+
+/// redis.log(): log the arguments, joined by spaces, to the script log.
+pub(crate) fn log_function(lua: &Lua, state: &Arc<SharedState>) -> LuaResult<LuaFunction> {
+    let state = Arc::clone(state);
+    lua.create_function(move |_, args: Variadic<LuaValue>| {
+        if args.len() < 2 {
+            return Err(LuaError::RuntimeError(
+                "redis.log() requires two arguments or more.".to_string(),
+            ));
+        }
+        let level = match &args[0] {
+            LuaValue::Integer(n) => *n,
+            LuaValue::Number(n) => *n as i64,
+            _ => {
+                return Err(LuaError::RuntimeError(
+                    "First argument must be a number".to_string(),
+                ));
+            }
+        };
+        let Some(level) = LogLevel::from_lua(level) else {
+            return Err(LuaError::RuntimeError("Invalid debug level.".to_string()));
+        };
+        let message = args[1..]
+            .iter()
+            .filter_map(|arg| match arg {
+                LuaValue::String(s) => Some(String::from_utf8_lossy(&s.as_bytes()).to_string()),
+                LuaValue::Integer(n) => Some(n.to_string()),
+                LuaValue::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        state.script.lock().unwrap().add_log(level, message);
+        Ok(())
+    })
+}
+
+/// Wraps a script so that an error raised with an error reply table,
+/// like `error(redis.error_reply("..."))` or `error({err="..."})`, ends the
+/// script with that error reply. Other errors pass through unchanged.
+const SCRIPT_RUNNER: &str = r#"
+    return function(f, ...)
+        local ok, res = pcall(f, ...)
+        if ok or (type(res) == "table" and type(res.err) == "string") then
+            return res
+        end
+        error(res, 0)
+    end
+"#;
+
+/// Instructions between checks for SCRIPT KILL.
+const KILL_CHECK_INSTRUCTIONS: u32 = 1000;
+
+/// Call a script or function `f` with `args`, until it returns or
+/// `killed` is set. A killed script fails with `killed_msg`.
+pub(crate) fn call_script(
+    lua: &Lua,
+    f: LuaFunction,
+    args: LuaMultiValue,
+    killed: Arc<AtomicBool>,
+    killed_msg: &'static str,
+) -> LuaResult<LuaValue> {
+    lua.set_hook(
+        LuaHookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+        move |_, _| {
+            if killed.load(Ordering::Relaxed) {
+                return Err(LuaError::RuntimeError(killed_msg.to_string()));
+            }
+            Ok(LuaVmState::Continue)
+        },
+    )?;
+    let runner: LuaFunction = lua.load(SCRIPT_RUNNER).eval()?;
+    let mut call_args = args;
+    call_args.push_front(LuaValue::Function(f));
+    runner.call(call_args)
+}
+
+/// Run a Lua script. Returns the response Frame.
+fn run_lua_script(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    sha: &str,
+    script: &str,
+    read_only: bool,
+    args: &[Vec<u8>],
+) -> Result<Frame, String> {
+    // Parse numkeys and split args into KEYS/ARGV
+    let (keys, argv) = split_numkeys(args)?;
+
+    let lua = new_script_env(state, ctx, sha, read_only)?;
+
+    // Set KEYS and ARGV globals
+    let keys_table = lua_string_array(&lua, keys)?;
+    lua.globals()
+        .set("KEYS", keys_table)
+        .map_err(|e| e.to_string())?;
+    let argv_table = lua_string_array(&lua, argv)?;
+    lua.globals()
+        .set("ARGV", argv_table)
+        .map_err(|e| e.to_string())?;
+
     // Execute the script: compile to a function, then call it.
     // This ensures that only explicit `return` statements produce return values
     // (unlike eval() which may try to prepend `return` to the code).
@@ -853,34 +1039,49 @@ fn run_lua_script(
         inner.scripts.insert(sha.to_string(), script.to_string());
     }
 
-    let result: LuaValue = match func.call(()) {
+    let guard = crate::script::start(state, ctx.client_id, sha, &[], false);
+    let result = match call_script(
+        &lua,
+        func,
+        LuaMultiValue::new(),
+        guard.killed(),
+        MSG_SCRIPT_KILLED,
+    ) {
         Ok(v) => v,
-        Err(e) => {
-            let msg = sanitize_lua_error(&e.to_string());
-            // Check if it looks like a Redis error (starts with ERR, WRONGTYPE, etc.)
-            if msg.starts_with("ERR ")
-                || msg.starts_with("WRONGTYPE ")
-                || msg.starts_with("NOSCRIPT ")
-                || msg.starts_with("NOGROUP ")
-                || msg.starts_with("BUSYKEY ")
-                || msg.contains("@user_script")
-            {
-                return Err(msg);
-            }
-            return Err(format!("ERR @user_script:0: {}", msg));
-        }
+        Err(e) => return Err(script_runtime_error(&e, "@user_script")),
     };
 
     Ok(lua_to_frame(result))
 }
 
+/// Map a Lua runtime error to the RESP error returned to the client.
+/// `origin` is the chunk name Redis reports (`@user_script`, `@user_function`).
+pub(crate) fn script_runtime_error(e: &LuaError, origin: &str) -> String {
+    let msg = sanitize_lua_error(&e.to_string());
+    // Check if it looks like a Redis error (starts with ERR, WRONGTYPE, etc.)
+    if msg.starts_with("ERR ")
+        || msg.starts_with("WRONGTYPE ")
+        || msg.starts_with("NOSCRIPT ")
+        || msg.starts_with("NOGROUP ")
+        || msg.starts_with("BUSYKEY ")
+        || msg.contains(origin)
+    {
+        return msg;
+    }
+    format!("ERR {}:0: {}", origin, msg)
+}
+
 /// Implementation of redis.call() / redis.pcall() within Lua.
 #[allow(clippy::too_many_arguments)]
 fn redis_call_impl(
     lua: &Lua,
     state: &Arc<SharedState>,
     selected_db_cell: &Arc<AtomicUsize>,
+    resp3: &AtomicBool,
     authenticated: bool,
+    user: &str,
+    client_id: u64,
+    client_name: &Option<String>,
     sha: &str,
     fail_fast: bool,
     read_only: bool,
@@ -956,7 +1157,13 @@ fn redis_call_impl(
 
     // Check read-only mode
     if read_only && !meta.read_only {
-        let msg = "Write commands are not allowed in read-only scripts";
+        // Scripts on a read-only replica run read-only too.
+        let msg =
+            if state.replication.lock().unwrap().is_replica() && state.lock().replica_read_only {
+                MSG_READONLY
+            } else {
+                "Write commands are not allowed in read-only scripts"
+            };
         if fail_fast {
             return Err(LuaError::RuntimeError(msg.to_string()));
         }
@@ -965,12 +1172,27 @@ fn redis_call_impl(
         return Ok(LuaValue::Table(tbl));
     }
 
+    // Scripts can't grow the dataset if memory was over maxmemory when
+    // they started.
+    if deny_oom(&cmd, cmd_args_rest) && state.lock().oom {
+        if fail_fast {
+            return Err(LuaError::RuntimeError(MSG_OOM.to_string()));
+        }
+        let tbl = lua.create_table()?;
+        tbl.set("err", MSG_OOM)?;
+        return Ok(LuaValue::Table(tbl));
+    }
+
     // Create a nested ConnCtx for this call
     let mut nested_ctx = ConnCtx::new();
     nested_ctx.selected_db = selected_db_cell.load(Ordering::Relaxed);
     nested_ctx.authenticated = authenticated;
+    nested_ctx.user = user.to_string();
     nested_ctx.nested = true;
     nested_ctx.nested_sha = Some(sha.to_string());
+    nested_ctx.client_id = client_id;
+    nested_ctx.client_name = client_name.clone();
+    nested_ctx.resp3 = resp3.load(Ordering::Relaxed);
 
     // Check arity before executing
     if meta.arity != 0 {
@@ -994,8 +1216,27 @@ fn redis_call_impl(
         }
     }
 
-    // Execute the command
-    let frame = (meta.handler)(state, &mut nested_ctx, cmd_args_rest);
+    // Execute the command, unless an injected fault or the ACL fails it
+    let fault = state.faults.lock().unwrap().check(client_id, &cmd);
+    let frame = match fault {
+        Some(err) => Frame::error(err),
+        None => match check_acl(state, &nested_ctx, &cmd, cmd_args_rest, "lua") {
+            Some(err) => {
+                crate::latency::reject(state, &cmd_args);
+                err
+            }
+            None => {
+                let start = Instant::now();
+                let frame = (meta.handler)(state, &mut nested_ctx, cmd_args_rest);
+                crate::latency::record(state, &nested_ctx, &cmd_args, &frame, start.elapsed());
+                crate::monitor::feed(state, &nested_ctx, &cmd_args, &frame);
+                if !meta.read_only && !matches!(frame, Frame::Error(_)) {
+                    crate::script::wrote(state, client_id);
+                }
+                frame
+            }
+        },
+    };
 
     // If this was a SELECT command, update the shared selected_db
     if cmd == "SELECT" && !matches!(&frame, Frame::Error(_)) {
@@ -1012,7 +1253,7 @@ fn redis_call_impl(
             tbl.set("err", msg.as_str())?;
             Ok(LuaValue::Table(tbl))
         }
-        _ => frame_to_lua(lua, &frame),
+        _ => frame_to_lua(lua, &frame, nested_ctx.resp3),
     }
 }
 
@@ -1041,6 +1282,7 @@ fn cmd_eval_shared(
     let script = String::from_utf8_lossy(&args[0]).to_string();
     let sha = sha1_hex(&script);
     let remaining = &args[1..];
+    let read_only = read_only || rejects_writes(state, ctx);
 
     match run_lua_script(state, ctx, &sha, &script, read_only, remaining) {
         Ok(frame) => frame,
@@ -1070,6 +1312,7 @@ fn cmd_evalsha_shared(
 
     let sha = String::from_utf8_lossy(&args[0]).to_string();
     let remaining = &args[1..];
+    let read_only = read_only || rejects_writes(state, ctx);
 
     // Look up the script
     let script = {
@@ -1148,6 +1391,12 @@ fn cmd_script(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             inner.scripts.clear();
             Frame::ok()
         }
+        "KILL" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number("script|kill"));
+            }
+            crate::script::kill(state, false)
+        }
         _ => Frame::error(format!(
             "ERR unknown subcommand '{}'. Try SCRIPT HELP.",
             subcmd
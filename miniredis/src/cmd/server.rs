@@ -3,10 +3,11 @@ use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{DB_COUNT, Dataset, Inner, SharedState};
 use crate::dispatch::{CommandTable, MSG_INVALID_INT, MSG_SYNTAX_ERROR, err_wrong_number};
+use crate::eviction::{EvictionPolicy, parse_memory, used_memory};
+use crate::fault::{Fault, FaultKind};
 use crate::frame::Frame;
-use crate::types::KeyType;
 
 /// Raw RESP blob for COMMAND response, captured from Redis 5.0.7.
 static COMMAND_RESP: &[u8] = include_bytes!("command_resp.bin");
@@ -20,13 +21,21 @@ pub fn register(table: &mut CommandTable) {
     table.add("INFO", cmd_info, true, -1);
     table.add("SWAPDB", cmd_swapdb, false, 3);
     table.add("MEMORY", cmd_memory, true, -2);
+    table.add("CONFIG", cmd_config, false, -2);
+    table.add("SAVE", cmd_save, true, 1);
+    table.add("BGSAVE", cmd_bgsave, true, -1);
+    table.add("LASTSAVE", cmd_lastsave, true, 1);
+    table.add("DEBUG", cmd_debug, false, -2);
     table.add("MINIREDIS.FASTFORWARD", cmd_fastforward, false, 2);
+    table.add("MINIREDIS.FAULT", cmd_fault, false, -2);
+    table.add("MONITOR", cmd_monitor, true, 1);
+    table.add("SLOWLOG", cmd_slowlog, true, -2);
+    table.add("LATENCY", cmd_latency, true, -2);
 }
 
 /// DBSIZE
 fn cmd_dbsize(state: &Arc<SharedState>, ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
     Frame::Integer(db.keys.len() as i64)
 }
 
@@ -41,8 +50,7 @@ fn cmd_flushdb(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             return Frame::error(MSG_SYNTAX_ERROR);
         }
     }
-    let mut inner = state.lock();
-    inner.db_mut(ctx.selected_db).flush();
+    state.db(ctx.selected_db).flush();
     Frame::ok()
 }
 
@@ -57,9 +65,8 @@ fn cmd_flushall(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>])
             return Frame::error(MSG_SYNTAX_ERROR);
         }
     }
-    let mut inner = state.lock();
-    for i in 0..16 {
-        inner.db_mut(i).flush();
+    for db in &state.dbs {
+        db.lock().unwrap().flush();
     }
     Frame::ok()
 }
@@ -133,8 +140,7 @@ fn parse_resp_int(data: &[u8], pos: &mut usize) -> i64 {
 
 /// TIME — returns [seconds, microseconds] of server time.
 fn cmd_time(state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
-    let inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
     let since_epoch = now
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default();
@@ -163,9 +169,22 @@ fn cmd_info(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         String::new()
     };
 
-    let want_all = section.is_empty();
-
-    if !want_all && section != "clients" && section != "stats" {
+    // Without a section INFO leaves out the per-command sections, like
+    // Redis' "default".
+    let want_default = section.is_empty() || section == "default";
+    let want_all = want_default || section == "all" || section == "everything";
+
+    if !want_all
+        && ![
+            "clients",
+            "memory",
+            "stats",
+            "replication",
+            "commandstats",
+            "latencystats",
+        ]
+        .contains(&section.as_str())
+    {
         return Frame::error(format!("ERR section ({}) is not supported", section));
     }
 
@@ -175,13 +194,63 @@ fn cmd_info(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         result.push_str(&format!("# Clients\r\nconnected_clients:{}\r\n", connected));
     }
 
+    if want_all || section == "memory" {
+        let mut data = state.lock_all();
+        result.push_str(&format!(
+            "# Memory\r\nused_memory:{}\r\nmaxmemory:{}\r\nmaxmemory_policy:{}\r\n",
+            used_memory(&mut data),
+            data.inner.maxmemory,
+            data.inner.maxmemory_policy.as_str()
+        ));
+    }
+
     if want_all || section == "stats" {
+        let evicted = state.lock().evicted_keys;
         result.push_str(&format!(
-            "# Stats\r\ntotal_connections_received:{}\r\ntotal_commands_processed:{}\r\n",
-            total_conn, total_cmds
+            "# Stats\r\ntotal_connections_received:{}\r\ntotal_commands_processed:{}\r\n\
+             evicted_keys:{}\r\n",
+            total_conn, total_cmds, evicted
         ));
     }
 
+    if want_all || section == "replication" {
+        result.push_str(&crate::replication::info(state));
+    }
+
+    if (want_all && !want_default) || section == "commandstats" {
+        result.push_str("# Commandstats\r\n");
+        for (name, stats) in state.latency.lock().unwrap().command_stats() {
+            if stats.calls == 0 && stats.rejected_calls == 0 {
+                continue;
+            }
+            let per_call = if stats.calls == 0 {
+                0.0
+            } else {
+                stats.usec as f64 / stats.calls as f64
+            };
+            result.push_str(&format!(
+                "cmdstat_{}:calls={},usec={},usec_per_call={:.2},rejected_calls={},failed_calls={}\r\n",
+                name, stats.calls, stats.usec, per_call, stats.rejected_calls, stats.failed_calls
+            ));
+        }
+    }
+
+    if (want_all && !want_default) || section == "latencystats" {
+        result.push_str("# Latencystats\r\n");
+        for (name, stats) in state.latency.lock().unwrap().command_stats() {
+            if stats.calls == 0 {
+                continue;
+            }
+            result.push_str(&format!(
+                "latency_percentiles_usec_{}:p50={:.3},p99={:.3},p99.9={:.3}\r\n",
+                name,
+                stats.percentile(50.0) as f64,
+                stats.percentile(99.0) as f64,
+                stats.percentile(99.9) as f64
+            ));
+        }
+    }
+
     Frame::Bulk(result.into())
 }
 
@@ -207,13 +276,277 @@ fn cmd_swapdb(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     let db2 = db2 as usize;
 
     if db1 != db2 {
-        let mut inner = state.lock();
-        inner.dbs.swap(db1, db2);
+        let (mut first, mut second) = state.db_pair(db1, db2);
+        std::mem::swap(&mut *first, &mut *second);
     }
 
     Frame::ok()
 }
 
+/// Parameters known to CONFIG GET/SET, in CONFIG GET output order.
+const CONFIG_PARAMS: &[&str] = &[
+    "busy-reply-threshold",
+    "databases",
+    "dbfilename",
+    "dir",
+    "latency-monitor-threshold",
+    "lua-time-limit",
+    "maxmemory",
+    "maxmemory-policy",
+    "notify-keyspace-events",
+    "replica-read-only",
+    "slowlog-log-slower-than",
+    "slowlog-max-len",
+];
+
+/// Current value of a config parameter.
+fn config_get(inner: &Inner, param: &str) -> Option<String> {
+    match param {
+        "busy-reply-threshold" | "lua-time-limit" => Some(inner.busy_reply_threshold.to_string()),
+        "databases" => Some(DB_COUNT.to_string()),
+        "dbfilename" => Some(inner.dbfilename.clone()),
+        "dir" => Some(inner.dir.clone()),
+        "latency-monitor-threshold" => Some(inner.latency_monitor_threshold.to_string()),
+        "maxmemory" => Some(inner.maxmemory.to_string()),
+        "maxmemory-policy" => Some(inner.maxmemory_policy.as_str().to_string()),
+        "notify-keyspace-events" => Some(crate::pubsub::keyspace_events_to_string(
+            inner.notify_keyspace_events,
+        )),
+        "replica-read-only" => Some(if inner.replica_read_only { "yes" } else { "no" }.to_string()),
+        "slowlog-log-slower-than" => Some(inner.slowlog_log_slower_than.to_string()),
+        "slowlog-max-len" => Some(inner.slowlog_max_len.to_string()),
+        _ => None,
+    }
+}
+
+/// Update a config parameter. The error is the reason shown after
+/// "CONFIG SET failed".
+fn config_set(data: &mut Dataset, param: &str, value: &str) -> Result<(), String> {
+    let inner = &mut data.inner;
+    match param {
+        "busy-reply-threshold" | "lua-time-limit" => match value.parse() {
+            Ok(ms) => {
+                inner.busy_reply_threshold = ms;
+                Ok(())
+            }
+            Err(_) => Err("argument couldn't be parsed into an integer".to_string()),
+        },
+        "databases" => Err("can't set immutable config".to_string()),
+        "dbfilename" => {
+            if value.contains('/') {
+                return Err("dbfilename can't be a path, just a filename".to_string());
+            }
+            inner.dbfilename = value.to_string();
+            Ok(())
+        }
+        "dir" => {
+            if !std::path::Path::new(value).is_dir() {
+                return Err("No such file or directory".to_string());
+            }
+            inner.dir = value.to_string();
+            Ok(())
+        }
+        "latency-monitor-threshold" => match value.parse() {
+            Ok(ms) => {
+                inner.latency_monitor_threshold = ms;
+                Ok(())
+            }
+            Err(_) => Err("argument couldn't be parsed into an integer".to_string()),
+        },
+        "maxmemory" => match parse_memory(value) {
+            Some(bytes) => {
+                data.set_maxmemory(bytes);
+                Ok(())
+            }
+            None => Err("argument must be a memory value".to_string()),
+        },
+        "maxmemory-policy" => match EvictionPolicy::parse(value) {
+            Some(policy) => {
+                inner.maxmemory_policy = policy;
+                Ok(())
+            }
+            None => Err(format!(
+                "argument(s) must be one of the following: {}",
+                EvictionPolicy::names()
+            )),
+        },
+        "notify-keyspace-events" => match crate::pubsub::parse_keyspace_events(value) {
+            Some(flags) => {
+                data.set_notify_keyspace_events(flags);
+                Ok(())
+            }
+            None => Err("Invalid event class character. Use 'Ag$lshzxeKEtmdn'.".to_string()),
+        },
+        "replica-read-only" => {
+            inner.replica_read_only = match value.to_lowercase().as_str() {
+                "yes" => true,
+                "no" => false,
+                _ => return Err("argument must be 'yes' or 'no'".to_string()),
+            };
+            Ok(())
+        }
+        "slowlog-log-slower-than" => match value.parse() {
+            Ok(micros) => {
+                inner.slowlog_log_slower_than = micros;
+                Ok(())
+            }
+            Err(_) => Err("argument couldn't be parsed into an integer".to_string()),
+        },
+        "slowlog-max-len" => match value.parse() {
+            Ok(len) => {
+                inner.slowlog_max_len = len;
+                Ok(())
+            }
+            Err(_) => Err("argument couldn't be parsed into an integer".to_string()),
+        },
+        _ => Err("unknown parameter".to_string()),
+    }
+}
+
+/// CONFIG GET parameter [parameter ...] | SET parameter value [parameter value ...] | RESETSTAT
+fn cmd_config(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let sub_args = &args[1..];
+    match subcmd.as_str() {
+        "GET" => {
+            if sub_args.is_empty() {
+                return Frame::error(err_wrong_number("config|get"));
+            }
+            let patterns: Vec<String> = sub_args
+                .iter()
+                .map(|p| String::from_utf8_lossy(p).to_lowercase())
+                .collect();
+            let inner = state.lock();
+            let pairs = CONFIG_PARAMS
+                .iter()
+                .filter(|name| patterns.iter().any(|p| crate::keys::glob_match(p, name)))
+                .filter_map(|name| {
+                    config_get(&inner, name)
+                        .map(|value| (Frame::bulk_string(name), Frame::Bulk(value.into())))
+                })
+                .collect();
+            Frame::Map(pairs)
+        }
+        "SET" => {
+            if sub_args.is_empty() || !sub_args.len().is_multiple_of(2) {
+                return Frame::error(err_wrong_number("config|set"));
+            }
+            let mut data = state.lock_all();
+            for pair in sub_args.chunks(2) {
+                let param = String::from_utf8_lossy(&pair[0]).to_lowercase();
+                if !CONFIG_PARAMS.contains(&param.as_str()) {
+                    return Frame::error(format!(
+                        "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                        param
+                    ));
+                }
+                let value = String::from_utf8_lossy(&pair[1]);
+                if let Err(reason) = config_set(&mut data, &param, &value) {
+                    return Frame::error(format!(
+                        "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                        param, reason
+                    ));
+                }
+            }
+            Frame::ok()
+        }
+        "RESETSTAT" | "REWRITE" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number(&format!(
+                    "config|{}",
+                    subcmd.to_lowercase()
+                )));
+            }
+            if subcmd == "RESETSTAT" {
+                state.latency.lock().unwrap().reset_stats();
+            }
+            Frame::ok()
+        }
+        _ => Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+            subcmd.to_lowercase()
+        )),
+    }
+}
+
+// ── Persistence ─────────────────────────────────────────────────────
+
+/// Write an RDB snapshot to `dir`/`dbfilename`.
+fn save(state: &Arc<SharedState>) -> Result<(), String> {
+    let (data, path) = {
+        let dataset = state.lock_all();
+        (crate::rdb::encode(&dataset), dataset.inner.rdb_path())
+    };
+    crate::rdb::write_file(&path, &data).map_err(|e| e.to_string())?;
+    state.lock().last_save = state.now();
+    Ok(())
+}
+
+/// SAVE
+fn cmd_save(state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    match save(state) {
+        Ok(()) => Frame::ok(),
+        Err(e) => Frame::error(format!("ERR Failed saving the DB: {}", e)),
+    }
+}
+
+/// BGSAVE [SCHEDULE]
+///
+/// The snapshot is written before replying, so it's complete as soon as the
+/// client sees the reply.
+fn cmd_bgsave(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    match args {
+        [] => {}
+        [opt] if opt.eq_ignore_ascii_case(b"SCHEDULE") => {}
+        _ => return Frame::error(MSG_SYNTAX_ERROR),
+    }
+    match save(state) {
+        Ok(()) => Frame::Simple("Background saving started".to_string()),
+        Err(e) => Frame::error(format!("ERR Failed saving the DB: {}", e)),
+    }
+}
+
+/// LASTSAVE
+fn cmd_lastsave(state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    let inner = state.lock();
+    let secs = inner
+        .last_save
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    Frame::Integer(secs as i64)
+}
+
+/// DEBUG RELOAD [NOSAVE]
+///
+/// Saves the RDB file and loads it again, or with NOSAVE only loads it.
+fn cmd_debug(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    if subcmd != "RELOAD" {
+        return Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try DEBUG HELP.",
+            subcmd.to_lowercase()
+        ));
+    }
+    let nosave = match &args[1..] {
+        [] => false,
+        [opt] if opt.eq_ignore_ascii_case(b"NOSAVE") => true,
+        _ => return Frame::error(MSG_SYNTAX_ERROR),
+    };
+    if !nosave && let Err(e) = save(state) {
+        return Frame::error(format!("ERR Error trying to save the DB: {}", e));
+    }
+    let mut dataset = state.lock_all();
+    let data = match std::fs::read(dataset.inner.rdb_path()) {
+        Ok(data) => data,
+        Err(e) => return Frame::error(format!("ERR Error trying to load the RDB dump: {}", e)),
+    };
+    match crate::rdb::load(&mut dataset, &data) {
+        Ok(()) => Frame::ok(),
+        Err(e) => Frame::error(format!("ERR Error trying to load the RDB dump: {}", e)),
+    }
+}
+
 /// MEMORY USAGE key
 fn cmd_memory(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
@@ -226,16 +559,12 @@ fn cmd_memory(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 return Frame::error(crate::dispatch::MSG_SYNTAX_ERROR);
             }
 
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            match db.keys.get(key.as_ref()) {
+            match db.estimate_key_size(key) {
                 None => Frame::Null,
-                Some(kt) => {
-                    let size = estimate_key_size(db, &key, *kt);
-                    Frame::Integer(size as i64)
-                }
+                Some(size) => Frame::Integer(size as i64),
             }
         }
         "HELP" => Frame::Array(vec![Frame::Bulk(
@@ -248,38 +577,141 @@ fn cmd_memory(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
 }
 
-/// Estimate the memory usage of a key in bytes (simplified).
-fn estimate_key_size(db: &crate::db::RedisDB, key: &str, kt: KeyType) -> usize {
-    let key_overhead = 16 + key.len(); // pointer + key string
-    let value_size = match kt {
-        KeyType::String => db.string_keys.get(key).map(|v| v.len() + 3).unwrap_or(0),
-        KeyType::Hash => db
-            .hash_keys
-            .get(key)
-            .map(|h| h.iter().map(|(f, v)| f.len() + v.len() + 16).sum::<usize>() + 16)
-            .unwrap_or(0),
-        KeyType::List => db
-            .list_keys
-            .get(key)
-            .map(|l| l.iter().map(|v| v.len() + 16).sum::<usize>() + 16)
-            .unwrap_or(0),
-        KeyType::Set => db
-            .set_keys
-            .get(key)
-            .map(|s| s.iter().map(|m| m.len() + 16).sum::<usize>() + 16)
-            .unwrap_or(0),
-        KeyType::SortedSet => db
-            .sorted_set_keys
-            .get(key)
-            .map(|ss| ss.card() * 32 + 16)
-            .unwrap_or(0),
-        KeyType::Stream => 64,
-        KeyType::HyperLogLog => {
-            // 16384 registers + overhead
-            16384 + 24
+/// MONITOR
+///
+/// The connection loop handles MONITOR; this only runs inside MULTI.
+fn cmd_monitor(_state: &Arc<SharedState>, _ctx: &mut ConnCtx, _args: &[Vec<u8>]) -> Frame {
+    Frame::error("ERR MONITOR isn't allowed for DENY BLOCKING client")
+}
+
+/// SLOWLOG GET [count] | LEN | RESET
+fn cmd_slowlog(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let sub_args = &args[1..];
+    match subcmd.as_str() {
+        "GET" => {
+            if sub_args.len() > 1 {
+                return Frame::error(err_wrong_number("slowlog|get"));
+            }
+            let count = match sub_args.first() {
+                None => 10,
+                Some(arg) => match String::from_utf8_lossy(arg).parse::<i64>() {
+                    Ok(-1) => usize::MAX,
+                    Ok(n) if n >= 0 => n as usize,
+                    Ok(_) => {
+                        return Frame::error("ERR count should be greater than or equal to -1");
+                    }
+                    Err(_) => return Frame::error(MSG_INVALID_INT),
+                },
+            };
+            let entries = state.latency.lock().unwrap().slowlog(count);
+            Frame::Array(entries.iter().map(|e| e.to_frame()).collect())
         }
-    };
-    key_overhead + value_size
+        "LEN" | "RESET" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number(&format!(
+                    "slowlog|{}",
+                    subcmd.to_lowercase()
+                )));
+            }
+            let mut latency = state.latency.lock().unwrap();
+            if subcmd == "LEN" {
+                Frame::Integer(latency.slowlog_len() as i64)
+            } else {
+                latency.slowlog_reset();
+                Frame::ok()
+            }
+        }
+        _ => Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try SLOWLOG HELP.",
+            subcmd.to_lowercase()
+        )),
+    }
+}
+
+/// LATENCY LATEST | HISTORY event | RESET [event ...] | HISTOGRAM [command ...]
+fn cmd_latency(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let subcmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let sub_args: Vec<String> = args[1..]
+        .iter()
+        .map(|a| String::from_utf8_lossy(a).to_lowercase())
+        .collect();
+    let mut latency = state.latency.lock().unwrap();
+    match subcmd.as_str() {
+        "LATEST" => {
+            if !sub_args.is_empty() {
+                return Frame::error(err_wrong_number("latency|latest"));
+            }
+            let events = latency
+                .events()
+                .iter()
+                .filter_map(|(name, event)| {
+                    let &(time, latest) = event.samples.back()?;
+                    Some(Frame::Array(vec![
+                        Frame::bulk_string(name),
+                        Frame::Integer(time as i64),
+                        Frame::Integer(latest as i64),
+                        Frame::Integer(event.max as i64),
+                    ]))
+                })
+                .collect();
+            Frame::Array(events)
+        }
+        "HISTORY" => {
+            if sub_args.len() != 1 {
+                return Frame::error(err_wrong_number("latency|history"));
+            }
+            let samples = latency
+                .events()
+                .get(&sub_args[0])
+                .map(|event| {
+                    event
+                        .samples
+                        .iter()
+                        .map(|&(time, ms)| {
+                            Frame::Array(vec![
+                                Frame::Integer(time as i64),
+                                Frame::Integer(ms as i64),
+                            ])
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Frame::Array(samples)
+        }
+        "RESET" => Frame::Integer(latency.reset_events(&sub_args) as i64),
+        "HISTOGRAM" => {
+            let histograms = latency
+                .command_stats()
+                .into_iter()
+                .filter(|(name, stats)| {
+                    stats.calls > 0 && (sub_args.is_empty() || sub_args.contains(name))
+                })
+                .map(|(name, stats)| {
+                    let buckets = stats
+                        .cumulative()
+                        .into_iter()
+                        .map(|(bound, n)| (Frame::Integer(bound as i64), Frame::Integer(n as i64)))
+                        .collect();
+                    (
+                        Frame::bulk_string(&name),
+                        Frame::Map(vec![
+                            (
+                                Frame::bulk_string("calls"),
+                                Frame::Integer(stats.calls as i64),
+                            ),
+                            (Frame::bulk_string("histogram_usec"), Frame::Map(buckets)),
+                        ]),
+                    )
+                })
+                .collect();
+            Frame::Map(histograms)
+        }
+        _ => Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try LATENCY HELP.",
+            subcmd.to_lowercase()
+        )),
+    }
 }
 
 /// MINIREDIS.FASTFORWARD <ms>
@@ -292,7 +724,120 @@ fn cmd_fastforward(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>
         Err(_) => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    inner.fast_forward(Duration::from_millis(ms));
+    state.fast_forward_locked(Duration::from_millis(ms));
     Frame::ok()
 }
+
+/// MINIREDIS.FAULT ADD <ERROR message | LATENCY ms [max-ms] | DROP> [MATCH pattern]... [TIMES n]
+/// MINIREDIS.FAULT DEL id
+/// MINIREDIS.FAULT LIST
+/// MINIREDIS.FAULT CLEAR
+///
+/// Manage injected faults, see `Miniredis::inject_fault`. ADD returns the
+/// ID of the new fault.
+fn cmd_fault(state: &Arc<SharedState>, _ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let sub = String::from_utf8_lossy(&args[0]).to_uppercase();
+    match sub.as_str() {
+        "ADD" => match parse_fault(&args[1..]) {
+            Ok(fault) => Frame::Integer(state.faults.lock().unwrap().add(fault) as i64),
+            Err(err) => Frame::error(err),
+        },
+        "DEL" => {
+            if args.len() != 2 {
+                return Frame::error(err_wrong_number("miniredis.fault|del"));
+            }
+            let id: u64 = match String::from_utf8_lossy(&args[1]).parse() {
+                Ok(n) => n,
+                Err(_) => return Frame::error(MSG_INVALID_INT),
+            };
+            Frame::Integer(state.faults.lock().unwrap().remove(id) as i64)
+        }
+        "LIST" => {
+            let faults = state.faults.lock().unwrap();
+            let items = faults
+                .list()
+                .iter()
+                .map(|(id, fault)| {
+                    let kind = match &fault.kind {
+                        FaultKind::Error(msg) => format!("error {}", msg),
+                        FaultKind::Latency { min, max } if min == max => {
+                            format!("latency {}", min.as_millis())
+                        }
+                        FaultKind::Latency { min, max } => {
+                            format!("latency {} {}", min.as_millis(), max.as_millis())
+                        }
+                        FaultKind::DropConnection => "drop".to_string(),
+                    };
+                    Frame::Array(vec![
+                        Frame::Integer(*id as i64),
+                        Frame::Bulk(kind.into()),
+                        Frame::Array(
+                            fault
+                                .commands
+                                .iter()
+                                .map(|p| Frame::Bulk(p.clone().into()))
+                                .collect(),
+                        ),
+                        Frame::Integer(fault.remaining.map_or(-1, |n| n as i64)),
+                    ])
+                })
+                .collect();
+            Frame::Array(items)
+        }
+        "CLEAR" => {
+            state.faults.lock().unwrap().clear();
+            Frame::ok()
+        }
+        _ => Frame::error(format!(
+            "ERR unknown subcommand '{}'. Try MINIREDIS.FAULT ADD|DEL|LIST|CLEAR.",
+            String::from_utf8_lossy(&args[0])
+        )),
+    }
+}
+
+fn parse_fault(args: &[Vec<u8>]) -> Result<Fault, String> {
+    let parse_ms = |arg: &Vec<u8>| -> Result<Duration, String> {
+        String::from_utf8_lossy(arg)
+            .parse()
+            .map(Duration::from_millis)
+            .map_err(|_| MSG_INVALID_INT.to_string())
+    };
+
+    let Some(kind) = args.first() else {
+        return Err(err_wrong_number("miniredis.fault|add"));
+    };
+    let (mut fault, mut i) = match String::from_utf8_lossy(kind).to_uppercase().as_str() {
+        "ERROR" => {
+            let msg = args.get(1).ok_or(MSG_SYNTAX_ERROR)?;
+            (Fault::error(String::from_utf8_lossy(msg)), 2)
+        }
+        "LATENCY" => {
+            let min = parse_ms(args.get(1).ok_or(MSG_SYNTAX_ERROR)?)?;
+            match args.get(2).map(parse_ms) {
+                Some(Ok(max)) => (Fault::random_latency(min, max), 3),
+                _ => (Fault::latency(min), 2),
+            }
+        }
+        "DROP" => (Fault::drop_connection(), 1),
+        _ => return Err(MSG_SYNTAX_ERROR.to_string()),
+    };
+
+    while i < args.len() {
+        let opt = String::from_utf8_lossy(&args[i]).to_uppercase();
+        let value = args.get(i + 1).ok_or(MSG_SYNTAX_ERROR)?;
+        match opt.as_str() {
+            "MATCH" => fault
+                .commands
+                .push(String::from_utf8_lossy(value).to_uppercase()),
+            "TIMES" => {
+                let n: u64 = String::from_utf8_lossy(value)
+                    .parse()
+                    .map_err(|_| MSG_INVALID_INT.to_string())?;
+                fault = fault.times(n);
+            }
+            _ => return Err(MSG_SYNTAX_ERROR.to_string()),
+        }
+        i += 2;
+    }
+    Ok(fault)
+}
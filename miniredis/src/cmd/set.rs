@@ -11,6 +11,7 @@ use crate::dispatch::{
     MSG_SYNTAX_ERROR, MSG_WRONG_TYPE,
 };
 use crate::frame::Frame;
+use crate::pubsub::{NOTIFY_GENERIC, NOTIFY_SET};
 use crate::types::KeyType;
 
 use super::parse_int;
@@ -37,10 +38,9 @@ pub fn register(table: &mut CommandTable) {
 
 /// SADD key member [member ...]
 fn cmd_sadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -49,21 +49,20 @@ fn cmd_sadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let members: Vec<String> = args[1..]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let members: Vec<Vec<u8>> = args[1..].to_vec();
 
     let added = db.set_add(&key, &members, now);
+    if added > 0 {
+        db.notify(NOTIFY_SET, "sadd", &key);
+    }
     Frame::Integer(added)
 }
 
 /// SREM key member [member ...]
 fn cmd_srem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -76,46 +75,49 @@ fn cmd_srem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::Integer(0);
     }
 
-    let members: Vec<String> = args[1..]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let members: Vec<Vec<u8>> = args[1..].to_vec();
 
     let removed = db.set_rem(&key, &members, now);
+    if removed > 0 {
+        db.notify(NOTIFY_SET, "srem", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(removed)
 }
 
 /// SCARD key
 fn cmd_scard(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Set
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let count = db.set_keys.get(key.as_ref()).map(|s| s.len()).unwrap_or(0);
+    let count = db
+        .set_keys
+        .get(key.as_slice())
+        .map(|s| s.len())
+        .unwrap_or(0);
     Frame::Integer(count as i64)
 }
 
 /// SMEMBERS key
 fn cmd_smembers(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Set
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let members = db.set_members(&key);
+    let members = db.set_members(key);
     let items: Vec<Frame> = members.into_iter().map(|m| Frame::Bulk(m.into())).collect();
 
     if ctx.resp3 {
@@ -127,20 +129,19 @@ fn cmd_smembers(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
 
 /// SISMEMBER key member
 fn cmd_sismember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let member = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let member = &args[1];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Set
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    if db.set_is_member(&key, &member) {
+    if db.set_is_member(key, member) {
         Frame::Integer(1)
     } else {
         Frame::Integer(0)
@@ -149,12 +150,11 @@ fn cmd_sismember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
 
 /// SMISMEMBER key member [member ...]
 fn cmd_smismember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::Set
     {
         return Frame::error(MSG_WRONG_TYPE);
@@ -163,8 +163,8 @@ fn cmd_smismember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
     let results: Vec<Frame> = args[1..]
         .iter()
         .map(|a| {
-            let member = String::from_utf8_lossy(a);
-            if db.set_is_member(&key, &member) {
+            let member = a;
+            if db.set_is_member(key, member) {
                 Frame::Integer(1)
             } else {
                 Frame::Integer(0)
@@ -186,11 +186,10 @@ enum SetOp {
 fn set_op(
     state: &Arc<SharedState>,
     ctx: &mut ConnCtx,
-    keys: &[String],
+    keys: &[Vec<u8>],
     op: SetOp,
-) -> Result<HashSet<String>, Frame> {
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+) -> Result<HashSet<Vec<u8>>, Frame> {
+    let db = state.db(ctx.selected_db);
 
     for key in keys {
         if let Some(t) = db.key_type(key)
@@ -207,7 +206,7 @@ fn set_op(
     match op {
         SetOp::Diff => {
             let first = db.set_keys.get(&keys[0]).cloned().unwrap_or_default();
-            let mut result: HashSet<String> = first;
+            let mut result: HashSet<Vec<u8>> = first;
             for key in &keys[1..] {
                 if let Some(other) = db.set_keys.get(key) {
                     result = result.difference(other).cloned().collect();
@@ -222,7 +221,7 @@ fn set_op(
                 }
             }
             let first = db.set_keys.get(&keys[0]).cloned().unwrap_or_default();
-            let mut result: HashSet<String> = first;
+            let mut result: HashSet<Vec<u8>> = first;
             for key in &keys[1..] {
                 if let Some(other) = db.set_keys.get(key) {
                     result = result.intersection(other).cloned().collect();
@@ -244,8 +243,8 @@ fn set_op(
     }
 }
 
-fn set_to_frame(set: &HashSet<String>, resp3: bool) -> Frame {
-    let mut members: Vec<String> = set.iter().cloned().collect();
+fn set_to_frame(set: &HashSet<Vec<u8>>, resp3: bool) -> Frame {
+    let mut members: Vec<Vec<u8>> = set.iter().cloned().collect();
     members.sort();
     let items: Vec<Frame> = members.into_iter().map(|m| Frame::Bulk(m.into())).collect();
     if resp3 {
@@ -256,10 +255,7 @@ fn set_to_frame(set: &HashSet<String>, resp3: bool) -> Frame {
 }
 
 fn cmd_set_op(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], op: SetOp) -> Frame {
-    let keys: Vec<String> = args
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let keys: Vec<Vec<u8>> = args.to_vec();
     match set_op(state, ctx, &keys, op) {
         Ok(set) => set_to_frame(&set, ctx.resp3),
         Err(e) => e,
@@ -272,22 +268,27 @@ fn cmd_set_store(
     args: &[Vec<u8>],
     op: SetOp,
 ) -> Frame {
-    let dest = String::from_utf8_lossy(&args[0]).into_owned();
-    let keys: Vec<String> = args[1..]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let dest = args[0].clone();
+    let keys: Vec<Vec<u8>> = args[1..].to_vec();
+    let event = match op {
+        SetOp::Diff => "sdiffstore",
+        SetOp::Inter => "sinterstore",
+        SetOp::Union => "sunionstore",
+    };
     let result = match set_op(state, ctx, &keys, op) {
         Ok(set) => set,
         Err(e) => return e,
     };
     let count = result.len() as i64;
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.del(&dest);
-    if !result.is_empty() {
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    if result.is_empty() {
+        if db.del(&dest) {
+            db.notify(NOTIFY_GENERIC, "del", &dest);
+        }
+    } else {
         db.set_set(&dest, result, now);
+        db.notify(NOTIFY_SET, event, &dest);
     }
     Frame::Integer(count)
 }
@@ -324,13 +325,12 @@ fn cmd_sunionstore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
 
 /// SMOVE source destination member
 fn cmd_smove(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let src = String::from_utf8_lossy(&args[0]).into_owned();
-    let dst = String::from_utf8_lossy(&args[1]).into_owned();
-    let member = String::from_utf8_lossy(&args[2]).into_owned();
+    let src = args[0].clone();
+    let dst = args[1].clone();
+    let member = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&src);
     db.check_ttl(&dst);
 
@@ -355,13 +355,16 @@ fn cmd_smove(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     }
 
     db.set_rem(&src, std::slice::from_ref(&member), now);
+    db.notify(NOTIFY_SET, "srem", &src);
+    db.notify_if_deleted(&src);
     db.set_add(&dst, std::slice::from_ref(&member), now);
+    db.notify(NOTIFY_SET, "sadd", &dst);
     Frame::Integer(1)
 }
 
 /// SPOP key [count]
 fn cmd_spop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let mut with_count = false;
     let mut count: usize = 1;
 
@@ -379,9 +382,8 @@ fn cmd_spop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(MSG_INVALID_INT);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -404,12 +406,15 @@ fn cmd_spop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         if members.is_empty() {
             break;
         }
-        let idx = inner.rng.random_range(0..members.len());
+        let idx = state.lock().rng.random_range(0..members.len());
         let member = members.remove(idx);
-        let db = inner.db_mut(ctx.selected_db);
         db.set_rem(&key, std::slice::from_ref(&member), now);
         deleted.push(member);
     }
+    if !deleted.is_empty() {
+        db.notify(NOTIFY_SET, "spop", &key);
+        db.notify_if_deleted(&key);
+    }
 
     if !with_count {
         if deleted.is_empty() {
@@ -428,7 +433,7 @@ fn cmd_srandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let mut count: i64 = 0;
     let mut with_count = false;
 
@@ -442,9 +447,8 @@ fn cmd_srandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(&key);
 
     if !db.keys.contains_key(&key) {
         return if with_count {
@@ -467,14 +471,14 @@ fn cmd_srandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         let abs_count = (-count) as usize;
         let mut result = Vec::with_capacity(abs_count);
         for _ in 0..abs_count {
-            let idx = inner.rng.random_range(0..members.len());
+            let idx = state.lock().rng.random_range(0..members.len());
             result.push(Frame::Bulk(members[idx].clone().into()));
         }
         return Frame::Array(result);
     }
 
     // Positive count: unique members, shuffle
-    members.shuffle(&mut inner.rng);
+    members.shuffle(&mut state.lock().rng);
     let take = (count as usize).min(members.len());
 
     if !with_count {
@@ -491,7 +495,7 @@ fn cmd_srandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
 
 /// SSCAN key cursor [MATCH pattern] [COUNT count]
 fn cmd_sscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let cursor: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_CURSOR),
@@ -510,17 +514,16 @@ fn cmd_sscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         None => 0,
     };
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
-    if db.keys.contains_key(key.as_ref())
-        && let Some(t) = db.key_type(&key)
+    if db.keys.contains_key(key.as_slice())
+        && let Some(t) = db.key_type(key)
         && t != KeyType::Set
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let mut members = db.set_members(&key);
+    let mut members = db.set_members(key);
     members.sort();
 
     // Apply MATCH filter
@@ -569,10 +572,7 @@ fn cmd_sintercard(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         return Frame::error(MSG_INVALID_KEYS_NUMBER);
     }
 
-    let keys: Vec<String> = args[1..1 + num_keys]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let keys: Vec<Vec<u8>> = args[1..1 + num_keys].to_vec();
 
     let mut limit: usize = 0;
     let rest = &args[1 + num_keys..];
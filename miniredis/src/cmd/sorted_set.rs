@@ -3,17 +3,20 @@ use std::sync::Arc;
 use rand::Rng;
 use rand::seq::SliceRandom;
 
-use super::{parse_float, parse_int};
+use std::time::SystemTime;
+
+use super::{parse_float, parse_int, parse_mpop, parse_timeout};
 use crate::cmd::string::format_float;
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{RedisDB, SharedState};
 use crate::dispatch::{
     CommandTable, MSG_INVALID_CURSOR, MSG_INVALID_FLOAT, MSG_INVALID_INT, MSG_INVALID_MIN_MAX,
     MSG_INVALID_RANGE_ITEM, MSG_SINGLE_ELEMENT_PAIR, MSG_SYNTAX_ERROR, MSG_WRONG_TYPE,
     MSG_XX_AND_NX, err_wrong_number,
 };
 use crate::frame::Frame;
-use crate::types::{Direction, SSElem, SortedSet};
+use crate::pubsub::{NOTIFY_GENERIC, NOTIFY_ZSET};
+use crate::types::{Direction, LexBound, LexRange, SSElem, ScoreRange, SortedSet};
 
 pub fn register(table: &mut CommandTable) {
     table.add("ZADD", cmd_zadd, false, -4);
@@ -39,6 +42,11 @@ pub fn register(table: &mut CommandTable) {
     table.add("ZINTERSTORE", cmd_zinterstore, false, -4);
     table.add("ZPOPMIN", cmd_zpopmin, false, -2);
     table.add("ZPOPMAX", cmd_zpopmax, false, -2);
+    table.add("ZMPOP", cmd_zmpop, false, -4);
+    // Blocking commands: registered for MULTI/EXEC queueing (non-blocking attempt)
+    table.add("BZPOPMIN", cmd_bzpopmin, false, -3);
+    table.add("BZPOPMAX", cmd_bzpopmax, false, -3);
+    table.add("BZMPOP", cmd_bzmpop, false, -5);
     table.add("ZSCAN", cmd_zscan, true, -3);
     table.add("ZINTER", cmd_zinter, true, -3);
     table.add("ZUNION", cmd_zunion, true, -3);
@@ -74,81 +82,49 @@ fn parse_float_range(s: &str) -> Result<(f64, bool), ()> {
 }
 
 /// Parse a lex range like "[a", "(a", "+", "-".
-fn parse_lex_range(s: &str) -> Result<(String, bool), ()> {
-    if s.is_empty() {
-        return Err(());
-    }
-    if s == "+" || s == "-" {
-        return Ok((s.to_string(), false));
-    }
-    match s.as_bytes()[0] {
-        b'(' => Ok((s[1..].to_string(), false)),
-        b'[' => Ok((s[1..].to_string(), true)),
+fn parse_lex_range(s: &[u8]) -> Result<LexBound, ()> {
+    match s {
+        b"-" => Ok(LexBound::Min),
+        b"+" => Ok(LexBound::Max),
+        [b'(', rest @ ..] => Ok(LexBound::Excl(rest.to_vec())),
+        [b'[', rest @ ..] => Ok(LexBound::Incl(rest.to_vec())),
         _ => Err(()),
     }
 }
 
-/// Filter elements by score range.
-fn with_ss_range(
-    members: Vec<SSElem>,
-    min: f64,
-    min_incl: bool,
-    max: f64,
-    max_incl: bool,
-) -> Vec<SSElem> {
-    members
-        .into_iter()
-        .filter(|e| {
-            let above_min = if min_incl {
-                e.score >= min
-            } else {
-                e.score > min
-            };
-            let below_max = if max_incl {
-                e.score <= max
-            } else {
-                e.score < max
-            };
-            above_min && below_max
-        })
-        .collect()
+/// Parse the min and max arguments of ZCOUNT, ZRANGEBYSCORE, etc.
+fn parse_score_range(min: &[u8], max: &[u8]) -> Result<ScoreRange, Frame> {
+    let (min, min_incl) = parse_float_range(&String::from_utf8_lossy(min))
+        .map_err(|_| Frame::error(MSG_INVALID_MIN_MAX))?;
+    let (max, max_incl) = parse_float_range(&String::from_utf8_lossy(max))
+        .map_err(|_| Frame::error(MSG_INVALID_MIN_MAX))?;
+    Ok(ScoreRange {
+        min,
+        min_incl,
+        max,
+        max_incl,
+    })
 }
 
-/// Filter member names by lex range.
-fn with_lex_range(
-    members: Vec<String>,
-    min: &str,
-    min_incl: bool,
-    max: &str,
-    max_incl: bool,
-) -> Vec<String> {
-    if max == "-" || min == "+" {
-        return Vec::new();
-    }
-    members
-        .into_iter()
-        .filter(|m| {
-            let above_min = if min == "-" {
-                true
-            } else if min_incl {
-                m.as_str() >= min
-            } else {
-                m.as_str() > min
-            };
-            let below_max = if max == "+" {
-                true
-            } else if max_incl {
-                m.as_str() <= max
-            } else {
-                m.as_str() < max
-            };
-            above_min && below_max
-        })
-        .collect()
+/// Parse the min and max arguments of ZLEXCOUNT, ZRANGEBYLEX, etc.
+fn parse_lex_ranges(min: &[u8], max: &[u8]) -> Result<LexRange, Frame> {
+    let min = parse_lex_range(min).map_err(|_| Frame::error(MSG_INVALID_RANGE_ITEM))?;
+    let max = parse_lex_range(max).map_err(|_| Frame::error(MSG_INVALID_RANGE_ITEM))?;
+    Ok(LexRange { min, max })
+}
+
+/// Parse LIMIT offset and count. A negative offset selects nothing, a
+/// negative count means no limit.
+fn parse_limit(offset_s: &str, count_s: &str) -> Result<(Option<usize>, Option<usize>), Frame> {
+    let offset: i64 = offset_s
+        .parse()
+        .map_err(|_| Frame::error(MSG_INVALID_INT))?;
+    let count: i64 = count_s.parse().map_err(|_| Frame::error(MSG_INVALID_INT))?;
+    Ok((usize::try_from(offset).ok(), usize::try_from(count).ok()))
 }
 
 /// Normalize Redis-style range indices for sorted sets.
-fn redis_range(len: usize, start: i64, stop: i64) -> (usize, usize) {
+pub(crate) fn redis_range(len: usize, start: i64, stop: i64) -> (usize, usize) {
     let len = len as i64;
     let mut s = if start < 0 { len + start } else { start };
     let mut e = if stop < 0 { len + stop } else { stop };
@@ -168,7 +144,7 @@ fn redis_range(len: usize, start: i64, stop: i64) -> (usize, usize) {
 
 /// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]
 fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let mut i = 1;
     let mut nx = false;
     let mut xx = false;
@@ -216,14 +192,14 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
     }
 
     // Parse score-member pairs
-    let mut elems: Vec<(String, f64)> = Vec::new();
+    let mut elems: Vec<(Vec<u8>, f64)> = Vec::new();
     let mut j = 0;
     while j < remaining.len() {
         let score = match parse_float(&remaining[j]) {
             Some(f) => f,
             None => return Frame::error(MSG_INVALID_FLOAT),
         };
-        let member = String::from_utf8_lossy(&remaining[j + 1]).into_owned();
+        let member = remaining[j + 1].clone();
         elems.push((member, score));
         j += 2;
     }
@@ -239,9 +215,8 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(MSG_SINGLE_ELEMENT_PAIR);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -260,6 +235,7 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
             return Frame::Null;
         }
         let new_score = db.sset_incrby(&key, member, *delta, now);
+        db.notify(NOTIFY_ZSET, "zincr", &key);
         return if ctx.resp3 {
             Frame::Double(new_score)
         } else {
@@ -268,6 +244,7 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
     }
 
     let mut count = 0i64;
+    let mut changed = false;
     for (member, score) in &elems {
         let exists = db.sset_exists(&key, member);
         if nx && exists {
@@ -295,6 +272,10 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         if is_new || (ch && old_score != Some(*score)) {
             count += 1;
         }
+        changed |= is_new || old_score != Some(*score);
+    }
+    if changed {
+        db.notify(NOTIFY_ZSET, "zadd", &key);
     }
 
     Frame::Integer(count)
@@ -302,72 +283,60 @@ fn cmd_zadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// ZCARD key
 fn cmd_zcard(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(0);
     }
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    Frame::Integer(db.sset_card(&key) as i64)
+    Frame::Integer(db.sset_card(key) as i64)
 }
 
 /// ZCOUNT key min max
 fn cmd_zcount(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let min_s = String::from_utf8_lossy(&args[1]);
-    let max_s = String::from_utf8_lossy(&args[2]);
-
-    let (min, min_incl) = match parse_float_range(&min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
-    };
-    let (max, max_incl) = match parse_float_range(&max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
+    let key = &args[0];
+    let range = match parse_score_range(&args[1], &args[2]) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(0);
     }
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let ss = match db.sorted_set_keys.get(key.as_ref()) {
+    let ss = match db.sorted_set_keys.get(key.as_slice()) {
         Some(ss) => ss,
         None => return Frame::Integer(0),
     };
-    let elems = ss.by_score(Direction::Asc);
-    let filtered = with_ss_range(elems, min, min_incl, max, max_incl);
-    Frame::Integer(filtered.len() as i64)
+    Frame::Integer(ss.count_by_score(&range) as i64)
 }
 
 /// ZINCRBY key increment member
 fn cmd_zincrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let delta = match parse_float(&args[1]) {
         Some(f) => f,
         None => return Frame::error(MSG_INVALID_FLOAT),
     };
-    let member = String::from_utf8_lossy(&args[2]).into_owned();
+    let member = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -377,28 +346,28 @@ fn cmd_zincrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
 
     let new_score = db.sset_incrby(&key, &member, delta, now);
+    db.notify(NOTIFY_ZSET, "zincr", &key);
     Frame::Bulk(write_float(new_score).into())
 }
 
 /// ZSCORE key member
 fn cmd_zscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let member = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let member = &args[1];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Null;
     }
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.sset_score(&key, &member) {
+    match db.sset_score(key, member) {
         Some(score) => {
             if ctx.resp3 {
                 Frame::Double(score)
@@ -412,13 +381,12 @@ fn cmd_zscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// ZMSCORE key member [member ...]
 fn cmd_zmscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
@@ -427,8 +395,8 @@ fn cmd_zmscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     let results: Vec<Frame> = args[1..]
         .iter()
         .map(|a| {
-            let member = String::from_utf8_lossy(a);
-            match db.sset_score(&key, &member) {
+            let member = a;
+            match db.sset_score(key, member) {
                 Some(score) => Frame::Bulk(write_float(score).into()),
                 None => Frame::Null,
             }
@@ -459,8 +427,8 @@ fn zrank_impl(
         return Frame::error(err_wrong_number(cmd));
     }
 
-    let key = String::from_utf8_lossy(&args[0]);
-    let member = String::from_utf8_lossy(&args[1]);
+    let key = &args[0];
+    let member = &args[1];
 
     let with_score =
         args.len() == 3 && String::from_utf8_lossy(&args[2]).to_uppercase() == "WITHSCORE";
@@ -469,24 +437,23 @@ fn zrank_impl(
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return if with_score {
             Frame::NullArray
         } else {
             Frame::Null
         };
     }
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let ss = match db.sorted_set_keys.get(key.as_ref()) {
+    let ss = match db.sorted_set_keys.get(key.as_slice()) {
         Some(ss) => ss,
         None => {
             return if with_score {
@@ -497,10 +464,10 @@ fn zrank_impl(
         }
     };
 
-    match ss.rank(&member, dir) {
+    match ss.rank(member, dir) {
         Some(rank) => {
             if with_score {
-                let score = ss.get(&member).unwrap_or(0.0);
+                let score = ss.get(member).unwrap_or(0.0);
                 Frame::Array(vec![
                     Frame::Integer(rank as i64),
                     Frame::Bulk(write_float(score).into()),
@@ -521,10 +488,9 @@ fn zrank_impl(
 
 /// ZREM key member [member ...]
 fn cmd_zrem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -538,11 +504,15 @@ fn cmd_zrem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
     let mut deleted = 0i64;
     for a in &args[1..] {
-        let member = String::from_utf8_lossy(a);
-        if db.sset_rem(&key, &member, now) {
+        let member = a;
+        if db.sset_rem(&key, member, now) {
             deleted += 1;
         }
     }
+    if deleted > 0 {
+        db.notify(NOTIFY_ZSET, "zrem", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(deleted)
 }
 
@@ -550,7 +520,7 @@ fn cmd_zrem(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// ZRANGE key min max [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]
 fn cmd_zrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let min_s = String::from_utf8_lossy(&args[1]).into_owned();
     let max_s = String::from_utf8_lossy(&args[2]).into_owned();
 
@@ -600,16 +570,15 @@ fn cmd_zrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(&key);
 
     if by_score {
         run_range_by_score(
-            db,
+            &db,
             &key,
-            &min_s,
-            &max_s,
+            &args[1],
+            &args[2],
             reverse,
             with_limit,
             &offset_s,
@@ -618,7 +587,7 @@ fn cmd_zrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         )
     } else if by_lex {
         run_range_by_lex(
-            db, &key, &min_s, &max_s, reverse, with_limit, &offset_s, &count_s,
+            &db, &key, &args[1], &args[2], reverse, with_limit, &offset_s, &count_s,
         )
     } else {
         if with_limit {
@@ -626,13 +595,13 @@ fn cmd_zrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
             );
         }
-        run_range_by_rank(db, &key, &min_s, &max_s, reverse, with_scores)
+        run_range_by_rank(&db, &key, &min_s, &max_s, reverse, with_scores)
     }
 }
 
 /// ZREVRANGE key start stop [WITHSCORES]
 fn cmd_zrevrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let min_s = String::from_utf8_lossy(&args[1]).into_owned();
     let max_s = String::from_utf8_lossy(&args[2]).into_owned();
 
@@ -646,11 +615,10 @@ fn cmd_zrevrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(&key);
 
-    run_range_by_rank(db, &key, &min_s, &max_s, true, with_scores)
+    run_range_by_rank(&db, &key, &min_s, &max_s, true, with_scores)
 }
 
 /// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
@@ -669,9 +637,7 @@ fn zrangebyscore_impl(
     args: &[Vec<u8>],
     reverse: bool,
 ) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let min_s = String::from_utf8_lossy(&args[1]).into_owned();
-    let max_s = String::from_utf8_lossy(&args[2]).into_owned();
+    let key = args[0].clone();
 
     let mut with_scores = false;
     let mut with_limit = false;
@@ -700,15 +666,14 @@ fn zrangebyscore_impl(
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     run_range_by_score(
-        db,
+        &db,
         &key,
-        &min_s,
-        &max_s,
+        &args[1],
+        &args[2],
         reverse,
         with_limit,
         &offset_s,
@@ -733,9 +698,7 @@ fn zrangebylex_impl(
     args: &[Vec<u8>],
     reverse: bool,
 ) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let min_s = String::from_utf8_lossy(&args[1]).into_owned();
-    let max_s = String::from_utf8_lossy(&args[2]).into_owned();
+    let key = args[0].clone();
 
     let mut with_limit = false;
     let mut offset_s = String::new();
@@ -758,58 +721,46 @@ fn zrangebylex_impl(
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     run_range_by_lex(
-        db, &key, &min_s, &max_s, reverse, with_limit, &offset_s, &count_s,
+        &db, &key, &args[1], &args[2], reverse, with_limit, &offset_s, &count_s,
     )
 }
 
 /// ZLEXCOUNT key min max
 fn cmd_zlexcount(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let min_s = String::from_utf8_lossy(&args[1]);
-    let max_s = String::from_utf8_lossy(&args[2]);
-
-    let (min, min_incl) = match parse_lex_range(&min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
-    };
-    let (max, max_incl) = match parse_lex_range(&max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
+    let key = &args[0];
+    let range = match parse_lex_ranges(&args[1], &args[2]) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return Frame::Integer(0);
     }
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let ss = match db.sorted_set_keys.get(key.as_ref()) {
+    let ss = match db.sorted_set_keys.get(key.as_slice()) {
         Some(ss) => ss,
         None => return Frame::Integer(0),
     };
-    let mut members = ss.members_sorted();
-    members.sort();
-    let filtered = with_lex_range(members, &min, min_incl, &max, max_incl);
-    Frame::Integer(filtered.len() as i64)
+    Frame::Integer(ss.count_by_lex(&range) as i64)
 }
 
 // ── Remove range commands ────────────────────────────────────────────
 
 /// ZREMRANGEBYRANK key start stop
 fn cmd_zremrangebyrank(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let start = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -819,9 +770,8 @@ fn cmd_zremrangebyrank(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<
         None => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -837,34 +787,33 @@ fn cmd_zremrangebyrank(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<
         Some(ss) => ss,
         None => return Frame::Integer(0),
     };
-    let members = ss.members_sorted();
-    let (rs, re) = redis_range(members.len(), start, stop);
-    let to_remove: Vec<String> = members[rs..re].to_vec();
+    let (rs, re) = redis_range(ss.card(), start, stop);
+    let to_remove: Vec<Vec<u8>> = ss
+        .range(rs, re, Direction::Asc)
+        .into_iter()
+        .map(|e| e.member)
+        .collect();
 
     for m in &to_remove {
         db.sset_rem(&key, m, now);
     }
+    if !to_remove.is_empty() {
+        db.notify(NOTIFY_ZSET, "zremrangebyrank", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(to_remove.len() as i64)
 }
 
 /// ZREMRANGEBYSCORE key min max
 fn cmd_zremrangebyscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let min_s = String::from_utf8_lossy(&args[1]);
-    let max_s = String::from_utf8_lossy(&args[2]);
-
-    let (min, min_incl) = match parse_float_range(&min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
-    };
-    let (max, max_incl) = match parse_float_range(&max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
+    let key = args[0].clone();
+    let range = match parse_score_range(&args[1], &args[2]) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -880,34 +829,32 @@ fn cmd_zremrangebyscore(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec
         Some(ss) => ss,
         None => return Frame::Integer(0),
     };
-    let elems = ss.by_score(Direction::Asc);
-    let filtered = with_ss_range(elems, min, min_incl, max, max_incl);
-    let to_remove: Vec<String> = filtered.into_iter().map(|e| e.member).collect();
+    let to_remove: Vec<Vec<u8>> = ss
+        .range_by_score(&range, Direction::Asc, 0, None)
+        .into_iter()
+        .map(|e| e.member)
+        .collect();
 
     for m in &to_remove {
         db.sset_rem(&key, m, now);
     }
+    if !to_remove.is_empty() {
+        db.notify(NOTIFY_ZSET, "zremrangebyscore", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(to_remove.len() as i64)
 }
 
 /// ZREMRANGEBYLEX key min max
 fn cmd_zremrangebylex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let min_s = String::from_utf8_lossy(&args[1]);
-    let max_s = String::from_utf8_lossy(&args[2]);
-
-    let (min, min_incl) = match parse_lex_range(&min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
-    };
-    let (max, max_incl) = match parse_lex_range(&max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
+    let key = args[0].clone();
+    let range = match parse_lex_ranges(&args[1], &args[2]) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -923,13 +870,19 @@ fn cmd_zremrangebylex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u
         Some(ss) => ss,
         None => return Frame::Integer(0),
     };
-    let mut members = ss.members_sorted();
-    members.sort();
-    let filtered = with_lex_range(members, &min, min_incl, &max, max_incl);
+    let filtered: Vec<Vec<u8>> = ss
+        .range_by_lex(&range, Direction::Asc, 0, None)
+        .into_iter()
+        .map(|e| e.member)
+        .collect();
 
     for m in &filtered {
         db.sset_rem(&key, m, now);
     }
+    if !filtered.is_empty() {
+        db.notify(NOTIFY_ZSET, "zremrangebylex", &key);
+        db.notify_if_deleted(&key);
+    }
     Frame::Integer(filtered.len() as i64)
 }
 
@@ -951,7 +904,7 @@ fn zstore_impl(
     args: &[Vec<u8>],
     intersect: bool,
 ) -> Frame {
-    let dest = String::from_utf8_lossy(&args[0]).into_owned();
+    let dest = args[0].clone();
     let num_keys = match parse_int(&args[1]) {
         Some(n) if n > 0 => n as usize,
         Some(_) => {
@@ -964,10 +917,7 @@ fn zstore_impl(
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let keys: Vec<String> = args[2..2 + num_keys]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let keys: Vec<Vec<u8>> = args[2..2 + num_keys].to_vec();
     let mut rest = &args[2 + num_keys..];
 
     let mut weights: Vec<f64> = Vec::new();
@@ -1005,13 +955,12 @@ fn zstore_impl(
         }
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     // Collect all scores
-    let mut sset: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
-    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut sset: std::collections::HashMap<Vec<u8>, f64> = std::collections::HashMap::new();
+    let mut counts: std::collections::HashMap<Vec<u8>, usize> = std::collections::HashMap::new();
 
     for (i, key) in keys.iter().enumerate() {
         if !db.keys.contains_key(key) {
@@ -1019,7 +968,7 @@ fn zstore_impl(
         }
         let key_type = db.key_type(key);
 
-        let set: std::collections::HashMap<String, f64> = match key_type {
+        let set: std::collections::HashMap<Vec<u8>, f64> = match key_type {
             Some(crate::types::KeyType::Set) => db
                 .set_keys
                 .get(key)
@@ -1028,7 +977,7 @@ fn zstore_impl(
             Some(crate::types::KeyType::SortedSet) => db
                 .sorted_set_keys
                 .get(key)
-                .map(|ss| ss.scores.clone())
+                .map(|ss| ss.scores().clone())
                 .unwrap_or_default(),
             _ => return Frame::error(MSG_WRONG_TYPE),
         };
@@ -1066,13 +1015,22 @@ fn zstore_impl(
     }
 
     // Store result
-    db.del(&dest);
-    if !sset.is_empty() {
+    if sset.is_empty() {
+        if db.del(&dest) {
+            db.notify(NOTIFY_GENERIC, "del", &dest);
+        }
+    } else {
         let mut new_ss = SortedSet::new();
         for (member, score) in &sset {
             new_ss.set(*score, member);
         }
         db.sset_set(&dest, new_ss, now);
+        let event = if intersect {
+            "zinterstore"
+        } else {
+            "zunionstore"
+        };
+        db.notify(NOTIFY_ZSET, event, &dest);
     }
 
     Frame::Integer(sset.len() as i64)
@@ -1100,7 +1058,7 @@ fn zpop_impl(
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let count = if args.len() > 1 {
         match parse_int(&args[1]) {
             Some(n) if n >= 0 => n as usize,
@@ -1110,9 +1068,8 @@ fn zpop_impl(
         1
     };
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -1134,9 +1091,7 @@ fn zpop_impl(
     } else {
         Direction::Asc
     };
-    let elems = ss.by_score(dir);
-    let take = count.min(elems.len());
-    let to_pop: Vec<SSElem> = elems[..take].to_vec();
+    let to_pop: Vec<SSElem> = ss.range(0, count, dir);
 
     let mut result = Vec::new();
     for e in &to_pop {
@@ -1144,15 +1099,157 @@ fn zpop_impl(
         result.push(Frame::Bulk(write_float(e.score).into()));
         db.sset_rem(&key, &e.member, now);
     }
+    if !to_pop.is_empty() {
+        db.notify(
+            NOTIFY_ZSET,
+            if reverse { "zpopmax" } else { "zpopmin" },
+            &key,
+        );
+        db.notify_if_deleted(&key);
+    }
 
     Frame::Array(result)
 }
 
+/// ZMPOP numkeys key [key ...] MIN|MAX [COUNT count]
+fn cmd_zmpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    let opts = match parse_mpop(args, ["MIN", "MAX"]) {
+        Ok(opts) => opts,
+        Err(err) => return err,
+    };
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    for key in &opts.keys {
+        db.check_ttl(key);
+        if let Some(t) = db.key_type(key)
+            && t != crate::types::KeyType::SortedSet
+        {
+            return Frame::error(MSG_WRONG_TYPE);
+        }
+        if let Some(frame) = zset_mpop(&mut db, key, !opts.first, opts.count, ctx.resp3, now) {
+            return frame;
+        }
+    }
+    Frame::NullArray
+}
+
+/// BZMPOP timeout numkeys key [key ...] MIN|MAX [COUNT count] —
+/// non-blocking attempt
+fn cmd_bzmpop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    if let Err(err) = parse_timeout(&args[0]) {
+        return err;
+    }
+    cmd_zmpop(state, ctx, &args[1..])
+}
+
+/// BZPOPMIN key [key ...] timeout — non-blocking attempt
+fn cmd_bzpopmin(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    bzpop_impl(state, ctx, args, false)
+}
+
+/// BZPOPMAX key [key ...] timeout — non-blocking attempt
+fn cmd_bzpopmax(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    bzpop_impl(state, ctx, args, true)
+}
+
+fn bzpop_impl(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>], max: bool) -> Frame {
+    if let Err(err) = parse_timeout(&args[args.len() - 1]) {
+        return err;
+    }
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    for key in &args[..args.len() - 1] {
+        db.check_ttl(key);
+        if let Some(t) = db.key_type(key)
+            && t != crate::types::KeyType::SortedSet
+        {
+            return Frame::error(MSG_WRONG_TYPE);
+        }
+        if let Some(frame) = zset_bpop(&mut db, key, max, ctx.resp3, now) {
+            return frame;
+        }
+    }
+    Frame::NullArray
+}
+
+/// Pop up to `count` lowest (or highest) scored members, as ZMPOP does:
+/// `[key, [[member, score] ...]]`. Returns None if the set is empty.
+pub(crate) fn zset_mpop(
+    db: &mut RedisDB,
+    key: &[u8],
+    max: bool,
+    count: usize,
+    resp3: bool,
+    now: SystemTime,
+) -> Option<Frame> {
+    let popped = zset_pop(db, key, max, count, now);
+    if popped.is_empty() {
+        return None;
+    }
+    let elems = popped
+        .into_iter()
+        .map(|e| {
+            Frame::Array(vec![
+                Frame::Bulk(e.member.into()),
+                score_frame(e.score, resp3),
+            ])
+        })
+        .collect();
+    Some(Frame::Array(vec![
+        Frame::Bulk(key.to_vec().into()),
+        Frame::Array(elems),
+    ]))
+}
+
+/// Pop the lowest (or highest) scored member, as BZPOPMIN does:
+/// `[key, member, score]`. Returns None if the set is empty.
+pub(crate) fn zset_bpop(
+    db: &mut RedisDB,
+    key: &[u8],
+    max: bool,
+    resp3: bool,
+    now: SystemTime,
+) -> Option<Frame> {
+    let e = zset_pop(db, key, max, 1, now).pop()?;
+    Some(Frame::Array(vec![
+        Frame::Bulk(key.to_vec().into()),
+        Frame::Bulk(e.member.into()),
+        score_frame(e.score, resp3),
+    ]))
+}
+
+fn zset_pop(db: &mut RedisDB, key: &[u8], max: bool, count: usize, now: SystemTime) -> Vec<SSElem> {
+    let dir = if max { Direction::Desc } else { Direction::Asc };
+    let popped = match db.sorted_set_keys.get(key) {
+        Some(ss) => ss.range(0, count, dir),
+        None => return Vec::new(),
+    };
+    for e in &popped {
+        db.sset_rem(key, &e.member, now);
+    }
+    if !popped.is_empty() {
+        db.notify(NOTIFY_ZSET, if max { "zpopmax" } else { "zpopmin" }, key);
+        db.notify_if_deleted(key);
+    }
+    popped
+}
+
+/// A score in a reply: a double in RESP3, a bulk string in RESP2.
+fn score_frame(score: f64, resp3: bool) -> Frame {
+    if resp3 {
+        Frame::Double(score)
+    } else {
+        Frame::Bulk(write_float(score).into())
+    }
+}
+
 // ── ZSCAN ────────────────────────────────────────────────────────────
 
 /// ZSCAN key cursor [MATCH pattern] [COUNT count]
 fn cmd_zscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let _cursor = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_CURSOR),
@@ -1163,17 +1260,16 @@ fn cmd_zscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         Err(e) => return e,
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let ss = match db.sorted_set_keys.get(key.as_ref()) {
+    let ss = match db.sorted_set_keys.get(key.as_slice()) {
         Some(ss) => ss,
         None => {
             return Frame::Array(vec![Frame::Bulk("0".into()), Frame::Array(vec![])]);
@@ -1203,7 +1299,7 @@ fn cmd_zscan(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
 fn run_range_by_rank(
     db: &crate::db::RedisDB,
-    key: &str,
+    key: &[u8],
     min_s: &str,
     max_s: &str,
     reverse: bool,
@@ -1237,11 +1333,10 @@ fn run_range_by_rank(
     } else {
         Direction::Asc
     };
-    let elems = ss.by_score(dir);
-    let (rs, re) = redis_range(elems.len(), min, max);
+    let (rs, re) = redis_range(ss.card(), min, max);
 
     let mut result = Vec::new();
-    for e in &elems[rs..re] {
+    for e in &ss.range(rs, re, dir) {
         result.push(Frame::Bulk(e.member.clone().into()));
         if with_scores {
             result.push(Frame::Bulk(write_float(e.score).into()));
@@ -1253,36 +1348,33 @@ fn run_range_by_rank(
 #[allow(clippy::too_many_arguments)]
 fn run_range_by_score(
     db: &crate::db::RedisDB,
-    key: &str,
-    min_s: &str,
-    max_s: &str,
+    key: &[u8],
+    min_s: &[u8],
+    max_s: &[u8],
     reverse: bool,
     with_limit: bool,
     offset_s: &str,
     count_s: &str,
     with_scores: bool,
 ) -> Frame {
-    let mut limit_offset = 0i64;
-    let mut limit_count = -1i64;
-
-    if with_limit {
-        limit_offset = match offset_s.parse() {
-            Ok(n) => n,
-            Err(_) => return Frame::error(MSG_INVALID_INT),
-        };
-        limit_count = match count_s.parse() {
-            Ok(n) => n,
-            Err(_) => return Frame::error(MSG_INVALID_INT),
-        };
-    }
+    let (offset, count) = if with_limit {
+        match parse_limit(offset_s, count_s) {
+            Ok(v) => v,
+            Err(e) => return e,
+        }
+    } else {
+        (Some(0), None)
+    };
 
-    let (min, min_incl) = match parse_float_range(min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
+    // For reverse, the arguments are max then min.
+    let (min_s, max_s) = if reverse {
+        (max_s, min_s)
+    } else {
+        (min_s, max_s)
     };
-    let (max, max_incl) = match parse_float_range(max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_MIN_MAX),
+    let range = match parse_score_range(min_s, max_s) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
     if !db.keys.contains_key(key) {
@@ -1298,44 +1390,18 @@ fn run_range_by_score(
         Some(ss) => ss,
         None => return Frame::Array(vec![]),
     };
+    let Some(offset) = offset else {
+        return Frame::Array(vec![]);
+    };
 
-    let elems = ss.by_score(Direction::Asc);
-
-    // For reverse, swap min/max and their inclusiveness
-    let (fmin, fmin_incl, fmax, fmax_incl) = if reverse {
-        (max, max_incl, min, min_incl)
+    let dir = if reverse {
+        Direction::Desc
     } else {
-        (min, min_incl, max, max_incl)
+        Direction::Asc
     };
-
-    let mut filtered = with_ss_range(elems, fmin, fmin_incl, fmax, fmax_incl);
-    if reverse {
-        filtered.reverse();
-    }
-
-    // Apply LIMIT
-    if with_limit {
-        if limit_offset < 0 {
-            filtered = Vec::new();
-        } else {
-            let offset = limit_offset as usize;
-            if offset < filtered.len() {
-                filtered = filtered[offset..].to_vec();
-            } else {
-                filtered = Vec::new();
-            }
-            if limit_count >= 0 {
-                let count = limit_count as usize;
-                if filtered.len() > count {
-                    filtered.truncate(count);
-                }
-            }
-        }
-    }
-
     let mut result = Vec::new();
-    for e in &filtered {
-        result.push(Frame::Bulk(e.member.clone().into()));
+    for e in ss.range_by_score(&range, dir, offset, count) {
+        result.push(Frame::Bulk(e.member.into()));
         if with_scores {
             result.push(Frame::Bulk(write_float(e.score).into()));
         }
@@ -1346,35 +1412,32 @@ fn run_range_by_score(
 #[allow(clippy::too_many_arguments)]
 fn run_range_by_lex(
     db: &crate::db::RedisDB,
-    key: &str,
-    min_s: &str,
-    max_s: &str,
+    key: &[u8],
+    min_s: &[u8],
+    max_s: &[u8],
     reverse: bool,
     with_limit: bool,
     offset_s: &str,
     count_s: &str,
 ) -> Frame {
-    let mut limit_offset = 0i64;
-    let mut limit_count = -1i64;
-
-    if with_limit {
-        limit_offset = match offset_s.parse() {
-            Ok(n) => n,
-            Err(_) => return Frame::error(MSG_INVALID_INT),
-        };
-        limit_count = match count_s.parse() {
-            Ok(n) => n,
-            Err(_) => return Frame::error(MSG_INVALID_INT),
-        };
-    }
+    let (offset, count) = if with_limit {
+        match parse_limit(offset_s, count_s) {
+            Ok(v) => v,
+            Err(e) => return e,
+        }
+    } else {
+        (Some(0), None)
+    };
 
-    let (min, min_incl) = match parse_lex_range(min_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
+    // For reverse, the arguments are max then min.
+    let (min_s, max_s) = if reverse {
+        (max_s, min_s)
+    } else {
+        (min_s, max_s)
     };
-    let (max, max_incl) = match parse_lex_range(max_s) {
-        Ok(v) => v,
-        Err(_) => return Frame::error(MSG_INVALID_RANGE_ITEM),
+    let range = match parse_lex_ranges(min_s, max_s) {
+        Ok(r) => r,
+        Err(e) => return e,
     };
 
     if !db.keys.contains_key(key) {
@@ -1390,45 +1453,19 @@ fn run_range_by_lex(
         Some(ss) => ss,
         None => return Frame::Array(vec![]),
     };
+    let Some(offset) = offset else {
+        return Frame::Array(vec![]);
+    };
 
-    let mut members = ss.members_sorted();
-    members.sort();
-
-    // For reverse, swap min/max
-    let (fmin, fmin_incl, fmax, fmax_incl) = if reverse {
-        (max.clone(), max_incl, min.clone(), min_incl)
+    let dir = if reverse {
+        Direction::Desc
     } else {
-        (min, min_incl, max, max_incl)
+        Direction::Asc
     };
-
-    let mut filtered = with_lex_range(members, &fmin, fmin_incl, &fmax, fmax_incl);
-    if reverse {
-        filtered.reverse();
-    }
-
-    // Apply LIMIT
-    if with_limit {
-        if limit_offset < 0 {
-            filtered = Vec::new();
-        } else {
-            let offset = limit_offset as usize;
-            if offset < filtered.len() {
-                filtered = filtered[offset..].to_vec();
-            } else {
-                filtered = Vec::new();
-            }
-            if limit_count >= 0 {
-                let count = limit_count as usize;
-                if filtered.len() > count {
-                    filtered.truncate(count);
-                }
-            }
-        }
-    }
-
-    let result: Vec<Frame> = filtered
+    let result: Vec<Frame> = ss
+        .range_by_lex(&range, dir, offset, count)
         .into_iter()
-        .map(|m| Frame::Bulk(m.into()))
+        .map(|e| Frame::Bulk(e.member.into()))
         .collect();
     Frame::Array(result)
 }
@@ -1456,10 +1493,7 @@ fn zop_impl(
         return Frame::error(MSG_SYNTAX_ERROR);
     }
 
-    let keys: Vec<String> = args[1..1 + num_keys]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let keys: Vec<Vec<u8>> = args[1..1 + num_keys].to_vec();
     let mut rest = &args[1 + num_keys..];
 
     let mut weights: Vec<f64> = Vec::new();
@@ -1502,18 +1536,17 @@ fn zop_impl(
         }
     }
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
-    let mut sset: std::collections::HashMap<String, f64> = std::collections::HashMap::new();
-    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut sset: std::collections::HashMap<Vec<u8>, f64> = std::collections::HashMap::new();
+    let mut counts: std::collections::HashMap<Vec<u8>, usize> = std::collections::HashMap::new();
 
     for (i, key) in keys.iter().enumerate() {
         if !db.keys.contains_key(key) {
             continue;
         }
         let key_type = db.key_type(key);
-        let set: std::collections::HashMap<String, f64> = match key_type {
+        let set: std::collections::HashMap<Vec<u8>, f64> = match key_type {
             Some(crate::types::KeyType::Set) => db
                 .set_keys
                 .get(key)
@@ -1522,7 +1555,7 @@ fn zop_impl(
             Some(crate::types::KeyType::SortedSet) => db
                 .sorted_set_keys
                 .get(key)
-                .map(|ss| ss.scores.clone())
+                .map(|ss| ss.scores().clone())
                 .unwrap_or_default(),
             _ => return Frame::error(MSG_WRONG_TYPE),
         };
@@ -1559,7 +1592,7 @@ fn zop_impl(
     }
 
     // Sort by score, then by member
-    let mut elems: Vec<(String, f64)> = sset.into_iter().collect();
+    let mut elems: Vec<(Vec<u8>, f64)> = sset.into_iter().collect();
     elems.sort_by(|a, b| {
         a.1.partial_cmp(&b.1)
             .unwrap_or(std::cmp::Ordering::Equal)
@@ -1592,7 +1625,7 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         return Frame::error(err_wrong_number("zrandmember"));
     }
 
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let mut count: i64 = 0;
     let mut with_count = false;
     let mut with_scores = false;
@@ -1615,11 +1648,10 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if !db.keys.contains_key(key.as_ref()) {
+    if !db.keys.contains_key(key.as_slice()) {
         return if with_count {
             Frame::Array(vec![])
         } else {
@@ -1627,13 +1659,13 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         };
     }
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != crate::types::KeyType::SortedSet
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let ss = match db.sorted_set_keys.get(key.as_ref()) {
+    let ss = match db.sorted_set_keys.get(key.as_slice()) {
         Some(ss) => ss,
         None => {
             return if with_count {
@@ -1645,8 +1677,8 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
     };
 
     let mut members = ss.members_sorted();
-    // Collect scores before shuffling (avoids borrow issues with inner.rng)
-    let scores: std::collections::HashMap<String, f64> = members
+    // Collect scores before shuffling
+    let scores: std::collections::HashMap<Vec<u8>, f64> = members
         .iter()
         .map(|m| (m.clone(), ss.get(m).unwrap_or(0.0)))
         .collect();
@@ -1656,7 +1688,7 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         let abs_count = (-count) as usize;
         let mut result = Vec::new();
         for _ in 0..abs_count {
-            let idx = inner.rng.random_range(0..members.len());
+            let idx = state.lock().rng.random_range(0..members.len());
             result.push(Frame::Bulk(members[idx].clone().into()));
             if with_scores {
                 let score = scores.get(&members[idx]).copied().unwrap_or(0.0);
@@ -1667,7 +1699,7 @@ fn cmd_zrandmember(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
     }
 
     // Positive count: unique, shuffle
-    members.shuffle(&mut inner.rng);
+    members.shuffle(&mut state.lock().rng);
     let take = (count as usize).min(members.len());
 
     if !with_count {
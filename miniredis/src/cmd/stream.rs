@@ -4,6 +4,7 @@ use crate::connection::ConnCtx;
 use crate::db::SharedState;
 use crate::dispatch::{CommandTable, MSG_WRONG_TYPE, err_wrong_number};
 use crate::frame::Frame;
+use crate::pubsub::NOTIFY_STREAM;
 use crate::types::{KeyType, Stream, format_stream_range_bound};
 
 pub fn register(table: &mut CommandTable) {
@@ -25,7 +26,7 @@ pub fn register(table: &mut CommandTable) {
 
 /// XADD key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold] id field value [field value ...]
 fn cmd_xadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let mut i = 1;
     let mut nomkstream = false;
     let mut maxlen: Option<usize> = None;
@@ -94,18 +95,14 @@ fn cmd_xadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(err_wrong_number("xadd"));
     }
 
-    let values: Vec<String> = remaining
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).to_string())
-        .collect();
+    let values: Vec<Vec<u8>> = remaining.to_vec();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
+    let now = state.now();
     let ms = now
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis() as u64;
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
 
     // Type check
     if let Some(kt) = db.keys.get(&key) {
@@ -116,19 +113,24 @@ fn cmd_xadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::Null;
     }
 
-    db.keys.entry(key.clone()).or_insert(KeyType::Stream);
+    db.add_key(&key, KeyType::Stream);
     let stream = db.stream_keys.entry(key.clone()).or_default();
 
     match stream.add(&id, values, ms) {
         Ok(final_id) => {
+            let mut trimmed = 0;
             if let Some(ml) = maxlen {
-                stream.trim_maxlen(ml);
+                trimmed += stream.trim_maxlen(ml);
             }
             if let Some(mi) = minid {
                 let normalized = Stream::normalize_id(&mi);
-                stream.trim_minid(&normalized);
+                trimmed += stream.trim_minid(&normalized);
             }
             db.incr_version(&key, now);
+            db.notify(NOTIFY_STREAM, "xadd", &key);
+            if trimmed > 0 {
+                db.notify(NOTIFY_STREAM, "xtrim", &key);
+            }
             Frame::Bulk(final_id.into())
         }
         Err(e) => Frame::error(e),
@@ -137,17 +139,16 @@ fn cmd_xadd(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// XLEN key
 fn cmd_xlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let key = &args[0];
+    let db = state.db(ctx.selected_db);
 
-    if let Some(kt) = db.keys.get(key.as_ref())
+    if let Some(kt) = db.keys.get(key.as_slice())
         && *kt != KeyType::Stream
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.stream_keys.get(key.as_ref()) {
+    match db.stream_keys.get(key.as_slice()) {
         Some(stream) => Frame::Integer(stream.entries.len() as i64),
         None => Frame::Integer(0),
     }
@@ -169,7 +170,7 @@ fn cmd_xrange_impl(
     args: &[Vec<u8>],
     reverse: bool,
 ) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let arg_start = String::from_utf8_lossy(&args[1]).to_string();
     let arg_end = String::from_utf8_lossy(&args[2]).to_string();
 
@@ -212,16 +213,15 @@ fn cmd_xrange_impl(
         (s, e)
     };
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
-    if let Some(kt) = db.keys.get(key.as_ref())
+    if let Some(kt) = db.keys.get(key.as_slice())
         && *kt != KeyType::Stream
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let stream = match db.stream_keys.get(key.as_ref()) {
+    let stream = match db.stream_keys.get(key.as_slice()) {
         Some(s) => s,
         None => return Frame::Array(vec![]),
     };
@@ -302,19 +302,15 @@ fn cmd_xread(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     }
 
     let half = remaining.len() / 2;
-    let keys: Vec<String> = remaining[..half]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).to_string())
-        .collect();
+    let keys: Vec<Vec<u8>> = remaining[..half].to_vec();
 
-    let inner = state.lock();
+    let db = state.db(ctx.selected_db);
 
     let mut ids = Vec::with_capacity(half);
     for (idx, a) in remaining[half..].iter().enumerate() {
         let s = String::from_utf8_lossy(a).to_string();
         if s == "$" {
             // Get current last ID for this stream
-            let db = inner.db(ctx.selected_db);
             ids.push(
                 db.stream_keys
                     .get(&keys[idx])
@@ -330,7 +326,6 @@ fn cmd_xread(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         }
     }
 
-    let db = inner.db(ctx.selected_db);
     let mut results = Vec::new();
     let mut has_data = false;
 
@@ -385,16 +380,15 @@ fn cmd_xread(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
 /// XDEL key id [id ...]
 fn cmd_xdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let ids: Vec<String> = args[1..]
         .iter()
         .map(|a| String::from_utf8_lossy(a).to_string())
         .collect();
     let id_refs: Vec<&str> = ids.iter().map(|s| s.as_str()).collect();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -415,6 +409,9 @@ fn cmd_xdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
             }
             let count = stream.del(&id_refs);
             db.incr_version(&key, now);
+            if count > 0 {
+                db.notify(NOTIFY_STREAM, "xdel", &key);
+            }
             Frame::Integer(count)
         }
         None => {
@@ -426,7 +423,7 @@ fn cmd_xdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// XTRIM key MAXLEN|MINID [=|~] threshold [LIMIT count]
 fn cmd_xtrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let strategy = String::from_utf8_lossy(&args[1]).to_uppercase();
 
     if strategy != "MAXLEN" && strategy != "MINID" {
@@ -481,9 +478,8 @@ fn cmd_xtrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         return Frame::error("ERR syntax error");
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -513,6 +509,9 @@ fn cmd_xtrim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     };
 
     db.incr_version(&key, now);
+    if count > 0 {
+        db.notify(NOTIFY_STREAM, "xtrim", &key);
+    }
     Frame::Integer(count)
 }
 
@@ -524,7 +523,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() < 3 {
                 return Frame::error(err_wrong_number("xgroup|create"));
             }
-            let key = String::from_utf8_lossy(&args[1]).to_string();
+            let key = args[1].clone();
             let group = String::from_utf8_lossy(&args[2]).to_string();
             let id = if args.len() > 3 {
                 String::from_utf8_lossy(&args[3]).to_string()
@@ -535,9 +534,8 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             let mkstream =
                 args.len() > 4 && String::from_utf8_lossy(&args[4]).to_uppercase() == "MKSTREAM";
 
-            let mut inner = state.lock();
-            let now = inner.effective_now();
-            let db = inner.db_mut(ctx.selected_db);
+            let now = state.now();
+            let mut db = state.db(ctx.selected_db);
 
             if let Some(kt) = db.keys.get(&key) {
                 if *kt != KeyType::Stream {
@@ -548,7 +546,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                     "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.",
                 );
             } else {
-                db.keys.insert(key.clone(), KeyType::Stream);
+                db.add_key(&key, KeyType::Stream);
                 db.stream_keys.insert(key.clone(), Stream::new());
             }
 
@@ -556,6 +554,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             match stream.create_group(&group, &id) {
                 Ok(()) => {
                     db.incr_version(&key, now);
+                    db.notify(NOTIFY_STREAM, "xgroup-create", &key);
                     Frame::ok()
                 }
                 Err(e) => Frame::error(e),
@@ -565,12 +564,11 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() < 3 {
                 return Frame::error(err_wrong_number("xgroup|destroy"));
             }
-            let key = String::from_utf8_lossy(&args[1]).to_string();
+            let key = args[1].clone();
             let group = String::from_utf8_lossy(&args[2]).to_string();
 
-            let mut inner = state.lock();
-            let now = inner.effective_now();
-            let db = inner.db_mut(ctx.selected_db);
+            let now = state.now();
+            let mut db = state.db(ctx.selected_db);
 
             if let Some(kt) = db.keys.get(&key)
                 && *kt != KeyType::Stream
@@ -589,6 +587,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
             if stream.groups.remove(&group).is_some() {
                 db.incr_version(&key, now);
+                db.notify(NOTIFY_STREAM, "xgroup-destroy", &key);
                 Frame::Integer(1)
             } else {
                 Frame::Integer(0)
@@ -598,13 +597,12 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() < 4 {
                 return Frame::error(err_wrong_number("xgroup|createconsumer"));
             }
-            let key = String::from_utf8_lossy(&args[1]).to_string();
+            let key = args[1].clone();
             let group_name = String::from_utf8_lossy(&args[2]).to_string();
             let consumer_name = String::from_utf8_lossy(&args[3]).to_string();
 
-            let mut inner = state.lock();
-            let now = inner.effective_now();
-            let db = inner.db_mut(ctx.selected_db);
+            let now = state.now();
+            let mut db = state.db(ctx.selected_db);
 
             if let Some(kt) = db.keys.get(&key)
                 && *kt != KeyType::Stream
@@ -624,7 +622,8 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 None => {
                     return Frame::error(format!(
                         "NOGROUP No such consumer group '{}' for key name '{}'",
-                        group_name, key
+                        group_name,
+                        String::from_utf8_lossy(&key)
                     ));
                 }
             };
@@ -637,6 +636,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                     last_seen: now,
                     last_success: now,
                 });
+                db.notify(NOTIFY_STREAM, "xgroup-createconsumer", &key);
                 Frame::Integer(1)
             } else {
                 Frame::Integer(0)
@@ -646,13 +646,12 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             if args.len() < 4 {
                 return Frame::error(err_wrong_number("xgroup|delconsumer"));
             }
-            let key = String::from_utf8_lossy(&args[1]).to_string();
+            let key = args[1].clone();
             let group_name = String::from_utf8_lossy(&args[2]).to_string();
             let consumer_name = String::from_utf8_lossy(&args[3]).to_string();
 
-            let mut inner = state.lock();
-            let now = inner.effective_now();
-            let db = inner.db_mut(ctx.selected_db);
+            let now = state.now();
+            let mut db = state.db(ctx.selected_db);
 
             if let Some(kt) = db.keys.get(&key)
                 && *kt != KeyType::Stream
@@ -672,7 +671,8 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
                 None => {
                     return Frame::error(format!(
                         "NOGROUP No such consumer group '{}' for key name '{}'",
-                        group_name, key
+                        group_name,
+                        String::from_utf8_lossy(&key)
                     ));
                 }
             };
@@ -685,6 +685,7 @@ fn cmd_xgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
             group.pending.retain(|pe| pe.consumer != consumer_name);
             group.consumers.remove(&consumer_name);
             db.incr_version(&key, now);
+            db.notify(NOTIFY_STREAM, "xgroup-delconsumer", &key);
             Frame::Integer(pending_count)
         }
         _ => Frame::error(format!(
@@ -769,10 +770,7 @@ fn cmd_xreadgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
     }
 
     let half = remaining.len() / 2;
-    let keys: Vec<String> = remaining[..half]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).to_string())
-        .collect();
+    let keys: Vec<Vec<u8>> = remaining[..half].to_vec();
 
     // Collect IDs (validation deferred to per-stream loop, after group check)
     let mut ids = Vec::with_capacity(half);
@@ -780,9 +778,8 @@ fn cmd_xreadgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         ids.push(String::from_utf8_lossy(a).to_string());
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     let mut results = Vec::new();
     let mut has_data = false;
@@ -799,7 +796,8 @@ fn cmd_xreadgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
             None => {
                 return Frame::error(format!(
                     "NOGROUP No such consumer group '{}' for key name '{}'",
-                    group_name, key
+                    group_name,
+                    String::from_utf8_lossy(key)
                 ));
             }
         };
@@ -808,7 +806,8 @@ fn cmd_xreadgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         if !stream.groups.contains_key(&group_name) {
             return Frame::error(format!(
                 "NOGROUP No such consumer group '{}' for key name '{}'",
-                group_name, key
+                group_name,
+                String::from_utf8_lossy(key)
             ));
         }
 
@@ -862,7 +861,7 @@ fn cmd_xreadgroup(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
 
 /// XACK key group id [id ...]
 fn cmd_xack(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let group_name = String::from_utf8_lossy(&args[1]).to_string();
     let ids: Vec<String> = args[2..]
         .iter()
@@ -879,8 +878,7 @@ fn cmd_xack(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
     let id_refs: Vec<&str> = ids.iter().map(|s| s.as_str()).collect();
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -901,11 +899,10 @@ fn cmd_xack(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// XPENDING key group [[IDLE ms] start end count [consumer]]
 fn cmd_xpending(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let group_name = String::from_utf8_lossy(&args[1]).to_string();
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -918,7 +915,8 @@ fn cmd_xpending(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
         None => {
             return Frame::error(format!(
                 "NOGROUP No such consumer group '{}' for key name '{}'",
-                group_name, key
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -928,7 +926,8 @@ fn cmd_xpending(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
         None => {
             return Frame::error(format!(
                 "NOGROUP No such consumer group '{}' for key name '{}'",
-                group_name, key
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -1043,7 +1042,7 @@ fn cmd_xpending(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
         return Frame::Array(vec![]);
     }
 
-    let now = inner.effective_now();
+    let now = state.now();
     let mut result = Vec::new();
 
     for pe in &group.pending {
@@ -1088,7 +1087,7 @@ fn cmd_xpending(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
 
 /// XCLAIM key group consumer min-idle-ms id [id ...] [IDLE ms] [TIME ms] [RETRYCOUNT count] [FORCE] [JUSTID]
 fn cmd_xclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let group_name = String::from_utf8_lossy(&args[1]).to_string();
     let consumer_name = String::from_utf8_lossy(&args[2]).to_string();
     let _min_idle_ms = match String::from_utf8_lossy(&args[3]).parse::<u64>() {
@@ -1172,9 +1171,8 @@ fn cmd_xclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         }
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -1187,7 +1185,9 @@ fn cmd_xclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         None => {
             return Frame::error(format!(
                 "NOGROUP No such key '{}' or consumer group '{}' in XCLAIM for key name '{}'",
-                key, group_name, key
+                String::from_utf8_lossy(&key),
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -1197,7 +1197,9 @@ fn cmd_xclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         None => {
             return Frame::error(format!(
                 "NOGROUP No such key '{}' or consumer group '{}' in XCLAIM for key name '{}'",
-                key, group_name, key
+                String::from_utf8_lossy(&key),
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -1297,7 +1299,7 @@ fn cmd_xclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// XAUTOCLAIM key group consumer min-idle-ms start [COUNT count] [JUSTID]
 fn cmd_xautoclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).to_string();
+    let key = args[0].clone();
     let group_name = String::from_utf8_lossy(&args[1]).to_string();
     let consumer_name = String::from_utf8_lossy(&args[2]).to_string();
     let min_idle_ms = match String::from_utf8_lossy(&args[3]).parse::<u64>() {
@@ -1343,9 +1345,8 @@ fn cmd_xautoclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         }
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     if let Some(kt) = db.keys.get(&key)
         && *kt != KeyType::Stream
@@ -1358,7 +1359,9 @@ fn cmd_xautoclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         None => {
             return Frame::error(format!(
                 "NOGROUP No such key '{}' or consumer group '{}' in XAUTOCLAIM for key name '{}'",
-                key, group_name, key
+                String::from_utf8_lossy(&key),
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -1368,7 +1371,9 @@ fn cmd_xautoclaim(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>])
         None => {
             return Frame::error(format!(
                 "NOGROUP No such key '{}' or consumer group '{}' in XAUTOCLAIM for key name '{}'",
-                key, group_name, key
+                String::from_utf8_lossy(&key),
+                group_name,
+                String::from_utf8_lossy(&key)
             ));
         }
     };
@@ -1471,17 +1476,16 @@ fn cmd_xinfo(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
             if args.len() < 2 {
                 return Frame::error(err_wrong_number("xinfo|stream"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            if let Some(kt) = db.keys.get(key.as_ref())
+            if let Some(kt) = db.keys.get(key.as_slice())
                 && *kt != KeyType::Stream
             {
                 return Frame::error(MSG_WRONG_TYPE);
             }
 
-            let stream = match db.stream_keys.get(key.as_ref()) {
+            let stream = match db.stream_keys.get(key.as_slice()) {
                 Some(s) => s,
                 None => return Frame::error("ERR no such key"),
             };
@@ -1499,17 +1503,16 @@ fn cmd_xinfo(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
             if args.len() < 2 {
                 return Frame::error(err_wrong_number("xinfo|groups"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
-            let inner = state.lock();
-            let db = inner.db(ctx.selected_db);
+            let key = &args[1];
+            let db = state.db(ctx.selected_db);
 
-            if let Some(kt) = db.keys.get(key.as_ref())
+            if let Some(kt) = db.keys.get(key.as_slice())
                 && *kt != KeyType::Stream
             {
                 return Frame::error(MSG_WRONG_TYPE);
             }
 
-            let stream = match db.stream_keys.get(key.as_ref()) {
+            let stream = match db.stream_keys.get(key.as_slice()) {
                 Some(s) => s,
                 None => return Frame::error("ERR no such key"),
             };
@@ -1551,19 +1554,18 @@ fn cmd_xinfo(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
             if args.len() < 3 {
                 return Frame::error(err_wrong_number("xinfo|consumers"));
             }
-            let key = String::from_utf8_lossy(&args[1]);
+            let key = &args[1];
             let group_name = String::from_utf8_lossy(&args[2]);
-            let inner = state.lock();
-            let now = inner.effective_now();
-            let db = inner.db(ctx.selected_db);
+            let now = state.now();
+            let db = state.db(ctx.selected_db);
 
-            if let Some(kt) = db.keys.get(key.as_ref())
+            if let Some(kt) = db.keys.get(key.as_slice())
                 && *kt != KeyType::Stream
             {
                 return Frame::error(MSG_WRONG_TYPE);
             }
 
-            let stream = match db.stream_keys.get(key.as_ref()) {
+            let stream = match db.stream_keys.get(key.as_slice()) {
                 Some(s) => s,
                 None => return Frame::error("ERR no such key"),
             };
@@ -1573,7 +1575,8 @@ fn cmd_xinfo(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
                 None => {
                     return Frame::error(format!(
                         "NOGROUP No such consumer group '{}' for key name '{}'",
-                        group_name, key
+                        group_name,
+                        String::from_utf8_lossy(key)
                     ));
                 }
             };
@@ -10,6 +10,7 @@ use crate::dispatch::{
     err_wrong_number,
 };
 use crate::frame::Frame;
+use crate::pubsub::{NOTIFY_GENERIC, NOTIFY_STRING};
 use crate::types::KeyType;
 
 pub fn register(table: &mut CommandTable) {
@@ -39,6 +40,8 @@ pub fn register(table: &mut CommandTable) {
     table.add("BITCOUNT", cmd_bitcount, true, -2);
     table.add("BITOP", cmd_bitop, false, -4);
     table.add("BITPOS", cmd_bitpos, true, -3);
+    table.add("BITFIELD", cmd_bitfield, false, -2);
+    table.add("BITFIELD_RO", cmd_bitfield_ro, true, -2);
 }
 
 // ── Helpers ──────────────────────────────────────────────────────────
@@ -46,12 +49,11 @@ pub fn register(table: &mut CommandTable) {
 fn string_incr(
     state: &Arc<SharedState>,
     ctx: &mut ConnCtx,
-    key: &str,
+    key: &[u8],
     delta: i64,
 ) -> Result<i64, Frame> {
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(key);
 
     // Check type
@@ -75,6 +77,7 @@ fn string_incr(
     };
 
     db.string_set(key, new_val.to_string().into_bytes(), now);
+    db.notify(NOTIFY_STRING, "incrby", key);
     Ok(new_val)
 }
 
@@ -82,18 +85,17 @@ fn string_incr(
 
 /// GET key
 fn cmd_get(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.string_get(&key) {
+    match db.string_get(key) {
         Some(val) => Frame::Bulk(val.clone().into()),
         None => Frame::Null,
     }
@@ -101,7 +103,7 @@ fn cmd_get(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
 
 /// SET key value [EX seconds] [PX milliseconds] [NX|XX] [KEEPTTL] [GET]
 fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let value = args[1].clone();
 
     let mut ex: Option<Duration> = None;
@@ -167,14 +169,12 @@ fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
                 if ts <= 0 {
                     return Frame::error(MSG_INVALID_SE_TIME);
                 }
-                let inner = state.lock();
-                let now = inner.effective_now();
+                let now = state.now();
                 let target = std::time::UNIX_EPOCH + Duration::from_secs(ts as u64);
                 match target.duration_since(now) {
                     Ok(d) => ex = Some(d),
                     Err(_) => ex = Some(Duration::ZERO),
                 }
-                drop(inner);
             }
             "PXAT" => {
                 if expire_opt_set {
@@ -192,14 +192,12 @@ fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
                 if ts <= 0 {
                     return Frame::error(MSG_INVALID_SE_TIME);
                 }
-                let inner = state.lock();
-                let now = inner.effective_now();
+                let now = state.now();
                 let target = std::time::UNIX_EPOCH + Duration::from_millis(ts as u64);
                 match target.duration_since(now) {
                     Ok(d) => ex = Some(d),
                     Err(_) => ex = Some(Duration::ZERO),
                 }
-                drop(inner);
             }
             "NX" => nx = true,
             "XX" => xx = true,
@@ -214,9 +212,8 @@ fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
         return Frame::error(MSG_XX_AND_NX);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     let old_value = if get {
@@ -246,8 +243,10 @@ fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
 
     db.string_set(&key, value, now);
 
+    db.notify(NOTIFY_STRING, "set", &key);
     if let Some(ttl) = ex {
         db.ttl.insert(key.clone(), ttl);
+        db.notify(NOTIFY_GENERIC, "expire", &key);
     } else if let Some(old_ttl) = old_ttl {
         db.ttl.insert(key.clone(), old_ttl);
     } else {
@@ -259,11 +258,10 @@ fn cmd_set(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fra
 
 /// SETNX key value
 fn cmd_setnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let value = args[1].clone();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if db.keys.contains_key(&key) {
@@ -272,12 +270,13 @@ fn cmd_setnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
     db.string_set(&key, value, now);
     db.ttl.remove(&key);
+    db.notify(NOTIFY_STRING, "set", &key);
     Frame::Integer(1)
 }
 
 /// SETEX key seconds value
 fn cmd_setex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let secs: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -287,18 +286,18 @@ fn cmd_setex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     }
     let value = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.del(&key);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.string_set(&key, value, now);
-    db.ttl.insert(key, Duration::from_secs(secs as u64));
+    db.ttl.insert(key.clone(), Duration::from_secs(secs as u64));
+    db.notify(NOTIFY_STRING, "set", &key);
+    db.notify(NOTIFY_GENERIC, "expire", &key);
     Frame::ok()
 }
 
 /// PSETEX key milliseconds value
 fn cmd_psetex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let ms: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -308,22 +307,21 @@ fn cmd_psetex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
     let value = args[2].clone();
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
-    db.del(&key);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.string_set(&key, value, now);
-    db.ttl.insert(key, Duration::from_millis(ms as u64));
+    db.ttl.insert(key.clone(), Duration::from_millis(ms as u64));
+    db.notify(NOTIFY_STRING, "set", &key);
+    db.notify(NOTIFY_GENERIC, "expire", &key);
     Frame::ok()
 }
 
 /// GETSET key value
 fn cmd_getset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let value = args[1].clone();
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -339,21 +337,21 @@ fn cmd_getset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
     db.string_set(&key, value, now);
     db.ttl.remove(&key);
+    db.notify(NOTIFY_STRING, "set", &key);
     old
 }
 
 /// MGET key [key ...]
 fn cmd_mget(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
     let mut results = Vec::with_capacity(args.len());
 
     for arg in args {
-        let key = String::from_utf8_lossy(arg);
-        db.check_ttl(&key);
-        match db.key_type(&key) {
+        let key = arg;
+        db.check_ttl_read(key);
+        match db.key_type(key) {
             Some(KeyType::String) => {
-                if let Some(val) = db.string_get(&key) {
+                if let Some(val) = db.string_get(key) {
                     results.push(Frame::Bulk(val.clone().into()));
                 } else {
                     results.push(Frame::Null);
@@ -372,15 +370,15 @@ fn cmd_mset(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
         return Frame::error(err_wrong_number("mset"));
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     for pair in args.chunks_exact(2) {
-        let key = String::from_utf8_lossy(&pair[0]).into_owned();
+        let key = pair[0].clone();
         let value = pair[1].clone();
-        db.del(&key);
         db.string_set(&key, value, now);
+        db.ttl.remove(&key);
+        db.notify(NOTIFY_STRING, "set", &key);
     }
 
     Frame::ok()
@@ -392,23 +390,23 @@ fn cmd_msetnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         return Frame::error(err_wrong_number("msetnx"));
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     // Check if ANY key already exists
     for pair in args.chunks_exact(2) {
-        let key = String::from_utf8_lossy(&pair[0]);
-        if db.keys.contains_key(key.as_ref()) {
+        let key = &pair[0];
+        if db.keys.contains_key(key.as_slice()) {
             return Frame::Integer(0);
         }
     }
 
     // Set all
     for pair in args.chunks_exact(2) {
-        let key = String::from_utf8_lossy(&pair[0]).into_owned();
+        let key = pair[0].clone();
         let value = pair[1].clone();
         db.string_set(&key, value, now);
+        db.notify(NOTIFY_STRING, "set", &key);
     }
 
     Frame::Integer(1)
@@ -416,8 +414,8 @@ fn cmd_msetnx(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// INCR key
 fn cmd_incr(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    match string_incr(state, ctx, &key, 1) {
+    let key = &args[0];
+    match string_incr(state, ctx, key, 1) {
         Ok(n) => Frame::Integer(n),
         Err(f) => f,
     }
@@ -425,12 +423,12 @@ fn cmd_incr(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// INCRBY key increment
 fn cmd_incrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let delta: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
     };
-    match string_incr(state, ctx, &key, delta) {
+    match string_incr(state, ctx, key, delta) {
         Ok(n) => Frame::Integer(n),
         Err(f) => f,
     }
@@ -438,7 +436,7 @@ fn cmd_incrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// INCRBYFLOAT key increment
 fn cmd_incrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let delta_str = String::from_utf8_lossy(&args[1]).into_owned();
 
     // Validate by parsing as f64 first
@@ -450,9 +448,8 @@ fn cmd_incrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
         return Frame::error(MSG_INVALID_FLOAT);
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -483,13 +480,14 @@ fn cmd_incrbyfloat(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]
     }
 
     db.string_set(&key, formatted.as_bytes().to_vec(), now);
+    db.notify(NOTIFY_STRING, "incrbyfloat", &key);
     Frame::Bulk(formatted.into_bytes().into())
 }
 
 /// DECR key
 fn cmd_decr(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    match string_incr(state, ctx, &key, -1) {
+    let key = &args[0];
+    match string_incr(state, ctx, key, -1) {
         Ok(n) => Frame::Integer(n),
         Err(f) => f,
     }
@@ -497,12 +495,12 @@ fn cmd_decr(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Fr
 
 /// DECRBY key decrement
 fn cmd_decrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let delta: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
     };
-    match string_incr(state, ctx, &key, -delta) {
+    match string_incr(state, ctx, key, -delta) {
         Ok(n) => Frame::Integer(n),
         Err(f) => f,
     }
@@ -510,18 +508,17 @@ fn cmd_decrby(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// STRLEN key
 fn cmd_strlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let key = &args[0];
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    match db.string_get(&key) {
+    match db.string_get(key) {
         Some(val) => Frame::Integer(val.len() as i64),
         None => Frame::Integer(0),
     }
@@ -529,12 +526,11 @@ fn cmd_strlen(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// APPEND key value
 fn cmd_append(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let value = &args[1];
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -547,12 +543,13 @@ fn cmd_append(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     current.extend_from_slice(value);
     let new_len = current.len() as i64;
     db.string_set(&key, current, now);
+    db.notify(NOTIFY_STRING, "append", &key);
     Frame::Integer(new_len)
 }
 
 /// GETRANGE key start end (also aliased as SUBSTR)
 fn cmd_getrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let start: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -562,17 +559,16 @@ fn cmd_getrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
         None => return Frame::error(MSG_INVALID_INT),
     };
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let val = match db.string_get(&key) {
+    let val = match db.string_get(key) {
         Some(v) => v.clone(),
         None => return Frame::Bulk(bytes::Bytes::new()),
     };
@@ -588,7 +584,7 @@ fn cmd_getrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
 
 /// SETRANGE key offset value
 fn cmd_setrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let offset: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -599,9 +595,8 @@ fn cmd_setrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
     let offset = offset as usize;
     let replacement = &args[2];
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -622,14 +617,14 @@ fn cmd_setrange(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -
     val[offset..offset + replacement.len()].copy_from_slice(replacement);
     let new_len = val.len() as i64;
     db.string_set(&key, val, now);
+    db.notify(NOTIFY_STRING, "setrange", &key);
     Frame::Integer(new_len)
 }
 
 /// GETDEL key
 fn cmd_getdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let key = args[0].clone();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -648,12 +643,13 @@ fn cmd_getdel(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         .unwrap_or(Frame::Null);
 
     db.del(&key);
+    db.notify(NOTIFY_GENERIC, "del", &key);
     val
 }
 
 /// GETEX key [PERSIST | EX seconds | PX ms | EXAT ts | PXAT ts]
 fn cmd_getex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
 
     // Parse options
     let mut persist = false;
@@ -705,11 +701,9 @@ fn cmd_getex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
                 if ts <= 0 {
                     return Frame::error(MSG_INVALID_SE_TIME);
                 }
-                let inner = state.lock();
-                let now = inner.effective_now();
+                let now = state.now();
                 let target = std::time::UNIX_EPOCH + Duration::from_secs(ts as u64);
                 ex = Some(target.duration_since(now).unwrap_or(Duration::ZERO));
-                drop(inner);
             }
             "PXAT" => {
                 if args.len() != 3 {
@@ -722,18 +716,15 @@ fn cmd_getex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
                 if ts <= 0 {
                     return Frame::error(MSG_INVALID_SE_TIME);
                 }
-                let inner = state.lock();
-                let now = inner.effective_now();
+                let now = state.now();
                 let target = std::time::UNIX_EPOCH + Duration::from_millis(ts as u64);
                 ex = Some(target.duration_since(now).unwrap_or(Duration::ZERO));
-                drop(inner);
             }
             _ => return Frame::error(MSG_SYNTAX_ERROR),
         }
     }
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if !db.keys.contains_key(&key) {
@@ -748,9 +739,12 @@ fn cmd_getex(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
 
     // Apply TTL changes
     if persist {
-        db.ttl.remove(&key);
+        if db.ttl.remove(&key).is_some() {
+            db.notify(NOTIFY_GENERIC, "persist", &key);
+        }
     } else if let Some(ttl) = ex {
         db.ttl.insert(key.clone(), ttl);
+        db.notify(NOTIFY_GENERIC, "expire", &key);
     }
 
     match db.string_get(&key) {
@@ -930,7 +924,7 @@ fn parse_decimal_fixed(s: &str, prec: u32) -> Option<i128> {
 
 /// GETBIT key offset
 fn cmd_getbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let offset: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error("ERR bit offset is not an integer or out of range"),
@@ -940,17 +934,16 @@ fn cmd_getbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
     let offset = offset as usize;
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let val = db.string_get(&key).cloned().unwrap_or_default();
+    let val = db.string_get(key).cloned().unwrap_or_default();
     let byte_idx = offset / 8;
     let bit_idx = 7 - (offset % 8);
 
@@ -964,7 +957,7 @@ fn cmd_getbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
 
 /// SETBIT key offset value
 fn cmd_setbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]).into_owned();
+    let key = args[0].clone();
     let offset: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error("ERR bit offset is not an integer or out of range"),
@@ -982,9 +975,8 @@ fn cmd_setbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         return Frame::error("ERR bit is not an integer or out of range");
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
     db.check_ttl(&key);
 
     if let Some(t) = db.key_type(&key)
@@ -1011,24 +1003,24 @@ fn cmd_setbit(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
 
     db.string_set(&key, val, now);
+    db.notify(NOTIFY_STRING, "setbit", &key);
     Frame::Integer(old_bit as i64)
 }
 
 /// BITCOUNT key [start end [BYTE|BIT]]
 fn cmd_bitcount(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let val = db.string_get(&key).cloned().unwrap_or_default();
+    let val = db.string_get(key).cloned().unwrap_or_default();
 
     if args.len() == 1 {
         // Count all bits
@@ -1113,19 +1105,15 @@ fn bitcount_range(start: i64, end: i64, len: i64) -> (i64, i64) {
 /// BITOP operation destkey key [key ...]
 fn cmd_bitop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
     let op = String::from_utf8_lossy(&args[0]).to_uppercase();
-    let dest = String::from_utf8_lossy(&args[1]).into_owned();
-    let src_keys: Vec<String> = args[2..]
-        .iter()
-        .map(|a| String::from_utf8_lossy(a).into_owned())
-        .collect();
+    let dest = args[1].clone();
+    let src_keys: Vec<Vec<u8>> = args[2..].to_vec();
 
     if op == "NOT" && src_keys.len() != 1 {
         return Frame::error("ERR BITOP NOT must be called with a single source key.");
     }
 
-    let mut inner = state.lock();
-    let now = inner.effective_now();
-    let db = inner.db_mut(ctx.selected_db);
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
 
     // Collect all source values
     let mut values: Vec<Vec<u8>> = Vec::new();
@@ -1185,16 +1173,19 @@ fn cmd_bitop(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
     let len = result.len() as i64;
     if result.is_empty() {
         // No source data → delete destination key (like Redis)
-        db.del(&dest);
+        if db.del(&dest) {
+            db.notify(NOTIFY_GENERIC, "del", &dest);
+        }
     } else {
         db.string_set(&dest, result, now);
+        db.notify(NOTIFY_STRING, "set", &dest);
     }
     Frame::Integer(len)
 }
 
 /// BITPOS key bit [start [end [BYTE|BIT]]]
 fn cmd_bitpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
-    let key = String::from_utf8_lossy(&args[0]);
+    let key = &args[0];
     let target_bit: i64 = match parse_int(&args[1]) {
         Some(n) => n,
         None => return Frame::error(MSG_INVALID_INT),
@@ -1204,18 +1195,17 @@ fn cmd_bitpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
     }
     let target = target_bit as u8;
 
-    let mut inner = state.lock();
-    let db = inner.db_mut(ctx.selected_db);
-    db.check_ttl(&key);
+    let mut db = state.db(ctx.selected_db);
+    db.check_ttl_read(key);
 
-    if let Some(t) = db.key_type(&key)
+    if let Some(t) = db.key_type(key)
         && t != KeyType::String
     {
         return Frame::error(MSG_WRONG_TYPE);
     }
 
-    let key_exists = db.keys.contains_key(key.as_ref());
-    let val = db.string_get(&key).cloned().unwrap_or_default();
+    let key_exists = db.keys.contains_key(key.as_slice());
+    let val = db.string_get(key).cloned().unwrap_or_default();
     if val.is_empty() {
         if !key_exists && target == 0 {
             // Non-existent key: virtual infinite zeros, first 0-bit at position 0
@@ -1320,3 +1310,276 @@ fn cmd_bitpos(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) ->
         Frame::Integer(-1)
     }
 }
+
+// ── BITFIELD ─────────────────────────────────────────────────────────
+
+const MSG_INVALID_BITFIELD_TYPE: &str = "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.";
+const MSG_INVALID_BIT_OFFSET: &str = "ERR bit offset is not an integer or out of range";
+
+/// What BITFIELD does when a SET or INCRBY doesn't fit the field.
+#[derive(Clone, Copy, PartialEq)]
+enum Overflow {
+    Wrap,
+    Sat,
+    Fail,
+}
+
+enum BitfieldOp {
+    Get,
+    Set(i64),
+    Incrby(i64),
+}
+
+struct BitfieldCmd {
+    op: BitfieldOp,
+    signed: bool,
+    bits: u32,
+    offset: usize,
+    overflow: Overflow,
+}
+
+/// BITFIELD key [GET encoding offset | [OVERFLOW WRAP|SAT|FAIL]
+/// SET encoding offset value | INCRBY encoding offset increment ...]
+fn cmd_bitfield(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    bitfield_impl(state, ctx, args, false)
+}
+
+/// BITFIELD_RO key [GET encoding offset ...]
+fn cmd_bitfield_ro(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> Frame {
+    bitfield_impl(state, ctx, args, true)
+}
+
+fn bitfield_impl(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+    read_only: bool,
+) -> Frame {
+    let key = args[0].clone();
+
+    let mut cmds = Vec::new();
+    let mut overflow = Overflow::Wrap;
+    let mut i = 1;
+    while i < args.len() {
+        let sub = String::from_utf8_lossy(&args[i]).to_uppercase();
+        let argc = match sub.as_str() {
+            "GET" => 2,
+            "OVERFLOW" => 1,
+            "SET" | "INCRBY" => 3,
+            _ => return Frame::error(MSG_SYNTAX_ERROR),
+        };
+        if i + argc >= args.len() {
+            return Frame::error(MSG_SYNTAX_ERROR);
+        }
+        if read_only && sub != "GET" {
+            return Frame::error("ERR BITFIELD_RO only supports the GET subcommand");
+        }
+
+        if sub == "OVERFLOW" {
+            overflow = match String::from_utf8_lossy(&args[i + 1])
+                .to_uppercase()
+                .as_str()
+            {
+                "WRAP" => Overflow::Wrap,
+                "SAT" => Overflow::Sat,
+                "FAIL" => Overflow::Fail,
+                _ => return Frame::error("ERR Invalid OVERFLOW type specified"),
+            };
+            i += 2;
+            continue;
+        }
+
+        let Some((signed, bits)) = parse_bitfield_type(&args[i + 1]) else {
+            return Frame::error(MSG_INVALID_BITFIELD_TYPE);
+        };
+        let Some(offset) = parse_bitfield_offset(&args[i + 2], bits) else {
+            return Frame::error(MSG_INVALID_BIT_OFFSET);
+        };
+        let op = match sub.as_str() {
+            "GET" => BitfieldOp::Get,
+            _ => {
+                let Some(n) = parse_int(&args[i + 3]) else {
+                    return Frame::error(MSG_INVALID_INT);
+                };
+                if sub == "SET" {
+                    BitfieldOp::Set(n)
+                } else {
+                    BitfieldOp::Incrby(n)
+                }
+            }
+        };
+        cmds.push(BitfieldCmd {
+            op,
+            signed,
+            bits,
+            offset,
+            overflow,
+        });
+        i += argc + 1;
+    }
+
+    let now = state.now();
+    let mut db = state.db(ctx.selected_db);
+    if read_only {
+        db.check_ttl_read(&key);
+    } else {
+        db.check_ttl(&key);
+    }
+    if let Some(t) = db.key_type(&key)
+        && t != KeyType::String
+    {
+        return Frame::error(MSG_WRONG_TYPE);
+    }
+
+    let mut val = db.string_get(&key).cloned().unwrap_or_default();
+    // Like SETBIT, writes grow the string to fit, even if they then fail.
+    let write_end = cmds
+        .iter()
+        .filter(|c| !matches!(c.op, BitfieldOp::Get))
+        .map(|c| (c.offset + c.bits as usize).div_ceil(8))
+        .max();
+    let grown = write_end.is_some_and(|end| end > val.len());
+    if let Some(end) = write_end
+        && end > val.len()
+    {
+        val.resize(end, 0);
+    }
+
+    let mut changes = 0;
+    let mut results = Vec::with_capacity(cmds.len());
+    for cmd in &cmds {
+        let old = get_bits(&val, cmd.offset, cmd.bits);
+        let (new, reply) = match cmd.op {
+            BitfieldOp::Get => {
+                results.push(Frame::Integer(bitfield_value(old, cmd.signed, cmd.bits)));
+                continue;
+            }
+            BitfieldOp::Set(n) => {
+                let new = bitfield_overflow(n as u64, 0, cmd);
+                (new, new.map(|_| bitfield_value(old, cmd.signed, cmd.bits)))
+            }
+            BitfieldOp::Incrby(n) => {
+                let new =
+                    bitfield_overflow(bitfield_value(old, cmd.signed, cmd.bits) as u64, n, cmd);
+                (new, new.map(|v| bitfield_value(v, cmd.signed, cmd.bits)))
+            }
+        };
+        match (new, reply) {
+            (Some(new), Some(reply)) => {
+                set_bits(&mut val, cmd.offset, cmd.bits, new);
+                changes += 1;
+                results.push(Frame::Integer(reply));
+            }
+            _ => results.push(Frame::Null),
+        }
+    }
+
+    if changes > 0 || grown {
+        db.string_set(&key, val, now);
+    }
+    if changes > 0 {
+        db.notify(NOTIFY_STRING, "setbit", &key);
+    }
+    Frame::Array(results)
+}
+
+/// Parse a BITFIELD type: i1 to i64, or u1 to u63.
+fn parse_bitfield_type(arg: &[u8]) -> Option<(bool, u32)> {
+    let (signed, bits) = match arg.split_first()? {
+        (b'i' | b'I', rest) => (true, rest),
+        (b'u' | b'U', rest) => (false, rest),
+        _ => return None,
+    };
+    let bits: u32 = std::str::from_utf8(bits).ok()?.parse().ok()?;
+    let max = if signed { 64 } else { 63 };
+    (1..=max).contains(&bits).then_some((signed, bits))
+}
+
+/// Parse a BITFIELD offset. `#n` means the n-th field of `bits` bits.
+fn parse_bitfield_offset(arg: &[u8], bits: u32) -> Option<usize> {
+    let (multiplier, num) = match arg.strip_prefix(b"#") {
+        Some(num) => (bits as i64, num),
+        None => (1, arg),
+    };
+    let offset = parse_int(num)?.checked_mul(multiplier)?;
+    // Strings are at most 512MB.
+    (0..512 * 1024 * 1024 * 8)
+        .contains(&offset)
+        .then_some(offset as usize)
+}
+
+/// Read `bits` bits at bit `offset`, most significant bit first. Bits
+/// past the end of the string are 0.
+fn get_bits(val: &[u8], offset: usize, bits: u32) -> u64 {
+    let mut v = 0u64;
+    for pos in offset..offset + bits as usize {
+        let bit = val.get(pos / 8).map_or(0, |b| (b >> (7 - pos % 8)) & 1);
+        v = (v << 1) | bit as u64;
+    }
+    v
+}
+
+/// Write the low `bits` bits of `v` at bit `offset`. `val` must be long
+/// enough.
+fn set_bits(val: &mut [u8], offset: usize, bits: u32, v: u64) {
+    for (i, pos) in (offset..offset + bits as usize).enumerate() {
+        let bit = (v >> (bits as usize - 1 - i)) & 1;
+        if bit == 1 {
+            val[pos / 8] |= 1 << (7 - pos % 8);
+        } else {
+            val[pos / 8] &= !(1 << (7 - pos % 8));
+        }
+    }
+}
+
+/// Interpret the low `bits` bits of `raw` as a signed or unsigned field.
+fn bitfield_value(raw: u64, signed: bool, bits: u32) -> i64 {
+    if signed && bits < 64 && raw & (1 << (bits - 1)) != 0 {
+        (raw | (u64::MAX << bits)) as i64
+    } else {
+        raw as i64
+    }
+}
+
+/// Add `incr` to the field value `value` (as stored: two's complement for
+/// signed fields), applying the overflow policy. Returns the bits to
+/// store, or None if the operation fails (OVERFLOW FAIL).
+/// Follows Redis' checkSignedBitfieldOverflow and
+/// checkUnsignedBitfieldOverflow.
+fn bitfield_overflow(value: u64, incr: i64, cmd: &BitfieldCmd) -> Option<u64> {
+    let bits = cmd.bits;
+    let mask = if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    };
+    let wrapped = value.wrapping_add(incr as u64) & mask;
+
+    let saturated = if cmd.signed {
+        let max = if bits == 64 {
+            i64::MAX
+        } else {
+            (1 << (bits - 1)) - 1
+        };
+        let min = -max - 1;
+        let saturated = match (value as i64).checked_add(incr) {
+            Some(sum) if (min..=max).contains(&sum) => return Some(sum as u64 & mask),
+            Some(sum) if sum > max => max,
+            None if incr > 0 => max,
+            _ => min,
+        };
+        saturated as u64
+    } else {
+        match value.checked_add_signed(incr) {
+            Some(sum) if sum <= mask => return Some(sum),
+            _ if incr >= 0 || value > mask => mask,
+            _ => 0,
+        }
+    };
+
+    match cmd.overflow {
+        Overflow::Wrap => Some(wrapped),
+        Overflow::Sat => Some(saturated & mask),
+        Overflow::Fail => None,
+    }
+}
@@ -41,11 +41,10 @@ fn cmd_watch(state: &Arc<SharedState>, ctx: &mut ConnCtx, args: &[Vec<u8>]) -> F
         return Frame::error("ERR WATCH inside MULTI is not allowed");
     }
 
-    let inner = state.lock();
-    let db = inner.db(ctx.selected_db);
+    let db = state.db(ctx.selected_db);
 
     for arg in args {
-        let key = String::from_utf8_lossy(arg).to_string();
+        let key = arg.to_vec();
         let version = db.key_version.get(&key).copied().unwrap_or(0);
         ctx.watch.insert((ctx.selected_db, key), version);
     }
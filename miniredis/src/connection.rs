@@ -112,12 +112,14 @@ pub struct ConnCtx {
     pub selected_db: usize,
     /// True once the client has sent a valid AUTH command (when passwords are configured).
     pub authenticated: bool,
+    /// The ACL user commands run as.
+    pub user: String,
     /// If Some, we're inside a MULTI block; the vec holds queued command args.
     pub transaction: Option<Vec<QueuedCommand>>,
     /// Set to true if any error occurs while queuing commands in a MULTI.
     pub dirty_transaction: bool,
     /// WATCH map: (db_index, key) -> version at WATCH time.
-    pub watch: HashMap<(usize, String), u64>,
+    pub watch: HashMap<(usize, Vec<u8>), u64>,
     /// True if the client negotiated RESP3 via HELLO.
     pub resp3: bool,
     /// CLIENT SETNAME value.
@@ -130,6 +132,24 @@ pub struct ConnCtx {
     pub pending_subscribe: Vec<String>,
     /// Patterns to subscribe to after EXEC completes (for PSUBSCRIBE inside MULTI).
     pub pending_psubscribe: Vec<String>,
+    /// Shard channels to subscribe to after EXEC completes (for SSUBSCRIBE inside MULTI).
+    pub pending_ssubscribe: Vec<String>,
+    /// Unique client ID (CLIENT ID). 0 for contexts without a connection.
+    pub client_id: u64,
+    /// CLIENT CACHING yes/no, applies to the next command only.
+    pub tracking_caching: Option<bool>,
+    /// Set by ASKING; lets the next command use a slot this cluster node
+    /// is importing.
+    pub asking: bool,
+    /// Set for the link a replica applies its master's commands through;
+    /// it may write to a read-only replica.
+    pub master_link: bool,
+    /// Replication offset after this client's last write, for WAIT.
+    pub repl_offset: u64,
+    /// The client's address, None for contexts without a connection.
+    pub addr: Option<std::net::SocketAddr>,
+    /// Set by MONITOR; the client may no longer use the keyspace.
+    pub monitor: bool,
 }
 
 /// A command queued inside a MULTI transaction.
@@ -149,6 +169,7 @@ impl ConnCtx {
         ConnCtx {
             selected_db: 0,
             authenticated: false,
+            user: "default".to_string(),
             transaction: None,
             dirty_transaction: false,
             watch: HashMap::new(),
@@ -158,6 +179,14 @@ impl ConnCtx {
             nested_sha: None,
             pending_subscribe: Vec::new(),
             pending_psubscribe: Vec::new(),
+            pending_ssubscribe: Vec::new(),
+            client_id: 0,
+            tracking_caching: None,
+            asking: false,
+            master_link: false,
+            repl_offset: 0,
+            addr: None,
+            monitor: false,
         }
     }
 
@@ -1,42 +1,142 @@
+use std::cell::Cell;
 use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::Arc;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, SystemTime};
 
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 use tokio::sync::{Notify, broadcast};
 
+use crate::connection::ConnCtx;
 use crate::hll::HyperLogLog;
+use crate::pubsub::{
+    KeyspaceEvent, NOTIFY_EXPIRED, NOTIFY_GENERIC, NOTIFY_HASH, NOTIFY_KEY_MISS, NOTIFY_KEYEVENT,
+    NOTIFY_KEYSPACE, NOTIFY_NEW,
+};
 use crate::types::{KeyType, SortedSet, Stream};
 
+/// Source of `Access::clock` values, shared by all databases.
+static ACCESS_CLOCK: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The client whose command runs on this thread, if any.
+    static RUNNING: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Credits the keyspace events, reads and writes made on this thread to
+/// the command of a client, until dropped. Commands run in parallel, so
+/// this is how each publishes its own in [`SharedState::publish_changes`].
+pub struct CommandScope(Option<u64>);
+
+impl CommandScope {
+    pub fn enter(client_id: u64) -> Self {
+        CommandScope(RUNNING.replace(Some(client_id)))
+    }
+}
+
+impl Drop for CommandScope {
+    fn drop(&mut self) {
+        RUNNING.set(self.0);
+    }
+}
+
+/// What [`SharedState::publish_changes`] takes from the databases.
+#[derive(Default)]
+struct Changes {
+    /// Keyspace events and the index of their database, in the order they
+    /// were raised.
+    events: Vec<(usize, KeyspaceEvent)>,
+    /// Keys the command read.
+    read: Vec<Vec<u8>>,
+    /// Keys the command modified.
+    modified: Vec<Vec<u8>>,
+    /// Keys modified outside commands, e.g. through the `Miniredis` API.
+    modified_outside: Vec<Vec<u8>>,
+    /// Whether a database was flushed.
+    flushed: bool,
+}
+
+/// Remove the entries `take` picks by their client from `entries`.
+fn take_entries<T>(
+    entries: &mut Vec<(Option<u64>, T)>,
+    take: impl Fn(Option<u64>) -> bool,
+) -> Vec<(Option<u64>, T)> {
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    let (taken, kept) = std::mem::take(entries)
+        .into_iter()
+        .partition(|(client, _)| take(*client));
+    *entries = kept;
+    taken
+}
+
+/// How recently and how often a key was used, for the LRU and LFU
+/// eviction policies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Access {
+    /// Position in the order of all key accesses; lower is less recent.
+    pub clock: u64,
+    /// Number of accesses, saturating at 255.
+    pub freq: u8,
+}
+
 /// A single numbered Redis database (0-15).
 #[derive(Debug)]
 pub struct RedisDB {
     /// Master map: key name -> type tag.
-    pub keys: HashMap<String, KeyType>,
+    pub keys: HashMap<Vec<u8>, KeyType>,
     /// String values.
-    pub string_keys: HashMap<String, Vec<u8>>,
+    pub string_keys: HashMap<Vec<u8>, Vec<u8>>,
     /// Hash values: key -> (field -> value).
-    pub hash_keys: HashMap<String, HashMap<String, Vec<u8>>>,
+    pub hash_keys: HashMap<Vec<u8>, HashMap<Vec<u8>, Vec<u8>>>,
     /// List values.
-    pub list_keys: HashMap<String, VecDeque<Vec<u8>>>,
+    pub list_keys: HashMap<Vec<u8>, VecDeque<Vec<u8>>>,
     /// Set values.
-    pub set_keys: HashMap<String, HashSet<String>>,
+    pub set_keys: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
     /// Sorted set values.
-    pub sorted_set_keys: HashMap<String, SortedSet>,
+    pub sorted_set_keys: HashMap<Vec<u8>, SortedSet>,
     /// Stream values.
-    pub stream_keys: HashMap<String, Stream>,
+    pub stream_keys: HashMap<Vec<u8>, Stream>,
     /// HyperLogLog values.
-    pub hll_keys: HashMap<String, HyperLogLog>,
+    pub hll_keys: HashMap<Vec<u8>, HyperLogLog>,
+    /// JSON documents.
+    #[cfg(feature = "json")]
+    pub json_keys: HashMap<Vec<u8>, serde_json::Value>,
+    /// Bloom filters, Cuckoo filters, Count-Min sketches and Top-Ks.
+    #[cfg(feature = "bloom")]
+    pub bloom_keys: HashMap<Vec<u8>, crate::bloom::Sketch>,
     /// Key TTLs (remaining duration).
-    pub ttl: HashMap<String, Duration>,
+    pub ttl: HashMap<Vec<u8>, Duration>,
     /// Hash field TTLs: key -> (field -> remaining duration).
-    pub hash_field_ttls: HashMap<String, HashMap<String, Duration>>,
+    pub hash_field_ttls: HashMap<Vec<u8>, HashMap<Vec<u8>, Duration>>,
     /// Key versions (bumped on every mutation, used by WATCH).
-    pub key_version: HashMap<String, u64>,
+    pub key_version: HashMap<Vec<u8>, u64>,
     /// Last-recently-used timestamps.
-    pub lru: HashMap<String, SystemTime>,
+    pub lru: HashMap<Vec<u8>, SystemTime>,
+    /// Access order and counts, for the maxmemory eviction policies.
+    pub access: HashMap<Vec<u8>, Access>,
+    /// Cached key size estimates, kept while `maxmemory` is set.
+    pub memory: Option<crate::eviction::MemoryUsage>,
+    /// Keyspace notification flags (mirrors `Inner::notify_keyspace_events`).
+    pub notify_flags: u32,
+    /// Keyspace events raised since they were last taken. Like the keys
+    /// below, each comes with the client whose command raised it, or None
+    /// outside commands.
+    pub events: Vec<(Option<u64>, KeyspaceEvent)>,
+    /// Whether any client uses CLIENT TRACKING; only then are the keys
+    /// below recorded.
+    pub tracking: bool,
+    /// Keys read by running commands, for CLIENT TRACKING.
+    pub read_keys: Vec<(Option<u64>, Vec<u8>)>,
+    /// Keys modified since invalidations were last sent.
+    pub modified_keys: Vec<(Option<u64>, Vec<u8>)>,
+    /// Set by `flush`; tracking clients get a full invalidation.
+    pub flushed: bool,
+    /// Number of changes made, so replication can tell whether a command
+    /// wrote anything.
+    pub dirty: u64,
 }
 
 impl Default for RedisDB {
@@ -56,47 +156,211 @@ impl RedisDB {
             sorted_set_keys: HashMap::new(),
             stream_keys: HashMap::new(),
             hll_keys: HashMap::new(),
+            #[cfg(feature = "json")]
+            json_keys: HashMap::new(),
+            #[cfg(feature = "bloom")]
+            bloom_keys: HashMap::new(),
             ttl: HashMap::new(),
             hash_field_ttls: HashMap::new(),
             key_version: HashMap::new(),
             lru: HashMap::new(),
+            access: HashMap::new(),
+            memory: None,
+            notify_flags: 0,
+            events: Vec::new(),
+            tracking: false,
+            read_keys: Vec::new(),
+            modified_keys: Vec::new(),
+            flushed: false,
+            dirty: 0,
+        }
+    }
+
+    /// Queue a keyspace event, if notifications for its class are enabled.
+    /// Queued events are published by `SharedState::publish_changes`.
+    pub fn notify(&mut self, class: u32, event: &'static str, key: &[u8]) {
+        if self.notify_flags & class == 0
+            || self.notify_flags & (NOTIFY_KEYSPACE | NOTIFY_KEYEVENT) == 0
+        {
+            return;
+        }
+        self.events.push((
+            RUNNING.get(),
+            KeyspaceEvent {
+                class,
+                event,
+                key: key.to_owned(),
+            },
+        ));
+    }
+
+    /// Register a key with the given type, raising a "new" event if it
+    /// didn't exist yet.
+    pub fn add_key(&mut self, key: &[u8], key_type: KeyType) {
+        if !self.keys.contains_key(key) {
+            self.keys.insert(key.to_owned(), key_type);
+            self.resized(key);
+            self.notify(NOTIFY_NEW, "new", key);
+        }
+    }
+
+    /// Raise a "del" event if `key` is gone, e.g. after a command popped the
+    /// last element of a list.
+    pub fn notify_if_deleted(&mut self, key: &[u8]) {
+        if !self.keys.contains_key(key) {
+            self.notify(NOTIFY_GENERIC, "del", key);
+        }
+    }
+
+    /// Replace whatever is stored at `key` with an empty value of the given
+    /// type, keeping the TTL. Only raises "new" if the key didn't exist.
+    fn overwrite_key(&mut self, key: &[u8], key_type: KeyType) {
+        if self.keys.contains_key(key) {
+            self.del_keep_ttl(key);
+            self.hash_field_ttls.remove(key);
+            self.keys.insert(key.to_owned(), key_type);
+        } else {
+            self.add_key(key, key_type);
         }
     }
 
     /// Check if a key exists (also updates LRU).
-    pub fn exists(&mut self, key: &str, now: SystemTime) -> bool {
+    pub fn exists(&mut self, key: &[u8], now: SystemTime) -> bool {
         if self.keys.contains_key(key) {
             self.lru.insert(key.to_owned(), now);
+            self.touch(key);
             true
         } else {
             false
         }
     }
 
+    /// Estimate the memory usage of a key in bytes (simplified), as reported
+    /// by MEMORY USAGE and counted against maxmemory.
+    pub fn estimate_key_size(&self, key: &[u8]) -> Option<usize> {
+        let kt = *self.keys.get(key)?;
+        let key_overhead = 16 + key.len(); // pointer + key string
+        let value_size = match kt {
+            KeyType::String => self.string_keys.get(key).map(|v| v.len() + 3).unwrap_or(0),
+            KeyType::Hash => self
+                .hash_keys
+                .get(key)
+                .map(|h| h.iter().map(|(f, v)| f.len() + v.len() + 16).sum::<usize>() + 16)
+                .unwrap_or(0),
+            KeyType::List => self
+                .list_keys
+                .get(key)
+                .map(|l| l.iter().map(|v| v.len() + 16).sum::<usize>() + 16)
+                .unwrap_or(0),
+            KeyType::Set => self
+                .set_keys
+                .get(key)
+                .map(|s| s.iter().map(|m| m.len() + 16).sum::<usize>() + 16)
+                .unwrap_or(0),
+            KeyType::SortedSet => self
+                .sorted_set_keys
+                .get(key)
+                .map(|ss| ss.card() * 32 + 16)
+                .unwrap_or(0),
+            KeyType::Stream => 64,
+            KeyType::HyperLogLog => {
+                // 16384 registers + overhead
+                16384 + 24
+            }
+            #[cfg(feature = "json")]
+            KeyType::Json => self
+                .json_keys
+                .get(key)
+                .map(|v| v.to_string().len() + 16)
+                .unwrap_or(0),
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                self.bloom_keys.get(key).map(|s| s.size()).unwrap_or(0)
+            }
+        };
+        Some(key_overhead + value_size)
+    }
+
+    /// Mark `key` as changed, so `used_memory` estimates its size again.
+    pub fn resized(&mut self, key: &[u8]) {
+        if let Some(memory) = &mut self.memory {
+            memory.stale.insert(key.to_owned());
+        }
+    }
+
+    /// Start or stop caching key sizes for `used_memory`.
+    pub fn track_memory(&mut self, on: bool) {
+        self.memory = on.then(|| crate::eviction::MemoryUsage {
+            stale: self.keys.keys().cloned().collect(),
+            ..Default::default()
+        });
+    }
+
+    /// The estimated memory use of all keys. With `track_memory` on, only
+    /// the keys changed since the last call are estimated again.
+    pub fn used_memory(&mut self) -> usize {
+        let Some(mut memory) = self.memory.take() else {
+            return self
+                .keys
+                .keys()
+                .filter_map(|k| self.estimate_key_size(k))
+                .sum();
+        };
+        for key in std::mem::take(&mut memory.stale) {
+            let size = self.estimate_key_size(&key);
+            memory.set(key, size);
+        }
+        let total = memory.total;
+        self.memory = Some(memory);
+        total
+    }
+
     /// Get the type of a key, or None.
-    pub fn key_type(&self, key: &str) -> Option<KeyType> {
+    pub fn key_type(&self, key: &[u8]) -> Option<KeyType> {
         self.keys.get(key).copied()
     }
 
+    /// Record an access to `key` for the eviction policies.
+    pub fn touch(&mut self, key: &[u8]) {
+        if !self.keys.contains_key(key) {
+            return;
+        }
+        let access = self.access.entry(key.to_owned()).or_default();
+        access.clock = ACCESS_CLOCK.fetch_add(1, Ordering::Relaxed) + 1;
+        access.freq = access.freq.saturating_add(1);
+    }
+
     /// Increment the key version and update LRU.
-    pub fn incr_version(&mut self, key: &str, now: SystemTime) {
+    pub fn incr_version(&mut self, key: &[u8], now: SystemTime) {
         self.lru.insert(key.to_owned(), now);
+        self.touch(key);
         let v = self.key_version.entry(key.to_owned()).or_insert(0);
         *v += 1;
+        self.dirty += 1;
+        if self.tracking {
+            self.modified_keys.push((RUNNING.get(), key.to_owned()));
+        }
+        self.resized(key);
     }
 
     /// Delete a key and its data. Returns true if the key existed.
-    pub fn del(&mut self, key: &str) -> bool {
+    pub fn del(&mut self, key: &[u8]) -> bool {
         let key_type = match self.keys.remove(key) {
             Some(t) => t,
             None => return false,
         };
 
         self.lru.remove(key);
+        self.access.remove(key);
         self.ttl.remove(key);
         self.hash_field_ttls.remove(key);
+        self.resized(key);
         let v = self.key_version.entry(key.to_owned()).or_insert(0);
         *v += 1;
+        self.dirty += 1;
+        if self.tracking {
+            self.modified_keys.push((RUNNING.get(), key.to_owned()));
+        }
 
         match key_type {
             KeyType::String => {
@@ -120,17 +384,26 @@ impl RedisDB {
             KeyType::HyperLogLog => {
                 self.hll_keys.remove(key);
             }
+            #[cfg(feature = "json")]
+            KeyType::Json => {
+                self.json_keys.remove(key);
+            }
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                self.bloom_keys.remove(key);
+            }
         }
 
         true
     }
 
     /// Delete a key without removing its TTL (used by string_set etc.).
-    pub fn del_keep_ttl(&mut self, key: &str) {
+    pub fn del_keep_ttl(&mut self, key: &[u8]) {
         let key_type = match self.keys.remove(key) {
             Some(t) => t,
             None => return,
         };
+        self.resized(key);
 
         match key_type {
             KeyType::String => {
@@ -154,11 +427,19 @@ impl RedisDB {
             KeyType::HyperLogLog => {
                 self.hll_keys.remove(key);
             }
+            #[cfg(feature = "json")]
+            KeyType::Json => {
+                self.json_keys.remove(key);
+            }
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                self.bloom_keys.remove(key);
+            }
         }
     }
 
     /// GET: returns the value of a string key, or None.
-    pub fn string_get(&self, key: &str) -> Option<&Vec<u8>> {
+    pub fn string_get(&self, key: &[u8]) -> Option<&Vec<u8>> {
         if self.keys.get(key) != Some(&KeyType::String) {
             return None;
         }
@@ -166,18 +447,32 @@ impl RedisDB {
     }
 
     /// SET: force-set a string key. Does NOT remove TTL.
-    pub fn string_set(&mut self, key: &str, value: Vec<u8>, now: SystemTime) {
-        self.del_keep_ttl(key);
-        self.keys.insert(key.to_owned(), KeyType::String);
+    pub fn string_set(&mut self, key: &[u8], value: Vec<u8>, now: SystemTime) {
+        self.overwrite_key(key, KeyType::String);
         self.string_keys.insert(key.to_owned(), value);
         self.incr_version(key, now);
     }
 
     // ── Hash helpers ──────────────────────────────────────────────────
 
-    /// Set hash fields. Returns the number of NEW fields added.
-    pub fn hash_set(&mut self, key: &str, pairs: &[(String, Vec<u8>)], now: SystemTime) -> i64 {
-        self.keys.entry(key.to_owned()).or_insert(KeyType::Hash);
+    /// Set hash fields, dropping their TTLs. Returns the number of NEW
+    /// fields added.
+    pub fn hash_set(&mut self, key: &[u8], pairs: &[(Vec<u8>, Vec<u8>)], now: SystemTime) -> i64 {
+        for (field, _) in pairs {
+            self.hash_persist_field(key, field);
+        }
+        self.hash_set_keep_ttl(key, pairs, now)
+    }
+
+    /// Set hash fields, keeping the TTLs they have (HINCRBY). Returns the
+    /// number of NEW fields added.
+    pub fn hash_set_keep_ttl(
+        &mut self,
+        key: &[u8],
+        pairs: &[(Vec<u8>, Vec<u8>)],
+        now: SystemTime,
+    ) -> i64 {
+        self.add_key(key, KeyType::Hash);
         let hash = self.hash_keys.entry(key.to_owned()).or_default();
         let mut new_count = 0i64;
         for (field, value) in pairs {
@@ -191,12 +486,12 @@ impl RedisDB {
     }
 
     /// Get a hash field value.
-    pub fn hash_get(&self, key: &str, field: &str) -> Option<&Vec<u8>> {
+    pub fn hash_get(&self, key: &[u8], field: &[u8]) -> Option<&Vec<u8>> {
         self.hash_keys.get(key)?.get(field)
     }
 
     /// Delete hash fields. Returns the number deleted. Removes key if hash becomes empty.
-    pub fn hash_del(&mut self, key: &str, fields: &[String], now: SystemTime) -> i64 {
+    pub fn hash_del(&mut self, key: &[u8], fields: &[Vec<u8>], now: SystemTime) -> i64 {
         let hash = match self.hash_keys.get_mut(key) {
             Some(h) => h,
             None => return 0,
@@ -207,7 +502,11 @@ impl RedisDB {
                 count += 1;
             }
         }
-        if hash.is_empty() {
+        let empty = hash.is_empty();
+        for field in fields {
+            self.hash_persist_field(key, field);
+        }
+        if empty {
             self.del(key);
         } else {
             self.incr_version(key, now);
@@ -215,11 +514,41 @@ impl RedisDB {
         count
     }
 
+    /// The remaining TTL of a hash field, if it has one.
+    pub fn hash_field_ttl(&self, key: &[u8], field: &[u8]) -> Option<Duration> {
+        self.hash_field_ttls.get(key)?.get(field).copied()
+    }
+
+    /// Set the TTL of a hash field. A zero TTL deletes the field, and the
+    /// key with it if it was the last one.
+    pub fn set_hash_field_ttl(&mut self, key: &[u8], field: &[u8], ttl: Duration, now: SystemTime) {
+        if ttl.is_zero() {
+            self.hash_del(key, &[field.to_vec()], now);
+            return;
+        }
+        self.hash_field_ttls
+            .entry(key.to_owned())
+            .or_default()
+            .insert(field.to_owned(), ttl);
+    }
+
+    /// Drop the TTL of a hash field. Returns whether it had one.
+    pub fn hash_persist_field(&mut self, key: &[u8], field: &[u8]) -> bool {
+        let Some(ttls) = self.hash_field_ttls.get_mut(key) else {
+            return false;
+        };
+        let had_ttl = ttls.remove(field).is_some();
+        if ttls.is_empty() {
+            self.hash_field_ttls.remove(key);
+        }
+        had_ttl
+    }
+
     /// Get all hash field names, sorted.
-    pub fn hash_fields(&self, key: &str) -> Vec<String> {
+    pub fn hash_fields(&self, key: &[u8]) -> Vec<Vec<u8>> {
         match self.hash_keys.get(key) {
             Some(h) => {
-                let mut fields: Vec<String> = h.keys().cloned().collect();
+                let mut fields: Vec<Vec<u8>> = h.keys().cloned().collect();
                 fields.sort();
                 fields
             }
@@ -228,7 +557,7 @@ impl RedisDB {
     }
 
     /// Get all hash values in field-sorted order.
-    pub fn hash_values(&self, key: &str) -> Vec<Vec<u8>> {
+    pub fn hash_values(&self, key: &[u8]) -> Vec<Vec<u8>> {
         let fields = self.hash_fields(key);
         let hash = match self.hash_keys.get(key) {
             Some(h) => h,
@@ -240,8 +569,8 @@ impl RedisDB {
     // ── List helpers ─────────────────────────────────────────────────
 
     /// LPUSH: prepend value(s) to a list. Returns new length.
-    pub fn list_lpush(&mut self, key: &str, values: &[Vec<u8>], now: SystemTime) -> i64 {
-        self.keys.entry(key.to_owned()).or_insert(KeyType::List);
+    pub fn list_lpush(&mut self, key: &[u8], values: &[Vec<u8>], now: SystemTime) -> i64 {
+        self.add_key(key, KeyType::List);
         let list = self.list_keys.entry(key.to_owned()).or_default();
         for v in values {
             list.push_front(v.clone());
@@ -252,8 +581,8 @@ impl RedisDB {
     }
 
     /// RPUSH: append value(s) to a list. Returns new length.
-    pub fn list_rpush(&mut self, key: &str, values: &[Vec<u8>], now: SystemTime) -> i64 {
-        self.keys.entry(key.to_owned()).or_insert(KeyType::List);
+    pub fn list_rpush(&mut self, key: &[u8], values: &[Vec<u8>], now: SystemTime) -> i64 {
+        self.add_key(key, KeyType::List);
         let list = self.list_keys.entry(key.to_owned()).or_default();
         for v in values {
             list.push_back(v.clone());
@@ -264,7 +593,7 @@ impl RedisDB {
     }
 
     /// LPOP: remove and return the first element.
-    pub fn list_lpop(&mut self, key: &str, now: SystemTime) -> Option<Vec<u8>> {
+    pub fn list_lpop(&mut self, key: &[u8], now: SystemTime) -> Option<Vec<u8>> {
         let list = self.list_keys.get_mut(key)?;
         let val = list.pop_front()?;
         if list.is_empty() {
@@ -276,7 +605,7 @@ impl RedisDB {
     }
 
     /// RPOP: remove and return the last element.
-    pub fn list_rpop(&mut self, key: &str, now: SystemTime) -> Option<Vec<u8>> {
+    pub fn list_rpop(&mut self, key: &[u8], now: SystemTime) -> Option<Vec<u8>> {
         let list = self.list_keys.get_mut(key)?;
         let val = list.pop_back()?;
         if list.is_empty() {
@@ -290,8 +619,8 @@ impl RedisDB {
     // ── Set helpers ──────────────────────────────────────────────────
 
     /// SADD: add members to a set. Returns count of new members added.
-    pub fn set_add(&mut self, key: &str, members: &[String], now: SystemTime) -> i64 {
-        self.keys.entry(key.to_owned()).or_insert(KeyType::Set);
+    pub fn set_add(&mut self, key: &[u8], members: &[Vec<u8>], now: SystemTime) -> i64 {
+        self.add_key(key, KeyType::Set);
         let set = self.set_keys.entry(key.to_owned()).or_default();
         let mut added = 0i64;
         for m in members {
@@ -304,7 +633,7 @@ impl RedisDB {
     }
 
     /// SREM: remove members from a set. Returns count removed.
-    pub fn set_rem(&mut self, key: &str, members: &[String], now: SystemTime) -> i64 {
+    pub fn set_rem(&mut self, key: &[u8], members: &[Vec<u8>], now: SystemTime) -> i64 {
         let set = match self.set_keys.get_mut(key) {
             Some(s) => s,
             None => return 0,
@@ -324,10 +653,10 @@ impl RedisDB {
     }
 
     /// Get all members of a set, sorted.
-    pub fn set_members(&self, key: &str) -> Vec<String> {
+    pub fn set_members(&self, key: &[u8]) -> Vec<Vec<u8>> {
         match self.set_keys.get(key) {
             Some(s) => {
-                let mut members: Vec<String> = s.iter().cloned().collect();
+                let mut members: Vec<Vec<u8>> = s.iter().cloned().collect();
                 members.sort();
                 members
             }
@@ -336,7 +665,7 @@ impl RedisDB {
     }
 
     /// Check if a member is in a set.
-    pub fn set_is_member(&self, key: &str, member: &str) -> bool {
+    pub fn set_is_member(&self, key: &[u8], member: &[u8]) -> bool {
         self.set_keys
             .get(key)
             .map(|s| s.contains(member))
@@ -344,12 +673,15 @@ impl RedisDB {
     }
 
     /// Replace a set entirely (used by set operations like SDIFFSTORE).
-    pub fn set_set(&mut self, key: &str, members: HashSet<String>, now: SystemTime) {
+    pub fn set_set(&mut self, key: &[u8], members: HashSet<Vec<u8>>, now: SystemTime) {
         if members.is_empty() {
             return;
         }
-        self.del(key);
-        self.keys.insert(key.to_owned(), KeyType::Set);
+        if self.del(key) {
+            self.keys.insert(key.to_owned(), KeyType::Set);
+        } else {
+            self.add_key(key, KeyType::Set);
+        }
         self.set_keys.insert(key.to_owned(), members);
         self.incr_version(key, now);
     }
@@ -357,10 +689,8 @@ impl RedisDB {
     // ── Sorted set helpers ───────────────────────────────────────────
 
     /// ZADD: add a member with score. Returns true if the member was new.
-    pub fn sset_add(&mut self, key: &str, score: f64, member: &str, now: SystemTime) -> bool {
-        self.keys
-            .entry(key.to_owned())
-            .or_insert(KeyType::SortedSet);
+    pub fn sset_add(&mut self, key: &[u8], score: f64, member: &[u8], now: SystemTime) -> bool {
+        self.add_key(key, KeyType::SortedSet);
         let ss = self.sorted_set_keys.entry(key.to_owned()).or_default();
         let is_new = ss.set(score, member);
         self.incr_version(key, now);
@@ -368,7 +698,7 @@ impl RedisDB {
     }
 
     /// Check if a member exists in a sorted set.
-    pub fn sset_exists(&self, key: &str, member: &str) -> bool {
+    pub fn sset_exists(&self, key: &[u8], member: &[u8]) -> bool {
         self.sorted_set_keys
             .get(key)
             .map(|ss| ss.exists(member))
@@ -376,12 +706,12 @@ impl RedisDB {
     }
 
     /// Get a member's score.
-    pub fn sset_score(&self, key: &str, member: &str) -> Option<f64> {
+    pub fn sset_score(&self, key: &[u8], member: &[u8]) -> Option<f64> {
         self.sorted_set_keys.get(key)?.get(member)
     }
 
     /// Get cardinality of sorted set.
-    pub fn sset_card(&self, key: &str) -> usize {
+    pub fn sset_card(&self, key: &[u8]) -> usize {
         self.sorted_set_keys
             .get(key)
             .map(|ss| ss.card())
@@ -389,10 +719,8 @@ impl RedisDB {
     }
 
     /// ZINCRBY: increment member's score. Returns new score.
-    pub fn sset_incrby(&mut self, key: &str, member: &str, delta: f64, now: SystemTime) -> f64 {
-        self.keys
-            .entry(key.to_owned())
-            .or_insert(KeyType::SortedSet);
+    pub fn sset_incrby(&mut self, key: &[u8], member: &[u8], delta: f64, now: SystemTime) -> f64 {
+        self.add_key(key, KeyType::SortedSet);
         let ss = self.sorted_set_keys.entry(key.to_owned()).or_default();
         let new_score = ss.incrby(member, delta);
         self.incr_version(key, now);
@@ -400,7 +728,7 @@ impl RedisDB {
     }
 
     /// Remove a member from a sorted set. Returns true if it existed.
-    pub fn sset_rem(&mut self, key: &str, member: &str, now: SystemTime) -> bool {
+    pub fn sset_rem(&mut self, key: &[u8], member: &[u8], now: SystemTime) -> bool {
         let ss = match self.sorted_set_keys.get_mut(key) {
             Some(ss) => ss,
             None => return false,
@@ -415,13 +743,16 @@ impl RedisDB {
     }
 
     /// Replace a sorted set entirely.
-    pub fn sset_set(&mut self, key: &str, ss: SortedSet, now: SystemTime) {
+    pub fn sset_set(&mut self, key: &[u8], ss: SortedSet, now: SystemTime) {
         if ss.card() == 0 {
             self.del(key);
             return;
         }
-        self.del(key);
-        self.keys.insert(key.to_owned(), KeyType::SortedSet);
+        if self.del(key) {
+            self.keys.insert(key.to_owned(), KeyType::SortedSet);
+        } else {
+            self.add_key(key, KeyType::SortedSet);
+        }
         self.sorted_set_keys.insert(key.to_owned(), ss);
         self.incr_version(key, now);
     }
@@ -429,14 +760,12 @@ impl RedisDB {
     // ── HyperLogLog helpers ────────────────────────────────────────
 
     /// PFADD: add items to a HyperLogLog. Returns 1 if any register changed, 0 otherwise.
-    pub fn hll_add(&mut self, key: &str, items: &[&str], now: SystemTime) -> i64 {
-        self.keys
-            .entry(key.to_owned())
-            .or_insert(KeyType::HyperLogLog);
+    pub fn hll_add(&mut self, key: &[u8], items: &[&[u8]], now: SystemTime) -> i64 {
+        self.add_key(key, KeyType::HyperLogLog);
         let hll = self.hll_keys.entry(key.to_owned()).or_default();
         let mut changed = false;
         for item in items {
-            if hll.add(item.as_bytes()) {
+            if hll.add(item) {
                 changed = true;
             }
         }
@@ -445,7 +774,7 @@ impl RedisDB {
     }
 
     /// PFCOUNT: count across one or more HLL keys. Returns error if any key is wrong type.
-    pub fn hll_count(&self, keys: &[&str]) -> Result<i64, &'static str> {
+    pub fn hll_count(&self, keys: &[&[u8]]) -> Result<i64, &'static str> {
         if keys.len() == 1 {
             let key = keys[0];
             if let Some(kt) = self.keys.get(key)
@@ -476,7 +805,7 @@ impl RedisDB {
 
     /// PFMERGE: merge source HLLs into dest. keys[0] is dest, rest are sources.
     /// Returns error if any key is wrong type.
-    pub fn hll_merge(&mut self, keys: &[&str], now: SystemTime) -> Result<(), &'static str> {
+    pub fn hll_merge(&mut self, keys: &[&[u8]], now: SystemTime) -> Result<(), &'static str> {
         // Validate all keys first
         for &key in keys {
             if let Some(kt) = self.keys.get(key)
@@ -498,7 +827,7 @@ impl RedisDB {
         }
 
         // Store the result
-        self.keys.insert(dest.to_owned(), KeyType::HyperLogLog);
+        self.add_key(dest, KeyType::HyperLogLog);
         self.hll_keys.insert(dest.to_owned(), merged);
         self.incr_version(dest, now);
         Ok(())
@@ -507,7 +836,7 @@ impl RedisDB {
     // ── Key rename helper ────────────────────────────────────────────
 
     /// Rename a key. Returns false if source doesn't exist.
-    pub fn rename(&mut self, from: &str, to: &str, now: SystemTime) -> bool {
+    pub fn rename(&mut self, from: &[u8], to: &[u8], now: SystemTime) -> bool {
         let key_type = match self.keys.remove(from) {
             Some(t) => t,
             None => return false,
@@ -517,7 +846,7 @@ impl RedisDB {
         self.del(to);
 
         // Move the type tag
-        self.keys.insert(to.to_owned(), key_type);
+        self.add_key(to, key_type);
 
         // Move the actual data
         match key_type {
@@ -556,6 +885,18 @@ impl RedisDB {
                     self.hll_keys.insert(to.to_owned(), v);
                 }
             }
+            #[cfg(feature = "json")]
+            KeyType::Json => {
+                if let Some(v) = self.json_keys.remove(from) {
+                    self.json_keys.insert(to.to_owned(), v);
+                }
+            }
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                if let Some(v) = self.bloom_keys.remove(from) {
+                    self.bloom_keys.insert(to.to_owned(), v);
+                }
+            }
         }
 
         // Move TTL
@@ -572,6 +913,9 @@ impl RedisDB {
         if let Some(lru) = self.lru.remove(from) {
             self.lru.insert(to.to_owned(), lru);
         }
+        if let Some(access) = self.access.remove(from) {
+            self.access.insert(to.to_owned(), access);
+        }
 
         // Update versions
         self.incr_version(from, now);
@@ -581,18 +925,36 @@ impl RedisDB {
     }
 
     /// Check and delete a key if its TTL has expired.
-    pub fn check_ttl(&mut self, key: &str) -> bool {
+    pub fn check_ttl(&mut self, key: &[u8]) -> bool {
         if let Some(&ttl) = self.ttl.get(key)
             && ttl <= Duration::ZERO
         {
+            self.notify(NOTIFY_EXPIRED, "expired", key);
             self.del(key);
             return true; // key was expired
         }
         false // key still alive or has no TTL
     }
 
+    /// `check_ttl` for commands that read `key`: also raises "keymiss" when
+    /// the key isn't there, and records the read for CLIENT TRACKING.
+    pub fn check_ttl_read(&mut self, key: &[u8]) -> bool {
+        let expired = self.check_ttl(key);
+        if self.tracking {
+            self.read_keys.push((RUNNING.get(), key.to_owned()));
+        }
+        if self.keys.contains_key(key) {
+            self.touch(key);
+        } else {
+            self.notify(NOTIFY_KEY_MISS, "keymiss", key);
+        }
+        expired
+    }
+
     /// Remove all keys and values.
     pub fn flush(&mut self) {
+        self.flushed |= self.tracking;
+        self.dirty += 1;
         self.keys.clear();
         self.string_keys.clear();
         self.hash_keys.clear();
@@ -601,20 +963,28 @@ impl RedisDB {
         self.sorted_set_keys.clear();
         self.stream_keys.clear();
         self.hll_keys.clear();
+        #[cfg(feature = "json")]
+        self.json_keys.clear();
+        #[cfg(feature = "bloom")]
+        self.bloom_keys.clear();
         self.ttl.clear();
         self.hash_field_ttls.clear();
         self.key_version.clear();
         self.lru.clear();
+        self.access.clear();
+        if self.memory.is_some() {
+            self.track_memory(true);
+        }
     }
 
     /// Deep-copy a key's data (type, value, TTL) within the same DB. Returns true on success.
-    pub fn copy_key(&mut self, from: &str, to: &str, now: SystemTime) -> bool {
+    pub fn copy_key(&mut self, from: &[u8], to: &[u8], now: SystemTime) -> bool {
         let key_type = match self.keys.get(from) {
             Some(t) => *t,
             None => return false,
         };
 
-        self.keys.insert(to.to_owned(), key_type);
+        self.add_key(to, key_type);
 
         match key_type {
             KeyType::String => {
@@ -652,6 +1022,18 @@ impl RedisDB {
                     self.hll_keys.insert(to.to_owned(), v);
                 }
             }
+            #[cfg(feature = "json")]
+            KeyType::Json => {
+                if let Some(v) = self.json_keys.get(from).cloned() {
+                    self.json_keys.insert(to.to_owned(), v);
+                }
+            }
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                if let Some(v) = self.bloom_keys.get(from).cloned() {
+                    self.bloom_keys.insert(to.to_owned(), v);
+                }
+            }
         }
 
         // Copy TTL
@@ -668,16 +1050,88 @@ impl RedisDB {
         true
     }
 
+    /// Move a key's data (type, value, TTL) to another database, e.g. on
+    /// another cluster node, replacing what `dst` has at `key`. Returns
+    /// false if the key doesn't exist.
+    pub fn move_key(&mut self, key: &[u8], dst: &mut RedisDB, now: SystemTime) -> bool {
+        let Some(key_type) = self.keys.get(key).copied() else {
+            return false;
+        };
+
+        dst.del(key);
+        dst.add_key(key, key_type);
+        match key_type {
+            KeyType::String => {
+                if let Some(v) = self.string_keys.remove(key) {
+                    dst.string_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::Hash => {
+                if let Some(v) = self.hash_keys.remove(key) {
+                    dst.hash_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::List => {
+                if let Some(v) = self.list_keys.remove(key) {
+                    dst.list_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::Set => {
+                if let Some(v) = self.set_keys.remove(key) {
+                    dst.set_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::SortedSet => {
+                if let Some(v) = self.sorted_set_keys.remove(key) {
+                    dst.sorted_set_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::Stream => {
+                if let Some(v) = self.stream_keys.remove(key) {
+                    dst.stream_keys.insert(key.to_owned(), v);
+                }
+            }
+            KeyType::HyperLogLog => {
+                if let Some(v) = self.hll_keys.remove(key) {
+                    dst.hll_keys.insert(key.to_owned(), v);
+                }
+            }
+            #[cfg(feature = "json")]
+            KeyType::Json => {
+                if let Some(v) = self.json_keys.remove(key) {
+                    dst.json_keys.insert(key.to_owned(), v);
+                }
+            }
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                if let Some(v) = self.bloom_keys.remove(key) {
+                    dst.bloom_keys.insert(key.to_owned(), v);
+                }
+            }
+        }
+        if let Some(ttl) = self.ttl.get(key).copied() {
+            dst.ttl.insert(key.to_owned(), ttl);
+        }
+        if let Some(field_ttls) = self.hash_field_ttls.remove(key) {
+            dst.hash_field_ttls.insert(key.to_owned(), field_ttls);
+        }
+        dst.incr_version(key, now);
+
+        self.del(key);
+        true
+    }
+
     /// Return all keys, sorted.
-    pub fn all_keys(&self) -> Vec<String> {
-        let mut keys: Vec<String> = self.keys.keys().cloned().collect();
+    pub fn all_keys(&self) -> Vec<Vec<u8>> {
+        let mut keys: Vec<Vec<u8>> = self.keys.keys().cloned().collect();
         keys.sort();
         keys
     }
 
-    /// Decrease all TTLs by `duration`, deleting expired keys.
-    pub fn fast_forward(&mut self, duration: Duration) {
-        let keys: Vec<String> = self.ttl.keys().cloned().collect();
+    /// Decrease all TTLs by `duration`, deleting expired keys. `now` is
+    /// the time after that.
+    pub fn fast_forward(&mut self, duration: Duration, now: SystemTime) {
+        let keys: Vec<Vec<u8>> = self.ttl.keys().cloned().collect();
         for key in keys {
             if let Some(ttl) = self.ttl.get_mut(&key) {
                 *ttl = ttl.saturating_sub(duration);
@@ -686,15 +1140,15 @@ impl RedisDB {
         }
 
         // Handle hash field TTLs
-        let hash_keys: Vec<String> = self.hash_field_ttls.keys().cloned().collect();
+        let hash_keys: Vec<Vec<u8>> = self.hash_field_ttls.keys().cloned().collect();
         for key in hash_keys {
-            self.check_hash_field_ttls(&key, duration);
+            self.check_hash_field_ttls(&key, duration, now);
         }
     }
 
     /// Check and expire hash field TTLs. Removes expired fields, and if
     /// the hash becomes empty, deletes the key entirely.
-    pub fn check_hash_field_ttls(&mut self, key: &str, duration: Duration) {
+    pub fn check_hash_field_ttls(&mut self, key: &[u8], duration: Duration, now: SystemTime) {
         let field_ttls = match self.hash_field_ttls.get_mut(key) {
             Some(t) => t,
             None => return,
@@ -719,29 +1173,71 @@ impl RedisDB {
             self.hash_field_ttls.remove(key);
         }
 
+        if !expired_fields.is_empty() {
+            self.incr_version(key, now);
+            self.notify(NOTIFY_HASH, "hexpired", key);
+        }
+
         // If hash is now empty, delete the key entirely
         if let Some(hash) = self.hash_keys.get(key)
             && hash.is_empty()
         {
             self.del(key);
+            self.notify(NOTIFY_GENERIC, "del", key);
         }
     }
 }
 
-/// The inner state shared across all connections.
+/// Number of databases (0-15).
+pub const DB_COUNT: usize = 16;
+
+/// Server state shared across all connections, other than the databases.
 /// Protected by a `std::sync::Mutex` (never held across .await).
 #[derive(Debug)]
 pub struct Inner {
-    /// 16 databases (0-15).
-    pub dbs: Vec<RedisDB>,
     /// Cached Lua scripts: SHA1 hex -> source.
     pub scripts: HashMap<String, String>,
-    /// AUTH passwords: username -> password.
-    pub passwords: HashMap<String, String>,
-    /// Mock time. If None, use real time.
-    pub now: Option<SystemTime>,
+    /// Function libraries loaded with FUNCTION LOAD: library name -> library.
+    pub functions: HashMap<String, crate::cmd::functions::FunctionLibrary>,
+    /// ACL users and the ACL LOG.
+    pub acl: crate::acl::Acl,
     /// Seeded RNG for deterministic tests.
     pub rng: StdRng,
+    /// `notify-keyspace-events` flags (see `crate::pubsub::NOTIFY_*`).
+    pub notify_keyspace_events: u32,
+    /// Whether any client has CLIENT TRACKING on.
+    pub tracking: bool,
+    /// Directory SAVE writes the RDB file to (`dir` config).
+    pub dir: String,
+    /// RDB file name (`dbfilename` config).
+    pub dbfilename: String,
+    /// When the last successful save happened (LASTSAVE).
+    pub last_save: SystemTime,
+    /// Clients blocked in BLPOP, BZPOPMIN, etc.
+    pub blocked: crate::blocking::BlockedClients,
+    /// Whether a replica refuses writes from clients (`replica-read-only`
+    /// config).
+    pub replica_read_only: bool,
+    /// Memory limit in bytes (`maxmemory` config); 0 means no limit.
+    pub maxmemory: u64,
+    /// What to evict when over `maxmemory` (`maxmemory-policy` config).
+    pub maxmemory_policy: crate::eviction::EvictionPolicy,
+    /// Number of keys evicted so far (INFO stats).
+    pub evicted_keys: u64,
+    /// Whether memory use was still over `maxmemory` after evicting for
+    /// the running command. Scripts check this before writing.
+    pub oom: bool,
+    /// Commands taking at least this many microseconds go to the slow
+    /// log; negative disables it (`slowlog-log-slower-than` config).
+    pub slowlog_log_slower_than: i64,
+    /// Entries the slow log keeps (`slowlog-max-len` config).
+    pub slowlog_max_len: usize,
+    /// Commands taking at least this many milliseconds are latency
+    /// events; 0 disables them (`latency-monitor-threshold` config).
+    pub latency_monitor_threshold: u64,
+    /// Scripts running longer than this many milliseconds make other
+    /// clients get BUSY (`busy-reply-threshold` config).
+    pub busy_reply_threshold: u64,
 }
 
 impl Default for Inner {
@@ -752,51 +1248,117 @@ impl Default for Inner {
 
 impl Inner {
     pub fn new() -> Self {
-        let mut dbs = Vec::with_capacity(16);
-        for _ in 0..16 {
-            dbs.push(RedisDB::new());
-        }
         Inner {
-            dbs,
             scripts: HashMap::new(),
-            passwords: HashMap::new(),
-            now: None,
+            functions: HashMap::new(),
+            acl: crate::acl::Acl::new(),
             rng: StdRng::from_os_rng(),
+            notify_keyspace_events: 0,
+            tracking: false,
+            dir: ".".to_string(),
+            dbfilename: "dump.rdb".to_string(),
+            last_save: SystemTime::now(),
+            blocked: crate::blocking::BlockedClients::new(),
+            replica_read_only: true,
+            maxmemory: 0,
+            maxmemory_policy: crate::eviction::EvictionPolicy::default(),
+            evicted_keys: 0,
+            oom: false,
+            slowlog_log_slower_than: 10_000,
+            slowlog_max_len: 128,
+            latency_monitor_threshold: 0,
+            busy_reply_threshold: 5000,
         }
     }
 
-    /// Get the effective "now" time (mock or real).
-    pub fn effective_now(&self) -> SystemTime {
-        self.now.unwrap_or_else(SystemTime::now)
+    /// Where SAVE writes the RDB file: `dir`/`dbfilename`.
+    pub fn rdb_path(&self) -> std::path::PathBuf {
+        std::path::Path::new(&self.dir).join(&self.dbfilename)
     }
+}
 
-    /// Advance mock time and expire keys in all databases.
-    pub fn fast_forward(&mut self, duration: Duration) {
-        if let Some(ref mut now) = self.now {
-            *now += duration;
-        }
-        for db in &mut self.dbs {
-            db.fast_forward(duration);
-        }
+/// How much of the keyspace a command needs to itself, see
+/// `SharedState::lock_keyspace`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Isolation {
+    /// Runs alongside everything but exclusive commands.
+    Read,
+    /// Writes to one database: runs alongside reads and writes to other
+    /// databases, and one at a time with other writes to this one.
+    Write(usize),
+    /// Runs alone: transactions, scripts and commands that span databases.
+    Exclusive,
+}
+
+/// Held while a command runs, see `SharedState::lock_keyspace`.
+pub enum KeyspaceGuard<'a> {
+    Shared(RwLockReadGuard<'a, ()>, Option<MutexGuard<'a, ()>>),
+    Exclusive(RwLockWriteGuard<'a, ()>),
+}
+
+impl KeyspaceGuard<'_> {
+    pub fn is_exclusive(&self) -> bool {
+        matches!(self, KeyspaceGuard::Exclusive(_))
     }
+}
+
+/// All databases and the inner state, locked together for work on the
+/// whole dataset: SAVE, replication syncs, eviction and the like.
+pub struct Dataset<'a> {
+    pub dbs: Vec<MutexGuard<'a, RedisDB>>,
+    pub inner: MutexGuard<'a, Inner>,
+    /// The time when the dataset was locked.
+    pub now: SystemTime,
+}
 
-    /// Get a reference to a database.
-    pub fn db(&self, idx: usize) -> &RedisDB {
-        &self.dbs[idx]
+impl Dataset<'_> {
+    /// Number of changes made to all databases.
+    pub fn dirty(&self) -> u64 {
+        self.dbs.iter().map(|db| db.dirty).sum()
     }
 
-    /// Get a mutable reference to a database.
-    pub fn db_mut(&mut self, idx: usize) -> &mut RedisDB {
-        &mut self.dbs[idx]
+    /// Set the `maxmemory` limit, caching key sizes in all databases while
+    /// there is one.
+    pub fn set_maxmemory(&mut self, bytes: u64) {
+        self.inner.maxmemory = bytes;
+        self.inner.oom = false;
+        for db in &mut self.dbs {
+            if db.memory.is_some() != (bytes != 0) {
+                db.track_memory(bytes != 0);
+            }
+        }
+    }
+
+    /// Set the `notify-keyspace-events` flags for all databases.
+    pub fn set_notify_keyspace_events(&mut self, flags: u32) {
+        self.inner.notify_keyspace_events = flags;
+        for db in &mut self.dbs {
+            db.notify_flags = flags;
+            db.events.clear();
+        }
     }
 }
 
 /// The shared state wrapper used across all connections.
-/// `inner` is a std::sync::Mutex (not tokio::sync::Mutex) because we never
-/// hold the lock across an .await point.
+/// The locks are std::sync ones (not tokio::sync) because we never hold
+/// them across an .await point.
+///
+/// Each database has its own lock, so commands on different databases, and
+/// reads of the same one, run in parallel. Locks are taken in this order:
+/// `keyspace`, `writers`, `dbs` (by index), `inner`, and then the others.
 pub struct SharedState {
-    /// The inner database state.
+    /// The databases (0-15).
+    pub dbs: Vec<Mutex<RedisDB>>,
+    /// Everything else: scripts, ACL users, configuration and so on.
     pub inner: std::sync::Mutex<Inner>,
+    /// Mock time. If None, use real time.
+    pub clock: RwLock<Option<SystemTime>>,
+    /// Held shared by every command, and exclusively by the ones that must
+    /// see no other command run in between their steps.
+    pub keyspace: RwLock<()>,
+    /// Held while running commands that may write to a database, so they
+    /// reach the replicas in the order they ran.
+    pub writers: Vec<Mutex<()>>,
     /// Notifies blocking commands (BLPOP, XREAD BLOCK, etc.) when data changes.
     pub notify: Notify,
     /// Shutdown signal broadcaster.
@@ -811,13 +1373,38 @@ pub struct SharedState {
     pub pubsub: std::sync::Mutex<crate::pubsub::PubsubRegistry>,
     /// Command dispatch table (set once at server startup, used by Lua scripting).
     pub command_table: std::sync::OnceLock<Arc<crate::dispatch::CommandTable>>,
+    /// CLIENT TRACKING state of all connections.
+    pub tracking: std::sync::Mutex<crate::tracking::TrackingTable>,
+    /// Last handed out client ID.
+    pub next_client_id: AtomicU64,
+    /// Injected faults.
+    pub faults: std::sync::Mutex<crate::fault::FaultTable>,
+    /// MONITOR clients and the command log.
+    pub monitor: std::sync::Mutex<crate::monitor::Monitor>,
+    /// Slow log, latency events and command statistics.
+    pub latency: std::sync::Mutex<crate::latency::Latency>,
+    /// Running scripts and the redis.log() messages.
+    pub script: std::sync::Mutex<crate::script::ScriptState>,
+    /// Set for the nodes of a `MiniredisCluster`.
+    pub cluster: std::sync::OnceLock<crate::cluster::ClusterHandle>,
+    /// Replication role, links to replicas and the replication offset.
+    pub replication: std::sync::Mutex<crate::replication::Replication>,
+    /// Notified when a replica acknowledges commands, for WAIT.
+    pub repl_acks: Notify,
+    /// Held by a running script, and shared by the commands of clients,
+    /// see `crate::script::wait`.
+    pub script_gate: Arc<tokio::sync::RwLock<()>>,
 }
 
 impl SharedState {
     pub fn new() -> Arc<Self> {
         let (shutdown_tx, _) = broadcast::channel(1);
         Arc::new(SharedState {
+            dbs: (0..DB_COUNT).map(|_| Mutex::new(RedisDB::new())).collect(),
             inner: std::sync::Mutex::new(Inner::new()),
+            clock: RwLock::new(None),
+            keyspace: RwLock::new(()),
+            writers: (0..DB_COUNT).map(|_| Mutex::new(())).collect(),
             notify: Notify::new(),
             shutdown_tx,
             total_connections_received: AtomicU64::new(0),
@@ -825,6 +1412,16 @@ impl SharedState {
             total_commands_processed: AtomicU64::new(0),
             pubsub: std::sync::Mutex::new(crate::pubsub::PubsubRegistry::new()),
             command_table: std::sync::OnceLock::new(),
+            tracking: std::sync::Mutex::new(crate::tracking::TrackingTable::new()),
+            next_client_id: AtomicU64::new(0),
+            faults: std::sync::Mutex::new(crate::fault::FaultTable::new()),
+            monitor: std::sync::Mutex::new(crate::monitor::Monitor::new()),
+            latency: std::sync::Mutex::new(crate::latency::Latency::new()),
+            script: std::sync::Mutex::new(crate::script::ScriptState::new()),
+            cluster: std::sync::OnceLock::new(),
+            replication: std::sync::Mutex::new(crate::replication::Replication::new()),
+            repl_acks: Notify::new(),
+            script_gate: Arc::new(tokio::sync::RwLock::new(())),
         })
     }
 
@@ -832,6 +1429,197 @@ impl SharedState {
     pub fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
         self.inner.lock().unwrap()
     }
+
+    /// Lock a database.
+    pub fn db(&self, idx: usize) -> MutexGuard<'_, RedisDB> {
+        self.dbs[idx].lock().unwrap()
+    }
+
+    /// Lock two different databases, in index order.
+    pub fn db_pair(
+        &self,
+        a: usize,
+        b: usize,
+    ) -> (MutexGuard<'_, RedisDB>, MutexGuard<'_, RedisDB>) {
+        assert_ne!(a, b, "db_pair needs two different databases");
+        if a < b {
+            let first = self.db(a);
+            (first, self.db(b))
+        } else {
+            let first = self.db(b);
+            (self.db(a), first)
+        }
+    }
+
+    /// Lock all databases and the inner state.
+    pub fn lock_all(&self) -> Dataset<'_> {
+        let dbs = self.dbs.iter().map(|db| db.lock().unwrap()).collect();
+        Dataset {
+            dbs,
+            inner: self.lock(),
+            now: self.now(),
+        }
+    }
+
+    /// Keep other commands from interfering with the one about to run, as
+    /// much as `isolation` asks for.
+    pub fn lock_keyspace(&self, isolation: Isolation) -> KeyspaceGuard<'_> {
+        match isolation {
+            Isolation::Read => KeyspaceGuard::Shared(self.keyspace.read().unwrap(), None),
+            Isolation::Write(db) => {
+                let keyspace = self.keyspace.read().unwrap();
+                KeyspaceGuard::Shared(keyspace, Some(self.writers[db].lock().unwrap()))
+            }
+            Isolation::Exclusive => KeyspaceGuard::Exclusive(self.keyspace.write().unwrap()),
+        }
+    }
+
+    /// Get the effective "now" time (mock or real).
+    pub fn now(&self) -> SystemTime {
+        self.clock.read().unwrap().unwrap_or_else(SystemTime::now)
+    }
+
+    /// Set the mock time; None goes back to real time.
+    pub fn set_now(&self, now: Option<SystemTime>) {
+        *self.clock.write().unwrap() = now;
+    }
+
+    /// Number of changes made to all databases.
+    pub fn dirty(&self) -> u64 {
+        self.dbs.iter().map(|db| db.lock().unwrap().dirty).sum()
+    }
+
+    /// Advance mock time and expire keys in all databases, once running
+    /// commands are done.
+    pub fn fast_forward(&self, duration: Duration) {
+        let _keyspace = self.lock_keyspace(Isolation::Exclusive);
+        self.fast_forward_locked(duration);
+    }
+
+    /// `fast_forward` for a caller that holds the keyspace exclusively.
+    pub fn fast_forward_locked(&self, duration: Duration) {
+        if let Some(now) = self.clock.write().unwrap().as_mut() {
+            *now += duration;
+        }
+        let now = self.now();
+        for db in &self.dbs {
+            db.lock().unwrap().fast_forward(duration, now);
+        }
+    }
+
+    /// Take the keyspace events, and the keys read and modified, of the
+    /// command `client` runs, along with what changed outside commands.
+    /// None takes only the latter.
+    fn take_changes(&self, client: Option<u64>) -> Changes {
+        let mut changes = Changes::default();
+        let ours = |by: Option<u64>| by.is_none() || by == client;
+        for (idx, db) in self.dbs.iter().enumerate() {
+            let mut db = db.lock().unwrap();
+            changes.events.extend(
+                take_entries(&mut db.events, ours)
+                    .into_iter()
+                    .map(|(_, event)| (idx, event)),
+            );
+            for (by, key) in take_entries(&mut db.modified_keys, ours) {
+                match by {
+                    Some(_) => changes.modified.push(key),
+                    None => changes.modified_outside.push(key),
+                }
+            }
+            // Reads outside commands aren't tracked.
+            for (by, key) in take_entries(&mut db.read_keys, ours) {
+                if by.is_some() {
+                    changes.read.push(key);
+                }
+            }
+            changes.flushed |= std::mem::take(&mut db.flushed);
+        }
+        changes
+    }
+
+    /// Publish what the command of `reader` changed: its keyspace events on
+    /// the `__keyspace@<db>__:<key>` and `__keyevent@<db>__:<event>`
+    /// channels, then the CLIENT TRACKING invalidations, and `reader`
+    /// remembers the keys it read if it tracks them. Changes made outside
+    /// commands go along; `reader` is None for those alone, like
+    /// `fast_forward` expiring keys. Call it before the command lets other
+    /// commands run, so events go out in the order they happened.
+    pub fn publish_changes(&self, reader: Option<&ConnCtx>) {
+        let changes = self.take_changes(reader.map(|ctx| ctx.client_id));
+        if !changes.events.is_empty() {
+            let flags = self.lock().notify_keyspace_events;
+            let registry = self.pubsub.lock().unwrap();
+            for (idx, event) in &changes.events {
+                let key = String::from_utf8_lossy(&event.key);
+                if flags & NOTIFY_KEYSPACE != 0 {
+                    registry.publish(&format!("__keyspace@{}__:{}", idx, key), event.event);
+                }
+                if flags & NOTIFY_KEYEVENT != 0 {
+                    registry.publish(&format!("__keyevent@{}__:{}", idx, event.event), &key);
+                }
+            }
+        }
+        self.send_invalidations(changes, reader);
+    }
+
+    /// Record reads and writes in the databases only while some client
+    /// has CLIENT TRACKING on.
+    pub fn update_tracking(&self) {
+        let active = self.tracking.lock().unwrap().active();
+        if self.lock().tracking == active {
+            return;
+        }
+        let mut data = self.lock_all();
+        data.inner.tracking = active;
+        for db in &mut data.dbs {
+            db.tracking = active;
+            db.read_keys.clear();
+            db.modified_keys.clear();
+            db.flushed = false;
+        }
+    }
+
+    /// CLIENT TRACKING bookkeeping after a command: send invalidations for
+    /// the keys modified, then remember the keys the command read if
+    /// `reader` tracks them.
+    fn send_invalidations(&self, changes: Changes, reader: Option<&ConnCtx>) {
+        let Changes {
+            read,
+            modified,
+            modified_outside,
+            flushed,
+            ..
+        } = changes;
+        if !flushed && modified.is_empty() && modified_outside.is_empty() && read.is_empty() {
+            return;
+        }
+        let mut table = self.tracking.lock().unwrap();
+        if flushed {
+            table.invalidate_all();
+        }
+        if !modified.is_empty() {
+            table.invalidate(&modified, reader.map(|ctx| ctx.client_id));
+        }
+        if !modified_outside.is_empty() {
+            table.invalidate(&modified_outside, None);
+        }
+        let Some(ctx) = reader else {
+            return;
+        };
+        if read.is_empty() {
+            return;
+        }
+        let track = match table.opts(ctx.client_id) {
+            Some(opts) if opts.bcast => false,
+            Some(opts) if opts.optin => ctx.tracking_caching == Some(true),
+            Some(opts) if opts.optout => ctx.tracking_caching != Some(false),
+            Some(_) => true,
+            None => false,
+        };
+        if track {
+            table.track(ctx.client_id, read);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -843,9 +1631,9 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("hello", b"world".to_vec(), now);
-        assert_eq!(db.string_get("hello"), Some(&b"world".to_vec()));
-        assert_eq!(db.key_type("hello"), Some(KeyType::String));
+        db.string_set(b"hello", b"world".to_vec(), now);
+        assert_eq!(db.string_get(b"hello"), Some(&b"world".to_vec()));
+        assert_eq!(db.key_type(b"hello"), Some(KeyType::String));
     }
 
     #[test]
@@ -853,17 +1641,17 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("key", b"val".to_vec(), now);
-        assert!(db.exists("key", now));
-        assert!(db.del("key"));
-        assert!(!db.exists("key", now));
-        assert_eq!(db.string_get("key"), None);
+        db.string_set(b"key", b"val".to_vec(), now);
+        assert!(db.exists(b"key", now));
+        assert!(db.del(b"key"));
+        assert!(!db.exists(b"key", now));
+        assert_eq!(db.string_get(b"key"), None);
     }
 
     #[test]
     fn test_redis_db_del_nonexistent() {
         let mut db = RedisDB::new();
-        assert!(!db.del("nope"));
+        assert!(!db.del(b"nope"));
     }
 
     #[test]
@@ -871,9 +1659,9 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("str", b"val".to_vec(), now);
-        assert_eq!(db.key_type("str"), Some(KeyType::String));
-        assert_eq!(db.key_type("nonexistent"), None);
+        db.string_set(b"str", b"val".to_vec(), now);
+        assert_eq!(db.key_type(b"str"), Some(KeyType::String));
+        assert_eq!(db.key_type(b"nonexistent"), None);
     }
 
     #[test]
@@ -881,18 +1669,44 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("ephemeral", b"data".to_vec(), now);
+        db.string_set(b"ephemeral", b"data".to_vec(), now);
         db.ttl
-            .insert("ephemeral".to_owned(), Duration::from_secs(10));
+            .insert(b"ephemeral".to_vec(), Duration::from_secs(10));
 
         // Fast forward 5s -- key should still be alive
-        db.fast_forward(Duration::from_secs(5));
-        assert!(db.keys.contains_key("ephemeral"));
+        db.fast_forward(Duration::from_secs(5), now);
+        assert!(db.keys.contains_key(b"ephemeral".as_slice()));
 
         // Fast forward another 6s -- key should be gone
-        db.fast_forward(Duration::from_secs(6));
-        assert!(!db.keys.contains_key("ephemeral"));
-        assert_eq!(db.string_get("ephemeral"), None);
+        db.fast_forward(Duration::from_secs(6), now);
+        assert!(!db.keys.contains_key(b"ephemeral".as_slice()));
+        assert_eq!(db.string_get(b"ephemeral"), None);
+    }
+
+    #[test]
+    fn test_hash_field_expiration() {
+        let mut db = RedisDB::new();
+        let now = SystemTime::now();
+        db.track_memory(true);
+        db.hash_set(
+            b"h",
+            &[
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"b".to_vec(), vec![b'x'; 100]),
+            ],
+            now,
+        );
+        db.hash_field_ttls.insert(
+            b"h".to_vec(),
+            HashMap::from([(b"b".to_vec(), Duration::from_secs(10))]),
+        );
+        let version = db.key_version[&b"h"[..]];
+        let used = db.used_memory();
+
+        db.fast_forward(Duration::from_secs(11), now);
+        assert_eq!(db.hash_keys[&b"h"[..]].len(), 1);
+        assert!(db.key_version[&b"h"[..]] > version);
+        assert!(db.used_memory() < used);
     }
 
     #[test]
@@ -900,8 +1714,8 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("a", b"1".to_vec(), now);
-        db.string_set("b", b"2".to_vec(), now);
+        db.string_set(b"a", b"1".to_vec(), now);
+        db.string_set(b"b", b"2".to_vec(), now);
         assert_eq!(db.keys.len(), 2);
 
         db.flush();
@@ -914,11 +1728,14 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("charlie", b"3".to_vec(), now);
-        db.string_set("alpha", b"1".to_vec(), now);
-        db.string_set("bravo", b"2".to_vec(), now);
+        db.string_set(b"charlie", b"3".to_vec(), now);
+        db.string_set(b"alpha", b"1".to_vec(), now);
+        db.string_set(b"bravo", b"2".to_vec(), now);
 
-        assert_eq!(db.all_keys(), vec!["alpha", "bravo", "charlie"]);
+        assert_eq!(
+            db.all_keys(),
+            vec![b"alpha".to_vec(), b"bravo".to_vec(), b"charlie".to_vec()]
+        );
     }
 
     #[test]
@@ -926,11 +1743,11 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("k", b"v1".to_vec(), now);
-        let v1 = db.key_version["k"];
+        db.string_set(b"k", b"v1".to_vec(), now);
+        let v1 = db.key_version[b"k".as_slice()];
 
-        db.string_set("k", b"v2".to_vec(), now);
-        let v2 = db.key_version["k"];
+        db.string_set(b"k", b"v2".to_vec(), now);
+        let v2 = db.key_version[b"k".as_slice()];
 
         assert!(v2 > v1);
     }
@@ -940,48 +1757,89 @@ mod tests {
         let mut db = RedisDB::new();
         let now = SystemTime::now();
 
-        db.string_set("key", b"val".to_vec(), now);
-        assert_eq!(db.key_type("key"), Some(KeyType::String));
+        db.string_set(b"key", b"val".to_vec(), now);
+        assert_eq!(db.key_type(b"key"), Some(KeyType::String));
 
-        db.string_set("key", b"new_val".to_vec(), now);
-        assert_eq!(db.string_get("key"), Some(&b"new_val".to_vec()));
+        db.string_set(b"key", b"new_val".to_vec(), now);
+        assert_eq!(db.string_get(b"key"), Some(&b"new_val".to_vec()));
     }
 
     #[test]
-    fn test_inner_16_dbs() {
-        let inner = Inner::new();
-        assert_eq!(inner.dbs.len(), 16);
+    fn test_shared_state_16_dbs() {
+        let state = SharedState::new();
+        assert_eq!(state.dbs.len(), 16);
+        assert_eq!(state.lock_all().dbs.len(), 16);
     }
 
     #[test]
-    fn test_inner_effective_now_real() {
-        let inner = Inner::new();
-        let now = inner.effective_now();
+    fn test_shared_state_now_real() {
+        let state = SharedState::new();
+        let now = state.now();
         let real_now = SystemTime::now();
         let diff = real_now.duration_since(now).unwrap_or(Duration::ZERO);
         assert!(diff < Duration::from_secs(1));
     }
 
     #[test]
-    fn test_inner_effective_now_mock() {
-        let mut inner = Inner::new();
+    fn test_shared_state_now_mock() {
+        let state = SharedState::new();
         let mock_time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
-        inner.now = Some(mock_time);
-        assert_eq!(inner.effective_now(), mock_time);
+        state.set_now(Some(mock_time));
+        assert_eq!(state.now(), mock_time);
+        state.fast_forward(Duration::from_secs(1));
+        assert_eq!(state.now(), mock_time + Duration::from_secs(1));
     }
 
     #[test]
     fn test_shared_state_lock() {
         let state = SharedState::new();
-        {
-            let mut inner = state.lock();
-            inner
-                .db_mut(0)
-                .string_set("test", b"value".to_vec(), SystemTime::now());
-        }
-        {
-            let inner = state.lock();
-            assert_eq!(inner.db(0).string_get("test"), Some(&b"value".to_vec()));
+        state
+            .db(0)
+            .string_set(b"test", b"value".to_vec(), SystemTime::now());
+        assert_eq!(state.db(0).string_get(b"test"), Some(&b"value".to_vec()));
+        assert_eq!(state.db(1).string_get(b"test"), None);
+    }
+
+    #[test]
+    fn test_lock_keyspace() {
+        let state = SharedState::new();
+        // Writes to other databases and reads don't wait for a write.
+        let write = state.lock_keyspace(Isolation::Write(0));
+        let other = state.lock_keyspace(Isolation::Write(1));
+        let read = state.lock_keyspace(Isolation::Read);
+        assert!(!write.is_exclusive());
+        assert!(state.writers[0].try_lock().is_err());
+        assert!(state.keyspace.try_write().is_err());
+        drop((write, other, read));
+
+        let exclusive = state.lock_keyspace(Isolation::Exclusive);
+        assert!(exclusive.is_exclusive());
+        assert!(state.keyspace.try_read().is_err());
+    }
+
+    #[test]
+    fn test_take_changes() {
+        let state = SharedState::new();
+        let now = SystemTime::now();
+        state.db(0).tracking = true;
+        state.db(0).string_set(b"outside", b"1".to_vec(), now);
+        for (client, key) in [(1, &b"a"[..]), (2, &b"b"[..])] {
+            let _running = CommandScope::enter(client);
+            let mut db = state.db(0);
+            db.check_ttl_read(key);
+            db.incr_version(key, now);
         }
+        assert_eq!(RUNNING.get(), None);
+
+        let changes = state.take_changes(Some(1));
+        assert_eq!(changes.read, vec![b"a".to_vec()]);
+        assert_eq!(changes.modified, vec![b"a".to_vec()]);
+        assert_eq!(changes.modified_outside, vec![b"outside".to_vec()]);
+        let changes = state.take_changes(Some(2));
+        assert_eq!(changes.read, vec![b"b".to_vec()]);
+        assert_eq!(changes.modified, vec![b"b".to_vec()]);
+        assert!(changes.modified_outside.is_empty());
+        let db = state.db(0);
+        assert!(db.read_keys.is_empty() && db.modified_keys.is_empty());
     }
 }
@@ -0,0 +1,225 @@
+//! Values returned by the direct-access API of `Miniredis`: stream
+//! entries, consumer groups, and the structured dump of a database.
+//!
+//! Keys, members, fields and values are converted with
+//! `String::from_utf8_lossy`, like the `&str` methods of `Miniredis` do.
+use std::time::{Duration, SystemTime};
+
+use crate::db::RedisDB;
+use crate::error::{Error, Result, WrongType};
+use crate::types::{self, Direction, KeyType};
+
+/// A stream entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    pub id: String,
+    /// Field-value pairs, in the order they were added.
+    pub values: Vec<(String, String)>,
+}
+
+/// An entry of a consumer group's pending entries list: delivered to a
+/// consumer, and not acknowledged yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub id: String,
+    pub consumer: String,
+    pub delivery_count: u64,
+    pub last_delivery: SystemTime,
+}
+
+/// A consumer group of a stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamGroup {
+    pub name: String,
+    /// ID of the last entry delivered to the group.
+    pub last_id: String,
+    /// The pending entries list, by ID.
+    pub pending: Vec<PendingEntry>,
+    /// Consumer names, sorted.
+    pub consumers: Vec<String>,
+}
+
+/// The value of a key, in [`DumpedKey`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+    /// Members, sorted.
+    Set(Vec<String>),
+    /// Field-value pairs, sorted by field.
+    Hash(Vec<(String, String)>),
+    /// Members and scores, sorted by score, then member.
+    SortedSet(Vec<(String, f64)>),
+    /// Entries by ID, and the consumer groups by name.
+    Stream {
+        entries: Vec<StreamEntry>,
+        groups: Vec<StreamGroup>,
+    },
+    /// The estimated cardinality.
+    HyperLogLog(u64),
+    /// A JSON document.
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+    /// The number of items added to a Bloom filter.
+    #[cfg(feature = "bloom")]
+    BloomFilter {
+        items: u64,
+    },
+    /// The number of items in a Cuckoo filter.
+    #[cfg(feature = "bloom")]
+    CuckooFilter {
+        items: u64,
+    },
+    /// The dimensions of a Count-Min sketch, and the sum of its increments.
+    #[cfg(feature = "bloom")]
+    CountMinSketch {
+        width: usize,
+        depth: usize,
+        count: u64,
+    },
+    /// The top items of a Top-K, and their counts, highest count first.
+    #[cfg(feature = "bloom")]
+    TopK(Vec<(String, u64)>),
+}
+
+/// A key in the output of
+/// [`Miniredis::dump_db()`](crate::Miniredis::dump_db).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpedKey {
+    pub key: String,
+    pub ttl: Option<Duration>,
+    pub value: Value,
+}
+
+fn lossy(b: &[u8]) -> String {
+    String::from_utf8_lossy(b).into_owned()
+}
+
+/// Whether `key` exists, or WrongType when it holds another type.
+pub(crate) fn check_type(db: &RedisDB, key: &[u8], want: KeyType) -> Result<bool> {
+    match db.key_type(key) {
+        None => Ok(false),
+        Some(t) if t == want => Ok(true),
+        Some(_) => Err(WrongType.into()),
+    }
+}
+
+/// The error for a stream or consumer group that doesn't exist.
+pub(crate) fn no_group(key: &str, group: &str) -> Error {
+    format!("NOGROUP No such key '{key}' or consumer group '{group}'").into()
+}
+
+pub(crate) fn stream_entry(entry: &types::StreamEntry) -> StreamEntry {
+    StreamEntry {
+        id: entry.id.clone(),
+        values: entry
+            .values
+            .chunks_exact(2)
+            .map(|fv| (lossy(&fv[0]), lossy(&fv[1])))
+            .collect(),
+    }
+}
+
+pub(crate) fn pending_entries(group: &types::StreamGroup) -> Vec<PendingEntry> {
+    let mut pending: Vec<PendingEntry> = group
+        .pending
+        .iter()
+        .map(|pe| PendingEntry {
+            id: pe.id.clone(),
+            consumer: pe.consumer.clone(),
+            delivery_count: pe.delivery_count.max(0) as u64,
+            last_delivery: pe.last_delivery,
+        })
+        .collect();
+    pending.sort_by(|a, b| types::Stream::cmp_ids(&a.id, &b.id));
+    pending
+}
+
+pub(crate) fn stream_groups(stream: &types::Stream) -> Vec<StreamGroup> {
+    let mut groups: Vec<StreamGroup> = stream
+        .groups
+        .iter()
+        .map(|(name, group)| {
+            let mut consumers: Vec<String> = group.consumers.keys().cloned().collect();
+            consumers.sort();
+            StreamGroup {
+                name: name.clone(),
+                last_id: group.last_id.clone(),
+                pending: pending_entries(group),
+                consumers,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    groups
+}
+
+#[cfg(feature = "bloom")]
+fn sketch_value(sketch: &crate::bloom::Sketch) -> Value {
+    use crate::bloom::Sketch;
+    match sketch {
+        Sketch::Bloom(bf) => Value::BloomFilter { items: bf.items() },
+        Sketch::Cuckoo(cf) => Value::CuckooFilter { items: cf.items() },
+        Sketch::CountMin(cms) => Value::CountMinSketch {
+            width: cms.width,
+            depth: cms.depth,
+            count: cms.count,
+        },
+        Sketch::TopK(topk) => Value::TopK(
+            topk.list()
+                .into_iter()
+                .map(|(item, count)| (lossy(item), count as u64))
+                .collect(),
+        ),
+    }
+}
+
+/// Every key of `db`, sorted.
+pub(crate) fn dump(db: &RedisDB) -> Vec<DumpedKey> {
+    db.all_keys()
+        .into_iter()
+        .filter_map(|key| {
+            let value = match db.key_type(&key)? {
+                KeyType::String => Value::String(lossy(db.string_get(&key)?)),
+                KeyType::List => {
+                    Value::List(db.list_keys.get(&key)?.iter().map(|v| lossy(v)).collect())
+                }
+                KeyType::Set => Value::Set(db.set_members(&key).iter().map(|m| lossy(m)).collect()),
+                KeyType::Hash => {
+                    let hash = db.hash_keys.get(&key)?;
+                    let mut pairs: Vec<(String, String)> =
+                        hash.iter().map(|(f, v)| (lossy(f), lossy(v))).collect();
+                    pairs.sort();
+                    Value::Hash(pairs)
+                }
+                KeyType::SortedSet => Value::SortedSet(
+                    db.sorted_set_keys
+                        .get(&key)?
+                        .by_score(Direction::Asc)
+                        .into_iter()
+                        .map(|e| (lossy(&e.member), e.score))
+                        .collect(),
+                ),
+                KeyType::Stream => {
+                    let stream = db.stream_keys.get(&key)?;
+                    Value::Stream {
+                        entries: stream.entries.iter().map(stream_entry).collect(),
+                        groups: stream_groups(stream),
+                    }
+                }
+                KeyType::HyperLogLog => Value::HyperLogLog(db.hll_keys.get(&key)?.count()),
+                #[cfg(feature = "json")]
+                KeyType::Json => Value::Json(db.json_keys.get(&key)?.clone()),
+                #[cfg(feature = "bloom")]
+                KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK => {
+                    sketch_value(db.bloom_keys.get(&key)?)
+                }
+            };
+            Some(DumpedKey {
+                ttl: db.ttl.get(&key).copied(),
+                key: lossy(&key),
+                value,
+            })
+        })
+        .collect()
+}
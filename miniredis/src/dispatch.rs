@@ -1,10 +1,16 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
+use std::time::Instant;
 
+use crate::acl::{Denied, full_command_name, in_category};
+use crate::cluster::key_slot;
 use crate::connection::ConnCtx;
-use crate::db::SharedState;
+use crate::db::{CommandScope, Isolation, SharedState};
+use crate::eviction::{deny_oom, perform_evictions};
 use crate::frame::Frame;
+use crate::keyspec::{command_keys, shard_channels};
+use crate::replication::{Propagator, rejects_writes};
 
 // ── Error message constants ──────────────────────────────────────────
 
@@ -51,6 +57,15 @@ pub const MSG_NX_AND_XX_GT_LT: &str =
 pub const MSG_NUM_FIELDS_INVALID: &str = "ERR Parameter `numFields` should be greater than 0";
 pub const MSG_NUM_FIELDS_PARAMETER: &str =
     "ERR The `numfields` parameter must match the number of arguments";
+pub const MSG_FIELDS_MISSING: &str =
+    "ERR Mandatory argument FIELDS is missing or not at the right position";
+pub const MSG_NUMKEYS_NOT_POSITIVE: &str = "ERR numkeys should be greater than 0";
+pub const MSG_COUNT_NOT_POSITIVE: &str = "ERR count should be greater than 0";
+pub const MSG_SORT_SCORE: &str = "ERR One or more scores can't be converted into double";
+pub const MSG_CROSSSLOT: &str = "CROSSSLOT Keys in request don't hash to the same slot";
+pub const MSG_TRYAGAIN: &str = "TRYAGAIN Multiple keys request during rehashing of slot";
+pub const MSG_READONLY: &str = "READONLY You can't write against a read only replica.";
+pub const MSG_OOM: &str = "OOM command not allowed when used memory > 'maxmemory'.";
 
 /// Generate the "wrong number of arguments" error for a command.
 pub fn err_wrong_number(cmd: &str) -> String {
@@ -109,6 +124,7 @@ impl CommandTable {
 
         // Register all implemented commands.
         crate::cmd::connection::register(&mut table);
+        crate::cmd::acl::register(&mut table);
         crate::cmd::string::register(&mut table);
         crate::cmd::generic::register(&mut table);
         crate::cmd::server::register(&mut table);
@@ -120,11 +136,17 @@ impl CommandTable {
         crate::cmd::hll::register(&mut table);
         crate::cmd::geo::register(&mut table);
         crate::cmd::pubsub::register(&mut table);
+        crate::cmd::replication::register(&mut table);
         crate::cmd::client::register(&mut table);
         crate::cmd::cluster::register(&mut table);
         crate::cmd::object::register(&mut table);
         crate::cmd::stream::register(&mut table);
         crate::cmd::scripting::register(&mut table);
+        crate::cmd::functions::register(&mut table);
+        #[cfg(feature = "json")]
+        crate::cmd::json::register(&mut table);
+        #[cfg(feature = "bloom")]
+        crate::cmd::bloom::register(&mut table);
 
         table
     }
@@ -153,6 +175,17 @@ impl CommandTable {
     pub fn get(&self, name: &str) -> Option<&CommandMeta> {
         self.commands.get(name)
     }
+
+    /// Whether `name` (uppercase) is a command. Unlike `get` this knows
+    /// EXEC, which `dispatch` handles itself.
+    pub fn is_command(&self, name: &str) -> bool {
+        name == "EXEC" || self.commands.contains_key(name)
+    }
+
+    /// All registered command names.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.commands.keys().copied()
+    }
 }
 
 impl fmt::Debug for CommandTable {
@@ -177,9 +210,53 @@ pub fn dispatch(
         return (Frame::error("ERR empty command"), false);
     }
 
+    // Queued commands are reported when EXEC runs them.
+    let queued = is_queued(ctx, args);
+    let (response, should_close) = run_command(table, state, ctx, args);
+    if !queued {
+        crate::monitor::feed(state, ctx, args, &response);
+    }
+    (response, should_close)
+}
+
+/// Refuse a command before dispatching it, e.g. with BUSY: like an error
+/// `dispatch` replies, it fails the transaction the client is in.
+pub fn refuse(state: &SharedState, ctx: &mut ConnCtx, args: &[Vec<u8>], err: Frame) -> Frame {
+    let queued = is_queued(ctx, args);
+    if ctx.in_tx() {
+        ctx.dirty_transaction = true;
+    }
+    if !queued {
+        crate::monitor::feed(state, ctx, args, &err);
+    }
+    err
+}
+
+/// Whether the command would be queued in the client's transaction.
+fn is_queued(ctx: &ConnCtx, args: &[Vec<u8>]) -> bool {
+    ctx.in_tx()
+        && !["EXEC", "DISCARD", "MULTI", "WATCH"]
+            .iter()
+            .any(|c| args[0].eq_ignore_ascii_case(c.as_bytes()))
+}
+
+fn run_command(
+    table: &CommandTable,
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: &[Vec<u8>],
+) -> (Frame, bool) {
     let cmd = String::from_utf8_lossy(&args[0]).to_uppercase();
     let cmd_args = &args[1..];
 
+    // Other clients wait while a script runs too long.
+    if let Some(err) = crate::script::check_busy(state, ctx.client_id, &cmd, cmd_args) {
+        if ctx.in_tx() {
+            ctx.dirty_transaction = true;
+        }
+        return (err, false);
+    }
+
     // Handle MULTI/EXEC/DISCARD specially — they're not queued.
     // Note: the MULTI path only runs when authenticated (you can't enter MULTI
     // without being authenticated), so no auth check is needed here.
@@ -203,15 +280,26 @@ pub fn dispatch(
             };
             if bad {
                 ctx.dirty_transaction = true;
+                crate::latency::reject(state, args);
                 return (Frame::error(err_wrong_number(&cmd.to_lowercase())), false);
             }
         }
 
+        if let Some(err) = check_acl(state, ctx, &cmd, cmd_args, "toplevel")
+            .or_else(|| check_cluster(state, ctx, &cmd, cmd_args))
+            .or_else(|| check_replica(state, ctx, &cmd))
+            .or_else(|| check_oom(state, ctx, &cmd, cmd_args))
+        {
+            ctx.dirty_transaction = true;
+            crate::latency::reject(state, args);
+            return (err, false);
+        }
+
         // Special case: validate SCRIPT subcommands before queueing.
         // Real Redis (and Go miniredis) rejects unknown subcommands immediately.
         if cmd == "SCRIPT" && !cmd_args.is_empty() {
             let subcmd = String::from_utf8_lossy(&cmd_args[0]).to_uppercase();
-            if !["LOAD", "EXISTS", "FLUSH"].contains(&subcmd.as_str()) {
+            if !["LOAD", "EXISTS", "FLUSH", "KILL"].contains(&subcmd.as_str()) {
                 ctx.dirty_transaction = true;
                 return (
                     Frame::error(format!(
@@ -265,7 +353,11 @@ pub fn dispatch(
 
     // Handle EXEC specially — it needs the command table to replay queued commands.
     if cmd == "EXEC" {
-        return (cmd_exec(table, state, ctx, cmd_args), false);
+        let start = Instant::now();
+        let response = cmd_exec(table, state, ctx, cmd_args);
+        crate::latency::record(state, ctx, args, &response, start.elapsed());
+        ctx.tracking_caching = None;
+        return (response, false);
     }
 
     // Look up the command
@@ -273,11 +365,8 @@ pub fn dispatch(
         Some(m) => m,
         None => {
             // Unknown commands: check auth before returning unknown error
-            if !ctx.authenticated {
-                let inner = state.lock();
-                if !inner.passwords.is_empty() {
-                    return (Frame::error("NOAUTH Authentication required."), false);
-                }
+            if !ctx.authenticated && state.lock().acl.auth_required() {
+                return (Frame::error("NOAUTH Authentication required."), false);
             }
             return (Frame::error(err_unknown_command(&cmd, cmd_args)), false);
         }
@@ -292,34 +381,267 @@ pub fn dispatch(
             n < -meta.arity
         };
         if bad {
+            crate::latency::reject(state, args);
             return (Frame::error(err_wrong_number(&cmd.to_lowercase())), false);
         }
     }
 
-    // Check auth (after arity validation)
-    if !ctx.authenticated {
-        let inner = state.lock();
-        if !inner.passwords.is_empty() && cmd != "AUTH" && cmd != "HELLO" && cmd != "QUIT" {
-            return (Frame::error("NOAUTH Authentication required."), false);
-        }
+    // Check auth and ACL permissions (after arity validation)
+    if let Some(err) = check_access(state, ctx, &cmd, cmd_args) {
+        crate::latency::reject(state, args);
+        return (err, false);
     }
 
-    // Execute the command under the lock
-    let response = with_lock(state, ctx, meta.handler, cmd_args);
+    // Execute the command under the lock. Stopping a running script
+    // can't wait for the script to finish.
+    let response = if crate::script::skips_write_lock(&cmd, cmd_args) {
+        (meta.handler)(state, ctx, cmd_args)
+    } else {
+        with_lock(state, ctx, &cmd, meta, args)
+    };
     let should_close = cmd == "QUIT";
 
+    // CLIENT CACHING only applies to the command right after it.
+    let caching = cmd == "CLIENT"
+        && cmd_args
+            .first()
+            .is_some_and(|sub| sub.eq_ignore_ascii_case(b"caching"));
+    if !caching {
+        ctx.tracking_caching = None;
+    }
+
     (response, should_close)
 }
 
-/// Execute a command handler under the database lock.
+/// Commands any client may run, even before AUTH.
+const NO_AUTH_COMMANDS: &[&str] = &["AUTH", "HELLO", "QUIT"];
+
+/// Commands no other command may run alongside: scripts, which run
+/// atomically, and commands that touch more than one database.
+const EXCLUSIVE_COMMANDS: &[&str] = &[
+    "COPY",
+    "DEBUG",
+    "EVAL",
+    "EVALSHA",
+    "EVALSHA_RO",
+    "EVAL_RO",
+    "FCALL",
+    "FCALL_RO",
+    "FLUSHALL",
+    "MINIREDIS.FASTFORWARD",
+    "MOVE",
+    "SWAPDB",
+];
+
+/// Check that the client may run `cmd`: it has authenticated when that's
+/// required, its ACL user may run the command, in cluster mode this node
+/// serves its keys, and a replica only gets writes from its master.
+pub fn check_access(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Option<Frame> {
+    if ctx.master_link {
+        return None;
+    }
+    if !ctx.authenticated && !NO_AUTH_COMMANDS.contains(&cmd) && state.lock().acl.auth_required() {
+        return Some(Frame::error("NOAUTH Authentication required."));
+    }
+    if ctx.monitor && (in_category(cmd, "read") || in_category(cmd, "write")) {
+        return Some(Frame::error("ERR Replica can't interact with the keyspace"));
+    }
+    check_acl(state, ctx, cmd, args, "toplevel")
+        .or_else(|| check_cluster(state, ctx, cmd, args))
+        .or_else(|| check_replica(state, ctx, cmd))
+        .or_else(|| check_oom(state, ctx, cmd, args))
+}
+
+/// A read-only replica refuses write commands from its clients.
+pub fn check_replica(state: &Arc<SharedState>, ctx: &ConnCtx, cmd: &str) -> Option<Frame> {
+    if in_category(cmd, "write") && rejects_writes(state, ctx) {
+        return Some(Frame::error(MSG_READONLY));
+    }
+    None
+}
+
+/// With maxmemory set, evict keys before running a command. Commands that
+/// may grow the dataset, and EXEC of a transaction with one, fail with OOM
+/// if memory use is still over the limit.
+pub fn check_oom(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Option<Frame> {
+    let grows = deny_oom(cmd, args)
+        || (cmd == "EXEC"
+            && ctx.transaction.iter().flatten().any(|queued| {
+                let name = String::from_utf8_lossy(&queued.args[0]).to_uppercase();
+                deny_oom(&name, &queued.args[1..])
+            }));
+    if !perform_evictions(state) && grows {
+        return Some(Frame::error(MSG_OOM));
+    }
+    None
+}
+
+/// In cluster mode, check that the keys and shard channels of `cmd` are all
+/// in one slot, and that this node serves that slot. Otherwise the client
+/// gets a CROSSSLOT error or is redirected with MOVED or ASK. Inside MULTI
+/// the keys of the queued commands must be in the same slot too.
+pub fn check_cluster(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Option<Frame> {
+    let cluster = state.cluster.get()?;
+    let asking = std::mem::take(&mut ctx.asking);
+    let keys: Vec<&[u8]> = command_keys(cmd, args)
+        .iter()
+        .map(|k| args[k.index].as_slice())
+        .collect();
+    let mut slots: Vec<u16> = keys.iter().map(|k| key_slot(k)).collect();
+    slots.extend(shard_channels(cmd, args).iter().map(|c| key_slot(c)));
+    let slot = *slots.first()?;
+    for queued in ctx.transaction.iter().flatten() {
+        let name = String::from_utf8_lossy(&queued.args[0]).to_uppercase();
+        let queued_args = &queued.args[1..];
+        slots.extend(
+            command_keys(&name, queued_args)
+                .iter()
+                .map(|k| key_slot(&queued_args[k.index])),
+        );
+        slots.extend(
+            shard_channels(&name, queued_args)
+                .iter()
+                .map(|c| key_slot(c)),
+        );
+    }
+    if slots.iter().any(|&s| s != slot) {
+        return Some(Frame::error(MSG_CROSSSLOT));
+    }
+
+    let (owner, importing) = {
+        let topology = cluster.topology.lock().unwrap();
+        let node = |i: usize| (i, topology.nodes[i].addr);
+        (
+            node(topology.owner(slot)),
+            topology.importing_node(slot).map(node),
+        )
+    };
+    let missing = || {
+        let db = state.db(ctx.selected_db);
+        keys.iter().filter(|k| !db.keys.contains_key(**k)).count()
+    };
+
+    if owner.0 != cluster.myself {
+        // A node importing the slot serves clients that sent ASKING.
+        if asking && importing.is_some_and(|(to, _)| to == cluster.myself) {
+            if keys.len() > 1 && missing() > 0 {
+                return Some(Frame::error(MSG_TRYAGAIN));
+            }
+            return None;
+        }
+        return Some(Frame::error(format!("MOVED {slot} {}", owner.1)));
+    }
+    // Keys this node doesn't have may already be on the importing node.
+    let (_, to_addr) = importing?;
+    match missing() {
+        0 => None,
+        n if n < keys.len() => Some(Frame::error(MSG_TRYAGAIN)),
+        _ => Some(Frame::error(format!("ASK {slot} {to_addr}"))),
+    }
+}
+
+/// Check that the client's ACL user may run `cmd` with these keys and
+/// channels. Refusals go to the ACL LOG, with `context` "toplevel",
+/// "multi" or "lua".
+pub fn check_acl(
+    state: &Arc<SharedState>,
+    ctx: &ConnCtx,
+    cmd: &str,
+    args: &[Vec<u8>],
+    context: &'static str,
+) -> Option<Frame> {
+    if NO_AUTH_COMMANDS.contains(&cmd) {
+        return None;
+    }
+    let mut inner = state.lock();
+    let denied = match inner.acl.user(&ctx.user) {
+        Some(user) => user.check(cmd, args).err()?,
+        None => Denied::Command,
+    };
+
+    let (reason, object, msg) = match denied {
+        Denied::Command => {
+            let name = full_command_name(cmd, args.first().map(|a| a.as_slice()));
+            let msg = format!(
+                "NOPERM User {} has no permissions to run the '{}' command",
+                ctx.user, name
+            );
+            ("command", name, msg)
+        }
+        Denied::Key(key) => (
+            "key",
+            String::from_utf8_lossy(&key).to_string(),
+            "NOPERM No permissions to access a key".to_string(),
+        ),
+        Denied::Channel(channel) => (
+            "channel",
+            String::from_utf8_lossy(&channel).to_string(),
+            "NOPERM No permissions to access a channel".to_string(),
+        ),
+    };
+    let now = state.now();
+    let client_info = client_info(ctx);
+    inner
+        .acl
+        .log(reason, context, object, ctx.user.clone(), client_info, now);
+    Some(Frame::error(msg))
+}
+
+/// The client description in ACL LOG entries.
+pub fn client_info(ctx: &ConnCtx) -> String {
+    format!(
+        "id={} name={} db={} user={}",
+        ctx.client_id,
+        ctx.client_name.as_deref().unwrap_or(""),
+        ctx.selected_db,
+        ctx.user
+    )
+}
+
+/// Execute a command handler under the keyspace lock.
 /// This is the normal (non-MULTI) path: lock → execute → notify → unlock.
+/// Commands that may write are passed on to the replicas.
 fn with_lock(
     state: &Arc<SharedState>,
     ctx: &mut ConnCtx,
-    handler: CommandHandler,
+    cmd: &str,
+    meta: &CommandMeta,
     args: &[Vec<u8>],
 ) -> Frame {
-    let response = handler(state, ctx, args);
+    let start = Instant::now();
+    let exclusive = EXCLUSIVE_COMMANDS.contains(&cmd);
+    let _running = CommandScope::enter(ctx.client_id);
+    let response = if meta.read_only && !exclusive {
+        let _keyspace = state.lock_keyspace(Isolation::Read);
+        let response = (meta.handler)(state, ctx, &args[1..]);
+        state.publish_changes(Some(ctx));
+        response
+    } else {
+        let mut propagator = Propagator::new(state, ctx.selected_db, exclusive);
+        let response = (meta.handler)(state, ctx, &args[1..]);
+        propagator.record(args, &response);
+        state.publish_changes(Some(ctx));
+        if let Some(offset) = propagator.finish(false, ctx.selected_db) {
+            ctx.repl_offset = offset;
+        }
+        response
+    };
+    crate::latency::record(state, ctx, args, &response, start.elapsed());
 
     // Notify any blocking commands that data may have changed.
     state.notify.notify_waiters();
@@ -349,17 +671,28 @@ fn cmd_exec(
         return Frame::error("EXECABORT Transaction discarded because of previous errors.");
     }
 
-    // Check WATCHed keys.
+    // Evict first; a transaction that may grow the dataset is refused if
+    // memory use is still over maxmemory.
+    if !ctx.master_link
+        && let Some(Frame::Error(err)) = check_oom(state, ctx, "EXEC", args)
     {
-        let inner = state.lock();
-        for ((db_idx, key), version) in &ctx.watch {
-            let current = inner.db(*db_idx).key_version.get(key).copied().unwrap_or(0);
-            if current > *version {
-                // WATCH detected a change — abort.
-                ctx.transaction = None;
-                ctx.watch.clear();
-                return Frame::NullArray;
-            }
+        ctx.transaction = None;
+        ctx.watch.clear();
+        return Frame::error(format!("EXECABORT Transaction discarded because of: {err}"));
+    }
+
+    // No other command runs until the transaction is done.
+    let mut propagator = Propagator::new(state, ctx.selected_db, true);
+    let _running = CommandScope::enter(ctx.client_id);
+
+    // Check WATCHed keys.
+    for ((db_idx, key), version) in &ctx.watch {
+        let current = state.db(*db_idx).key_version.get(key).copied().unwrap_or(0);
+        if current > *version {
+            // WATCH detected a change — abort.
+            ctx.transaction = None;
+            ctx.watch.clear();
+            return Frame::NullArray;
         }
     }
 
@@ -381,9 +714,27 @@ fn cmd_exec(
             }
         };
 
+        if let Some(err) = state.faults.lock().unwrap().check(ctx.client_id, &cmd_name) {
+            results.push(Frame::error(err));
+            continue;
+        }
+        if let Some(err) = check_acl(state, ctx, &cmd_name, cmd_args, "multi") {
+            crate::latency::reject(state, &queued.args);
+            results.push(err);
+            continue;
+        }
+
+        let start = Instant::now();
         let result = (meta.handler)(state, ctx, cmd_args);
+        crate::latency::record(state, ctx, &queued.args, &result, start.elapsed());
+        propagator.record(&queued.args, &result);
+        crate::monitor::feed(state, ctx, &queued.args, &result);
         results.push(result);
     }
+    state.publish_changes(Some(ctx));
+    if let Some(offset) = propagator.finish(true, ctx.selected_db) {
+        ctx.repl_offset = offset;
+    }
 
     // Notify any blocking commands that data may have changed.
     state.notify.notify_waiters();
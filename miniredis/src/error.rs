@@ -3,3 +3,16 @@ pub type Error = Box<dyn std::error::Error + Send + Sync>;
 
 /// Result type for miniredis-rs.
 pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error of the direct-access methods of `Miniredis` when the key
+/// holds another type of value. Check for it with `err.is::<WrongType>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrongType;
+
+impl std::fmt::Display for WrongType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(crate::dispatch::MSG_WRONG_TYPE)
+    }
+}
+
+impl std::error::Error for WrongType {}
@@ -0,0 +1,420 @@
+//! maxmemory and key eviction.
+//!
+//! Memory use is the sum of `RedisDB::estimate_key_size` over all keys, the
+//! same estimate MEMORY USAGE reports. While `maxmemory` is set, each
+//! database caches its key sizes and estimates only the keys changed since
+//! the last check. Before every command, keys are evicted following
+//! `maxmemory-policy` until the estimate fits; commands that can grow the
+//! dataset fail with OOM when it still doesn't. Unlike Redis, which
+//! samples a few keys, the victim is always the exact best candidate, so
+//! tests see a predictable order.
+
+use std::collections::{HashMap, HashSet};
+
+use rand::seq::SliceRandom;
+
+use crate::db::{Dataset, Isolation, SharedState};
+use crate::pubsub::NOTIFY_EVICTED;
+
+/// The `maxmemory-policy` config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    VolatileLru,
+    VolatileLfu,
+    VolatileRandom,
+    VolatileTtl,
+}
+
+const POLICIES: &[(EvictionPolicy, &str)] = &[
+    (EvictionPolicy::VolatileLru, "volatile-lru"),
+    (EvictionPolicy::VolatileLfu, "volatile-lfu"),
+    (EvictionPolicy::VolatileRandom, "volatile-random"),
+    (EvictionPolicy::VolatileTtl, "volatile-ttl"),
+    (EvictionPolicy::AllKeysLru, "allkeys-lru"),
+    (EvictionPolicy::AllKeysLfu, "allkeys-lfu"),
+    (EvictionPolicy::AllKeysRandom, "allkeys-random"),
+    (EvictionPolicy::NoEviction, "noeviction"),
+];
+
+impl EvictionPolicy {
+    /// Parse a policy name, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        POLICIES
+            .iter()
+            .find(|(_, n)| n.eq_ignore_ascii_case(name))
+            .map(|(p, _)| *p)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        POLICIES.iter().find(|(p, _)| *p == self).unwrap().1
+    }
+
+    /// All policy names, as listed in the CONFIG SET error.
+    pub fn names() -> String {
+        POLICIES
+            .iter()
+            .map(|(_, n)| *n)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Whether the policy tracks access frequency (OBJECT FREQ).
+    pub fn is_lfu(self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu
+        )
+    }
+
+    /// Whether only keys with a TTL may be evicted.
+    fn volatile(self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+}
+
+/// The cached key sizes of a database, see `RedisDB::used_memory`.
+#[derive(Debug, Default)]
+pub struct MemoryUsage {
+    pub sizes: HashMap<Vec<u8>, usize>,
+    /// Sum of `sizes`.
+    pub total: usize,
+    /// Keys changed since their size was cached.
+    pub stale: HashSet<Vec<u8>>,
+}
+
+impl MemoryUsage {
+    /// Cache the size of `key`, or forget it if the key is gone.
+    pub fn set(&mut self, key: Vec<u8>, size: Option<usize>) {
+        let old = match size {
+            Some(size) => self.sizes.insert(key, size),
+            None => self.sizes.remove(&key),
+        };
+        self.total = self.total - old.unwrap_or(0) + size.unwrap_or(0);
+    }
+}
+
+/// Commands that may grow the dataset, which fail with OOM when memory use
+/// is over `maxmemory`. Deleting commands keep working so clients can free
+/// memory.
+const DENY_OOM: &str = "APPEND BITFIELD BITOP BLMOVE BRPOPLPUSH COPY DECR DECRBY GEOADD GEORADIUS \
+     GEORADIUSBYMEMBER GEOSEARCHSTORE GETSET HINCRBY HINCRBYFLOAT HMSET HSET HSETEX HSETNX INCR INCRBY \
+     INCRBYFLOAT LINSERT LMOVE LPUSH LPUSHX LSET MSET MSETNX PFADD PFMERGE PSETEX RESTORE \
+     RPOPLPUSH RPUSH RPUSHX SADD SDIFFSTORE SET SETBIT SETEX SETNX SETRANGE SINTERSTORE SORT \
+     SUNIONSTORE XADD ZADD ZDIFFSTORE ZINCRBY ZINTERSTORE ZRANGESTORE ZUNIONSTORE \
+     FUNCTION|LOAD FUNCTION|RESTORE XGROUP|CREATE JSON.SET JSON.ARRAPPEND JSON.NUMINCRBY \
+     JSON.STRAPPEND BF.RESERVE BF.ADD BF.MADD CF.ADD CMS.INITBYDIM CMS.INCRBY TOPK.RESERVE \
+     TOPK.ADD";
+
+/// Whether the command (upper-case name, arguments without the name) may
+/// grow the dataset.
+pub fn deny_oom(cmd: &str, args: &[Vec<u8>]) -> bool {
+    let sub = args
+        .first()
+        .map(|sub| format!("{}|{}", cmd, String::from_utf8_lossy(sub).to_uppercase()));
+    DENY_OOM
+        .split_ascii_whitespace()
+        .any(|c| c == cmd || Some(c) == sub.as_deref())
+}
+
+/// Estimated memory use of all databases, in bytes.
+pub fn used_memory(data: &mut Dataset) -> u64 {
+    data.dbs.iter_mut().map(|db| db.used_memory() as u64).sum()
+}
+
+/// Evict keys until memory use fits `maxmemory`, adding the evicted keys to
+/// `evicted` as (database, key). Returns false if it still doesn't fit
+/// because of the policy or because no key qualifies.
+pub fn evict(data: &mut Dataset, evicted: &mut Vec<(usize, Vec<u8>)>) -> bool {
+    if data.inner.maxmemory == 0 {
+        return true;
+    }
+    let mut used = used_memory(data);
+    if used <= data.inner.maxmemory {
+        return true;
+    }
+    let policy = data.inner.maxmemory_policy;
+    if policy == EvictionPolicy::NoEviction {
+        return false;
+    }
+
+    let mut candidates: Vec<(usize, &Vec<u8>)> = data
+        .dbs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, db)| {
+            let keys: Box<dyn Iterator<Item = &Vec<u8>>> = if policy.volatile() {
+                Box::new(db.ttl.keys())
+            } else {
+                Box::new(db.keys.keys())
+            };
+            keys.map(move |k| (i, k))
+        })
+        .collect();
+    // Sort first so the order doesn't depend on hash map iteration.
+    candidates.sort();
+    let mut rng = data.inner.rng.clone();
+    let access = |(db, key): &(usize, &Vec<u8>)| {
+        data.dbs[*db]
+            .access
+            .get(key.as_slice())
+            .copied()
+            .unwrap_or_default()
+    };
+    match policy {
+        EvictionPolicy::AllKeysLru | EvictionPolicy::VolatileLru => {
+            candidates.sort_by_key(|c| access(c).clock)
+        }
+        EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu => {
+            candidates.sort_by_key(|c| (access(c).freq, access(c).clock))
+        }
+        EvictionPolicy::VolatileTtl => {
+            candidates.sort_by_key(|(db, key)| data.dbs[*db].ttl.get(key.as_slice()).copied())
+        }
+        EvictionPolicy::AllKeysRandom | EvictionPolicy::VolatileRandom => {
+            candidates.shuffle(&mut rng);
+        }
+        EvictionPolicy::NoEviction => unreachable!(),
+    }
+    let mut victims = Vec::new();
+    for (db, key) in candidates {
+        if used <= data.inner.maxmemory {
+            break;
+        }
+        let size = data.dbs[db].estimate_key_size(key).unwrap_or(0) as u64;
+        used = used.saturating_sub(size);
+        victims.push((db, key.clone()));
+    }
+    data.inner.rng = rng;
+
+    for (db, key) in victims {
+        let db_ref = &mut data.dbs[db];
+        db_ref.notify(NOTIFY_EVICTED, "evicted", &key);
+        db_ref.del(&key);
+        data.inner.evicted_keys += 1;
+        evicted.push((db, key));
+    }
+    used <= data.inner.maxmemory
+}
+
+/// Evict keys as needed before running a command, sending the deletes to
+/// the replicas. Returns false if memory use is still over `maxmemory`.
+/// Replicas ignore `maxmemory`; they get their master's evictions.
+pub fn perform_evictions(state: &SharedState) -> bool {
+    let maxmemory = state.lock().maxmemory;
+    if maxmemory == 0 || state.replication.lock().unwrap().is_replica() {
+        return true;
+    }
+    // Memory use usually fits: check that one database at a time, without
+    // stopping the other commands.
+    let used: u64 = (0..state.dbs.len())
+        .map(|i| state.db(i).used_memory() as u64)
+        .sum();
+    let mut inner = state.lock();
+    if used <= maxmemory || inner.maxmemory_policy == EvictionPolicy::NoEviction {
+        inner.oom = used > maxmemory;
+        return !inner.oom;
+    }
+    drop(inner);
+    let _keyspace = state.lock_keyspace(Isolation::Exclusive);
+    let mut evicted = Vec::new();
+    let fits = {
+        let mut data = state.lock_all();
+        let fits = evict(&mut data, &mut evicted);
+        data.inner.oom = !fits;
+        fits
+    };
+    if !evicted.is_empty() {
+        crate::replication::propagate_evicted(state, &evicted);
+        state.publish_changes(None);
+    }
+    fits
+}
+
+/// Parse a memory size as accepted by CONFIG SET maxmemory: bytes, or a
+/// number with a k, kb, m, mb, g or gb unit (case-insensitive).
+pub fn parse_memory(value: &str) -> Option<u64> {
+    let lower = value.to_ascii_lowercase();
+    let split = lower
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(lower.len());
+    let (digits, unit) = lower.split_at(split);
+    let n: u64 = digits.parse().ok()?;
+    let mul = match unit {
+        "" | "b" => 1,
+        "k" => 1_000,
+        "kb" => 1_024,
+        "m" => 1_000_000,
+        "mb" => 1_024 * 1_024,
+        "g" => 1_000_000_000,
+        "gb" => 1_024 * 1_024 * 1_024,
+        _ => return None,
+    };
+    n.checked_mul(mul)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use super::*;
+
+    fn filled(policy: EvictionPolicy) -> Arc<SharedState> {
+        let state = SharedState::new();
+        let now = SystemTime::now();
+        for key in ["a", "b", "c", "d"] {
+            state
+                .db(0)
+                .string_set(key.as_bytes(), b"0123456789".to_vec(), now);
+        }
+        state.lock().maxmemory_policy = policy;
+        state
+    }
+
+    fn keys(data: &Dataset) -> Vec<String> {
+        let mut keys: Vec<String> = data.dbs[0]
+            .keys
+            .keys()
+            .map(|k| String::from_utf8_lossy(k).to_string())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn test_policy_names() {
+        for (policy, name) in POLICIES {
+            assert_eq!(EvictionPolicy::parse(name), Some(*policy));
+            assert_eq!(policy.as_str(), *name);
+        }
+        assert_eq!(
+            EvictionPolicy::parse("ALLKEYS-LRU"),
+            Some(EvictionPolicy::AllKeysLru)
+        );
+        assert_eq!(EvictionPolicy::parse("lru"), None);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("100"), Some(100));
+        assert_eq!(parse_memory("1k"), Some(1_000));
+        assert_eq!(parse_memory("1KB"), Some(1_024));
+        assert_eq!(parse_memory("2mb"), Some(2 * 1_024 * 1_024));
+        assert_eq!(parse_memory("1g"), Some(1_000_000_000));
+        assert_eq!(parse_memory("-1"), None);
+        assert_eq!(parse_memory("1tb"), None);
+        assert_eq!(parse_memory("mb"), None);
+    }
+
+    #[test]
+    fn test_deny_oom() {
+        assert!(deny_oom("SET", &[]));
+        assert!(!deny_oom("DEL", &[]));
+        assert!(!deny_oom("GET", &[]));
+        assert!(deny_oom("FUNCTION", &[b"load".to_vec()]));
+        assert!(!deny_oom("FUNCTION", &[b"DELETE".to_vec()]));
+    }
+
+    #[test]
+    fn test_used_memory_tracked() {
+        let state = filled(EvictionPolicy::NoEviction);
+        let mut data = state.lock_all();
+        let full = |data: &Dataset| -> usize {
+            let db = &data.dbs[0];
+            db.keys.keys().filter_map(|k| db.estimate_key_size(k)).sum()
+        };
+        data.set_maxmemory(1_000_000);
+        assert_eq!(used_memory(&mut data), full(&data) as u64);
+
+        let now = data.now;
+        data.dbs[0].string_set(b"a", vec![0; 100], now);
+        data.dbs[0].del(b"b");
+        data.dbs[0].rename(b"c", b"e", now);
+        data.dbs[0].list_rpush(b"l", &[b"x".to_vec()], now);
+        assert_eq!(used_memory(&mut data), full(&data) as u64);
+
+        data.dbs[0].flush();
+        assert_eq!(used_memory(&mut data), 0);
+
+        data.set_maxmemory(0);
+        assert!(data.dbs[0].memory.is_none());
+    }
+
+    #[test]
+    fn test_evict_lru() {
+        let state = filled(EvictionPolicy::AllKeysLru);
+        let mut data = state.lock_all();
+        let size = data.dbs[0].estimate_key_size(b"a").unwrap() as u64;
+        data.dbs[0].touch(b"a");
+        data.inner.maxmemory = size * 2;
+
+        let mut evicted = Vec::new();
+        assert!(evict(&mut data, &mut evicted));
+        assert_eq!(evicted, vec![(0, b"b".to_vec()), (0, b"c".to_vec())]);
+        assert_eq!(keys(&data), vec!["a", "d"]);
+        assert_eq!(data.inner.evicted_keys, 2);
+    }
+
+    #[test]
+    fn test_evict_lfu() {
+        let state = filled(EvictionPolicy::AllKeysLfu);
+        let mut data = state.lock_all();
+        let size = data.dbs[0].estimate_key_size(b"a").unwrap() as u64;
+        for key in [b"a", b"c", b"a", b"d"] {
+            data.dbs[0].touch(key);
+        }
+        data.inner.maxmemory = size * 3;
+
+        let mut evicted = Vec::new();
+        assert!(evict(&mut data, &mut evicted));
+        assert_eq!(keys(&data), vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_evict_volatile() {
+        let state = filled(EvictionPolicy::VolatileTtl);
+        let mut data = state.lock_all();
+        let size = data.dbs[0].estimate_key_size(b"a").unwrap() as u64;
+        data.dbs[0]
+            .ttl
+            .insert(b"c".to_vec(), Duration::from_secs(10));
+        data.dbs[0]
+            .ttl
+            .insert(b"d".to_vec(), Duration::from_secs(5));
+        data.inner.maxmemory = size * 3;
+
+        let mut evicted = Vec::new();
+        assert!(evict(&mut data, &mut evicted));
+        assert_eq!(keys(&data), vec!["a", "b", "c"]);
+
+        // Only keys with a TTL qualify.
+        data.inner.maxmemory = size;
+        assert!(!evict(&mut data, &mut evicted));
+        assert_eq!(keys(&data), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_evict_noeviction() {
+        let state = filled(EvictionPolicy::NoEviction);
+        let mut data = state.lock_all();
+        data.inner.maxmemory = 1;
+        let mut evicted = Vec::new();
+        assert!(!evict(&mut data, &mut evicted));
+        assert!(evicted.is_empty());
+        assert_eq!(keys(&data).len(), 4);
+
+        data.inner.maxmemory = 0;
+        assert!(evict(&mut data, &mut evicted));
+    }
+}
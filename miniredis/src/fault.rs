@@ -0,0 +1,182 @@
+//! Fault injection: make commands fail, reply slowly, or drop the
+//! connection, to test client retry and timeout handling.
+//!
+//! Faults are added with `Miniredis::inject_fault` or the
+//! `MINIREDIS.FAULT` command, and apply to every command run, including
+//! commands inside MULTI/EXEC and Lua scripts.
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::keys::glob_match;
+
+/// What a fault does to a matching command.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FaultKind {
+    /// Reply with this error (e.g. "ERR boom", "LOADING ...") instead of
+    /// running the command.
+    Error(String),
+    /// Run the command, but delay the reply by a random duration in
+    /// `min..=max`. Use equal bounds for a fixed delay.
+    Latency { min: Duration, max: Duration },
+    /// Run the command, then close the connection halfway through writing
+    /// the reply.
+    DropConnection,
+}
+
+/// A fault to inject.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fault {
+    pub kind: FaultKind,
+    /// Glob patterns for the (uppercase) command names the fault applies
+    /// to. Empty matches every command.
+    pub commands: Vec<String>,
+    /// How many more times the fault triggers; `None` until removed.
+    pub remaining: Option<u64>,
+}
+
+impl Fault {
+    fn new(kind: FaultKind) -> Self {
+        Fault {
+            kind,
+            commands: Vec::new(),
+            remaining: None,
+        }
+    }
+
+    /// Reply with an error instead of running the command.
+    pub fn error(msg: impl Into<String>) -> Self {
+        Self::new(FaultKind::Error(msg.into()))
+    }
+
+    /// Delay replies by a fixed duration.
+    pub fn latency(delay: Duration) -> Self {
+        Self::random_latency(delay, delay)
+    }
+
+    /// Delay replies by a random duration between `min` and `max`.
+    pub fn random_latency(min: Duration, max: Duration) -> Self {
+        Self::new(FaultKind::Latency {
+            min,
+            max: max.max(min),
+        })
+    }
+
+    /// Close the connection in the middle of the reply.
+    pub fn drop_connection() -> Self {
+        Self::new(FaultKind::DropConnection)
+    }
+
+    /// Only apply to commands matching one of these glob patterns, such as
+    /// "GET" or "Z*". Matching is case insensitive.
+    pub fn commands(mut self, patterns: &[&str]) -> Self {
+        self.commands = patterns.iter().map(|p| p.to_uppercase()).collect();
+        self
+    }
+
+    /// Only trigger for the next `n` matching commands.
+    pub fn times(mut self, n: u64) -> Self {
+        self.remaining = Some(n);
+        self
+    }
+
+    fn matches(&self, cmd: &str) -> bool {
+        self.commands.is_empty() || self.commands.iter().any(|p| glob_match(p, cmd))
+    }
+}
+
+/// Delays and disconnects owed to a connection by faults that triggered
+/// while running its last command.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaultEffect {
+    pub delay: Duration,
+    pub drop_connection: bool,
+}
+
+/// All injected faults.
+#[derive(Debug, Default)]
+pub struct FaultTable {
+    next_id: u64,
+    /// Faults by ID, in the order they were added.
+    faults: Vec<(u64, Fault)>,
+    /// Effects not yet applied, by client ID.
+    pending: HashMap<u64, FaultEffect>,
+}
+
+impl FaultTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a fault. Returns its ID.
+    pub fn add(&mut self, fault: Fault) -> u64 {
+        self.next_id += 1;
+        self.faults.push((self.next_id, fault));
+        self.next_id
+    }
+
+    /// Remove a fault. Returns false if there was no such fault.
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.faults.len();
+        self.faults.retain(|(fid, _)| *fid != id);
+        self.faults.len() != before
+    }
+
+    /// Remove all faults.
+    pub fn clear(&mut self) {
+        self.faults.clear();
+        self.pending.clear();
+    }
+
+    /// The active faults, with their IDs.
+    pub fn list(&self) -> &[(u64, Fault)] {
+        &self.faults
+    }
+
+    /// Apply the faults to command `cmd` (uppercase), run by client
+    /// `client_id`. Returns the error to reply with if an error fault
+    /// triggers. Latency and disconnect faults are recorded, to be applied
+    /// with `take_effect` once the reply is ready.
+    ///
+    /// MINIREDIS.* commands are never affected, so faults can always be
+    /// removed again.
+    pub fn check(&mut self, client_id: u64, cmd: &str) -> Option<String> {
+        if self.faults.is_empty() || cmd.starts_with("MINIREDIS.") {
+            return None;
+        }
+        let mut error = None;
+        for (_, fault) in &mut self.faults {
+            if fault.remaining == Some(0) || !fault.matches(cmd) {
+                continue;
+            }
+            if let Some(n) = &mut fault.remaining {
+                *n -= 1;
+            }
+            match &fault.kind {
+                FaultKind::Error(msg) => {
+                    error = Some(msg.clone());
+                    break;
+                }
+                FaultKind::Latency { min, max } => {
+                    let delay = if min == max {
+                        *min
+                    } else {
+                        rand::rng().random_range(*min..=*max)
+                    };
+                    self.pending.entry(client_id).or_default().delay += delay;
+                }
+                FaultKind::DropConnection => {
+                    self.pending.entry(client_id).or_default().drop_connection = true;
+                }
+            }
+        }
+        self.faults.retain(|(_, f)| f.remaining != Some(0));
+        error
+    }
+
+    /// Take the effects recorded for a client since the last call.
+    pub fn take_effect(&mut self, client_id: u64) -> FaultEffect {
+        self.pending.remove(&client_id).unwrap_or_default()
+    }
+}
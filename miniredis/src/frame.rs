@@ -440,7 +440,7 @@ impl fmt::Display for Frame {
 }
 
 /// Format a f64 for RESP3 Double wire encoding.
-fn format_double(f: f64) -> String {
+pub(crate) fn format_double(f: f64) -> String {
     if f.is_infinite() {
         if f.is_sign_positive() {
             "inf".to_string()
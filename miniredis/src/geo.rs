@@ -109,6 +109,29 @@ pub fn haversine_distance(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
     2.0 * EARTH_RADIUS * h.sqrt().asin()
 }
 
+/// The distance in meters from a point (latitude, longitude) to the
+/// center of a `width` x `height` meter box, if the point lies in the box.
+///
+/// Like Redis (geohashGetDistanceIfInRectangle), the height is measured
+/// along the meridian and the width along the point's own parallel.
+pub fn box_distance(
+    center_lat: f64,
+    center_lon: f64,
+    width: f64,
+    height: f64,
+    lat: f64,
+    lon: f64,
+) -> Option<f64> {
+    let lat_distance = EARTH_RADIUS * ((lat - center_lat) * PI / 180.0).abs();
+    if lat_distance > height / 2.0 {
+        return None;
+    }
+    if haversine_distance(lat, center_lon, lat, lon) > width / 2.0 {
+        return None;
+    }
+    Some(haversine_distance(center_lat, center_lon, lat, lon))
+}
+
 // ── Unit conversion ─────────────────────────────────────────────────
 
 /// Parse a distance unit string and return the conversion factor to meters.
@@ -158,6 +181,42 @@ mod tests {
         assert!((d - 166274.0).abs() < 100.0, "distance: {}", d);
     }
 
+    #[test]
+    fn test_box_distance() {
+        // Palermo is ~150km west and ~68km north of Catania.
+        let (palermo_lat, palermo_lon) = (38.115556, 13.361389);
+        let (catania_lat, catania_lon) = (37.502669, 15.087269);
+        let d = box_distance(
+            catania_lat,
+            catania_lon,
+            400_000.0,
+            200_000.0,
+            palermo_lat,
+            palermo_lon,
+        );
+        assert!((d.unwrap() - 166274.0).abs() < 100.0, "distance: {:?}", d);
+
+        // Too narrow, too low.
+        let narrow = box_distance(
+            catania_lat,
+            catania_lon,
+            200_000.0,
+            200_000.0,
+            palermo_lat,
+            palermo_lon,
+        );
+        assert_eq!(narrow, None);
+        let low = box_distance(
+            catania_lat,
+            catania_lon,
+            400_000.0,
+            100_000.0,
+            palermo_lat,
+            palermo_lon,
+        );
+        assert_eq!(low, None);
+    }
+
     #[test]
     fn test_parse_unit() {
         assert_eq!(parse_unit("m"), Some(1.0));
@@ -54,6 +54,15 @@ fn get_pos_val(hash: u64) -> (usize, u8) {
     (idx, rho)
 }
 
+/// Size of the Redis HLL header: magic, encoding, padding, cached count.
+const HLL_HDR_SIZE: usize = 16;
+/// Bits per register in the Redis dense representation.
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_DENSE_SIZE: usize = (M * HLL_BITS).div_ceil(8);
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+
 /// HyperLogLog probabilistic set cardinality estimator.
 #[derive(Clone, Debug)]
 pub struct HyperLogLog {
@@ -113,6 +122,85 @@ impl HyperLogLog {
             }
         }
     }
+
+    /// Serialize in the Redis dense representation ("HYLL" header followed
+    /// by 16384 packed 6-bit registers), as stored in RDB files.
+    ///
+    /// Redis hashes elements differently, so a real Redis reading this gets
+    /// the right PFCOUNT, but PFADDs of already counted elements may count
+    /// again (and vice versa for `decode`).
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HLL_HDR_SIZE + HLL_DENSE_SIZE);
+        out.extend_from_slice(b"HYLL");
+        out.push(HLL_DENSE);
+        out.extend_from_slice(&[0; 3]);
+        // Cached cardinality, flagged as invalid so Redis recomputes it.
+        out.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0x80]);
+        let mut dense = vec![0u8; HLL_DENSE_SIZE + 1];
+        for (i, &reg) in self.registers.iter().enumerate() {
+            let byte = i * HLL_BITS / 8;
+            let shift = i * HLL_BITS % 8;
+            let v = (reg.min(HLL_REGISTER_MAX) as u16) << shift;
+            dense[byte] |= v as u8;
+            dense[byte + 1] |= (v >> 8) as u8;
+        }
+        out.extend_from_slice(&dense[..HLL_DENSE_SIZE]);
+        out
+    }
+
+    /// Parse a Redis HyperLogLog string, in either dense or sparse
+    /// representation. Returns None if `data` isn't a valid HLL.
+    pub fn decode(data: &[u8]) -> Option<HyperLogLog> {
+        if data.len() < HLL_HDR_SIZE || &data[..4] != b"HYLL" {
+            return None;
+        }
+        let body = &data[HLL_HDR_SIZE..];
+        let mut registers = vec![0u8; M];
+        match data[4] {
+            HLL_DENSE => {
+                if body.len() != HLL_DENSE_SIZE {
+                    return None;
+                }
+                for (i, reg) in registers.iter_mut().enumerate() {
+                    let byte = i * HLL_BITS / 8;
+                    let shift = i * HLL_BITS % 8;
+                    let b0 = body[byte] as u16;
+                    let b1 = body.get(byte + 1).copied().unwrap_or(0) as u16;
+                    *reg = (((b0 | (b1 << 8)) >> shift) as u8) & HLL_REGISTER_MAX;
+                }
+            }
+            HLL_SPARSE => {
+                // Opcodes: ZERO 00xxxxxx, XZERO 01xxxxxx yyyyyyyy,
+                // VAL 1vvvvvxx.
+                let mut idx = 0usize;
+                let mut pos = 0;
+                while pos < body.len() {
+                    let op = body[pos];
+                    let (value, run) = if op & 0xc0 == 0 {
+                        pos += 1;
+                        (0, (op & 0x3f) as usize + 1)
+                    } else if op & 0xc0 == 0x40 {
+                        let next = *body.get(pos + 1)?;
+                        pos += 2;
+                        (0, ((((op & 0x3f) as usize) << 8) | next as usize) + 1)
+                    } else {
+                        pos += 1;
+                        (((op >> 2) & 0x1f) + 1, (op & 0x03) as usize + 1)
+                    };
+                    if idx + run > M {
+                        return None;
+                    }
+                    registers[idx..idx + run].fill(value);
+                    idx += run;
+                }
+                if idx != M {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+        Some(HyperLogLog { registers })
+    }
 }
 
 #[cfg(test)]
@@ -198,4 +286,30 @@ mod tests {
         // p=14 gives ~0.8% standard error, allow 5% margin
         assert!((9500..=10500).contains(&count), "count was {}", count);
     }
+
+    #[test]
+    fn test_hll_encode_decode() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..1000 {
+            hll.add(format!("element-{}", i).as_bytes());
+        }
+        let data = hll.encode();
+        assert_eq!(data.len(), 16 + 12288);
+        assert_eq!(&data[..4], b"HYLL");
+        let decoded = HyperLogLog::decode(&data).unwrap();
+        assert_eq!(decoded.registers, hll.registers);
+        assert!(HyperLogLog::decode(b"HYLLnope").is_none());
+        assert!(HyperLogLog::decode(b"plain string value").is_none());
+    }
+
+    #[test]
+    fn test_hll_decode_sparse() {
+        // What Redis stores after `PFADD k a`: register 10423 set to 2.
+        let mut data = b"HYLL\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x80".to_vec();
+        data.extend_from_slice(&[0x68, 0xb6, 0x84, 0x57, 0x47]);
+        let hll = HyperLogLog::decode(&data).unwrap();
+        assert_eq!(hll.registers[10423], 2);
+        assert_eq!(hll.registers.iter().filter(|&&r| r != 0).count(), 1);
+        assert_eq!(hll.count(), 1);
+    }
 }
@@ -0,0 +1,641 @@
+//! JSON documents for the RedisJSON commands: path parsing and evaluation,
+//! and the formatting options of JSON.GET.
+//!
+//! Paths starting with `$` are JSONPath, and select any number of values.
+//! Anything else is a legacy path, such as `.a.b[0]` or `.`, which selects
+//! a single value. Both support member names (`.name`, `['name']`),
+//! indexes (`[0]`, `[-1]`), slices (`[1:3]`), unions (`[0,2]`), wildcards
+//! (`*`, `[*]`) and recursive descent (`..name`). Filter expressions are
+//! not supported.
+use serde_json::{Map, Value};
+
+/// A step from a value to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PathElem {
+    Key(String),
+    Index(usize),
+}
+
+/// The location of a value in a document; empty for the root.
+pub type Location = Vec<PathElem>;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(i64),
+    /// Start, end and step. Missing bounds are the ends of the array.
+    Slice(Option<i64>, Option<i64>, i64),
+    Wildcard,
+    Union(Vec<Selector>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    /// `..`: apply the selector to the value and all its descendants.
+    descendants: bool,
+    selector: Selector,
+}
+
+/// A parsed JSONPath or legacy path.
+#[derive(Debug, Clone)]
+pub struct Path {
+    steps: Vec<Step>,
+    /// A legacy path, selecting a single value.
+    pub legacy: bool,
+    /// The path as given.
+    pub text: String,
+}
+
+impl Path {
+    /// Parse a JSONPath (`$...`) or legacy path.
+    pub fn parse(text: &str) -> Result<Path, String> {
+        let (rest, legacy) = match text.strip_prefix('$') {
+            Some(rest) => (rest.to_string(), false),
+            None if text.is_empty() || text == "." => (String::new(), true),
+            None if text.starts_with('.') || text.starts_with('[') => (text.to_string(), true),
+            None => (format!(".{text}"), true),
+        };
+        let steps = Parser {
+            chars: rest.chars().collect(),
+            pos: 0,
+        }
+        .steps()
+        .ok_or_else(|| format!("ERR invalid JSON path '{text}'"))?;
+        Ok(Path {
+            steps,
+            legacy,
+            text: text.to_string(),
+        })
+    }
+
+    /// The legacy root path, the default of most commands.
+    pub fn root() -> Path {
+        Path {
+            steps: Vec::new(),
+            legacy: true,
+            text: ".".to_string(),
+        }
+    }
+
+    /// Whether the path selects the whole document.
+    pub fn is_root(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// The error for a legacy path that selects nothing.
+    pub fn missing(&self) -> String {
+        format!("ERR Path '{}' does not exist", self.text)
+    }
+
+    /// The locations of the values the path selects, in document order.
+    pub fn find(&self, doc: &Value) -> Vec<Location> {
+        let mut found = vec![Vec::new()];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for loc in found {
+                let Some(value) = get(doc, &loc) else {
+                    continue;
+                };
+                if step.descendants {
+                    let mut all = Vec::new();
+                    descendants(value, loc, &mut all);
+                    for (loc, value) in all {
+                        select(&step.selector, value, &loc, &mut next);
+                    }
+                } else {
+                    select(&step.selector, value, &loc, &mut next);
+                }
+            }
+            found = next;
+        }
+        found
+    }
+
+    /// The values the path selects: all of them for JSONPath, the first
+    /// one for a legacy path.
+    pub fn values<'a>(&self, doc: &'a Value) -> Vec<&'a Value> {
+        let mut values: Vec<&Value> = self
+            .find(doc)
+            .iter()
+            .filter_map(|loc| get(doc, loc))
+            .collect();
+        if self.legacy {
+            values.truncate(1);
+        }
+        values
+    }
+
+    /// What JSON.GET returns for the path: an array of the selected values
+    /// for JSONPath, the value itself for a legacy path.
+    pub fn query(&self, doc: &Value) -> Result<Value, String> {
+        let values = self.values(doc);
+        if !self.legacy {
+            return Ok(Value::Array(values.into_iter().cloned().collect()));
+        }
+        values
+            .first()
+            .map(|v| (*v).clone())
+            .ok_or_else(|| self.missing())
+    }
+
+    /// The path without a final member name, and that name. None if the
+    /// path doesn't end in a plain member name.
+    fn split_name(&self) -> Option<(Path, &str)> {
+        let (last, parent) = self.steps.split_last()?;
+        match last {
+            Step {
+                descendants: false,
+                selector: Selector::Name(name),
+            } => Some((
+                Path {
+                    steps: parent.to_vec(),
+                    legacy: self.legacy,
+                    text: self.text.clone(),
+                },
+                name.as_str(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Push `value` and all values below it, depth first.
+fn descendants<'a>(value: &'a Value, loc: Location, out: &mut Vec<(Location, &'a Value)>) {
+    out.push((loc.clone(), value));
+    match value {
+        Value::Object(obj) => {
+            for (k, v) in obj {
+                let mut child = loc.clone();
+                child.push(PathElem::Key(k.clone()));
+                descendants(v, child, out);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                let mut child = loc.clone();
+                child.push(PathElem::Index(i));
+                descendants(v, child, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Push the locations of the children of `value` that `selector` selects.
+fn select(selector: &Selector, value: &Value, loc: &Location, out: &mut Vec<Location>) {
+    let mut push = |elem| {
+        let mut child = loc.clone();
+        child.push(elem);
+        out.push(child);
+    };
+    match (selector, value) {
+        (Selector::Name(name), Value::Object(obj)) if obj.contains_key(name) => {
+            push(PathElem::Key(name.clone()))
+        }
+        (Selector::Index(i), Value::Array(arr)) => {
+            let len = arr.len() as i64;
+            let i = if *i < 0 { len + i } else { *i };
+            if (0..len).contains(&i) {
+                push(PathElem::Index(i as usize));
+            }
+        }
+        (Selector::Slice(start, end, step), Value::Array(arr)) => {
+            let len = arr.len() as i64;
+            let bound = |b: i64| if b < 0 { (len + b).max(0) } else { b.min(len) };
+            let start = start.map_or(0, bound);
+            let end = end.map_or(len, bound);
+            for i in (start..end).step_by(*step as usize) {
+                push(PathElem::Index(i as usize));
+            }
+        }
+        (Selector::Wildcard, Value::Object(obj)) => {
+            for k in obj.keys() {
+                push(PathElem::Key(k.clone()));
+            }
+        }
+        (Selector::Wildcard, Value::Array(arr)) => {
+            for i in 0..arr.len() {
+                push(PathElem::Index(i));
+            }
+        }
+        (Selector::Union(selectors), _) => {
+            for s in selectors {
+                select(s, value, loc, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The value at `loc`.
+pub fn get<'a>(doc: &'a Value, loc: &[PathElem]) -> Option<&'a Value> {
+    loc.iter().try_fold(doc, |v, elem| match elem {
+        PathElem::Key(k) => v.as_object()?.get(k),
+        PathElem::Index(i) => v.as_array()?.get(*i),
+    })
+}
+
+/// The value at `loc`, mutably.
+pub fn get_mut<'a>(doc: &'a mut Value, loc: &[PathElem]) -> Option<&'a mut Value> {
+    loc.iter().try_fold(doc, |v, elem| match elem {
+        PathElem::Key(k) => v.as_object_mut()?.get_mut(k),
+        PathElem::Index(i) => v.as_array_mut()?.get_mut(*i),
+    })
+}
+
+/// JSON.SET on an existing document: replace the values `path` selects,
+/// or, when it selects none, add the member it names to its parent
+/// objects. NX only adds, XX only replaces. Returns whether anything was
+/// set.
+pub fn set(doc: &mut Value, path: &Path, value: Value, nx: bool, xx: bool) -> bool {
+    let found = path.find(doc);
+    if !found.is_empty() {
+        if nx {
+            return false;
+        }
+        for loc in &found {
+            if let Some(v) = get_mut(doc, loc) {
+                *v = value.clone();
+            }
+        }
+        return true;
+    }
+    if xx {
+        return false;
+    }
+    let Some((parent, name)) = path.split_name() else {
+        return false;
+    };
+    let mut set = false;
+    for loc in parent.find(doc) {
+        if let Some(Value::Object(obj)) = get_mut(doc, &loc) {
+            obj.insert(name.to_string(), value.clone());
+            set = true;
+        }
+    }
+    set
+}
+
+/// Remove the values `path` selects, other than the root. Returns the
+/// number of values removed.
+pub fn delete(doc: &mut Value, path: &Path) -> usize {
+    let mut found = path.find(doc);
+    // Last first, so removing an array element doesn't shift the indexes
+    // of the others, and children go before their parents.
+    found.sort_unstable_by(|a, b| b.cmp(a));
+    found.dedup();
+    let mut removed = 0;
+    for loc in found {
+        let Some((last, parent)) = loc.split_last() else {
+            continue;
+        };
+        let gone = match (get_mut(doc, parent), last) {
+            (Some(Value::Object(obj)), PathElem::Key(k)) => obj.shift_remove(k).is_some(),
+            (Some(Value::Array(arr)), PathElem::Index(i)) if *i < arr.len() => {
+                arr.remove(*i);
+                true
+            }
+            _ => false,
+        };
+        removed += gone as usize;
+    }
+    removed
+}
+
+/// The name JSON.TYPE reports for a value.
+pub fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// The INDENT, NEWLINE and SPACE options of JSON.GET.
+#[derive(Debug, Clone, Default)]
+pub struct Format {
+    pub indent: String,
+    pub newline: String,
+    pub space: String,
+}
+
+impl Format {
+    /// Serialize `value`; compact unless options are set.
+    pub fn format(&self, value: &Value) -> String {
+        if self.indent.is_empty() && self.newline.is_empty() && self.space.is_empty() {
+            return value.to_string();
+        }
+        let mut out = String::new();
+        self.write(&mut out, value, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, value: &Value, depth: usize) {
+        let line = |out: &mut String, depth: usize| {
+            out.push_str(&self.newline);
+            for _ in 0..depth {
+                out.push_str(&self.indent);
+            }
+        };
+        match value {
+            Value::Array(arr) if !arr.is_empty() => {
+                out.push('[');
+                for (i, v) in arr.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    line(out, depth + 1);
+                    self.write(out, v, depth + 1);
+                }
+                line(out, depth);
+                out.push(']');
+            }
+            Value::Object(obj) if !obj.is_empty() => {
+                out.push('{');
+                for (i, (k, v)) in obj.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    line(out, depth + 1);
+                    out.push_str(&Value::String(k.clone()).to_string());
+                    out.push(':');
+                    out.push_str(&self.space);
+                    self.write(out, v, depth + 1);
+                }
+                line(out, depth);
+                out.push('}');
+            }
+            _ => out.push_str(&value.to_string()),
+        }
+    }
+}
+
+/// JSON.GET with several paths: an object with the result of each path.
+/// Legacy paths give the value itself, unless any path is JSONPath.
+pub fn query_all(doc: &Value, paths: &[Path]) -> Result<Value, String> {
+    let legacy = paths.iter().all(|p| p.legacy);
+    let mut obj = Map::new();
+    for path in paths {
+        let value = if legacy {
+            path.query(doc)?
+        } else {
+            Value::Array(
+                path.find(doc)
+                    .iter()
+                    .filter_map(|loc| get(doc, loc))
+                    .cloned()
+                    .collect(),
+            )
+        };
+        obj.insert(path.text.clone(), value);
+    }
+    Ok(Value::Object(obj))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn steps(&mut self) -> Option<Vec<Step>> {
+        let mut steps = Vec::new();
+        while self.peek().is_some() {
+            let step = if self.eat('.') {
+                let descendants = self.eat('.');
+                let selector = if self.eat('*') {
+                    Selector::Wildcard
+                } else if descendants && self.eat('[') {
+                    self.bracket()?
+                } else {
+                    self.name()?
+                };
+                Step {
+                    descendants,
+                    selector,
+                }
+            } else if self.eat('[') {
+                Step {
+                    descendants: false,
+                    selector: self.bracket()?,
+                }
+            } else {
+                return None;
+            };
+            steps.push(step);
+        }
+        Some(steps)
+    }
+
+    /// A member name after a dot.
+    fn name(&mut self) -> Option<Selector> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c != '.' && c != '[') {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return None;
+        }
+        Some(Selector::Name(self.chars[start..self.pos].iter().collect()))
+    }
+
+    /// The selectors between `[` and `]`, the `[` already read.
+    fn bracket(&mut self) -> Option<Selector> {
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_spaces();
+            selectors.push(match self.peek()? {
+                '*' => {
+                    self.pos += 1;
+                    Selector::Wildcard
+                }
+                '\'' | '"' => Selector::Name(self.quoted()?),
+                _ => self.index_or_slice()?,
+            });
+            self.skip_spaces();
+            if self.eat(']') {
+                break;
+            }
+            if !self.eat(',') {
+                return None;
+            }
+        }
+        Some(if selectors.len() == 1 {
+            selectors.pop()?
+        } else {
+            Selector::Union(selectors)
+        })
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek() == Some(' ') {
+            self.pos += 1;
+        }
+    }
+
+    fn quoted(&mut self) -> Option<String> {
+        let quote = self.peek()?;
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            match self.peek()? {
+                '\\' => {
+                    self.pos += 1;
+                    s.push(self.peek()?);
+                }
+                c if c == quote => break,
+                c => s.push(c),
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        Some(s)
+    }
+
+    fn int(&mut self) -> Option<Option<i64>> {
+        self.skip_spaces();
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Some(None);
+        }
+        let n: String = self.chars[start..self.pos].iter().collect();
+        n.parse().ok().map(Some)
+    }
+
+    fn index_or_slice(&mut self) -> Option<Selector> {
+        let start = self.int()?;
+        self.skip_spaces();
+        if !self.eat(':') {
+            return start.map(Selector::Index);
+        }
+        let end = self.int()?;
+        self.skip_spaces();
+        let step = if self.eat(':') {
+            self.int()?.unwrap_or(1)
+        } else {
+            1
+        };
+        (step > 0).then_some(Selector::Slice(start, end, step))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn find(doc: &Value, path: &str) -> Vec<Value> {
+        let path = Path::parse(path).unwrap();
+        path.find(doc)
+            .iter()
+            .map(|loc| get(doc, loc).unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(Path::parse("$").unwrap().is_root());
+        assert!(Path::parse(".").unwrap().is_root());
+        assert!(Path::parse("").unwrap().legacy);
+        assert!(!Path::parse("$.a").unwrap().legacy);
+        assert!(Path::parse("a.b").unwrap().legacy);
+        assert!(Path::parse("$.a[").is_err());
+        assert!(Path::parse("$.a[1:2:0]").is_err());
+        assert!(Path::parse("$.a[?(@.b>1)]").is_err());
+        assert!(Path::parse("$..").is_err());
+    }
+
+    #[test]
+    fn test_find() {
+        let doc = json!({"a": 1, "b": {"a": 2, "c": [3, 4, {"a": 5}]}, "d": "x y"});
+        assert_eq!(find(&doc, "$.a"), vec![json!(1)]);
+        assert_eq!(find(&doc, ".b.a"), vec![json!(2)]);
+        assert_eq!(find(&doc, "b.c[1]"), vec![json!(4)]);
+        assert_eq!(find(&doc, "$.b.c[-1].a"), vec![json!(5)]);
+        assert_eq!(find(&doc, "$['d']"), vec![json!("x y")]);
+        assert_eq!(find(&doc, "$..a"), vec![json!(1), json!(2), json!(5)]);
+        assert_eq!(find(&doc, "$.b.c[0:2]"), vec![json!(3), json!(4)]);
+        assert_eq!(find(&doc, "$.b.c[::2]"), vec![json!(3), json!({"a": 5})]);
+        assert_eq!(find(&doc, "$.b.c[0,2].a"), vec![json!(5)]);
+        assert_eq!(find(&doc, "$.b.*").len(), 2);
+        assert_eq!(find(&doc, "$.b.c[*]").len(), 3);
+        assert!(find(&doc, "$.nope").is_empty());
+        assert!(find(&doc, "$.a.b").is_empty());
+    }
+
+    #[test]
+    fn test_set_and_delete() {
+        let mut doc = json!({"a": [1, 2, 3], "b": {"c": 1}});
+        assert!(set(
+            &mut doc,
+            &Path::parse("$.b.d").unwrap(),
+            json!(true),
+            false,
+            false
+        ));
+        assert!(!set(
+            &mut doc,
+            &Path::parse("$.x.y").unwrap(),
+            json!(1),
+            false,
+            false
+        ));
+        assert!(!set(
+            &mut doc,
+            &Path::parse("$.b.c").unwrap(),
+            json!(2),
+            true,
+            false
+        ));
+        assert!(!set(
+            &mut doc,
+            &Path::parse("$.b.e").unwrap(),
+            json!(2),
+            false,
+            true
+        ));
+        assert_eq!(doc, json!({"a": [1, 2, 3], "b": {"c": 1, "d": true}}));
+
+        assert_eq!(delete(&mut doc, &Path::parse("$.a[0,2]").unwrap()), 2);
+        assert_eq!(delete(&mut doc, &Path::parse("$..c").unwrap()), 1);
+        assert_eq!(doc, json!({"a": [2], "b": {"d": true}}));
+    }
+
+    #[test]
+    fn test_format() {
+        let doc = json!({"a": [1, {}], "b": "x"});
+        let fmt = Format {
+            indent: "  ".into(),
+            newline: "\n".into(),
+            space: " ".into(),
+        };
+        assert_eq!(
+            fmt.format(&doc),
+            "{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": \"x\"\n}"
+        );
+        assert_eq!(Format::default().format(&doc), r#"{"a":[1,{}],"b":"x"}"#);
+    }
+}
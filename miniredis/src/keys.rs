@@ -2,11 +2,10 @@
 ///
 /// Supports *, ?, [abc], [a-z], [^a], \ escape.
 ///
-/// Simple glob matching: supports *, ?, [abc], [a-z], [^a].
-pub fn glob_match(pattern: &str, text: &str) -> bool {
-    let pat = pattern.as_bytes();
-    let txt = text.as_bytes();
-    glob_match_inner(pat, txt)
+/// Simple glob matching: supports *, ?, [abc], [a-z], [^a]. Works on
+/// bytes, so binary keys can be matched too.
+pub fn glob_match(pattern: impl AsRef<[u8]>, text: impl AsRef<[u8]>) -> bool {
+    glob_match_inner(pattern.as_ref(), text.as_ref())
 }
 
 fn glob_match_inner(pat: &[u8], txt: &[u8]) -> bool {
@@ -119,9 +118,9 @@ fn match_char_class(pat: &[u8], ch: u8) -> (bool, usize) {
     (matched, i)
 }
 
-/// Filter a list of strings by a glob pattern. Used by SSCAN, HSCAN etc.
-pub fn match_keys_vec(keys: &[String], pattern: &str) -> Vec<String> {
-    if pattern == "*" {
+/// Filter a list of byte strings by a glob pattern. Used by SSCAN, HSCAN etc.
+pub fn match_keys_vec(keys: &[Vec<u8>], pattern: &[u8]) -> Vec<Vec<u8>> {
+    if pattern == b"*" {
         return keys.to_vec();
     }
     keys.iter()
@@ -161,4 +160,15 @@ mod tests {
         assert!(!glob_match("[\\[o]*", "two"));
         assert!(glob_match("[\\[o]*", "other"));
     }
+
+    #[test]
+    fn test_glob_match_binary() {
+        assert!(glob_match(b"k\xff*", b"k\xff\x00rest"));
+        assert!(glob_match(b"?\x00", b"\x80\x00"));
+        assert!(!glob_match(b"k\xfe*", b"k\xff"));
+        assert_eq!(
+            match_keys_vec(&[b"a\xff".to_vec(), b"b".to_vec()], b"a*"),
+            vec![b"a\xff".to_vec()]
+        );
+    }
 }
@@ -0,0 +1,253 @@
+//! Which arguments of a command are key names.
+//!
+//! Redis describes this with the key specs in COMMAND DOCS. ACL key
+//! patterns need to know every key a command touches, and whether it
+//! reads or writes it, before the command runs.
+
+/// A key argument of a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyRef {
+    /// Index into the arguments, not counting the command name.
+    pub index: usize,
+    /// The command reads the value stored at the key.
+    pub read: bool,
+    /// The command modifies, creates or deletes the key.
+    pub write: bool,
+}
+
+impl KeyRef {
+    fn new(index: usize, access: Access) -> Self {
+        KeyRef {
+            index,
+            read: access != Access::Write,
+            write: access != Access::Read,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// The key arguments of `cmd` (uppercase) called with `args`. Arguments
+/// that are missing, because the command is called with too few of them,
+/// are left out.
+pub fn command_keys(cmd: &str, args: &[Vec<u8>]) -> Vec<KeyRef> {
+    use Access::*;
+
+    let all = |from: usize, to: usize, access: Access| -> Vec<KeyRef> {
+        (from..to.min(args.len()))
+            .map(|i| KeyRef::new(i, access))
+            .collect()
+    };
+    // KEYS given as `numkeys key [key ...]`, with numkeys at `at`.
+    let numkeys = |at: usize, access: Access| -> Vec<KeyRef> {
+        let n = args
+            .get(at)
+            .and_then(|n| std::str::from_utf8(n).ok()?.parse::<usize>().ok())
+            .unwrap_or(0);
+        all(at + 1, at + 1 + n, access)
+    };
+
+    let mut keys = match cmd {
+        // ── Read-only, single key ────────────────────────────────────
+        "GET" | "STRLEN" | "GETRANGE" | "SUBSTR" | "GETBIT" | "BITCOUNT" | "BITPOS"
+        | "BITFIELD_RO" | "TYPE" | "TTL" | "PTTL" | "EXPIRETIME" | "PEXPIRETIME" | "DUMP"
+        | "SORT_RO" => all(0, 1, Read),
+        "HGET" | "HMGET" | "HEXISTS" | "HGETALL" | "HKEYS" | "HVALS" | "HLEN" | "HSTRLEN"
+        | "HSCAN" | "HRANDFIELD" | "HTTL" | "HPTTL" | "HEXPIRETIME" | "HPEXPIRETIME" | "LLEN"
+        | "LINDEX" | "LRANGE" | "LPOS" | "SCARD" | "SMEMBERS" | "SISMEMBER" | "SMISMEMBER"
+        | "SRANDMEMBER" | "SSCAN" => all(0, 1, Read),
+        "ZCARD" | "ZCOUNT" | "ZSCORE" | "ZMSCORE" | "ZRANK" | "ZREVRANK" | "ZRANGE"
+        | "ZREVRANGE" | "ZRANGEBYSCORE" | "ZREVRANGEBYSCORE" | "ZRANGEBYLEX" | "ZREVRANGEBYLEX"
+        | "ZLEXCOUNT" | "ZSCAN" | "ZRANDMEMBER" => all(0, 1, Read),
+        "XLEN" | "XRANGE" | "XREVRANGE" | "XPENDING" => all(0, 1, Read),
+        "JSON.GET" | "JSON.TYPE" | "JSON.ARRLEN" | "JSON.OBJKEYS" => all(0, 1, Read),
+        "BF.EXISTS" | "BF.MEXISTS" | "BF.INFO" | "CF.EXISTS" | "CMS.QUERY" | "TOPK.LIST" => {
+            all(0, 1, Read)
+        }
+        "GEODIST" | "GEOPOS" | "GEOSEARCH" | "GEORADIUS_RO" | "GEORADIUSBYMEMBER_RO" => {
+            all(0, 1, Read)
+        }
+
+        // ── Read-only, several keys ──────────────────────────────────
+        "EXISTS" | "TOUCH" | "MGET" | "PFCOUNT" | "SDIFF" | "SINTER" | "SUNION" | "WATCH" => {
+            all(0, args.len(), Read)
+        }
+        "SINTERCARD" | "ZINTER" | "ZUNION" | "ZDIFF" | "ZINTERCARD" => numkeys(0, Read),
+        "EVAL_RO" | "EVALSHA_RO" | "FCALL_RO" => numkeys(1, Read),
+        "JSON.MGET" => all(0, args.len().saturating_sub(1), Read),
+        "OBJECT" | "XINFO" => all(1, 2, Read),
+        "MEMORY"
+            if args
+                .first()
+                .is_some_and(|s| s.eq_ignore_ascii_case(b"usage")) =>
+        {
+            all(1, 2, Read)
+        }
+        "XREAD" | "XREADGROUP" => {
+            let access = if cmd == "XREAD" { Read } else { ReadWrite };
+            match args.iter().position(|a| a.eq_ignore_ascii_case(b"streams")) {
+                Some(i) => {
+                    let rest = args.len() - i - 1;
+                    all(i + 1, i + 1 + rest / 2, access)
+                }
+                None => Vec::new(),
+            }
+        }
+
+        // ── Write-only: the command doesn't read the old value ───────
+        "SET" => {
+            let get = args.iter().skip(2).any(|a| a.eq_ignore_ascii_case(b"get"));
+            all(0, 1, if get { ReadWrite } else { Write })
+        }
+        "SETNX" | "SETEX" | "PSETEX" | "APPEND" | "SETRANGE" | "LPUSH" | "RPUSH" | "LPUSHX"
+        | "RPUSHX" | "LSET" | "LINSERT" | "LREM" | "LTRIM" | "SADD" | "SREM" | "HSET" | "HMSET"
+        | "HSETNX" | "HDEL" | "HEXPIRE" | "HPEXPIRE" | "HEXPIREAT" | "HPEXPIREAT" | "HPERSIST"
+        | "HSETEX" | "ZADD" | "ZREM" | "ZREMRANGEBYRANK" | "ZREMRANGEBYSCORE"
+        | "ZREMRANGEBYLEX" | "PFADD" | "GEOADD" | "XADD" | "XDEL" | "XTRIM" | "XACK" | "EXPIRE"
+        | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT" | "PERSIST" | "RESTORE" => all(0, 1, Write),
+        "XGROUP" => all(1, 2, Write),
+        "DEL" | "UNLINK" => all(0, args.len(), Write),
+        "MSET" | "MSETNX" => (0..args.len())
+            .step_by(2)
+            .map(|i| KeyRef::new(i, Write))
+            .collect(),
+
+        // ── Read and write, single key ───────────────────────────────
+        "GETSET" | "GETDEL" | "GETEX" | "INCR" | "INCRBY" | "INCRBYFLOAT" | "DECR" | "DECRBY"
+        | "SETBIT" | "BITFIELD" | "HINCRBY" | "HINCRBYFLOAT" | "HGETDEL" | "HGETEX" | "LPOP"
+        | "RPOP" | "SPOP" | "ZINCRBY" | "ZPOPMIN" | "ZPOPMAX" | "XCLAIM" | "XAUTOCLAIM"
+        | "JSON.SET" | "JSON.DEL" | "JSON.FORGET" | "JSON.ARRAPPEND" | "JSON.NUMINCRBY"
+        | "JSON.STRAPPEND" => all(0, 1, ReadWrite),
+        "BF.RESERVE" | "BF.ADD" | "BF.MADD" | "CF.ADD" | "CF.DEL" | "CMS.INITBYDIM"
+        | "CMS.INCRBY" | "TOPK.RESERVE" | "TOPK.ADD" => all(0, 1, ReadWrite),
+        "BLPOP" | "BRPOP" | "BZPOPMIN" | "BZPOPMAX" => {
+            all(0, args.len().saturating_sub(1), ReadWrite)
+        }
+        "LMPOP" | "ZMPOP" => numkeys(0, ReadWrite),
+        "BLMPOP" | "BZMPOP" => numkeys(1, ReadWrite),
+        "EVAL" | "EVALSHA" | "FCALL" => numkeys(1, ReadWrite),
+
+        // ── Source and destination ───────────────────────────────────
+        "RENAME" | "RENAMENX" => {
+            let mut keys = all(0, 1, ReadWrite);
+            keys.extend(all(1, 2, Write));
+            keys
+        }
+        "COPY" => {
+            let mut keys = all(0, 1, Read);
+            keys.extend(all(1, 2, Write));
+            keys
+        }
+        "SMOVE" => all(0, 2, ReadWrite),
+        "RPOPLPUSH" | "BRPOPLPUSH" | "LMOVE" | "BLMOVE" => {
+            let mut keys = all(0, 1, ReadWrite);
+            keys.extend(all(1, 2, Write));
+            keys
+        }
+        "SDIFFSTORE" | "SINTERSTORE" | "SUNIONSTORE" | "GEOSEARCHSTORE" => {
+            let mut keys = all(0, 1, Write);
+            keys.extend(all(1, args.len(), Read));
+            keys
+        }
+        "PFMERGE" => {
+            let mut keys = all(0, 1, ReadWrite);
+            keys.extend(all(1, args.len(), Read));
+            keys
+        }
+        "ZUNIONSTORE" | "ZINTERSTORE" | "ZDIFFSTORE" => {
+            let mut keys = all(0, 1, Write);
+            keys.extend(numkeys(1, Read));
+            keys
+        }
+        "BITOP" => {
+            let mut keys = all(1, 2, Write);
+            keys.extend(all(2, args.len(), Read));
+            keys
+        }
+        "SORT" | "GEORADIUS" | "GEORADIUSBYMEMBER" => all(0, 1, Read),
+
+        _ => Vec::new(),
+    };
+
+    // Destinations given as an option.
+    if matches!(cmd, "SORT" | "GEORADIUS" | "GEORADIUSBYMEMBER") {
+        let mut i = 1;
+        while i + 1 < args.len() {
+            let opt = &args[i];
+            if opt.eq_ignore_ascii_case(b"store") || opt.eq_ignore_ascii_case(b"storedist") {
+                keys.push(KeyRef::new(i + 1, Write));
+                i += 1;
+            }
+            i += 1;
+        }
+    }
+    keys
+}
+
+/// The shard channel arguments of `cmd` (uppercase). In cluster mode
+/// shard channels live in the hash slot of their name, like keys.
+pub fn shard_channels<'a>(cmd: &str, args: &'a [Vec<u8>]) -> &'a [Vec<u8>] {
+    match cmd {
+        "SPUBLISH" => &args[..args.len().min(1)],
+        "SSUBSCRIBE" | "SUNSUBSCRIBE" => args,
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<Vec<u8>> {
+        s.split(' ').map(|a| a.as_bytes().to_vec()).collect()
+    }
+
+    fn indexes(cmd: &str, a: &str) -> Vec<(usize, bool, bool)> {
+        command_keys(cmd, &args(a))
+            .into_iter()
+            .map(|k| (k.index, k.read, k.write))
+            .collect()
+    }
+
+    #[test]
+    fn test_command_keys() {
+        assert_eq!(indexes("GET", "foo"), [(0, true, false)]);
+        assert_eq!(indexes("SET", "foo bar"), [(0, false, true)]);
+        assert_eq!(indexes("SET", "foo bar GET"), [(0, true, true)]);
+        assert_eq!(
+            indexes("MSET", "a 1 b 2"),
+            [(0, false, true), (2, false, true)]
+        );
+        assert_eq!(
+            indexes("BLPOP", "a b 0"),
+            [(0, true, true), (1, true, true)]
+        );
+        assert_eq!(
+            indexes("EVAL", "script 2 a b arg"),
+            [(2, true, true), (3, true, true)]
+        );
+        assert_eq!(
+            indexes("ZUNIONSTORE", "dst 2 a b WEIGHTS 1 2"),
+            [(0, false, true), (2, true, false), (3, true, false)]
+        );
+        assert_eq!(
+            indexes("XREAD", "COUNT 2 STREAMS a b 0 0"),
+            [(3, true, false), (4, true, false)]
+        );
+        assert_eq!(
+            indexes("SORT", "src BY w_* STORE dst"),
+            [(0, true, false), (4, false, true)]
+        );
+        // numkeys larger than the arguments
+        assert_eq!(
+            indexes("LMPOP", "5 a LEFT"),
+            [(1, true, true), (2, true, true)]
+        );
+        assert_eq!(indexes("PING", "foo"), []);
+    }
+}
@@ -0,0 +1,364 @@
+//! SLOWLOG, the latency monitor and per-command statistics.
+//!
+//! The dispatcher reports every command it runs with how long the
+//! handler took. That feeds INFO commandstats and latencystats, LATENCY
+//! HISTOGRAM, the slow log (`slowlog-log-slower-than`, `slowlog-max-len`)
+//! and the `command` latency event (`latency-monitor-threshold`). Time a
+//! blocking command spends waiting doesn't count.
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::acl::full_command_name;
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::frame::Frame;
+
+/// The slow log keeps at most this many arguments of a command...
+const SLOWLOG_MAX_ARGC: usize = 32;
+/// ...and this many bytes of each argument.
+const SLOWLOG_MAX_ARGLEN: usize = 128;
+/// Samples kept per latency event.
+const LATENCY_HISTORY_LEN: usize = 160;
+
+/// A command in the slow log.
+#[derive(Debug, Clone)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    /// Unix time the command ran, in seconds.
+    pub time: u64,
+    /// Microseconds the command took.
+    pub duration: u64,
+    /// The command and its arguments, shortened.
+    pub args: Vec<Vec<u8>>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+impl SlowlogEntry {
+    pub fn to_frame(&self) -> Frame {
+        Frame::Array(vec![
+            Frame::Integer(self.id as i64),
+            Frame::Integer(self.time as i64),
+            Frame::Integer(self.duration as i64),
+            Frame::Array(
+                self.args
+                    .iter()
+                    .map(|a| Frame::Bulk(a.clone().into()))
+                    .collect(),
+            ),
+            Frame::bulk_string(&self.client_addr),
+            Frame::bulk_string(&self.client_name),
+        ])
+    }
+}
+
+/// Calls and timings of one command (INFO commandstats).
+#[derive(Debug, Clone)]
+pub struct CommandStats {
+    pub calls: u64,
+    /// Total microseconds spent in the command.
+    pub usec: u64,
+    /// Calls refused before running: arity, ACL, OOM and the like.
+    pub rejected_calls: u64,
+    /// Calls that ran and replied with an error.
+    pub failed_calls: u64,
+    /// `histogram[i]` counts calls that took up to 2^i microseconds.
+    pub histogram: [u64; 64],
+}
+
+impl Default for CommandStats {
+    fn default() -> Self {
+        CommandStats {
+            calls: 0,
+            usec: 0,
+            rejected_calls: 0,
+            failed_calls: 0,
+            histogram: [0; 64],
+        }
+    }
+}
+
+impl CommandStats {
+    fn add(&mut self, micros: u64, failed: bool) {
+        self.calls += 1;
+        self.usec += micros;
+        if failed {
+            self.failed_calls += 1;
+        }
+        self.histogram[bucket(micros)] += 1;
+    }
+
+    /// The non-empty buckets as (upper bound in microseconds, calls up to
+    /// that bound), like LATENCY HISTOGRAM shows them.
+    pub fn cumulative(&self) -> Vec<(u64, u64)> {
+        let mut total = 0;
+        let mut out = Vec::new();
+        for (i, &n) in self.histogram.iter().enumerate() {
+            if n > 0 {
+                total += n;
+                out.push((1 << i, total));
+            }
+        }
+        out
+    }
+
+    /// The latency `p` percent of the calls stayed under, in microseconds.
+    pub fn percentile(&self, p: f64) -> u64 {
+        let target = ((p / 100.0) * self.calls as f64).ceil().max(1.0) as u64;
+        self.cumulative()
+            .into_iter()
+            .find(|&(_, n)| n >= target)
+            .map_or(0, |(bound, _)| bound)
+    }
+}
+
+/// The histogram bucket for a duration: the smallest i with
+/// `micros <= 2^i`.
+fn bucket(micros: u64) -> usize {
+    (64 - micros.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// Samples of a latency event (LATENCY HISTORY).
+#[derive(Debug, Clone, Default)]
+pub struct LatencyEvent {
+    /// (unix seconds, milliseconds), oldest first.
+    pub samples: VecDeque<(u64, u64)>,
+    /// Highest latency ever seen, in milliseconds.
+    pub max: u64,
+}
+
+/// Slow log, latency events and command statistics.
+#[derive(Default)]
+pub struct Latency {
+    slowlog: VecDeque<SlowlogEntry>,
+    next_slowlog_id: u64,
+    commands: HashMap<String, CommandStats>,
+    events: BTreeMap<String, LatencyEvent>,
+}
+
+impl Latency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The newest `count` slow log entries, newest first.
+    pub fn slowlog(&self, count: usize) -> Vec<SlowlogEntry> {
+        self.slowlog.iter().rev().take(count).cloned().collect()
+    }
+
+    pub fn slowlog_len(&self) -> usize {
+        self.slowlog.len()
+    }
+
+    pub fn slowlog_reset(&mut self) {
+        self.slowlog.clear();
+    }
+
+    /// Command statistics by command name (`get`, `config|set`), for
+    /// commands that were called at least once.
+    pub fn command_stats(&self) -> BTreeMap<String, CommandStats> {
+        self.commands
+            .iter()
+            .map(|(name, stats)| (name.clone(), stats.clone()))
+            .collect()
+    }
+
+    /// Forget all command statistics (CONFIG RESETSTAT).
+    pub fn reset_stats(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn events(&self) -> &BTreeMap<String, LatencyEvent> {
+        &self.events
+    }
+
+    /// Drop the given latency events, or all of them. Returns how many
+    /// were dropped.
+    pub fn reset_events(&mut self, names: &[String]) -> usize {
+        if names.is_empty() {
+            let n = self.events.len();
+            self.events.clear();
+            return n;
+        }
+        names
+            .iter()
+            .filter(|name| self.events.remove(*name).is_some())
+            .count()
+    }
+
+    fn add_event(&mut self, name: &str, time: u64, millis: u64) {
+        let event = self.events.entry(name.to_string()).or_default();
+        event.max = event.max.max(millis);
+        // One sample per second, with the highest latency seen in it.
+        match event.samples.back_mut() {
+            Some((t, ms)) if *t == time => *ms = (*ms).max(millis),
+            _ => {
+                if event.samples.len() == LATENCY_HISTORY_LEN {
+                    event.samples.pop_front();
+                }
+                event.samples.push_back((time, millis));
+            }
+        }
+    }
+}
+
+/// Record a command that ran on `ctx`'s connection, taking `duration`.
+/// Commands called by scripts only count in the statistics.
+pub fn record(
+    state: &SharedState,
+    ctx: &ConnCtx,
+    args: &[Vec<u8>],
+    response: &Frame,
+    duration: Duration,
+) {
+    let (now, slower_than, max_len, threshold) = {
+        let inner = state.lock();
+        (
+            state.now(),
+            inner.slowlog_log_slower_than,
+            inner.slowlog_max_len,
+            inner.latency_monitor_threshold,
+        )
+    };
+    let micros = duration.as_micros() as u64;
+    let time = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut latency = state.latency.lock().unwrap();
+    latency
+        .commands
+        .entry(command_name(args))
+        .or_default()
+        .add(micros, matches!(response, Frame::Error(_)));
+    if ctx.nested {
+        return;
+    }
+
+    if slower_than >= 0 && micros >= slower_than as u64 && max_len > 0 {
+        let id = latency.next_slowlog_id;
+        latency.next_slowlog_id += 1;
+        while latency.slowlog.len() >= max_len {
+            latency.slowlog.pop_front();
+        }
+        latency.slowlog.push_back(SlowlogEntry {
+            id,
+            time,
+            duration: micros,
+            args: slowlog_args(args),
+            client_addr: ctx.addr.map(|a| a.to_string()).unwrap_or_default(),
+            client_name: ctx.client_name.clone().unwrap_or_default(),
+        });
+    }
+
+    let millis = duration.as_millis() as u64;
+    if threshold > 0 && millis >= threshold {
+        latency.add_event("command", time, millis);
+    }
+}
+
+/// Count a call that was refused before it ran.
+pub fn reject(state: &SharedState, args: &[Vec<u8>]) {
+    state
+        .latency
+        .lock()
+        .unwrap()
+        .commands
+        .entry(command_name(args))
+        .or_default()
+        .rejected_calls += 1;
+}
+
+/// The name statistics use for a command: `get`, `config|set`.
+fn command_name(args: &[Vec<u8>]) -> String {
+    let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+    full_command_name(&name, args.get(1).map(|a| a.as_slice()))
+}
+
+/// Shorten a command the way Redis does for the slow log.
+fn slowlog_args(args: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let args = crate::monitor::redact(&name, args);
+    let keep = if args.len() > SLOWLOG_MAX_ARGC {
+        SLOWLOG_MAX_ARGC - 1
+    } else {
+        args.len()
+    };
+    let mut out: Vec<Vec<u8>> = args[..keep]
+        .iter()
+        .map(|arg| {
+            if arg.len() > SLOWLOG_MAX_ARGLEN {
+                let mut short = arg[..SLOWLOG_MAX_ARGLEN].to_vec();
+                short.extend_from_slice(
+                    format!("... ({} more bytes)", arg.len() - SLOWLOG_MAX_ARGLEN).as_bytes(),
+                );
+                short
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    if keep < args.len() {
+        out.push(format!("... ({} more arguments)", args.len() - keep).into_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket() {
+        assert_eq!(bucket(0), 0);
+        assert_eq!(bucket(1), 0);
+        assert_eq!(bucket(2), 1);
+        assert_eq!(bucket(3), 2);
+        assert_eq!(bucket(4), 2);
+        assert_eq!(bucket(5), 3);
+        assert_eq!(bucket(1024), 10);
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut stats = CommandStats::default();
+        for _ in 0..98 {
+            stats.add(1, false);
+        }
+        stats.add(10, true);
+        stats.add(1000, false);
+        assert_eq!(stats.calls, 100);
+        assert_eq!(stats.usec, 98 + 10 + 1000);
+        assert_eq!(stats.failed_calls, 1);
+        assert_eq!(stats.cumulative(), vec![(1, 98), (16, 99), (1024, 100)]);
+        assert_eq!(stats.percentile(50.0), 1);
+        assert_eq!(stats.percentile(99.0), 16);
+        assert_eq!(stats.percentile(99.9), 1024);
+    }
+
+    #[test]
+    fn test_slowlog_args() {
+        let long = vec![b'x'; 130];
+        let args = slowlog_args(&[b"SET".to_vec(), b"k".to_vec(), long]);
+        assert_eq!(args[2].len(), 128 + "... (2 more bytes)".len());
+        assert!(args[2].ends_with(b"... (2 more bytes)"));
+
+        let many: Vec<Vec<u8>> = (0..40).map(|i| i.to_string().into_bytes()).collect();
+        let args = slowlog_args(&many);
+        assert_eq!(args.len(), 32);
+        assert_eq!(args[31], b"... (9 more arguments)".to_vec());
+
+        let args = slowlog_args(&[b"auth".to_vec(), b"secret".to_vec()]);
+        assert_eq!(args[1], b"(redacted)".to_vec());
+    }
+
+    #[test]
+    fn test_events() {
+        let mut latency = Latency::new();
+        latency.add_event("command", 10, 5);
+        latency.add_event("command", 10, 3);
+        latency.add_event("command", 11, 7);
+        let event = &latency.events()["command"];
+        assert_eq!(event.samples, VecDeque::from(vec![(10, 5), (11, 7)]));
+        assert_eq!(event.max, 7);
+        assert_eq!(latency.reset_events(&["nosuch".to_string()]), 0);
+        assert_eq!(latency.reset_events(&[]), 1);
+    }
+}
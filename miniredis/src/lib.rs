@@ -17,21 +17,47 @@
 //! # }
 //! ```
 
+pub mod acl;
+pub mod blocking;
+#[cfg(feature = "bloom")]
+pub mod bloom;
+pub mod checkpoint;
+pub mod cluster;
 pub mod cmd;
 pub mod connection;
 pub mod db;
+pub mod direct;
 pub mod dispatch;
+pub mod eviction;
+pub mod fault;
 pub mod frame;
 pub mod geo;
 pub mod hll;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod keys;
+pub mod keyspec;
+pub mod latency;
+pub mod monitor;
 pub mod pubsub;
+pub mod rdb;
+pub mod replication;
+pub mod script;
 pub mod server;
+pub mod skiplist;
+pub mod tracking;
 pub mod types;
 
 mod error;
 
-pub use error::{Error, Result};
+pub use checkpoint::{Change, Checkpoint, KeyChange};
+pub use cluster::MiniredisCluster;
+pub use direct::{DumpedKey, PendingEntry, StreamEntry, StreamGroup, Value};
+pub use error::{Error, Result, WrongType};
+pub use eviction::EvictionPolicy;
+pub use fault::{Fault, FaultKind};
+pub use monitor::{LoggedCommand, ReplyType};
+pub use script::{LogLevel, ScriptLogEntry};
 
 use std::net::SocketAddr;
 use std::sync::Arc;
@@ -39,7 +65,7 @@ use std::time::{Duration, SystemTime};
 
 use tokio::net::TcpListener;
 
-use crate::db::SharedState;
+use crate::db::{Isolation, SharedState};
 
 /// A running miniredis instance for use in tests.
 ///
@@ -70,6 +96,7 @@ impl Miniredis {
         tokio::spawn(async move {
             server::run(listener, state_clone, shutdown_rx, None).await;
         });
+        replication::register(local_addr, &state);
 
         Ok(Miniredis {
             state,
@@ -99,6 +126,7 @@ impl Miniredis {
         tokio::spawn(async move {
             server::run(listener, state_clone, shutdown_rx, Some(acceptor)).await;
         });
+        replication::register(local_addr, &state);
 
         Ok(Miniredis {
             state,
@@ -109,6 +137,7 @@ impl Miniredis {
 
     /// Shut down the server.
     pub async fn close(&self) {
+        replication::unregister(self.addr);
         let _ = self.state.shutdown_tx.send(());
         // Give tasks a moment to clean up
         tokio::task::yield_now().await;
@@ -154,32 +183,75 @@ impl Miniredis {
 
     /// Require AUTH with a password (default user).
     pub fn require_auth(&self, password: &str) {
-        let mut inner = self.state.lock();
-        inner
-            .passwords
-            .insert("default".to_owned(), password.to_owned());
+        let rules = [b"resetpass".to_vec(), format!(">{password}").into_bytes()];
+        self.state
+            .lock()
+            .acl
+            .set_user("default", &rules, |_| true)
+            .unwrap();
     }
 
-    /// Require AUTH with a username and password.
+    /// Require AUTH with a username and password. The user may run every
+    /// command; the default user needs a password from now on.
     pub fn require_user_auth(&self, username: &str, password: &str) {
+        let rules = [
+            "on".to_string(),
+            format!(">{password}"),
+            "~*".to_string(),
+            "&*".to_string(),
+            "+@all".to_string(),
+        ];
+        let rules: Vec<Vec<u8>> = rules.into_iter().map(String::into_bytes).collect();
         let mut inner = self.state.lock();
-        inner
-            .passwords
-            .insert(username.to_owned(), password.to_owned());
+        if !inner.acl.auth_required() {
+            inner
+                .acl
+                .set_user("default", &[b"resetpass".to_vec()], |_| true)
+                .unwrap();
+        }
+        inner.acl.set_user(username, &rules, |_| true).unwrap();
+    }
+
+    /// Create or change an ACL user, as ACL SETUSER does, e.g.
+    /// `m.acl_setuser("alice", &["on", ">pw", "~cache:*", "+@read"])`.
+    pub fn acl_setuser(&self, username: &str, rules: &[&str]) -> Result<()> {
+        let rules: Vec<Vec<u8>> = rules.iter().map(|r| r.as_bytes().to_vec()).collect();
+        let table = self.state.command_table.get().unwrap();
+        self.state
+            .lock()
+            .acl
+            .set_user(username, &rules, |cmd| table.is_command(cmd))
+            .map_err(Into::into)
     }
 
     // ── Time & determinism ───────────────────────────────────────────
 
     /// Set a fixed mock time. Affects EXPIREAT, stream IDs, etc.
     pub fn set_time(&self, t: SystemTime) {
-        let mut inner = self.state.lock();
-        inner.now = Some(t);
+        self.state.set_now(Some(t));
     }
 
     /// Decrease all TTLs by `duration`, expiring any that drop to zero.
+    /// Replicas fast forward too.
     pub fn fast_forward(&self, duration: Duration) {
-        let mut inner = self.state.lock();
-        inner.fast_forward(duration);
+        let mut propagator = replication::Propagator::new(&self.state, self.selected_db, true);
+        self.state.fast_forward_locked(duration);
+        let args = [
+            b"MINIREDIS.FASTFORWARD".to_vec(),
+            duration.as_millis().to_string().into_bytes(),
+        ];
+        propagator.record(&args, &frame::Frame::ok());
+        self.state.publish_changes(None);
+        propagator.finish(false, self.selected_db);
+    }
+
+    /// Limit memory use to `bytes`, or no limit with 0, evicting keys
+    /// following `policy`: the same as CONFIG SET maxmemory and
+    /// maxmemory-policy.
+    pub fn set_maxmemory(&self, bytes: u64, policy: EvictionPolicy) {
+        let mut data = self.state.lock_all();
+        data.set_maxmemory(bytes);
+        data.inner.maxmemory_policy = policy;
     }
 
     /// Seed the random number generator for deterministic tests.
@@ -190,25 +262,36 @@ impl Miniredis {
     }
 
     // ── Key management ───────────────────────────────────────────────
+    //
+    // Keys, members, fields and values are binary safe. The `&str` methods
+    // are shorthands for the `_bytes` variants, and convert values back
+    // with `String::from_utf8_lossy`.
 
     /// Delete a key. Returns true if it existed.
     pub fn del(&self, key: &str) -> bool {
-        let mut inner = self.state.lock();
-        inner.db_mut(self.selected_db).del(key)
+        self.del_bytes(key.as_bytes())
+    }
+
+    /// Delete a binary key. Returns true if it existed.
+    pub fn del_bytes(&self, key: &[u8]) -> bool {
+        self.state.db(self.selected_db).del(key)
     }
 
     /// Check if a key exists.
     pub fn exists(&self, key: &str) -> bool {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
-        inner.db_mut(self.selected_db).exists(key, now)
+        self.exists_bytes(key.as_bytes())
+    }
+
+    /// Check if a binary key exists.
+    pub fn exists_bytes(&self, key: &[u8]) -> bool {
+        let now = self.state.now();
+        self.state.db(self.selected_db).exists(key, now)
     }
 
     /// Return the type of a key ("string", "list", "set", "hash", "zset",
     /// "stream", "none").
     pub fn key_type(&self, key: &str) -> &'static str {
-        let inner = self.state.lock();
-        match inner.db(self.selected_db).key_type(key) {
+        match self.state.db(self.selected_db).key_type(key.as_bytes()) {
             Some(t) => t.as_str(),
             None => "none",
         }
@@ -216,55 +299,66 @@ impl Miniredis {
 
     /// Return all keys from the selected database, sorted.
     pub fn keys(&self) -> Vec<String> {
-        let inner = self.state.lock();
-        inner.db(self.selected_db).all_keys()
+        self.keys_bytes().into_iter().map(lossy).collect()
+    }
+
+    /// Return all keys from the selected database as bytes, sorted.
+    pub fn keys_bytes(&self) -> Vec<Vec<u8>> {
+        self.state.db(self.selected_db).all_keys()
     }
 
     /// Get the TTL of a key. Returns None if the key has no TTL.
     pub fn ttl(&self, key: &str) -> Option<Duration> {
-        let inner = self.state.lock();
-        inner.db(self.selected_db).ttl.get(key).copied()
+        self.state
+            .db(self.selected_db)
+            .ttl
+            .get(key.as_bytes())
+            .copied()
     }
 
     /// Set the TTL for a key.
     pub fn set_ttl(&self, key: &str, ttl: Duration) {
-        let mut inner = self.state.lock();
-        inner
-            .db_mut(self.selected_db)
+        self.state
+            .db(self.selected_db)
             .ttl
-            .insert(key.to_owned(), ttl);
+            .insert(key.as_bytes().to_vec(), ttl);
     }
 
     // ── String operations ────────────────────────────────────────────
 
     /// Get a string key value.
     pub fn get(&self, key: &str) -> Option<String> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.string_get(key)
-            .map(|v| String::from_utf8_lossy(v).into_owned())
+        self.get_bytes(key.as_bytes()).map(lossy)
+    }
+
+    /// Get a string value by binary key.
+    pub fn get_bytes(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.state.db(self.selected_db).string_get(key).cloned()
     }
 
     /// Set a string key. Removes any existing TTL.
     pub fn set(&self, key: &str, value: &str) {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
-        let db = inner.db_mut(self.selected_db);
-        db.string_set(key, value.as_bytes().to_vec(), now);
+        self.set_bytes(key.as_bytes(), value.as_bytes());
+    }
+
+    /// Set a binary key to a binary value. Removes any existing TTL.
+    pub fn set_bytes(&self, key: &[u8], value: &[u8]) {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        db.string_set(key, value.to_vec(), now);
         db.ttl.remove(key);
     }
 
     /// Increment a string key by delta. Creates the key if it doesn't exist.
     pub fn incr(&self, key: &str, delta: i64) -> i64 {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
-        let db = inner.db_mut(self.selected_db);
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
         let current = db
-            .string_get(key)
+            .string_get(key.as_bytes())
             .and_then(|v| String::from_utf8_lossy(v).parse::<i64>().ok())
             .unwrap_or(0);
         let new_val = current + delta;
-        db.string_set(key, new_val.to_string().into_bytes(), now);
+        db.string_set(key.as_bytes(), new_val.to_string().into_bytes(), now);
         new_val
     }
 
@@ -273,89 +367,111 @@ impl Miniredis {
     /// Push values to the end (right) of a list. Creates the key if needed.
     /// Returns the new list length.
     pub fn push(&self, key: &str, values: &[&str]) -> usize {
-        let mut inner = self.state.lock();
+        let values: Vec<&[u8]> = values.iter().map(|v| v.as_bytes()).collect();
+        self.push_bytes(key.as_bytes(), &values)
+    }
 
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::List);
-        let list = db.list_keys.entry(key.to_owned()).or_default();
+    /// Push binary values to the end (right) of a list. Returns the new
+    /// list length.
+    pub fn push_bytes(&self, key: &[u8], values: &[&[u8]]) -> usize {
+        let mut db = self.state.db(self.selected_db);
+        db.keys.insert(key.to_vec(), types::KeyType::List);
+        let list = db.list_keys.entry(key.to_vec()).or_default();
         for v in values {
-            list.push_back(v.as_bytes().to_vec());
+            list.push_back(v.to_vec());
         }
-        list.len()
+        let len = list.len();
+        db.resized(key);
+        len
     }
 
     /// Push a value to the beginning (left) of a list.
     pub fn lpush(&self, key: &str, value: &str) -> usize {
-        let mut inner = self.state.lock();
-
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::List);
-        let list = db.list_keys.entry(key.to_owned()).or_default();
+        let mut db = self.state.db(self.selected_db);
+        db.keys
+            .insert(key.as_bytes().to_vec(), types::KeyType::List);
+        let list = db.list_keys.entry(key.as_bytes().to_vec()).or_default();
         list.push_front(value.as_bytes().to_vec());
-        list.len()
+        let len = list.len();
+        db.resized(key.as_bytes());
+        len
     }
 
     /// Pop from the end (right) of a list.
     pub fn pop(&self, key: &str) -> Option<String> {
-        let mut inner = self.state.lock();
-        let db = inner.db_mut(self.selected_db);
-        let list = db.list_keys.get_mut(key)?;
+        let mut db = self.state.db(self.selected_db);
+        let list = db.list_keys.get_mut(key.as_bytes())?;
         let val = list.pop_back()?;
         if list.is_empty() {
-            db.list_keys.remove(key);
-            db.del(key);
+            db.list_keys.remove(key.as_bytes());
+            db.del(key.as_bytes());
         }
-        Some(String::from_utf8_lossy(&val).into_owned())
+        db.resized(key.as_bytes());
+        Some(lossy(val))
     }
 
     /// Pop from the beginning (left) of a list.
     pub fn lpop(&self, key: &str) -> Option<String> {
-        let mut inner = self.state.lock();
-        let db = inner.db_mut(self.selected_db);
-        let list = db.list_keys.get_mut(key)?;
+        let mut db = self.state.db(self.selected_db);
+        let list = db.list_keys.get_mut(key.as_bytes())?;
         let val = list.pop_front()?;
         if list.is_empty() {
-            db.list_keys.remove(key);
-            db.del(key);
+            db.list_keys.remove(key.as_bytes());
+            db.del(key.as_bytes());
         }
-        Some(String::from_utf8_lossy(&val).into_owned())
+        db.resized(key.as_bytes());
+        Some(lossy(val))
     }
 
     /// Get all values in a list.
     pub fn list(&self, key: &str) -> Option<Vec<String>> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.list_keys.get(key).map(|list| {
-            list.iter()
-                .map(|v| String::from_utf8_lossy(v).into_owned())
-                .collect()
-        })
+        self.list_bytes(key.as_bytes())
+            .map(|list| list.into_iter().map(lossy).collect())
+    }
+
+    /// Get all values in a list stored at a binary key.
+    pub fn list_bytes(&self, key: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let db = self.state.db(self.selected_db);
+        db.list_keys
+            .get(key)
+            .map(|list| list.iter().cloned().collect())
     }
 
     // ── Set operations ───────────────────────────────────────────────
 
     /// Add members to a set. Returns the number of new members added.
     pub fn set_add(&self, key: &str, members: &[&str]) -> usize {
-        let mut inner = self.state.lock();
+        let members: Vec<&[u8]> = members.iter().map(|m| m.as_bytes()).collect();
+        self.set_add_bytes(key.as_bytes(), &members)
+    }
 
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::Set);
-        let set = db.set_keys.entry(key.to_owned()).or_default();
+    /// Add binary members to a set. Returns the number of new members
+    /// added.
+    pub fn set_add_bytes(&self, key: &[u8], members: &[&[u8]]) -> usize {
+        let mut db = self.state.db(self.selected_db);
+        db.keys.insert(key.to_vec(), types::KeyType::Set);
+        let set = db.set_keys.entry(key.to_vec()).or_default();
         let mut added = 0;
         for m in members {
-            if set.insert(m.to_string()) {
+            if set.insert(m.to_vec()) {
                 added += 1;
             }
         }
+        db.resized(key);
         added
     }
 
     /// Get all members of a set, sorted.
     pub fn members(&self, key: &str) -> Option<Vec<String>> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
+        self.members_bytes(key.as_bytes())
+            .map(|members| members.into_iter().map(lossy).collect())
+    }
+
+    /// Get all members of a set as bytes, sorted.
+    pub fn members_bytes(&self, key: &[u8]) -> Option<Vec<Vec<u8>>> {
+        let db = self.state.db(self.selected_db);
         db.set_keys.get(key).map(|set| {
-            let mut v: Vec<String> = set.iter().cloned().collect();
+            let mut v: Vec<Vec<u8>> = set.iter().cloned().collect();
             v.sort();
             v
         })
@@ -363,8 +479,12 @@ impl Miniredis {
 
     /// Check if a value is a member of a set.
     pub fn is_member(&self, key: &str, member: &str) -> bool {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
+        self.is_member_bytes(key.as_bytes(), member.as_bytes())
+    }
+
+    /// Check if a binary value is a member of a set.
+    pub fn is_member_bytes(&self, key: &[u8], member: &[u8]) -> bool {
+        let db = self.state.db(self.selected_db);
         db.set_keys
             .get(key)
             .map(|set| set.contains(member))
@@ -372,142 +492,373 @@ impl Miniredis {
     }
 
     // ── Hash operations ──────────────────────────────────────────────
+    //
+    // The methods for hashes, sorted sets, streams and HyperLogLogs return
+    // a `WrongType` error for a key holding another type of value. Reading
+    // a key that doesn't exist is not an error.
 
-    /// Set a hash field.
-    pub fn hset(&self, key: &str, field: &str, value: &str) {
-        let mut inner = self.state.lock();
+    /// Set a hash field. Returns true if the field is new.
+    pub fn hset(&self, key: &str, field: &str, value: &str) -> Result<bool> {
+        self.hset_bytes(key.as_bytes(), field.as_bytes(), value.as_bytes())
+    }
 
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::Hash);
-        let hash = db.hash_keys.entry(key.to_owned()).or_default();
-        hash.insert(field.to_owned(), value.as_bytes().to_vec());
+    /// Set a binary hash field. Returns true if the field is new.
+    pub fn hset_bytes(&self, key: &[u8], field: &[u8], value: &[u8]) -> Result<bool> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::Hash)?;
+        Ok(db.hash_set(key, &[(field.to_vec(), value.to_vec())], now) == 1)
     }
 
     /// Get a hash field value.
-    pub fn hget(&self, key: &str, field: &str) -> Option<String> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.hash_keys
-            .get(key)
-            .and_then(|h| h.get(field))
-            .map(|v| String::from_utf8_lossy(v).into_owned())
+    pub fn hget(&self, key: &str, field: &str) -> Result<Option<String>> {
+        Ok(self
+            .hget_bytes(key.as_bytes(), field.as_bytes())?
+            .map(lossy))
+    }
+
+    /// Get a binary hash field value.
+    pub fn hget_bytes(&self, key: &[u8], field: &[u8]) -> Result<Option<Vec<u8>>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::Hash)?;
+        Ok(db.hash_get(key, field).cloned())
     }
 
     /// Get all field names in a hash, sorted.
-    pub fn hkeys(&self, key: &str) -> Option<Vec<String>> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.hash_keys.get(key).map(|h| {
-            let mut keys: Vec<String> = h.keys().cloned().collect();
-            keys.sort();
-            keys
-        })
+    pub fn hkeys(&self, key: &str) -> Result<Vec<String>> {
+        Ok(self
+            .hkeys_bytes(key.as_bytes())?
+            .into_iter()
+            .map(lossy)
+            .collect())
+    }
+
+    /// Get all field names in a hash as bytes, sorted.
+    pub fn hkeys_bytes(&self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::Hash)?;
+        Ok(db.hash_fields(key))
+    }
+
+    /// Get all fields and values of a hash, sorted by field.
+    pub fn hgetall(&self, key: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .hgetall_bytes(key.as_bytes())?
+            .into_iter()
+            .map(|(field, value)| (lossy(field), lossy(value)))
+            .collect())
+    }
+
+    /// Get all fields and values of a hash as bytes, sorted by field.
+    pub fn hgetall_bytes(&self, key: &[u8]) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::Hash)?;
+        let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = db
+            .hash_keys
+            .get(key)
+            .map(|h| h.iter().map(|(f, v)| (f.clone(), v.clone())).collect())
+            .unwrap_or_default();
+        pairs.sort();
+        Ok(pairs)
     }
 
     /// Delete a hash field. Returns true if the field existed.
-    pub fn hdel(&self, key: &str, field: &str) -> bool {
-        let mut inner = self.state.lock();
-        let db = inner.db_mut(self.selected_db);
-        if let Some(hash) = db.hash_keys.get_mut(key) {
-            let removed = hash.remove(field).is_some();
-            if hash.is_empty() {
-                db.hash_keys.remove(key);
-                db.del(key);
-            }
-            removed
-        } else {
-            false
-        }
+    pub fn hdel(&self, key: &str, field: &str) -> Result<bool> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Hash)?;
+        Ok(db.hash_del(key.as_bytes(), &[field.as_bytes().to_vec()], now) == 1)
     }
 
     // ── Sorted set operations ────────────────────────────────────────
 
-    /// Add a member to a sorted set with the given score.
-    /// Returns true if the member was new.
-    pub fn zadd(&self, key: &str, score: f64, member: &str) -> bool {
-        let mut inner = self.state.lock();
+    /// Add a member to a sorted set with the given score, or change the
+    /// score of an existing one. Returns true if the member was new.
+    pub fn zadd(&self, key: &str, score: f64, member: &str) -> Result<bool> {
+        self.zadd_bytes(key.as_bytes(), score, member.as_bytes())
+    }
 
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::SortedSet);
-        let ss = db.sorted_set_keys.entry(key.to_owned()).or_default();
-        ss.set(score, member)
+    /// Add a binary member to a sorted set. Returns true if the member was
+    /// new.
+    pub fn zadd_bytes(&self, key: &[u8], score: f64, member: &[u8]) -> Result<bool> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::SortedSet)?;
+        Ok(db.sset_add(key, score, member, now))
+    }
+
+    /// Add `delta` to the score of a member, which starts at 0 for a new
+    /// one. Returns the new score.
+    pub fn zincrby(&self, key: &str, delta: f64, member: &str) -> Result<f64> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::SortedSet)?;
+        Ok(db.sset_incrby(key.as_bytes(), member.as_bytes(), delta, now))
+    }
+
+    /// Remove a member from a sorted set. Returns true if it existed.
+    pub fn zrem(&self, key: &str, member: &str) -> Result<bool> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::SortedSet)?;
+        Ok(db.sset_rem(key.as_bytes(), member.as_bytes(), now))
     }
 
     /// Get the score of a member in a sorted set.
-    pub fn zscore(&self, key: &str, member: &str) -> Option<f64> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.sorted_set_keys.get(key).and_then(|ss| ss.get(member))
+    pub fn zscore(&self, key: &str, member: &str) -> Result<Option<f64>> {
+        self.zscore_bytes(key.as_bytes(), member.as_bytes())
+    }
+
+    /// Get the score of a binary member in a sorted set.
+    pub fn zscore_bytes(&self, key: &[u8], member: &[u8]) -> Result<Option<f64>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::SortedSet)?;
+        Ok(db.sset_score(key, member))
+    }
+
+    /// Number of members in a sorted set.
+    pub fn zcard(&self, key: &str) -> Result<usize> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::SortedSet)?;
+        Ok(db.sset_card(key.as_bytes()))
     }
 
     /// Get all members of a sorted set, sorted by score then member name.
-    pub fn zmembers(&self, key: &str) -> Option<Vec<String>> {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.sorted_set_keys.get(key).map(|ss| ss.members_sorted())
+    pub fn zmembers(&self, key: &str) -> Result<Vec<String>> {
+        Ok(self
+            .zmembers_bytes(key.as_bytes())?
+            .into_iter()
+            .map(lossy)
+            .collect())
+    }
+
+    /// Get all members of a sorted set as bytes, sorted by score then
+    /// member.
+    pub fn zmembers_bytes(&self, key: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key, types::KeyType::SortedSet)?;
+        Ok(db
+            .sorted_set_keys
+            .get(key)
+            .map(|ss| ss.members_sorted())
+            .unwrap_or_default())
+    }
+
+    /// Members and scores at ranks `start` to `stop`, both included, as
+    /// ZRANGE with WITHSCORES returns them. Negative ranks count from the
+    /// end, so `0, -1` returns the whole set.
+    pub fn zrange_with_scores(
+        &self,
+        key: &str,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(String, f64)>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::SortedSet)?;
+        let Some(ss) = db.sorted_set_keys.get(key.as_bytes()) else {
+            return Ok(Vec::new());
+        };
+        let (start, end) = cmd::sorted_set::redis_range(ss.card(), start, stop);
+        Ok(ss
+            .range(start, end, types::Direction::Asc)
+            .into_iter()
+            .map(|e| (lossy(e.member), e.score))
+            .collect())
     }
 
     // ── Stream operations ────────────────────────────────────────────
 
-    /// Add an entry to a stream. Returns the assigned ID.
-    pub fn xadd(&self, key: &str, id: &str, values: &[(&str, &str)]) -> String {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
+    /// Add an entry to a stream, creating the stream if needed. The `id`
+    /// is as for XADD, e.g. "*" for a new one. Returns the assigned ID.
+    pub fn xadd(&self, key: &str, id: &str, values: &[(&str, &str)]) -> Result<String> {
+        let now = self.state.now();
         let now_ms = now
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64;
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::Stream);
-        let stream = db.stream_keys.entry(key.to_owned()).or_default();
-        let field_values: Vec<String> = values
+        let key = key.as_bytes();
+        let mut db = self.state.db(self.selected_db);
+        let exists = direct::check_type(&db, key, types::KeyType::Stream)?;
+        let field_values: Vec<Vec<u8>> = values
             .iter()
-            .flat_map(|(k, v)| vec![k.to_string(), v.to_string()])
+            .flat_map(|(k, v)| [k.as_bytes().to_vec(), v.as_bytes().to_vec()])
             .collect();
-        stream.add(id, field_values, now_ms).unwrap_or_default()
+        let stream = db.stream_keys.entry(key.to_vec()).or_default();
+        match stream.add(id, field_values, now_ms) {
+            Ok(id) => {
+                db.add_key(key, types::KeyType::Stream);
+                db.incr_version(key, now);
+                Ok(id)
+            }
+            Err(e) => {
+                if !exists {
+                    db.stream_keys.remove(key);
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Entries with an ID from `start` to `end`, both included, as XRANGE
+    /// returns them: "-" and "+" are the first and the last ID, and a "("
+    /// prefix leaves an ID out.
+    pub fn xrange(&self, key: &str, start: &str, end: &str) -> Result<Vec<StreamEntry>> {
+        let start = types::format_stream_range_bound(start, true)?;
+        let end = types::format_stream_range_bound(end, false)?;
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        Ok(db
+            .stream_keys
+            .get(key.as_bytes())
+            .map(|s| {
+                s.range(&start, &end, None)
+                    .into_iter()
+                    .map(direct::stream_entry)
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Number of entries in a stream.
+    pub fn xlen(&self, key: &str) -> Result<usize> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        Ok(db
+            .stream_keys
+            .get(key.as_bytes())
+            .map_or(0, |s| s.entries.len()))
+    }
+
+    /// Create a consumer group which gets the entries after `id`, or only
+    /// new ones for "$". Creates the stream if needed, like XGROUP CREATE
+    /// with MKSTREAM.
+    pub fn xgroup_create(&self, key: &str, group: &str, id: &str) -> Result<()> {
+        let now = self.state.now();
+        let key = key.as_bytes();
+        let mut db = self.state.db(self.selected_db);
+        let exists = direct::check_type(&db, key, types::KeyType::Stream)?;
+        let stream = db.stream_keys.entry(key.to_vec()).or_default();
+        if let Err(e) = stream.create_group(group, id) {
+            if !exists {
+                db.stream_keys.remove(key);
+            }
+            return Err(e.into());
+        }
+        db.add_key(key, types::KeyType::Stream);
+        db.incr_version(key, now);
+        Ok(())
+    }
+
+    /// Deliver up to `count` (all if None) new entries of a consumer group
+    /// to `consumer`, as XREADGROUP with ">" does. The entries go to the
+    /// pending entries list of the group.
+    pub fn xreadgroup(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        let stream = db
+            .stream_keys
+            .get_mut(key.as_bytes())
+            .ok_or_else(|| direct::no_group(key, group))?;
+        let entries = stream.read_group(group, consumer, ">", count, false, now)?;
+        db.incr_version(key.as_bytes(), now);
+        Ok(entries.iter().map(direct::stream_entry).collect())
+    }
+
+    /// Acknowledge entries of a consumer group, removing them from its
+    /// pending entries list. Returns the number acknowledged.
+    pub fn xack(&self, key: &str, group: &str, ids: &[&str]) -> Result<usize> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        let Some(stream) = db.stream_keys.get_mut(key.as_bytes()) else {
+            return Ok(0);
+        };
+        let acked = stream.ack(group, ids)?;
+        db.incr_version(key.as_bytes(), now);
+        Ok(acked as usize)
+    }
+
+    /// The pending entries list of a consumer group, sorted by ID.
+    pub fn xgroup_pending(&self, key: &str, group: &str) -> Result<Vec<PendingEntry>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        db.stream_keys
+            .get(key.as_bytes())
+            .and_then(|s| s.groups.get(group))
+            .map(direct::pending_entries)
+            .ok_or_else(|| direct::no_group(key, group))
+    }
+
+    /// The consumer groups of a stream, sorted by name.
+    pub fn xgroups(&self, key: &str) -> Result<Vec<StreamGroup>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Stream)?;
+        Ok(db
+            .stream_keys
+            .get(key.as_bytes())
+            .map(direct::stream_groups)
+            .unwrap_or_default())
     }
 
     // ── HyperLogLog operations ───────────────────────────────────────
 
     /// Add elements to a HyperLogLog. Returns true if the cardinality estimate changed.
-    pub fn pfadd(&self, key: &str, elements: &[&str]) -> bool {
-        let mut inner = self.state.lock();
-
-        let db = inner.db_mut(self.selected_db);
-        db.keys.insert(key.to_owned(), types::KeyType::HyperLogLog);
-        let hll = db.hll_keys.entry(key.to_owned()).or_default();
-        let mut changed = false;
-        for elem in elements {
-            if hll.add(elem.as_bytes()) {
-                changed = true;
-            }
-        }
-        changed
+    pub fn pfadd(&self, key: &str, elements: &[&str]) -> Result<bool> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::HyperLogLog)?;
+        let items: Vec<&[u8]> = elements.iter().map(|e| e.as_bytes()).collect();
+        Ok(db.hll_add(key.as_bytes(), &items, now) == 1)
     }
 
     /// Get the cardinality estimate of a HyperLogLog.
-    pub fn pfcount(&self, key: &str) -> i64 {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        db.hll_keys
-            .get(key)
-            .map(|hll| hll.count() as i64)
-            .unwrap_or(0)
+    pub fn pfcount(&self, key: &str) -> Result<u64> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::HyperLogLog)?;
+        Ok(db.hll_keys.get(key.as_bytes()).map_or(0, |hll| hll.count()))
+    }
+
+    // ── JSON operations ──────────────────────────────────────────────
+
+    /// Set a JSON document, replacing a document that's already there. The
+    /// TTL of an existing document is kept, like JSON.SET does.
+    #[cfg(feature = "json")]
+    pub fn json_set(&self, key: &str, doc: serde_json::Value) -> Result<()> {
+        let now = self.state.now();
+        let mut db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Json)?;
+        db.add_key(key.as_bytes(), types::KeyType::Json);
+        db.json_keys.insert(key.as_bytes().to_vec(), doc);
+        db.incr_version(key.as_bytes(), now);
+        Ok(())
+    }
+
+    /// Get a JSON document.
+    #[cfg(feature = "json")]
+    pub fn json_get(&self, key: &str) -> Result<Option<serde_json::Value>> {
+        let db = self.state.db(self.selected_db);
+        direct::check_type(&db, key.as_bytes(), types::KeyType::Json)?;
+        Ok(db.json_keys.get(key.as_bytes()).cloned())
     }
 
     // ── Flush ────────────────────────────────────────────────────────
 
     /// Remove all keys from the selected database.
     pub fn flush_db(&self) {
-        let mut inner = self.state.lock();
-        inner.db_mut(self.selected_db).flush();
+        self.state.db(self.selected_db).flush();
     }
 
     /// Remove all keys from all databases.
     pub fn flush_all(&self) {
-        let mut inner = self.state.lock();
-        for db in &mut inner.dbs {
-            db.flush();
+        for db in &self.state.dbs {
+            db.lock().unwrap().flush();
         }
     }
 
@@ -554,6 +905,28 @@ impl Miniredis {
         registry.numpat()
     }
 
+    /// Publish a message on a shard channel. Returns the number of
+    /// subscribers that received it.
+    pub fn spublish(&self, channel: &str, message: &str) -> i64 {
+        let registry = self.state.pubsub.lock().unwrap();
+        registry.spublish(channel, message)
+    }
+
+    /// Return active shard channels, optionally filtered by glob pattern.
+    pub fn pubsub_shard_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        let registry = self.state.pubsub.lock().unwrap();
+        registry.active_shard_channels(pattern)
+    }
+
+    /// Return the number of subscribers for specific shard channels.
+    pub fn pubsub_shard_numsub(&self, channels: &[&str]) -> Vec<(String, i64)> {
+        let registry = self.state.pubsub.lock().unwrap();
+        channels
+            .iter()
+            .map(|ch| (ch.to_string(), registry.shard_numsub(ch)))
+            .collect()
+    }
+
     // ── Testing assertions ───────────────────────────────────────────
 
     /// Assert that a list key has the expected values. Panics on mismatch.
@@ -627,18 +1000,135 @@ impl Miniredis {
         tokio::spawn(async move {
             server::run(listener, state_clone, shutdown_rx, None).await;
         });
+        replication::register(self.addr, &self.state);
+
+        Ok(())
+    }
+
+    // ── Persistence ─────────────────────────────────────────────────
+
+    /// Write all databases to an RDB file, which can be read by
+    /// [`load_from()`](Self::load_from) or a real Redis.
+    pub fn save_to(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let data = rdb::encode(&self.state.lock_all());
+        rdb::write_file(path.as_ref(), &data)?;
+        let mut inner = self.state.lock();
+        inner.last_save = self.state.now();
+        Ok(())
+    }
 
+    /// Replace all databases with the contents of an RDB file, as written
+    /// by [`save_to()`](Self::save_to), SAVE, or a real Redis (RDB version
+    /// 9 and up). Keys with a TTL in the past aren't loaded.
+    pub fn load_from(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let data = std::fs::read(path)?;
+        let _keyspace = self.state.lock_keyspace(Isolation::Exclusive);
+        rdb::load(&mut self.state.lock_all(), &data)?;
+        self.state.publish_changes(None);
+        self.state.notify.notify_waiters();
         Ok(())
     }
 
+    // ── Checkpoints ─────────────────────────────────────────────────
+
+    /// Copy all databases, the cached scripts, the function libraries
+    /// and the ACL users, to go back to with [`restore()`](Self::restore).
+    /// Build a fixture once, then restore it between test cases.
+    pub fn checkpoint(&self) -> Checkpoint {
+        checkpoint::take(&self.state)
+    }
+
+    /// Replace all databases, scripts, functions and ACL users with the
+    /// ones of `checkpoint`. Keys get back the TTL they had left at the
+    /// checkpoint. Replicas aren't updated.
+    pub fn restore(&self, checkpoint: &Checkpoint) {
+        checkpoint::restore(&self.state, checkpoint);
+    }
+
+    /// The keys added, removed or modified since `checkpoint`, in all
+    /// databases, sorted by database and key. A TTL that changed, e.g.
+    /// by [`fast_forward()`](Self::fast_forward), counts as a change.
+    pub fn diff(&self, checkpoint: &Checkpoint) -> Vec<KeyChange> {
+        checkpoint::diff(&self.state, checkpoint)
+    }
+
+    // ── Fault injection ─────────────────────────────────────────────
+
+    /// Inject a fault into command processing, e.g.
+    /// `Fault::error("LOADING loading").commands(&["GET"]).times(2)`.
+    /// Returns an ID for [`remove_fault()`](Self::remove_fault).
+    pub fn inject_fault(&self, fault: Fault) -> u64 {
+        self.state.faults.lock().unwrap().add(fault)
+    }
+
+    /// Fail the next `n` commands with the given error.
+    pub fn fail_next(&self, n: u64, error: &str) -> u64 {
+        self.inject_fault(Fault::error(error).times(n))
+    }
+
+    /// Remove an injected fault. Returns false if it was already gone.
+    pub fn remove_fault(&self, id: u64) -> bool {
+        self.state.faults.lock().unwrap().remove(id)
+    }
+
+    /// Remove all injected faults.
+    pub fn clear_faults(&self) {
+        self.state.faults.lock().unwrap().clear();
+    }
+
+    // ── Command log ─────────────────────────────────────────────────
+
+    /// Start keeping the commands the server runs again, after
+    /// `disable_command_log`.
+    pub fn enable_command_log(&self) {
+        self.state.monitor.lock().unwrap().set_logging(true);
+    }
+
+    /// Stop keeping commands and forget the logged ones, for servers that
+    /// run long and don't need the log.
+    pub fn disable_command_log(&self) {
+        self.state.monitor.lock().unwrap().set_logging(false);
+    }
+
+    /// The last 100,000 commands the server ran, oldest first: what
+    /// MONITOR would have shown, plus admin commands. Commands in a
+    /// transaction are logged when EXEC runs them, commands called by Lua
+    /// scripts have `script` set, and AUTH and HELLO passwords are
+    /// redacted. The log is on unless `disable_command_log` was called.
+    pub fn command_log(&self) -> Vec<LoggedCommand> {
+        self.state.monitor.lock().unwrap().log()
+    }
+
+    /// Forget the logged commands.
+    pub fn clear_command_log(&self) {
+        self.state.monitor.lock().unwrap().clear_log();
+    }
+
+    // ── Script log ──────────────────────────────────────────────────
+
+    /// The messages Lua scripts and functions logged with redis.log(),
+    /// oldest first.
+    pub fn script_log(&self) -> Vec<ScriptLogEntry> {
+        self.state.script.lock().unwrap().log()
+    }
+
+    /// Forget the logged script messages.
+    pub fn clear_script_log(&self) {
+        self.state.script.lock().unwrap().clear_log();
+    }
+
     // ── Dump ────────────────────────────────────────────────────────
 
     /// Return a text representation of the selected database, useful for
     /// debugging.
     pub fn dump(&self) -> String {
-        let inner = self.state.lock();
-        let db = inner.db(self.selected_db);
-        dump_db(db)
+        dump_text(&self.state.db(self.selected_db))
+    }
+
+    /// Every key of the selected database with its TTL and value, sorted
+    /// by key. Compare it to the expected keys, or snapshot it.
+    pub fn dump_db(&self) -> Vec<DumpedKey> {
+        direct::dump(&self.state.db(self.selected_db))
     }
 
     // ── Internals (for advanced usage) ───────────────────────────────
@@ -650,8 +1140,7 @@ impl Miniredis {
 
     /// Number of keys in the selected database.
     pub fn db_size(&self) -> usize {
-        let inner = self.state.lock();
-        inner.db(self.selected_db).keys.len()
+        self.state.db(self.selected_db).keys.len()
     }
 }
 
@@ -665,39 +1154,51 @@ pub struct DbRef<'a> {
 impl DbRef<'_> {
     /// Return all keys, sorted.
     pub fn keys(&self) -> Vec<String> {
-        let inner = self.state.lock();
-        inner.db(self.db_id).all_keys()
+        self.keys_bytes().into_iter().map(lossy).collect()
+    }
+
+    /// Return all keys as bytes, sorted.
+    pub fn keys_bytes(&self) -> Vec<Vec<u8>> {
+        self.state.db(self.db_id).all_keys()
     }
 
     /// Get a string key value.
     pub fn get(&self, key: &str) -> Option<String> {
-        let inner = self.state.lock();
-        inner
-            .db(self.db_id)
-            .string_get(key)
-            .map(|v| String::from_utf8_lossy(v).into_owned())
+        self.get_bytes(key.as_bytes()).map(lossy)
+    }
+
+    /// Get a string value by binary key.
+    pub fn get_bytes(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.state.db(self.db_id).string_get(key).cloned()
     }
 
     /// Set a string key. Removes any existing TTL.
     pub fn set(&self, key: &str, value: &str) {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
-        let db = inner.db_mut(self.db_id);
-        db.string_set(key, value.as_bytes().to_vec(), now);
+        self.set_bytes(key.as_bytes(), value.as_bytes());
+    }
+
+    /// Set a binary key to a binary value. Removes any existing TTL.
+    pub fn set_bytes(&self, key: &[u8], value: &[u8]) {
+        let now = self.state.now();
+        let mut db = self.state.db(self.db_id);
+        db.string_set(key, value.to_vec(), now);
         db.ttl.remove(key);
     }
 
     /// Check if a key exists.
     pub fn exists(&self, key: &str) -> bool {
-        let mut inner = self.state.lock();
-        let now = inner.effective_now();
-        inner.db_mut(self.db_id).exists(key, now)
+        self.exists_bytes(key.as_bytes())
+    }
+
+    /// Check if a binary key exists.
+    pub fn exists_bytes(&self, key: &[u8]) -> bool {
+        let now = self.state.now();
+        self.state.db(self.db_id).exists(key, now)
     }
 
     /// Return the type of a key.
     pub fn key_type(&self, key: &str) -> &'static str {
-        let inner = self.state.lock();
-        match inner.db(self.db_id).key_type(key) {
+        match self.state.db(self.db_id).key_type(key.as_bytes()) {
             Some(t) => t.as_str(),
             None => "none",
         }
@@ -705,21 +1206,28 @@ impl DbRef<'_> {
 
     /// Number of keys in this database.
     pub fn db_size(&self) -> usize {
-        let inner = self.state.lock();
-        inner.db(self.db_id).keys.len()
+        self.state.db(self.db_id).keys.len()
     }
 
     /// Return a text representation of this database.
     pub fn dump(&self) -> String {
-        let inner = self.state.lock();
-        dump_db(inner.db(self.db_id))
+        dump_text(&self.state.db(self.db_id))
+    }
+
+    /// Every key of this database with its TTL and value, sorted by key.
+    pub fn dump_db(&self) -> Vec<DumpedKey> {
+        direct::dump(&self.state.db(self.db_id))
     }
 }
 
 /// Maximum number of characters to show per value in [`Miniredis::dump()`].
 const DUMP_MAX_LINE_LEN: usize = 200;
 
-fn dump_db(db: &db::RedisDB) -> String {
+fn lossy(b: Vec<u8>) -> String {
+    String::from_utf8(b).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+fn dump_text(db: &db::RedisDB) -> String {
     use std::fmt::Write;
     use types::Direction;
 
@@ -737,7 +1245,7 @@ fn dump_db(db: &db::RedisDB) -> String {
     };
 
     for k in db.all_keys() {
-        let _ = writeln!(r, "- {}", k);
+        let _ = writeln!(r, "- {}", String::from_utf8_lossy(&k));
         match db.key_type(&k) {
             Some(types::KeyType::String) => {
                 if let Some(v) = db.string_get(&k) {
@@ -746,11 +1254,17 @@ fn dump_db(db: &db::RedisDB) -> String {
             }
             Some(types::KeyType::Hash) => {
                 if let Some(hash) = db.hash_keys.get(&k) {
-                    let mut fields: Vec<&String> = hash.keys().collect();
+                    let mut fields: Vec<&Vec<u8>> = hash.keys().collect();
                     fields.sort();
                     for f in fields {
                         let v = String::from_utf8_lossy(&hash[f]);
-                        let _ = writeln!(r, "{}{}: {}", indent, f, truncate(&v));
+                        let _ = writeln!(
+                            r,
+                            "{}{}: {}",
+                            indent,
+                            String::from_utf8_lossy(f),
+                            truncate(&v)
+                        );
                     }
                 }
             }
@@ -764,17 +1278,23 @@ fn dump_db(db: &db::RedisDB) -> String {
             }
             Some(types::KeyType::Set) => {
                 if let Some(set) = db.set_keys.get(&k) {
-                    let mut members: Vec<&String> = set.iter().collect();
+                    let mut members: Vec<&Vec<u8>> = set.iter().collect();
                     members.sort();
                     for m in members {
-                        let _ = writeln!(r, "{}{}", indent, truncate(m));
+                        let _ = writeln!(r, "{}{}", indent, truncate(&String::from_utf8_lossy(m)));
                     }
                 }
             }
             Some(types::KeyType::SortedSet) => {
                 if let Some(ss) = db.sorted_set_keys.get(&k) {
                     for el in ss.by_score(Direction::Asc) {
-                        let _ = writeln!(r, "{}{}: {}", indent, el.score, truncate(&el.member));
+                        let _ = writeln!(
+                            r,
+                            "{}{}: {}",
+                            indent,
+                            el.score,
+                            truncate(&String::from_utf8_lossy(&el.member))
+                        );
                     }
                 }
             }
@@ -790,8 +1310,8 @@ fn dump_db(db: &db::RedisDB) -> String {
                                 "{}{}{}: {}",
                                 indent,
                                 indent,
-                                truncate(&ev[i]),
-                                truncate(&ev[i + 1])
+                                truncate(&String::from_utf8_lossy(&ev[i])),
+                                truncate(&String::from_utf8_lossy(&ev[i + 1]))
                             );
                             i += 2;
                         }
@@ -801,6 +1321,28 @@ fn dump_db(db: &db::RedisDB) -> String {
             Some(types::KeyType::HyperLogLog) => {
                 let _ = writeln!(r, "{}(HyperLogLog)", indent);
             }
+            #[cfg(feature = "json")]
+            Some(types::KeyType::Json) => {
+                if let Some(doc) = db.json_keys.get(&k) {
+                    let _ = writeln!(r, "{}{}", indent, truncate(&doc.to_string()));
+                }
+            }
+            #[cfg(feature = "bloom")]
+            Some(types::KeyType::Bloom) => {
+                let _ = writeln!(r, "{}(Bloom filter)", indent);
+            }
+            #[cfg(feature = "bloom")]
+            Some(types::KeyType::Cuckoo) => {
+                let _ = writeln!(r, "{}(Cuckoo filter)", indent);
+            }
+            #[cfg(feature = "bloom")]
+            Some(types::KeyType::CountMinSketch) => {
+                let _ = writeln!(r, "{}(Count-Min sketch)", indent);
+            }
+            #[cfg(feature = "bloom")]
+            Some(types::KeyType::TopK) => {
+                let _ = writeln!(r, "{}(Top-K)", indent);
+            }
             None => {}
         }
     }
@@ -0,0 +1,287 @@
+//! MONITOR and the command log.
+//!
+//! Every command the server runs is reported here after it ran, with the
+//! same rules Redis uses for MONITOR: commands inside MULTI show up when
+//! EXEC runs them, and commands called by Lua scripts are included. The
+//! command log keeps them for `Miniredis::command_log`, unless
+//! `Miniredis::disable_command_log` turned it off; MONITOR clients get them
+//! as text lines, without QUIT and the admin commands. Both see passwords
+//! redacted.
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::mpsc;
+
+use crate::acl::{full_command_name, in_category};
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::frame::Frame;
+
+/// The command log keeps this many commands, dropping the oldest.
+const MAX_LOGGED: usize = 100_000;
+
+/// The kind of reply a command got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyType {
+    Status,
+    Error,
+    Integer,
+    Bulk,
+    Nil,
+    Array,
+    Map,
+    Set,
+    Push,
+    Double,
+}
+
+impl ReplyType {
+    pub fn of(frame: &Frame) -> Self {
+        match frame {
+            Frame::Simple(_) => ReplyType::Status,
+            Frame::Error(_) => ReplyType::Error,
+            Frame::Integer(_) => ReplyType::Integer,
+            Frame::Bulk(_) => ReplyType::Bulk,
+            Frame::Null | Frame::NullArray => ReplyType::Nil,
+            Frame::Array(_) => ReplyType::Array,
+            Frame::Map(_) => ReplyType::Map,
+            Frame::Set(_) => ReplyType::Set,
+            Frame::Push(_) => ReplyType::Push,
+            Frame::Double(_) => ReplyType::Double,
+        }
+    }
+}
+
+/// A command recorded in the command log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggedCommand {
+    /// When the command ran (mock time if set).
+    pub time: SystemTime,
+    /// The database selected after the command.
+    pub db: usize,
+    /// ID of the client that sent it (CLIENT ID).
+    pub client_id: u64,
+    /// The client's CLIENT SETNAME name.
+    pub client_name: Option<String>,
+    /// Whether a Lua script called the command.
+    pub script: bool,
+    /// The command name and arguments, with passwords redacted.
+    pub args: Vec<Vec<u8>>,
+    pub reply: ReplyType,
+}
+
+impl LoggedCommand {
+    /// The command name, upper-case.
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.args[0]).to_uppercase()
+    }
+
+    /// The command name and arguments as strings.
+    pub fn command(&self) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|a| String::from_utf8_lossy(a).to_string())
+            .collect()
+    }
+}
+
+/// The MONITOR clients and the command log.
+pub struct Monitor {
+    monitors: Vec<mpsc::UnboundedSender<String>>,
+    logging: bool,
+    log: VecDeque<LoggedCommand>,
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Monitor {
+            monitors: Vec::new(),
+            logging: true,
+            log: VecDeque::new(),
+        }
+    }
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a MONITOR client. Lines arrive on the returned channel until
+    /// it's dropped.
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<String> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.monitors.push(tx);
+        rx
+    }
+
+    pub fn log(&self) -> Vec<LoggedCommand> {
+        self.log.iter().cloned().collect()
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    /// Start or stop keeping commands in the log. Stopping drops the
+    /// commands logged so far.
+    pub fn set_logging(&mut self, on: bool) {
+        self.logging = on;
+        if !on {
+            self.log = VecDeque::new();
+        }
+    }
+}
+
+/// Report a command that ran on `ctx`'s connection with `response`.
+pub fn feed(state: &SharedState, ctx: &ConnCtx, args: &[Vec<u8>], response: &Frame) {
+    let mut monitor = state.monitor.lock().unwrap();
+    if monitor.monitors.is_empty() && !monitor.logging {
+        return;
+    }
+    let time = state.now();
+    let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let args = redact(&name, args);
+    let full_name = full_command_name(&name, args.get(1).map(|a| a.as_slice())).to_uppercase();
+    if !monitor.monitors.is_empty() && name != "QUIT" && !in_category(&full_name, "admin") {
+        let line = format_line(time, ctx.selected_db, &client_label(ctx), &args);
+        monitor.monitors.retain(|tx| tx.send(line.clone()).is_ok());
+    }
+    if !monitor.logging {
+        return;
+    }
+    if monitor.log.len() == MAX_LOGGED {
+        monitor.log.pop_front();
+    }
+    monitor.log.push_back(LoggedCommand {
+        time,
+        db: ctx.selected_db,
+        client_id: ctx.client_id,
+        client_name: ctx.client_name.clone(),
+        script: ctx.nested,
+        args,
+        reply: ReplyType::of(response),
+    });
+}
+
+/// Where a command came from, as MONITOR shows it.
+fn client_label(ctx: &ConnCtx) -> String {
+    if ctx.nested {
+        return "lua".to_string();
+    }
+    ctx.addr
+        .map_or_else(|| "unknown".to_string(), |addr| addr.to_string())
+}
+
+/// Hide passwords, like Redis does.
+pub(crate) fn redact(name: &str, args: &[Vec<u8>]) -> Vec<Vec<u8>> {
+    let mut args = args.to_vec();
+    match name {
+        "AUTH" => args[1..].fill(b"(redacted)".to_vec()),
+        "HELLO" => {
+            if let Some(i) = args.iter().position(|a| a.eq_ignore_ascii_case(b"AUTH")) {
+                for arg in args.iter_mut().skip(i + 1).take(2) {
+                    *arg = b"(redacted)".to_vec();
+                }
+            }
+        }
+        _ => {}
+    }
+    args
+}
+
+/// A MONITOR line: `1339518083.107412 [0 127.0.0.1:60866] "keys" "*"`.
+pub fn format_line(time: SystemTime, db: usize, client: &str, args: &[Vec<u8>]) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut line = format!(
+        "{}.{:06} [{} {}]",
+        since.as_secs(),
+        since.subsec_micros(),
+        db,
+        client
+    );
+    for arg in args {
+        line.push(' ');
+        quote(&mut line, arg);
+    }
+    line
+}
+
+/// Quote a string the way Redis' sdscatrepr does.
+fn quote(out: &mut String, arg: &[u8]) {
+    out.push('"');
+    for &b in arg {
+        match b {
+            b'\\' => out.push_str("\\\\"),
+            b'"' => out.push_str("\\\""),
+            b'\n' => out.push_str("\\n"),
+            b'\r' => out.push_str("\\r"),
+            b'\t' => out.push_str("\\t"),
+            0x07 => out.push_str("\\a"),
+            0x08 => out.push_str("\\b"),
+            b' '..=b'~' => out.push(b as char),
+            _ => out.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn args(cmd: &[&str]) -> Vec<Vec<u8>> {
+        cmd.iter().map(|a| a.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_format_line() {
+        let time = UNIX_EPOCH + Duration::from_micros(1_339_518_083_107_412);
+        assert_eq!(
+            format_line(time, 0, "127.0.0.1:60866", &args(&["keys", "*"])),
+            r#"1339518083.107412 [0 127.0.0.1:60866] "keys" "*""#
+        );
+        assert_eq!(
+            format_line(
+                UNIX_EPOCH,
+                3,
+                "lua",
+                &[b"set".to_vec(), b"a\"b\\\n\x01\xff".to_vec()]
+            ),
+            r#"0.000000 [3 lua] "set" "a\"b\\\n\x01\xff""#
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("AUTH", &args(&["auth", "user", "pass"])),
+            args(&["auth", "(redacted)", "(redacted)"])
+        );
+        assert_eq!(
+            redact(
+                "HELLO",
+                &args(&["hello", "3", "AUTH", "user", "pass", "SETNAME", "x"])
+            ),
+            args(&[
+                "hello",
+                "3",
+                "AUTH",
+                "(redacted)",
+                "(redacted)",
+                "SETNAME",
+                "x"
+            ])
+        );
+        assert_eq!(redact("GET", &args(&["get", "a"])), args(&["get", "a"]));
+    }
+
+    #[test]
+    fn test_reply_type() {
+        assert_eq!(ReplyType::of(&Frame::ok()), ReplyType::Status);
+        assert_eq!(ReplyType::of(&Frame::NullArray), ReplyType::Nil);
+        assert_eq!(ReplyType::of(&Frame::error("ERR x")), ReplyType::Error);
+    }
+}
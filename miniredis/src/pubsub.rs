@@ -5,7 +5,7 @@ use tokio::sync::mpsc;
 
 /// A message delivered to a pub/sub subscriber.
 pub struct PubsubMessage {
-    /// "message" or "pmessage"
+    /// "message", "pmessage" or "smessage"
     pub kind: &'static str,
     /// The pattern that matched (for pmessage only)
     pub pattern: Option<String>,
@@ -18,10 +18,14 @@ pub struct PubsubMessage {
 /// A pattern matcher: (pattern_string, compiled_matcher).
 pub type PatternMatcher = (String, Box<dyn Fn(&str) -> bool + Send + Sync>);
 
-/// Per-subscriber state: channels, patterns, and message sender.
+/// Per-subscriber state: channels, patterns, shard channels, and message
+/// sender.
 pub struct SubscriberInner {
     pub channels: HashSet<String>,
     pub patterns: Vec<PatternMatcher>,
+    /// Shard channels (SSUBSCRIBE). These are a namespace of their own:
+    /// PUBLISH doesn't reach them, and patterns don't match them.
+    pub shard_channels: HashSet<String>,
     pub tx: mpsc::UnboundedSender<PubsubMessage>,
 }
 
@@ -86,12 +90,45 @@ impl PubsubRegistry {
         count
     }
 
+    /// Publish a message to the subscribers of a shard channel. Returns the
+    /// delivery count.
+    pub fn spublish(&self, channel: &str, message: &str) -> i64 {
+        let mut count = 0i64;
+        for sub in &self.subscribers {
+            let inner = sub.lock().unwrap();
+            if inner.shard_channels.contains(channel) {
+                let _ = inner.tx.send(PubsubMessage {
+                    kind: "smessage",
+                    pattern: None,
+                    channel: channel.to_string(),
+                    data: message.to_string(),
+                });
+                count += 1;
+            }
+        }
+        count
+    }
+
     /// Return all unique channels that have at least one subscriber, optionally filtered by pattern.
     pub fn active_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        self.collect_channels(|inner| &inner.channels, pattern)
+    }
+
+    /// Return all unique shard channels that have at least one subscriber,
+    /// optionally filtered by pattern.
+    pub fn active_shard_channels(&self, pattern: Option<&str>) -> Vec<String> {
+        self.collect_channels(|inner| &inner.shard_channels, pattern)
+    }
+
+    fn collect_channels(
+        &self,
+        which: impl Fn(&SubscriberInner) -> &HashSet<String>,
+        pattern: Option<&str>,
+    ) -> Vec<String> {
         let mut channels = HashSet::new();
         for sub in &self.subscribers {
             let inner = sub.lock().unwrap();
-            for ch in &inner.channels {
+            for ch in which(&inner) {
                 channels.insert(ch.clone());
             }
         }
@@ -119,6 +156,18 @@ impl PubsubRegistry {
         count
     }
 
+    /// Count subscribers for a specific shard channel.
+    pub fn shard_numsub(&self, channel: &str) -> i64 {
+        let mut count = 0i64;
+        for sub in &self.subscribers {
+            let inner = sub.lock().unwrap();
+            if inner.shard_channels.contains(channel) {
+                count += 1;
+            }
+        }
+        count
+    }
+
     /// Total number of pattern subscriptions across all subscribers.
     pub fn numpat(&self) -> i64 {
         let mut count = 0i64;
@@ -145,6 +194,7 @@ impl PubsubCtx {
         let inner = SubscriberInner {
             channels: HashSet::new(),
             patterns: Vec::new(),
+            shard_channels: HashSet::new(),
             tx,
         };
         let handle = Arc::new(Mutex::new(inner));
@@ -184,6 +234,21 @@ impl PubsubCtx {
         inner.channels.len() + inner.patterns.len()
     }
 
+    /// Subscribe to a shard channel. Returns the shard subscription count.
+    pub fn ssubscribe(&self, channel: &str) -> usize {
+        let mut inner = self.handle.lock().unwrap();
+        inner.shard_channels.insert(channel.to_string());
+        inner.shard_channels.len()
+    }
+
+    /// Unsubscribe from a shard channel. Returns the shard subscription
+    /// count.
+    pub fn sunsubscribe(&self, channel: &str) -> usize {
+        let mut inner = self.handle.lock().unwrap();
+        inner.shard_channels.remove(channel);
+        inner.shard_channels.len()
+    }
+
     /// Get all subscribed channel names.
     pub fn channels(&self) -> Vec<String> {
         let inner = self.handle.lock().unwrap();
@@ -198,9 +263,135 @@ impl PubsubCtx {
         inner.patterns.iter().map(|(p, _)| p.clone()).collect()
     }
 
+    /// Get all subscribed shard channel names.
+    pub fn shard_channels(&self) -> Vec<String> {
+        let inner = self.handle.lock().unwrap();
+        let mut channels: Vec<String> = inner.shard_channels.iter().cloned().collect();
+        channels.sort();
+        channels
+    }
+
     /// Total subscription count (channels + patterns).
     pub fn total_count(&self) -> usize {
         let inner = self.handle.lock().unwrap();
         inner.channels.len() + inner.patterns.len()
     }
+
+    /// Number of shard channel subscriptions.
+    pub fn shard_count(&self) -> usize {
+        self.handle.lock().unwrap().shard_channels.len()
+    }
+
+    /// Whether any subscription is left; the connection stays in pub/sub
+    /// mode until there are none.
+    pub fn is_subscribed(&self) -> bool {
+        self.total_count() + self.shard_count() > 0
+    }
+}
+
+// ── Keyspace notifications ───────────────────────────────────────────
+
+/// `K`: publish on `__keyspace@<db>__:<key>`.
+pub const NOTIFY_KEYSPACE: u32 = 1 << 0;
+/// `E`: publish on `__keyevent@<db>__:<event>`.
+pub const NOTIFY_KEYEVENT: u32 = 1 << 1;
+/// `g`: generic commands (DEL, EXPIRE, RENAME, ...).
+pub const NOTIFY_GENERIC: u32 = 1 << 2;
+/// `$`: string commands.
+pub const NOTIFY_STRING: u32 = 1 << 3;
+/// `l`: list commands.
+pub const NOTIFY_LIST: u32 = 1 << 4;
+/// `s`: set commands.
+pub const NOTIFY_SET: u32 = 1 << 5;
+/// `h`: hash commands.
+pub const NOTIFY_HASH: u32 = 1 << 6;
+/// `z`: sorted set commands.
+pub const NOTIFY_ZSET: u32 = 1 << 7;
+/// `x`: expired events (a key reached its TTL).
+pub const NOTIFY_EXPIRED: u32 = 1 << 8;
+/// `e`: evicted events (a key was evicted for maxmemory).
+pub const NOTIFY_EVICTED: u32 = 1 << 9;
+/// `t`: stream commands.
+pub const NOTIFY_STREAM: u32 = 1 << 10;
+/// `m`: key-miss events (a read command hit a missing key).
+pub const NOTIFY_KEY_MISS: u32 = 1 << 11;
+/// `n`: new key events.
+pub const NOTIFY_NEW: u32 = 1 << 12;
+/// `A`: alias for `g$lshzxet`.
+pub const NOTIFY_ALL: u32 = NOTIFY_GENERIC
+    | NOTIFY_STRING
+    | NOTIFY_LIST
+    | NOTIFY_SET
+    | NOTIFY_HASH
+    | NOTIFY_ZSET
+    | NOTIFY_EXPIRED
+    | NOTIFY_EVICTED
+    | NOTIFY_STREAM;
+
+/// Flag characters in the order Redis prints them, `A` aside.
+const NOTIFY_CLASS_CHARS: &[(char, u32)] = &[
+    ('g', NOTIFY_GENERIC),
+    ('$', NOTIFY_STRING),
+    ('l', NOTIFY_LIST),
+    ('s', NOTIFY_SET),
+    ('h', NOTIFY_HASH),
+    ('z', NOTIFY_ZSET),
+    ('x', NOTIFY_EXPIRED),
+    ('e', NOTIFY_EVICTED),
+    ('t', NOTIFY_STREAM),
+];
+
+/// Parse a `notify-keyspace-events` value such as `"KEA"` or `"Elx"`.
+/// Returns None on an unknown class character.
+pub fn parse_keyspace_events(s: &str) -> Option<u32> {
+    let mut flags = 0;
+    for c in s.chars() {
+        flags |= match c {
+            'A' => NOTIFY_ALL,
+            'K' => NOTIFY_KEYSPACE,
+            'E' => NOTIFY_KEYEVENT,
+            'm' => NOTIFY_KEY_MISS,
+            'n' => NOTIFY_NEW,
+            // Module events; accepted for compatibility, never emitted.
+            'd' => 0,
+            _ => NOTIFY_CLASS_CHARS.iter().find(|(ch, _)| *ch == c)?.1,
+        };
+    }
+    Some(flags)
+}
+
+/// Format notification flags the way CONFIG GET reports them.
+pub fn keyspace_events_to_string(flags: u32) -> String {
+    let mut s = String::new();
+    if flags & NOTIFY_ALL == NOTIFY_ALL {
+        s.push('A');
+    } else {
+        for (c, flag) in NOTIFY_CLASS_CHARS {
+            if flags & flag != 0 {
+                s.push(*c);
+            }
+        }
+    }
+    for (c, flag) in [
+        ('K', NOTIFY_KEYSPACE),
+        ('E', NOTIFY_KEYEVENT),
+        ('m', NOTIFY_KEY_MISS),
+        ('n', NOTIFY_NEW),
+    ] {
+        if flags & flag != 0 {
+            s.push(c);
+        }
+    }
+    s
+}
+
+/// A keyspace event raised by a command or by key expiry, waiting to be
+/// published once the command finishes.
+#[derive(Debug, Clone)]
+pub struct KeyspaceEvent {
+    /// One of the `NOTIFY_*` class flags.
+    pub class: u32,
+    /// Event name, e.g. "set", "del", "expired".
+    pub event: &'static str,
+    pub key: Vec<u8>,
 }
@@ -0,0 +1,1713 @@
+//! RDB snapshot encoding and decoding, compatible with Redis 7.x.
+//!
+//! Snapshots are written as RDB version 11 (version 12 when hash fields
+//! have TTLs), and files written by Redis using RDB version 9 and up can be
+//! loaded, including the compact encodings (ziplist, listpack, intset, LZF
+//! compressed strings). RedisBloom values are saved in miniredis-rs' own
+//! layout, which only miniredis-rs loads.
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "bloom")]
+use crate::bloom::Sketch;
+use crate::db::{Dataset, RedisDB};
+use crate::hll::HyperLogLog;
+use crate::types::{
+    KeyType, PendingEntry, SortedSet, Stream, StreamConsumer, StreamEntry, StreamGroup,
+};
+
+/// The RDB version written when no newer features are needed.
+pub const RDB_VERSION: u16 = 11;
+/// Oldest RDB version we can load.
+const MIN_RDB_VERSION: u16 = 9;
+/// Newest RDB version we can load (and the one used for hash field TTLs).
+const MAX_RDB_VERSION: u16 = 12;
+
+/// The Redis version advertised in the `redis-ver` aux field.
+const REDIS_VER: &str = "7.2.0";
+
+/// Maximum number of entries per stream listpack node, as Redis'
+/// `stream-node-max-entries` default.
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+// ── Opcodes ─────────────────────────────────────────────────────────
+
+const OPCODE_SLOT_INFO: u8 = 244;
+pub(crate) const OPCODE_FUNCTION2: u8 = 245;
+const OPCODE_FUNCTION_PRE_GA: u8 = 246;
+const OPCODE_MODULE_AUX: u8 = 247;
+const OPCODE_IDLE: u8 = 248;
+const OPCODE_FREQ: u8 = 249;
+const OPCODE_AUX: u8 = 250;
+const OPCODE_RESIZEDB: u8 = 251;
+const OPCODE_EXPIRETIME_MS: u8 = 252;
+const OPCODE_EXPIRETIME: u8 = 253;
+const OPCODE_SELECTDB: u8 = 254;
+const OPCODE_EOF: u8 = 255;
+
+// ── Object types ────────────────────────────────────────────────────
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_MODULE_PRE_GA: u8 = 6;
+const TYPE_MODULE_2: u8 = 7;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_SET_LISTPACK: u8 = 20;
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+const TYPE_HASH_METADATA: u8 = 24;
+const TYPE_HASH_LISTPACK_EX: u8 = 25;
+
+/// Special string encodings (length byte `11xxxxxx`).
+const ENC_INT8: u8 = 0;
+const ENC_INT16: u8 = 1;
+const ENC_INT32: u8 = 2;
+const ENC_LZF: u8 = 3;
+
+/// Quicklist node containers.
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+const QUICKLIST_NODE_PACKED: u64 = 2;
+
+/// The most bytes one byte of LZF data decompresses to: a three byte back
+/// reference copies up to 264.
+const LZF_MAX_EXPANSION: usize = 88;
+
+/// Module value opcodes.
+#[cfg(any(feature = "json", feature = "bloom"))]
+const MODULE_OPCODE_EOF: u64 = 0;
+#[cfg(any(feature = "json", feature = "bloom"))]
+const MODULE_OPCODE_STRING: u64 = 5;
+
+/// The RedisJSON module type and the encoding version of its values,
+/// which hold the document as a JSON string.
+#[cfg(feature = "json")]
+const JSON_MODULE_TYPE: &str = "ReJSON-RL";
+#[cfg(feature = "json")]
+const JSON_ENCODING_VERSION: u64 = 3;
+
+/// The encoding version RedisBloom values are saved with, under the
+/// RedisBloom module types. It's the highest there is, so RedisBloom
+/// refuses them instead of misreading miniredis-rs' layout.
+#[cfg(feature = "bloom")]
+const BLOOM_ENCODING_VERSION: u64 = 1023;
+
+/// Stream entry flags.
+const STREAM_ITEM_FLAG_DELETED: i64 = 1;
+const STREAM_ITEM_FLAG_SAMEFIELDS: i64 = 2;
+
+type Result<T> = std::result::Result<T, String>;
+
+fn truncated() -> String {
+    "unexpected end of RDB data".to_string()
+}
+
+// ── Checksums and lengths ───────────────────────────────────────────
+
+/// CRC-64/Jones, as used by Redis for RDB and DUMP payload checksums.
+pub(crate) fn crc64(data: &[u8]) -> u64 {
+    const POLY: u64 = 0x95ac_9329_ac4b_c9b5;
+    let mut crc = 0u64;
+    for &b in data {
+        crc ^= b as u64;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Append an RDB-encoded length.
+pub(crate) fn write_len(out: &mut Vec<u8>, len: u64) {
+    if len < 1 << 6 {
+        out.push(len as u8);
+    } else if len < 1 << 14 {
+        out.push(0x40 | (len >> 8) as u8);
+        out.push(len as u8);
+    } else if len <= u32::MAX as u64 {
+        out.push(0x80);
+        out.extend_from_slice(&(len as u32).to_be_bytes());
+    } else {
+        out.push(0x81);
+        out.extend_from_slice(&len.to_be_bytes());
+    }
+}
+
+/// A decoded length field: either a plain length or the tag of a special
+/// string encoding.
+enum Len {
+    Plain(u64),
+    Encoded(u8),
+}
+
+fn decode_len(data: &[u8], pos: &mut usize) -> Option<Len> {
+    let first = *data.get(*pos)?;
+    *pos += 1;
+    match first >> 6 {
+        0 => Some(Len::Plain((first & 0x3f) as u64)),
+        1 => {
+            let second = *data.get(*pos)?;
+            *pos += 1;
+            Some(Len::Plain((((first & 0x3f) as u64) << 8) | second as u64))
+        }
+        2 if first == 0x80 => {
+            let bytes = data.get(*pos..*pos + 4)?;
+            *pos += 4;
+            Some(Len::Plain(u32::from_be_bytes(bytes.try_into().ok()?) as u64))
+        }
+        2 if first == 0x81 => {
+            let bytes = data.get(*pos..*pos + 8)?;
+            *pos += 8;
+            Some(Len::Plain(u64::from_be_bytes(bytes.try_into().ok()?)))
+        }
+        3 => Some(Len::Encoded(first & 0x3f)),
+        _ => None,
+    }
+}
+
+/// Read a plain RDB-encoded length.
+pub(crate) fn read_len(data: &[u8], pos: &mut usize) -> Option<usize> {
+    match decode_len(data, pos)? {
+        Len::Plain(n) => usize::try_from(n).ok(),
+        Len::Encoded(_) => None,
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, s: &[u8]) {
+    write_len(out, s.len() as u64);
+    out.extend_from_slice(s);
+}
+
+fn write_aux(out: &mut Vec<u8>, key: &str, value: &str) {
+    out.push(OPCODE_AUX);
+    write_string(out, key.as_bytes());
+    write_string(out, value.as_bytes());
+}
+
+fn unix_ms(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+fn from_unix_ms(ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(ms)
+}
+
+// ── Encoding ────────────────────────────────────────────────────────
+
+/// Serialize all databases and function libraries as an RDB file.
+pub fn encode(data: &Dataset) -> Vec<u8> {
+    let now = unix_ms(data.now);
+    let version = if data.dbs.iter().any(|db| !db.hash_field_ttls.is_empty()) {
+        MAX_RDB_VERSION
+    } else {
+        RDB_VERSION
+    };
+
+    let mut out = format!("REDIS{:04}", version).into_bytes();
+    write_aux(&mut out, "redis-ver", REDIS_VER);
+    write_aux(&mut out, "redis-bits", "64");
+    write_aux(&mut out, "ctime", &(now / 1000).to_string());
+    write_aux(&mut out, "used-mem", "0");
+    write_aux(&mut out, "aof-base", "0");
+
+    let mut libraries: Vec<_> = data.inner.functions.values().collect();
+    libraries.sort_by(|a, b| a.name.cmp(&b.name));
+    for lib in libraries {
+        out.push(OPCODE_FUNCTION2);
+        write_string(&mut out, lib.code.as_bytes());
+    }
+
+    for (idx, db) in data.dbs.iter().enumerate() {
+        if db.keys.is_empty() {
+            continue;
+        }
+        out.push(OPCODE_SELECTDB);
+        write_len(&mut out, idx as u64);
+        out.push(OPCODE_RESIZEDB);
+        write_len(&mut out, db.keys.len() as u64);
+        write_len(&mut out, db.ttl.len() as u64);
+        for key in db.all_keys() {
+            let Some((obj_type, body)) = encode_object(db, &key, now) else {
+                continue;
+            };
+            if let Some(ttl) = db.ttl.get(&key) {
+                out.push(OPCODE_EXPIRETIME_MS);
+                out.extend_from_slice(&(now + ttl.as_millis() as u64).to_le_bytes());
+            }
+            out.push(obj_type);
+            write_string(&mut out, &key);
+            out.extend_from_slice(&body);
+        }
+    }
+
+    out.push(OPCODE_EOF);
+    let crc = crc64(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    out
+}
+
+/// Encode the value stored at `key`. Returns the RDB type and the payload.
+fn encode_object(db: &RedisDB, key: &[u8], now: u64) -> Option<(u8, Vec<u8>)> {
+    let mut out = Vec::new();
+    let obj_type = match db.keys.get(key)? {
+        KeyType::String => {
+            write_string(&mut out, db.string_keys.get(key)?);
+            TYPE_STRING
+        }
+        KeyType::HyperLogLog => {
+            write_string(&mut out, &db.hll_keys.get(key)?.encode());
+            TYPE_STRING
+        }
+        KeyType::List => {
+            let list = db.list_keys.get(key)?;
+            write_len(&mut out, list.len() as u64);
+            for item in list {
+                write_string(&mut out, item);
+            }
+            TYPE_LIST
+        }
+        KeyType::Set => {
+            let members = db.set_members(key);
+            write_len(&mut out, members.len() as u64);
+            for m in members {
+                write_string(&mut out, &m);
+            }
+            TYPE_SET
+        }
+        KeyType::SortedSet => {
+            let elems = db
+                .sorted_set_keys
+                .get(key)?
+                .by_score(crate::types::Direction::Asc);
+            write_len(&mut out, elems.len() as u64);
+            for e in elems {
+                write_string(&mut out, &e.member);
+                out.extend_from_slice(&e.score.to_le_bytes());
+            }
+            TYPE_ZSET_2
+        }
+        KeyType::Hash => {
+            let hash = db.hash_keys.get(key)?;
+            let mut fields: Vec<&Vec<u8>> = hash.keys().collect();
+            fields.sort();
+            match db.hash_field_ttls.get(key).filter(|t| !t.is_empty()) {
+                Some(ttls) => {
+                    // Field TTLs are stored relative to the earliest one,
+                    // with 0 meaning "no TTL".
+                    let min_expire = now + ttls.values().min()?.as_millis() as u64;
+                    out.extend_from_slice(&min_expire.to_le_bytes());
+                    write_len(&mut out, fields.len() as u64);
+                    for field in fields {
+                        let ttl = ttls
+                            .get(field)
+                            .map_or(0, |t| now + t.as_millis() as u64 - min_expire + 1);
+                        write_len(&mut out, ttl);
+                        write_string(&mut out, field);
+                        write_string(&mut out, &hash[field]);
+                    }
+                    TYPE_HASH_METADATA
+                }
+                None => {
+                    write_len(&mut out, fields.len() as u64);
+                    for field in fields {
+                        write_string(&mut out, field);
+                        write_string(&mut out, &hash[field]);
+                    }
+                    TYPE_HASH
+                }
+            }
+        }
+        KeyType::Stream => {
+            encode_stream(&mut out, db.stream_keys.get(key)?);
+            TYPE_STREAM_LISTPACKS_3
+        }
+        #[cfg(feature = "json")]
+        KeyType::Json => {
+            write_len(&mut out, module_id(JSON_MODULE_TYPE, JSON_ENCODING_VERSION));
+            write_len(&mut out, MODULE_OPCODE_STRING);
+            write_string(&mut out, db.json_keys.get(key)?.to_string().as_bytes());
+            write_len(&mut out, MODULE_OPCODE_EOF);
+            TYPE_MODULE_2
+        }
+        #[cfg(feature = "bloom")]
+        key_type @ (KeyType::Bloom | KeyType::Cuckoo | KeyType::CountMinSketch | KeyType::TopK) => {
+            write_len(
+                &mut out,
+                module_id(key_type.as_str(), BLOOM_ENCODING_VERSION),
+            );
+            write_len(&mut out, MODULE_OPCODE_STRING);
+            write_string(&mut out, &db.bloom_keys.get(key)?.encode());
+            write_len(&mut out, MODULE_OPCODE_EOF);
+            TYPE_MODULE_2
+        }
+    };
+    Some((obj_type, out))
+}
+
+/// The 64 bit ID a module type is saved with: its 9 character name in
+/// 6 bit groups, then a 10 bit encoding version.
+#[cfg(any(feature = "json", feature = "bloom"))]
+fn module_id(name: &str, encver: u64) -> u64 {
+    const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let id = name.bytes().fold(0u64, |id, c| {
+        let pos = CHARSET.iter().position(|&x| x == c).unwrap_or(0);
+        (id << 6) | pos as u64
+    });
+    (id << 10) | encver
+}
+
+fn parse_id(id: &str) -> (u64, u64) {
+    Stream::parse_id(id).unwrap_or((0, 0))
+}
+
+/// A stream ID as the 16 byte big-endian key Redis uses in its radix trees.
+fn raw_id(id: &str) -> [u8; 16] {
+    let (ms, seq) = parse_id(id);
+    let mut raw = [0u8; 16];
+    raw[..8].copy_from_slice(&ms.to_be_bytes());
+    raw[8..].copy_from_slice(&seq.to_be_bytes());
+    raw
+}
+
+fn encode_stream(out: &mut Vec<u8>, stream: &Stream) {
+    // Listpack nodes, each keyed by the ID of its first ("master") entry.
+    let nodes: Vec<&[StreamEntry]> = stream.entries.chunks(STREAM_NODE_MAX_ENTRIES).collect();
+    write_len(out, nodes.len() as u64);
+    for node in nodes {
+        let master = &node[0];
+        let (master_ms, master_seq) = parse_id(&master.id);
+        let master_fields: Vec<&Vec<u8>> = master.values.iter().step_by(2).collect();
+
+        let mut lp = ListpackWriter::new();
+        lp.push_int(node.len() as i64);
+        lp.push_int(0); // deleted entries
+        lp.push_int(master_fields.len() as i64);
+        for f in &master_fields {
+            lp.push_str(f);
+        }
+        lp.push_int(0);
+        for entry in node {
+            let (ms, seq) = parse_id(&entry.id);
+            let fields: Vec<&Vec<u8>> = entry.values.iter().step_by(2).collect();
+            let n = fields.len() as i64;
+            let same = fields == master_fields;
+            lp.push_int(if same { STREAM_ITEM_FLAG_SAMEFIELDS } else { 0 });
+            lp.push_int(ms.wrapping_sub(master_ms) as i64);
+            lp.push_int(seq.wrapping_sub(master_seq) as i64);
+            if same {
+                for v in entry.values.iter().skip(1).step_by(2) {
+                    lp.push_str(v);
+                }
+                lp.push_int(n + 3);
+            } else {
+                lp.push_int(n);
+                for v in &entry.values {
+                    lp.push_str(v);
+                }
+                lp.push_int(2 * n + 4);
+            }
+        }
+        write_string(out, &raw_id(&master.id));
+        write_string(out, &lp.finish());
+    }
+
+    let last_entry = stream.last_id();
+    let last_id = if !stream.last_allocated_id.is_empty()
+        && Stream::cmp_ids(&stream.last_allocated_id, last_entry).is_gt()
+    {
+        stream.last_allocated_id.as_str()
+    } else {
+        last_entry
+    };
+    let first_id = stream.entries.first().map_or("0-0", |e| e.id.as_str());
+    write_len(out, stream.entries.len() as u64);
+    for id in [last_id, first_id, "0-0"] {
+        let (ms, seq) = parse_id(id);
+        write_len(out, ms);
+        write_len(out, seq);
+    }
+    write_len(out, stream.entries.len() as u64); // entries added
+
+    let mut groups: Vec<(&String, &StreamGroup)> = stream.groups.iter().collect();
+    groups.sort_by(|a, b| a.0.cmp(b.0));
+    write_len(out, groups.len() as u64);
+    for (name, group) in groups {
+        write_string(out, name.as_bytes());
+        let (ms, seq) = parse_id(&group.last_id);
+        write_len(out, ms);
+        write_len(out, seq);
+        let entries_read = if group.entries_read_known {
+            stream
+                .entries
+                .iter()
+                .filter(|e| Stream::cmp_ids(&e.id, &group.last_id).is_le())
+                .count() as u64
+        } else {
+            u64::MAX // -1: unknown
+        };
+        write_len(out, entries_read);
+
+        let mut pending: Vec<&PendingEntry> = group.pending.iter().collect();
+        pending.sort_by(|a, b| Stream::cmp_ids(&a.id, &b.id));
+        write_len(out, pending.len() as u64);
+        for p in &pending {
+            out.extend_from_slice(&raw_id(&p.id));
+            out.extend_from_slice(&unix_ms(p.last_delivery).to_le_bytes());
+            write_len(out, p.delivery_count.max(0) as u64);
+        }
+
+        let mut consumers: Vec<(&String, &StreamConsumer)> = group.consumers.iter().collect();
+        consumers.sort_by(|a, b| a.0.cmp(b.0));
+        write_len(out, consumers.len() as u64);
+        for (cname, consumer) in consumers {
+            write_string(out, cname.as_bytes());
+            out.extend_from_slice(&unix_ms(consumer.last_seen).to_le_bytes());
+            out.extend_from_slice(&unix_ms(consumer.last_success).to_le_bytes());
+            let owned: Vec<&&PendingEntry> =
+                pending.iter().filter(|p| &p.consumer == cname).collect();
+            write_len(out, owned.len() as u64);
+            for p in owned {
+                out.extend_from_slice(&raw_id(&p.id));
+            }
+        }
+    }
+}
+
+/// Builds a listpack, the compact list encoding used inside RDB values.
+struct ListpackWriter {
+    buf: Vec<u8>,
+    count: usize,
+}
+
+impl ListpackWriter {
+    fn new() -> Self {
+        ListpackWriter {
+            buf: vec![0; 6], // total bytes + element count, set by finish()
+            count: 0,
+        }
+    }
+
+    fn push_int(&mut self, v: i64) {
+        let start = self.buf.len();
+        if (0..=127).contains(&v) {
+            self.buf.push(v as u8);
+        } else if (-4096..=4095).contains(&v) {
+            let u = (v as u16) & 0x1fff;
+            self.buf.push(0xc0 | (u >> 8) as u8);
+            self.buf.push(u as u8);
+        } else if i16::try_from(v).is_ok() {
+            self.buf.push(0xf1);
+            self.buf.extend_from_slice(&(v as i16).to_le_bytes());
+        } else if (-(1 << 23)..(1 << 23)).contains(&v) {
+            self.buf.push(0xf2);
+            self.buf.extend_from_slice(&(v as i32).to_le_bytes()[..3]);
+        } else if i32::try_from(v).is_ok() {
+            self.buf.push(0xf3);
+            self.buf.extend_from_slice(&(v as i32).to_le_bytes());
+        } else {
+            self.buf.push(0xf4);
+            self.buf.extend_from_slice(&v.to_le_bytes());
+        }
+        self.push_backlen(start);
+    }
+
+    fn push_str(&mut self, s: &[u8]) {
+        let start = self.buf.len();
+        if s.len() < 64 {
+            self.buf.push(0x80 | s.len() as u8);
+        } else if s.len() < 4096 {
+            self.buf.push(0xe0 | (s.len() >> 8) as u8);
+            self.buf.push(s.len() as u8);
+        } else {
+            self.buf.push(0xf0);
+            self.buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        }
+        self.buf.extend_from_slice(s);
+        self.push_backlen(start);
+    }
+
+    /// Append the entry's "backlen": its size in 7 bit groups, most
+    /// significant first, all but the first with the high bit set.
+    fn push_backlen(&mut self, start: usize) {
+        let size = self.buf.len() - start;
+        let n = backlen_size(size);
+        for i in 0..n {
+            let mut b = ((size >> (7 * (n - 1 - i))) & 127) as u8;
+            if i > 0 {
+                b |= 128;
+            }
+            self.buf.push(b);
+        }
+        self.count += 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.buf.push(0xff);
+        let total = self.buf.len() as u32;
+        self.buf[..4].copy_from_slice(&total.to_le_bytes());
+        let count = self.count.min(u16::MAX as usize) as u16;
+        self.buf[4..6].copy_from_slice(&count.to_le_bytes());
+        self.buf
+    }
+}
+
+/// Number of bytes used by the backlen of a listpack entry of `size` bytes.
+fn backlen_size(size: usize) -> usize {
+    match size {
+        0..=127 => 1,
+        128..16383 => 2,
+        16383..2097151 => 3,
+        2097151..268435455 => 4,
+        _ => 5,
+    }
+}
+
+// ── Decoding ────────────────────────────────────────────────────────
+
+/// The contents of an RDB file.
+pub struct Snapshot {
+    /// All 16 databases.
+    pub dbs: Vec<RedisDB>,
+    /// Function library sources, in file order.
+    pub functions: Vec<String>,
+}
+
+/// A decoded value, before it's stored in a database.
+enum Value {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Set(HashSet<Vec<u8>>),
+    SortedSet(SortedSet),
+    /// Fields, and absolute expiry times in unix ms of fields with a TTL.
+    Hash(HashMap<Vec<u8>, Vec<u8>>, HashMap<Vec<u8>, u64>),
+    Stream(Stream),
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+    #[cfg(feature = "bloom")]
+    Sketch(Sketch),
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self.data.get(self.pos).ok_or_else(truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(n).ok_or_else(truncated)?;
+        let b = self.data.get(self.pos..end).ok_or_else(truncated)?;
+        self.pos = end;
+        Ok(b)
+    }
+
+    fn u64_le(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    fn len(&mut self) -> Result<u64> {
+        match decode_len(self.data, &mut self.pos).ok_or_else(truncated)? {
+            Len::Plain(n) => Ok(n),
+            Len::Encoded(_) => Err("unexpected encoded length".to_string()),
+        }
+    }
+
+    /// A length used to size a collection.
+    fn count(&mut self) -> Result<usize> {
+        let n = self.len()?;
+        // Every element takes at least one byte; reject absurd counts
+        // before allocating for them.
+        if n > (self.data.len() - self.pos) as u64 {
+            return Err(truncated());
+        }
+        Ok(n as usize)
+    }
+
+    fn string(&mut self) -> Result<Vec<u8>> {
+        match decode_len(self.data, &mut self.pos).ok_or_else(truncated)? {
+            Len::Plain(n) => Ok(self
+                .bytes(usize::try_from(n).map_err(|_| truncated())?)?
+                .to_vec()),
+            Len::Encoded(ENC_INT8) => Ok((self.byte()? as i8).to_string().into_bytes()),
+            Len::Encoded(ENC_INT16) => {
+                let v = i16::from_le_bytes(self.bytes(2)?.try_into().unwrap());
+                Ok(v.to_string().into_bytes())
+            }
+            Len::Encoded(ENC_INT32) => {
+                let v = i32::from_le_bytes(self.bytes(4)?.try_into().unwrap());
+                Ok(v.to_string().into_bytes())
+            }
+            Len::Encoded(ENC_LZF) => {
+                let clen = usize::try_from(self.len()?).map_err(|_| truncated())?;
+                let ulen = usize::try_from(self.len()?).unwrap_or(usize::MAX);
+                lzf_decompress(self.bytes(clen)?, ulen)
+            }
+            Len::Encoded(enc) => Err(format!("unknown string encoding {}", enc)),
+        }
+    }
+
+    fn utf8(&mut self) -> Result<String> {
+        Ok(String::from_utf8_lossy(&self.string()?).into_owned())
+    }
+
+    /// A sorted set score in the old text format (RDB_TYPE_ZSET).
+    fn string_double(&mut self) -> Result<f64> {
+        match self.byte()? {
+            253 => Ok(f64::NAN),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            n => parse_f64(self.bytes(n as usize)?),
+        }
+    }
+
+    fn stream_id(&mut self) -> Result<String> {
+        let ms = self.len()?;
+        let seq = self.len()?;
+        Ok(Stream::format_id(ms, seq))
+    }
+
+    fn raw_stream_id(&mut self) -> Result<String> {
+        let raw = self.bytes(16)?;
+        let ms = u64::from_be_bytes(raw[..8].try_into().unwrap());
+        let seq = u64::from_be_bytes(raw[8..].try_into().unwrap());
+        Ok(Stream::format_id(ms, seq))
+    }
+}
+
+fn parse_f64(b: &[u8]) -> Result<f64> {
+    let s = String::from_utf8_lossy(b);
+    match s.as_ref() {
+        "inf" | "+inf" => Ok(f64::INFINITY),
+        "-inf" => Ok(f64::NEG_INFINITY),
+        _ => s.parse().map_err(|_| format!("invalid score '{}'", s)),
+    }
+}
+
+fn parse_i64(b: &[u8]) -> Result<i64> {
+    String::from_utf8_lossy(b)
+        .parse()
+        .map_err(|_| "invalid integer in listpack".to_string())
+}
+
+fn lossy(b: Vec<u8>) -> String {
+    String::from_utf8(b).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+/// Parse an RDB file. Keys which expired before `now` are skipped.
+pub fn decode(data: &[u8], now: SystemTime) -> Result<Snapshot> {
+    if data.len() < 9 || &data[..5] != b"REDIS" {
+        return Err("wrong signature trying to load DB from file".to_string());
+    }
+    let version: u16 = std::str::from_utf8(&data[5..9])
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| "wrong signature trying to load DB from file".to_string())?;
+    if !(MIN_RDB_VERSION..=MAX_RDB_VERSION).contains(&version) {
+        return Err(format!("can't handle RDB format version {}", version));
+    }
+
+    let now = unix_ms(now);
+    let mut dbs: Vec<RedisDB> = (0..16).map(|_| RedisDB::new()).collect();
+    let mut functions = Vec::new();
+    let mut r = Reader::new(data);
+    r.pos = 9;
+    let mut db_idx = 0usize;
+    let mut expire_at: Option<u64> = None;
+    let mut idle: Option<u64> = None;
+    loop {
+        let op = r.byte()?;
+        match op {
+            OPCODE_EOF => break,
+            OPCODE_SELECTDB => {
+                db_idx = r.len()? as usize;
+                if db_idx >= dbs.len() {
+                    return Err(format!("DB index {} out of range", db_idx));
+                }
+            }
+            OPCODE_RESIZEDB => {
+                r.len()?;
+                r.len()?;
+            }
+            OPCODE_AUX => {
+                r.string()?;
+                r.string()?;
+            }
+            OPCODE_EXPIRETIME_MS => expire_at = Some(r.u64_le()?),
+            OPCODE_EXPIRETIME => {
+                let secs = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap());
+                expire_at = Some(secs as u64 * 1000);
+            }
+            OPCODE_IDLE => idle = Some(r.len()?),
+            OPCODE_FREQ => {
+                r.byte()?;
+            }
+            OPCODE_SLOT_INFO => {
+                r.len()?;
+                r.len()?;
+                r.len()?;
+            }
+            OPCODE_FUNCTION2 => functions.push(lossy(r.string()?)),
+            OPCODE_FUNCTION_PRE_GA | OPCODE_MODULE_AUX => {
+                return Err("modules and pre-GA functions are not supported".to_string());
+            }
+            obj_type => {
+                let key = r.string()?;
+                let value = read_object(&mut r, obj_type)?;
+                let expire = expire_at.take();
+                let idle = idle.take();
+                if expire.is_some_and(|at| at <= now) {
+                    continue;
+                }
+                let db = &mut dbs[db_idx];
+                if !insert_value(db, &key, value, now) {
+                    continue;
+                }
+                if let Some(at) = expire {
+                    db.ttl.insert(key.clone(), Duration::from_millis(at - now));
+                }
+                if let Some(secs) = idle {
+                    let seen = from_unix_ms(now).checked_sub(Duration::from_secs(secs));
+                    db.lru.insert(key, seen.unwrap_or(UNIX_EPOCH));
+                }
+            }
+        }
+    }
+
+    let body_len = r.pos;
+    if data.len() >= body_len + 8 {
+        let crc = u64::from_le_bytes(data[body_len..body_len + 8].try_into().unwrap());
+        // A zero checksum means the file was written with checksums off.
+        if crc != 0 && crc != crc64(&data[..body_len]) {
+            return Err("wrong RDB checksum".to_string());
+        }
+    }
+
+    Ok(Snapshot { dbs, functions })
+}
+
+/// Store a decoded value. Returns false if nothing was stored, e.g. a hash
+/// whose fields all expired.
+fn insert_value(db: &mut RedisDB, key: &[u8], value: Value, now: u64) -> bool {
+    let key_type = match value {
+        Value::String(s) => match HyperLogLog::decode(&s) {
+            Some(hll) => {
+                db.hll_keys.insert(key.to_owned(), hll);
+                KeyType::HyperLogLog
+            }
+            None => {
+                db.string_keys.insert(key.to_owned(), s);
+                KeyType::String
+            }
+        },
+        Value::List(list) => {
+            db.list_keys.insert(key.to_owned(), list);
+            KeyType::List
+        }
+        Value::Set(set) => {
+            db.set_keys.insert(key.to_owned(), set);
+            KeyType::Set
+        }
+        Value::SortedSet(ss) => {
+            db.sorted_set_keys.insert(key.to_owned(), ss);
+            KeyType::SortedSet
+        }
+        Value::Hash(mut hash, expires) => {
+            let mut ttls = HashMap::new();
+            for (field, at) in expires {
+                if at <= now {
+                    hash.remove(&field);
+                } else if hash.contains_key(&field) {
+                    ttls.insert(field, Duration::from_millis(at - now));
+                }
+            }
+            if hash.is_empty() {
+                return false;
+            }
+            if !ttls.is_empty() {
+                db.hash_field_ttls.insert(key.to_owned(), ttls);
+            }
+            db.hash_keys.insert(key.to_owned(), hash);
+            KeyType::Hash
+        }
+        Value::Stream(stream) => {
+            db.stream_keys.insert(key.to_owned(), stream);
+            KeyType::Stream
+        }
+        #[cfg(feature = "json")]
+        Value::Json(doc) => {
+            db.json_keys.insert(key.to_owned(), doc);
+            KeyType::Json
+        }
+        #[cfg(feature = "bloom")]
+        Value::Sketch(sketch) => {
+            let key_type = sketch.key_type();
+            db.bloom_keys.insert(key.to_owned(), sketch);
+            key_type
+        }
+    };
+    db.keys.insert(key.to_owned(), key_type);
+    db.resized(key);
+    true
+}
+
+fn read_object(r: &mut Reader, obj_type: u8) -> Result<Value> {
+    Ok(match obj_type {
+        TYPE_STRING => Value::String(r.string()?),
+        TYPE_LIST => {
+            let n = r.count()?;
+            let mut list = VecDeque::with_capacity(n);
+            for _ in 0..n {
+                list.push_back(r.string()?);
+            }
+            Value::List(list)
+        }
+        TYPE_SET => {
+            let n = r.count()?;
+            let mut set = HashSet::with_capacity(n);
+            for _ in 0..n {
+                set.insert(r.string()?);
+            }
+            Value::Set(set)
+        }
+        TYPE_ZSET | TYPE_ZSET_2 => {
+            let n = r.count()?;
+            let mut ss = SortedSet::new();
+            for _ in 0..n {
+                let member = r.string()?;
+                let score = if obj_type == TYPE_ZSET_2 {
+                    f64::from_le_bytes(r.bytes(8)?.try_into().unwrap())
+                } else {
+                    r.string_double()?
+                };
+                ss.set(score, &member);
+            }
+            Value::SortedSet(ss)
+        }
+        TYPE_HASH => {
+            let n = r.count()?;
+            let mut hash = HashMap::with_capacity(n);
+            for _ in 0..n {
+                let field = r.string()?;
+                hash.insert(field, r.string()?);
+            }
+            Value::Hash(hash, HashMap::new())
+        }
+        TYPE_HASH_METADATA => {
+            let min_expire = r.u64_le()?;
+            let n = r.count()?;
+            let mut hash = HashMap::with_capacity(n);
+            let mut expires = HashMap::new();
+            for _ in 0..n {
+                let ttl = r.len()?;
+                let field = r.string()?;
+                if ttl != 0 {
+                    let at = min_expire
+                        .checked_add(ttl - 1)
+                        .ok_or("invalid hash field TTL")?;
+                    expires.insert(field.clone(), at);
+                }
+                hash.insert(field, r.string()?);
+            }
+            Value::Hash(hash, expires)
+        }
+        TYPE_LIST_ZIPLIST => Value::List(ziplist_entries(&r.string()?)?.into()),
+        TYPE_LIST_QUICKLIST | TYPE_LIST_QUICKLIST_2 => {
+            let nodes = r.count()?;
+            let mut list = VecDeque::new();
+            for _ in 0..nodes {
+                let container = if obj_type == TYPE_LIST_QUICKLIST_2 {
+                    r.len()?
+                } else {
+                    QUICKLIST_NODE_PACKED
+                };
+                let node = r.string()?;
+                match (obj_type, container) {
+                    (_, QUICKLIST_NODE_PLAIN) => list.push_back(node),
+                    (TYPE_LIST_QUICKLIST, _) => list.extend(ziplist_entries(&node)?),
+                    (_, QUICKLIST_NODE_PACKED) => list.extend(listpack_entries(&node)?),
+                    _ => return Err(format!("unknown quicklist container {}", container)),
+                }
+            }
+            Value::List(list)
+        }
+        TYPE_SET_INTSET => Value::Set(
+            intset_entries(&r.string()?)?
+                .into_iter()
+                .map(|v| v.to_string().into_bytes())
+                .collect(),
+        ),
+        TYPE_SET_LISTPACK => Value::Set(listpack_entries(&r.string()?)?.into_iter().collect()),
+        TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+            let blob = r.string()?;
+            let items = if obj_type == TYPE_ZSET_ZIPLIST {
+                ziplist_entries(&blob)?
+            } else {
+                listpack_entries(&blob)?
+            };
+            let mut ss = SortedSet::new();
+            for pair in items.chunks_exact(2) {
+                ss.set(parse_f64(&pair[1])?, &pair[0]);
+            }
+            Value::SortedSet(ss)
+        }
+        TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK => {
+            let blob = r.string()?;
+            let items = if obj_type == TYPE_HASH_ZIPLIST {
+                ziplist_entries(&blob)?
+            } else {
+                listpack_entries(&blob)?
+            };
+            let mut hash = HashMap::new();
+            let mut items = items.into_iter();
+            while let (Some(f), Some(v)) = (items.next(), items.next()) {
+                hash.insert(f, v);
+            }
+            Value::Hash(hash, HashMap::new())
+        }
+        TYPE_HASH_LISTPACK_EX => {
+            r.u64_le()?; // earliest field expiry
+            let mut hash = HashMap::new();
+            let mut expires = HashMap::new();
+            for triple in listpack_entries(&r.string()?)?.chunks_exact(3) {
+                let field = triple[0].clone();
+                let at = parse_i64(&triple[2])?;
+                if at > 0 {
+                    expires.insert(field.clone(), at as u64);
+                }
+                hash.insert(field, triple[1].clone());
+            }
+            Value::Hash(hash, expires)
+        }
+        TYPE_STREAM_LISTPACKS | TYPE_STREAM_LISTPACKS_2 | TYPE_STREAM_LISTPACKS_3 => {
+            Value::Stream(read_stream(r, obj_type)?)
+        }
+        #[cfg(any(feature = "json", feature = "bloom"))]
+        TYPE_MODULE_2 => read_module(r)?,
+        #[cfg(not(any(feature = "json", feature = "bloom")))]
+        TYPE_MODULE_2 => {
+            return Err("module data types are not supported".to_string());
+        }
+        TYPE_MODULE_PRE_GA => {
+            return Err("module data types are not supported".to_string());
+        }
+        _ => return Err(format!("unknown RDB object type {}", obj_type)),
+    })
+}
+
+/// Read a module value. Only RedisJSON documents, and RedisBloom values
+/// saved by miniredis-rs, are supported.
+#[cfg(any(feature = "json", feature = "bloom"))]
+fn read_module(r: &mut Reader) -> Result<Value> {
+    let id = r.len()?;
+    #[cfg(feature = "json")]
+    if id >> 10 == module_id(JSON_MODULE_TYPE, 0) >> 10 {
+        let payload = read_module_string(r, "JSON")?;
+        let doc = serde_json::from_slice(&payload).map_err(|e| e.to_string())?;
+        return Ok(Value::Json(doc));
+    }
+    #[cfg(feature = "bloom")]
+    for key_type in [
+        KeyType::Bloom,
+        KeyType::Cuckoo,
+        KeyType::CountMinSketch,
+        KeyType::TopK,
+    ] {
+        if id >> 10 != module_id(key_type.as_str(), 0) >> 10 {
+            continue;
+        }
+        if id & 1023 != BLOOM_ENCODING_VERSION {
+            return Err("values saved by RedisBloom are not supported".to_string());
+        }
+        let payload = read_module_string(r, "RedisBloom")?;
+        return match Sketch::decode(&payload) {
+            Some(sketch) if sketch.key_type() == key_type => Ok(Value::Sketch(sketch)),
+            _ => Err("invalid RedisBloom module value".to_string()),
+        };
+    }
+    Err("module data types are not supported".to_string())
+}
+
+/// The payload of a module value saved as a single string.
+#[cfg(any(feature = "json", feature = "bloom"))]
+fn read_module_string(r: &mut Reader, module: &str) -> Result<Vec<u8>> {
+    if r.len()? != MODULE_OPCODE_STRING {
+        return Err(format!("invalid {} module value", module));
+    }
+    let payload = r.string()?;
+    if r.len()? != MODULE_OPCODE_EOF {
+        return Err(format!("invalid {} module value", module));
+    }
+    Ok(payload)
+}
+
+fn read_stream(r: &mut Reader, obj_type: u8) -> Result<Stream> {
+    let mut stream = Stream::new();
+
+    let nodes = r.count()?;
+    for _ in 0..nodes {
+        let master = r.string()?;
+        if master.len() != 16 {
+            return Err("invalid stream node key".to_string());
+        }
+        let master_ms = u64::from_be_bytes(master[..8].try_into().unwrap());
+        let master_seq = u64::from_be_bytes(master[8..].try_into().unwrap());
+        let items = listpack_entries(&r.string()?)?;
+        let mut items = items.into_iter();
+        let mut next = || {
+            items
+                .next()
+                .ok_or_else(|| "invalid stream listpack".to_string())
+        };
+
+        let count = parse_i64(&next()?)?;
+        let deleted = parse_i64(&next()?)?;
+        let num_fields = parse_i64(&next()?)? as usize;
+        let mut master_fields = Vec::with_capacity(num_fields);
+        for _ in 0..num_fields {
+            master_fields.push(next()?);
+        }
+        next()?; // master entry terminator
+
+        for _ in 0..count + deleted {
+            let flags = parse_i64(&next()?)?;
+            let ms = master_ms.wrapping_add(parse_i64(&next()?)? as u64);
+            let seq = master_seq.wrapping_add(parse_i64(&next()?)? as u64);
+            let mut values = Vec::new();
+            if flags & STREAM_ITEM_FLAG_SAMEFIELDS != 0 {
+                for f in &master_fields {
+                    values.push(f.clone());
+                    values.push(next()?);
+                }
+            } else {
+                let n = parse_i64(&next()?)?;
+                for _ in 0..2 * n {
+                    values.push(next()?);
+                }
+            }
+            next()?; // lp-count
+            if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+                stream.entries.push(StreamEntry {
+                    id: Stream::format_id(ms, seq),
+                    values,
+                });
+            }
+        }
+    }
+
+    r.len()?; // length
+    let last_id = r.stream_id()?;
+    if obj_type >= TYPE_STREAM_LISTPACKS_2 {
+        r.stream_id()?; // first id
+        r.stream_id()?; // max deleted id
+        r.len()?; // entries added
+    }
+    if last_id != "0-0" {
+        stream.last_allocated_id = last_id;
+    }
+
+    let groups = r.count()?;
+    for _ in 0..groups {
+        let name = r.utf8()?;
+        let last_id = r.stream_id()?;
+        let entries_read_known = if obj_type >= TYPE_STREAM_LISTPACKS_2 {
+            r.len()? != u64::MAX
+        } else {
+            false
+        };
+
+        let mut pending = Vec::new();
+        for _ in 0..r.count()? {
+            let id = r.raw_stream_id()?;
+            let last_delivery = from_unix_ms(r.u64_le()?);
+            let delivery_count = r.len()? as i64;
+            pending.push(PendingEntry {
+                id,
+                consumer: String::new(),
+                delivery_count,
+                last_delivery,
+            });
+        }
+
+        let mut consumers = HashMap::new();
+        for _ in 0..r.count()? {
+            let cname = r.utf8()?;
+            let last_seen = from_unix_ms(r.u64_le()?);
+            let last_success = if obj_type >= TYPE_STREAM_LISTPACKS_3 {
+                from_unix_ms(r.u64_le()?)
+            } else {
+                last_seen
+            };
+            let owned = r.count()?;
+            for _ in 0..owned {
+                let id = r.raw_stream_id()?;
+                match pending.iter_mut().find(|p| p.id == id) {
+                    Some(p) => p.consumer = cname.clone(),
+                    None => return Err("consumer PEL entry not in group PEL".to_string()),
+                }
+            }
+            consumers.insert(
+                cname,
+                StreamConsumer {
+                    num_pending: owned as i64,
+                    last_seen,
+                    last_success,
+                },
+            );
+        }
+
+        stream.groups.insert(
+            name,
+            StreamGroup {
+                last_id,
+                pending,
+                consumers,
+                entries_read_known,
+            },
+        );
+    }
+    Ok(stream)
+}
+
+// ── Compact encodings ───────────────────────────────────────────────
+
+/// Decompress LZF data (as produced by Redis' bundled liblzf).
+fn lzf_decompress(input: &[u8], ulen: usize) -> Result<Vec<u8>> {
+    let corrupt = || "invalid LZF compressed string".to_string();
+    // The length comes from the payload; don't allocate more than the
+    // input can make.
+    if ulen > input.len().saturating_mul(LZF_MAX_EXPANSION) {
+        return Err(corrupt());
+    }
+    let mut out = Vec::with_capacity(ulen);
+    let mut ip = 0;
+    while ip < input.len() {
+        let ctrl = input[ip] as usize;
+        ip += 1;
+        if ctrl < 32 {
+            // Literal run of ctrl + 1 bytes.
+            let lit = input.get(ip..ip + ctrl + 1).ok_or_else(corrupt)?;
+            out.extend_from_slice(lit);
+            ip += ctrl + 1;
+        } else {
+            // Back reference.
+            let mut len = ctrl >> 5;
+            if len == 7 {
+                len += *input.get(ip).ok_or_else(corrupt)? as usize;
+                ip += 1;
+            }
+            let offset = ((ctrl & 0x1f) << 8) + *input.get(ip).ok_or_else(corrupt)? as usize + 1;
+            ip += 1;
+            if offset > out.len() {
+                return Err(corrupt());
+            }
+            let start = out.len() - offset;
+            for i in 0..len + 2 {
+                out.push(out[start + i]);
+            }
+        }
+    }
+    if out.len() != ulen {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+/// Elements of a ziplist, with integers formatted as decimal strings.
+fn ziplist_entries(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let corrupt = || "invalid ziplist".to_string();
+    let mut r = Reader::new(data);
+    r.bytes(10).map_err(|_| corrupt())?; // zlbytes, zltail, zllen
+    let mut items = Vec::new();
+    loop {
+        let prevlen = r.byte().map_err(|_| corrupt())?;
+        if prevlen == 0xff {
+            break;
+        }
+        if prevlen == 0xfe {
+            r.bytes(4).map_err(|_| corrupt())?;
+        }
+        let enc = r.byte().map_err(|_| corrupt())?;
+        let item = match enc >> 6 {
+            0 => r.bytes((enc & 0x3f) as usize)?.to_vec(),
+            1 => {
+                let len = (((enc & 0x3f) as usize) << 8) | r.byte()? as usize;
+                r.bytes(len)?.to_vec()
+            }
+            2 => {
+                let len = u32::from_be_bytes(r.bytes(4)?.try_into().unwrap());
+                r.bytes(len as usize)?.to_vec()
+            }
+            _ => {
+                let v: i64 = match enc {
+                    0xc0 => i16::from_le_bytes(r.bytes(2)?.try_into().unwrap()) as i64,
+                    0xd0 => i32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as i64,
+                    0xe0 => i64::from_le_bytes(r.bytes(8)?.try_into().unwrap()),
+                    0xf0 => {
+                        let b = r.bytes(3)?;
+                        (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as i64
+                    }
+                    0xfe => r.byte()? as i8 as i64,
+                    0xf1..=0xfd => (enc & 0x0f) as i64 - 1,
+                    _ => return Err(corrupt()),
+                };
+                v.to_string().into_bytes()
+            }
+        };
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Elements of an intset.
+fn intset_entries(data: &[u8]) -> Result<Vec<i64>> {
+    let mut r = Reader::new(data);
+    let width = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as usize;
+    let len = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap()) as usize;
+    if ![2, 4, 8].contains(&width) || len * width != data.len() - 8 {
+        return Err("invalid intset".to_string());
+    }
+    (0..len)
+        .map(|_| {
+            let b = r.bytes(width)?;
+            Ok(match width {
+                2 => i16::from_le_bytes(b.try_into().unwrap()) as i64,
+                4 => i32::from_le_bytes(b.try_into().unwrap()) as i64,
+                _ => i64::from_le_bytes(b.try_into().unwrap()),
+            })
+        })
+        .collect()
+}
+
+/// Elements of a listpack, with integers formatted as decimal strings.
+fn listpack_entries(data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let corrupt = || "invalid listpack".to_string();
+    let mut r = Reader::new(data);
+    r.bytes(6).map_err(|_| corrupt())?; // total bytes, element count
+    let mut items = Vec::new();
+    loop {
+        let start = r.pos;
+        let enc = r.byte().map_err(|_| corrupt())?;
+        let item = if enc & 0x80 == 0 {
+            (enc as i64).to_string().into_bytes()
+        } else if enc & 0xc0 == 0x80 {
+            r.bytes((enc & 0x3f) as usize)?.to_vec()
+        } else if enc & 0xe0 == 0xc0 {
+            let u = (((enc & 0x1f) as i64) << 8) | r.byte()? as i64;
+            let v = if u >= 1 << 12 { u - (1 << 13) } else { u };
+            v.to_string().into_bytes()
+        } else if enc & 0xf0 == 0xe0 {
+            let len = (((enc & 0x0f) as usize) << 8) | r.byte()? as usize;
+            r.bytes(len)?.to_vec()
+        } else {
+            match enc {
+                0xf0 => {
+                    let len = u32::from_le_bytes(r.bytes(4)?.try_into().unwrap());
+                    r.bytes(len as usize)?.to_vec()
+                }
+                0xf1 => i16::from_le_bytes(r.bytes(2)?.try_into().unwrap())
+                    .to_string()
+                    .into_bytes(),
+                0xf2 => {
+                    let b = r.bytes(3)?;
+                    (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8)
+                        .to_string()
+                        .into_bytes()
+                }
+                0xf3 => i32::from_le_bytes(r.bytes(4)?.try_into().unwrap())
+                    .to_string()
+                    .into_bytes(),
+                0xf4 => i64::from_le_bytes(r.bytes(8)?.try_into().unwrap())
+                    .to_string()
+                    .into_bytes(),
+                0xff => break,
+                _ => return Err(corrupt()),
+            }
+        };
+        r.bytes(backlen_size(r.pos - start))
+            .map_err(|_| corrupt())?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+// ── DUMP payloads ───────────────────────────────────────────────────
+
+/// Serialize the value at `key` as DUMP does: the RDB object, then the
+/// RDB version and a CRC64 of everything before it.
+pub fn dump_value(db: &RedisDB, key: &[u8], now: SystemTime) -> Option<Vec<u8>> {
+    let (obj_type, body) = encode_object(db, key, unix_ms(now))?;
+    let version = if obj_type == TYPE_HASH_METADATA {
+        MAX_RDB_VERSION
+    } else {
+        RDB_VERSION
+    };
+    let mut out = vec![obj_type];
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&version.to_le_bytes());
+    let crc = crc64(&out);
+    out.extend_from_slice(&crc.to_le_bytes());
+    Some(out)
+}
+
+/// Whether `payload` ends in a DUMP footer with a version we can load and
+/// a matching checksum.
+pub fn is_dump_payload(payload: &[u8]) -> bool {
+    if payload.len() < 11 {
+        return false;
+    }
+    let (body, crc) = payload.split_at(payload.len() - 8);
+    let version = u16::from_le_bytes(body[body.len() - 2..].try_into().unwrap());
+    version <= MAX_RDB_VERSION && u64::from_le_bytes(crc.try_into().unwrap()) == crc64(body)
+}
+
+/// Store a DUMP payload at `key`, replacing what's there. Nothing changes
+/// if the payload can't be parsed. Returns false if nothing was stored
+/// because all fields of a hash had expired.
+pub fn restore_value(
+    db: &mut RedisDB,
+    key: &[u8],
+    payload: &[u8],
+    now: SystemTime,
+) -> Result<bool> {
+    let body = &payload[..payload.len() - 10];
+    let mut r = Reader::new(body);
+    let obj_type = r.byte()?;
+    let value = read_object(&mut r, obj_type)?;
+    if r.pos != body.len() {
+        return Err("trailing data after the object".to_string());
+    }
+    db.del(key);
+    Ok(insert_value(db, key, value, unix_ms(now)))
+}
+
+// ── Files ───────────────────────────────────────────────────────────
+
+/// Write an RDB file via a temporary file and a rename, so readers never
+/// see a partial file.
+pub fn write_file(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let tmp = path.with_file_name(format!(
+        "temp-{}-{}.rdb",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&tmp, data)?;
+    std::fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp);
+    })
+}
+
+// ── Loading ─────────────────────────────────────────────────────────
+
+/// Replace all databases and function libraries with the contents of an
+/// RDB file. Nothing changes if the file can't be parsed.
+pub fn load(dataset: &mut Dataset, data: &[u8]) -> Result<()> {
+    let snapshot = decode(data, dataset.now)?;
+
+    let mut libraries = HashMap::new();
+    for code in &snapshot.functions {
+        let lib = crate::cmd::functions::compile_library(code)?;
+        libraries.insert(lib.name.clone(), lib);
+    }
+    dataset.inner.functions = libraries;
+
+    for (old, mut new) in dataset.dbs.iter_mut().zip(snapshot.dbs) {
+        new.notify_flags = old.notify_flags;
+        new.tracking = old.tracking;
+        new.flushed = old.tracking;
+        // Bump versions of everything that was or now is there, so WATCH
+        // notices the reload.
+        new.key_version = std::mem::take(&mut old.key_version);
+        for key in old.keys.keys().chain(new.keys.keys()) {
+            *new.key_version.entry(key.clone()).or_insert(0) += 1;
+        }
+        **old = new;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_len_roundtrip() {
+        for n in [
+            0u64,
+            63,
+            64,
+            16383,
+            16384,
+            u32::MAX as u64,
+            u32::MAX as u64 + 1,
+        ] {
+            let mut buf = Vec::new();
+            write_len(&mut buf, n);
+            let mut pos = 0;
+            assert_eq!(read_len(&buf, &mut pos), Some(n as usize));
+            assert_eq!(pos, buf.len());
+        }
+    }
+
+    #[test]
+    fn test_dump_restore_value() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut db = RedisDB::new();
+        db.hash_set(b"h", &[(b"a".to_vec(), b"1".to_vec())], now);
+        db.set_hash_field_ttl(b"h", b"a", Duration::from_secs(10), now);
+
+        let payload = dump_value(&db, b"h", now).unwrap();
+        assert!(is_dump_payload(&payload));
+        assert_eq!(payload[payload.len() - 10], MAX_RDB_VERSION as u8);
+
+        let mut other = RedisDB::new();
+        assert_eq!(restore_value(&mut other, b"x", &payload, now), Ok(true));
+        assert_eq!(other.hash_get(b"x", b"a"), Some(&b"1".to_vec()));
+        assert_eq!(
+            other.hash_field_ttl(b"x", b"a"),
+            Some(Duration::from_secs(10))
+        );
+
+        // Everything expired by the time it's restored
+        let later = now + Duration::from_secs(20);
+        assert_eq!(restore_value(&mut other, b"y", &payload, later), Ok(false));
+        assert!(!other.keys.contains_key(b"y".as_slice()));
+
+        // A good footer on a broken body
+        let mut broken = payload.clone();
+        broken[0] = 200;
+        let crc_at = broken.len() - 8;
+        let crc = crc64(&broken[..crc_at]);
+        broken[crc_at..].copy_from_slice(&crc.to_le_bytes());
+        assert!(is_dump_payload(&broken));
+        assert!(restore_value(&mut other, b"z", &broken, now).is_err());
+
+        let mut corrupt = payload;
+        corrupt[1] ^= 1;
+        assert!(!is_dump_payload(&corrupt));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_dump_restore_json() {
+        let now = UNIX_EPOCH;
+        let mut db = RedisDB::new();
+        db.keys.insert(b"j".to_vec(), KeyType::Json);
+        db.json_keys
+            .insert(b"j".to_vec(), serde_json::json!({"a": [1, "x"]}));
+
+        let payload = dump_value(&db, b"j", now).unwrap();
+        assert_eq!(payload[0], TYPE_MODULE_2);
+        let mut other = RedisDB::new();
+        assert_eq!(restore_value(&mut other, b"x", &payload, now), Ok(true));
+        assert_eq!(other.key_type(b"x"), Some(KeyType::Json));
+        assert_eq!(other.json_keys.get(&b"x"[..]), db.json_keys.get(&b"j"[..]));
+
+        // Another module's type
+        let mut body = vec![TYPE_MODULE_2];
+        write_len(&mut body, module_id("ft_index0", 0));
+        assert!(read_object(&mut Reader::new(&body[1..]), TYPE_MODULE_2).is_err());
+    }
+
+    #[cfg(feature = "bloom")]
+    #[test]
+    fn test_dump_restore_bloom() {
+        use crate::bloom::{BloomFilter, TopK};
+
+        let now = UNIX_EPOCH;
+        let mut db = RedisDB::new();
+        let mut bf = BloomFilter::new(0.01, 100, Some(2));
+        bf.add(b"a").unwrap();
+        db.keys.insert(b"bf".to_vec(), KeyType::Bloom);
+        db.bloom_keys.insert(b"bf".to_vec(), Sketch::Bloom(bf));
+        db.keys.insert(b"tk".to_vec(), KeyType::TopK);
+        db.bloom_keys
+            .insert(b"tk".to_vec(), Sketch::TopK(TopK::new(3, 8, 7, 0.9)));
+
+        let mut other = RedisDB::new();
+        for (key, key_type) in [(&b"bf"[..], KeyType::Bloom), (&b"tk"[..], KeyType::TopK)] {
+            let payload = dump_value(&db, key, now).unwrap();
+            assert_eq!(payload[0], TYPE_MODULE_2);
+            assert_eq!(restore_value(&mut other, key, &payload, now), Ok(true));
+            assert_eq!(other.key_type(key), Some(key_type));
+            assert_eq!(other.bloom_keys.get(key), db.bloom_keys.get(key));
+        }
+
+        // Saved by RedisBloom itself
+        let mut body = vec![TYPE_MODULE_2];
+        write_len(&mut body, module_id("MBbloom--", 4));
+        assert!(read_object(&mut Reader::new(&body[1..]), TYPE_MODULE_2).is_err());
+
+        // A payload of another RedisBloom type
+        let mut body = Vec::new();
+        write_len(&mut body, module_id("CMSk-TYPE", BLOOM_ENCODING_VERSION));
+        write_len(&mut body, MODULE_OPCODE_STRING);
+        write_string(&mut body, &db.bloom_keys[&b"bf"[..]].encode());
+        write_len(&mut body, MODULE_OPCODE_EOF);
+        assert!(read_object(&mut Reader::new(&body), TYPE_MODULE_2).is_err());
+    }
+
+    #[test]
+    fn test_crc64() {
+        // The check value from the Redis sources.
+        assert_eq!(crc64(b"123456789"), 0xe9c6_d914_c4b8_d9ca);
+    }
+
+    #[test]
+    fn test_string_encodings() {
+        let mut r = Reader::new(&[0xc0, 0xfe, 0xc1, 0x39, 0x30, 0xc2, 0x87, 0xd6, 0x12, 0x00]);
+        assert_eq!(r.string().unwrap(), b"-2");
+        assert_eq!(r.string().unwrap(), b"12345");
+        assert_eq!(r.string().unwrap(), b"1234567");
+    }
+
+    #[test]
+    fn test_lzf() {
+        // 20 times "a": a one byte literal, then a 19 byte back reference.
+        let compressed = [0x00, b'a', 0xe0, 0x0a, 0x00];
+        assert_eq!(lzf_decompress(&compressed, 20).unwrap(), vec![b'a'; 20]);
+        assert!(lzf_decompress(&compressed, 21).is_err());
+        assert!(lzf_decompress(&[0x20, 0x05], 3).is_err());
+        // A length the input can't decompress to isn't allocated.
+        assert!(lzf_decompress(&compressed, usize::MAX).is_err());
+
+        let mut data = vec![0xc0 | ENC_LZF];
+        write_len(&mut data, compressed.len() as u64);
+        write_len(&mut data, u64::MAX);
+        data.extend_from_slice(&compressed);
+        assert!(Reader::new(&data).string().is_err());
+    }
+
+    #[test]
+    fn test_hash_field_ttl_overflow() {
+        let mut body = u64::MAX.to_le_bytes().to_vec();
+        write_len(&mut body, 1);
+        write_len(&mut body, 2);
+        write_string(&mut body, b"field");
+        write_string(&mut body, b"value");
+        let err = read_object(&mut Reader::new(&body), TYPE_HASH_METADATA).err();
+        assert_eq!(err.as_deref(), Some("invalid hash field TTL"));
+    }
+
+    #[test]
+    fn test_ziplist() {
+        // RPUSH l a 1 300 -5 hello, as a ziplist.
+        let data = [
+            0x1e, 0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x05, 0x00, // header
+            0x00, 0x01, b'a', // "a"
+            0x03, 0xf2, // 1
+            0x02, 0xc0, 0x2c, 0x01, // 300
+            0x04, 0xfe, 0xfb, // -5
+            0x03, 0x05, b'h', b'e', b'l', b'l', b'o', // "hello"
+            0xff,
+        ];
+        let items = ziplist_entries(&data).unwrap();
+        let items: Vec<&[u8]> = items.iter().map(|v| v.as_slice()).collect();
+        assert_eq!(items, vec![&b"a"[..], b"1", b"300", b"-5", b"hello"]);
+    }
+
+    #[test]
+    fn test_intset() {
+        let data = [
+            0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0xff, 0xff, 0x01, 0x00, 0x00, 0x01,
+        ];
+        assert_eq!(intset_entries(&data).unwrap(), vec![-1, 1, 256]);
+        assert!(intset_entries(&data[..12]).is_err());
+    }
+
+    #[test]
+    fn test_listpack_roundtrip() {
+        let long = vec![b'x'; 5000];
+        let ints = [
+            0i64,
+            127,
+            128,
+            -1,
+            4095,
+            -4096,
+            30000,
+            -30000,
+            1 << 20,
+            1 << 30,
+            1 << 40,
+        ];
+        let mut lp = ListpackWriter::new();
+        lp.push_str(b"foo");
+        lp.push_str(&[b'y'; 100]);
+        lp.push_str(&long);
+        for v in ints {
+            lp.push_int(v);
+        }
+        let data = lp.finish();
+        assert_eq!(
+            u32::from_le_bytes(data[..4].try_into().unwrap()) as usize,
+            data.len()
+        );
+        assert_eq!(u16::from_le_bytes([data[4], data[5]]), 14);
+
+        let items = listpack_entries(&data).unwrap();
+        assert_eq!(items[0], b"foo");
+        assert_eq!(items[1], vec![b'y'; 100]);
+        assert_eq!(items[2], long);
+        for (item, v) in items[3..].iter().zip(ints) {
+            assert_eq!(item, v.to_string().as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_listpack_redis() {
+        // SADD s a 1 -100, as a set listpack.
+        let data = [
+            0x0f, 0x00, 0x00, 0x00, 0x03, 0x00, // header
+            0x81, b'a', 0x02, // "a"
+            0x01, 0x01, // 1
+            0xdf, 0x9c, 0x02, // -100
+            0xff,
+        ];
+        let items = listpack_entries(&data).unwrap();
+        let items: Vec<&[u8]> = items.iter().map(|v| v.as_slice()).collect();
+        assert_eq!(items, vec![&b"a"[..], b"1", b"-100"]);
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let now = SystemTime::now();
+        assert!(decode(b"", now).is_err());
+        assert!(decode(b"RUBBISH01", now).is_err());
+        assert!(decode(b"REDIS0006\xff", now).is_err());
+        assert!(decode(b"REDIS0011\xfe", now).is_err());
+
+        let mut data = b"REDIS0011\xff".to_vec();
+        data.extend_from_slice(&crc64(&data).to_le_bytes());
+        assert!(decode(&data, now).is_ok());
+        let n = data.len();
+        data[n - 1] ^= 1;
+        assert_eq!(decode(&data, now).err().unwrap(), "wrong RDB checksum");
+    }
+}
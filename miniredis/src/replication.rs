@@ -0,0 +1,824 @@
+//! Replication between servers in the same process.
+//!
+//! `REPLICAOF host port` makes a server a replica of another [`Miniredis`]
+//! running in this process. The replica first loads an RDB snapshot of the
+//! master (the full sync). From then on the master sends it every command
+//! that changed its dataset, the way Redis propagates commands. Commands
+//! with random effects are rewritten first: SPOP becomes SREM, BLPOP
+//! becomes LPOP, XADD gets the ID the master picked, and so on. Replicas
+//! acknowledge what they applied, which is what WAIT counts.
+//!
+//! Changes made through the direct API, other than `fast_forward()`, aren't
+//! propagated. They do end up in the snapshot of a full sync.
+//!
+//! [`Miniredis`]: crate::Miniredis
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
+use std::time::Duration;
+
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::connection::ConnCtx;
+use crate::db::{Isolation, KeyspaceGuard, SharedState};
+use crate::dispatch::dispatch;
+use crate::frame::Frame;
+
+/// How long a replica waits before looking for its master again.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Running servers by address, for replicas to find their master.
+static SERVERS: LazyLock<Mutex<HashMap<SocketAddr, Weak<SharedState>>>> =
+    LazyLock::new(Default::default);
+
+/// Make a server findable for replicas. A replica that was restarted
+/// connects to its master again.
+pub fn register(addr: SocketAddr, state: &Arc<SharedState>) {
+    SERVERS.lock().unwrap().insert(addr, Arc::downgrade(state));
+    let master = {
+        let mut repl = state.replication.lock().unwrap();
+        repl.addr = Some(addr);
+        repl.master.take().map(|m| (m.host, m.port))
+    };
+    if let Some((host, port)) = master {
+        replicate(state, &host, port);
+    }
+}
+
+/// Forget a server that shut down.
+pub fn unregister(addr: SocketAddr) {
+    SERVERS.lock().unwrap().remove(&addr);
+}
+
+/// Commands for a replica to apply, and the master's replication offset
+/// after them.
+type Message = (Arc<Vec<Vec<Vec<u8>>>>, u64);
+
+/// The replication state of a server.
+#[derive(Debug)]
+pub struct Replication {
+    /// The address the server listens on.
+    addr: Option<SocketAddr>,
+    /// ID of the replication stream this server serves or follows.
+    replid: String,
+    /// The ID before the last promotion, and the offset it was valid to.
+    replid2: String,
+    second_offset: i64,
+    /// Bytes of commands in the replication stream so far.
+    offset: u64,
+    /// Database the last command sent to the replicas ran in.
+    last_db: Option<usize>,
+    replicas: Vec<ReplicaLink>,
+    next_link_id: u64,
+    /// Set while this server is a replica.
+    master: Option<MasterLink>,
+}
+
+#[derive(Debug)]
+struct ReplicaLink {
+    id: u64,
+    /// The address the replica listens on.
+    addr: Option<SocketAddr>,
+    tx: mpsc::UnboundedSender<Message>,
+    /// Offset up to which the replica applied the stream.
+    ack: u64,
+}
+
+#[derive(Debug)]
+struct MasterLink {
+    id: u64,
+    host: String,
+    port: u16,
+    /// Whether the full sync is done and commands stream in.
+    up: bool,
+    /// Dropped to stop the link task.
+    _stop: oneshot::Sender<()>,
+}
+
+impl Default for Replication {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Replication {
+    pub fn new() -> Self {
+        Replication {
+            addr: None,
+            replid: new_replid(),
+            replid2: "0".repeat(40),
+            second_offset: -1,
+            offset: 0,
+            last_db: None,
+            replicas: Vec::new(),
+            next_link_id: 0,
+            master: None,
+        }
+    }
+
+    /// Whether this server is a replica.
+    pub fn is_replica(&self) -> bool {
+        self.master.is_some()
+    }
+
+    /// The replication offset: how much of the stream the server sent, or
+    /// applied as a replica.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+fn new_replid() -> String {
+    rand::random::<[u8; 20]>()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+// ── Roles ───────────────────────────────────────────────────────────
+
+/// Become a replica of `host:port`, replacing the current master link.
+/// Returns false if the server already replicates from there.
+pub fn replicate(state: &Arc<SharedState>, host: &str, port: u16) -> bool {
+    let mut repl = state.replication.lock().unwrap();
+    if repl
+        .master
+        .as_ref()
+        .is_some_and(|m| m.host == host && m.port == port)
+    {
+        return false;
+    }
+    repl.next_link_id += 1;
+    let id = repl.next_link_id;
+    let (stop_tx, stop_rx) = oneshot::channel();
+    repl.master = Some(MasterLink {
+        id,
+        host: host.to_string(),
+        port,
+        up: false,
+        _stop: stop_tx,
+    });
+    tokio::spawn(run_link(
+        Arc::clone(state),
+        id,
+        host.to_string(),
+        port,
+        stop_rx,
+    ));
+    true
+}
+
+/// Stop replicating and become a master (REPLICAOF NO ONE). The data
+/// stays, and the replication stream gets a new ID.
+pub fn promote(state: &SharedState) {
+    let mut repl = state.replication.lock().unwrap();
+    if repl.master.take().is_none() {
+        return;
+    }
+    repl.replid2 = std::mem::replace(&mut repl.replid, new_replid());
+    repl.second_offset = repl.offset as i64 + 1;
+}
+
+/// Whether `ctx` may not write: the server is a read-only replica, and
+/// `ctx` isn't its link to the master.
+pub fn rejects_writes(state: &SharedState, ctx: &ConnCtx) -> bool {
+    !ctx.master_link
+        && state.replication.lock().unwrap().is_replica()
+        && state.lock().replica_read_only
+}
+
+// ── Propagation ─────────────────────────────────────────────────────
+
+/// Collects the commands a client runs that change the dataset, and sends
+/// them to the replicas. Commands that may write run while a `Propagator`
+/// exists, so they reach the replicas in the order they ran.
+pub struct Propagator<'a> {
+    state: &'a SharedState,
+    keyspace: KeyspaceGuard<'a>,
+    db: usize,
+    dirty: u64,
+    commands: Vec<Vec<Vec<u8>>>,
+}
+
+impl<'a> Propagator<'a> {
+    /// Start running commands in database `db`. Only `exclusive` ones may
+    /// change other databases, or run several commands atomically.
+    pub fn new(state: &'a SharedState, db: usize, exclusive: bool) -> Self {
+        let keyspace = state.lock_keyspace(if exclusive {
+            Isolation::Exclusive
+        } else {
+            Isolation::Write(db)
+        });
+        let mut propagator = Propagator {
+            state,
+            keyspace,
+            db,
+            dirty: 0,
+            commands: Vec::new(),
+        };
+        propagator.dirty = propagator.dirty();
+        propagator
+    }
+
+    /// Number of changes made to the databases commands may write to.
+    fn dirty(&self) -> u64 {
+        if self.keyspace.is_exclusive() {
+            self.state.dirty()
+        } else {
+            self.state.db(self.db).dirty
+        }
+    }
+
+    /// Record a command (name included) that ran with `response`. It goes
+    /// to the replicas if it changed the dataset.
+    pub fn record(&mut self, args: &[Vec<u8>], response: &Frame) {
+        let dirty = self.dirty();
+        let changed = std::mem::replace(&mut self.dirty, dirty) != dirty;
+        if changed || always_propagated(args, response) {
+            self.commands.push(rewrite(self.state, args, response));
+        }
+    }
+
+    /// Record a blocking command, which changed the dataset if it was
+    /// served.
+    pub fn record_served(&mut self, args: &[Vec<u8>], response: &Frame) {
+        if !matches!(response, Frame::Null | Frame::NullArray | Frame::Error(_)) {
+            self.commands.push(rewrite(self.state, args, response));
+        }
+    }
+
+    /// Send the recorded commands to the replicas, in a MULTI/EXEC block
+    /// for a transaction. `db` is the database selected afterwards.
+    /// Returns the replication offset after the commands, or None if
+    /// nothing changed.
+    pub fn finish(self, transaction: bool, db: usize) -> Option<u64> {
+        let mut commands = self.commands;
+        if commands
+            .iter()
+            .all(|c| c[0].eq_ignore_ascii_case(b"SELECT"))
+        {
+            return None;
+        }
+        if transaction {
+            commands.insert(0, vec![b"MULTI".to_vec()]);
+            commands.push(vec![b"EXEC".to_vec()]);
+        }
+        Some(feed(self.state, self.db, commands, db))
+    }
+}
+
+/// Commands sent to the replicas even when they don't change any key.
+fn always_propagated(args: &[Vec<u8>], response: &Frame) -> bool {
+    if matches!(response, Frame::Error(_)) {
+        return false;
+    }
+    match String::from_utf8_lossy(&args[0]).to_uppercase().as_str() {
+        "PUBLISH" | "SPUBLISH" | "SWAPDB" | "SELECT" | "MINIREDIS.FASTFORWARD" => true,
+        "FUNCTION" => args.get(1).is_some_and(|sub| {
+            ["LOAD", "DELETE", "FLUSH", "RESTORE"]
+                .iter()
+                .any(|s| sub.eq_ignore_ascii_case(s.as_bytes()))
+        }),
+        _ => false,
+    }
+}
+
+/// The command to send to the replicas for `args`: the command itself, or
+/// for commands with random effects one that has the same effect.
+fn rewrite(state: &SharedState, args: &[Vec<u8>], response: &Frame) -> Vec<Vec<u8>> {
+    let name = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let cmd = |name: &str, rest: &[&[u8]]| {
+        std::iter::once(name.as_bytes())
+            .chain(rest.iter().copied())
+            .map(|a| a.to_vec())
+            .collect::<Vec<_>>()
+    };
+    // The key BLPOP and friends popped from, and the reply without it.
+    let popped = || match response {
+        Frame::Array(items) => match items.as_slice() {
+            [Frame::Bulk(key), rest @ ..] => Some((key.to_vec(), rest)),
+            _ => None,
+        },
+        _ => None,
+    };
+    let has_arg = |arg: &str| args.iter().any(|a| a.eq_ignore_ascii_case(arg.as_bytes()));
+
+    match name.as_str() {
+        // Replicas may not have the script cached.
+        "EVALSHA" => {
+            let script = state
+                .lock()
+                .scripts
+                .get(String::from_utf8_lossy(&args[1]).as_ref())
+                .cloned();
+            if let Some(script) = script {
+                let mut out = cmd("EVAL", &[script.as_bytes()]);
+                out.extend_from_slice(&args[2..]);
+                return out;
+            }
+        }
+        "SPOP" => {
+            let members: Vec<&[u8]> = match response {
+                Frame::Bulk(m) => vec![m],
+                Frame::Array(ms) | Frame::Set(ms) => ms
+                    .iter()
+                    .filter_map(|m| match m {
+                        Frame::Bulk(m) => Some(m.as_ref()),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            let mut out = cmd("SREM", &[&args[1]]);
+            out.extend(members.iter().map(|m| m.to_vec()));
+            return out;
+        }
+        "XADD" => {
+            if let Frame::Bulk(id) = response {
+                let mut out = args.to_vec();
+                if let Some(arg) = out.get_mut(xadd_id_index(args)) {
+                    *arg = id.to_vec();
+                }
+                return out;
+            }
+        }
+        "BLPOP" | "BRPOP" | "BZPOPMIN" | "BZPOPMAX" => {
+            if let Some((key, _)) = popped() {
+                return cmd(&name[1..], &[&key]);
+            }
+        }
+        "BRPOPLPUSH" => return cmd("RPOPLPUSH", &[&args[1], &args[2]]),
+        "BLMOVE" => return cmd("LMOVE", &[&args[1], &args[2], &args[3], &args[4]]),
+        "BLMPOP" | "BZMPOP" => {
+            if let Some((key, [elements])) = popped() {
+                let count = match elements {
+                    Frame::Array(items) => items.len(),
+                    Frame::Map(items) => items.len(),
+                    _ => 1,
+                };
+                let pop = match (name.as_str(), has_arg("LEFT") || has_arg("MIN")) {
+                    ("BLMPOP", true) => "LPOP",
+                    ("BLMPOP", false) => "RPOP",
+                    (_, true) => "ZPOPMIN",
+                    (_, false) => "ZPOPMAX",
+                };
+                return cmd(pop, &[&key, count.to_string().as_bytes()]);
+            }
+        }
+        "XREADGROUP" => {
+            let mut out = Vec::with_capacity(args.len());
+            let mut iter = args.iter();
+            while let Some(arg) = iter.next() {
+                if arg.eq_ignore_ascii_case(b"BLOCK") {
+                    iter.next();
+                } else {
+                    out.push(arg.clone());
+                }
+            }
+            return out;
+        }
+        _ => {}
+    }
+    args.to_vec()
+}
+
+/// Position of the ID argument of an XADD command.
+fn xadd_id_index(args: &[Vec<u8>]) -> usize {
+    let mut i = 2;
+    while let Some(arg) = args.get(i) {
+        match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+            "NOMKSTREAM" => i += 1,
+            "MAXLEN" | "MINID" => {
+                i += 1;
+                if args
+                    .get(i)
+                    .is_some_and(|a| a.as_slice() == b"=" || a.as_slice() == b"~")
+                {
+                    i += 1;
+                }
+                i += 1;
+            }
+            "LIMIT" => i += 2,
+            _ => break,
+        }
+    }
+    i
+}
+
+/// Send commands that ran in database `db` to the replicas. A replica
+/// passes on what it applies with the offset it got from its master.
+fn feed(state: &SharedState, db: usize, commands: Vec<Vec<Vec<u8>>>, end_db: usize) -> u64 {
+    let mut repl = state.replication.lock().unwrap();
+    if repl.replicas.is_empty() {
+        return repl.offset;
+    }
+    let mut stream = Vec::with_capacity(commands.len() + 1);
+    if repl.last_db != Some(db) {
+        stream.push(vec![b"SELECT".to_vec(), db.to_string().into_bytes()]);
+    }
+    stream.extend(commands);
+    repl.last_db = Some(end_db);
+    if !repl.is_replica() {
+        repl.offset += stream.iter().map(|c| encoded_len(c)).sum::<u64>();
+    }
+    let msg: Message = (Arc::new(stream), repl.offset);
+    repl.replicas.retain(|r| r.tx.send(msg.clone()).is_ok());
+    repl.offset
+}
+
+/// Send DELs for keys evicted for maxmemory, as (database, key) pairs.
+/// The caller holds the keyspace exclusively.
+pub fn propagate_evicted(state: &SharedState, evicted: &[(usize, Vec<u8>)]) {
+    let Some(&(first, _)) = evicted.first() else {
+        return;
+    };
+    let mut db = first;
+    let mut commands = Vec::with_capacity(evicted.len());
+    for (key_db, key) in evicted {
+        if *key_db != db {
+            db = *key_db;
+            commands.push(vec![b"SELECT".to_vec(), db.to_string().into_bytes()]);
+        }
+        commands.push(vec![b"DEL".to_vec(), key.clone()]);
+    }
+    feed(state, first, commands, db);
+}
+
+/// Length of a command in the RESP protocol, which is what replication
+/// offsets count.
+fn encoded_len(args: &[Vec<u8>]) -> u64 {
+    let header = |n: usize| n.to_string().len() as u64 + 3;
+    header(args.len())
+        + args
+            .iter()
+            .map(|a| header(a.len()) + a.len() as u64 + 2)
+            .sum::<u64>()
+}
+
+// ── WAIT ────────────────────────────────────────────────────────────
+
+/// Number of replicas that applied the stream up to `offset`.
+pub fn acked_replicas(state: &SharedState, offset: u64) -> i64 {
+    let repl = state.replication.lock().unwrap();
+    repl.replicas.iter().filter(|r| r.ack >= offset).count() as i64
+}
+
+/// Wait until `numreplicas` replicas applied the stream up to `offset`,
+/// or until the timeout (in milliseconds, 0 for none) expires. Returns the
+/// number of replicas that did.
+pub async fn wait(
+    state: &SharedState,
+    offset: u64,
+    numreplicas: i64,
+    timeout_ms: i64,
+    shutdown_rx: &mut broadcast::Receiver<()>,
+) -> i64 {
+    let timeout = if timeout_ms == 0 {
+        Duration::from_secs(300) // max wait
+    } else {
+        Duration::from_millis(timeout_ms as u64)
+    };
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let notified = state.repl_acks.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let acked = acked_replicas(state, offset);
+        if acked >= numreplicas {
+            return acked;
+        }
+        tokio::select! {
+            _ = &mut notified => {}
+            _ = tokio::time::sleep_until(deadline) => return acked_replicas(state, offset),
+            _ = shutdown_rx.recv() => return acked,
+        }
+    }
+}
+
+// ── Replica side ────────────────────────────────────────────────────
+
+/// Keep a replica in sync with the master at `host:port` until `stop`
+/// fires or the replica shuts down. While the master isn't running the
+/// replica keeps looking for it.
+async fn run_link(
+    replica: Arc<SharedState>,
+    link: u64,
+    host: String,
+    port: u16,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let mut shutdown = replica.shutdown_tx.subscribe();
+    loop {
+        let addrs: Vec<SocketAddr> = match tokio::net::lookup_host((host.as_str(), port)).await {
+            Ok(addrs) => addrs.collect(),
+            Err(_) => Vec::new(),
+        };
+        let master = {
+            let servers = SERVERS.lock().unwrap();
+            addrs.iter().find_map(|a| servers.get(a)?.upgrade())
+        };
+        if let Some(master) = master.filter(|m| !Arc::ptr_eq(m, &replica)) {
+            let mut master_shutdown = master.shutdown_tx.subscribe();
+            if let Some((id, mut rx)) = full_sync(&master, &replica, link) {
+                let mut ctx = ConnCtx::new();
+                ctx.authenticated = true;
+                ctx.master_link = true;
+                let stopped = loop {
+                    tokio::select! {
+                        msg = rx.recv() => match msg {
+                            Some((commands, offset)) => {
+                                apply(&replica, &mut ctx, &commands, offset);
+                                ack(&master, id, offset);
+                            }
+                            None => break false,
+                        },
+                        _ = master_shutdown.recv() => break false,
+                        _ = &mut stop => break true,
+                        _ = shutdown.recv() => break true,
+                    }
+                };
+                master
+                    .replication
+                    .lock()
+                    .unwrap()
+                    .replicas
+                    .retain(|r| r.id != id);
+                if let Some(m) = replica.replication.lock().unwrap().master.as_mut()
+                    && m.id == link
+                {
+                    m.up = false;
+                }
+                if stopped {
+                    return;
+                }
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+            _ = &mut stop => return,
+            _ = shutdown.recv() => return,
+        }
+    }
+}
+
+/// Load a snapshot of the master into the replica, and register the
+/// replica with the master so it gets all commands after the snapshot.
+fn full_sync(
+    master: &SharedState,
+    replica: &SharedState,
+    link: u64,
+) -> Option<(u64, mpsc::UnboundedReceiver<Message>)> {
+    let addr = replica.replication.lock().unwrap().addr;
+    let (tx, rx) = mpsc::unbounded_channel();
+    let (data, id, replid, offset) = {
+        let _keyspace = master.lock_keyspace(Isolation::Exclusive);
+        let data = crate::rdb::encode(&master.lock_all());
+        let mut repl = master.replication.lock().unwrap();
+        repl.next_link_id += 1;
+        let id = repl.next_link_id;
+        let offset = repl.offset;
+        repl.last_db = None;
+        repl.replicas.push(ReplicaLink {
+            id,
+            addr,
+            tx,
+            ack: offset,
+        });
+        (data, id, repl.replid.clone(), offset)
+    };
+
+    let loaded = {
+        let _keyspace = replica.lock_keyspace(Isolation::Exclusive);
+        let loaded = crate::rdb::load(&mut replica.lock_all(), &data);
+        replica.publish_changes(None);
+        loaded
+    };
+    replica.notify.notify_waiters();
+
+    let mut repl = replica.replication.lock().unwrap();
+    match repl.master.as_mut() {
+        Some(m) if m.id == link && loaded.is_ok() => m.up = true,
+        _ => {
+            drop(repl);
+            master
+                .replication
+                .lock()
+                .unwrap()
+                .replicas
+                .retain(|r| r.id != id);
+            return None;
+        }
+    }
+    repl.replid = replid;
+    repl.offset = offset;
+    // Replicas of this replica have outdated data now; they sync again.
+    repl.replicas.clear();
+    repl.last_db = None;
+    Some((id, rx))
+}
+
+/// Run commands from the master.
+fn apply(replica: &Arc<SharedState>, ctx: &mut ConnCtx, commands: &[Vec<Vec<u8>>], offset: u64) {
+    // Set first, so the commands go to this replica's replicas with the
+    // master's offset.
+    replica.replication.lock().unwrap().offset = offset;
+    let table = Arc::clone(replica.command_table.get().unwrap());
+    for args in commands {
+        dispatch(&table, replica, ctx, args);
+    }
+}
+
+/// Record that replica `id` applied the stream up to `offset`.
+fn ack(master: &SharedState, id: u64, offset: u64) {
+    let mut repl = master.replication.lock().unwrap();
+    if let Some(r) = repl.replicas.iter_mut().find(|r| r.id == id) {
+        r.ack = offset;
+    }
+    drop(repl);
+    master.repl_acks.notify_waiters();
+}
+
+// ── Introspection ───────────────────────────────────────────────────
+
+/// The ROLE reply.
+pub fn role(state: &SharedState) -> Frame {
+    let repl = state.replication.lock().unwrap();
+    match &repl.master {
+        Some(m) => Frame::Array(vec![
+            Frame::bulk_string("slave"),
+            Frame::Bulk(m.host.clone().into()),
+            Frame::Integer(m.port as i64),
+            Frame::bulk_string(if m.up { "connected" } else { "connect" }),
+            Frame::Integer(if m.up { repl.offset as i64 } else { -1 }),
+        ]),
+        None => Frame::Array(vec![
+            Frame::bulk_string("master"),
+            Frame::Integer(repl.offset as i64),
+            Frame::Array(
+                repl.replicas
+                    .iter()
+                    .map(|r| {
+                        let (ip, port) = addr_parts(r.addr);
+                        Frame::Array(vec![
+                            Frame::Bulk(ip.into()),
+                            Frame::Bulk(port.to_string().into()),
+                            Frame::Bulk(r.ack.to_string().into()),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ]),
+    }
+}
+
+/// The "# Replication" section of INFO.
+pub fn info(state: &SharedState) -> String {
+    let read_only = state.lock().replica_read_only;
+    let repl = state.replication.lock().unwrap();
+    let mut out = String::from("# Replication\r\n");
+    match &repl.master {
+        Some(m) => {
+            out.push_str(&format!(
+                "role:slave\r\nmaster_host:{}\r\nmaster_port:{}\r\nmaster_link_status:{}\r\n\
+                 master_last_io_seconds_ago:{}\r\nmaster_sync_in_progress:0\r\n\
+                 slave_read_repl_offset:{}\r\nslave_repl_offset:{}\r\nslave_priority:100\r\n\
+                 slave_read_only:{}\r\nreplica_announced:1\r\n",
+                m.host,
+                m.port,
+                if m.up { "up" } else { "down" },
+                if m.up { 0 } else { -1 },
+                repl.offset,
+                repl.offset,
+                read_only as u8,
+            ));
+        }
+        None => out.push_str("role:master\r\n"),
+    }
+    out.push_str(&format!("connected_slaves:{}\r\n", repl.replicas.len()));
+    for (i, r) in repl.replicas.iter().enumerate() {
+        let (ip, port) = addr_parts(r.addr);
+        out.push_str(&format!(
+            "slave{i}:ip={ip},port={port},state=online,offset={},lag=0\r\n",
+            r.ack
+        ));
+    }
+    out.push_str(&format!(
+        "master_failover_state:no-failover\r\nmaster_replid:{}\r\nmaster_replid2:{}\r\n\
+         master_repl_offset:{}\r\nsecond_repl_offset:{}\r\n",
+        repl.replid, repl.replid2, repl.offset, repl.second_offset
+    ));
+    out
+}
+
+fn addr_parts(addr: Option<SocketAddr>) -> (String, u16) {
+    match addr {
+        Some(addr) => (addr.ip().to_string(), addr.port()),
+        None => ("?".to_string(), 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &[&str]) -> Vec<Vec<u8>> {
+        cmd.iter().map(|a| a.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        let cmd = args(&["SET", "foo", "bar"]);
+        let frame = Frame::Array(cmd.iter().map(|a| Frame::Bulk(a.clone().into())).collect());
+        assert_eq!(encoded_len(&cmd), frame.serialize().len() as u64);
+    }
+
+    #[test]
+    fn test_xadd_id_index() {
+        assert_eq!(xadd_id_index(&args(&["XADD", "s", "*", "f", "v"])), 2);
+        assert_eq!(
+            xadd_id_index(&args(&[
+                "XADD",
+                "s",
+                "NOMKSTREAM",
+                "MAXLEN",
+                "~",
+                "10",
+                "LIMIT",
+                "5",
+                "*",
+                "f",
+                "v"
+            ])),
+            8
+        );
+        assert_eq!(
+            xadd_id_index(&args(&["XADD", "s", "minid", "0-1", "1-*", "f", "v"])),
+            4
+        );
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let state = SharedState::new();
+        let bulk = |s: &str| Frame::Bulk(s.as_bytes().to_vec().into());
+
+        assert_eq!(
+            rewrite(
+                &state,
+                &args(&["SPOP", "s", "2"]),
+                &Frame::Array(vec![bulk("a"), bulk("b")])
+            ),
+            args(&["SREM", "s", "a", "b"])
+        );
+        assert_eq!(
+            rewrite(&state, &args(&["XADD", "s", "*", "f", "v"]), &bulk("5-0")),
+            args(&["XADD", "s", "5-0", "f", "v"])
+        );
+        assert_eq!(
+            rewrite(
+                &state,
+                &args(&["BLPOP", "a", "b", "0"]),
+                &Frame::Array(vec![bulk("b"), bulk("x")])
+            ),
+            args(&["LPOP", "b"])
+        );
+        assert_eq!(
+            rewrite(
+                &state,
+                &args(&["BZMPOP", "0", "1", "z", "MAX", "COUNT", "5"]),
+                &Frame::Array(vec![
+                    bulk("z"),
+                    Frame::Array(vec![
+                        Frame::Array(vec![bulk("m1"), bulk("1")]),
+                        Frame::Array(vec![bulk("m2"), bulk("2")]),
+                    ])
+                ])
+            ),
+            args(&["ZPOPMAX", "z", "2"])
+        );
+        assert_eq!(
+            rewrite(
+                &state,
+                &args(&[
+                    "XREADGROUP",
+                    "GROUP",
+                    "g",
+                    "c",
+                    "BLOCK",
+                    "0",
+                    "STREAMS",
+                    "s",
+                    ">"
+                ]),
+                &Frame::Null
+            ),
+            args(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", ">"])
+        );
+        assert_eq!(
+            rewrite(&state, &args(&["SET", "a", "1"]), &Frame::ok()),
+            args(&["SET", "a", "1"])
+        );
+    }
+}
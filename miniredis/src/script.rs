@@ -0,0 +1,390 @@
+//! Running Lua scripts: the BUSY state, SCRIPT KILL and redis.log().
+//!
+//! EVAL and FCALL register the script they run here. Once a script has run
+//! longer than `busy-reply-threshold` milliseconds, other clients get BUSY
+//! for everything but a few commands, SCRIPT KILL (FUNCTION KILL for
+//! functions) among them. Killing sets a flag the Lua hook of the script
+//! checks, so the script stops with an error at its next instructions.
+//! A script that already wrote can't be killed.
+//!
+//! Scripts run on tokio's blocking threads, holding `SharedState::script_gate`.
+//! The commands of other clients wait for the gate without blocking a
+//! worker thread, so a runaway script doesn't stop the server from reading
+//! commands and answering BUSY or SCRIPT KILL.
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{OwnedRwLockWriteGuard, RwLockReadGuard};
+
+use crate::acl::full_command_name;
+use crate::connection::ConnCtx;
+use crate::db::SharedState;
+use crate::frame::Frame;
+
+pub const MSG_BUSY_SCRIPT: &str =
+    "BUSY Redis is busy running a script. You can only call SCRIPT KILL or SHUTDOWN NOSAVE.";
+pub const MSG_BUSY_FUNCTION: &str =
+    "BUSY Redis is busy running a script. You can only call FUNCTION KILL or SHUTDOWN NOSAVE.";
+pub const MSG_NOT_BUSY: &str = "NOTBUSY No scripts in execution right now.";
+pub const MSG_UNKILLABLE: &str = "UNKILLABLE Sorry the script already executed write commands against the dataset. You can either wait the script termination or kill the server in a hard way using the SHUTDOWN NOSAVE command.";
+pub const MSG_SCRIPT_KILLED: &str = "ERR Script killed by user with SCRIPT KILL...";
+pub const MSG_FUNCTION_KILLED: &str = "ERR Script killed by user with FUNCTION KILL...";
+
+/// The script log keeps this many messages, dropping the oldest.
+const MAX_LOGGED: usize = 10_000;
+
+/// Commands other clients may still run while a script is busy.
+const ALLOWED_WHILE_BUSY: &[&str] = &[
+    "auth",
+    "discard",
+    "function|kill",
+    "function|stats",
+    "hello",
+    "multi",
+    "quit",
+    "reset",
+    "script|kill",
+    "shutdown",
+    "unwatch",
+    "watch",
+];
+
+/// Commands that run a script.
+const SCRIPT_COMMANDS: &[&str] = &[
+    "EVAL",
+    "EVALSHA",
+    "EVALSHA_RO",
+    "EVAL_RO",
+    "FCALL",
+    "FCALL_RO",
+];
+
+/// How often a command waiting for a script checks whether it gets BUSY.
+const BUSY_POLL: Duration = Duration::from_millis(5);
+
+/// Level of a redis.log() message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Verbose,
+    Notice,
+    Warning,
+}
+
+impl LogLevel {
+    /// The level for `redis.LOG_DEBUG` (0) to `redis.LOG_WARNING` (3).
+    pub fn from_lua(level: i64) -> Option<Self> {
+        match level {
+            0 => Some(LogLevel::Debug),
+            1 => Some(LogLevel::Verbose),
+            2 => Some(LogLevel::Notice),
+            3 => Some(LogLevel::Warning),
+            _ => None,
+        }
+    }
+}
+
+/// A message a script logged with redis.log().
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLogEntry {
+    pub level: LogLevel,
+    pub message: String,
+}
+
+/// A script or function being run.
+struct RunningScript {
+    id: u64,
+    client_id: u64,
+    /// Function name, or the SHA of a script.
+    name: String,
+    /// The FCALL command that runs a function, for FUNCTION STATS.
+    command: Vec<Vec<u8>>,
+    is_function: bool,
+    started: Instant,
+    /// Whether it ran a write command.
+    wrote: bool,
+    killed: Arc<AtomicBool>,
+}
+
+/// Running scripts and the messages they logged.
+#[derive(Default)]
+pub struct ScriptState {
+    running: Vec<RunningScript>,
+    next_id: u64,
+    log: Vec<ScriptLogEntry>,
+}
+
+impl ScriptState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The messages scripts logged, oldest first.
+    pub fn log(&self) -> Vec<ScriptLogEntry> {
+        self.log.clone()
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    pub fn add_log(&mut self, level: LogLevel, message: String) {
+        if self.log.len() == MAX_LOGGED {
+            self.log.remove(0);
+        }
+        self.log.push(ScriptLogEntry { level, message });
+    }
+
+    /// The script other clients wait on: the one running longest.
+    fn busy(&self) -> Option<&RunningScript> {
+        self.running.iter().min_by_key(|s| s.started)
+    }
+}
+
+/// Registers a running script until dropped.
+pub struct ScriptGuard {
+    state: Arc<SharedState>,
+    id: u64,
+    killed: Arc<AtomicBool>,
+}
+
+impl ScriptGuard {
+    /// Set once SCRIPT KILL or FUNCTION KILL stopped the script.
+    pub fn killed(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.killed)
+    }
+}
+
+impl Drop for ScriptGuard {
+    fn drop(&mut self) {
+        let mut scripts = self.state.script.lock().unwrap();
+        scripts.running.retain(|s| s.id != self.id);
+    }
+}
+
+/// Register a script or function `client_id` starts to run. `command`
+/// is the FCALL of a function.
+pub fn start(
+    state: &Arc<SharedState>,
+    client_id: u64,
+    name: &str,
+    command: &[Vec<u8>],
+    is_function: bool,
+) -> ScriptGuard {
+    let killed = Arc::new(AtomicBool::new(false));
+    let mut scripts = state.script.lock().unwrap();
+    let id = scripts.next_id;
+    scripts.next_id += 1;
+    scripts.running.push(RunningScript {
+        id,
+        client_id,
+        name: name.to_string(),
+        command: command.to_vec(),
+        is_function,
+        started: Instant::now(),
+        wrote: false,
+        killed: Arc::clone(&killed),
+    });
+    ScriptGuard {
+        state: Arc::clone(state),
+        id,
+        killed,
+    }
+}
+
+/// Note that the script of `client_id` ran a write command.
+pub fn wrote(state: &SharedState, client_id: u64) {
+    let mut scripts = state.script.lock().unwrap();
+    if let Some(script) = scripts
+        .running
+        .iter_mut()
+        .find(|s| s.client_id == client_id)
+    {
+        script.wrote = true;
+    }
+}
+
+/// The BUSY error for a command of `client_id`, when a script of another
+/// client has run too long and the command has to wait for it.
+pub fn check_busy(
+    state: &SharedState,
+    client_id: u64,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Option<Frame> {
+    let (started, is_function) = {
+        let scripts = state.script.lock().unwrap();
+        let busy = scripts.busy()?;
+        if busy.client_id == client_id {
+            return None;
+        }
+        (busy.started, busy.is_function)
+    };
+    let threshold = Duration::from_millis(state.lock().busy_reply_threshold);
+    if started.elapsed() < threshold {
+        return None;
+    }
+    let name = full_command_name(cmd, args.first().map(|a| a.as_slice()));
+    if ALLOWED_WHILE_BUSY.contains(&name.as_str()) {
+        return None;
+    }
+    Some(Frame::error(if is_function {
+        MSG_BUSY_FUNCTION
+    } else {
+        MSG_BUSY_SCRIPT
+    }))
+}
+
+/// Whether `cmd` (upper-case) of `ctx`'s client runs a script: a script
+/// command outside MULTI, or EXEC of a transaction with one.
+pub fn runs_script(ctx: &ConnCtx, cmd: &str) -> bool {
+    let is_script = |name: &[u8]| {
+        SCRIPT_COMMANDS
+            .iter()
+            .any(|c| name.eq_ignore_ascii_case(c.as_bytes()))
+    };
+    match &ctx.transaction {
+        None => is_script(cmd.as_bytes()),
+        Some(queued) => cmd == "EXEC" && queued.iter().any(|q| is_script(&q.args[0])),
+    }
+}
+
+/// Wait until no script runs, without blocking the thread, before running
+/// a command of `client_id` that doesn't run a script. The guard keeps
+/// scripts from starting until the command is done. Commands that stop or
+/// report on a script don't wait.
+pub async fn wait<'a>(
+    state: &'a SharedState,
+    client_id: u64,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Result<Option<RwLockReadGuard<'a, ()>>, Frame> {
+    if skips_write_lock(cmd, args) {
+        return Ok(None);
+    }
+    wait_for(state, client_id, cmd, args, state.script_gate.read())
+        .await
+        .map(Some)
+}
+
+/// Wait until no script or other command runs before running a script
+/// command of `client_id`. The guard moves to the thread running it.
+pub async fn wait_to_run(
+    state: &Arc<SharedState>,
+    client_id: u64,
+    cmd: &str,
+    args: &[Vec<u8>],
+) -> Result<OwnedRwLockWriteGuard<()>, Frame> {
+    let gate = Arc::clone(&state.script_gate);
+    wait_for(state, client_id, cmd, args, gate.write_owned()).await
+}
+
+/// Wait for `acquire`. Once a script has run longer than
+/// `busy-reply-threshold`, the error is BUSY, or the command keeps waiting
+/// if it's allowed while busy.
+async fn wait_for<G>(
+    state: &SharedState,
+    client_id: u64,
+    cmd: &str,
+    args: &[Vec<u8>],
+    acquire: impl Future<Output = G>,
+) -> Result<G, Frame> {
+    tokio::pin!(acquire);
+    loop {
+        tokio::select! {
+            biased;
+            guard = &mut acquire => return Ok(guard),
+            _ = tokio::time::sleep(BUSY_POLL) => {}
+        }
+        if let Some(err) = check_busy(state, client_id, cmd, args) {
+            return Err(err);
+        }
+    }
+}
+
+/// Whether a command mustn't wait for writes in progress, because it
+/// stops or reports on a running script.
+pub fn skips_write_lock(cmd: &str, args: &[Vec<u8>]) -> bool {
+    let name = full_command_name(cmd, args.first().map(|a| a.as_slice()));
+    matches!(
+        name.as_str(),
+        "script|kill" | "function|kill" | "function|stats"
+    )
+}
+
+/// SCRIPT KILL, or FUNCTION KILL with `function` set.
+pub fn kill(state: &SharedState, function: bool) -> Frame {
+    let scripts = state.script.lock().unwrap();
+    let Some(busy) = scripts.busy() else {
+        return Frame::error(MSG_NOT_BUSY);
+    };
+    if busy.is_function != function {
+        return Frame::error(if busy.is_function {
+            MSG_BUSY_FUNCTION
+        } else {
+            MSG_BUSY_SCRIPT
+        });
+    }
+    if busy.wrote {
+        return Frame::error(MSG_UNKILLABLE);
+    }
+    busy.killed.store(true, Ordering::Relaxed);
+    Frame::ok()
+}
+
+/// The `running_script` field of FUNCTION STATS.
+pub fn running_function(state: &SharedState) -> Frame {
+    let scripts = state.script.lock().unwrap();
+    match scripts.busy().filter(|s| s.is_function) {
+        Some(script) => Frame::Map(vec![
+            (Frame::bulk_string("name"), Frame::bulk_string(&script.name)),
+            (
+                Frame::bulk_string("command"),
+                Frame::Array(
+                    script
+                        .command
+                        .iter()
+                        .map(|a| Frame::Bulk(a.clone().into()))
+                        .collect(),
+                ),
+            ),
+            (
+                Frame::bulk_string("duration_ms"),
+                Frame::Integer(script.started.elapsed().as_millis() as i64),
+            ),
+        ]),
+        None => Frame::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_busy_and_kill() {
+        let state = SharedState::new();
+        state.lock().busy_reply_threshold = 0;
+        let get = [b"k".to_vec()];
+        assert_eq!(kill(&state, false), Frame::error(MSG_NOT_BUSY));
+
+        let guard = start(&state, 1, "sha", &[b"EVAL".to_vec()], false);
+        assert!(check_busy(&state, 1, "GET", &get).is_none());
+        assert_eq!(
+            check_busy(&state, 2, "GET", &get),
+            Some(Frame::error(MSG_BUSY_SCRIPT))
+        );
+        assert!(check_busy(&state, 2, "SCRIPT", &[b"kill".to_vec()]).is_none());
+        assert_eq!(kill(&state, true), Frame::error(MSG_BUSY_SCRIPT));
+        assert_eq!(kill(&state, false), Frame::ok());
+        assert!(guard.killed().load(Ordering::Relaxed));
+        drop(guard);
+        assert!(check_busy(&state, 2, "GET", &get).is_none());
+
+        let _guard = start(&state, 1, "f", &[b"FCALL".to_vec()], true);
+        wrote(&state, 1);
+        assert_eq!(kill(&state, true), Frame::error(MSG_UNKILLABLE));
+        assert!(matches!(running_function(&state), Frame::Map(_)));
+    }
+}
@@ -1,13 +1,20 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use tokio::net::TcpListener;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, mpsc};
 
+use crate::cmd::generic::parse_wait;
 use crate::connection::{ConnCtx, Connection};
-use crate::db::SharedState;
-use crate::dispatch::{CommandTable, dispatch, err_wrong_number};
+use crate::db::{CommandScope, Isolation, SharedState};
+use crate::dispatch::{CommandTable, check_access, dispatch, err_wrong_number, refuse};
 use crate::frame::Frame;
+use crate::latency;
+use crate::monitor;
 use crate::pubsub::PubsubCtx;
+use crate::replication::{self, Propagator};
+use crate::script;
+use crate::tracking::{INVALIDATE_CHANNEL, Invalidation};
 
 /// Start the server: bind to the given address, accept connections, and
 /// dispatch commands.
@@ -28,7 +35,7 @@ pub async fn run(
     loop {
         tokio::select! {
             result = listener.accept() => {
-                let (socket, _addr) = match result {
+                let (socket, addr) = match result {
                     Ok(s) => s,
                     Err(_) => continue,
                 };
@@ -46,6 +53,7 @@ pub async fn run(
                         if let Ok(tls_stream) = acceptor.accept(socket).await {
                             handle_connection_stream(
                                 Connection::new_stream(tls_stream),
+                                addr,
                                 state,
                                 table,
                                 shutdown_rx,
@@ -57,6 +65,7 @@ pub async fn run(
 
                     handle_connection_stream(
                         Connection::new(socket),
+                        addr,
                         state,
                         table,
                         shutdown_rx,
@@ -74,6 +83,7 @@ pub async fn run(
 /// Handle a single client connection (plain or TLS).
 async fn handle_connection_stream(
     mut conn: Connection,
+    addr: SocketAddr,
     state: Arc<SharedState>,
     table: Arc<CommandTable>,
     mut shutdown_rx: broadcast::Receiver<()>,
@@ -86,18 +96,26 @@ async fn handle_connection_stream(
     state.connected_clients.fetch_add(1, Ordering::Relaxed);
 
     let mut ctx = ConnCtx::new();
+    ctx.client_id = state.next_client_id.fetch_add(1, Ordering::Relaxed) + 1;
+    ctx.addr = Some(addr);
     let mut pubsub: Option<PubsubCtx> = None;
+    let mut invalidations = state.tracking.lock().unwrap().register(ctx.client_id);
 
     handle_connection_inner(
         &mut conn,
         &mut ctx,
         &mut pubsub,
+        &mut invalidations,
         &state,
         &table,
         &mut shutdown_rx,
     )
     .await;
 
+    state.tracking.lock().unwrap().unregister(ctx.client_id);
+    state.update_tracking();
+    state.faults.lock().unwrap().take_effect(ctx.client_id);
+
     // Cleanup: remove subscriber from registry if in pub/sub mode
     if let Some(ps) = pubsub.take() {
         let mut registry = state.pubsub.lock().unwrap();
@@ -111,10 +129,12 @@ async fn handle_connection_inner(
     conn: &mut Connection,
     ctx: &mut ConnCtx,
     pubsub: &mut Option<PubsubCtx>,
+    invalidations: &mut mpsc::UnboundedReceiver<Invalidation>,
     state: &Arc<SharedState>,
     table: &Arc<CommandTable>,
     shutdown_rx: &mut broadcast::Receiver<()>,
 ) {
+    let mut monitor_rx: Option<mpsc::UnboundedReceiver<String>> = None;
     loop {
         if let Some(ps) = pubsub.as_mut() {
             // ── Pub/Sub mode event loop ────────────────────────────
@@ -142,6 +162,31 @@ async fn handle_connection_inner(
                     let cmd = String::from_utf8_lossy(&args[0]).to_uppercase();
                     let cmd_args = &args[1..];
 
+                    let fault = state.faults.lock().unwrap().check(ctx.client_id, &cmd);
+                    if let Some(err) = fault {
+                        if !write_reply(conn, state, ctx, &Frame::error(err)).await {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    if matches!(cmd.as_str(), "SUBSCRIBE" | "PSUBSCRIBE" | "SSUBSCRIBE")
+                        && let Some(err) = check_access(state, ctx, &cmd, cmd_args)
+                    {
+                        if !write_reply(conn, state, ctx, &err).await {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    match cmd.as_str() {
+                        "SUBSCRIBE" | "PSUBSCRIBE" | "SSUBSCRIBE" if cmd_args.is_empty() => {}
+                        "SUBSCRIBE" | "PSUBSCRIBE" | "SSUBSCRIBE" | "UNSUBSCRIBE"
+                        | "PUNSUBSCRIBE" | "SUNSUBSCRIBE" | "PING" => feed_pubsub(state, ctx, &args),
+                        "QUIT" => report(state, ctx, &args, &Frame::ok()),
+                        _ => {}
+                    }
+
                     match cmd.as_str() {
                         "SUBSCRIBE" => {
                             if cmd_args.is_empty() {
@@ -156,7 +201,7 @@ async fn handle_connection_inner(
                                     Frame::Bulk(channel.into()),
                                     Frame::Integer(count as i64),
                                 ]);
-                                if conn.write_frame(&confirm).await.is_err() {
+                                if !write_reply(conn, state, ctx, &confirm).await {
                                     return;
                                 }
                             }
@@ -171,7 +216,7 @@ async fn handle_connection_inner(
                                         Frame::Null,
                                         Frame::Integer(ps.total_count() as i64),
                                     ]);
-                                    if conn.write_frame(&confirm).await.is_err() {
+                                    if !write_reply(conn, state, ctx, &confirm).await {
                                         return;
                                     }
                                 } else {
@@ -182,7 +227,7 @@ async fn handle_connection_inner(
                                             Frame::Bulk(channel.into()),
                                             Frame::Integer(count as i64),
                                         ]);
-                                        if conn.write_frame(&confirm).await.is_err() {
+                                        if !write_reply(conn, state, ctx, &confirm).await {
                                             return;
                                         }
                                     }
@@ -196,13 +241,13 @@ async fn handle_connection_inner(
                                         Frame::Bulk(channel.into()),
                                         Frame::Integer(count as i64),
                                     ]);
-                                    if conn.write_frame(&confirm).await.is_err() {
+                                    if !write_reply(conn, state, ctx, &confirm).await {
                                         return;
                                     }
                                 }
                             }
                             // Exit pub/sub mode if no subscriptions left
-                            if ps.total_count() == 0 {
+                            if !ps.is_subscribed() {
                                 let handle = &ps.handle;
                                 let mut registry = state.pubsub.lock().unwrap();
                                 registry.remove(handle);
@@ -223,7 +268,7 @@ async fn handle_connection_inner(
                                     Frame::Bulk(pattern.into()),
                                     Frame::Integer(count as i64),
                                 ]);
-                                if conn.write_frame(&confirm).await.is_err() {
+                                if !write_reply(conn, state, ctx, &confirm).await {
                                     return;
                                 }
                             }
@@ -237,7 +282,7 @@ async fn handle_connection_inner(
                                         Frame::Null,
                                         Frame::Integer(ps.total_count() as i64),
                                     ]);
-                                    if conn.write_frame(&confirm).await.is_err() {
+                                    if !write_reply(conn, state, ctx, &confirm).await {
                                         return;
                                     }
                                 } else {
@@ -248,7 +293,7 @@ async fn handle_connection_inner(
                                             Frame::Bulk(pattern.into()),
                                             Frame::Integer(count as i64),
                                         ]);
-                                        if conn.write_frame(&confirm).await.is_err() {
+                                        if !write_reply(conn, state, ctx, &confirm).await {
                                             return;
                                         }
                                     }
@@ -262,13 +307,67 @@ async fn handle_connection_inner(
                                         Frame::Bulk(pattern.into()),
                                         Frame::Integer(count as i64),
                                     ]);
-                                    if conn.write_frame(&confirm).await.is_err() {
+                                    if !write_reply(conn, state, ctx, &confirm).await {
                                         return;
                                     }
                                 }
                             }
                             // Exit pub/sub mode if no subscriptions left
-                            if ps.total_count() == 0 {
+                            if !ps.is_subscribed() {
+                                let handle = &ps.handle;
+                                let mut registry = state.pubsub.lock().unwrap();
+                                registry.remove(handle);
+                                drop(registry);
+                                *pubsub = None;
+                            }
+                        }
+                        "SSUBSCRIBE" => {
+                            if cmd_args.is_empty() {
+                                let _ = conn.write_frame(&Frame::error(err_wrong_number("ssubscribe"))).await;
+                                continue;
+                            }
+                            for arg in cmd_args {
+                                let channel = String::from_utf8_lossy(arg).to_string();
+                                let count = ps.ssubscribe(&channel);
+                                let confirm = pubsub_msg(ctx.resp3, vec![
+                                    Frame::Bulk("ssubscribe".into()),
+                                    Frame::Bulk(channel.into()),
+                                    Frame::Integer(count as i64),
+                                ]);
+                                if !write_reply(conn, state, ctx, &confirm).await {
+                                    return;
+                                }
+                            }
+                        }
+                        "SUNSUBSCRIBE" => {
+                            let channels = if cmd_args.is_empty() {
+                                ps.shard_channels()
+                            } else {
+                                cmd_args.iter().map(|a| String::from_utf8_lossy(a).to_string()).collect()
+                            };
+                            if channels.is_empty() {
+                                let confirm = pubsub_msg(ctx.resp3, vec![
+                                    Frame::Bulk("sunsubscribe".into()),
+                                    Frame::Null,
+                                    Frame::Integer(ps.shard_count() as i64),
+                                ]);
+                                if !write_reply(conn, state, ctx, &confirm).await {
+                                    return;
+                                }
+                            }
+                            for channel in channels {
+                                let count = ps.sunsubscribe(&channel);
+                                let confirm = pubsub_msg(ctx.resp3, vec![
+                                    Frame::Bulk("sunsubscribe".into()),
+                                    Frame::Bulk(channel.into()),
+                                    Frame::Integer(count as i64),
+                                ]);
+                                if !write_reply(conn, state, ctx, &confirm).await {
+                                    return;
+                                }
+                            }
+                            // Exit pub/sub mode if no subscriptions left
+                            if !ps.is_subscribed() {
                                 let handle = &ps.handle;
                                 let mut registry = state.pubsub.lock().unwrap();
                                 registry.remove(handle);
@@ -282,7 +381,7 @@ async fn handle_connection_inner(
                                     Frame::Bulk("pong".into()),
                                     Frame::Bulk("".into()),
                                 ]);
-                                if conn.write_frame(&pong).await.is_err() {
+                                if !write_reply(conn, state, ctx, &pong).await {
                                     return;
                                 }
                             } else {
@@ -291,7 +390,7 @@ async fn handle_connection_inner(
                                     Frame::Bulk("pong".into()),
                                     Frame::Bulk(msg.into()),
                                 ]);
-                                if conn.write_frame(&pong).await.is_err() {
+                                if !write_reply(conn, state, ctx, &pong).await {
                                     return;
                                 }
                             }
@@ -302,10 +401,10 @@ async fn handle_connection_inner(
                         }
                         _ => {
                             let err = format!(
-                                "ERR Can't execute '{}': only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING / QUIT are allowed in this context",
+                                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT are allowed in this context",
                                 cmd.to_lowercase()
                             );
-                            if conn.write_frame(&Frame::error(err)).await.is_err() {
+                            if !write_reply(conn, state, ctx, &Frame::error(err)).await {
                                 return;
                             }
                         }
@@ -326,6 +425,11 @@ async fn handle_connection_inner(
                                     Frame::Bulk(m.channel.into()),
                                     Frame::Bulk(m.data.into()),
                                 ]),
+                                "smessage" => pubsub_msg(ctx.resp3, vec![
+                                    Frame::Bulk("smessage".into()),
+                                    Frame::Bulk(m.channel.into()),
+                                    Frame::Bulk(m.data.into()),
+                                ]),
                                 _ => continue,
                             };
                             if conn.write_frame(&frame).await.is_err() {
@@ -335,6 +439,13 @@ async fn handle_connection_inner(
                         None => return, // channel closed
                     }
                 }
+                Some(msg) = invalidations.recv() => {
+                    if let Some(frame) = invalidation_frame(msg, ctx.resp3, Some(ps))
+                        && conn.write_frame(&frame).await.is_err()
+                    {
+                        return;
+                    }
+                }
                 _ = shutdown_rx.recv() => {
                     return;
                 }
@@ -364,14 +475,39 @@ async fn handle_connection_inner(
 
                     let cmd = String::from_utf8_lossy(&args[0]).to_uppercase();
 
-                    // Handle SUBSCRIBE/PSUBSCRIBE — enter pub/sub mode
+                    // Injected faults. Commands queued by MULTI are checked
+                    // when EXEC runs them.
+                    let fault = if !ctx.in_tx() || cmd == "EXEC" || cmd == "DISCARD" {
+                        state.faults.lock().unwrap().check(ctx.client_id, &cmd)
+                    } else {
+                        None
+                    };
+                    if let Some(err) = fault {
+                        if cmd == "EXEC" {
+                            ctx.transaction = None;
+                            ctx.watch.clear();
+                        }
+                        if !write_reply(conn, state, ctx, &Frame::error(err)).await {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    // Handle (P|S)SUBSCRIBE — enter pub/sub mode
                     // (but not inside MULTI — let dispatch queue it)
-                    if (cmd == "SUBSCRIBE" || cmd == "PSUBSCRIBE") && !ctx.in_tx() {
+                    if matches!(cmd.as_str(), "SUBSCRIBE" | "PSUBSCRIBE" | "SSUBSCRIBE") && !ctx.in_tx() {
                         let cmd_args = &args[1..];
                         if cmd_args.is_empty() {
                             let _ = conn.write_frame(&Frame::error(err_wrong_number(&cmd.to_lowercase()))).await;
                             continue;
                         }
+                        if let Some(err) = check_access(state, ctx, &cmd, cmd_args) {
+                            if !write_reply(conn, state, ctx, &err).await {
+                                return;
+                            }
+                            continue;
+                        }
+                        feed_pubsub(state, ctx, &args);
 
                         // Create pub/sub context
                         let ps = {
@@ -391,11 +527,11 @@ async fn handle_connection_inner(
                                     Frame::Bulk(channel.into()),
                                     Frame::Integer(count as i64),
                                 ]);
-                                if conn.write_frame(&confirm).await.is_err() {
+                                if !write_reply(conn, state, ctx, &confirm).await {
                                     return;
                                 }
                             }
-                        } else {
+                        } else if cmd == "PSUBSCRIBE" {
                             for arg in cmd_args {
                                 let pattern = String::from_utf8_lossy(arg).to_string();
                                 let count = ps.psubscribe(&pattern);
@@ -404,7 +540,20 @@ async fn handle_connection_inner(
                                     Frame::Bulk(pattern.into()),
                                     Frame::Integer(count as i64),
                                 ]);
-                                if conn.write_frame(&confirm).await.is_err() {
+                                if !write_reply(conn, state, ctx, &confirm).await {
+                                    return;
+                                }
+                            }
+                        } else {
+                            for arg in cmd_args {
+                                let channel = String::from_utf8_lossy(arg).to_string();
+                                let count = ps.ssubscribe(&channel);
+                                let confirm = pubsub_msg(ctx.resp3, vec![
+                                    Frame::Bulk("ssubscribe".into()),
+                                    Frame::Bulk(channel.into()),
+                                    Frame::Integer(count as i64),
+                                ]);
+                                if !write_reply(conn, state, ctx, &confirm).await {
                                     return;
                                 }
                             }
@@ -412,37 +561,93 @@ async fn handle_connection_inner(
                         continue;
                     }
 
-                    // Handle UNSUBSCRIBE/PUNSUBSCRIBE outside pub/sub mode (no-op)
+                    // Handle (P|S)UNSUBSCRIBE outside pub/sub mode (no-op)
                     // But not inside MULTI — let dispatch queue it.
                     if cmd == "UNSUBSCRIBE" && !ctx.in_tx() {
+                        feed_pubsub(state, ctx, &args);
                         let confirm = pubsub_msg(ctx.resp3, vec![
                             Frame::Bulk("unsubscribe".into()),
                             Frame::Null,
                             Frame::Integer(0),
                         ]);
-                        let _ = conn.write_frame(&confirm).await;
+                        if !write_reply(conn, state, ctx, &confirm).await {
+                            return;
+                        }
+                        continue;
+                    }
+                    if cmd == "SUNSUBSCRIBE" && !ctx.in_tx() {
+                        feed_pubsub(state, ctx, &args);
+                        let confirm = pubsub_msg(ctx.resp3, vec![
+                            Frame::Bulk("sunsubscribe".into()),
+                            Frame::Null,
+                            Frame::Integer(0),
+                        ]);
+                        if !write_reply(conn, state, ctx, &confirm).await {
+                            return;
+                        }
                         continue;
                     }
                     if cmd == "PUNSUBSCRIBE" && !ctx.in_tx() {
+                        feed_pubsub(state, ctx, &args);
                         let confirm = pubsub_msg(ctx.resp3, vec![
                             Frame::Bulk("punsubscribe".into()),
                             Frame::Null,
                             Frame::Integer(0),
                         ]);
-                        let _ = conn.write_frame(&confirm).await;
+                        if !write_reply(conn, state, ctx, &confirm).await {
+                            return;
+                        }
                         continue;
                     }
 
                     state.total_commands_processed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 
+                    // MONITOR — stream every command the server runs to
+                    // this client (but not inside MULTI — let dispatch
+                    // queue it)
+                    if cmd == "MONITOR" && !ctx.in_tx() {
+                        let response = if args.len() != 1 {
+                            Frame::error(err_wrong_number("monitor"))
+                        } else if let Some(err) = check_access(state, ctx, &cmd, &args[1..]) {
+                            err
+                        } else {
+                            if monitor_rx.is_none() {
+                                monitor_rx = Some(state.monitor.lock().unwrap().subscribe());
+                                ctx.monitor = true;
+                            }
+                            report(state, ctx, &args, &Frame::ok());
+                            Frame::ok()
+                        };
+                        if !write_reply(conn, state, ctx, &response).await {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    // Blocking commands don't go through dispatch, so check
+                    // their permissions here.
+                    if !ctx.in_tx()
+                        && (is_blocking_command(&cmd)
+                            || cmd == "WAIT"
+                            || (matches!(cmd.as_str(), "XREAD" | "XREADGROUP")
+                                && has_block_arg(&args[1..])))
+                        && let Some(err) = check_access(state, ctx, &cmd, &args[1..])
+                    {
+                        if !write_reply(conn, state, ctx, &err).await {
+                            return;
+                        }
+                        continue;
+                    }
+
                     // Intercept blocking commands (outside MULTI/EXEC)
-                    if !ctx.in_tx() && matches!(cmd.as_str(), "BLPOP" | "BRPOP" | "BRPOPLPUSH" | "BLMOVE") {
+                    if !ctx.in_tx() && is_blocking_command(&cmd) {
                         let response = handle_blocking_command(
-                            &cmd, &args[1..], state, ctx, shutdown_rx
+                            &cmd, &args, state, ctx, shutdown_rx
                         ).await;
+                        report(state, ctx, &args, &response);
 
                         conn.resp3 = ctx.resp3;
-                        if conn.write_frame(&response).await.is_err() {
+                        if !write_reply(conn, state, ctx, &response).await {
                             return;
                         }
                         continue;
@@ -453,22 +658,46 @@ async fn handle_connection_inner(
                         && has_block_arg(&args[1..])
                     {
                         let response = handle_blocking_stream_command(
-                            &cmd, &args[1..], state, ctx, shutdown_rx
+                            &cmd, &args, state, ctx, shutdown_rx
                         ).await;
+                        report(state, ctx, &args, &response);
 
                         conn.resp3 = ctx.resp3;
-                        if conn.write_frame(&response).await.is_err() {
+                        if !write_reply(conn, state, ctx, &response).await {
                             return;
                         }
                         continue;
                     }
 
-                    let (response, should_close) = dispatch(table, state, ctx, &args);
+                    // WAIT blocks until enough replicas acknowledged the
+                    // client's writes (outside MULTI/EXEC)
+                    if !ctx.in_tx() && cmd == "WAIT" && args.len() == 3 {
+                        let response = match parse_wait(state, &args[1..]) {
+                            Ok((numreplicas, timeout)) => Frame::Integer(
+                                replication::wait(state, ctx.repl_offset, numreplicas, timeout, shutdown_rx).await,
+                            ),
+                            Err(err) => err,
+                        };
+                        report(state, ctx, &args, &response);
+                        if !write_reply(conn, state, ctx, &response).await {
+                            return;
+                        }
+                        continue;
+                    }
+
+                    let (response, should_close) = if script::runs_script(ctx, &cmd) {
+                        run_script(table, state, ctx, args).await
+                    } else {
+                        match script::wait(state, ctx.client_id, &cmd, &args[1..]).await {
+                            Ok(_gate) => dispatch(table, state, ctx, &args),
+                            Err(busy) => (refuse(state, ctx, &args, busy), false),
+                        }
+                    };
 
                     // Sync RESP3 flag (set by HELLO command)
                     conn.resp3 = ctx.resp3;
 
-                    if conn.write_frame(&response).await.is_err() {
+                    if !write_reply(conn, state, ctx, &response).await {
                         return;
                     }
 
@@ -476,11 +705,15 @@ async fn handle_connection_inner(
                         return;
                     }
 
-                    // Check if SUBSCRIBE/PSUBSCRIBE was executed inside EXEC.
+                    // Check if (P|S)SUBSCRIBE was executed inside EXEC.
                     // If so, enter pub/sub mode with the pending channels/patterns.
-                    if !ctx.pending_subscribe.is_empty() || !ctx.pending_psubscribe.is_empty() {
+                    if !ctx.pending_subscribe.is_empty()
+                        || !ctx.pending_psubscribe.is_empty()
+                        || !ctx.pending_ssubscribe.is_empty()
+                    {
                         let channels = std::mem::take(&mut ctx.pending_subscribe);
                         let patterns = std::mem::take(&mut ctx.pending_psubscribe);
+                        let shard_channels = std::mem::take(&mut ctx.pending_ssubscribe);
 
                         let ps = {
                             let mut registry = state.pubsub.lock().unwrap();
@@ -495,6 +728,21 @@ async fn handle_connection_inner(
                         for pattern in patterns {
                             ps.psubscribe(&pattern);
                         }
+                        for channel in shard_channels {
+                            ps.ssubscribe(&channel);
+                        }
+                    }
+                }
+                Some(line) = next_monitor_line(&mut monitor_rx) => {
+                    if conn.write_frame(&Frame::Simple(line)).await.is_err() {
+                        return;
+                    }
+                }
+                Some(msg) = invalidations.recv() => {
+                    if let Some(frame) = invalidation_frame(msg, ctx.resp3, None)
+                        && conn.write_frame(&frame).await.is_err()
+                    {
+                        return;
                     }
                 }
                 _ = shutdown_rx.recv() => {
@@ -505,6 +753,75 @@ async fn handle_connection_inner(
     }
 }
 
+/// Dispatch a command that runs a script on a blocking thread, so a long
+/// script doesn't hold up the connections served by this worker.
+async fn run_script(
+    table: &Arc<CommandTable>,
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    args: Vec<Vec<u8>>,
+) -> (Frame, bool) {
+    let cmd = String::from_utf8_lossy(&args[0]).to_uppercase();
+    let gate = match script::wait_to_run(state, ctx.client_id, &cmd, &args[1..]).await {
+        Ok(gate) => gate,
+        Err(busy) => return (refuse(state, ctx, &args, busy), false),
+    };
+    let (table, state) = (Arc::clone(table), Arc::clone(state));
+    let mut owned = std::mem::take(ctx);
+    let (reply, owned) = tokio::task::spawn_blocking(move || {
+        let _gate = gate;
+        let reply = dispatch(&table, &state, &mut owned, &args);
+        (reply, owned)
+    })
+    .await
+    .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+    *ctx = owned;
+    reply
+}
+
+/// Report a command that ran outside `dispatch` to MONITOR and the
+/// command statistics. These are blocking, pub/sub and MONITOR commands,
+/// which take next to no time once they don't wait anymore.
+fn report(state: &SharedState, ctx: &ConnCtx, args: &[Vec<u8>], response: &Frame) {
+    monitor::feed(state, ctx, args, response);
+    latency::record(state, ctx, args, response, std::time::Duration::ZERO);
+}
+
+/// Report a pub/sub command; its replies are pushes, one per channel.
+fn feed_pubsub(state: &SharedState, ctx: &ConnCtx, args: &[Vec<u8>]) {
+    report(state, ctx, args, &pubsub_msg(ctx.resp3, Vec::new()));
+}
+
+/// The next line for a MONITOR client; never ready for other clients.
+async fn next_monitor_line(
+    monitor_rx: &mut Option<mpsc::UnboundedReceiver<String>>,
+) -> Option<String> {
+    match monitor_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Write a command reply, applying the latency and disconnect faults the
+/// command triggered. Returns false if the connection should be closed.
+async fn write_reply(
+    conn: &mut Connection,
+    state: &SharedState,
+    ctx: &ConnCtx,
+    frame: &Frame,
+) -> bool {
+    let effect = state.faults.lock().unwrap().take_effect(ctx.client_id);
+    if !effect.delay.is_zero() {
+        tokio::time::sleep(effect.delay).await;
+    }
+    if effect.drop_connection {
+        let data = frame.serialize_resp(conn.resp3);
+        let _ = conn.write_all(&data[..data.len() / 2]).await;
+        return false;
+    }
+    conn.write_frame(frame).await.is_ok()
+}
+
 /// Wrap pub/sub confirmation/message frames: use Push in RESP3, Array in RESP2.
 fn pubsub_msg(resp3: bool, elements: Vec<Frame>) -> Frame {
     if resp3 {
@@ -514,305 +831,307 @@ fn pubsub_msg(resp3: bool, elements: Vec<Frame>) -> Frame {
     }
 }
 
-/// Handle blocking list commands (BLPOP, BRPOP, BRPOPLPUSH, BLMOVE).
+/// Format a CLIENT TRACKING invalidation: a RESP3 `invalidate` push, or
+/// for RESP2 a message on `__redis__:invalidate` if the client is
+/// subscribed to it. Other RESP2 clients can't receive invalidations.
+fn invalidation_frame(msg: Invalidation, resp3: bool, pubsub: Option<&PubsubCtx>) -> Option<Frame> {
+    let keys = match msg {
+        Invalidation::Keys(keys) => {
+            Frame::Array(keys.into_iter().map(|k| Frame::Bulk(k.into())).collect())
+        }
+        Invalidation::All => Frame::Null,
+    };
+    if resp3 {
+        return Some(Frame::Push(vec![Frame::Bulk("invalidate".into()), keys]));
+    }
+    let subscribed = pubsub.is_some_and(|ps| ps.channels().iter().any(|c| c == INVALIDATE_CHANNEL));
+    subscribed.then(|| {
+        Frame::Array(vec![
+            Frame::Bulk("message".into()),
+            Frame::Bulk(INVALIDATE_CHANNEL.into()),
+            keys,
+        ])
+    })
+}
+
+/// Handle blocking list and sorted set commands (BLPOP, BRPOP,
+/// BRPOPLPUSH, BLMOVE, BLMPOP, BZPOPMIN, BZPOPMAX, BZMPOP).
 /// These block until data is available or timeout expires.
 async fn handle_blocking_command(
     cmd: &str,
-    args: &[Vec<u8>],
+    command: &[Vec<u8>],
     state: &Arc<SharedState>,
     ctx: &mut ConnCtx,
     shutdown_rx: &mut broadcast::Receiver<()>,
 ) -> Frame {
-    use crate::dispatch::{
-        MSG_INVALID_TIMEOUT, MSG_SYNTAX_ERROR, MSG_TIMEOUT_IS_OUT_OF_RANGE, MSG_TIMEOUT_NEGATIVE,
-        MSG_WRONG_TYPE,
-    };
+    use crate::cmd::list::{list_move, list_mpop};
+    use crate::cmd::sorted_set::{zset_bpop, zset_mpop};
+    use crate::cmd::{parse_mpop, parse_timeout};
+    use crate::dispatch::MSG_SYNTAX_ERROR;
+    use crate::pubsub::NOTIFY_LIST;
     use crate::types::KeyType;
 
+    let args = &command[1..];
+    let min_args = match cmd {
+        "BLPOP" | "BRPOP" | "BZPOPMIN" | "BZPOPMAX" => 2,
+        "BRPOPLPUSH" => 3,
+        "BLMPOP" | "BZMPOP" => 4,
+        _ => 5,
+    };
+    if args.len() < min_args || (matches!(cmd, "BRPOPLPUSH" | "BLMOVE") && args.len() != min_args) {
+        return Frame::error(err_wrong_number(cmd));
+    }
+    let resp3 = ctx.resp3;
+
     match cmd {
         "BLPOP" | "BRPOP" => {
-            if args.len() < 2 {
-                return Frame::error(crate::dispatch::err_wrong_number(&cmd.to_lowercase()));
-            }
-            let keys = &args[..args.len() - 1];
-            let timeout_str = String::from_utf8_lossy(&args[args.len() - 1]);
-            let timeout_lower = timeout_str.to_lowercase();
-            if timeout_lower == "inf" || timeout_lower == "+inf" || timeout_lower == "-inf" {
-                return Frame::error(MSG_TIMEOUT_IS_OUT_OF_RANGE);
-            }
-            let timeout_s: f64 = match timeout_str.parse() {
+            let (keys, timeout) = args.split_at(args.len() - 1);
+            let timeout = match parse_timeout(&timeout[0]) {
                 Ok(t) => t,
-                Err(_) => return Frame::error(MSG_INVALID_TIMEOUT),
+                Err(err) => return err,
             };
-            if timeout_s < 0.0 {
-                return Frame::error(MSG_TIMEOUT_NEGATIVE);
-            }
-            let is_left = cmd == "BLPOP";
-
-            // Try immediate pop
-            {
-                let mut inner = state.lock();
-                let now = inner.effective_now();
-                for key_bytes in keys {
-                    let key = String::from_utf8_lossy(key_bytes).into_owned();
-                    let db = inner.db_mut(ctx.selected_db);
-                    db.check_ttl(&key);
-                    if let Some(t) = db.key_type(&key)
-                        && t != KeyType::List
-                    {
-                        return Frame::error(MSG_WRONG_TYPE);
-                    }
-                    let val = if is_left {
-                        db.list_lpop(&key, now)
+            let left = cmd == "BLPOP";
+            block_pop(
+                state,
+                ctx,
+                command,
+                keys,
+                KeyType::List,
+                timeout,
+                shutdown_rx,
+                |db, key, now| {
+                    let val = if left {
+                        db.list_lpop(key, now)
                     } else {
-                        db.list_rpop(&key, now)
-                    };
-                    if let Some(v) = val {
-                        state.notify.notify_waiters();
-                        return Frame::Array(vec![Frame::Bulk(key.into()), Frame::Bulk(v.into())]);
-                    }
-                }
-            }
-
-            // Block until data or timeout
-            let timeout_dur = if timeout_s == 0.0 {
-                std::time::Duration::from_secs(300) // max wait
+                        db.list_rpop(key, now)
+                    }?;
+                    db.notify(NOTIFY_LIST, if left { "lpop" } else { "rpop" }, key);
+                    db.notify_if_deleted(key);
+                    Some(Frame::Array(vec![
+                        Frame::Bulk(key.to_vec().into()),
+                        Frame::Bulk(val.into()),
+                    ]))
+                },
+            )
+            .await
+        }
+        "BRPOPLPUSH" | "BLMOVE" => {
+            let (src, dst) = (&args[0], &args[1]);
+            let (pop_left, push_left) = if cmd == "BRPOPLPUSH" {
+                (false, true)
             } else {
-                std::time::Duration::from_secs_f64(timeout_s)
-            };
-            let deadline = tokio::time::Instant::now() + timeout_dur;
-
-            loop {
-                tokio::select! {
-                    _ = state.notify.notified() => {
-                        let mut inner = state.lock();
-                        let now = inner.effective_now();
-                        for key_bytes in keys {
-                            let key = String::from_utf8_lossy(key_bytes).into_owned();
-                            let db = inner.db_mut(ctx.selected_db);
-                            db.check_ttl(&key);
-                            let val = if is_left {
-                                db.list_lpop(&key, now)
-                            } else {
-                                db.list_rpop(&key, now)
-                            };
-                            if let Some(v) = val {
-                                state.notify.notify_waiters();
-                                return Frame::Array(vec![
-                                    Frame::Bulk(key.into()),
-                                    Frame::Bulk(v.into()),
-                                ]);
-                            }
-                        }
-                    }
-                    _ = tokio::time::sleep_until(deadline) => {
-                        return Frame::NullArray;
-                    }
-                    _ = shutdown_rx.recv() => {
-                        return Frame::NullArray;
-                    }
+                let side = |arg: &[u8]| match String::from_utf8_lossy(arg).to_uppercase().as_str() {
+                    "LEFT" => Some(true),
+                    "RIGHT" => Some(false),
+                    _ => None,
+                };
+                match (side(&args[2]), side(&args[3])) {
+                    (Some(pop_left), Some(push_left)) => (pop_left, push_left),
+                    _ => return Frame::error(MSG_SYNTAX_ERROR),
                 }
-            }
+            };
+            let timeout = match parse_timeout(&args[args.len() - 1]) {
+                Ok(t) => t,
+                Err(err) => return err,
+            };
+            block_pop(
+                state,
+                ctx,
+                command,
+                std::slice::from_ref(src),
+                KeyType::List,
+                timeout,
+                shutdown_rx,
+                |db, key, now| list_move(db, key, dst, pop_left, push_left, now),
+            )
+            .await
         }
-        "BRPOPLPUSH" => {
-            if args.len() != 3 {
-                return Frame::error(crate::dispatch::err_wrong_number("brpoplpush"));
-            }
-            let src = String::from_utf8_lossy(&args[0]).into_owned();
-            let dst = String::from_utf8_lossy(&args[1]).into_owned();
-            let timeout_str = String::from_utf8_lossy(&args[2]);
-            let timeout_lower = timeout_str.to_lowercase();
-            if timeout_lower == "inf" || timeout_lower == "+inf" || timeout_lower == "-inf" {
-                return Frame::error(MSG_TIMEOUT_IS_OUT_OF_RANGE);
-            }
-            let timeout_s: f64 = match timeout_str.parse() {
+        "BLMPOP" | "BZMPOP" => {
+            let timeout = match parse_timeout(&args[0]) {
                 Ok(t) => t,
-                Err(_) => return Frame::error(MSG_INVALID_TIMEOUT),
+                Err(err) => return err,
             };
-            if timeout_s < 0.0 {
-                return Frame::error(MSG_TIMEOUT_NEGATIVE);
-            }
-
-            // Try immediate
-            {
-                let mut inner = state.lock();
-                let now = inner.effective_now();
-                let db = inner.db_mut(ctx.selected_db);
-                db.check_ttl(&src);
-                db.check_ttl(&dst);
-                if let Some(t) = db.key_type(&src)
-                    && t != KeyType::List
-                {
-                    return Frame::error(MSG_WRONG_TYPE);
-                }
-                if let Some(t) = db.key_type(&dst)
-                    && t != KeyType::List
-                {
-                    return Frame::error(MSG_WRONG_TYPE);
-                }
-                if let Some(val) = db.list_rpop(&src, now) {
-                    db.list_lpush(&dst, std::slice::from_ref(&val), now);
-                    state.notify.notify_waiters();
-                    return Frame::Bulk(val.into());
-                }
-            }
-
-            let timeout_dur = if timeout_s == 0.0 {
-                std::time::Duration::from_secs(300)
+            if cmd == "BLMPOP" {
+                let opts = match parse_mpop(&args[1..], ["LEFT", "RIGHT"]) {
+                    Ok(opts) => opts,
+                    Err(err) => return err,
+                };
+                block_pop(
+                    state,
+                    ctx,
+                    command,
+                    &opts.keys,
+                    KeyType::List,
+                    timeout,
+                    shutdown_rx,
+                    |db, key, now| list_mpop(db, key, opts.first, opts.count, now),
+                )
+                .await
             } else {
-                std::time::Duration::from_secs_f64(timeout_s)
-            };
-            let deadline = tokio::time::Instant::now() + timeout_dur;
-
-            loop {
-                tokio::select! {
-                    _ = state.notify.notified() => {
-                        let mut inner = state.lock();
-                        let now = inner.effective_now();
-                        let db = inner.db_mut(ctx.selected_db);
-                        db.check_ttl(&src);
-                        if let Some(val) = db.list_rpop(&src, now) {
-                            db.list_lpush(&dst, std::slice::from_ref(&val), now);
-                            state.notify.notify_waiters();
-                            return Frame::Bulk(val.into());
-                        }
-                    }
-                    _ = tokio::time::sleep_until(deadline) => {
-                        return Frame::NullArray;
-                    }
-                    _ = shutdown_rx.recv() => {
-                        return Frame::NullArray;
-                    }
-                }
+                let opts = match parse_mpop(&args[1..], ["MIN", "MAX"]) {
+                    Ok(opts) => opts,
+                    Err(err) => return err,
+                };
+                block_pop(
+                    state,
+                    ctx,
+                    command,
+                    &opts.keys,
+                    KeyType::SortedSet,
+                    timeout,
+                    shutdown_rx,
+                    |db, key, now| zset_mpop(db, key, !opts.first, opts.count, resp3, now),
+                )
+                .await
             }
         }
-        "BLMOVE" => {
-            if args.len() != 5 {
-                return Frame::error(crate::dispatch::err_wrong_number("blmove"));
-            }
-            let src = String::from_utf8_lossy(&args[0]).into_owned();
-            let dst = String::from_utf8_lossy(&args[1]).into_owned();
-            let src_dir = String::from_utf8_lossy(&args[2]).to_uppercase();
-            let dst_dir = String::from_utf8_lossy(&args[3]).to_uppercase();
-
-            let pop_left = match src_dir.as_str() {
-                "LEFT" => true,
-                "RIGHT" => false,
-                _ => return Frame::error(MSG_SYNTAX_ERROR),
-            };
-            let push_left = match dst_dir.as_str() {
-                "LEFT" => true,
-                "RIGHT" => false,
-                _ => return Frame::error(MSG_SYNTAX_ERROR),
-            };
-
-            let timeout_str = String::from_utf8_lossy(&args[4]);
-            let timeout_lower = timeout_str.to_lowercase();
-            if timeout_lower == "inf" || timeout_lower == "+inf" || timeout_lower == "-inf" {
-                return Frame::error(MSG_TIMEOUT_IS_OUT_OF_RANGE);
-            }
-            let timeout_s: f64 = match timeout_str.parse() {
+        "BZPOPMIN" | "BZPOPMAX" => {
+            let (keys, timeout) = args.split_at(args.len() - 1);
+            let timeout = match parse_timeout(&timeout[0]) {
                 Ok(t) => t,
-                Err(_) => return Frame::error(MSG_INVALID_TIMEOUT),
+                Err(err) => return err,
             };
-            if timeout_s < 0.0 {
-                return Frame::error(MSG_TIMEOUT_NEGATIVE);
-            }
+            let max = cmd == "BZPOPMAX";
+            block_pop(
+                state,
+                ctx,
+                command,
+                keys,
+                KeyType::SortedSet,
+                timeout,
+                shutdown_rx,
+                |db, key, now| zset_bpop(db, key, max, resp3, now),
+            )
+            .await
+        }
+        _ => Frame::error("ERR unsupported blocking command"),
+    }
+}
 
-            // Try immediate
-            {
-                let mut inner = state.lock();
-                let now = inner.effective_now();
-                let db = inner.db_mut(ctx.selected_db);
-                db.check_ttl(&src);
-                db.check_ttl(&dst);
-                if let Some(t) = db.key_type(&src)
-                    && t != KeyType::List
-                {
-                    return Frame::error(MSG_WRONG_TYPE);
-                }
-                if let Some(t) = db.key_type(&dst)
-                    && t != KeyType::List
-                {
-                    return Frame::error(MSG_WRONG_TYPE);
-                }
+/// Block until `pop` takes something from one of `keys`, or until the
+/// timeout (in seconds, 0 for none) expires.
+///
+/// `pop` is called under the lock, in key order, for every key that holds
+/// a `kind` value, and returns the reply if it took something. The reply
+/// goes to the replicas as `command` before other writes to the database. Clients
+/// blocked on the same key are served in the order they blocked (see
+/// `crate::blocking`). A key of another type is an error right away, but
+/// is skipped once the client is blocked.
+#[allow(clippy::too_many_arguments)]
+async fn block_pop(
+    state: &Arc<SharedState>,
+    ctx: &mut ConnCtx,
+    command: &[Vec<u8>],
+    keys: &[Vec<u8>],
+    kind: crate::types::KeyType,
+    timeout_s: f64,
+    shutdown_rx: &mut broadcast::Receiver<()>,
+    mut pop: impl FnMut(&mut crate::db::RedisDB, &[u8], std::time::SystemTime) -> Option<Frame>,
+) -> Frame {
+    /// Takes the client out of the queue if it stops waiting early, e.g.
+    /// because the connection closed.
+    struct Unblock<'a>(&'a SharedState, u64);
+    impl Drop for Unblock<'_> {
+        fn drop(&mut self) {
+            self.0.lock().blocked.unblock(self.1);
+        }
+    }
 
-                // Save TTL when src == dst
-                let saved_ttl = if src == dst {
-                    db.ttl.get(&src).cloned()
-                } else {
-                    None
-                };
-                let val = if pop_left {
-                    db.list_lpop(&src, now)
-                } else {
-                    db.list_rpop(&src, now)
-                };
-                if let Some(v) = val {
-                    if push_left {
-                        db.list_lpush(&dst, std::slice::from_ref(&v), now);
-                    } else {
-                        db.list_rpush(&dst, std::slice::from_ref(&v), now);
-                    }
-                    if let Some(ttl) = saved_ttl {
-                        db.ttl.insert(dst.clone(), ttl);
+    let timeout_dur = if timeout_s == 0.0 {
+        std::time::Duration::from_secs(300) // max wait
+    } else {
+        std::time::Duration::from_secs_f64(timeout_s)
+    };
+    let deadline = tokio::time::Instant::now() + timeout_dur;
+    let db_idx = ctx.selected_db;
+    let mut blocked: Option<Unblock> = None;
+
+    loop {
+        // Listen before looking, so a change right after we look isn't missed.
+        let notified = state.notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let id = blocked.as_ref().map(|b| b.1);
+        let reply = {
+            let _running = CommandScope::enter(ctx.client_id);
+            let propagator = Propagator::new(state, db_idx, false);
+            let now = state.now();
+            let mut db = state.db(db_idx);
+            let mut inner = state.lock();
+            let mut reply = None;
+            for key in keys {
+                db.check_ttl(key);
+                match db.key_type(key) {
+                    Some(t) if t == kind => {}
+                    Some(_) if id.is_none() => {
+                        reply = Some(Frame::error(crate::dispatch::MSG_WRONG_TYPE));
+                        break;
                     }
-                    state.notify.notify_waiters();
-                    return Frame::Bulk(v.into());
+                    _ => continue,
+                }
+                if !inner.blocked.may_serve(id, db_idx, key, kind) {
+                    continue;
+                }
+                reply = pop(&mut db, key, now);
+                if reply.is_some() {
+                    break;
                 }
             }
 
-            let timeout_dur = if timeout_s == 0.0 {
-                std::time::Duration::from_secs(300)
-            } else {
-                std::time::Duration::from_secs_f64(timeout_s)
-            };
-            let deadline = tokio::time::Instant::now() + timeout_dur;
-
-            loop {
-                tokio::select! {
-                    _ = state.notify.notified() => {
-                        let mut inner = state.lock();
-                        let now = inner.effective_now();
-                        let db = inner.db_mut(ctx.selected_db);
-                        db.check_ttl(&src);
-                        // Save TTL when src == dst
-                        let saved_ttl = if src == dst {
-                            db.ttl.get(&src).cloned()
-                        } else {
-                            None
-                        };
-                        let val = if pop_left {
-                            db.list_lpop(&src, now)
-                        } else {
-                            db.list_rpop(&src, now)
-                        };
-                        if let Some(v) = val {
-                            if push_left {
-                                db.list_lpush(&dst, std::slice::from_ref(&v), now);
-                            } else {
-                                db.list_rpush(&dst, std::slice::from_ref(&v), now);
-                            }
-                            if let Some(ttl) = saved_ttl {
-                                db.ttl.insert(dst.clone(), ttl);
-                            }
-                            state.notify.notify_waiters();
-                            return Frame::Bulk(v.into());
-                        }
-                    }
-                    _ = tokio::time::sleep_until(deadline) => {
-                        return Frame::NullArray;
-                    }
-                    _ = shutdown_rx.recv() => {
-                        return Frame::NullArray;
-                    }
+            match id {
+                Some(id) if reply.is_some() => inner.blocked.unblock(id),
+                None if reply.is_none() => {
+                    blocked = Some(Unblock(state, inner.blocked.block(db_idx, keys, kind)));
                 }
+                _ => {}
+            }
+            drop((inner, db));
+            state.publish_changes(Some(ctx));
+            if let Some(reply) = &reply {
+                propagate_served(propagator, ctx, command, reply);
+            }
+            reply
+        };
+        if let Some(reply) = reply {
+            state.notify.notify_waiters();
+            return reply;
+        }
+
+        tokio::select! {
+            _ = &mut notified => {}
+            _ = tokio::time::sleep_until(deadline) => {
+                return Frame::NullArray;
+            }
+            _ = shutdown_rx.recv() => {
+                return Frame::NullArray;
             }
         }
-        _ => Frame::error("ERR unsupported blocking command"),
     }
 }
 
+/// Send a blocking command that got served to the replicas. `propagator`
+/// was started before the attempt that served it.
+fn propagate_served(
+    mut propagator: Propagator,
+    ctx: &mut ConnCtx,
+    command: &[Vec<u8>],
+    response: &Frame,
+) {
+    propagator.record_served(command, response);
+    if let Some(offset) = propagator.finish(false, ctx.selected_db) {
+        ctx.repl_offset = offset;
+    }
+}
+
+/// The list and sorted set commands `handle_blocking_command` runs.
+fn is_blocking_command(cmd: &str) -> bool {
+    matches!(
+        cmd,
+        "BLPOP" | "BRPOP" | "BRPOPLPUSH" | "BLMOVE" | "BLMPOP" | "BZPOPMIN" | "BZPOPMAX" | "BZMPOP"
+    )
+}
+
 /// Check if BLOCK argument is present in the command args.
 fn has_block_arg(args: &[Vec<u8>]) -> bool {
     args.iter()
@@ -822,7 +1141,7 @@ fn has_block_arg(args: &[Vec<u8>]) -> bool {
 /// Handle blocking XREAD/XREADGROUP commands.
 async fn handle_blocking_stream_command(
     cmd: &str,
-    args: &[Vec<u8>],
+    command: &[Vec<u8>],
     state: &Arc<SharedState>,
     ctx: &mut ConnCtx,
     shutdown_rx: &mut broadcast::Receiver<()>,
@@ -830,6 +1149,7 @@ async fn handle_blocking_stream_command(
     use crate::dispatch::MSG_WRONG_TYPE;
     use crate::types::{KeyType, Stream};
 
+    let args = &command[1..];
     match cmd {
         "XREAD" => {
             if args.len() < 3 {
@@ -892,16 +1212,12 @@ async fn handle_blocking_stream_command(
             }
 
             let half = remaining.len() / 2;
-            let keys: Vec<String> = remaining[..half]
-                .iter()
-                .map(|a| String::from_utf8_lossy(a).to_string())
-                .collect();
+            let keys: Vec<Vec<u8>> = remaining[..half].to_vec();
 
             // Resolve $ IDs to current last IDs
             let mut ids = Vec::with_capacity(half);
             {
-                let inner = state.lock();
-                let db = inner.db(ctx.selected_db);
+                let db = state.db(ctx.selected_db);
                 for (idx, a) in remaining[half..].iter().enumerate() {
                     let s = String::from_utf8_lossy(a).to_string();
                     if s == "$" {
@@ -925,12 +1241,12 @@ async fn handle_blocking_stream_command(
 
             // Helper closure to try reading from streams
             let try_read = |state: &SharedState,
-                            keys: &[String],
+                            keys: &[Vec<u8>],
                             ids: &[String],
                             count: Option<usize>|
              -> Option<Frame> {
-                let inner = state.lock();
-                let db = inner.db(ctx.selected_db);
+                let _keyspace = state.lock_keyspace(Isolation::Read);
+                let db = state.db(ctx.selected_db);
                 let mut results = Vec::new();
 
                 for (idx, key) in keys.iter().enumerate() {
@@ -1088,10 +1404,7 @@ async fn handle_blocking_stream_command(
             }
 
             let half = remaining.len() / 2;
-            let keys: Vec<String> = remaining[..half]
-                .iter()
-                .map(|a| String::from_utf8_lossy(a).to_string())
-                .collect();
+            let keys: Vec<Vec<u8>> = remaining[..half].to_vec();
             let ids: Vec<String> = remaining[half..]
                 .iter()
                 .map(|a| String::from_utf8_lossy(a).to_string())
@@ -1116,8 +1429,7 @@ async fn handle_blocking_stream_command(
 
             // Validate group existence for all streams before blocking
             {
-                let inner = state.lock();
-                let db = inner.db(ctx.selected_db);
+                let db = state.db(ctx.selected_db);
                 for key in &keys {
                     if let Some(kt) = db.keys.get(key)
                         && *kt != KeyType::Stream
@@ -1129,14 +1441,16 @@ async fn handle_blocking_stream_command(
                             if !stream.groups.contains_key(&group_name) {
                                 return Frame::error(format!(
                                     "NOGROUP No such consumer group '{}' for key name '{}'",
-                                    group_name, key
+                                    group_name,
+                                    String::from_utf8_lossy(key)
                                 ));
                             }
                         }
                         None => {
                             return Frame::error(format!(
                                 "NOGROUP No such consumer group '{}' for key name '{}'",
-                                group_name, key
+                                group_name,
+                                String::from_utf8_lossy(key)
                             ));
                         }
                     }
@@ -1145,16 +1459,16 @@ async fn handle_blocking_stream_command(
 
             // Helper closure to try reading from groups
             let try_read_group = |state: &SharedState,
-                                  ctx: &ConnCtx,
-                                  keys: &[String],
+                                  ctx: &mut ConnCtx,
+                                  keys: &[Vec<u8>],
                                   group_name: &str,
                                   consumer_name: &str,
                                   count: Option<usize>,
                                   noack: bool|
              -> Result<Option<Frame>, Frame> {
-                let mut inner = state.lock();
-                let now = inner.effective_now();
-                let db = inner.db_mut(ctx.selected_db);
+                let propagator = Propagator::new(state, ctx.selected_db, false);
+                let now = state.now();
+                let mut db = state.db(ctx.selected_db);
                 let mut results = Vec::new();
 
                 for key in keys {
@@ -1163,7 +1477,8 @@ async fn handle_blocking_stream_command(
                         None => {
                             return Err(Frame::error(format!(
                                 "NOGROUP No such consumer group '{}' for key name '{}'",
-                                group_name, key
+                                group_name,
+                                String::from_utf8_lossy(key)
                             )));
                         }
                     };
@@ -1203,10 +1518,12 @@ async fn handle_blocking_stream_command(
                 }
 
                 if results.is_empty() {
-                    Ok(None)
-                } else {
-                    Ok(Some(Frame::Array(results)))
+                    return Ok(None);
                 }
+                drop(db);
+                let reply = Frame::Array(results);
+                propagate_served(propagator, ctx, command, &reply);
+                Ok(Some(reply))
             };
 
             // Try immediate read
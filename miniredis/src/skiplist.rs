@@ -0,0 +1,456 @@
+//! Skiplist ordered by (score, member), the ordered index behind
+//! `types::SortedSet`.
+//!
+//! Modeled on Redis' zskiplist: every link records how many elements it
+//! skips ("span"), so finding the element at a rank, or the rank of an
+//! element, takes O(log n). Nodes live in an arena and link to each other
+//! by index.
+use std::cmp::Ordering;
+
+use crate::types::Direction;
+
+const MAX_LEVEL: usize = 32;
+/// Each level holds about a quarter of the nodes of the level below.
+const LEVEL_P: u32 = u32::MAX / 4;
+/// The index of the header node, which holds no element.
+const HEAD: usize = 0;
+const NIL: usize = usize::MAX;
+
+#[derive(Clone, Copy, Debug)]
+struct Link {
+    next: usize,
+    /// Number of elements this link moves forward. Links to NIL count the
+    /// elements up to the end of the list.
+    span: usize,
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    score: f64,
+    member: Vec<u8>,
+    prev: usize,
+    links: Vec<Link>,
+}
+
+/// Compare (score, member) pairs. Scores compare as floats, so -0 and 0
+/// are equal and ordered by member.
+fn cmp_elem(score: f64, member: &[u8], other_score: f64, other_member: &[u8]) -> Ordering {
+    score
+        .partial_cmp(&other_score)
+        .unwrap_or(Ordering::Equal)
+        .then_with(|| member.cmp(other_member))
+}
+
+/// A skiplist of (score, member) elements. Members must be unique; that's
+/// up to the caller.
+#[derive(Clone, Debug)]
+pub struct SkipList {
+    nodes: Vec<Node>,
+    /// Unused slots in `nodes`.
+    free: Vec<usize>,
+    tail: usize,
+    level: usize,
+    len: usize,
+    rng: u64,
+}
+
+impl Default for SkipList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SkipList {
+    pub fn new() -> Self {
+        SkipList {
+            nodes: vec![Node {
+                score: 0.0,
+                member: Vec::new(),
+                prev: NIL,
+                links: vec![Link { next: NIL, span: 0 }; MAX_LEVEL],
+            }],
+            free: Vec::new(),
+            tail: NIL,
+            level: 1,
+            len: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// xorshift64; the levels only need to be spread, not unpredictable.
+    fn random_level(&mut self) -> usize {
+        let mut level = 1;
+        while level < MAX_LEVEL {
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 7;
+            self.rng ^= self.rng << 17;
+            if (self.rng as u32) >= LEVEL_P {
+                break;
+            }
+            level += 1;
+        }
+        level
+    }
+
+    fn before(&self, node: usize, score: f64, member: &[u8]) -> bool {
+        let n = &self.nodes[node];
+        cmp_elem(n.score, &n.member, score, member) == Ordering::Less
+    }
+
+    /// For every level, the last node before (score, member).
+    fn find_update(&self, score: f64, member: &[u8]) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let mut update = [HEAD; MAX_LEVEL];
+        let mut rank = [0; MAX_LEVEL];
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            rank[i] = if i + 1 == self.level { 0 } else { rank[i + 1] };
+            loop {
+                let link = self.nodes[x].links[i];
+                if link.next != NIL && self.before(link.next, score, member) {
+                    rank[i] += link.span;
+                    x = link.next;
+                } else {
+                    break;
+                }
+            }
+            update[i] = x;
+        }
+        (update, rank)
+    }
+
+    /// Insert an element. The member must not be in the list already.
+    pub fn insert(&mut self, score: f64, member: Vec<u8>) {
+        let (mut update, mut rank) = self.find_update(score, &member);
+        let level = self.random_level();
+        if level > self.level {
+            for i in self.level..level {
+                rank[i] = 0;
+                update[i] = HEAD;
+                self.nodes[HEAD].links[i].span = self.len;
+            }
+            self.level = level;
+        }
+
+        let node = Node {
+            score,
+            member,
+            prev: if update[0] == HEAD { NIL } else { update[0] },
+            links: vec![Link { next: NIL, span: 0 }; level],
+        };
+        let x = match self.free.pop() {
+            Some(slot) => {
+                self.nodes[slot] = node;
+                slot
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+
+        for i in 0..level {
+            let prev = self.nodes[update[i]].links[i];
+            let skipped = rank[0] - rank[i];
+            self.nodes[x].links[i] = Link {
+                next: prev.next,
+                span: prev.span - skipped,
+            };
+            self.nodes[update[i]].links[i] = Link {
+                next: x,
+                span: skipped + 1,
+            };
+        }
+        for (i, &u) in update.iter().enumerate().take(self.level).skip(level) {
+            self.nodes[u].links[i].span += 1;
+        }
+
+        match self.nodes[x].links[0].next {
+            NIL => self.tail = x,
+            next => self.nodes[next].prev = x,
+        }
+        self.len += 1;
+    }
+
+    /// Remove an element. Returns false if it wasn't there.
+    pub fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let (update, _) = self.find_update(score, member);
+        let x = self.nodes[update[0]].links[0].next;
+        if x == NIL {
+            return false;
+        }
+        let n = &self.nodes[x];
+        if cmp_elem(n.score, &n.member, score, member) != Ordering::Equal {
+            return false;
+        }
+
+        for (i, &u) in update.iter().enumerate().take(self.level) {
+            let link = self.nodes[u].links[i];
+            if link.next == x {
+                let removed = self.nodes[x].links[i];
+                self.nodes[u].links[i] = Link {
+                    next: removed.next,
+                    span: link.span + removed.span - 1,
+                };
+            } else {
+                self.nodes[u].links[i].span -= 1;
+            }
+        }
+        let (prev, next) = (self.nodes[x].prev, self.nodes[x].links[0].next);
+        match next {
+            NIL => self.tail = prev,
+            next => self.nodes[next].prev = prev,
+        }
+        while self.level > 1 && self.nodes[HEAD].links[self.level - 1].next == NIL {
+            self.level -= 1;
+        }
+
+        let node = &mut self.nodes[x];
+        node.member = Vec::new();
+        node.links = Vec::new();
+        self.free.push(x);
+        self.len -= 1;
+        true
+    }
+
+    /// The number of leading elements for which `pred(score, member)`
+    /// holds. `pred` must hold for a prefix of the list and not after it,
+    /// e.g. "score < 5".
+    pub fn count_while(&self, pred: impl Fn(f64, &[u8]) -> bool) -> usize {
+        let mut count = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[x].links[i];
+                if link.next == NIL {
+                    break;
+                }
+                let n = &self.nodes[link.next];
+                if !pred(n.score, &n.member) {
+                    break;
+                }
+                count += link.span;
+                x = link.next;
+            }
+        }
+        count
+    }
+
+    /// The 0-based ascending rank of (score, member), if it's in the list.
+    pub fn rank(&self, score: f64, member: &[u8]) -> Option<usize> {
+        let (update, rank) = self.find_update(score, member);
+        let x = self.nodes[update[0]].links[0].next;
+        if x == NIL {
+            return None;
+        }
+        let n = &self.nodes[x];
+        (cmp_elem(n.score, &n.member, score, member) == Ordering::Equal).then_some(rank[0])
+    }
+
+    /// The node at a 0-based ascending rank.
+    fn node_at(&self, rank: usize) -> usize {
+        if rank >= self.len {
+            return NIL;
+        }
+        let target = rank + 1;
+        let mut traversed = 0;
+        let mut x = HEAD;
+        for i in (0..self.level).rev() {
+            loop {
+                let link = self.nodes[x].links[i];
+                if link.next != NIL && traversed + link.span <= target {
+                    traversed += link.span;
+                    x = link.next;
+                } else {
+                    break;
+                }
+            }
+            if traversed == target {
+                return x;
+            }
+        }
+        NIL
+    }
+
+    /// Iterate over the elements at positions `start..end`, where positions
+    /// count from the lowest element for `Direction::Asc` and from the
+    /// highest for `Direction::Desc`.
+    pub fn range(&self, start: usize, end: usize, dir: Direction) -> Iter<'_> {
+        let end = end.min(self.len);
+        if start >= end {
+            return Iter {
+                list: self,
+                node: NIL,
+                dir,
+                remaining: 0,
+            };
+        }
+        let node = match dir {
+            Direction::Asc => self.node_at(start),
+            Direction::Desc => self.node_at(self.len - 1 - start),
+        };
+        Iter {
+            list: self,
+            node,
+            dir,
+            remaining: end - start,
+        }
+    }
+
+    /// Iterate over all elements.
+    pub fn iter(&self, dir: Direction) -> Iter<'_> {
+        Iter {
+            list: self,
+            node: match dir {
+                Direction::Asc => self.nodes[HEAD].links[0].next,
+                Direction::Desc => self.tail,
+            },
+            dir,
+            remaining: self.len,
+        }
+    }
+}
+
+/// Iterator over (score, member) elements, returned by [`SkipList::range`].
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    node: usize,
+    dir: Direction,
+    remaining: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (f64, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 || self.node == NIL {
+            return None;
+        }
+        let n = &self.list.nodes[self.node];
+        self.node = match self.dir {
+            Direction::Asc => n.links[0].next,
+            Direction::Desc => n.prev,
+        };
+        self.remaining -= 1;
+        Some((n.score, &n.member))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn members(it: Iter<'_>) -> Vec<String> {
+        it.map(|(_, m)| String::from_utf8_lossy(m).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_skiplist_order() {
+        let mut sl = SkipList::new();
+        sl.insert(2.0, b"b".to_vec());
+        sl.insert(1.0, b"z".to_vec());
+        sl.insert(2.0, b"a".to_vec());
+        sl.insert(f64::NEG_INFINITY, b"m".to_vec());
+        assert_eq!(sl.len(), 4);
+        assert_eq!(members(sl.iter(Direction::Asc)), vec!["m", "z", "a", "b"]);
+        assert_eq!(members(sl.iter(Direction::Desc)), vec!["b", "a", "z", "m"]);
+        assert_eq!(sl.rank(2.0, b"a"), Some(2));
+        assert_eq!(sl.rank(2.0, b"c"), None);
+        assert_eq!(sl.rank(1.0, b"a"), None);
+    }
+
+    #[test]
+    fn test_skiplist_remove() {
+        let mut sl = SkipList::new();
+        for i in 0..10 {
+            sl.insert(i as f64, format!("m{i}").into_bytes());
+        }
+        assert!(sl.remove(3.0, b"m3"));
+        assert!(!sl.remove(3.0, b"m3"));
+        assert!(!sl.remove(4.0, b"m5"));
+        assert!(sl.remove(9.0, b"m9"));
+        assert!(sl.remove(0.0, b"m0"));
+        assert_eq!(sl.len(), 7);
+        assert_eq!(
+            members(sl.iter(Direction::Asc)),
+            vec!["m1", "m2", "m4", "m5", "m6", "m7", "m8"]
+        );
+        assert_eq!(members(sl.iter(Direction::Desc))[0], "m8");
+        // Freed slots are reused.
+        sl.insert(3.5, b"x".to_vec());
+        assert_eq!(sl.nodes.len(), 11);
+        assert_eq!(sl.rank(3.5, b"x"), Some(2));
+    }
+
+    #[test]
+    fn test_skiplist_range() {
+        let mut sl = SkipList::new();
+        for i in 0..100 {
+            sl.insert(i as f64, format!("{i:03}").into_bytes());
+        }
+        assert_eq!(
+            members(sl.range(10, 13, Direction::Asc)),
+            vec!["010", "011", "012"]
+        );
+        assert_eq!(members(sl.range(0, 2, Direction::Desc)), vec!["099", "098"]);
+        assert_eq!(
+            members(sl.range(98, 200, Direction::Asc)),
+            vec!["098", "099"]
+        );
+        assert_eq!(sl.range(5, 5, Direction::Asc).count(), 0);
+        assert_eq!(sl.range(200, 300, Direction::Asc).count(), 0);
+        assert_eq!(sl.count_while(|score, _| score < 42.0), 42);
+        assert_eq!(sl.count_while(|_, m| m <= b"050".as_slice()), 51);
+        assert_eq!(sl.count_while(|_, _| false), 0);
+    }
+
+    #[test]
+    fn test_skiplist_random_ops() {
+        // Compare against a sorted Vec.
+        let mut sl = SkipList::new();
+        let mut model: Vec<(f64, Vec<u8>)> = Vec::new();
+        let mut seed = 42u64;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as usize
+        };
+        for _ in 0..2000 {
+            let score = (next() % 50) as f64;
+            let member = format!("m{}", next() % 300).into_bytes();
+            match model.iter().position(|(_, m)| *m == member) {
+                Some(pos) => {
+                    let (old, _) = model.remove(pos);
+                    assert!(sl.remove(old, &member));
+                }
+                None => {
+                    sl.insert(score, member.clone());
+                    model.push((score, member));
+                }
+            }
+            model.sort_by(|a, b| cmp_elem(a.0, &a.1, b.0, &b.1));
+            assert_eq!(sl.len(), model.len());
+        }
+        let all: Vec<(f64, Vec<u8>)> = sl
+            .iter(Direction::Asc)
+            .map(|(s, m)| (s, m.to_vec()))
+            .collect();
+        assert_eq!(all, model);
+        for (i, (score, member)) in model.iter().enumerate() {
+            assert_eq!(sl.rank(*score, member), Some(i));
+            let (s, m) = sl.range(i, i + 1, Direction::Asc).next().unwrap();
+            assert_eq!((s, m), (*score, member.as_slice()));
+        }
+    }
+}
@@ -0,0 +1,177 @@
+/// Server-assisted client side caching (CLIENT TRACKING).
+use std::collections::{HashMap, HashSet};
+use tokio::sync::mpsc;
+
+/// The channel RESP2 clients subscribe to when receiving redirected
+/// invalidation messages.
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// An invalidation message delivered to a connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Invalidation {
+    /// These keys were modified.
+    Keys(Vec<Vec<u8>>),
+    /// The whole keyspace was flushed (sent as a null key list).
+    All,
+}
+
+/// CLIENT TRACKING options for a single client.
+#[derive(Debug, Clone, Default)]
+pub struct TrackingOpts {
+    /// Client ID invalidations are redirected to (REDIRECT).
+    pub redirect: Option<u64>,
+    /// Broadcasting mode: invalidate every key matching `prefixes`.
+    pub bcast: bool,
+    /// Key prefixes for BCAST mode. Empty means every key.
+    pub prefixes: Vec<Vec<u8>>,
+    /// Only track keys read right after CLIENT CACHING yes.
+    pub optin: bool,
+    /// Track keys unless the read follows CLIENT CACHING no.
+    pub optout: bool,
+    /// Don't send invalidations for keys modified by this client.
+    pub noloop: bool,
+}
+
+struct TrackedClient {
+    tx: mpsc::UnboundedSender<Invalidation>,
+    opts: Option<TrackingOpts>,
+}
+
+/// Registry of connected clients and the keys they track.
+#[derive(Default)]
+pub struct TrackingTable {
+    clients: HashMap<u64, TrackedClient>,
+    /// Default mode: key -> IDs of the clients that read it.
+    keys: HashMap<Vec<u8>, HashSet<u64>>,
+}
+
+impl TrackingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a connection; invalidations for it arrive on the returned
+    /// receiver.
+    pub fn register(&mut self, id: u64) -> mpsc::UnboundedReceiver<Invalidation> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.clients.insert(id, TrackedClient { tx, opts: None });
+        rx
+    }
+
+    /// Forget a connection, and everything it tracked.
+    pub fn unregister(&mut self, id: u64) {
+        self.clients.remove(&id);
+        self.forget_keys(id);
+    }
+
+    pub fn exists(&self, id: u64) -> bool {
+        self.clients.contains_key(&id)
+    }
+
+    /// Tracking options of a client, if tracking is on.
+    pub fn opts(&self, id: u64) -> Option<&TrackingOpts> {
+        self.clients.get(&id).and_then(|c| c.opts.as_ref())
+    }
+
+    /// Turn tracking on (`Some`) or off (`None`) for a client.
+    pub fn set_opts(&mut self, id: u64, opts: Option<TrackingOpts>) {
+        if opts.is_none() {
+            self.forget_keys(id);
+        }
+        if let Some(c) = self.clients.get_mut(&id) {
+            c.opts = opts;
+        }
+    }
+
+    /// Whether any client has tracking enabled.
+    pub fn active(&self) -> bool {
+        self.clients.values().any(|c| c.opts.is_some())
+    }
+
+    /// Remember that client `id` read `keys` (default mode only).
+    pub fn track(&mut self, id: u64, keys: impl IntoIterator<Item = Vec<u8>>) {
+        for key in keys {
+            self.keys.entry(key).or_default().insert(id);
+        }
+    }
+
+    fn forget_keys(&mut self, id: u64) {
+        self.keys.retain(|_, ids| {
+            ids.remove(&id);
+            !ids.is_empty()
+        });
+    }
+
+    /// Send invalidations for `keys`, modified by client `writer` (if the
+    /// change didn't come from the server itself, e.g. an expiry).
+    pub fn invalidate(&mut self, keys: &[Vec<u8>], writer: Option<u64>) {
+        let mut pending: HashMap<u64, Vec<Vec<u8>>> = HashMap::new();
+        for key in keys {
+            let mut targets: Vec<u64> = self
+                .keys
+                .remove(key)
+                .map(|ids| ids.into_iter().collect())
+                .unwrap_or_default();
+            for (id, c) in &self.clients {
+                if let Some(opts) = &c.opts
+                    && opts.bcast
+                    && (opts.prefixes.is_empty()
+                        || opts.prefixes.iter().any(|p| key.starts_with(p)))
+                {
+                    targets.push(*id);
+                }
+            }
+            for id in targets {
+                let Some(opts) = self.opts(id) else {
+                    continue;
+                };
+                if opts.noloop && writer == Some(id) {
+                    continue;
+                }
+                let keys = pending.entry(opts.redirect.unwrap_or(id)).or_default();
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        for (id, keys) in pending {
+            self.send(id, Invalidation::Keys(keys));
+        }
+    }
+
+    /// Invalidate everything, for FLUSHDB and FLUSHALL.
+    pub fn invalidate_all(&mut self) {
+        self.keys.clear();
+        let targets: HashSet<u64> = self
+            .clients
+            .iter()
+            .filter_map(|(id, c)| c.opts.as_ref().map(|o| o.redirect.unwrap_or(*id)))
+            .collect();
+        for id in targets {
+            self.send(id, Invalidation::All);
+        }
+    }
+
+    fn send(&self, id: u64, msg: Invalidation) {
+        if let Some(c) = self.clients.get(&id) {
+            let _ = c.tx.send(msg);
+        }
+    }
+}
+
+/// Check that no BCAST prefix is a prefix of another one, the way Redis
+/// validates CLIENT TRACKING ... PREFIX arguments.
+pub fn check_prefixes(prefixes: &[Vec<u8>]) -> Result<(), String> {
+    for (i, a) in prefixes.iter().enumerate() {
+        for b in &prefixes[i + 1..] {
+            if a.starts_with(b) || b.starts_with(a) {
+                return Err(format!(
+                    "ERR Prefix '{}' overlaps with another provided prefix '{}'. Prefixes for a single client must not overlap.",
+                    String::from_utf8_lossy(a),
+                    String::from_utf8_lossy(b)
+                ));
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::skiplist::SkipList;
+
 /// The type tag for a Redis key.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KeyType {
@@ -10,6 +12,21 @@ pub enum KeyType {
     SortedSet,
     Stream,
     HyperLogLog,
+    /// A RedisJSON document.
+    #[cfg(feature = "json")]
+    Json,
+    /// A RedisBloom scalable Bloom filter.
+    #[cfg(feature = "bloom")]
+    Bloom,
+    /// A RedisBloom Cuckoo filter.
+    #[cfg(feature = "bloom")]
+    Cuckoo,
+    /// A RedisBloom Count-Min sketch.
+    #[cfg(feature = "bloom")]
+    CountMinSketch,
+    /// A RedisBloom Top-K.
+    #[cfg(feature = "bloom")]
+    TopK,
 }
 
 impl KeyType {
@@ -23,6 +40,16 @@ impl KeyType {
             KeyType::SortedSet => "zset",
             KeyType::Stream => "stream",
             KeyType::HyperLogLog => "hll", // not "string" — miniredis uses a distinct type
+            #[cfg(feature = "json")]
+            KeyType::Json => "ReJSON-RL",
+            #[cfg(feature = "bloom")]
+            KeyType::Bloom => "MBbloom--",
+            #[cfg(feature = "bloom")]
+            KeyType::Cuckoo => "MBbloomCF",
+            #[cfg(feature = "bloom")]
+            KeyType::CountMinSketch => "CMSk-TYPE",
+            #[cfg(feature = "bloom")]
+            KeyType::TopK => "TopK-TYPE",
         }
     }
 }
@@ -31,7 +58,7 @@ impl KeyType {
 #[derive(Clone, Debug)]
 pub struct SSElem {
     pub score: f64,
-    pub member: String,
+    pub member: Vec<u8>,
 }
 
 /// Ascending or descending direction for sorted set operations.
@@ -41,10 +68,79 @@ pub enum Direction {
     Desc,
 }
 
-/// Redis sorted set — uses a HashMap for O(1) score lookups, sorts on demand for range queries.
+/// A score interval, as given to ZRANGEBYSCORE and friends.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreRange {
+    pub min: f64,
+    pub min_incl: bool,
+    pub max: f64,
+    pub max_incl: bool,
+}
+
+impl ScoreRange {
+    fn above_min(&self, score: f64) -> bool {
+        if self.min_incl {
+            score >= self.min
+        } else {
+            score > self.min
+        }
+    }
+
+    fn below_max(&self, score: f64) -> bool {
+        if self.max_incl {
+            score <= self.max
+        } else {
+            score < self.max
+        }
+    }
+}
+
+/// One end of a lex interval, as given to ZRANGEBYLEX and friends.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexBound {
+    /// "-": before every member.
+    Min,
+    /// "+": after every member.
+    Max,
+    /// "[member"
+    Incl(Vec<u8>),
+    /// "(member"
+    Excl(Vec<u8>),
+}
+
+/// A lex interval. Only meaningful when all members have the same score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl LexRange {
+    fn above_min(&self, member: &[u8]) -> bool {
+        match &self.min {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Incl(b) => member >= b.as_slice(),
+            LexBound::Excl(b) => member > b.as_slice(),
+        }
+    }
+
+    fn below_max(&self, member: &[u8]) -> bool {
+        match &self.max {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Incl(b) => member <= b.as_slice(),
+            LexBound::Excl(b) => member < b.as_slice(),
+        }
+    }
+}
+
+/// Redis sorted set — a HashMap for O(1) score lookups, plus a skiplist
+/// ordered by (score, member) for rank and range queries in O(log n + m).
 #[derive(Clone, Debug, Default)]
 pub struct SortedSet {
-    pub scores: HashMap<String, f64>,
+    scores: HashMap<Vec<u8>, f64>,
+    index: SkipList,
 }
 
 impl SortedSet {
@@ -56,73 +152,157 @@ impl SortedSet {
         self.scores.len()
     }
 
+    /// All members and their scores, unordered.
+    pub fn scores(&self) -> &HashMap<Vec<u8>, f64> {
+        &self.scores
+    }
+
     /// Add or update a member. Returns true if the member was new.
-    pub fn set(&mut self, score: f64, member: &str) -> bool {
-        let is_new = !self.scores.contains_key(member);
-        self.scores.insert(member.to_owned(), score);
-        is_new
+    pub fn set(&mut self, score: f64, member: &[u8]) -> bool {
+        match self.scores.get_mut(member) {
+            Some(old) => {
+                if *old != score {
+                    self.index.remove(*old, member);
+                    self.index.insert(score, member.to_owned());
+                    *old = score;
+                }
+                false
+            }
+            None => {
+                self.scores.insert(member.to_owned(), score);
+                self.index.insert(score, member.to_owned());
+                true
+            }
+        }
     }
 
     /// Get a member's score.
-    pub fn get(&self, member: &str) -> Option<f64> {
+    pub fn get(&self, member: &[u8]) -> Option<f64> {
         self.scores.get(member).copied()
     }
 
     /// Check if a member exists.
-    pub fn exists(&self, member: &str) -> bool {
+    pub fn exists(&self, member: &[u8]) -> bool {
         self.scores.contains_key(member)
     }
 
     /// Remove a member. Returns true if it existed.
-    pub fn remove(&mut self, member: &str) -> bool {
-        self.scores.remove(member).is_some()
+    pub fn remove(&mut self, member: &[u8]) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => self.index.remove(score, member),
+            None => false,
+        }
     }
 
     /// Return all elements sorted by (score, member).
     pub fn by_score(&self, dir: Direction) -> Vec<SSElem> {
-        let mut elems: Vec<SSElem> = self
-            .scores
-            .iter()
-            .map(|(m, &s)| SSElem {
-                score: s,
-                member: m.clone(),
-            })
-            .collect();
-        elems.sort_by(|a, b| {
-            a.score
-                .partial_cmp(&b.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-                .then_with(|| a.member.cmp(&b.member))
-        });
-        if dir == Direction::Desc {
-            elems.reverse();
-        }
-        elems
+        self.range(0, self.card(), dir)
     }
 
     /// Return sorted member names (all same score → lex order).
-    pub fn members_sorted(&self) -> Vec<String> {
-        self.by_score(Direction::Asc)
-            .into_iter()
-            .map(|e| e.member)
+    pub fn members_sorted(&self) -> Vec<Vec<u8>> {
+        self.index
+            .iter(Direction::Asc)
+            .map(|(_, m)| m.to_vec())
             .collect()
     }
 
     /// Get the rank (0-based index) of a member in sorted order.
-    pub fn rank(&self, member: &str, dir: Direction) -> Option<usize> {
-        if !self.scores.contains_key(member) {
-            return None;
+    pub fn rank(&self, member: &[u8], dir: Direction) -> Option<usize> {
+        let score = self.get(member)?;
+        let rank = self.index.rank(score, member)?;
+        Some(match dir {
+            Direction::Asc => rank,
+            Direction::Desc => self.card() - 1 - rank,
+        })
+    }
+
+    /// The elements at ranks `start..end`, counting in `dir` order.
+    pub fn range(&self, start: usize, end: usize, dir: Direction) -> Vec<SSElem> {
+        self.index
+            .range(start, end, dir)
+            .map(|(score, m)| SSElem {
+                score,
+                member: m.to_vec(),
+            })
+            .collect()
+    }
+
+    /// Ascending ranks of the elements with a score in `r`.
+    fn score_ranks(&self, r: &ScoreRange) -> std::ops::Range<usize> {
+        let lo = self.index.count_while(|score, _| !r.above_min(score));
+        let hi = self.index.count_while(|score, _| r.below_max(score));
+        lo..hi.max(lo)
+    }
+
+    /// Ascending ranks of the elements with a member in `r`.
+    fn lex_ranks(&self, r: &LexRange) -> std::ops::Range<usize> {
+        let lo = self.index.count_while(|_, m| !r.above_min(m));
+        let hi = self.index.count_while(|_, m| r.below_max(m));
+        lo..hi.max(lo)
+    }
+
+    /// Elements with ascending ranks in `ranks`, in `dir` order, skipping
+    /// the first `offset` and returning at most `count`.
+    fn limit(
+        &self,
+        ranks: std::ops::Range<usize>,
+        dir: Direction,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<SSElem> {
+        let first = match dir {
+            Direction::Asc => ranks.start,
+            Direction::Desc => self.card() - ranks.end,
+        };
+        let start = first.saturating_add(offset);
+        let mut end = first + ranks.len();
+        if let Some(count) = count {
+            end = end.min(start.saturating_add(count));
         }
-        let elems = self.by_score(dir);
-        elems.iter().position(|e| e.member == member)
+        self.range(start, end, dir)
+    }
+
+    /// Number of elements with a score in `r`.
+    pub fn count_by_score(&self, r: &ScoreRange) -> usize {
+        self.score_ranks(r).len()
+    }
+
+    /// Elements with a score in `r`, in `dir` order, after skipping
+    /// `offset` and limited to `count`.
+    pub fn range_by_score(
+        &self,
+        r: &ScoreRange,
+        dir: Direction,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<SSElem> {
+        self.limit(self.score_ranks(r), dir, offset, count)
+    }
+
+    /// Number of elements with a member in `r`.
+    pub fn count_by_lex(&self, r: &LexRange) -> usize {
+        self.lex_ranks(r).len()
+    }
+
+    /// Elements with a member in `r`, in `dir` order, after skipping
+    /// `offset` and limited to `count`.
+    pub fn range_by_lex(
+        &self,
+        r: &LexRange,
+        dir: Direction,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<SSElem> {
+        self.limit(self.lex_ranks(r), dir, offset, count)
     }
 
     /// Increment a member's score by delta. Creates the member if it doesn't exist.
     /// Returns the new score.
-    pub fn incrby(&mut self, member: &str, delta: f64) -> f64 {
-        let score = self.scores.entry(member.to_owned()).or_insert(0.0);
-        *score += delta;
-        *score
+    pub fn incrby(&mut self, member: &[u8], delta: f64) -> f64 {
+        let score = self.get(member).unwrap_or(0.0) + delta;
+        self.set(score, member);
+        score
     }
 }
 
@@ -130,8 +310,8 @@ impl SortedSet {
 #[derive(Clone, Debug)]
 pub struct StreamEntry {
     pub id: String,
-    /// Alternating key-value pairs.
-    pub values: Vec<String>,
+    /// Alternating field-value pairs.
+    pub values: Vec<Vec<u8>>,
 }
 
 /// A pending entry in a consumer group's PEL.
@@ -253,7 +433,7 @@ impl Stream {
     pub fn add(
         &mut self,
         id: &str,
-        values: Vec<String>,
+        values: Vec<Vec<u8>>,
         now_ms: u64,
     ) -> Result<String, &'static str> {
         let final_id = if id.is_empty() || id == "*" {
@@ -0,0 +1,324 @@
+mod helpers;
+use helpers::*;
+
+use std::collections::HashMap;
+
+/// Connect a second client and log it in as `user`.
+async fn login(
+    m: &miniredis_rs::Miniredis,
+    user: &str,
+    pass: &str,
+) -> redis::aio::MultiplexedConnection {
+    let mut c = redis::Client::open(m.redis_url())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    must_ok!(c, "AUTH", user, pass);
+    c
+}
+
+// ── User management ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_acl_setuser() {
+    let (_m, mut c) = start().await;
+
+    must_strs!(c, "ACL", "USERS"; ["default"]);
+    must_strs!(c, "ACL", "LIST"; ["user default on nopass ~* &* +@all"]);
+    must_str!(c, "ACL", "WHOAMI"; "default");
+
+    must_ok!(
+        c,
+        "ACL",
+        "SETUSER",
+        "alice",
+        "on",
+        ">pw",
+        "~cache:*",
+        "&news.*",
+        "+@read",
+        "-@dangerous"
+    );
+    must_ok!(c, "ACL", "SETUSER", "bob");
+    must_strs!(c, "ACL", "USERS"; ["alice", "bob", "default"]);
+    must_strs!(c, "ACL", "LIST"; [
+        "user alice on #30c952fab122c3f9759f02a6d95c3758b246b4fee239957b2d4fee46e26170c4 ~cache:* &news.* -@all +@read -@dangerous",
+        "user bob off resetchannels -@all",
+        "user default on nopass ~* &* +@all",
+    ]);
+
+    // Rules are applied on top of the existing user.
+    must_ok!(c, "ACL", "SETUSER", "bob", "on", "nopass", "+get");
+    let list: Vec<String> = redis::cmd("ACL")
+        .arg("LIST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(list[1], "user bob on nopass resetchannels -@all +get");
+
+    let user: HashMap<String, redis::Value> = redis::cmd("ACL")
+        .arg("GETUSER")
+        .arg("alice")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        user["commands"],
+        redis::Value::BulkString(b"-@all +@read -@dangerous".to_vec())
+    );
+    assert_eq!(user["keys"], redis::Value::BulkString(b"~cache:*".to_vec()));
+    assert_eq!(
+        user["channels"],
+        redis::Value::BulkString(b"&news.*".to_vec())
+    );
+    must_nil!(c, "ACL", "GETUSER", "nosuch");
+
+    // A bad rule leaves the user untouched.
+    must_fail!(c, "ACL", "SETUSER", "alice", "+get", "+nosuch"; "Error in ACL SETUSER modifier '+nosuch': Unknown command");
+    must_fail!(c, "ACL", "SETUSER", "alice", "%X~foo"; "Syntax error");
+    must_fail!(c, "ACL", "SETUSER", "alice", "+@nosuch"; "Unknown command or category name in ACL");
+
+    must_fail!(c, "ACL", "DELUSER", "default"; "The 'default' user cannot be removed");
+    must_int!(c, "ACL", "DELUSER", "alice", "bob", "nosuch"; 2);
+    must_strs!(c, "ACL", "USERS"; ["default"]);
+
+    must_fail!(c, "ACL", "GETUSER"; "wrong number of arguments for 'acl|getuser' command");
+    must_fail!(c, "ACL", "NOSUCH"; "unknown subcommand 'nosuch'. Try ACL HELP.");
+}
+
+#[tokio::test]
+async fn test_acl_cat() {
+    let (_m, mut c) = start().await;
+
+    let cats: Vec<String> = redis::cmd("ACL")
+        .arg("CAT")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    for cat in [
+        "keyspace",
+        "read",
+        "write",
+        "dangerous",
+        "pubsub",
+        "sortedset",
+    ] {
+        assert!(cats.contains(&cat.to_string()), "missing {cat}");
+    }
+
+    let read: Vec<String> = redis::cmd("ACL")
+        .arg("CAT")
+        .arg("read")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(read.contains(&"get".to_string()));
+    assert!(!read.contains(&"set".to_string()));
+
+    let dangerous: Vec<String> = redis::cmd("ACL")
+        .arg("CAT")
+        .arg("dangerous")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(dangerous.contains(&"flushall".to_string()));
+
+    must_fail!(c, "ACL", "CAT", "nosuch"; "Unknown category 'nosuch'");
+}
+
+// ── Enforcement ──────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_acl_command_permissions() {
+    let (m, mut c) = start().await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    must_ok!(
+        c,
+        "ACL",
+        "SETUSER",
+        "reader",
+        "on",
+        ">pw",
+        "~*",
+        "+@all",
+        "-@write",
+        "-@dangerous"
+    );
+    let mut r = login(&m, "reader", "pw").await;
+
+    must_str!(r, "ACL", "WHOAMI"; "reader");
+    must_str!(r, "GET", "foo"; "bar");
+    must_fail!(r, "SET", "foo", "baz"; "User reader has no permissions to run the 'set' command");
+    must_fail!(r, "FLUSHALL"; "User reader has no permissions to run the 'flushall' command");
+    must_fail!(r, "CONFIG", "GET", "maxmemory"; "no permissions to run the 'config|get' command");
+
+    // Single subcommands can be allowed.
+    must_ok!(c, "ACL", "SETUSER", "reader", "+config|get");
+    must_fail!(r, "CONFIG", "SET", "maxmemory", "0"; "no permissions to run the 'config|set' command");
+
+    // Permissions change for connections already logged in.
+    must_ok!(c, "ACL", "SETUSER", "reader", "+set");
+    must_ok!(r, "SET", "foo", "baz");
+
+    // A disabled user can't log in.
+    must_ok!(c, "ACL", "SETUSER", "reader", "off");
+    must_fail!(c, "AUTH", "reader", "pw"; "WRONGPASS");
+}
+
+#[tokio::test]
+async fn test_acl_key_permissions() {
+    let (m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "ACL",
+        "SETUSER",
+        "app",
+        "on",
+        ">pw",
+        "+@all",
+        "~app:*",
+        "%R~shared:*",
+        "%W~out:*"
+    );
+    let mut a = login(&m, "app", "pw").await;
+
+    must_ok!(a, "SET", "app:1", "v");
+    must_str!(a, "GET", "app:1"; "v");
+    must_fail!(a, "GET", "other"; "No permissions to access a key");
+
+    // Read-only keys
+    must_nil!(a, "GET", "shared:1");
+    must_fail!(a, "SET", "shared:1", "v"; "No permissions to access a key");
+
+    // Write-only keys
+    must_ok!(a, "SET", "out:1", "v");
+    must_fail!(a, "GET", "out:1"; "No permissions to access a key");
+    must_0!(a, "COPY", "shared:1", "out:2");
+    must_fail!(a, "COPY", "out:1", "app:2"; "permissions");
+
+    // Every key of a multi-key command is checked.
+    must_fail!(a, "MGET", "app:1", "other"; "No permissions to access a key");
+    must_fail!(a, "DEL", "app:1", "shared:1"; "No permissions to access a key");
+}
+
+#[tokio::test]
+async fn test_acl_channel_permissions() {
+    let (m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "ACL",
+        "SETUSER",
+        "sub",
+        "on",
+        ">pw",
+        "+@all",
+        "~*",
+        "resetchannels",
+        "&news.*"
+    );
+    let mut s = login(&m, "sub", "pw").await;
+
+    must_int!(s, "PUBLISH", "news.tech", "hi"; 0);
+    must_fail!(s, "PUBLISH", "sports", "hi"; "No permissions to access a channel");
+    must_fail!(s, "SUBSCRIBE", "sports"; "No permissions to access a channel");
+    // Patterns need to match one of the user's patterns literally.
+    must_fail!(s, "PSUBSCRIBE", "news.t*"; "No permissions to access a channel");
+}
+
+#[tokio::test]
+async fn test_acl_transactions_and_scripts() {
+    let (m, mut c) = start().await;
+
+    must_ok!(
+        c, "ACL", "SETUSER", "ro", "on", ">pw", "~*", "+@all", "-@write"
+    );
+    let mut r = login(&m, "ro", "pw").await;
+
+    must_ok!(r, "MULTI");
+    must_fail!(r, "SET", "foo", "bar"; "permissions");
+    must_fail!(r, "EXEC"; "Transaction discarded");
+
+    // Commands called from a script are checked too.
+    must_fail!(r, "EVAL", "return redis.call('SET', KEYS[1], 'bar')", 1, "foo"; "permissions");
+    must_nil!(c, "GET", "foo");
+}
+
+#[tokio::test]
+async fn test_acl_log() {
+    let (m, mut c) = start().await;
+
+    must_ok!(c, "ACL", "SETUSER", "app", "on", ">pw", "+get", "~app:*");
+    let mut a = login(&m, "app", "pw").await;
+
+    must_fail!(a, "SET", "app:1", "v"; "permissions");
+    must_fail!(a, "SET", "app:1", "v"; "permissions");
+    must_fail!(a, "GET", "other"; "permissions");
+    must_fail!(c, "AUTH", "app", "wrong"; "WRONGPASS");
+
+    let log: Vec<HashMap<String, redis::Value>> = redis::cmd("ACL")
+        .arg("LOG")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 3);
+    // Newest first
+    assert_eq!(log[0]["reason"], redis::Value::BulkString(b"auth".to_vec()));
+    assert_eq!(
+        log[0]["username"],
+        redis::Value::BulkString(b"app".to_vec())
+    );
+    assert_eq!(log[1]["reason"], redis::Value::BulkString(b"key".to_vec()));
+    assert_eq!(
+        log[1]["object"],
+        redis::Value::BulkString(b"other".to_vec())
+    );
+    assert_eq!(
+        log[2]["reason"],
+        redis::Value::BulkString(b"command".to_vec())
+    );
+    assert_eq!(log[2]["object"], redis::Value::BulkString(b"set".to_vec()));
+    assert_eq!(log[2]["count"], redis::Value::Int(2));
+    assert_eq!(
+        log[2]["context"],
+        redis::Value::BulkString(b"toplevel".to_vec())
+    );
+
+    let log: Vec<redis::Value> = redis::cmd("ACL")
+        .arg("LOG")
+        .arg(1)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(log.len(), 1);
+
+    must_ok!(c, "ACL", "LOG", "RESET");
+    let log: Vec<redis::Value> = redis::cmd("ACL")
+        .arg("LOG")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(log.is_empty());
+    must_fail!(c, "ACL", "LOG", "foo"; "value is not an integer or out of range");
+}
+
+// ── Direct API ───────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_acl_direct() {
+    let (m, mut c) = start().await;
+
+    m.acl_setuser("app", &["on", ">pw", "~app:*", "+@read"])
+        .unwrap();
+    assert!(m.acl_setuser("app", &["+nosuch"]).is_err());
+
+    let mut a = login(&m, "app", "pw").await;
+    must_nil!(a, "GET", "app:1");
+    must_fail!(a, "SET", "app:1", "v"; "permissions");
+
+    // The default user is unaffected.
+    must_ok!(c, "SET", "foo", "bar");
+}
@@ -223,3 +223,193 @@ async fn test_bitpos() {
     must_fail!(c, "BITPOS", "foo"; "wrong number of arguments");
     must_fail!(c, "BITPOS", "foo", "noint"; "not an integer");
 }
+
+#[tokio::test]
+async fn test_bitfield() {
+    let (m, mut c) = helpers::start().await;
+
+    // Examples from the BITFIELD docs.
+    let v: Vec<i64> = redis::cmd("BITFIELD")
+        .arg(&["mykey", "INCRBY", "i5", "100", "1", "GET", "u4", "0"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![1, 0]);
+
+    let mut got = Vec::new();
+    for _ in 0..4 {
+        let v: Vec<i64> = redis::cmd("BITFIELD")
+            .arg(&[
+                "counters", "INCRBY", "u2", "100", "1", "OVERFLOW", "SAT", "INCRBY", "u2", "102",
+                "1",
+            ])
+            .query_async(&mut c)
+            .await
+            .unwrap();
+        got.push(v);
+    }
+    assert_eq!(got, vec![vec![1, 1], vec![2, 2], vec![3, 3], vec![0, 3]]);
+
+    let v: Vec<Option<i64>> = redis::cmd("BITFIELD")
+        .arg(&[
+            "counters", "OVERFLOW", "FAIL", "INCRBY", "u2", "102", "1", "GET", "u2", "102",
+        ])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![None, Some(3)]);
+
+    // SET returns the old value; # offsets count fields.
+    let v: Vec<i64> = redis::cmd("BITFIELD")
+        .arg(&[
+            "bf", "SET", "u8", "#0", "255", "SET", "u8", "#1", "200", "GET", "i8", "#1",
+        ])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![0, 0, -56]);
+    assert_eq!(m.get_bytes(b"bf"), Some(vec![255, 200]));
+    must_int!(c, "GETBIT", "bf", "0"; 1);
+    must_int!(c, "BITCOUNT", "bf"; 11);
+
+    // GETs on a missing key don't create it.
+    let v: Vec<i64> = redis::cmd("BITFIELD")
+        .arg(&["nosuch", "GET", "u8", "0", "GET", "i64", "100"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![0, 0]);
+    must_0!(c, "EXISTS", "nosuch");
+
+    // No subcommands.
+    let v: Vec<i64> = redis::cmd("BITFIELD")
+        .arg("bf")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(v.is_empty());
+}
+
+#[tokio::test]
+async fn test_bitfield_overflow() {
+    let (_m, mut c) = helpers::start().await;
+
+    let run = |args: &'static [&'static str]| {
+        let mut cmd = redis::cmd("BITFIELD");
+        cmd.arg("k").arg(args);
+        cmd
+    };
+
+    // WRAP (the default)
+    let v: Vec<i64> = run(&[
+        "SET", "i8", "0", "127", "INCRBY", "i8", "0", "1", "INCRBY", "i8", "0", "-1",
+    ])
+    .query_async(&mut c)
+    .await
+    .unwrap();
+    assert_eq!(v, vec![0, -128, 127]);
+    let v: Vec<i64> = run(&["SET", "u8", "0", "300", "INCRBY", "u8", "0", "-45"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![127, 255]);
+
+    // SAT
+    let v: Vec<i64> = run(&[
+        "OVERFLOW", "SAT", "SET", "i8", "0", "1000", "INCRBY", "i8", "0", "-300", "SET", "u8", "0",
+        "-1", "INCRBY", "u8", "0", "-500",
+    ])
+    .query_async(&mut c)
+    .await
+    .unwrap();
+    assert_eq!(v, vec![-1, -128, 128, 0]);
+
+    // FAIL, only for the ops after it
+    let v: Vec<Option<i64>> = run(&[
+        "INCRBY", "u4", "0", "17", "OVERFLOW", "FAIL", "INCRBY", "u4", "0", "15", "INCRBY", "u4",
+        "0", "14", "GET", "u4", "0",
+    ])
+    .query_async(&mut c)
+    .await
+    .unwrap();
+    assert_eq!(v, vec![Some(1), None, Some(15), Some(15)]);
+
+    // 64 bit signed fields.
+    let v: Vec<Option<i64>> = run(&[
+        "SET",
+        "i64",
+        "64",
+        "9223372036854775807",
+        "INCRBY",
+        "i64",
+        "64",
+        "1",
+        "OVERFLOW",
+        "FAIL",
+        "INCRBY",
+        "i64",
+        "64",
+        "-1",
+        "INCRBY",
+        "i64",
+        "64",
+        "-1",
+    ])
+    .query_async(&mut c)
+    .await
+    .unwrap();
+    assert_eq!(v, vec![Some(0), Some(i64::MIN), None, None]);
+    let v: Vec<i64> = run(&[
+        "OVERFLOW", "SAT", "INCRBY", "i64", "64", "-1", "GET", "u63", "65",
+    ])
+    .query_async(&mut c)
+    .await
+    .unwrap();
+    assert_eq!(v, vec![i64::MIN, 0]);
+}
+
+#[tokio::test]
+async fn test_bitfield_errors() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_fail!(c, "BITFIELD"; "wrong number of arguments");
+    must_fail!(c, "BITFIELD", "k", "GET", "u8"; "syntax error");
+    must_fail!(c, "BITFIELD", "k", "SET", "u8", "0"; "syntax error");
+    must_fail!(c, "BITFIELD", "k", "FOO", "u8", "0"; "syntax error");
+    must_fail!(c, "BITFIELD", "k", "GET", "u64", "0"; "Invalid bitfield type");
+    must_fail!(c, "BITFIELD", "k", "GET", "i65", "0"; "Invalid bitfield type");
+    must_fail!(c, "BITFIELD", "k", "GET", "u0", "0"; "Invalid bitfield type");
+    must_fail!(c, "BITFIELD", "k", "GET", "x8", "0"; "Invalid bitfield type");
+    must_fail!(c, "BITFIELD", "k", "GET", "u8", "-1"; "bit offset is not an integer");
+    must_fail!(c, "BITFIELD", "k", "GET", "u8", "#x"; "bit offset is not an integer");
+    must_fail!(c, "BITFIELD", "k", "GET", "u8", "4294967296"; "bit offset is not an integer");
+    must_fail!(c, "BITFIELD", "k", "SET", "u8", "0", "x"; "not an integer");
+    must_fail!(c, "BITFIELD", "k", "OVERFLOW", "NOPE"; "Invalid OVERFLOW type");
+
+    // Nothing is written when any subcommand is invalid.
+    must_fail!(c, "BITFIELD", "k", "SET", "u8", "0", "1", "GET", "u99", "0"; "Invalid bitfield type");
+    must_0!(c, "EXISTS", "k");
+
+    must_int!(c, "HSET", "h", "f", "v"; 1);
+    must_fail!(c, "BITFIELD", "h", "GET", "u8", "0"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_bitfield_ro() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_ok!(c, "SET", "k", "A");
+    let v: Vec<i64> = redis::cmd("BITFIELD_RO")
+        .arg(&["k", "GET", "u8", "0", "GET", "u4", "4"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec![65, 1]);
+
+    must_fail!(c, "BITFIELD_RO", "k", "SET", "u8", "0", "1"; "BITFIELD_RO only supports the GET subcommand");
+    must_fail!(c, "BITFIELD_RO", "k", "OVERFLOW", "SAT"; "BITFIELD_RO only supports the GET subcommand");
+
+    // Allowed in read-only scripts, unlike BITFIELD.
+    must_int!(c, "EVAL_RO", "return redis.call('BITFIELD_RO', KEYS[1], 'GET', 'u8', 0)[1]", "1", "k"; 65);
+    must_fail!(c, "EVAL_RO", "return redis.call('BITFIELD', KEYS[1], 'GET', 'u8', 0)", "1", "k"; "Write commands are not allowed");
+}
@@ -0,0 +1,320 @@
+mod helpers;
+use helpers::*;
+
+use miniredis_rs::direct::Value;
+
+// ── Bloom filters ────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_bf_add_exists() {
+    let (_m, mut c) = start().await;
+
+    must_0!(c, "BF.EXISTS", "bf", "a");
+    must_1!(c, "BF.ADD", "bf", "a");
+    must_0!(c, "BF.ADD", "bf", "a");
+    must_1!(c, "BF.EXISTS", "bf", "a");
+    must_0!(c, "BF.EXISTS", "bf", "b");
+    must_str!(c, "TYPE", "bf"; "MBbloom--");
+
+    let added: Vec<i64> = redis::cmd("BF.MADD")
+        .arg("bf")
+        .arg(&["a", "b", "c"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(added, vec![0, 1, 1]);
+    let exists: Vec<i64> = redis::cmd("BF.MEXISTS")
+        .arg("bf")
+        .arg(&["a", "c", "d"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(exists, vec![1, 1, 0]);
+    let exists: Vec<i64> = redis::cmd("BF.MEXISTS")
+        .arg("nosuch")
+        .arg(&["a", "b"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(exists, vec![0, 0]);
+
+    // Scales past the default capacity of 100.
+    for i in 0..300 {
+        redis::cmd("BF.ADD")
+            .arg("big")
+            .arg(format!("item{i}"))
+            .query_async::<()>(&mut c)
+            .await
+            .unwrap();
+    }
+    for i in 0..300 {
+        must_1!(c, "BF.EXISTS", "big", format!("item{i}"));
+    }
+
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "BF.ADD", "str", "a"; "WRONGTYPE");
+    must_fail!(c, "BF.MADD", "str", "a"; "WRONGTYPE");
+    must_fail!(c, "BF.EXISTS", "str", "a"; "WRONGTYPE");
+    must_fail!(c, "BF.ADD", "bf"; "wrong number of arguments");
+}
+
+#[tokio::test]
+async fn test_bf_reserve_info() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "BF.RESERVE", "bf", "0.01", "1000");
+    must_fail!(c, "BF.RESERVE", "bf", "0.01", "1000"; "item exists");
+    must_1!(c, "BF.ADD", "bf", "a");
+    let info: Vec<redis::Value> = redis::cmd("BF.INFO")
+        .arg("bf")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let names: Vec<String> = info
+        .iter()
+        .step_by(2)
+        .map(|v| redis::from_redis_value(v.clone()).unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "Capacity",
+            "Size",
+            "Number of filters",
+            "Number of items inserted",
+            "Expansion rate"
+        ]
+    );
+    assert_eq!(info[1], redis::Value::Int(1000));
+    assert_eq!(info[5], redis::Value::Int(1));
+    assert_eq!(info[7], redis::Value::Int(1));
+    assert_eq!(info[9], redis::Value::Int(2));
+
+    let items: Vec<i64> = redis::cmd("BF.INFO")
+        .arg("bf")
+        .arg("items")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(items, vec![1]);
+    must_fail!(c, "BF.INFO", "bf", "nope"; "information value");
+    must_fail!(c, "BF.INFO", "nosuch"; "not found");
+
+    // Non-scaling filters fill up.
+    must_ok!(c, "BF.RESERVE", "fixed", "0.01", "2", "NONSCALING");
+    must_1!(c, "BF.ADD", "fixed", "a");
+    must_1!(c, "BF.ADD", "fixed", "b");
+    must_fail!(c, "BF.ADD", "fixed", "c"; "non scaling filter is full");
+    let expansion: Vec<Option<i64>> = redis::cmd("BF.INFO")
+        .arg("fixed")
+        .arg("EXPANSION")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(expansion, vec![None]);
+
+    must_ok!(c, "BF.RESERVE", "wide", "0.001", "10", "EXPANSION", "4");
+    must_fail!(c, "BF.RESERVE", "x", "nope", "10"; "bad error rate");
+    must_fail!(c, "BF.RESERVE", "x", "1.5", "10"; "(0 < error rate range < 1)");
+    must_fail!(c, "BF.RESERVE", "x", "0.01", "nope"; "bad capacity");
+    must_fail!(c, "BF.RESERVE", "x", "0.01", "0"; "(capacity should be larger than 0)");
+    must_fail!(c, "BF.RESERVE", "x", "0.01", "10", "EXPANSION", "x"; "bad expansion");
+    must_fail!(c, "BF.RESERVE", "x", "0.01", "10", "EXPANSION", "0"; "greater or equal to 1");
+    must_fail!(
+        c, "BF.RESERVE", "x", "0.01", "10", "EXPANSION", "2", "NONSCALING";
+        "Nonscaling filters cannot expand"
+    );
+    must_0!(c, "EXISTS", "x");
+}
+
+// ── Cuckoo filters ───────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_cf() {
+    let (_m, mut c) = start().await;
+
+    must_0!(c, "CF.EXISTS", "cf", "a");
+    must_fail!(c, "CF.DEL", "cf", "a"; "found");
+    must_1!(c, "CF.ADD", "cf", "a");
+    must_1!(c, "CF.ADD", "cf", "a");
+    must_str!(c, "TYPE", "cf"; "MBbloomCF");
+    must_1!(c, "CF.EXISTS", "cf", "a");
+    must_0!(c, "CF.EXISTS", "cf", "b");
+
+    // Added twice, so deleted twice.
+    must_1!(c, "CF.DEL", "cf", "a");
+    must_1!(c, "CF.EXISTS", "cf", "a");
+    must_1!(c, "CF.DEL", "cf", "a");
+    must_0!(c, "CF.EXISTS", "cf", "a");
+    must_0!(c, "CF.DEL", "cf", "a");
+
+    for i in 0..2000 {
+        must_1!(c, "CF.ADD", "many", format!("item{i}"));
+    }
+    for i in 0..2000 {
+        must_1!(c, "CF.EXISTS", "many", format!("item{i}"));
+    }
+
+    must_1!(c, "BF.ADD", "bf", "a");
+    must_fail!(c, "CF.ADD", "bf", "a"; "WRONGTYPE");
+    must_fail!(c, "CF.EXISTS", "bf", "a"; "WRONGTYPE");
+    must_fail!(c, "BF.ADD", "cf", "a"; "WRONGTYPE");
+}
+
+// ── Count-Min sketches ───────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_cms() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "CMS.INITBYDIM", "cms", "2000", "5");
+    must_fail!(c, "CMS.INITBYDIM", "cms", "2000", "5"; "key already exists");
+    must_str!(c, "TYPE", "cms"; "CMSk-TYPE");
+
+    let counts: Vec<i64> = redis::cmd("CMS.INCRBY")
+        .arg(&["cms", "a", "3", "b", "1", "a", "2"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(counts, vec![3, 1, 5]);
+    let counts: Vec<i64> = redis::cmd("CMS.QUERY")
+        .arg(&["cms", "a", "b", "c"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(counts, vec![5, 1, 0]);
+
+    must_fail!(c, "CMS.INCRBY", "cms", "a", "4294967295"; "INCRBY overflow");
+    must_fail!(c, "CMS.INCRBY", "cms", "a", "x"; "Cannot parse number");
+    must_fail!(c, "CMS.INCRBY", "cms", "a", "-1"; "Cannot parse number");
+    must_fail!(c, "CMS.INCRBY", "cms", "a", "1", "b"; "wrong number of arguments");
+    must_fail!(c, "CMS.INCRBY", "nosuch", "a", "1"; "key does not exist");
+    must_fail!(c, "CMS.QUERY", "nosuch", "a"; "key does not exist");
+    must_fail!(c, "CMS.INITBYDIM", "x", "0", "5"; "invalid width");
+    must_fail!(c, "CMS.INITBYDIM", "x", "10", "nope"; "invalid depth");
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "CMS.QUERY", "str", "a"; "WRONGTYPE");
+}
+
+// ── Top-K ────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_topk() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "TOPK.RESERVE", "topk", "2");
+    must_fail!(c, "TOPK.RESERVE", "topk", "2"; "key already exists");
+    must_str!(c, "TYPE", "topk"; "TopK-TYPE");
+
+    let expelled: Vec<Option<String>> = redis::cmd("TOPK.ADD")
+        .arg(&["topk", "a", "a", "a", "b", "b"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(expelled, vec![None; 5]);
+    must_strs!(c, "TOPK.LIST", "topk"; ["a", "b"]);
+    let list: Vec<redis::Value> = redis::cmd("TOPK.LIST")
+        .arg(&["topk", "WITHCOUNT"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        list,
+        vec![
+            redis::Value::BulkString(b"a".to_vec()),
+            redis::Value::Int(3),
+            redis::Value::BulkString(b"b".to_vec()),
+            redis::Value::Int(2),
+        ]
+    );
+
+    // A more frequent item pushes out the least frequent one.
+    let expelled: Vec<Option<String>> = redis::cmd("TOPK.ADD")
+        .arg(&["topk", "c", "c", "c", "c"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(expelled.contains(&Some("b".to_string())), "{expelled:?}");
+    must_strs!(c, "TOPK.LIST", "topk"; ["c", "a"]);
+
+    must_ok!(c, "TOPK.RESERVE", "custom", "5", "100", "4", "0.95");
+    must_fail!(c, "TOPK.RESERVE", "x", "5", "100"; "wrong number of arguments");
+    must_fail!(c, "TOPK.RESERVE", "x", "0"; "invalid k");
+    must_fail!(c, "TOPK.RESERVE", "x", "5", "0", "4", "0.9"; "invalid width");
+    must_fail!(c, "TOPK.RESERVE", "x", "5", "8", "0", "0.9"; "invalid depth");
+    must_fail!(c, "TOPK.RESERVE", "x", "5", "8", "7", "2"; "invalid decay value");
+    must_fail!(c, "TOPK.ADD", "nosuch", "a"; "key does not exist");
+    must_fail!(c, "TOPK.LIST", "nosuch"; "key does not exist");
+    must_fail!(c, "TOPK.LIST", "topk", "nope"; "syntax error");
+}
+
+// ── Keyspace ─────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_bloom_keyspace() {
+    let (m, mut c) = start().await;
+
+    must_1!(c, "BF.ADD", "bf", "a");
+    must_1!(c, "EXPIRE", "bf", "100");
+    must_1!(c, "COPY", "bf", "copy");
+    must_1!(c, "BF.EXISTS", "copy", "a");
+    must_ok!(c, "RENAME", "copy", "renamed");
+    must_1!(c, "BF.EXISTS", "renamed", "a");
+    must_0!(c, "EXISTS", "copy");
+
+    let dump: Vec<u8> = redis::cmd("DUMP")
+        .arg("bf")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    must_ok!(c, "RESTORE", "restored", "0", dump.as_slice());
+    must_str!(c, "TYPE", "restored"; "MBbloom--");
+    must_1!(c, "BF.EXISTS", "restored", "a");
+    must_0!(c, "BF.EXISTS", "restored", "b");
+
+    let dir = std::env::temp_dir().join(format!("miniredis-{}-bloom", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    must_ok!(c, "CONFIG", "SET", "dir", dir.to_str().unwrap());
+    must_ok!(c, "DEBUG", "RELOAD");
+    must_1!(c, "BF.EXISTS", "restored", "a");
+    let ttl: i64 = redis::cmd("TTL")
+        .arg("bf")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!((1..=100).contains(&ttl), "{ttl}");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    must_ok!(c, "CMS.INITBYDIM", "cms", "10", "2");
+    let checkpoint = m.checkpoint();
+    must_ok!(c, "TOPK.RESERVE", "topk", "3");
+    redis::cmd("CMS.INCRBY")
+        .arg(&["cms", "a", "1"])
+        .query_async::<()>(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(m.diff(&checkpoint).len(), 2);
+    m.restore(&checkpoint);
+    must_0!(c, "EXISTS", "topk");
+    let counts: Vec<i64> = redis::cmd("CMS.QUERY")
+        .arg(&["cms", "a"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(counts, vec![0]);
+
+    let dump = m.dump_db();
+    let value = |key: &str| dump.iter().find(|k| k.key == key).unwrap().value.clone();
+    assert_eq!(value("bf"), Value::BloomFilter { items: 1 });
+    assert_eq!(
+        value("cms"),
+        Value::CountMinSketch {
+            width: 10,
+            depth: 2,
+            count: 0
+        }
+    );
+
+    must_1!(c, "DEL", "bf");
+    must_0!(c, "BF.EXISTS", "bf", "a");
+}
@@ -1,4 +1,7 @@
 mod helpers;
+use helpers::{raw_cmd, raw_read};
+
+use tokio::net::TcpStream;
 
 #[tokio::test]
 async fn test_client_setname_getname() {
@@ -38,3 +41,208 @@ async fn test_client_errors() {
     must_fail!(c, "CLIENT"; "wrong number of arguments");
     must_fail!(c, "CLIENT", "NOSUCHSUB"; "unknown subcommand");
 }
+
+// ── CLIENT TRACKING ─────────────────────────────────────────────────
+
+async fn resp3_client(m: &miniredis_rs::Miniredis) -> TcpStream {
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    raw_cmd(&mut s, &["HELLO", "3"]).await;
+    s
+}
+
+#[tokio::test]
+async fn test_client_id() {
+    let (m, mut c) = helpers::start().await;
+
+    let id: i64 = redis::cmd("CLIENT")
+        .arg("ID")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(id > 0);
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    let other = raw_cmd(&mut s, &["CLIENT", "ID"]).await;
+    assert_ne!(other, format!(":{id}\r\n"));
+}
+
+#[tokio::test]
+async fn test_client_tracking_default() {
+    let (m, mut c) = helpers::start().await;
+    let mut s = resp3_client(&m).await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    assert_eq!(
+        raw_cmd(&mut s, &["CLIENT", "TRACKING", "ON"]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(raw_cmd(&mut s, &["GET", "foo"]).await, "$3\r\nbar\r\n");
+
+    // Untracked keys don't cause invalidations.
+    must_ok!(c, "SET", "other", "x");
+    assert_eq!(raw_read(&mut s).await, "");
+
+    must_ok!(c, "SET", "foo", "baz");
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+    );
+
+    // Invalidation is sent once; the key has to be read again to be tracked.
+    must_ok!(c, "SET", "foo", "qux");
+    assert_eq!(raw_read(&mut s).await, "");
+
+    // Expiring keys are invalidated too.
+    raw_cmd(&mut s, &["GET", "foo"]).await;
+    must_1!(c, "EXPIRE", "foo", "10");
+    raw_read(&mut s).await;
+    raw_cmd(&mut s, &["GET", "foo"]).await;
+    m.fast_forward(std::time::Duration::from_secs(11));
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+    );
+
+    // FLUSHALL invalidates everything with a null key list.
+    must_ok!(c, "FLUSHALL");
+    assert_eq!(raw_read(&mut s).await, ">2\r\n$10\r\ninvalidate\r\n_\r\n");
+
+    assert_eq!(
+        raw_cmd(&mut s, &["CLIENT", "TRACKING", "OFF"]).await,
+        "+OK\r\n"
+    );
+    raw_cmd(&mut s, &["GET", "foo"]).await;
+    must_ok!(c, "SET", "foo", "bar");
+    assert_eq!(raw_read(&mut s).await, "");
+}
+
+#[tokio::test]
+async fn test_client_tracking_bcast() {
+    let (m, mut c) = helpers::start().await;
+    let mut s = resp3_client(&m).await;
+
+    assert_eq!(
+        raw_cmd(
+            &mut s,
+            &["CLIENT", "TRACKING", "ON", "BCAST", "PREFIX", "user:"]
+        )
+        .await,
+        "+OK\r\n"
+    );
+    must_ok!(c, "SET", "session:1", "x");
+    assert_eq!(raw_read(&mut s).await, "");
+    must_ok!(c, "SET", "user:1", "x");
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$6\r\nuser:1\r\n"
+    );
+    // BCAST doesn't need the key to be read first, and keeps going.
+    must_int!(c, "DEL", "user:1"; 1);
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$6\r\nuser:1\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_client_tracking_optin_optout() {
+    let (m, mut c) = helpers::start().await;
+    let mut s = resp3_client(&m).await;
+
+    raw_cmd(&mut s, &["CLIENT", "TRACKING", "ON", "OPTIN"]).await;
+    raw_cmd(&mut s, &["GET", "a"]).await;
+    assert_eq!(
+        raw_cmd(&mut s, &["CLIENT", "CACHING", "YES"]).await,
+        "+OK\r\n"
+    );
+    raw_cmd(&mut s, &["GET", "b"]).await;
+    must_ok!(c, "SET", "a", "1");
+    must_ok!(c, "SET", "b", "1");
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$1\r\nb\r\n"
+    );
+
+    raw_cmd(&mut s, &["CLIENT", "TRACKING", "OFF"]).await;
+    raw_cmd(&mut s, &["CLIENT", "TRACKING", "ON", "OPTOUT"]).await;
+    raw_cmd(&mut s, &["CLIENT", "CACHING", "NO"]).await;
+    raw_cmd(&mut s, &["GET", "a"]).await;
+    raw_cmd(&mut s, &["GET", "b"]).await;
+    must_ok!(c, "SET", "a", "2");
+    must_ok!(c, "SET", "b", "2");
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$1\r\nb\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_client_tracking_noloop() {
+    let (m, mut c) = helpers::start().await;
+    let mut s = resp3_client(&m).await;
+
+    raw_cmd(&mut s, &["CLIENT", "TRACKING", "ON", "BCAST", "NOLOOP"]).await;
+    assert_eq!(raw_cmd(&mut s, &["SET", "foo", "bar"]).await, "+OK\r\n");
+    assert_eq!(raw_read(&mut s).await, "");
+
+    must_ok!(c, "SET", "foo", "qux");
+    assert_eq!(
+        raw_read(&mut s).await,
+        ">2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_client_tracking_redirect() {
+    let (m, mut c) = helpers::start().await;
+
+    // RESP2 clients get invalidations through __redis__:invalidate.
+    let mut sub = TcpStream::connect(m.addr()).await.unwrap();
+    let id = raw_cmd(&mut sub, &["CLIENT", "ID"]).await;
+    let id = id.trim_start_matches(':').trim_end().to_string();
+    raw_cmd(&mut sub, &["SUBSCRIBE", "__redis__:invalidate"]).await;
+
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    assert_eq!(
+        raw_cmd(&mut s, &["CLIENT", "TRACKING", "ON", "REDIRECT", &id]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        raw_cmd(&mut s, &["CLIENT", "GETREDIR"]).await,
+        format!(":{id}\r\n")
+    );
+    raw_cmd(&mut s, &["GET", "foo"]).await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    assert_eq!(
+        raw_read(&mut sub).await,
+        "*3\r\n$7\r\nmessage\r\n$20\r\n__redis__:invalidate\r\n*1\r\n$3\r\nfoo\r\n"
+    );
+    assert_eq!(raw_read(&mut s).await, "");
+}
+
+#[tokio::test]
+async fn test_client_tracking_errors() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "CLIENT", "GETREDIR"; -1);
+    must_fail!(c, "CLIENT", "TRACKING"; "wrong number of arguments");
+    must_fail!(c, "CLIENT", "TRACKING", "MAYBE"; "syntax error");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "BOGUS"; "syntax error");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "PREFIX", "a"; "PREFIX option requires BCAST mode");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "BCAST", "OPTIN"; "not compatible with BCAST");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "OPTIN", "OPTOUT"; "You can't use both OPTIN and OPTOUT");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "REDIRECT", "9999"; "does not exist");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "BCAST", "PREFIX", "a", "PREFIX", "ab"; "overlaps");
+    must_fail!(c, "CLIENT", "CACHING", "YES"; "only when the client is in tracking mode");
+
+    must_ok!(c, "CLIENT", "TRACKING", "ON");
+    must_int!(c, "CLIENT", "GETREDIR"; 0);
+    must_fail!(c, "CLIENT", "CACHING", "YES"; "only when the client is in tracking mode");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "BCAST"; "can't switch BCAST mode");
+    must_ok!(c, "CLIENT", "TRACKING", "OFF");
+
+    must_ok!(c, "CLIENT", "TRACKING", "ON", "OPTIN");
+    must_fail!(c, "CLIENT", "CACHING", "NO"; "only valid when tracking is enabled in OPTOUT mode");
+    must_fail!(c, "CLIENT", "CACHING", "MAYBE"; "syntax error");
+    must_fail!(c, "CLIENT", "TRACKING", "ON", "OPTOUT"; "can't switch OPTIN/OPTOUT mode");
+}
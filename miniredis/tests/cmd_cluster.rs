@@ -54,7 +54,7 @@ async fn test_cluster_keyslot() {
         .await
         .unwrap();
 
-    assert_eq!(v, 163);
+    assert_eq!(v, 15118);
 }
 
 #[tokio::test]
@@ -83,3 +83,142 @@ async fn test_cluster_errors() {
     must_fail!(c, "CLUSTER"; "wrong number of arguments");
     must_fail!(c, "CLUSTER", "NOSUCHSUB"; "unknown subcommand");
 }
+
+// ── MiniredisCluster ─────────────────────────────────────────────────
+
+use miniredis_rs::MiniredisCluster;
+
+async fn connect(m: &miniredis_rs::Miniredis) -> redis::aio::MultiplexedConnection {
+    redis::Client::open(m.redis_url())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_cluster_topology() {
+    let cluster = MiniredisCluster::run(3).await.unwrap();
+    let mut c = connect(cluster.node(1)).await;
+
+    let slots: Vec<(i64, i64, (String, i64, String))> = redis::cmd("CLUSTER")
+        .arg("SLOTS")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let ranges: Vec<(i64, i64, i64)> = slots
+        .iter()
+        .map(|(start, end, (_, port, _))| (*start, *end, *port))
+        .collect();
+    let ports: Vec<i64> = cluster.nodes().iter().map(|m| m.port() as i64).collect();
+    assert_eq!(
+        ranges,
+        [
+            (0, 5460, ports[0]),
+            (5461, 10922, ports[1]),
+            (10923, 16383, ports[2]),
+        ]
+    );
+
+    let nodes: String = redis::cmd("CLUSTER")
+        .arg("NODES")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let lines: Vec<&str> = nodes.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].ends_with(" master - 0 0 1 connected 0-5460"));
+    assert!(lines[1].ends_with(" myself,master - 0 0 2 connected 5461-10922"));
+    let myid: String = redis::cmd("CLUSTER")
+        .arg("MYID")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(lines[1].starts_with(&format!("{myid} 127.0.0.1:{}@", ports[1])));
+
+    let shards: Vec<redis::Value> = redis::cmd("CLUSTER")
+        .arg("SHARDS")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(shards.len(), 3);
+
+    cluster.close().await;
+}
+
+#[tokio::test]
+async fn test_cluster_redirects() {
+    let cluster = MiniredisCluster::run(3).await.unwrap();
+    let mut c = connect(cluster.node(0)).await;
+    let addr = |i: usize| cluster.node(i).addr().to_string();
+
+    // "b" is in slot 3300 on node 0, "foo" in slot 12182 on node 2.
+    must_ok!(c, "SET", "b", "1");
+    must_fail!(c, "SET", "foo", "bar"; format!("12182 {}", addr(2)).as_str());
+    must_fail!(c, "GET", "foo"; "Moved");
+    assert_eq!(cluster.get("b"), Some("1".to_string()));
+    cluster.set("foo", "bar");
+    assert_eq!(cluster.node(2).get("foo"), Some("bar".to_string()));
+
+    // Commands without keys run anywhere.
+    must_str!(c, "PING"; "PONG");
+
+    // Multi-key commands need a single slot.
+    must_fail!(c, "MGET", "b", "bar"; "Keys in request don't hash to the same slot");
+    must_ok!(c, "MSET", "{b}1", "x", "{b}2", "y");
+    must_strs!(c, "MGET", "{b}1", "{b}2"; ["x", "y"]);
+    must_fail!(c, "SELECT", "1"; "SELECT is not allowed in cluster mode");
+
+    // Inside MULTI every command must be in the same slot.
+    must_ok!(c, "MULTI");
+    must_str!(c, "SET", "{b}1", "z"; "QUEUED");
+    must_fail!(c, "SET", "bar", "z"; "CrossSlot");
+    must_fail!(c, "EXEC"; "Transaction discarded");
+
+    cluster.close().await;
+}
+
+#[tokio::test]
+async fn test_cluster_migration() {
+    let cluster = MiniredisCluster::run(2).await.unwrap();
+    let mut src = connect(cluster.node(0)).await;
+    let mut dst = connect(cluster.node(1)).await;
+    let slot = 3300; // "b" and "{b}..."
+
+    must_ok!(src, "SET", "b", "1");
+    must_int!(src, "CLUSTER", "COUNTKEYSINSLOT", slot; 1);
+    must_strs!(src, "CLUSTER", "GETKEYSINSLOT", slot, 10; ["b"]);
+
+    cluster.begin_migration(slot, 1);
+    let nodes: String = redis::cmd("CLUSTER")
+        .arg("NODES")
+        .query_async(&mut src)
+        .await
+        .unwrap();
+    assert!(nodes.contains(&format!("[{slot}->-")));
+
+    // Keys the old owner still has are served there, the others get ASK.
+    must_str!(src, "GET", "b"; "1");
+    must_fail!(src, "GET", "{b}new"; format!("{slot} {}", cluster.node(1).addr()).as_str());
+    must_fail!(src, "MGET", "b", "{b}new"; "Multiple keys request during rehashing");
+
+    // The importing node only serves the slot after ASKING.
+    must_fail!(dst, "SET", "{b}new", "x"; format!("{slot} {}", cluster.node(0).addr()).as_str());
+    must_ok!(dst, "ASKING");
+    must_ok!(dst, "SET", "{b}new", "x");
+    must_fail!(dst, "GET", "{b}new"; "Moved");
+
+    cluster.finish_migration(slot);
+    assert_eq!(cluster.slot_owner(slot), 1);
+    must_fail!(src, "GET", "b"; format!("{slot} {}", cluster.node(1).addr()).as_str());
+    must_str!(dst, "GET", "b"; "1");
+    must_str!(dst, "GET", "{b}new"; "x");
+    assert!(!cluster.node(0).exists("b"));
+
+    // And back in one go.
+    cluster.migrate_slot(slot, 0);
+    must_str!(src, "GET", "b"; "1");
+    must_int!(dst, "CLUSTER", "COUNTKEYSINSLOT", slot; 0);
+
+    cluster.close().await;
+}
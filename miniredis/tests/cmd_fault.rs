@@ -0,0 +1,182 @@
+mod helpers;
+use helpers::*;
+
+use std::time::{Duration, Instant};
+
+use miniredis_rs::Fault;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// ── Errors ──────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_fault_error() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "SET", "a", "1");
+    must_int!(c, "MINIREDIS.FAULT", "ADD", "ERROR", "ERR boom", "MATCH", "get", "MATCH", "h*"; 1);
+
+    must_fail!(c, "GET", "a"; "boom");
+    must_fail!(c, "HGET", "h", "f"; "boom");
+    must_ok!(c, "SET", "a", "2");
+
+    must_1!(c, "MINIREDIS.FAULT", "DEL", "1");
+    must_0!(c, "MINIREDIS.FAULT", "DEL", "1");
+    must_str!(c, "GET", "a"; "2");
+
+    must_fail!(c, "MINIREDIS.FAULT", "ADD", "ERROR"; "syntax error");
+    must_fail!(c, "MINIREDIS.FAULT", "ADD", "BOOM"; "syntax error");
+    must_fail!(c, "MINIREDIS.FAULT", "ADD", "DROP", "TIMES", "x"; "not an integer");
+    must_fail!(c, "MINIREDIS.FAULT", "ADD", "DROP", "MATCH"; "syntax error");
+    must_fail!(c, "MINIREDIS.FAULT", "DEL", "x"; "not an integer");
+    must_fail!(c, "MINIREDIS.FAULT", "NOPE"; "unknown subcommand");
+}
+
+#[tokio::test]
+async fn test_fault_times() {
+    let (m, mut c) = start().await;
+
+    must_int!(c, "MINIREDIS.FAULT", "ADD", "ERROR", "ERR first", "TIMES", "2"; 1);
+    must_fail!(c, "PING"; "first");
+    must_fail!(c, "SET", "a", "1"; "first");
+    must_str!(c, "PING"; "PONG");
+
+    // MINIREDIS.* commands are never affected.
+    m.fail_next(1, "ERR second");
+    must_ok!(c, "MINIREDIS.FASTFORWARD", "1");
+    must_fail!(c, "PING"; "second");
+    must_str!(c, "PING"; "PONG");
+}
+
+#[tokio::test]
+async fn test_fault_list_clear() {
+    let (m, mut c) = start().await;
+
+    m.inject_fault(Fault::error("ERR boom").commands(&["get"]).times(3));
+    m.inject_fault(Fault::random_latency(
+        Duration::from_millis(1),
+        Duration::from_millis(5),
+    ));
+    let v: redis::Value = redis::cmd("MINIREDIS.FAULT")
+        .arg("LIST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let redis::Value::Array(items) = v else {
+        panic!("expected array, got {v:?}");
+    };
+    assert_eq!(items.len(), 2);
+    let first: (i64, String, Vec<String>, i64) = redis::from_redis_value(items[0].clone()).unwrap();
+    assert_eq!(first, (1, "error ERR boom".into(), vec!["GET".into()], 3));
+    let second: (i64, String, Vec<String>, i64) =
+        redis::from_redis_value(items[1].clone()).unwrap();
+    assert_eq!(second, (2, "latency 1 5".into(), vec![], -1));
+
+    must_ok!(c, "MINIREDIS.FAULT", "CLEAR");
+    must_nil!(c, "GET", "a");
+    let id = m.inject_fault(Fault::error("ERR boom"));
+    assert!(m.remove_fault(id));
+    assert!(!m.remove_fault(id));
+    m.inject_fault(Fault::error("ERR boom"));
+    m.clear_faults();
+    must_str!(c, "PING"; "PONG");
+}
+
+// ── Latency ─────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_fault_latency() {
+    let (m, mut c) = start().await;
+
+    must_int!(c, "MINIREDIS.FAULT", "ADD", "LATENCY", "200", "MATCH", "SET"; 1);
+
+    let start = Instant::now();
+    must_str!(c, "PING"; "PONG");
+    assert!(start.elapsed() < Duration::from_millis(200));
+
+    // The command runs; only the reply is late.
+    let start = Instant::now();
+    must_ok!(c, "SET", "a", "1");
+    assert!(start.elapsed() >= Duration::from_millis(200));
+    assert_eq!(m.get("a"), Some("1".into()));
+
+    m.clear_faults();
+    m.inject_fault(Fault::random_latency(
+        Duration::from_millis(100),
+        Duration::from_millis(150),
+    ));
+    let start = Instant::now();
+    must_str!(c, "GET", "a"; "1");
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+// ── Dropped connections ─────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_fault_drop() {
+    let m = miniredis_rs::Miniredis::run().await.unwrap();
+    m.set("a", "hello world");
+    m.inject_fault(Fault::drop_connection().commands(&["GET"]).times(1));
+
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    assert_eq!(raw_cmd(&mut s, &["PING"]).await, "+PONG\r\n");
+    s.write_all(b"*2\r\n$3\r\nGET\r\n$1\r\na\r\n")
+        .await
+        .unwrap();
+    let mut reply = Vec::new();
+    tokio::time::timeout(Duration::from_secs(1), s.read_to_end(&mut reply))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reply, b"$11\r\nhell");
+
+    // Other connections are fine, and the fault is used up.
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    assert_eq!(
+        raw_cmd(&mut s, &["GET", "a"]).await,
+        "$11\r\nhello world\r\n"
+    );
+}
+
+// ── MULTI/EXEC and scripts ──────────────────────────────────────────
+
+#[tokio::test]
+async fn test_fault_multi() {
+    let m = miniredis_rs::Miniredis::run().await.unwrap();
+    m.inject_fault(Fault::error("ERR boom").commands(&["INCR"]));
+
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    assert_eq!(raw_cmd(&mut s, &["MULTI"]).await, "+OK\r\n");
+    assert_eq!(raw_cmd(&mut s, &["SET", "a", "1"]).await, "+QUEUED\r\n");
+    assert_eq!(raw_cmd(&mut s, &["INCR", "a"]).await, "+QUEUED\r\n");
+    assert_eq!(
+        raw_cmd(&mut s, &["EXEC"]).await,
+        "*2\r\n+OK\r\n-ERR boom\r\n"
+    );
+    assert_eq!(m.get("a"), Some("1".into()));
+
+    // A failing EXEC discards the transaction.
+    m.clear_faults();
+    m.inject_fault(Fault::error("ERR exec").commands(&["EXEC"]));
+    assert_eq!(raw_cmd(&mut s, &["MULTI"]).await, "+OK\r\n");
+    assert_eq!(raw_cmd(&mut s, &["SET", "a", "2"]).await, "+QUEUED\r\n");
+    assert_eq!(raw_cmd(&mut s, &["EXEC"]).await, "-ERR exec\r\n");
+    assert_eq!(m.get("a"), Some("1".into()));
+    assert_eq!(raw_cmd(&mut s, &["SET", "a", "3"]).await, "+OK\r\n");
+}
+
+#[tokio::test]
+async fn test_fault_script() {
+    let (m, mut c) = start().await;
+    m.inject_fault(Fault::error("ERR boom").commands(&["GET"]));
+
+    must_str!(c, "EVAL", "local r = redis.pcall('GET', 'a') return r['err']", "0"; "ERR boom");
+    must_fail!(c, "EVAL", "return redis.call('GET', 'a')", "0"; "boom");
+
+    // Latency inside a script delays the script's reply.
+    m.clear_faults();
+    m.inject_fault(Fault::latency(Duration::from_millis(200)).commands(&["SET"]));
+    let start = Instant::now();
+    must_ok!(c, "EVAL", "return redis.call('SET', 'a', '1')", "0");
+    assert!(start.elapsed() >= Duration::from_millis(200));
+}
@@ -0,0 +1,227 @@
+mod helpers;
+use helpers::*;
+
+const LIB: &str = r#"#!lua name=mylib
+redis.register_function('getkey', function(keys, args)
+    return redis.call('GET', keys[1])
+end)
+redis.register_function('setkey', function(keys, args)
+    return redis.call('SET', keys[1], args[1])
+end)
+redis.register_function{
+    function_name = 'ro_get',
+    callback = function(keys, args) return redis.call('GET', keys[1]) end,
+    flags = { 'no-writes' },
+    description = 'read a key',
+}
+redis.register_function{
+    function_name = 'ro_set',
+    callback = function(keys, args) return redis.call('SET', keys[1], 'x') end,
+    flags = { 'no-writes' },
+}
+"#;
+
+// ── FUNCTION LOAD ────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_function_load() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_fail!(c, "FUNCTION", "LOAD", LIB; "Library 'mylib' already exists");
+    must_str!(c, "FUNCTION", "LOAD", "REPLACE", LIB; "mylib");
+}
+
+#[tokio::test]
+async fn test_function_load_errors() {
+    let (_m, mut c) = start().await;
+
+    must_fail!(c, "FUNCTION", "LOAD", "return 1"; "Missing library metadata");
+    must_fail!(c, "FUNCTION", "LOAD", "#!js name=foo\n"; "Engine 'js' not found");
+    must_fail!(c, "FUNCTION", "LOAD", "#!lua\n"; "Library name was not given");
+    must_fail!(c, "FUNCTION", "LOAD", "#!lua name=foo bar=baz\n"; "Invalid metadata value given: bar=baz");
+    must_fail!(c, "FUNCTION", "LOAD", "#!lua name=my-lib\n"; "Library names can only contain");
+    must_fail!(c, "FUNCTION", "LOAD", "#!lua name=empty\nlocal a = 1"; "No functions registered");
+    must_fail!(c, "FUNCTION", "LOAD", "#!lua name=bad\nthis is not lua"; "Error compiling function");
+    must_fail!(
+        c, "FUNCTION", "LOAD",
+        "#!lua name=badflag\nredis.register_function{function_name='f', callback=function() end, flags={'nope'}}";
+        "unknown flag given"
+    );
+    // Only register_function and log are available while loading.
+    must_fail!(
+        c, "FUNCTION", "LOAD",
+        "#!lua name=callatload\nredis.call('GET', 'a')\nredis.register_function('f', function() end)";
+        "Error registering functions"
+    );
+}
+
+#[tokio::test]
+async fn test_function_load_duplicate_function() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_fail!(
+        c, "FUNCTION", "LOAD",
+        "#!lua name=other\nredis.register_function('getkey', function() return 1 end)";
+        "Function getkey already exists"
+    );
+}
+
+// ── FCALL / FCALL_RO ─────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_fcall() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_ok!(c, "FCALL", "setkey", "1", "foo", "bar");
+    must_str!(c, "GET", "foo"; "bar");
+    must_str!(c, "FCALL", "getkey", "1", "foo"; "bar");
+    must_str!(c, "FCALL", "ro_get", "1", "foo"; "bar");
+
+    must_fail!(c, "FCALL", "nosuch", "0"; "Function not found");
+    must_fail!(c, "FCALL", "getkey", "notanumber"; "value is not an integer");
+    must_fail!(c, "FCALL", "getkey", "5", "foo"; "Number of keys can't be greater");
+}
+
+#[tokio::test]
+async fn test_fcall_ro() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_ok!(c, "SET", "foo", "bar");
+    must_str!(c, "FCALL_RO", "ro_get", "1", "foo"; "bar");
+    must_fail!(c, "FCALL_RO", "getkey", "1", "foo"; "Can not execute a script with write flag using *_ro command");
+
+    // no-writes functions can't write, even through FCALL.
+    must_fail!(c, "FCALL", "ro_set", "1", "foo"; "Write commands are not allowed");
+    must_fail!(c, "FCALL_RO", "ro_set", "1", "foo"; "Write commands are not allowed");
+    must_str!(c, "GET", "foo"; "bar");
+}
+
+#[tokio::test]
+async fn test_fcall_error() {
+    let (_m, mut c) = start().await;
+
+    must_str!(
+        c, "FUNCTION", "LOAD",
+        "#!lua name=errs\nredis.register_function('boom', function() error('kaboom') end)";
+        "errs"
+    );
+    must_fail!(c, "FCALL", "boom", "0"; "kaboom");
+}
+
+#[tokio::test]
+async fn test_fcall_not_from_scripts() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_fail!(c, "EVAL", "return redis.call('FCALL', 'getkey', 1, 'foo')", "0"; "not allowed from script");
+    must_fail!(c, "EVAL", "return redis.call('FUNCTION', 'FLUSH')", "0"; "not allowed from script");
+}
+
+// ── FUNCTION LIST / DELETE / FLUSH ───────────────────────────────────
+
+#[tokio::test]
+async fn test_function_list() {
+    let (_m, mut c) = start().await;
+
+    let list: Vec<redis::Value> = redis::cmd("FUNCTION")
+        .arg("LIST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(list.is_empty());
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_str!(
+        c, "FUNCTION", "LOAD",
+        "#!lua name=second\nredis.register_function('two', function() return 2 end)";
+        "second"
+    );
+
+    let list: Vec<redis::Value> = redis::cmd("FUNCTION")
+        .arg("LIST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(list.len(), 2);
+
+    let list: Vec<redis::Value> = redis::cmd("FUNCTION")
+        .arg("LIST")
+        .arg("LIBRARYNAME")
+        .arg("my*")
+        .arg("WITHCODE")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(list.len(), 1);
+    let text = format!("{:?}", list[0]);
+    assert!(text.contains("mylib"), "{text}");
+    assert!(text.contains("library_code"), "{text}");
+    assert!(text.contains("no-writes"), "{text}");
+    assert!(text.contains("read a key"), "{text}");
+
+    must_fail!(c, "FUNCTION", "LIST", "BOGUS"; "Unknown argument");
+}
+
+#[tokio::test]
+async fn test_function_delete_flush() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_ok!(c, "FUNCTION", "DELETE", "mylib");
+    must_fail!(c, "FUNCTION", "DELETE", "mylib"; "Library not found");
+    must_fail!(c, "FCALL", "getkey", "1", "foo"; "Function not found");
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    must_ok!(c, "FUNCTION", "FLUSH", "SYNC");
+    must_fail!(c, "FCALL", "getkey", "1", "foo"; "Function not found");
+    must_fail!(c, "FUNCTION", "FLUSH", "NOW"; "only supports SYNC|ASYNC");
+}
+
+// ── FUNCTION DUMP / RESTORE ──────────────────────────────────────────
+
+#[tokio::test]
+async fn test_function_dump_restore() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    let payload: Vec<u8> = redis::cmd("FUNCTION")
+        .arg("DUMP")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+
+    must_fail!(c, "FUNCTION", "RESTORE", payload.clone(); "Library mylib already exists");
+    must_fail!(c, "FUNCTION", "RESTORE", payload.clone(), "MERGE"; "Wrong restore policy");
+    must_ok!(c, "FUNCTION", "RESTORE", payload.clone(), "REPLACE");
+
+    must_ok!(c, "FUNCTION", "FLUSH");
+    must_ok!(c, "FUNCTION", "RESTORE", payload.clone());
+    must_ok!(c, "SET", "foo", "bar");
+    must_str!(c, "FCALL", "getkey", "1", "foo"; "bar");
+
+    let mut corrupted = payload.clone();
+    corrupted[3] ^= 0xff;
+    must_fail!(c, "FUNCTION", "RESTORE", corrupted, "FLUSH"; "payload version or checksum are wrong");
+    // A failed restore leaves the registered libraries alone.
+    must_str!(c, "FCALL", "getkey", "1", "foo"; "bar");
+}
+
+#[tokio::test]
+async fn test_function_stats_kill() {
+    let (_m, mut c) = start().await;
+
+    must_str!(c, "FUNCTION", "LOAD", LIB; "mylib");
+    let stats: redis::Value = redis::cmd("FUNCTION")
+        .arg("STATS")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let text = format!("{stats:?}");
+    assert!(text.contains("libraries_count"), "{text}");
+    must_fail!(c, "FUNCTION", "KILL"; "No scripts in execution right now");
+    must_fail!(c, "FUNCTION", "NOSUCH"; "unknown subcommand");
+}
@@ -307,6 +307,67 @@ async fn test_scan() {
     assert_eq!(keys, vec!["myset"]);
 }
 
+#[tokio::test]
+async fn test_binary_keys() {
+    let (m, mut c) = helpers::start().await;
+    let k1: &[u8] = b"bin\xff\x00";
+    let k2: &[u8] = b"bin\xfe";
+
+    must_ok!(c, "SET", k1, "1");
+    must_ok!(c, "SET", k2, "2");
+    must_str!(c, "GET", k1; "1");
+    must_str!(c, "GET", k2; "2");
+    assert_eq!(m.get_bytes(k1), Some(b"1".to_vec()));
+
+    // Keys that are only equal after lossy UTF-8 decoding stay distinct.
+    must_ok!(c, "SET", b"x\xff".as_slice(), "a");
+    must_ok!(c, "SET", b"x\xfe".as_slice(), "b");
+    must_str!(c, "GET", b"x\xff".as_slice(); "a");
+
+    let mut keys: Vec<Vec<u8>> = redis::cmd("KEYS")
+        .arg(b"bin\xff*".as_slice())
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(keys, vec![k1.to_vec()]);
+    keys = redis::cmd("KEYS")
+        .arg("bin[\u{0}-\u{7f}]*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(keys.is_empty());
+    let (_, keys): (String, Vec<Vec<u8>>) = redis::cmd("SCAN")
+        .arg("0")
+        .arg("MATCH")
+        .arg(b"bin\xfe".as_slice())
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(keys, vec![k2.to_vec()]);
+
+    // Members and fields.
+    must_int!(c, "DEL", k1; 1);
+    must_int!(c, "SADD", k1, b"\xff".as_slice(), b"\xfe".as_slice(); 2);
+    must_int!(c, "SISMEMBER", k1, b"\xff".as_slice(); 1);
+    must_int!(c, "HSET", "h", b"f\xff".as_slice(), b"v\x00".as_slice(); 1);
+    let v: Vec<u8> = redis::cmd("HGET")
+        .arg("h")
+        .arg(b"f\xff".as_slice())
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, b"v\x00");
+    must_int!(c, "ZADD", "z", 0, b"\xff".as_slice(), 0, b"\x80".as_slice(), 0, "a"; 3);
+    let members: Vec<Vec<u8>> = redis::cmd("ZRANGEBYLEX")
+        .arg("z")
+        .arg(b"(\x7f".as_slice())
+        .arg("+")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(members, vec![b"\x80".to_vec(), b"\xff".to_vec()]);
+}
+
 #[tokio::test]
 async fn test_touch() {
     let (_m, mut c) = helpers::start().await;
@@ -354,7 +415,7 @@ async fn test_randomkey() {
 async fn test_wait() {
     let (_m, mut c) = helpers::start().await;
 
-    // WAIT always returns 0 (standalone mode)
+    // No replicas acknowledge anything
     must_int!(c, "WAIT", "0", "0"; 0);
     must_int!(c, "WAIT", "1", "100"; 0);
 
@@ -515,7 +576,7 @@ async fn test_dump() {
     must_ok!(c, "SET", "existing-key", "value");
     must_str!(c, "DUMP", "existing-key"; "value");
 
-    // Non-string type returns nil (stub behavior)
+    // Other types dump as an RDB payload which RESTORE takes back
     let _: i64 = redis::cmd("HSET")
         .arg("set-key")
         .arg("a")
@@ -523,7 +584,14 @@ async fn test_dump() {
         .query_async(&mut c)
         .await
         .unwrap();
-    must_nil!(c, "DUMP", "set-key");
+    let payload: Vec<u8> = redis::cmd("DUMP")
+        .arg("set-key")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    must_fail!(c, "RESTORE", "set-key", "0", payload.clone(); "BUSYKEY");
+    must_ok!(c, "RESTORE", "copy", "0", payload.clone());
+    must_str!(c, "HGET", "copy", "a"; "b");
 
     // Errors
     must_fail!(c, "DUMP"; "wrong number of arguments");
@@ -550,3 +618,146 @@ async fn test_restore() {
     must_fail!(c, "RESTORE", "key"; "wrong number of arguments");
     must_fail!(c, "RESTORE", "key", "argh", "val"; "not an integer");
 }
+
+#[tokio::test]
+async fn test_sort() {
+    let (_m, mut c) = helpers::start().await;
+
+    // Missing key
+    must_strs!(c, "SORT", "nosuch"; [] as [&str; 0]);
+
+    must_int!(c, "RPUSH", "l", "3", "1", "2", "10"; 4);
+    must_strs!(c, "SORT", "l"; ["1", "2", "3", "10"]);
+    must_strs!(c, "SORT", "l", "DESC"; ["10", "3", "2", "1"]);
+    must_strs!(c, "SORT", "l", "ALPHA"; ["1", "10", "2", "3"]);
+    must_strs!(c, "SORT", "l", "LIMIT", "1", "2"; ["2", "3"]);
+    must_strs!(c, "SORT", "l", "LIMIT", "-5", "1"; ["1"]);
+    must_strs!(c, "SORT", "l", "LIMIT", "2", "-1"; ["3", "10"]);
+    must_strs!(c, "SORT", "l", "LIMIT", "10", "2"; [] as [&str; 0]);
+
+    must_int!(c, "SADD", "s", "b", "c", "a"; 3);
+    must_strs!(c, "SORT", "s", "ALPHA"; ["a", "b", "c"]);
+    must_fail!(c, "SORT", "s"; "One or more scores can't be converted into double");
+
+    must_int!(c, "ZADD", "z", "1", "c", "2", "b", "3", "a"; 3);
+    must_strs!(c, "SORT", "z", "ALPHA", "DESC"; ["c", "b", "a"]);
+    // nosort keeps the sorted set order
+    must_strs!(c, "SORT", "z", "BY", "nosort"; ["c", "b", "a"]);
+    must_strs!(c, "SORT", "z", "BY", "nosort", "DESC", "LIMIT", "0", "2"; ["a", "b"]);
+
+    // Errors
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "SORT", "str"; "WRONGTYPE");
+    must_fail!(c, "SORT"; "wrong number of arguments");
+    must_fail!(c, "SORT", "l", "LIMIT", "1"; "syntax error");
+    must_fail!(c, "SORT", "l", "LIMIT", "a", "1"; "not an integer");
+    must_fail!(c, "SORT", "l", "BY"; "syntax error");
+    must_fail!(c, "SORT", "l", "FOO"; "syntax error");
+}
+
+#[tokio::test]
+async fn test_sort_by_get() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "RPUSH", "ids", "1", "2", "3"; 3);
+    must_ok!(
+        c, "MSET", "weight_1", "30", "weight_2", "10", "weight_3", "20"
+    );
+    must_ok!(c, "MSET", "name_1", "one", "name_2", "two");
+    must_int!(c, "HSET", "user_1", "age", "5"; 1);
+    must_int!(c, "HSET", "user_3", "age", "7"; 1);
+
+    must_strs!(c, "SORT", "ids", "BY", "weight_*"; ["2", "3", "1"]);
+    must_strs!(c, "SORT", "ids", "BY", "weight_*", "DESC"; ["1", "3", "2"]);
+    // Missing weights count as 0
+    must_strs!(c, "SORT", "ids", "BY", "user_*->age"; ["2", "1", "3"]);
+
+    let res: Vec<Option<String>> = redis::cmd("SORT")
+        .arg(&["ids", "BY", "weight_*", "GET", "#", "GET", "name_*"])
+        .arg(&["GET", "user_*->age"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        [
+            Some("2"),
+            Some("two"),
+            None,
+            Some("3"),
+            None,
+            Some("7"),
+            Some("1"),
+            Some("one"),
+            Some("5"),
+        ]
+        .map(|v| v.map(String::from))
+    );
+
+    // Patterns without a `*` don't sort, and GET finds nothing
+    must_strs!(c, "SORT", "ids", "BY", "nosort", "DESC"; ["1", "2", "3"]);
+    let res: Vec<Option<String>> = redis::cmd("SORT")
+        .arg(&["ids", "BY", "nosort", "LIMIT", "0", "1", "GET", "constant"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, [None]);
+
+    must_ok!(c, "SET", "weight_2", "heavy");
+    must_fail!(c, "SORT", "ids", "BY", "weight_*"; "One or more scores can't be converted into double");
+    must_strs!(c, "SORT", "ids", "BY", "weight_*", "ALPHA"; ["3", "1", "2"]);
+
+    // Lookups happen in the selected database
+    must_ok!(c, "SELECT", "1");
+    must_int!(c, "RPUSH", "ids", "1", "2"; 2);
+    must_ok!(c, "SET", "weight_1", "2");
+    must_ok!(c, "SET", "weight_2", "1");
+    must_strs!(c, "SORT", "ids", "BY", "weight_*"; ["2", "1"]);
+}
+
+#[tokio::test]
+async fn test_sort_store() {
+    let (m, mut c) = helpers::start().await;
+
+    must_int!(c, "SADD", "s", "3", "1", "2"; 3);
+    must_ok!(c, "SET", "name_1", "one");
+    must_int!(c, "SORT", "s", "STORE", "dst"; 3);
+    must_strs!(c, "LRANGE", "dst", "0", "-1"; ["1", "2", "3"]);
+
+    // Missing GET values are stored as empty strings
+    must_int!(c, "SORT", "s", "GET", "name_*", "STORE", "dst"; 3);
+    must_strs!(c, "LRANGE", "dst", "0", "-1"; ["one", "", ""]);
+
+    // nosort on a set still stores a deterministic order
+    must_int!(c, "SORT", "s", "BY", "nosort", "DESC", "STORE", "dst"; 3);
+    must_strs!(c, "LRANGE", "dst", "0", "-1"; ["3", "2", "1"]);
+
+    // Overwrites other types and drops the TTL
+    must_ok!(c, "SET", "strdst", "value", "EX", "100");
+    must_int!(c, "SORT", "s", "LIMIT", "0", "1", "STORE", "strdst"; 1);
+    must_strs!(c, "LRANGE", "strdst", "0", "-1"; ["1"]);
+    assert!(m.ttl("strdst").is_none());
+
+    // An empty result deletes the destination
+    must_int!(c, "SORT", "nosuch", "STORE", "dst"; 0);
+    must_0!(c, "EXISTS", "dst");
+}
+
+#[tokio::test]
+async fn test_sort_ro() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "RPUSH", "l", "b", "a"; 2);
+    must_strs!(c, "SORT_RO", "l", "ALPHA"; ["a", "b"]);
+    must_fail!(c, "SORT_RO", "l", "STORE", "dst"; "syntax error");
+    must_fail!(c, "SORT_RO"; "wrong number of arguments");
+
+    let res: Vec<String> = redis::cmd("EVAL_RO")
+        .arg("return redis.call('SORT_RO', KEYS[1], 'ALPHA', 'DESC')")
+        .arg(1)
+        .arg("l")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, ["b", "a"]);
+}
@@ -427,3 +427,152 @@ async fn test_georadiusbymember_ro() {
     must_fail!(c, "GEORADIUSBYMEMBER_RO", "Sicily", "Palermo", "200", "km", "STORE", "foo"; "syntax error");
     must_fail!(c, "GEORADIUSBYMEMBER_RO", "Sicily", "Palermo", "200", "km", "STOREDIST", "foo"; "syntax error");
 }
+
+// ── GEOSEARCH / GEOSEARCHSTORE ──────────────────────────────────────
+
+/// The Sicily set from the GEOSEARCH docs.
+async fn add_sicily(c: &mut redis::aio::MultiplexedConnection) {
+    must_int!(*c, "GEOADD", "Sicily",
+        "13.361389", "38.115556", "Palermo",
+        "15.087269", "37.502669", "Catania",
+        "12.758489", "38.788135", "edge1",
+        "17.241510", "38.788135", "edge2"; 4);
+}
+
+#[tokio::test]
+async fn test_geosearch_byradius() {
+    let (_m, mut c) = start().await;
+    add_sicily(&mut c).await;
+
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km", "ASC"; ["Catania", "Palermo"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km", "DESC"; ["Palermo", "Catania"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMMEMBER", "Palermo", "BYRADIUS", "50", "km"; ["Palermo"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "frommember", "Palermo", "byradius", "200000", "m", "asc"; ["Palermo", "edge1", "Catania"]);
+
+    let v: Vec<(String, String, i64)> = redis::cmd("GEOSEARCH")
+        .arg(&[
+            "Sicily",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYRADIUS",
+            "200",
+            "km",
+            "ASC",
+        ])
+        .arg(&["WITHDIST", "WITHHASH"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        vec![
+            ("Catania".into(), "56.4413".into(), 3479447370796909),
+            ("Palermo".into(), "190.4424".into(), 3479099956230698),
+        ]
+    );
+
+    // Missing key
+    must_strs!(c, "GEOSEARCH", "nosuch", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km"; [] as [&str; 0]);
+}
+
+#[tokio::test]
+async fn test_geosearch_bybox() {
+    let (_m, mut c) = start().await;
+    add_sicily(&mut c).await;
+
+    let v: Vec<(String, String, (f64, f64))> = redis::cmd("GEOSEARCH")
+        .arg(&[
+            "Sicily",
+            "FROMLONLAT",
+            "15",
+            "37",
+            "BYBOX",
+            "400",
+            "400",
+            "km",
+            "ASC",
+        ])
+        .arg(&["WITHCOORD", "WITHDIST"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let names: Vec<&str> = v.iter().map(|(n, _, _)| n.as_str()).collect();
+    assert_eq!(names, ["Catania", "Palermo", "edge2", "edge1"]);
+    let dists: Vec<&str> = v.iter().map(|(_, d, _)| d.as_str()).collect();
+    assert_eq!(dists, ["56.4413", "190.4424", "279.7403", "279.7405"]);
+    let (lon, lat) = v[0].2;
+    assert!((lon - 15.087269).abs() < 0.00001 && (lat - 37.502669).abs() < 0.00001);
+
+    // The box is measured along the parallels and meridians: a box that
+    // is too narrow misses the edges, one that is too low misses Palermo.
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "300", "400", "km", "ASC"; ["Catania", "Palermo"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "200", "km", "ASC"; ["Catania"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMMEMBER", "Catania", "BYBOX", "1", "1", "mi"; ["Catania"]);
+}
+
+#[tokio::test]
+async fn test_geosearch_count() {
+    let (_m, mut c) = start().await;
+    add_sicily(&mut c).await;
+
+    // COUNT returns the nearest matches.
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "COUNT", "2"; ["Catania", "Palermo"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "COUNT", "2", "DESC"; ["edge1", "edge2"]);
+
+    // COUNT ANY returns the first matches found (in geohash order), and
+    // sorts only those.
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "COUNT", "2", "ANY"; ["Palermo", "edge1"]);
+    must_strs!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "COUNT", "2", "ANY", "DESC"; ["edge1", "Palermo"]);
+
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "COUNT", "0"; "COUNT must be > 0");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "COUNT", "x"; "not an integer");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "ANY"; "syntax error");
+}
+
+#[tokio::test]
+async fn test_geosearch_errors() {
+    let (_m, mut c) = start().await;
+    add_sicily(&mut c).await;
+
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37"; "wrong number of arguments");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "ASC", "WITHDIST"; "exactly one of BYRADIUS and BYBOX");
+    must_fail!(c, "GEOSEARCH", "Sicily", "BYRADIUS", "15", "km", "ASC", "WITHDIST"; "exactly one of FROMMEMBER or FROMLONLAT");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "FROMMEMBER", "Palermo", "BYRADIUS", "1", "km"; "exactly one of FROMMEMBER or FROMLONLAT");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "BYBOX", "1", "1", "km"; "exactly one of BYRADIUS and BYBOX");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "x", "37", "BYRADIUS", "1", "km"; "not a valid float");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "91", "BYRADIUS", "1", "km"; "invalid longitude,latitude pair");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "x", "km"; "need numeric radius");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "-1", "km"; "radius cannot be negative");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "1", "-1", "km"; "height or width cannot be negative");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "1", "1", "mm"; "unsupported unit");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "STOREDIST"; "syntax error");
+    must_fail!(c, "GEOSEARCH", "Sicily", "FROMMEMBER", "nosuch", "BYRADIUS", "1", "km"; "could not decode requested zset member");
+
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "GEOSEARCH", "str", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_geosearchstore() {
+    let (m, mut c) = start().await;
+    add_sicily(&mut c).await;
+
+    must_int!(c, "GEOSEARCHSTORE", "near", "Sicily", "FROMLONLAT", "15", "37", "BYBOX", "400", "400", "km", "ASC", "COUNT", "3"; 3);
+    must_strs!(c, "ZRANGE", "near", "0", "-1"; ["Palermo", "Catania", "edge2"]);
+    // Stored with their geohash, so they are still geo members.
+    must_strs!(c, "GEOSEARCH", "near", "FROMMEMBER", "Catania", "BYRADIUS", "100", "km"; ["Catania"]);
+
+    must_int!(c, "GEOSEARCHSTORE", "dists", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km", "STOREDIST"; 2);
+    let score = m.zscore("dists", "Catania").unwrap().unwrap();
+    assert!((score - 56.4413).abs() < 0.0001, "score: {score}");
+
+    // No matches, or no source, removes the destination.
+    must_int!(c, "GEOSEARCHSTORE", "near", "Sicily", "FROMLONLAT", "0", "0", "BYRADIUS", "1", "km"; 0);
+    must_0!(c, "EXISTS", "near");
+    must_int!(c, "GEOSEARCHSTORE", "dists", "nosuch", "FROMLONLAT", "15", "37", "BYRADIUS", "200", "km"; 0);
+    must_0!(c, "EXISTS", "dists");
+
+    must_fail!(c, "GEOSEARCHSTORE", "dst", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS", "1", "km", "WITHDIST"; "not compatible");
+    must_fail!(c, "GEOSEARCHSTORE", "dst", "Sicily", "FROMLONLAT", "15", "37", "BYRADIUS"; "wrong number of arguments");
+}
@@ -583,3 +583,362 @@ async fn test_hexpire() {
     must_fail!(c, "HEXPIRE", "myhash", "10", "GT", "LT", "FIELDS", "1", "f"; "GT and LT");
     must_fail!(c, "HEXPIRE", "myhash", "10", "NX", "XX", "FIELDS", "1", "f"; "NX and XX");
 }
+
+/// Run a command that replies with an array of integers.
+async fn ints(c: &mut redis::aio::MultiplexedConnection, args: &[&str]) -> Vec<i64> {
+    redis::cmd(args[0])
+        .arg(&args[1..])
+        .query_async(c)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_hexpire_variants() {
+    let (m, mut c) = helpers::start().await;
+    let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    m.set_time(now);
+
+    must_int!(c, "HSET", "h", "a", "1", "b", "2", "c", "3"; 3);
+    assert_eq!(
+        ints(
+            &mut c,
+            &["HPEXPIRE", "h", "1500", "FIELDS", "2", "a", "nosuch"]
+        )
+        .await,
+        vec![1, -2]
+    );
+    assert_eq!(
+        ints(
+            &mut c,
+            &["HEXPIREAT", "h", "1700000100", "FIELDS", "1", "b"]
+        )
+        .await,
+        vec![1]
+    );
+    assert_eq!(
+        ints(
+            &mut c,
+            &[
+                "HPEXPIREAT",
+                "h",
+                "1700000200000",
+                "NX",
+                "FIELDS",
+                "2",
+                "b",
+                "c"
+            ]
+        )
+        .await,
+        vec![0, 1]
+    );
+
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "3", "a", "b", "nosuch"]).await,
+        vec![1, 100, -2]
+    );
+    assert_eq!(
+        ints(&mut c, &["HPTTL", "h", "FIELDS", "1", "a"]).await,
+        vec![1500]
+    );
+    assert_eq!(
+        ints(&mut c, &["HEXPIRETIME", "h", "FIELDS", "1", "b"]).await,
+        vec![1_700_000_100]
+    );
+    assert_eq!(
+        ints(&mut c, &["HPEXPIRETIME", "h", "FIELDS", "1", "c"]).await,
+        vec![1_700_000_200_000]
+    );
+    assert_eq!(
+        ints(&mut c, &["HTTL", "nosuch", "FIELDS", "1", "a"]).await,
+        vec![-2]
+    );
+
+    // A time in the past deletes the field, and the key once it's empty
+    assert_eq!(
+        ints(
+            &mut c,
+            &["HEXPIREAT", "h", "1600000000", "FIELDS", "1", "a"]
+        )
+        .await,
+        vec![2]
+    );
+    must_int!(c, "HLEN", "h"; 2);
+    assert_eq!(
+        ints(&mut c, &["HEXPIRE", "h", "0", "FIELDS", "2", "b", "c"]).await,
+        vec![2, 2]
+    );
+    must_int!(c, "EXISTS", "h"; 0);
+
+    // LT applies to fields without a TTL, GT doesn't
+    must_int!(c, "HSET", "h", "a", "1"; 1);
+    assert_eq!(
+        ints(&mut c, &["HEXPIRE", "h", "10", "GT", "FIELDS", "1", "a"]).await,
+        vec![0]
+    );
+    assert_eq!(
+        ints(&mut c, &["HEXPIRE", "h", "10", "LT", "FIELDS", "1", "a"]).await,
+        vec![1]
+    );
+
+    must_fail!(c, "HEXPIRE", "h", "-1", "FIELDS", "1", "a"; "invalid expire time");
+    must_fail!(c, "HEXPIRE", "h", "10", "BOGUS", "FIELDS", "1", "a"; "FIELDS is missing");
+    must_fail!(c, "HPEXPIRE", "h", "10", "NX", "a", "b"; "FIELDS is missing");
+    must_fail!(c, "HTTL", "h", "FIELDS", "2", "a"; "numfields");
+    must_fail!(c, "HTTL", "h", "FIELDS", "0", "a"; "numFields");
+    must_fail!(c, "HTTL", "h", "NOFIELDS", "1", "a"; "FIELDS is missing");
+    must_ok!(c, "SET", "str", "val");
+    must_fail!(c, "HTTL", "str", "FIELDS", "1", "a"; "WRONGTYPE");
+    must_fail!(c, "HPEXPIREAT", "str", "1", "FIELDS", "1", "a"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_hpersist() {
+    let (m, mut c) = helpers::start().await;
+
+    must_int!(c, "HSET", "h", "a", "1", "b", "2"; 2);
+    ints(&mut c, &["HEXPIRE", "h", "10", "FIELDS", "1", "a"]).await;
+    assert_eq!(
+        ints(
+            &mut c,
+            &["HPERSIST", "h", "FIELDS", "3", "a", "b", "nosuch"]
+        )
+        .await,
+        vec![1, -1, -2]
+    );
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "a"]).await,
+        vec![-1]
+    );
+    m.fast_forward(std::time::Duration::from_secs(20));
+    must_str!(c, "HGET", "h", "a"; "1");
+    assert_eq!(
+        ints(&mut c, &["HPERSIST", "nosuch", "FIELDS", "1", "a"]).await,
+        vec![-2]
+    );
+
+    must_ok!(c, "SET", "str", "val");
+    must_fail!(c, "HPERSIST", "str", "FIELDS", "1", "a"; "WRONGTYPE");
+    must_fail!(c, "HPERSIST", "h", "FIELDS"; "wrong number of arguments");
+}
+
+#[tokio::test]
+async fn test_hgetdel() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "HSET", "h", "a", "1", "b", "2", "c", "3"; 3);
+    let got: Vec<Option<String>> = redis::cmd("HGETDEL")
+        .arg(&["h", "FIELDS", "2", "a", "nosuch"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![Some("1".to_string()), None]);
+    must_int!(c, "HLEN", "h"; 2);
+
+    let got: Vec<Option<String>> = redis::cmd("HGETDEL")
+        .arg(&["h", "FIELDS", "2", "b", "c"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![Some("2".to_string()), Some("3".to_string())]);
+    must_int!(c, "EXISTS", "h"; 0);
+
+    let got: Vec<Option<String>> = redis::cmd("HGETDEL")
+        .arg(&["h", "FIELDS", "1", "a"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![None]);
+
+    must_ok!(c, "SET", "str", "val");
+    must_fail!(c, "HGETDEL", "str", "FIELDS", "1", "a"; "WRONGTYPE");
+    must_fail!(c, "HGETDEL", "h", "FIELDS", "2", "a"; "numfields");
+}
+
+#[tokio::test]
+async fn test_hgetex() {
+    let (m, mut c) = helpers::start().await;
+    m.set_time(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+
+    must_int!(c, "HSET", "h", "a", "1", "b", "2", "c", "3"; 3);
+    let got: Vec<Option<String>> = redis::cmd("HGETEX")
+        .arg(&["h", "EX", "10", "FIELDS", "2", "a", "nosuch"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![Some("1".to_string()), None]);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "a"]).await,
+        vec![10]
+    );
+
+    let _: Vec<Option<String>> = redis::cmd("HGETEX")
+        .arg(&["h", "PXAT", "1700000005000", "FIELDS", "1", "b"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        ints(&mut c, &["HPTTL", "h", "FIELDS", "1", "b"]).await,
+        vec![5000]
+    );
+
+    let _: Vec<Option<String>> = redis::cmd("HGETEX")
+        .arg(&["h", "PERSIST", "FIELDS", "1", "a"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "a"]).await,
+        vec![-1]
+    );
+
+    // Without options it's just HMGET
+    let got: Vec<Option<String>> = redis::cmd("HGETEX")
+        .arg(&["h", "FIELDS", "1", "b"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![Some("2".to_string())]);
+    assert_eq!(
+        ints(&mut c, &["HPTTL", "h", "FIELDS", "1", "b"]).await,
+        vec![5000]
+    );
+
+    // A time in the past deletes the fields after reading them
+    let got: Vec<Option<String>> = redis::cmd("HGETEX")
+        .arg(&["h", "EXAT", "1600000000", "FIELDS", "1", "c"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![Some("3".to_string())]);
+    must_int!(c, "HEXISTS", "h", "c"; 0);
+
+    must_fail!(c, "HGETEX", "h", "EX", "0", "FIELDS", "1", "a"; "invalid expire time in 'hgetex' command");
+    must_fail!(c, "HGETEX", "h", "EX", "foo", "FIELDS", "1", "a"; "not an integer");
+    must_fail!(c, "HGETEX", "h", "EX", "10", "PERSIST", "FIELDS", "1", "a"; "syntax error");
+    must_fail!(c, "HGETEX", "h", "EX", "10", "a", "b"; "syntax error");
+    must_fail!(c, "HGETEX", "h", "FIELDS", "2", "a"; "numfields");
+    must_ok!(c, "SET", "str", "val");
+    must_fail!(c, "HGETEX", "str", "FIELDS", "1", "a"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_hsetex() {
+    let (m, mut c) = helpers::start().await;
+
+    must_int!(c, "HSETEX", "h", "EX", "10", "FIELDS", "2", "a", "1", "b", "2"; 1);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "2", "a", "b"]).await,
+        vec![10, 10]
+    );
+
+    // FNX: none of the fields may exist; FXX: all of them must
+    must_int!(c, "HSETEX", "h", "FNX", "FIELDS", "2", "a", "x", "c", "3"; 0);
+    must_int!(c, "HSETEX", "h", "FXX", "FIELDS", "2", "a", "x", "c", "3"; 0);
+    must_int!(c, "HSETEX", "h", "FNX", "PX", "500", "FIELDS", "1", "c", "3"; 1);
+    assert_eq!(
+        ints(&mut c, &["HPTTL", "h", "FIELDS", "1", "c"]).await,
+        vec![500]
+    );
+    must_int!(c, "HSETEX", "nosuch", "FXX", "FIELDS", "1", "a", "1"; 0);
+
+    // KEEPTTL keeps the TTL, otherwise it's cleared
+    must_int!(c, "HSETEX", "h", "FXX", "KEEPTTL", "FIELDS", "1", "a", "new"; 1);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "a"]).await,
+        vec![10]
+    );
+    must_str!(c, "HGET", "h", "a"; "new");
+    must_int!(c, "HSETEX", "h", "FIELDS", "1", "b", "new"; 1);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "b"]).await,
+        vec![-1]
+    );
+
+    m.fast_forward(std::time::Duration::from_secs(11));
+    must_strs!(c, "HKEYS", "h"; ["b"]);
+
+    must_fail!(c, "HSETEX", "h", "EX", "0", "FIELDS", "1", "a", "1"; "invalid expire time in 'hsetex' command");
+    must_fail!(c, "HSETEX", "h", "EX", "10", "PX", "10", "FIELDS", "1", "a", "1"; "syntax error");
+    must_fail!(c, "HSETEX", "h", "FNX", "FXX", "FIELDS", "1", "a", "1"; "syntax error");
+    must_fail!(c, "HSETEX", "h", "FIELDS", "1", "a", "1", "b"; "numfields");
+    must_fail!(c, "HSETEX", "h", "FIELDS", "2", "a", "1"; "numfields");
+    must_ok!(c, "SET", "str", "val");
+    must_fail!(c, "HSETEX", "str", "FIELDS", "1", "a", "1"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_hash_field_ttl_writes() {
+    let (m, mut c) = helpers::start().await;
+
+    // HSET drops a field's TTL, HINCRBY keeps it
+    must_int!(c, "HSET", "h", "a", "1", "b", "2"; 2);
+    ints(&mut c, &["HEXPIRE", "h", "10", "FIELDS", "2", "a", "b"]).await;
+    must_int!(c, "HSET", "h", "a", "5"; 0);
+    must_int!(c, "HINCRBY", "h", "b", "1"; 3);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "2", "a", "b"]).await,
+        vec![-1, 10]
+    );
+
+    // HDEL and re-adding a field forgets its TTL
+    must_int!(c, "HDEL", "h", "b"; 1);
+    must_int!(c, "HSET", "h", "b", "2"; 1);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "h", "FIELDS", "1", "b"]).await,
+        vec![-1]
+    );
+    m.fast_forward(std::time::Duration::from_secs(20));
+    must_int!(c, "HLEN", "h"; 2);
+}
+
+#[tokio::test]
+async fn test_hash_field_ttl_copies() {
+    let (m, mut c) = helpers::start().await;
+    // DUMP stores expire times, so keep the clock still.
+    m.set_time(std::time::SystemTime::now());
+
+    must_int!(c, "HSET", "h", "a", "1", "b", "2"; 2);
+    ints(&mut c, &["HEXPIRE", "h", "10", "FIELDS", "1", "a"]).await;
+
+    must_ok!(c, "RENAME", "h", "renamed");
+    assert_eq!(
+        ints(&mut c, &["HTTL", "renamed", "FIELDS", "2", "a", "b"]).await,
+        vec![10, -1]
+    );
+
+    must_int!(c, "COPY", "renamed", "copied"; 1);
+    assert_eq!(
+        ints(&mut c, &["HTTL", "copied", "FIELDS", "1", "a"]).await,
+        vec![10]
+    );
+    must_int!(c, "COPY", "renamed", "other", "DB", "2"; 1);
+    must_int!(c, "MOVE", "copied", "3"; 1);
+
+    let payload: Vec<u8> = redis::cmd("DUMP")
+        .arg("renamed")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let _: () = redis::cmd("RESTORE")
+        .arg("restored")
+        .arg(0)
+        .arg(payload)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    must_str!(c, "HGET", "restored", "b"; "2");
+    assert_eq!(
+        ints(&mut c, &["HTTL", "restored", "FIELDS", "2", "a", "b"]).await,
+        vec![10, -1]
+    );
+
+    m.fast_forward(std::time::Duration::from_secs(11));
+    must_strs!(c, "HKEYS", "renamed"; ["b"]);
+    must_strs!(c, "HKEYS", "restored"; ["b"]);
+    must_ok!(c, "SELECT", "2");
+    must_strs!(c, "HKEYS", "other"; ["b"]);
+    must_ok!(c, "SELECT", "3");
+    must_strs!(c, "HKEYS", "copied"; ["b"]);
+}
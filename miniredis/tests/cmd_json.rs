@@ -0,0 +1,336 @@
+mod helpers;
+use helpers::*;
+
+use std::time::Duration;
+
+// ── JSON.SET / JSON.GET ──────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_json_set_get() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":1,"b":{"c":"x"},"d":[1,2]}"#
+    );
+    must_str!(c, "JSON.GET", "doc"; r#"{"a":1,"b":{"c":"x"},"d":[1,2]}"#);
+    must_str!(c, "JSON.GET", "doc", "$.b.c"; r#"["x"]"#);
+    must_str!(c, "JSON.GET", "doc", ".b.c"; r#""x""#);
+    must_str!(c, "JSON.GET", "doc", "b"; r#"{"c":"x"}"#);
+    must_str!(c, "JSON.GET", "doc", "$.nope"; "[]");
+    must_fail!(c, "JSON.GET", "doc", ".nope"; "does not exist");
+    must_str!(c, "JSON.GET", "doc", ".a", ".d[-1]"; r#"{".a":1,".d[-1]":2}"#);
+    must_str!(c, "JSON.GET", "doc", "$.a", ".d[0]"; r#"{"$.a":[1],".d[0]":[1]}"#);
+    must_nil!(c, "JSON.GET", "nosuch");
+
+    // Replace and add members
+    must_ok!(c, "JSON.SET", "doc", "$.a", "2");
+    must_ok!(c, "JSON.SET", "doc", ".e", "null");
+    must_str!(c, "JSON.GET", "doc", "$.a", "$.e"; r#"{"$.a":[2],"$.e":[null]}"#);
+    must_nil!(c, "JSON.SET", "doc", "$.x.y", "1");
+
+    // NX and XX
+    must_nil!(c, "JSON.SET", "doc", "$.a", "3", "NX");
+    must_ok!(c, "JSON.SET", "doc", "$.a", "3", "XX");
+    must_nil!(c, "JSON.SET", "doc", "$.f", "3", "XX");
+    must_ok!(c, "JSON.SET", "doc", "$.f", "3", "NX");
+    must_nil!(c, "JSON.SET", "new", "$", "{}", "XX");
+    must_str!(c, "TYPE", "doc"; "ReJSON-RL");
+
+    must_fail!(c, "JSON.SET", "other", "$.a", "1"; "at the root");
+    must_fail!(c, "JSON.SET", "doc", "$", "{nope"; "key must be a string");
+    must_fail!(c, "JSON.SET", "doc", "$[", "1"; "invalid JSON path");
+    must_fail!(c, "JSON.SET", "doc", "$", "1", "NX", "XX"; "syntax error");
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "JSON.SET", "str", "$", "1"; "WRONGTYPE");
+    must_fail!(c, "JSON.GET", "str"; "WRONGTYPE");
+}
+
+#[tokio::test]
+async fn test_json_recursive_descent() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":1,"b":{"a":2,"c":[{"a":3}]}}"#
+    );
+    must_str!(c, "JSON.GET", "doc", "$..a"; "[1,2,3]");
+    must_str!(c, "JSON.GET", "doc", "..a"; "1");
+    must_ok!(c, "JSON.SET", "doc", "$..a", "0");
+    must_str!(c, "JSON.GET", "doc", "$..a"; "[0,0,0]");
+    must_str!(c, "JSON.GET", "doc", "$.b.*"; r#"[0,[{"a":0}]]"#);
+    must_str!(c, "JSON.GET", "doc", "$.b['c'][*].a"; "[0]");
+}
+
+#[tokio::test]
+async fn test_json_get_format() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "JSON.SET", "doc", "$", r#"{"a":[1,2],"b":{}}"#);
+    must_str!(
+        c, "JSON.GET", "doc", "INDENT", "\t", "NEWLINE", "\n", "SPACE", " ";
+        "{\n\t\"a\": [\n\t\t1,\n\t\t2\n\t],\n\t\"b\": {}\n}"
+    );
+    must_str!(c, "JSON.GET", "doc", "SPACE", " ", "$.b"; "[{}]");
+}
+
+// ── JSON.DEL / JSON.MGET / JSON.TYPE ─────────────────────────────────
+
+#[tokio::test]
+async fn test_json_del() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":1,"b":{"a":2},"c":[1,2,3]}"#
+    );
+    must_int!(c, "JSON.DEL", "doc", "$..a"; 2);
+    must_int!(c, "JSON.DEL", "doc", "$.c[0,2]"; 2);
+    must_int!(c, "JSON.DEL", "doc", "$.nope"; 0);
+    must_str!(c, "JSON.GET", "doc"; r#"{"b":{},"c":[2]}"#);
+    must_int!(c, "JSON.FORGET", "doc", ".b"; 1);
+    must_int!(c, "JSON.DEL", "doc"; 1);
+    must_0!(c, "EXISTS", "doc");
+    must_int!(c, "JSON.DEL", "doc"; 0);
+}
+
+#[tokio::test]
+async fn test_json_mget() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "JSON.SET", "a", "$", r#"{"x":1}"#);
+    must_ok!(c, "JSON.SET", "b", "$", r#"{"x":{"y":2}}"#);
+    must_ok!(c, "SET", "str", "value");
+    let res: Vec<Option<String>> = redis::cmd("JSON.MGET")
+        .arg("a")
+        .arg("b")
+        .arg("str")
+        .arg("nosuch")
+        .arg("$..x")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![
+            Some("[1]".to_string()),
+            Some(r#"[{"y":2}]"#.to_string()),
+            None,
+            None
+        ]
+    );
+    let res: Vec<Option<String>> = redis::cmd("JSON.MGET")
+        .arg("a")
+        .arg("b")
+        .arg(".x.y")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, vec![None, Some("2".to_string())]);
+}
+
+#[tokio::test]
+async fn test_json_type() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":1,"b":1.5,"c":"s","d":[],"e":null,"f":true}"#
+    );
+    must_str!(c, "JSON.TYPE", "doc"; "object");
+    must_str!(c, "JSON.TYPE", "doc", ".b"; "number");
+    must_strs!(
+        c, "JSON.TYPE", "doc", "$.*";
+        ["integer", "number", "string", "array", "null", "boolean"]
+    );
+    must_nil!(c, "JSON.TYPE", "nosuch");
+}
+
+// ── Arrays, strings, numbers, objects ────────────────────────────────
+
+#[tokio::test]
+async fn test_json_arrappend_arrlen() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":[1],"b":{"a":[]},"c":"s"}"#
+    );
+    must_int!(c, "JSON.ARRAPPEND", "doc", ".a", "2", r#""three""#; 3);
+    let res: Vec<Option<i64>> = redis::cmd("JSON.ARRAPPEND")
+        .arg("doc")
+        .arg("$..a")
+        .arg("{}")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, vec![Some(4), Some(1)]);
+    must_str!(c, "JSON.GET", "doc", "$.a"; r#"[[1,2,"three",{}]]"#);
+
+    must_int!(c, "JSON.ARRLEN", "doc", ".a"; 4);
+    let res: Vec<Option<i64>> = redis::cmd("JSON.ARRLEN")
+        .arg("doc")
+        .arg("$.*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, vec![Some(4), None, None]);
+    must_nil!(c, "JSON.ARRLEN", "nosuch");
+
+    must_fail!(c, "JSON.ARRAPPEND", "doc", ".c", "1"; "expected array but found string");
+    must_fail!(c, "JSON.ARRAPPEND", "doc", ".x", "1"; "does not exist");
+    must_fail!(c, "JSON.ARRAPPEND", "nosuch", "$", "1"; "doesn't exist");
+    must_fail!(c, "JSON.ARRLEN", "doc", ".c"; "expected array but found string");
+}
+
+#[tokio::test]
+async fn test_json_strappend() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":"foo","b":{"a":"x"},"c":1}"#
+    );
+    must_int!(c, "JSON.STRAPPEND", "doc", ".a", r#""bar""#; 6);
+    let res: Vec<Option<i64>> = redis::cmd("JSON.STRAPPEND")
+        .arg("doc")
+        .arg("$..*")
+        .arg(r#""!""#)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(res, vec![Some(7), None, None, Some(2)]);
+    must_str!(c, "JSON.GET", "doc", "$..a"; r#"["foobar!","x!"]"#);
+
+    must_ok!(c, "JSON.SET", "s", "$", r#""abc""#);
+    must_int!(c, "JSON.STRAPPEND", "s", r#""d""#; 4);
+    must_fail!(c, "JSON.STRAPPEND", "s", "d"; "expected value");
+    must_fail!(c, "JSON.STRAPPEND", "doc", ".c", r#""d""#; "expected string but found integer");
+}
+
+#[tokio::test]
+async fn test_json_numincrby() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"a":1,"b":{"a":2.5},"c":"s"}"#
+    );
+    must_str!(c, "JSON.NUMINCRBY", "doc", ".a", "2"; "3");
+    must_str!(c, "JSON.NUMINCRBY", "doc", "$..a", "1.5"; "[4.5,4.0]");
+    must_str!(c, "JSON.NUMINCRBY", "doc", "$.*", "1"; "[5.5,null,null]");
+    must_fail!(c, "JSON.NUMINCRBY", "doc", ".c", "1"; "expected number but found string");
+    must_fail!(c, "JSON.NUMINCRBY", "doc", ".a", r#""x""#; "expected number but found string");
+
+    must_ok!(c, "JSON.SET", "big", "$", &i64::MAX.to_string());
+    must_fail!(c, "JSON.NUMINCRBY", "big", "$", "1"; "overflow");
+}
+
+#[tokio::test]
+async fn test_json_objkeys() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(
+        c,
+        "JSON.SET",
+        "doc",
+        "$",
+        r#"{"z":1,"a":{"y":2,"x":3},"c":[]}"#
+    );
+    must_strs!(c, "JSON.OBJKEYS", "doc"; ["z", "a", "c"]);
+    must_strs!(c, "JSON.OBJKEYS", "doc", ".a"; ["y", "x"]);
+    let res: Vec<Option<Vec<String>>> = redis::cmd("JSON.OBJKEYS")
+        .arg("doc")
+        .arg("$.*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        res,
+        vec![None, Some(vec!["y".to_string(), "x".to_string()]), None]
+    );
+    must_fail!(c, "JSON.OBJKEYS", "doc", ".c"; "expected object but found array");
+}
+
+// ── Keyspace integration ─────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_json_ttl() {
+    let (m, mut c) = start().await;
+
+    must_ok!(c, "JSON.SET", "doc", "$", r#"{"a":1}"#);
+    must_1!(c, "EXPIRE", "doc", "10");
+    must_ok!(c, "JSON.SET", "doc", "$.a", "2");
+    must_ok!(c, "JSON.SET", "doc", "$", r#"{"a":3}"#);
+    must_int!(c, "JSON.NUMINCRBY", "doc", ".a", "1"; 4);
+    must_int!(c, "TTL", "doc"; 10);
+
+    m.fast_forward(Duration::from_secs(10));
+    must_nil!(c, "JSON.GET", "doc");
+    must_0!(c, "EXISTS", "doc");
+}
+
+#[tokio::test]
+async fn test_json_copy_rename_dump_restore() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "JSON.SET", "doc", "$", r#"{"a":[1,"x",{"b":null}]}"#);
+    must_1!(c, "EXPIRE", "doc", "100");
+    must_1!(c, "COPY", "doc", "copy");
+    must_1!(c, "COPY", "doc", "copy", "DB", "1");
+    must_int!(c, "JSON.ARRAPPEND", "copy", ".a", "2"; 4);
+    must_str!(c, "JSON.GET", "doc", ".a"; r#"[1,"x",{"b":null}]"#);
+    must_int!(c, "TTL", "copy"; 100);
+    must_ok!(c, "RENAME", "copy", "renamed");
+    must_str!(c, "TYPE", "renamed"; "ReJSON-RL");
+
+    let dump: Vec<u8> = redis::cmd("DUMP")
+        .arg("doc")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    must_ok!(c, "RESTORE", "restored", "0", dump.as_slice());
+    must_str!(c, "JSON.GET", "restored"; r#"{"a":[1,"x",{"b":null}]}"#);
+    must_str!(c, "TYPE", "restored"; "ReJSON-RL");
+    must_int!(c, "TTL", "restored"; -1);
+
+    must_ok!(c, "SELECT", "1");
+    must_str!(c, "JSON.GET", "copy", "$.a[2].b"; "[null]");
+}
+
+#[tokio::test]
+async fn test_json_direct() {
+    let (m, mut c) = start().await;
+
+    m.json_set("doc", serde_json::json!({"a": [1, 2]})).unwrap();
+    must_str!(c, "JSON.GET", "doc", "$.a[1]"; "[2]");
+    must_ok!(c, "JSON.SET", "doc", "$.b", r#""x""#);
+    assert_eq!(
+        m.json_get("doc").unwrap(),
+        Some(serde_json::json!({"a": [1, 2], "b": "x"}))
+    );
+    assert_eq!(m.json_get("nosuch").unwrap(), None);
+    m.set("str", "value");
+    assert!(m.json_get("str").is_err());
+}
@@ -630,3 +630,138 @@ async fn test_rpush_pop() {
     let list = m.list("l2");
     assert_eq!(list, Some(vec!["a".to_string(), "b".to_string()]));
 }
+
+#[tokio::test]
+async fn test_lmpop() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "RPUSH", "l2", "a", "b", "c", "d"; 4);
+
+    // The first non-empty key is used.
+    let v: (String, Vec<String>) = redis::cmd("LMPOP")
+        .arg(&["2", "l1", "l2", "LEFT"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("l2".into(), vec!["a".into()]));
+    let v: (String, Vec<String>) = redis::cmd("LMPOP")
+        .arg(&["2", "l1", "l2", "right", "COUNT", "10"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("l2".into(), vec!["d".into(), "c".into(), "b".into()]));
+    let v: Option<(String, Vec<String>)> = redis::cmd("LMPOP")
+        .arg(&["2", "l1", "l2", "LEFT"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, None);
+
+    // Errors
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "LMPOP", "1", "str", "LEFT"; "WRONGTYPE");
+    must_fail!(c, "LMPOP", "1", "l"; "wrong number of arguments");
+    must_fail!(c, "LMPOP", "0", "l", "LEFT"; "numkeys should be greater than 0");
+    must_fail!(c, "LMPOP", "x", "l", "LEFT"; "numkeys should be greater than 0");
+    must_fail!(c, "LMPOP", "3", "l", "LEFT"; "syntax error");
+    must_fail!(c, "LMPOP", "1", "l", "UP"; "syntax error");
+    must_fail!(c, "LMPOP", "1", "l", "LEFT", "COUNT"; "syntax error");
+    must_fail!(c, "LMPOP", "1", "l", "LEFT", "COUNT", "0"; "count should be greater than 0");
+    must_fail!(c, "LMPOP", "1", "l", "LEFT", "COUNT", "1", "COUNT", "1"; "syntax error");
+}
+
+#[tokio::test]
+async fn test_blmpop() {
+    let (m, mut c) = helpers::start().await;
+
+    // Non-blocking: data available
+    must_int!(c, "RPUSH", "l", "a", "b", "c"; 3);
+    let v: (String, Vec<String>) = redis::cmd("BLMPOP")
+        .arg(&["1", "1", "l", "LEFT", "COUNT", "2"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("l".into(), vec!["a".into(), "b".into()]));
+
+    // Timeout
+    let v: Option<(String, Vec<String>)> = redis::cmd("BLMPOP")
+        .arg(&["0.1", "1", "empty", "LEFT"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, None);
+
+    // Blocks until another client pushes.
+    let client = redis::Client::open(m.redis_url()).unwrap();
+    let mut c2 = client.get_multiplexed_async_connection().await.unwrap();
+    let waiter = tokio::spawn(async move {
+        let v: (String, Vec<String>) = redis::cmd("BLMPOP")
+            .arg(&["2", "2", "l1", "l2", "RIGHT", "COUNT", "5"])
+            .query_async(&mut c2)
+            .await
+            .unwrap();
+        v
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    must_int!(c, "RPUSH", "l2", "x", "y"; 2);
+    assert_eq!(
+        waiter.await.unwrap(),
+        ("l2".into(), vec!["y".into(), "x".into()])
+    );
+
+    // Inside MULTI it doesn't block.
+    must_ok!(c, "MULTI");
+    let v: String = redis::cmd("BLMPOP")
+        .arg(&["0", "1", "empty", "LEFT"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, "QUEUED");
+    let v: Vec<Option<(String, Vec<String>)>> =
+        redis::cmd("EXEC").query_async(&mut c).await.unwrap();
+    assert_eq!(v, vec![None]);
+
+    // Errors
+    must_fail!(c, "BLMPOP", "1", "1", "l"; "wrong number of arguments");
+    must_fail!(c, "BLMPOP", "-1", "1", "l", "LEFT"; "timeout is negative");
+    must_fail!(c, "BLMPOP", "x", "1", "l", "LEFT"; "timeout is not a float");
+    must_fail!(c, "BLMPOP", "1", "0", "l", "LEFT"; "numkeys should be greater than 0");
+    must_fail!(c, "BLMPOP", "1", "1", "l", "UP"; "syntax error");
+}
+
+#[tokio::test]
+async fn test_blocking_fairness() {
+    let (m, mut c) = helpers::start().await;
+    let client = redis::Client::open(m.redis_url()).unwrap();
+
+    // Clients blocked on a key are served in the order they blocked, also
+    // across commands.
+    let mut waiters = Vec::new();
+    for (i, cmd) in ["BLPOP", "BLMPOP", "BRPOP"].into_iter().enumerate() {
+        let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+        waiters.push(tokio::spawn(async move {
+            let args: &[&str] = match cmd {
+                "BLMPOP" => &["0.3", "1", "l", "LEFT"],
+                _ => &["other", "l", "0.3"],
+            };
+            let v: redis::Value = redis::cmd(cmd)
+                .arg(args)
+                .query_async(&mut conn)
+                .await
+                .unwrap();
+            (i, v)
+        }));
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    must_int!(c, "RPUSH", "l", "a", "b"; 2);
+    let mut served = Vec::new();
+    for w in waiters {
+        let (i, v) = w.await.unwrap();
+        if v != redis::Value::Nil {
+            served.push(i);
+        }
+    }
+    assert_eq!(served, vec![0, 1]);
+    must_int!(c, "LLEN", "l"; 0);
+}
@@ -171,7 +171,7 @@ async fn test_cluster_keyslot() {
         .await
         .unwrap();
 
-    assert_eq!(v, 163);
+    assert_eq!(v, 12182);
 }
 
 #[tokio::test]
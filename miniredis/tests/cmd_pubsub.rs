@@ -1,6 +1,8 @@
 mod helpers;
 use helpers::*;
 
+use tokio::net::TcpStream;
+
 // ── PUBLISH (through regular dispatch) ──────────────────────────────
 
 #[tokio::test]
@@ -294,5 +296,268 @@ async fn test_publish_direct_api() {
     assert_eq!(payload, "hello-direct");
 }
 
+// ── Keyspace notifications ──────────────────────────────────────────
+
+/// Collect the next `n` messages as (channel, payload) pairs.
+async fn next_messages(pubsub: &mut redis::aio::PubSub, n: usize) -> Vec<(String, String)> {
+    let mut stream = pubsub.on_message();
+    let mut out = Vec::new();
+    for _ in 0..n {
+        let msg = tokio::time::timeout(std::time::Duration::from_secs(2), stream.next())
+            .await
+            .expect("timeout waiting for notification")
+            .expect("no message received");
+        out.push((
+            msg.get_channel_name().to_string(),
+            msg.get_payload().unwrap(),
+        ));
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_keyspace_notifications_disabled() {
+    let (m, mut c) = start().await;
+
+    let sub_client = redis::Client::open(m.redis_url()).unwrap();
+    let mut pubsub = sub_client.get_async_pubsub().await.unwrap();
+    pubsub.psubscribe("__key*__:*").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    // Off by default: the SET is silent, only the PUBLISH gets through.
+    must_ok!(c, "SET", "foo", "bar");
+    must_int!(c, "PUBLISH", "__keyspace@0__:marker", "marker"; 1);
+    let got = next_messages(&mut pubsub, 1).await;
+    assert_eq!(got, vec![("__keyspace@0__:marker".into(), "marker".into())]);
+}
+
+#[tokio::test]
+async fn test_keyspace_notifications() {
+    let (m, mut c) = start().await;
+    must_ok!(c, "CONFIG", "SET", "notify-keyspace-events", "KEA");
+
+    let sub_client = redis::Client::open(m.redis_url()).unwrap();
+    let mut pubsub = sub_client.get_async_pubsub().await.unwrap();
+    pubsub.psubscribe("__keyspace@0__:*").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    must_int!(c, "RPUSH", "l", "a"; 1);
+    must_str!(c, "LPOP", "l"; "a");
+    must_int!(c, "DEL", "foo"; 1);
+
+    let got = next_messages(&mut pubsub, 5).await;
+    assert_eq!(
+        got,
+        vec![
+            ("__keyspace@0__:foo".into(), "set".into()),
+            ("__keyspace@0__:l".into(), "rpush".into()),
+            ("__keyspace@0__:l".into(), "lpop".into()),
+            ("__keyspace@0__:l".into(), "del".into()),
+            ("__keyspace@0__:foo".into(), "del".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_keyevent_expired() {
+    let (m, mut c) = start().await;
+    must_ok!(c, "CONFIG", "SET", "notify-keyspace-events", "Ex");
+
+    let sub_client = redis::Client::open(m.redis_url()).unwrap();
+    let mut pubsub = sub_client.get_async_pubsub().await.unwrap();
+    pubsub.subscribe("__keyevent@0__:expired").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    must_ok!(c, "SET", "session", "x", "EX", "10");
+    m.fast_forward(std::time::Duration::from_secs(11));
+
+    let got = next_messages(&mut pubsub, 1).await;
+    assert_eq!(
+        got,
+        vec![("__keyevent@0__:expired".into(), "session".into())]
+    );
+    assert!(!m.exists("session"));
+}
+
+#[tokio::test]
+async fn test_keyevent_classes() {
+    let (m, mut c) = start().await;
+    // Only hash events, and "new" for created keys.
+    must_ok!(c, "CONFIG", "SET", "notify-keyspace-events", "Ehn");
+
+    let sub_client = redis::Client::open(m.redis_url()).unwrap();
+    let mut pubsub = sub_client.get_async_pubsub().await.unwrap();
+    pubsub.psubscribe("__keyevent@*__:*").await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+    must_ok!(c, "SET", "s", "v");
+    must_int!(c, "HSET", "h", "f", "v"; 1);
+    must_int!(c, "HSET", "h", "g", "v"; 1);
+
+    let got = next_messages(&mut pubsub, 4).await;
+    assert_eq!(
+        got,
+        vec![
+            ("__keyevent@0__:new".into(), "s".into()),
+            ("__keyevent@0__:new".into(), "h".into()),
+            ("__keyevent@0__:hset".into(), "h".into()),
+            ("__keyevent@0__:hset".into(), "h".into()),
+        ]
+    );
+}
+
 // Need this import for Stream trait used by on_message()
 use futures_lite::StreamExt;
+
+// ── Sharded pub/sub ─────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_ssubscribe_and_spublish() {
+    let (m, mut c) = start().await;
+    let mut sub = TcpStream::connect(m.addr()).await.unwrap();
+
+    assert_eq!(
+        raw_cmd(&mut sub, &["SSUBSCRIBE", "orders"]).await,
+        "*3\r\n$10\r\nssubscribe\r\n$6\r\norders\r\n:1\r\n"
+    );
+    assert_eq!(
+        raw_cmd(&mut sub, &["SSUBSCRIBE", "users"]).await,
+        "*3\r\n$10\r\nssubscribe\r\n$5\r\nusers\r\n:2\r\n"
+    );
+
+    must_int!(c, "SPUBLISH", "orders", "o1"; 1);
+    assert_eq!(
+        raw_read(&mut sub).await,
+        "*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$2\r\no1\r\n"
+    );
+
+    // Shard channels and classic channels don't mix.
+    must_int!(c, "PUBLISH", "orders", "o2"; 0);
+    must_int!(c, "SPUBLISH", "nosuch", "o3"; 0);
+    assert_eq!(m.spublish("users", "u1"), 1);
+    assert_eq!(
+        raw_read(&mut sub).await,
+        "*3\r\n$8\r\nsmessage\r\n$5\r\nusers\r\n$2\r\nu1\r\n"
+    );
+
+    // Only pub/sub commands are allowed while subscribed.
+    assert!(
+        raw_cmd(&mut sub, &["GET", "foo"])
+            .await
+            .contains("only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT")
+    );
+
+    assert_eq!(
+        raw_cmd(&mut sub, &["SUNSUBSCRIBE", "orders"]).await,
+        "*3\r\n$12\r\nsunsubscribe\r\n$6\r\norders\r\n:1\r\n"
+    );
+    assert_eq!(
+        raw_cmd(&mut sub, &["SUNSUBSCRIBE"]).await,
+        "*3\r\n$12\r\nsunsubscribe\r\n$5\r\nusers\r\n:0\r\n"
+    );
+    // Out of pub/sub mode again.
+    assert_eq!(raw_cmd(&mut sub, &["GET", "foo"]).await, "$-1\r\n");
+}
+
+#[tokio::test]
+async fn test_ssubscribe_resp3() {
+    let (m, mut c) = start().await;
+    let mut sub = TcpStream::connect(m.addr()).await.unwrap();
+    raw_cmd(&mut sub, &["HELLO", "3"]).await;
+
+    assert_eq!(
+        raw_cmd(&mut sub, &["SSUBSCRIBE", "orders"]).await,
+        ">3\r\n$10\r\nssubscribe\r\n$6\r\norders\r\n:1\r\n"
+    );
+    // Classic subscriptions are counted separately.
+    assert_eq!(
+        raw_cmd(&mut sub, &["SUBSCRIBE", "news"]).await,
+        ">3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n"
+    );
+
+    must_int!(c, "SPUBLISH", "orders", "o1"; 1);
+    assert_eq!(
+        raw_read(&mut sub).await,
+        ">3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$2\r\no1\r\n"
+    );
+
+    // Still subscribed to "news" after dropping the shard channels.
+    raw_cmd(&mut sub, &["SUNSUBSCRIBE"]).await;
+    must_int!(c, "PUBLISH", "news", "n1"; 1);
+    assert_eq!(
+        raw_read(&mut sub).await,
+        ">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nn1\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_pubsub_shard_introspection() {
+    let (m, mut c) = start().await;
+
+    must_strs!(c, "PUBSUB", "SHARDCHANNELS"; [] as [&str; 0]);
+    must_int!(c, "SPUBLISH", "orders", "o1"; 0);
+
+    let mut sub1 = TcpStream::connect(m.addr()).await.unwrap();
+    raw_cmd(&mut sub1, &["SSUBSCRIBE", "orders", "users"]).await;
+    let mut sub2 = TcpStream::connect(m.addr()).await.unwrap();
+    raw_cmd(&mut sub2, &["SSUBSCRIBE", "orders"]).await;
+    let mut sub3 = TcpStream::connect(m.addr()).await.unwrap();
+    raw_cmd(&mut sub3, &["SUBSCRIBE", "news"]).await;
+
+    must_strs!(c, "PUBSUB", "SHARDCHANNELS"; ["orders", "users"]);
+    must_strs!(c, "PUBSUB", "SHARDCHANNELS", "u*"; ["users"]);
+    must_strs!(c, "PUBSUB", "CHANNELS"; ["news"]);
+    let v: Vec<(String, i64)> = redis::cmd("PUBSUB")
+        .arg("SHARDNUMSUB")
+        .arg("orders")
+        .arg("users")
+        .arg("news")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        [
+            ("orders".to_string(), 2),
+            ("users".to_string(), 1),
+            ("news".to_string(), 0)
+        ]
+    );
+    must_int!(c, "SPUBLISH", "orders", "o1"; 2);
+
+    assert_eq!(
+        m.pubsub_shard_channels(None),
+        ["orders".to_string(), "users".to_string()]
+    );
+    assert_eq!(
+        m.pubsub_shard_numsub(&["orders"]),
+        [("orders".to_string(), 2)]
+    );
+
+    must_fail!(c, "SPUBLISH", "orders"; "wrong number of arguments");
+    must_fail!(c, "SSUBSCRIBE"; "wrong number of arguments");
+    must_fail!(c, "PUBSUB", "SHARDCHANNELS", "a", "b"; "wrong number of arguments for 'pubsub|shardchannels'");
+}
+
+#[tokio::test]
+async fn test_ssubscribe_multi() {
+    let (m, mut c) = start().await;
+    let mut sub = TcpStream::connect(m.addr()).await.unwrap();
+
+    raw_cmd(&mut sub, &["MULTI"]).await;
+    assert_eq!(
+        raw_cmd(&mut sub, &["SSUBSCRIBE", "orders"]).await,
+        "+QUEUED\r\n"
+    );
+    assert_eq!(
+        raw_cmd(&mut sub, &["EXEC"]).await,
+        "*1\r\n*3\r\n$10\r\nssubscribe\r\n$6\r\norders\r\n:1\r\n"
+    );
+
+    must_int!(c, "SPUBLISH", "orders", "o1"; 1);
+    assert_eq!(
+        raw_read(&mut sub).await,
+        "*3\r\n$8\r\nsmessage\r\n$6\r\norders\r\n$2\r\no1\r\n"
+    );
+}
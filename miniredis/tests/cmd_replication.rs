@@ -0,0 +1,372 @@
+mod helpers;
+
+use std::time::Duration;
+
+use miniredis_rs::Miniredis;
+use redis::aio::MultiplexedConnection;
+
+/// Start a master and a replica following it, waiting for the initial sync.
+async fn start_pair() -> (
+    Miniredis,
+    MultiplexedConnection,
+    Miniredis,
+    MultiplexedConnection,
+) {
+    let (master, mc) = helpers::start().await;
+    let (replica, mut rc) = helpers::start().await;
+    must_ok!(rc, "REPLICAOF", master.host(), master.port());
+    wait_connected(&mut rc).await;
+    (master, mc, replica, rc)
+}
+
+async fn wait_connected(c: &mut MultiplexedConnection) {
+    for _ in 0..200 {
+        let role: redis::Value = redis::cmd("ROLE").query_async(c).await.unwrap();
+        if let redis::Value::Array(items) = role
+            && items.get(3) == Some(&redis::Value::BulkString(b"connected".to_vec()))
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("replica never connected");
+}
+
+/// Block until every write so far reached the replica.
+async fn sync(c: &mut MultiplexedConnection) {
+    must_int!(*c, "WAIT", 1, 1000; 1);
+}
+
+#[tokio::test]
+async fn test_replicaof_full_sync() {
+    let (master, mut mc) = helpers::start().await;
+    let (replica, mut rc) = helpers::start().await;
+
+    must_ok!(mc, "SET", "foo", "bar");
+    must_int!(mc, "RPUSH", "list", "a", "b"; 2);
+    must_ok!(mc, "SELECT", 3);
+    must_ok!(mc, "SET", "three", "3");
+    master.set_ttl("foo", Duration::from_secs(10));
+
+    must_ok!(rc, "SET", "stale", "gone");
+    must_ok!(rc, "REPLICAOF", master.host(), master.port());
+    wait_connected(&mut rc).await;
+
+    assert_eq!(replica.get("foo"), Some("bar".to_string()));
+    assert_eq!(replica.ttl("foo"), Some(Duration::from_secs(10)));
+    assert_eq!(
+        replica.list("list"),
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert!(!replica.exists("stale"));
+    must_ok!(rc, "SELECT", 3);
+    must_str!(rc, "GET", "three"; "3");
+
+    // Same master again is a no-op.
+    must_str!(rc, "REPLICAOF", master.host(), master.port(); "OK Already connected to specified master");
+
+    must_fail!(rc, "REPLICAOF", master.host(), "70000"; "Invalid master port");
+    must_fail!(rc, "REPLICAOF", master.host(), "nope"; "not an integer");
+    must_fail!(rc, "REPLICAOF", "NO"; "wrong number of arguments");
+    must_fail!(rc, "SLAVEOF", "NO", "ONE", "extra"; "wrong number of arguments");
+}
+
+#[tokio::test]
+async fn test_replica_streaming() {
+    let (master, mut mc, replica, mut rc) = start_pair().await;
+
+    must_ok!(mc, "SET", "foo", "bar");
+    must_int!(mc, "HSET", "h", "f", "v"; 1);
+    must_ok!(mc, "SELECT", 2);
+    must_ok!(mc, "SET", "two", "2");
+    must_int!(mc, "DEL", "missing"; 0);
+    sync(&mut mc).await;
+
+    assert_eq!(replica.get("foo"), Some("bar".to_string()));
+    assert_eq!(replica.hget("h", "f").unwrap(), Some("v".to_string()));
+    must_ok!(rc, "SELECT", 2);
+    must_str!(rc, "GET", "two"; "2");
+
+    // Transactions arrive as a whole.
+    redis::cmd("MULTI")
+        .query_async::<()>(&mut mc)
+        .await
+        .unwrap();
+    redis::cmd("INCR")
+        .arg("n")
+        .query_async::<()>(&mut mc)
+        .await
+        .unwrap();
+    redis::cmd("INCR")
+        .arg("n")
+        .query_async::<()>(&mut mc)
+        .await
+        .unwrap();
+    let _: redis::Value = redis::cmd("EXEC").query_async(&mut mc).await.unwrap();
+    sync(&mut mc).await;
+    must_str!(rc, "GET", "n"; "2");
+
+    // The clock moves on the replica too.
+    must_ok!(mc, "SET", "ttl", "x", "EX", 10);
+    master.fast_forward(Duration::from_secs(11));
+    sync(&mut mc).await;
+    must_nil!(rc, "GET", "ttl");
+}
+
+#[tokio::test]
+async fn test_replica_publish() {
+    let (_master, mut mc, replica, _rc) = start_pair().await;
+
+    let client = redis::Client::open(replica.redis_url()).unwrap();
+    let mut pubsub = client.get_async_pubsub().await.unwrap();
+    pubsub.subscribe("news").await.unwrap();
+
+    must_int!(mc, "PUBLISH", "news", "hello"; 0);
+
+    use futures_lite::StreamExt;
+    let msg = tokio::time::timeout(Duration::from_secs(1), pubsub.on_message().next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(msg.get_payload::<String>().unwrap(), "hello");
+}
+
+#[tokio::test]
+async fn test_replica_rewrites() {
+    let (master, mut mc, replica, mut rc) = start_pair().await;
+
+    // Random picks replay as the members that were picked.
+    must_int!(mc, "SADD", "s", "a", "b", "c"; 3);
+    let popped: String = redis::cmd("SPOP")
+        .arg("s")
+        .query_async(&mut mc)
+        .await
+        .unwrap();
+    sync(&mut mc).await;
+    assert!(!replica.is_member("s", &popped));
+    must_int!(rc, "SCARD", "s"; 2);
+
+    // Generated stream IDs are kept.
+    let id: String = redis::cmd("XADD")
+        .arg("st")
+        .arg("*")
+        .arg("k")
+        .arg("v")
+        .query_async(&mut mc)
+        .await
+        .unwrap();
+    sync(&mut mc).await;
+    let entries: redis::Value = redis::cmd("XRANGE")
+        .arg("st")
+        .arg("-")
+        .arg("+")
+        .query_async(&mut rc)
+        .await
+        .unwrap();
+    assert!(format!("{entries:?}").contains(&id));
+
+    // Served blocking pops replay as plain pops.
+    let mut blocked = redis::Client::open(master.redis_url())
+        .unwrap()
+        .get_multiplexed_async_connection()
+        .await
+        .unwrap();
+    let waiter = tokio::spawn(async move {
+        let v: Vec<String> = redis::cmd("BLPOP")
+            .arg("bl")
+            .arg(1)
+            .query_async(&mut blocked)
+            .await
+            .unwrap();
+        v
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    must_int!(mc, "RPUSH", "bl", "x", "y"; 2);
+    assert_eq!(waiter.await.unwrap(), vec!["bl", "x"]);
+    sync(&mut mc).await;
+    assert_eq!(replica.list("bl"), Some(vec!["y".to_string()]));
+
+    // EVALSHA replays as the script body.
+    let sha: String = redis::cmd("SCRIPT")
+        .arg("LOAD")
+        .arg("return redis.call('SET', KEYS[1], ARGV[1])")
+        .query_async(&mut mc)
+        .await
+        .unwrap();
+    must_ok!(mc, "EVALSHA", &sha, 1, "lua", "val");
+    sync(&mut mc).await;
+    must_str!(rc, "GET", "lua"; "val");
+}
+
+#[tokio::test]
+async fn test_replica_read_only() {
+    let (_master, mut mc, _replica, mut rc) = start_pair().await;
+
+    must_fail!(rc, "SET", "foo", "bar"; "You can't write against a read only replica");
+    must_fail!(rc, "EVAL", "return redis.call('SET', 'a', 'b')", 0; "You can't write against a read only replica");
+    // Reads are fine.
+    must_nil!(rc, "GET", "foo");
+    must_str!(rc, "EVAL", "return 'hi'", 0; "hi");
+
+    // Queued writes abort the transaction.
+    must_ok!(rc, "MULTI");
+    must_fail!(rc, "SET", "foo", "bar"; "You can't write against a read only replica");
+    must_fail!(rc, "EXEC"; "Transaction discarded");
+
+    must_ok!(rc, "CONFIG", "SET", "replica-read-only", "no");
+    must_ok!(rc, "SET", "local", "1");
+    must_str!(rc, "GET", "local"; "1");
+    must_fail!(rc, "CONFIG", "SET", "replica-read-only", "maybe"; "argument must be 'yes' or 'no'");
+
+    // The master is never read-only.
+    must_ok!(mc, "SET", "foo", "bar");
+}
+
+#[tokio::test]
+async fn test_role() {
+    let (master, mut mc, replica, mut rc) = start_pair().await;
+    must_ok!(mc, "SET", "foo", "bar");
+    sync(&mut mc).await;
+
+    let role: redis::Value = redis::cmd("ROLE").query_async(&mut mc).await.unwrap();
+    let redis::Value::Array(items) = role else {
+        panic!("expected array, got {role:?}");
+    };
+    assert_eq!(items[0], redis::Value::BulkString(b"master".to_vec()));
+    let redis::Value::Int(offset) = items[1] else {
+        panic!("expected offset, got {:?}", items[1]);
+    };
+    assert!(offset > 0);
+    let redis::Value::Array(replicas) = &items[2] else {
+        panic!("expected replica list, got {:?}", items[2]);
+    };
+    assert_eq!(replicas.len(), 1);
+    assert_eq!(
+        replicas[0],
+        redis::Value::Array(vec![
+            redis::Value::BulkString(b"127.0.0.1".to_vec()),
+            redis::Value::BulkString(replica.port().to_string().into_bytes()),
+            redis::Value::BulkString(offset.to_string().into_bytes()),
+        ])
+    );
+
+    let role: redis::Value = redis::cmd("ROLE").query_async(&mut rc).await.unwrap();
+    assert_eq!(
+        role,
+        redis::Value::Array(vec![
+            redis::Value::BulkString(b"slave".to_vec()),
+            redis::Value::BulkString(master.host().into_bytes()),
+            redis::Value::Int(master.port() as i64),
+            redis::Value::BulkString(b"connected".to_vec()),
+            redis::Value::Int(offset),
+        ])
+    );
+
+    must_fail!(rc, "ROLE", "extra"; "wrong number of arguments");
+}
+
+#[tokio::test]
+async fn test_info_replication() {
+    let (master, mut mc, _replica, mut rc) = start_pair().await;
+
+    let info: String = redis::cmd("INFO")
+        .arg("replication")
+        .query_async(&mut mc)
+        .await
+        .unwrap();
+    assert!(info.contains("# Replication"));
+    assert!(info.contains("role:master"));
+    assert!(info.contains("connected_slaves:1"));
+    assert!(info.contains("slave0:ip=127.0.0.1"));
+
+    let info: String = redis::cmd("INFO")
+        .arg("replication")
+        .query_async(&mut rc)
+        .await
+        .unwrap();
+    assert!(info.contains("role:slave"));
+    assert!(info.contains(&format!("master_port:{}", master.port())));
+    assert!(info.contains("master_link_status:up"));
+    assert!(info.contains("slave_read_only:1"));
+}
+
+#[tokio::test]
+async fn test_replicaof_no_one() {
+    let (_master, mut mc, replica, mut rc) = start_pair().await;
+    must_ok!(mc, "SET", "foo", "bar");
+    sync(&mut mc).await;
+
+    must_ok!(rc, "REPLICAOF", "NO", "ONE");
+    let role: redis::Value = redis::cmd("ROLE").query_async(&mut rc).await.unwrap();
+    let redis::Value::Array(items) = role else {
+        panic!("expected array, got {role:?}");
+    };
+    assert_eq!(items[0], redis::Value::BulkString(b"master".to_vec()));
+
+    // The data stays, and new writes no longer arrive.
+    assert_eq!(replica.get("foo"), Some("bar".to_string()));
+    must_ok!(rc, "SET", "own", "1");
+    must_ok!(mc, "SET", "foo", "baz");
+    must_int!(mc, "WAIT", 1, 50; 0);
+    assert_eq!(replica.get("foo"), Some("bar".to_string()));
+}
+
+#[tokio::test]
+async fn test_wait() {
+    let (master, mut mc) = helpers::start().await;
+    // Without replicas nothing acknowledges.
+    must_ok!(mc, "SET", "foo", "bar");
+    must_int!(mc, "WAIT", 1, 10; 0);
+
+    let (r1, mut rc1) = helpers::start().await;
+    let (_r2, mut rc2) = helpers::start().await;
+    must_ok!(rc1, "REPLICAOF", master.host(), master.port());
+    must_ok!(rc2, "SLAVEOF", master.host(), master.port());
+    wait_connected(&mut rc1).await;
+    wait_connected(&mut rc2).await;
+
+    must_ok!(mc, "SET", "foo", "baz");
+    must_int!(mc, "WAIT", 2, 1000; 2);
+    must_int!(mc, "WAIT", 0, 0; 2);
+
+    // Inside a transaction WAIT doesn't block.
+    must_ok!(mc, "MULTI");
+    redis::cmd("WAIT")
+        .arg(2)
+        .arg(0)
+        .query_async::<()>(&mut mc)
+        .await
+        .unwrap();
+    let v: Vec<i64> = redis::cmd("EXEC").query_async(&mut mc).await.unwrap();
+    assert_eq!(v, vec![2]);
+
+    r1.close().await;
+    must_ok!(mc, "SET", "foo", "qux");
+    must_int!(mc, "WAIT", 2, 200; 1);
+
+    must_fail!(rc2, "WAIT", 1, 0; "WAIT cannot be used with replica instances");
+    must_fail!(mc, "WAIT", "x", 0; "not an integer");
+}
+
+#[tokio::test]
+async fn test_replica_evictions() {
+    let (_master, mut mc, replica, _rc) = start_pair().await;
+    must_ok!(
+        mc,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "300",
+        "maxmemory-policy",
+        "allkeys-lru"
+    );
+
+    let value = "x".repeat(100);
+    for key in ["k1", "k2", "k3", "k4"] {
+        must_ok!(mc, "SET", key, &value);
+    }
+    sync(&mut mc).await;
+    // The replica drops what the master evicted.
+    assert!(!replica.exists("k1"));
+    assert!(replica.exists("k4"));
+}
@@ -94,6 +94,21 @@ async fn test_resp2_null_is_dollar_minus_one() {
     );
 }
 
+#[tokio::test]
+async fn test_bzpopmin_resp3_double() {
+    let m = miniredis_rs::Miniredis::run().await.unwrap();
+    let mut stream = raw_connect(&m).await;
+    let _ = raw_cmd(&mut stream, &["HELLO", "3"]).await;
+    let _ = raw_cmd(&mut stream, &["ZADD", "z", "1.5", "a", "2", "b"]).await;
+
+    let resp = raw_cmd(&mut stream, &["BZPOPMIN", "z", "0"]).await;
+    assert_eq!(resp, b"*3\r\n$1\r\nz\r\n$1\r\na\r\n,1.5\r\n");
+    let resp = raw_cmd(&mut stream, &["ZMPOP", "1", "z", "MIN"]).await;
+    assert_eq!(resp, b"*2\r\n$1\r\nz\r\n*1\r\n*2\r\n$1\r\nb\r\n,2\r\n");
+    let resp = raw_cmd(&mut stream, &["ZMPOP", "1", "z", "MIN"]).await;
+    assert_eq!(resp, b"_\r\n");
+}
+
 // ── HGETALL RESP3 map ───────────────────────────────────────────────
 
 #[tokio::test]
@@ -1,5 +1,6 @@
 mod helpers;
 use helpers::*;
+use miniredis_rs::{LogLevel, ScriptLogEntry};
 
 // ── EVAL basic ───────────────────────────────────────────────────────
 
@@ -440,6 +441,44 @@ async fn test_eval_log() {
     must_nil!(c, "EVAL", "redis.log(redis.LOG_NOTICE, 'hello')", "0");
 }
 
+#[tokio::test]
+async fn test_eval_log_messages() {
+    let (m, mut c) = start().await;
+
+    must_nil!(
+        c,
+        "EVAL",
+        "redis.log(redis.LOG_WARNING, 'hello', 'world', 42)",
+        "0"
+    );
+    must_nil!(
+        c,
+        "EVAL",
+        "redis.log(redis.LOG_DEBUG, ARGV[1])",
+        "0",
+        "debug"
+    );
+    assert_eq!(
+        m.script_log(),
+        vec![
+            ScriptLogEntry {
+                level: LogLevel::Warning,
+                message: "hello world 42".to_string(),
+            },
+            ScriptLogEntry {
+                level: LogLevel::Debug,
+                message: "debug".to_string(),
+            },
+        ]
+    );
+    m.clear_script_log();
+    assert!(m.script_log().is_empty());
+
+    must_fail!(c, "EVAL", "redis.log(redis.LOG_NOTICE)", "0"; "requires two arguments or more");
+    must_fail!(c, "EVAL", "redis.log('x', 'y')", "0"; "First argument must be a number");
+    must_fail!(c, "EVAL", "redis.log(9, 'y')", "0"; "Invalid debug level");
+}
+
 // ── redis.replicate_commands() / redis.set_repl() ────────────────────
 
 #[tokio::test]
@@ -457,3 +496,190 @@ async fn test_eval_set_repl() {
     // set_repl is a no-op, takes an integer argument
     must_nil!(c, "EVAL", "redis.set_repl(0)", "0");
 }
+
+// ── bit, struct and cmsgpack ─────────────────────────────────────────
+
+/// Run a script and return its reply as bytes.
+async fn eval_bytes(c: &mut redis::aio::MultiplexedConnection, script: &str) -> Vec<u8> {
+    redis::cmd("EVAL")
+        .arg(script)
+        .arg(0)
+        .query_async(c)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_eval_bit() {
+    let (_m, mut c) = start().await;
+
+    must_int!(c, "EVAL", "return bit.band(0xff, 0x0f, 0x3c)", "0"; 0x0c);
+    must_int!(c, "EVAL", "return bit.bor(1, 2, 4)", "0"; 7);
+    must_int!(c, "EVAL", "return bit.bxor(5, 3)", "0"; 6);
+    must_int!(c, "EVAL", "return bit.bnot(0)", "0"; -1);
+    must_int!(c, "EVAL", "return bit.tobit(0xffffffff)", "0"; -1);
+    must_int!(c, "EVAL", "return bit.lshift(1, 31)", "0"; -2147483648);
+    must_int!(c, "EVAL", "return bit.rshift(-1, 28)", "0"; 15);
+    must_int!(c, "EVAL", "return bit.arshift(-256, 4)", "0"; -16);
+    must_int!(c, "EVAL", "return bit.rol(0x12345678, 8)", "0"; 0x34567812);
+    must_int!(c, "EVAL", "return bit.bswap(0x12345678)", "0"; 0x78563412);
+    must_str!(c, "EVAL", "return bit.tohex(255)", "0"; "000000ff");
+    must_str!(c, "EVAL", "return bit.tohex(255, -4)", "0"; "00FF");
+}
+
+#[tokio::test]
+async fn test_eval_struct() {
+    let (_m, mut c) = start().await;
+
+    assert_eq!(
+        eval_bytes(&mut c, "return struct.pack('>I2<h', 258, -2)").await,
+        vec![1, 2, 0xfe, 0xff]
+    );
+    assert_eq!(
+        eval_bytes(&mut c, "return struct.pack('bsc0', 1, 'ab', 'xyz')").await,
+        b"\x01ab\x00xyz".to_vec()
+    );
+    let got: Vec<i64> = redis::cmd("EVAL")
+        .arg("return {struct.unpack('>HBb', '\\1\\2\\3\\255')}")
+        .arg(0)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![258, 3, -1, 5]);
+    must_str!(c, "EVAL", "return tostring(struct.unpack('<d', struct.pack('<d', 1.5)))", "0"; "1.5");
+    must_str!(c, "EVAL", "return (struct.unpack('Bc0', struct.pack('Bc0', 3, 'abc')))", "0"; "abc");
+    must_int!(c, "EVAL", "return struct.size('>iHd')", "0"; 14);
+    must_int!(c, "EVAL", "return struct.size('!4bi')", "0"; 8);
+    must_fail!(c, "EVAL", "return struct.unpack('i', 'ab')", "0"; "data string too short");
+    must_fail!(c, "EVAL", "return struct.pack('q', 1)", "0"; "invalid format option 'q'");
+}
+
+#[tokio::test]
+async fn test_eval_cmsgpack() {
+    let (_m, mut c) = start().await;
+
+    assert_eq!(
+        eval_bytes(&mut c, "return cmsgpack.pack({1, 2, 300})").await,
+        vec![0x93, 0x01, 0x02, 0xcd, 0x01, 0x2c]
+    );
+    assert_eq!(
+        eval_bytes(&mut c, "return cmsgpack.pack('hi', -1, true)").await,
+        vec![0xa2, b'h', b'i', 0xff, 0xc3]
+    );
+    // Doubles are packed as floats when that's lossless.
+    must_int!(c, "EVAL", "return #cmsgpack.pack(1.5)", "0"; 5);
+    must_int!(c, "EVAL", "return #cmsgpack.pack(0.1)", "0"; 9);
+    must_str!(c, "EVAL", "return tostring(cmsgpack.unpack(cmsgpack.pack(0.1)))", "0"; "0.1");
+
+    must_int!(c, "EVAL", "return cmsgpack.unpack(cmsgpack.pack({a = 1, b = {5, 6}})).b[2]", "0"; 6);
+    let got: Vec<i64> = redis::cmd("EVAL")
+        .arg("return {cmsgpack.unpack(cmsgpack.pack(1, 2, 3))}")
+        .arg(0)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![1, 2, 3]);
+    let got: Vec<i64> = redis::cmd("EVAL")
+        .arg("local data = cmsgpack.pack(7, 8) local o1, a = cmsgpack.unpack_one(data) local o2, b = cmsgpack.unpack_one(data, o1) return {o1, a, o2, b}")
+        .arg(0)
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(got, vec![1, 7, -1, 8]);
+    must_int!(c, "EVAL", "return select('#', cmsgpack.unpack_limit(cmsgpack.pack(1, 2, 3), 2))", "0"; 3);
+
+    must_fail!(c, "EVAL", "return cmsgpack.pack()", "0"; "MessagePack pack needs input.");
+    must_fail!(c, "EVAL", "return cmsgpack.unpack('\\146\\1')", "0"; "Missing bytes in input.");
+    must_fail!(c, "EVAL", "return cmsgpack.unpack('\\193')", "0"; "Bad data format in input.");
+}
+
+// ── redis.setresp() ──────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_eval_setresp() {
+    let (_m, mut c) = start().await;
+
+    must_int!(c, "HSET", "h", "f", "v"; 1);
+    must_int!(c, "ZADD", "z", "1.5", "m"; 1);
+    must_int!(c, "SADD", "s", "a"; 1);
+
+    // RESP2: nil is false, maps are flat and doubles are strings.
+    must_int!(c, "EVAL", "return redis.call('GET', 'nosuch') == false", "0"; 1);
+    must_strs!(c, "EVAL", "return redis.call('HGETALL', 'h')", "0"; ["f", "v"]);
+    must_str!(c, "EVAL", "return redis.call('ZSCORE', 'z', 'm')", "0"; "1.5");
+
+    // RESP3: nil, {map=...}, {set=...} and {double=...}.
+    must_int!(c, "EVAL", "redis.setresp(3) return redis.call('GET', 'nosuch') == nil", "0"; 1);
+    must_str!(c, "EVAL", "redis.setresp(3) return redis.call('HGETALL', 'h').map.f", "0"; "v");
+    must_int!(c, "EVAL", "redis.setresp(3) return redis.call('SMEMBERS', 's').set.a", "0"; 1);
+    must_int!(c, "EVAL", "redis.setresp(3) return redis.call('ZSCORE', 'z', 'm').double * 2", "0"; 3);
+
+    // Returned RESP3 tables become RESP3 replies.
+    must_str!(c, "EVAL", "return {double = 2.5}", "0"; "2.5");
+    must_strs!(c, "EVAL", "redis.setresp(3) return redis.call('HGETALL', 'h')", "0"; ["f", "v"]);
+    must_fail!(c, "EVAL", "redis.setresp(4)", "0"; "RESP version must be 2 or 3.");
+}
+
+// ── error tables ─────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_eval_error_tables() {
+    let (_m, mut c) = start().await;
+
+    must_fail!(c, "EVAL", "error({err = 'MYERR custom'})", "0"; "custom");
+    must_fail!(c, "EVAL", "error(redis.error_reply('OOPS bad\\r\\nthing'))", "0"; "bad  thing");
+    must_str!(c, "EVAL", "return redis.status_reply('FINE\\nNOW')", "0"; "FINE NOW");
+    // Other errors are unchanged.
+    must_fail!(c, "EVAL", "error('plain')", "0"; "plain");
+    must_fail!(c, "EVAL", "return redis.call('INCR', 'x', 'y')", "0"; "wrong number of arguments");
+}
+
+// ── SCRIPT KILL ──────────────────────────────────────────────────────
+
+// Scripts run off the async workers, so a single-threaded runtime still
+// serves c2 while c1's script runs.
+#[tokio::test]
+async fn test_script_kill() {
+    let (_m, c1, mut c2) = start_two_clients().await;
+
+    must_fail!(c2, "SCRIPT", "KILL"; "No scripts in execution right now.");
+    must_strs!(c2, "CONFIG", "GET", "lua-time-limit"; ["lua-time-limit", "5000"]);
+    must_ok!(c2, "CONFIG", "SET", "busy-reply-threshold", "100");
+
+    // Run a script on c1 in the background.
+    let spawn_eval = |mut c: redis::aio::MultiplexedConnection, script: &'static str| {
+        tokio::spawn(async move {
+            let res = redis::cmd("EVAL")
+                .arg(script)
+                .arg(0)
+                .query_async::<redis::Value>(&mut c)
+                .await;
+            (res, c)
+        })
+    };
+
+    let busy = spawn_eval(c1, "while true do end");
+    // A command sent before the threshold waits, then gets BUSY.
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    must_fail!(c2, "GET", "foo"; "Redis is busy running a script");
+    must_fail!(c2, "FUNCTION", "KILL"; "You can only call SCRIPT KILL");
+    must_ok!(c2, "SCRIPT", "KILL");
+    let (res, c1) = busy.await.unwrap();
+    let err = res.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("Script killed by user with SCRIPT KILL")
+    );
+    must_nil!(c2, "GET", "foo");
+
+    // A script that wrote can't be killed.
+    let busy = spawn_eval(
+        c1,
+        "redis.call('SET', 'foo', 'bar') local t = os.clock() while os.clock() - t < 0.2 do end",
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    must_fail!(c2, "SCRIPT", "KILL"; "already executed write commands");
+    let (res, _) = busy.await.unwrap();
+    assert!(res.is_ok());
+    must_str!(c2, "GET", "foo"; "bar");
+}
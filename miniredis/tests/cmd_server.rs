@@ -1,6 +1,11 @@
 mod helpers;
 use helpers::*;
 
+use std::time::{Duration, UNIX_EPOCH};
+
+use miniredis_rs::ReplyType;
+use tokio::net::TcpStream;
+
 // ── DBSIZE ──────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -178,3 +183,690 @@ async fn test_memory_errors() {
     must_fail!(c, "MEMORY", "USAGE"; "wrong number of arguments");
     must_fail!(c, "MEMORY", "BOGUS"; "unknown subcommand");
 }
+
+// ── CONFIG ──────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_config_get_set() {
+    let (_m, mut c) = start().await;
+
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("notify-keyspace-events")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec!["notify-keyspace-events", ""]);
+
+    must_ok!(c, "CONFIG", "SET", "notify-keyspace-events", "KEA");
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("notify-*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec!["notify-keyspace-events", "AKE"]);
+
+    must_ok!(c, "CONFIG", "SET", "notify-keyspace-events", "Elx");
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("notify-keyspace-events")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec!["notify-keyspace-events", "lxE"]);
+
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("databases")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec!["databases", "16"]);
+}
+
+#[tokio::test]
+async fn test_config_errors() {
+    let (_m, mut c) = start().await;
+
+    must_fail!(c, "CONFIG", "SET", "notify-keyspace-events", "Q"; "Invalid event class character");
+    must_fail!(c, "CONFIG", "SET", "databases", "4"; "can't set immutable config");
+    must_fail!(c, "CONFIG", "SET", "nosuch", "1"; "Unknown option or number of arguments");
+    must_fail!(c, "CONFIG", "SET", "notify-keyspace-events"; "wrong number of arguments");
+    must_fail!(c, "CONFIG", "BOGUS"; "unknown subcommand");
+}
+
+// ── MAXMEMORY ───────────────────────────────────────────────────────
+
+/// A value that makes each key count 121 bytes: 16 + 2 for a two-letter
+/// key, 103 for the value.
+fn value() -> String {
+    "x".repeat(100)
+}
+
+fn evicted_keys(info: &str) -> i64 {
+    info.lines()
+        .find_map(|l| l.strip_prefix("evicted_keys:"))
+        .unwrap()
+        .trim()
+        .parse()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_maxmemory_config() {
+    let (_m, mut c) = start().await;
+
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("maxmemory*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, vec!["maxmemory", "0", "maxmemory-policy", "noeviction"]);
+
+    must_ok!(
+        c,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "1mb",
+        "maxmemory-policy",
+        "ALLKEYS-LRU"
+    );
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("maxmemory*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        vec!["maxmemory", "1048576", "maxmemory-policy", "allkeys-lru"]
+    );
+
+    let info: String = redis::cmd("INFO")
+        .arg("memory")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(info.contains("maxmemory:1048576"));
+    assert!(info.contains("maxmemory_policy:allkeys-lru"));
+    assert!(info.contains("used_memory:0"));
+
+    must_fail!(c, "CONFIG", "SET", "maxmemory", "lots"; "argument must be a memory value");
+    must_fail!(c, "CONFIG", "SET", "maxmemory-policy", "lru"; "argument(s) must be one of the following");
+}
+
+#[tokio::test]
+async fn test_maxmemory_noeviction() {
+    let (m, mut c) = start().await;
+    must_ok!(c, "CONFIG", "SET", "maxmemory", "300");
+
+    must_ok!(c, "SET", "k1", value());
+    must_ok!(c, "SET", "k2", value());
+    must_ok!(c, "SET", "k3", value());
+    // Over the limit now: writes that grow the dataset fail, the rest works.
+    must_fail!(c, "SET", "k4", value(); "command not allowed when used memory > 'maxmemory'");
+    must_fail!(c, "LPUSH", "l", "a"; "command not allowed when used memory > 'maxmemory'");
+    must_str!(c, "GET", "k1"; value());
+    must_int!(c, "DBSIZE"; 3);
+
+    must_ok!(c, "MULTI");
+    must_fail!(c, "SET", "k4", value(); "command not allowed when used memory > 'maxmemory'");
+    must_fail!(c, "EXEC"; "Transaction discarded");
+
+    // Queued under the limit, but memory use is over it by EXEC.
+    must_int!(c, "DEL", "k3"; 1);
+    must_ok!(c, "MULTI");
+    must_str!(c, "SET", "k4", value(); "QUEUED");
+    must_str!(c, "GET", "k1"; "QUEUED");
+    m.set("k3", &value());
+    must_fail!(c, "EXEC"; "Transaction discarded because of: OOM command not allowed");
+    must_int!(c, "EXISTS", "k4"; 0);
+    must_ok!(c, "MULTI");
+    must_str!(c, "GET", "k1"; "QUEUED");
+    let replies: Vec<String> = redis::cmd("EXEC").query_async(&mut c).await.unwrap();
+    assert_eq!(replies, vec![value()]);
+
+    must_fail!(c, "EVAL", "return redis.call('SET', 'k4', 'v')", 0; "command not allowed when used memory > 'maxmemory'");
+    must_str!(c, "EVAL", "return redis.call('GET', 'k1')", 0; value());
+
+    // Deleting frees memory again.
+    must_int!(c, "DEL", "k1"; 1);
+    must_ok!(c, "SET", "k4", value());
+
+    must_ok!(c, "CONFIG", "SET", "maxmemory", "0");
+    must_ok!(c, "SET", "k5", value());
+}
+
+#[tokio::test]
+async fn test_maxmemory_allkeys_lru() {
+    let (_m, mut c) = start().await;
+    must_ok!(
+        c,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "400",
+        "maxmemory-policy",
+        "allkeys-lru"
+    );
+
+    must_ok!(c, "SET", "k1", value());
+    must_ok!(c, "SET", "k2", value());
+    must_ok!(c, "SET", "k3", value());
+    must_str!(c, "GET", "k1"; value());
+    must_ok!(c, "SET", "k4", value());
+    // Over the limit; the least recently used key goes before the next
+    // command runs.
+    must_int!(c, "EXISTS", "k2"; 0);
+    must_int!(c, "EXISTS", "k1", "k3", "k4"; 3);
+
+    let info: String = redis::cmd("INFO")
+        .arg("stats")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(evicted_keys(&info), 1);
+}
+
+#[tokio::test]
+async fn test_maxmemory_allkeys_lfu() {
+    let (_m, mut c) = start().await;
+    must_ok!(
+        c,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "400",
+        "maxmemory-policy",
+        "allkeys-lfu"
+    );
+
+    must_ok!(c, "SET", "k1", value());
+    must_ok!(c, "SET", "k2", value());
+    must_ok!(c, "SET", "k3", value());
+    must_str!(c, "GET", "k1"; value());
+    must_str!(c, "GET", "k1"; value());
+    must_str!(c, "GET", "k2"; value());
+    must_int!(c, "OBJECT", "FREQ", "k1"; 3);
+    must_ok!(c, "SET", "k4", value());
+
+    must_int!(c, "EXISTS", "k3"; 0);
+    must_int!(c, "EXISTS", "k1", "k2", "k4"; 3);
+}
+
+#[tokio::test]
+async fn test_maxmemory_volatile() {
+    let (_m, mut c) = start().await;
+    must_ok!(
+        c,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "400",
+        "maxmemory-policy",
+        "volatile-ttl"
+    );
+
+    must_ok!(c, "SET", "k1", value());
+    must_ok!(c, "SET", "k2", value(), "EX", 100);
+    must_ok!(c, "SET", "k3", value(), "EX", 50);
+    must_ok!(c, "SET", "k4", value());
+    must_int!(c, "EXISTS", "k3"; 0);
+    must_int!(c, "DBSIZE"; 3);
+
+    // Only keys with a TTL can go.
+    must_ok!(c, "CONFIG", "SET", "maxmemory-policy", "volatile-lru");
+    must_ok!(c, "SET", "k5", value());
+    must_ok!(c, "SET", "k6", value());
+    must_int!(c, "EXISTS", "k2"; 0);
+    must_fail!(c, "SET", "k7", value(); "command not allowed when used memory > 'maxmemory'");
+    must_int!(c, "EXISTS", "k1", "k4", "k5", "k6"; 4);
+}
+
+#[tokio::test]
+async fn test_maxmemory_allkeys_random() {
+    let (m, mut c) = start().await;
+    m.seed(42);
+    must_ok!(
+        c,
+        "CONFIG",
+        "SET",
+        "maxmemory",
+        "400",
+        "maxmemory-policy",
+        "allkeys-random"
+    );
+
+    for i in 0..10 {
+        must_ok!(c, "SET", format!("k{i}"), value());
+    }
+    must_int!(c, "DBSIZE"; 3);
+}
+
+// ── SAVE / BGSAVE / LASTSAVE / DEBUG RELOAD ─────────────────────────
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("miniredis-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn test_save() {
+    let (_m, mut c) = start().await;
+    let dir = temp_dir("save");
+
+    must_ok!(c, "CONFIG", "SET", "dir", dir.to_str().unwrap());
+    must_ok!(c, "CONFIG", "SET", "dbfilename", "snap.rdb");
+    let v: Vec<String> = redis::cmd("CONFIG")
+        .arg("GET")
+        .arg("d*")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        vec![
+            "databases",
+            "16",
+            "dbfilename",
+            "snap.rdb",
+            "dir",
+            dir.to_str().unwrap()
+        ]
+    );
+
+    let before: i64 = redis::cmd("LASTSAVE").query_async(&mut c).await.unwrap();
+    must_ok!(c, "SET", "foo", "bar");
+    must_ok!(c, "SAVE");
+    assert!(dir.join("snap.rdb").exists());
+    let after: i64 = redis::cmd("LASTSAVE").query_async(&mut c).await.unwrap();
+    assert!(after >= before);
+
+    must_str!(c, "BGSAVE"; "Background saving started");
+    must_str!(c, "BGSAVE", "SCHEDULE"; "Background saving started");
+
+    must_ok!(c, "SET", "foo", "changed");
+    must_ok!(c, "SET", "new", "key");
+    must_ok!(c, "DEBUG", "RELOAD", "NOSAVE");
+    must_str!(c, "GET", "foo"; "bar");
+    must_nil!(c, "GET", "new");
+
+    must_ok!(c, "SET", "foo", "baz");
+    must_ok!(c, "DEBUG", "RELOAD");
+    must_str!(c, "GET", "foo"; "baz");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_save_errors() {
+    let (_m, mut c) = start().await;
+    let dir = temp_dir("save_errors");
+
+    must_fail!(c, "CONFIG", "SET", "dir", "/no/such/dir"; "No such file or directory");
+    must_fail!(c, "CONFIG", "SET", "dbfilename", "a/b.rdb"; "dbfilename can't be a path");
+    must_fail!(c, "SAVE", "now"; "wrong number of arguments");
+    must_fail!(c, "BGSAVE", "now"; "syntax error");
+    must_fail!(c, "LASTSAVE", "now"; "wrong number of arguments");
+    must_fail!(c, "DEBUG", "nosuch"; "unknown subcommand 'nosuch'");
+    must_fail!(c, "DEBUG", "RELOAD", "now"; "syntax error");
+
+    must_ok!(c, "CONFIG", "SET", "dir", dir.to_str().unwrap());
+    must_fail!(c, "DEBUG", "RELOAD", "NOSAVE"; "Error trying to load the RDB dump");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// ── SLOWLOG ─────────────────────────────────────────────────────────
+
+/// Run SLOWLOG GET and return (id, timestamp, args, client name) per
+/// entry.
+async fn slowlog_get(
+    c: &mut redis::aio::MultiplexedConnection,
+    count: &str,
+) -> Vec<(i64, i64, Vec<String>, String)> {
+    let entries: Vec<(i64, i64, i64, Vec<String>, String, String)> = redis::cmd("SLOWLOG")
+        .arg("GET")
+        .arg(count)
+        .query_async(c)
+        .await
+        .unwrap();
+    entries
+        .into_iter()
+        .map(|(id, time, _, args, _, name)| (id, time, args, name))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_slowlog() {
+    let (m, mut c) = start().await;
+    m.set_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    must_strs!(c, "CONFIG", "GET", "slowlog-*"; ["slowlog-log-slower-than", "10000", "slowlog-max-len", "128"]);
+    must_int!(c, "SLOWLOG", "LEN"; 0);
+
+    must_ok!(c, "CLIENT", "SETNAME", "tooling");
+    must_ok!(c, "CONFIG", "SET", "slowlog-log-slower-than", "0");
+    must_ok!(c, "SET", "foo", "bar");
+    must_fail!(c, "AUTH", "secret"; "without any password configured");
+
+    let entries = slowlog_get(&mut c, "2").await;
+    assert_eq!(
+        entries,
+        vec![
+            (
+                2,
+                1_700_000_000,
+                vec!["AUTH".to_string(), "(redacted)".to_string()],
+                "tooling".to_string()
+            ),
+            (
+                1,
+                1_700_000_000,
+                vec!["SET".to_string(), "foo".to_string(), "bar".to_string()],
+                "tooling".to_string()
+            ),
+        ]
+    );
+    // CONFIG SET, SET, AUTH and the SLOWLOG GET
+    must_int!(c, "SLOWLOG", "LEN"; 4);
+    assert_eq!(slowlog_get(&mut c, "-1").await.len(), 5);
+
+    must_ok!(c, "CONFIG", "SET", "slowlog-max-len", "2");
+    for _ in 0..5 {
+        must_nil!(c, "GET", "nosuch");
+    }
+    must_int!(c, "SLOWLOG", "LEN"; 2);
+
+    must_ok!(c, "SLOWLOG", "RESET");
+    // only the RESET itself
+    must_int!(c, "SLOWLOG", "LEN"; 1);
+
+    must_ok!(c, "CONFIG", "SET", "slowlog-log-slower-than", "-1");
+    must_ok!(c, "SLOWLOG", "RESET");
+    must_ok!(c, "SET", "foo", "bar");
+    must_int!(c, "SLOWLOG", "LEN"; 0);
+}
+
+#[tokio::test]
+async fn test_slowlog_errors() {
+    let (_m, mut c) = start().await;
+
+    must_fail!(c, "SLOWLOG"; "wrong number of arguments");
+    must_fail!(c, "SLOWLOG", "GET", "-2"; "count should be greater than or equal to -1");
+    must_fail!(c, "SLOWLOG", "GET", "x"; "not an integer");
+    must_fail!(c, "SLOWLOG", "LEN", "1"; "wrong number of arguments");
+    must_fail!(c, "SLOWLOG", "NOSUCH"; "unknown subcommand 'nosuch'");
+    must_fail!(c, "CONFIG", "SET", "slowlog-max-len", "-1"; "couldn't be parsed into an integer");
+}
+
+// ── LATENCY ─────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_latency_histogram() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    must_ok!(c, "SET", "foo", "baz");
+    must_str!(c, "GET", "foo"; "baz");
+
+    let v: redis::Value = redis::cmd("LATENCY")
+        .arg("HISTOGRAM")
+        .arg("set")
+        .arg("nosuch")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let redis::Value::Array(items) = v else {
+        panic!("expected array, got {:?}", v);
+    };
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0], redis::Value::BulkString(b"set".to_vec()));
+    let redis::Value::Array(fields) = &items[1] else {
+        panic!("expected array, got {:?}", items[1]);
+    };
+    assert_eq!(fields[0], redis::Value::BulkString(b"calls".to_vec()));
+    assert_eq!(fields[1], redis::Value::Int(2));
+    assert_eq!(
+        fields[2],
+        redis::Value::BulkString(b"histogram_usec".to_vec())
+    );
+    let redis::Value::Array(buckets) = &fields[3] else {
+        panic!("expected array, got {:?}", fields[3]);
+    };
+    // cumulative counts, so the last bucket has all calls
+    assert_eq!(buckets.last(), Some(&redis::Value::Int(2)));
+}
+
+#[tokio::test]
+async fn test_latency_events() {
+    let (m, mut c) = start().await;
+    m.set_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+
+    let latest: Vec<(String, i64, i64, i64)> = redis::cmd("LATENCY")
+        .arg("LATEST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(latest.is_empty());
+
+    must_ok!(c, "CONFIG", "SET", "latency-monitor-threshold", "1");
+    let slow = "local i = 0 while i < 5000000 do i = i + 1 end return i";
+    must_int!(c, "EVAL", slow, "0"; 5_000_000);
+    must_int!(c, "EVAL", slow, "0"; 5_000_000);
+
+    let latest: Vec<(String, i64, i64, i64)> = redis::cmd("LATENCY")
+        .arg("LATEST")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(latest.len(), 1);
+    let (event, time, last, max) = &latest[0];
+    assert_eq!(event, "command");
+    assert_eq!(*time, 1_700_000_000);
+    assert!(*last >= 1 && *max >= *last);
+
+    // both runs fell in the same second
+    let history: Vec<(i64, i64)> = redis::cmd("LATENCY")
+        .arg("HISTORY")
+        .arg("command")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(history, vec![(1_700_000_000, *max)]);
+
+    must_int!(c, "LATENCY", "RESET", "nosuch"; 0);
+    must_int!(c, "LATENCY", "RESET"; 1);
+    let history: Vec<(i64, i64)> = redis::cmd("LATENCY")
+        .arg("HISTORY")
+        .arg("command")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(history.is_empty());
+
+    must_fail!(c, "LATENCY", "HISTORY"; "wrong number of arguments");
+    must_fail!(c, "LATENCY", "DOCTOR"; "unknown subcommand 'doctor'");
+}
+
+#[tokio::test]
+async fn test_info_commandstats() {
+    let (_m, mut c) = start().await;
+
+    must_ok!(c, "SET", "foo", "bar");
+    must_fail!(c, "INCR", "foo"; "not an integer");
+    must_fail!(c, "GET"; "wrong number of arguments");
+    must_ok!(c, "CONFIG", "SET", "maxmemory", "0");
+
+    let v: String = redis::cmd("INFO")
+        .arg("commandstats")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(v.starts_with("# Commandstats\r\n"));
+    assert!(v.contains("cmdstat_set:calls=1,usec="));
+    assert!(v.contains(",rejected_calls=0,failed_calls=1\r\n"), "{}", v);
+    assert!(
+        v.contains("cmdstat_get:calls=0,usec=0,usec_per_call=0.00,rejected_calls=1,failed_calls=0")
+    );
+    assert!(v.contains("cmdstat_config|set:calls=1,"));
+
+    let v: String = redis::cmd("INFO")
+        .arg("latencystats")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(v.starts_with("# Latencystats\r\n"));
+    assert!(v.contains("latency_percentiles_usec_set:p50="));
+    assert!(!v.contains("latency_percentiles_usec_get:"));
+
+    // only with "all"
+    let v: String = redis::cmd("INFO").query_async(&mut c).await.unwrap();
+    assert!(!v.contains("# Commandstats"));
+    let v: String = redis::cmd("INFO")
+        .arg("all")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(v.contains("# Commandstats") && v.contains("# Latencystats"));
+
+    must_ok!(c, "CONFIG", "RESETSTAT");
+    let v: String = redis::cmd("INFO")
+        .arg("commandstats")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert!(!v.contains("cmdstat_set"));
+}
+
+// ── MONITOR ─────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_monitor() {
+    let (m, _c) = start().await;
+    m.set_time(UNIX_EPOCH + Duration::from_micros(1_500_000_250));
+
+    let mut mon = TcpStream::connect(m.addr()).await.unwrap();
+    assert_eq!(raw_cmd(&mut mon, &["MONITOR"]).await, "+OK\r\n");
+
+    let mut s = TcpStream::connect(m.addr()).await.unwrap();
+    let addr = s.local_addr().unwrap();
+    assert_eq!(
+        raw_cmd(&mut s, &["SET", "foo", "bar \"baz\""]).await,
+        "+OK\r\n"
+    );
+    assert_eq!(
+        raw_read(&mut mon).await,
+        format!("+1500.000250 [0 {addr}] \"SET\" \"foo\" \"bar \\\"baz\\\"\"\r\n")
+    );
+
+    raw_cmd(&mut s, &["SELECT", "2"]).await;
+    raw_cmd(&mut s, &["EVAL", "return redis.call('GET', 'x')", "0"]).await;
+    assert_eq!(
+        raw_read(&mut mon).await,
+        format!(
+            "+1500.000250 [2 {addr}] \"SELECT\" \"2\"\r\n\
+             +1500.000250 [2 lua] \"GET\" \"x\"\r\n\
+             +1500.000250 [2 {addr}] \"EVAL\" \"return redis.call('GET', 'x')\" \"0\"\r\n"
+        )
+    );
+
+    // passwords and admin commands stay hidden
+    raw_cmd(&mut s, &["AUTH", "secret"]).await;
+    raw_cmd(&mut s, &["CONFIG", "GET", "maxmemory"]).await;
+    assert_eq!(
+        raw_read(&mut mon).await,
+        format!("+1500.000250 [2 {addr}] \"AUTH\" \"(redacted)\"\r\n")
+    );
+
+    // the monitor can't touch the keyspace anymore
+    let mut res = raw_cmd(&mut mon, &["GET", "foo"]).await;
+    res += &raw_read(&mut mon).await;
+    assert!(res.contains("-ERR Replica can't interact with the keyspace\r\n"));
+    assert!(res.contains(&format!(
+        "[0 {}] \"GET\" \"foo\"",
+        mon.local_addr().unwrap()
+    )));
+}
+
+#[tokio::test]
+async fn test_monitor_errors() {
+    let (_m, mut c) = start().await;
+
+    must_fail!(c, "MONITOR", "now"; "wrong number of arguments");
+    must_fail!(c, "EVAL", "return redis.call('MONITOR')", "0"; "not allowed from script");
+
+    must_ok!(c, "MULTI");
+    must_str!(c, "MONITOR"; "QUEUED");
+    let res: redis::RedisResult<redis::Value> = redis::cmd("EXEC").query_async(&mut c).await;
+    assert!(format!("{:?}", res).contains("MONITOR isn't allowed"));
+}
+
+#[tokio::test]
+async fn test_command_log() {
+    let (m, mut c) = start().await;
+
+    must_ok!(c, "CLIENT", "SETNAME", "logger");
+    assert_eq!(m.command_log().last().unwrap().name(), "CLIENT");
+    m.clear_command_log();
+    must_ok!(c, "SET", "foo", "bar");
+    must_str!(c, "GET", "foo"; "bar");
+    must_nil!(c, "GET", "nosuch");
+    must_fail!(c, "INCR", "foo"; "not an integer");
+    must_ok!(c, "SELECT", "3");
+    must_int!(c, "EVAL", "return redis.call('EXISTS', 'foo')", "0"; 0);
+
+    let log = m.command_log();
+    let summary: Vec<(String, usize, bool, ReplyType)> = log
+        .iter()
+        .map(|l| (l.name(), l.db, l.script, l.reply))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("SET".to_string(), 0, false, ReplyType::Status),
+            ("GET".to_string(), 0, false, ReplyType::Bulk),
+            ("GET".to_string(), 0, false, ReplyType::Nil),
+            ("INCR".to_string(), 0, false, ReplyType::Error),
+            ("SELECT".to_string(), 3, false, ReplyType::Status),
+            ("EXISTS".to_string(), 3, true, ReplyType::Integer),
+            ("EVAL".to_string(), 3, false, ReplyType::Integer),
+        ]
+    );
+    assert_eq!(log[0].command(), vec!["SET", "foo", "bar"]);
+    assert!(
+        log.iter()
+            .all(|l| l.client_name.as_deref() == Some("logger"))
+    );
+
+    m.clear_command_log();
+    assert!(m.command_log().is_empty());
+
+    must_fail!(c, "AUTH", "secret"; "without any password configured");
+    assert_eq!(m.command_log()[0].command(), vec!["AUTH", "(redacted)"]);
+
+    m.disable_command_log();
+    must_ok!(c, "SET", "foo", "baz");
+    assert!(m.command_log().is_empty());
+    m.enable_command_log();
+    must_ok!(c, "SET", "foo", "bar");
+    assert_eq!(m.command_log().len(), 1);
+}
+
+#[tokio::test]
+async fn test_command_log_multi() {
+    let (m, mut c) = start().await;
+
+    m.clear_command_log();
+    must_ok!(c, "MULTI");
+    must_str!(c, "SET", "a", "1"; "QUEUED");
+    must_str!(c, "INCR", "a"; "QUEUED");
+    let _: redis::Value = redis::cmd("EXEC").query_async(&mut c).await.unwrap();
+
+    let names: Vec<String> = m.command_log().iter().map(|l| l.name()).collect();
+    assert_eq!(names, vec!["MULTI", "SET", "INCR", "EXEC"]);
+}
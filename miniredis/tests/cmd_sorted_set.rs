@@ -800,3 +800,153 @@ async fn test_sorted_set_zrank_withscore() {
         _ => panic!("expected array from ZREVRANK WITHSCORE, got {:?}", v),
     }
 }
+
+#[tokio::test]
+async fn test_zmpop() {
+    let (_m, mut c) = helpers::start().await;
+
+    must_int!(c, "ZADD", "z2", "1", "one", "2", "two", "3", "three"; 3);
+
+    let v: (String, Vec<(String, f64)>) = redis::cmd("ZMPOP")
+        .arg(&["2", "z1", "z2", "MIN"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("z2".into(), vec![("one".into(), 1.0)]));
+    let v: (String, Vec<(String, f64)>) = redis::cmd("ZMPOP")
+        .arg(&["2", "z1", "z2", "max", "COUNT", "10"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        (
+            "z2".into(),
+            vec![("three".into(), 3.0), ("two".into(), 2.0)]
+        )
+    );
+    let v: Option<(String, Vec<(String, f64)>)> = redis::cmd("ZMPOP")
+        .arg(&["2", "z1", "z2", "MIN"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, None);
+
+    // Errors
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "ZMPOP", "1", "str", "MIN"; "WRONGTYPE");
+    must_fail!(c, "ZMPOP", "1", "z"; "wrong number of arguments");
+    must_fail!(c, "ZMPOP", "0", "z", "MIN"; "numkeys should be greater than 0");
+    must_fail!(c, "ZMPOP", "2", "z", "MIN"; "syntax error");
+    must_fail!(c, "ZMPOP", "1", "z", "LEFT"; "syntax error");
+    must_fail!(c, "ZMPOP", "1", "z", "MIN", "COUNT", "-1"; "count should be greater than 0");
+}
+
+#[tokio::test]
+async fn test_bzpopmin_bzpopmax() {
+    let (m, mut c) = helpers::start().await;
+
+    // Non-blocking: data available
+    must_int!(c, "ZADD", "z", "1", "one", "2", "two", "3", "three"; 3);
+    let v: (String, String, f64) = redis::cmd("BZPOPMIN")
+        .arg(&["nosuch", "z", "1"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("z".into(), "one".into(), 1.0));
+    let v: (String, String, f64) = redis::cmd("BZPOPMAX")
+        .arg(&["z", "1"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, ("z".into(), "three".into(), 3.0));
+
+    // Timeout
+    let v: Option<(String, String, f64)> = redis::cmd("BZPOPMIN")
+        .arg(&["empty", "0.1"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, None);
+
+    // Blocks until another client adds a member.
+    let client = redis::Client::open(m.redis_url()).unwrap();
+    let mut c2 = client.get_multiplexed_async_connection().await.unwrap();
+    let waiter = tokio::spawn(async move {
+        let v: (String, String, f64) = redis::cmd("BZPOPMIN")
+            .arg(&["jobs", "0"])
+            .query_async(&mut c2)
+            .await
+            .unwrap();
+        v
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    must_int!(c, "ZADD", "jobs", "5", "later", "1", "sooner"; 2);
+    assert_eq!(waiter.await.unwrap(), ("jobs".into(), "sooner".into(), 1.0));
+    must_strs!(c, "ZRANGE", "jobs", "0", "-1"; ["later"]);
+
+    // Inside MULTI it doesn't block.
+    must_ok!(c, "MULTI");
+    let v: String = redis::cmd("BZPOPMAX")
+        .arg(&["empty", "0"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, "QUEUED");
+    let v: Vec<Option<(String, String, f64)>> =
+        redis::cmd("EXEC").query_async(&mut c).await.unwrap();
+    assert_eq!(v, vec![None]);
+
+    // Errors
+    must_ok!(c, "SET", "str", "value");
+    must_fail!(c, "BZPOPMIN", "str", "1"; "WRONGTYPE");
+    must_fail!(c, "BZPOPMIN", "z"; "wrong number of arguments");
+    must_fail!(c, "BZPOPMAX", "z", "-1"; "timeout is negative");
+    must_fail!(c, "BZPOPMAX", "z", "inf"; "timeout is out of range");
+}
+
+#[tokio::test]
+async fn test_bzmpop() {
+    let (m, mut c) = helpers::start().await;
+
+    must_int!(c, "ZADD", "z", "1", "one", "2", "two"; 2);
+    let v: (String, Vec<(String, f64)>) = redis::cmd("BZMPOP")
+        .arg(&["1", "1", "z", "MAX", "COUNT", "5"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        v,
+        ("z".into(), vec![("two".into(), 2.0), ("one".into(), 1.0)])
+    );
+
+    let v: Option<(String, Vec<(String, f64)>)> = redis::cmd("BZMPOP")
+        .arg(&["0.1", "1", "z", "MIN"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(v, None);
+
+    // A blocked client isn't served from a key of another type.
+    let client = redis::Client::open(m.redis_url()).unwrap();
+    let mut c2 = client.get_multiplexed_async_connection().await.unwrap();
+    let waiter = tokio::spawn(async move {
+        let v: (String, Vec<(String, f64)>) = redis::cmd("BZMPOP")
+            .arg(&["0", "2", "a", "b", "MIN"])
+            .query_async(&mut c2)
+            .await
+            .unwrap();
+        v
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    must_int!(c, "RPUSH", "a", "x"; 1);
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    must_int!(c, "ZADD", "b", "7", "m"; 1);
+    assert_eq!(waiter.await.unwrap(), ("b".into(), vec![("m".into(), 7.0)]));
+    must_int!(c, "LLEN", "a"; 1);
+
+    // Errors
+    must_fail!(c, "BZMPOP", "1", "1", "z"; "wrong number of arguments");
+    must_fail!(c, "BZMPOP", "x", "1", "z", "MIN"; "timeout is not a float");
+    must_fail!(c, "BZMPOP", "1", "0", "z", "MIN"; "numkeys should be greater than 0");
+}
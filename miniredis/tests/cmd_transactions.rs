@@ -368,3 +368,36 @@ async fn test_lua_tx_evalsha() {
         _ => panic!("expected array from EXEC, got {:?}", v),
     }
 }
+
+// ── Atomicity under concurrent clients ──────────────────────────────
+
+// Writers on other threads can't interleave with a running EXEC.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_exec_atomic_with_concurrent_writers() {
+    let (_m, c1, mut c2) = start_two_clients().await;
+
+    let writer = tokio::spawn(async move {
+        let mut c = c1;
+        for _ in 0..500 {
+            let _: i64 = redis::cmd("INCR")
+                .arg("n")
+                .query_async(&mut c)
+                .await
+                .unwrap();
+        }
+    });
+    for _ in 0..100 {
+        let (a, b): (Option<i64>, Option<i64>) = redis::pipe()
+            .atomic()
+            .cmd("GET")
+            .arg("n")
+            .cmd("GET")
+            .arg("n")
+            .query_async(&mut c2)
+            .await
+            .unwrap();
+        assert_eq!(a, b);
+    }
+    writer.await.unwrap();
+    must_int!(c2, "GET", "n"; 500);
+}
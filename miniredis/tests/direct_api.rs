@@ -1,4 +1,7 @@
-use miniredis_rs::Miniredis;
+use miniredis_rs::{
+    Change, DumpedKey, KeyChange, Miniredis, PendingEntry, StreamEntry, StreamGroup, Value,
+    WrongType,
+};
 
 // ── String operations ────────────────────────────────────────────────
 
@@ -136,11 +139,11 @@ async fn test_direct_check_set() {
 #[tokio::test]
 async fn test_direct_hash() {
     let m = Miniredis::run().await.unwrap();
-    m.hset("h", "f1", "v1");
-    m.hset("h", "f2", "v2");
+    m.hset("h", "f1", "v1").unwrap();
+    m.hset("h", "f2", "v2").unwrap();
 
-    assert_eq!(m.hget("h", "f1"), Some("v1".to_string()));
-    assert_eq!(m.hget("h", "nosuch"), None);
+    assert_eq!(m.hget("h", "f1").unwrap(), Some("v1".to_string()));
+    assert_eq!(m.hget("h", "nosuch").unwrap(), None);
 
     let keys = m.hkeys("h").unwrap();
     assert_eq!(keys, vec!["f1", "f2"]);
@@ -149,10 +152,34 @@ async fn test_direct_hash() {
 #[tokio::test]
 async fn test_direct_hdel() {
     let m = Miniredis::run().await.unwrap();
-    m.hset("h", "f1", "v1");
-    assert!(m.hdel("h", "f1"));
-    assert!(!m.hdel("h", "f1"));
-    assert_eq!(m.hget("h", "f1"), None);
+    m.hset("h", "f1", "v1").unwrap();
+    assert!(m.hdel("h", "f1").unwrap());
+    assert!(!m.hdel("h", "f1").unwrap());
+    assert_eq!(m.hget("h", "f1").unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_direct_hgetall() {
+    let m = Miniredis::run().await.unwrap();
+    assert!(m.hset("h", "b", "2").unwrap());
+    assert!(m.hset("h", "a", "1").unwrap());
+    assert!(!m.hset("h", "a", "one").unwrap());
+    assert_eq!(
+        m.hgetall("h").unwrap(),
+        vec![
+            ("a".to_string(), "one".to_string()),
+            ("b".to_string(), "2".to_string())
+        ]
+    );
+    assert!(m.hgetall("nosuch").unwrap().is_empty());
+    assert!(m.hkeys("nosuch").unwrap().is_empty());
+
+    m.set("str", "value");
+    let err = m.hgetall("str").unwrap_err();
+    assert!(err.is::<WrongType>());
+    assert!(err.to_string().starts_with("WRONGTYPE"));
+    assert!(m.hset("str", "f", "v").unwrap_err().is::<WrongType>());
+    assert_eq!(m.get("str"), Some("value".to_string()));
 }
 
 // ── Sorted set operations ────────────────────────────────────────────
@@ -160,38 +187,168 @@ async fn test_direct_hdel() {
 #[tokio::test]
 async fn test_direct_sorted_set() {
     let m = Miniredis::run().await.unwrap();
-    assert!(m.zadd("ss", 1.0, "a"));
-    assert!(m.zadd("ss", 3.0, "c"));
-    assert!(m.zadd("ss", 2.0, "b"));
+    assert!(m.zadd("ss", 1.0, "a").unwrap());
+    assert!(m.zadd("ss", 3.0, "c").unwrap());
+    assert!(m.zadd("ss", 2.0, "b").unwrap());
 
     // Update existing
-    assert!(!m.zadd("ss", 1.5, "a"));
+    assert!(!m.zadd("ss", 1.5, "a").unwrap());
 
-    assert_eq!(m.zscore("ss", "a"), Some(1.5));
-    assert_eq!(m.zscore("ss", "nosuch"), None);
+    assert_eq!(m.zscore("ss", "a").unwrap(), Some(1.5));
+    assert_eq!(m.zscore("ss", "nosuch").unwrap(), None);
 
     let members = m.zmembers("ss").unwrap();
     assert_eq!(members, vec!["a", "b", "c"]); // sorted by score
 }
 
+#[tokio::test]
+async fn test_direct_zrange_with_scores() {
+    let m = Miniredis::run().await.unwrap();
+    m.zadd("ss", 1.0, "a").unwrap();
+    m.zadd("ss", 2.0, "b").unwrap();
+    m.zadd("ss", 3.0, "c").unwrap();
+    assert_eq!(m.zincrby("ss", 2.5, "a").unwrap(), 3.5);
+    assert_eq!(m.zincrby("ss", 0.5, "d").unwrap(), 0.5);
+    assert!(m.zrem("ss", "b").unwrap());
+    assert!(!m.zrem("ss", "b").unwrap());
+    assert_eq!(m.zcard("ss").unwrap(), 3);
+
+    let pairs = |v: &[(&str, f64)]| -> Vec<(String, f64)> {
+        v.iter().map(|(m, s)| (m.to_string(), *s)).collect()
+    };
+    assert_eq!(
+        m.zrange_with_scores("ss", 0, -1).unwrap(),
+        pairs(&[("d", 0.5), ("c", 3.0), ("a", 3.5)])
+    );
+    assert_eq!(
+        m.zrange_with_scores("ss", -2, 5).unwrap(),
+        pairs(&[("c", 3.0), ("a", 3.5)])
+    );
+    assert!(m.zrange_with_scores("ss", 3, -1).unwrap().is_empty());
+    assert!(m.zrange_with_scores("nosuch", 0, -1).unwrap().is_empty());
+
+    m.push("list", &["a"]);
+    assert!(m.zadd("list", 1.0, "a").unwrap_err().is::<WrongType>());
+    assert!(m.zscore("list", "a").unwrap_err().is::<WrongType>());
+}
+
 // ── Stream operations ────────────────────────────────────────────────
 
 #[tokio::test]
 async fn test_direct_stream() {
     let m = Miniredis::run().await.unwrap();
-    let id = m.xadd("stream", "1-0", &[("field", "value")]);
+    let id = m.xadd("stream", "1-0", &[("field", "value")]).unwrap();
     assert_eq!(id, "1-0");
     assert_eq!(m.key_type("stream"), "stream");
 }
 
+#[tokio::test]
+async fn test_direct_stream_range() {
+    let m = Miniredis::run().await.unwrap();
+    m.xadd("s", "1-1", &[("f", "v")]).unwrap();
+    m.xadd("s", "2-1", &[("f", "w"), ("g", "x")]).unwrap();
+    m.xadd("s", "3-1", &[("f", "y")]).unwrap();
+    assert_eq!(m.xlen("s").unwrap(), 3);
+
+    let entry = |id: &str, values: &[(&str, &str)]| StreamEntry {
+        id: id.to_string(),
+        values: values
+            .iter()
+            .map(|(f, v)| (f.to_string(), v.to_string()))
+            .collect(),
+    };
+    assert_eq!(
+        m.xrange("s", "2", "+").unwrap(),
+        vec![
+            entry("2-1", &[("f", "w"), ("g", "x")]),
+            entry("3-1", &[("f", "y")])
+        ]
+    );
+    assert_eq!(
+        m.xrange("s", "-", "(2-1").unwrap(),
+        vec![entry("1-1", &[("f", "v")])]
+    );
+    assert!(m.xrange("nosuch", "-", "+").unwrap().is_empty());
+    assert!(m.xrange("s", "bad", "+").is_err());
+
+    // Errors leave the stream alone.
+    let err = m.xadd("s", "2-1", &[("f", "v")]).unwrap_err();
+    assert!(err.to_string().contains("equal or smaller"));
+    assert!(m.xadd("new", "0-0", &[("f", "v")]).is_err());
+    assert!(!m.exists("new"));
+
+    m.set("str", "value");
+    assert!(
+        m.xadd("str", "*", &[("f", "v")])
+            .unwrap_err()
+            .is::<WrongType>()
+    );
+    assert!(m.xlen("str").unwrap_err().is::<WrongType>());
+}
+
+#[tokio::test]
+async fn test_direct_stream_groups() {
+    let m = Miniredis::run().await.unwrap();
+    let now = std::time::SystemTime::now();
+    m.set_time(now);
+    m.xgroup_create("s", "grp", "$").unwrap();
+    assert_eq!(m.xlen("s").unwrap(), 0);
+    assert!(m.xgroup_create("s", "grp", "$").is_err());
+    m.xadd("s", "1-1", &[("f", "v")]).unwrap();
+    m.xadd("s", "2-1", &[("f", "w")]).unwrap();
+
+    let read = m.xreadgroup("s", "grp", "alice", Some(1)).unwrap();
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].id, "1-1");
+    let read = m.xreadgroup("s", "grp", "bob", None).unwrap();
+    assert_eq!(read.len(), 1);
+    assert_eq!(read[0].id, "2-1");
+    assert!(m.xreadgroup("s", "grp", "bob", None).unwrap().is_empty());
+
+    let pending = |id: &str, consumer: &str| PendingEntry {
+        id: id.to_string(),
+        consumer: consumer.to_string(),
+        delivery_count: 1,
+        last_delivery: now,
+    };
+    assert_eq!(
+        m.xgroup_pending("s", "grp").unwrap(),
+        vec![pending("1-1", "alice"), pending("2-1", "bob")]
+    );
+    assert_eq!(m.xack("s", "grp", &["1-1", "9-9"]).unwrap(), 1);
+    assert_eq!(
+        m.xgroups("s").unwrap(),
+        vec![StreamGroup {
+            name: "grp".to_string(),
+            last_id: "2-1".to_string(),
+            pending: vec![pending("2-1", "bob")],
+            consumers: vec!["alice".to_string(), "bob".to_string()],
+        }]
+    );
+
+    assert!(
+        m.xgroup_pending("s", "nosuch")
+            .unwrap_err()
+            .to_string()
+            .starts_with("NOGROUP")
+    );
+    assert!(m.xreadgroup("nosuch", "grp", "alice", None).is_err());
+    assert!(m.xgroups("nosuch").unwrap().is_empty());
+}
+
 // ── HyperLogLog operations ──────────────────────────────────────────
 
 #[tokio::test]
 async fn test_direct_hll() {
     let m = Miniredis::run().await.unwrap();
-    assert!(m.pfadd("hll", &["a", "b", "c"]));
-    assert!(!m.pfadd("hll", &["a", "b"])); // no new elements
-    assert_eq!(m.pfcount("hll"), 3);
+    assert!(m.pfadd("hll", &["a", "b", "c"]).unwrap());
+    assert!(!m.pfadd("hll", &["a", "b"]).unwrap()); // no new elements
+    assert_eq!(m.pfcount("hll").unwrap(), 3);
+    assert_eq!(m.pfcount("nosuch").unwrap(), 0);
+
+    m.set("str", "value");
+    assert!(m.pfadd("str", &["a"]).unwrap_err().is::<WrongType>());
+    assert!(m.pfcount("str").unwrap_err().is::<WrongType>());
 }
 
 // ── TTL operations ───────────────────────────────────────────────────
@@ -333,16 +490,370 @@ async fn test_direct_restart() {
     assert_eq!(v, "restart");
 }
 
+// ── Persistence ─────────────────────────────────────────────────────
+
+fn rdb_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("miniredis-{}-{}.rdb", std::process::id(), name))
+}
+
+#[tokio::test]
+async fn test_direct_bytes() {
+    let m = Miniredis::run().await.unwrap();
+    let key: &[u8] = b"k\xff\x00";
+
+    m.set_bytes(key, b"\x01\xfe");
+    assert!(m.exists_bytes(key));
+    assert_eq!(m.get_bytes(key), Some(b"\x01\xfe".to_vec()));
+    assert_eq!(m.keys_bytes(), vec![key.to_vec()]);
+    assert_eq!(m.keys(), vec!["k\u{fffd}\0"]);
+    assert!(!m.exists("k\u{fffd}\0"));
+    assert!(m.del_bytes(key));
+
+    m.push_bytes(b"list\xff", &[b"\x00", b"\xff"]);
+    assert_eq!(
+        m.list_bytes(b"list\xff").unwrap(),
+        vec![b"\x00".to_vec(), b"\xff".to_vec()]
+    );
+    assert_eq!(m.set_add_bytes(b"set", &[b"\xff", b"\xfe", b"\xff"]), 2);
+    assert!(m.is_member_bytes(b"set", b"\xfe"));
+    assert_eq!(
+        m.members_bytes(b"set").unwrap(),
+        vec![b"\xfe".to_vec(), b"\xff".to_vec()]
+    );
+    m.hset_bytes(b"hash", b"f\xff", b"v\x00").unwrap();
+    assert_eq!(
+        m.hget_bytes(b"hash", b"f\xff").unwrap(),
+        Some(b"v\x00".to_vec())
+    );
+    assert_eq!(m.hkeys_bytes(b"hash").unwrap(), vec![b"f\xff".to_vec()]);
+    assert!(m.zadd_bytes(b"zset", 2.0, b"\xff").unwrap());
+    assert!(m.zadd_bytes(b"zset", 1.0, b"\xfe").unwrap());
+    assert_eq!(m.zscore_bytes(b"zset", b"\xff").unwrap(), Some(2.0));
+    assert_eq!(
+        m.zmembers_bytes(b"zset").unwrap(),
+        vec![b"\xfe".to_vec(), b"\xff".to_vec()]
+    );
+    m.db(2).set_bytes(b"\xff", b"x");
+    assert!(m.db(2).exists_bytes(b"\xff"));
+    assert_eq!(m.db(2).get_bytes(b"\xff"), Some(b"x".to_vec()));
+    assert_eq!(m.db(2).keys_bytes(), vec![b"\xff".to_vec()]);
+
+    // Binary keys, members and fields survive an RDB round trip.
+    let path = rdb_path("bytes");
+    m.save_to(&path).unwrap();
+    let m2 = Miniredis::run().await.unwrap();
+    m2.load_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(m2.keys_bytes(), m.keys_bytes());
+    assert!(m2.is_member_bytes(b"set", b"\xff"));
+    assert_eq!(
+        m2.hget_bytes(b"hash", b"f\xff").unwrap(),
+        Some(b"v\x00".to_vec())
+    );
+    assert_eq!(m2.zscore_bytes(b"zset", b"\xfe").unwrap(), Some(1.0));
+}
+
+#[tokio::test]
+async fn test_direct_save_load() {
+    let m = Miniredis::run().await.unwrap();
+    let client = redis::Client::open(m.redis_url()).unwrap();
+    let mut c = client.get_multiplexed_async_connection().await.unwrap();
+
+    m.set("str", "value");
+    m.set_ttl("str", std::time::Duration::from_secs(60));
+    m.push("list", &["a", "b", "c"]);
+    m.set_add("set", &["x", "y"]);
+    m.hset("hash", "field", "value").unwrap();
+    m.zadd("zset", 1.5, "one").unwrap();
+    m.zadd("zset", f64::INFINITY, "inf").unwrap();
+    m.pfadd("hll", &["a", "b", "c"]).unwrap();
+    m.db(3).set("other", "db");
+    m.xadd("stream", "1-1", &[("f", "v")]).unwrap();
+    m.xadd("stream", "2-1", &[("f", "w"), ("g", "x")]).unwrap();
+    let _: () = redis::cmd("XGROUP")
+        .arg(&["CREATE", "stream", "grp", "0"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let _: redis::Value = redis::cmd("XREADGROUP")
+        .arg(&[
+            "GROUP", "grp", "alice", "COUNT", "1", "STREAMS", "stream", ">",
+        ])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let _: String = redis::cmd("FUNCTION")
+        .arg("LOAD")
+        .arg("#!lua name=lib\nredis.register_function('hi', function() return 'hi' end)")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+
+    let path = rdb_path("save_load");
+    m.save_to(&path).unwrap();
+
+    let m2 = Miniredis::run().await.unwrap();
+    m2.set("gone", "after load");
+    m2.load_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!m2.exists("gone"));
+    assert_eq!(m2.get("str"), Some("value".to_string()));
+    let ttl = m2.ttl("str").unwrap();
+    assert!(ttl > std::time::Duration::from_secs(55) && ttl <= std::time::Duration::from_secs(60));
+    assert_eq!(m2.list("list").unwrap(), vec!["a", "b", "c"]);
+    assert_eq!(m2.members("set").unwrap(), vec!["x", "y"]);
+    assert_eq!(m2.hget("hash", "field").unwrap(), Some("value".to_string()));
+    assert_eq!(m2.zscore("zset", "one").unwrap(), Some(1.5));
+    assert_eq!(m2.zscore("zset", "inf").unwrap(), Some(f64::INFINITY));
+    assert_eq!(m2.key_type("hll"), "hll");
+    assert_eq!(m2.pfcount("hll").unwrap(), 3);
+    assert_eq!(m2.db(3).get("other"), Some("db".to_string()));
+
+    let client = redis::Client::open(m2.redis_url()).unwrap();
+    let mut c = client.get_multiplexed_async_connection().await.unwrap();
+    let entries: Vec<(String, Vec<String>)> = redis::cmd("XRANGE")
+        .arg(&["stream", "-", "+"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(
+        entries,
+        vec![
+            ("1-1".to_string(), vec!["f".to_string(), "v".to_string()]),
+            (
+                "2-1".to_string(),
+                vec!["f", "w", "g", "x"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            ),
+        ]
+    );
+    let (count, first, last, consumers): (i64, String, String, Vec<(String, String)>) =
+        redis::cmd("XPENDING")
+            .arg(&["stream", "grp"])
+            .query_async(&mut c)
+            .await
+            .unwrap();
+    assert_eq!((count, first.as_str(), last.as_str()), (1, "1-1", "1-1"));
+    assert_eq!(consumers, vec![("alice".to_string(), "1".to_string())]);
+    let id: String = redis::cmd("XADD")
+        .arg(&["stream", "*", "f", "z"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_ne!(id, "2-1");
+    let hi: String = redis::cmd("FCALL")
+        .arg(&["hi", "0"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(hi, "hi");
+}
+
+#[tokio::test]
+async fn test_direct_load_expired() {
+    let m = Miniredis::run().await.unwrap();
+    let now = std::time::SystemTime::now();
+    m.set_time(now);
+    m.set("short", "lived");
+    m.set_ttl("short", std::time::Duration::from_secs(10));
+    m.set("long", "lived");
+    m.set_ttl("long", std::time::Duration::from_secs(100));
+
+    let path = rdb_path("load_expired");
+    m.save_to(&path).unwrap();
+
+    let m2 = Miniredis::run().await.unwrap();
+    m2.set_time(now + std::time::Duration::from_secs(50));
+    m2.load_from(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(m2.keys(), vec!["long".to_string()]);
+    assert_eq!(m2.ttl("long"), Some(std::time::Duration::from_secs(50)));
+}
+
+#[tokio::test]
+async fn test_direct_load_errors() {
+    let m = Miniredis::run().await.unwrap();
+    m.set("k", "v");
+    assert!(m.load_from(rdb_path("nosuch")).is_err());
+
+    let path = rdb_path("load_errors");
+    std::fs::write(&path, b"REDIS0011\xfe\x00\x00").unwrap();
+    assert!(m.load_from(&path).is_err());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(m.get("k"), Some("v".to_string()));
+}
+
+// ── Checkpoints ─────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn test_direct_checkpoint() {
+    let m = Miniredis::run().await.unwrap();
+    let client = redis::Client::open(m.redis_url()).unwrap();
+    let mut c = client.get_multiplexed_async_connection().await.unwrap();
+
+    m.set_time(std::time::SystemTime::now());
+    m.set("str", "value");
+    m.set_ttl("str", std::time::Duration::from_secs(60));
+    m.db(2).set("other", "db");
+    m.xadd("stream", "1-1", &[("f", "v")]).unwrap();
+    let _: () = redis::cmd("XGROUP")
+        .arg(&["CREATE", "stream", "grp", "0"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let sha: String = redis::cmd("SCRIPT")
+        .arg(&["LOAD", "return 1"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let checkpoint = m.checkpoint();
+    assert!(m.diff(&checkpoint).is_empty());
+
+    m.fast_forward(std::time::Duration::from_secs(61));
+    m.set("new", "key");
+    m.db(2).set("other", "changed");
+    let _: redis::Value = redis::cmd("XREADGROUP")
+        .arg(&["GROUP", "grp", "alice", "STREAMS", "stream", ">"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    let _: () = redis::cmd("SCRIPT")
+        .arg("FLUSH")
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    m.require_auth("secret");
+    let change = |db, key: &str, change| KeyChange {
+        db,
+        key: key.as_bytes().to_vec(),
+        change,
+    };
+    assert_eq!(
+        m.diff(&checkpoint),
+        vec![
+            change(0, "new", Change::Added),
+            change(0, "str", Change::Removed),
+            change(0, "stream", Change::Modified),
+            change(2, "other", Change::Modified),
+        ]
+    );
+
+    m.restore(&checkpoint);
+    assert!(m.diff(&checkpoint).is_empty());
+    assert_eq!(m.get("str"), Some("value".to_string()));
+    assert_eq!(m.ttl("str"), Some(std::time::Duration::from_secs(60)));
+    assert!(!m.exists("new"));
+    assert_eq!(m.db(2).get("other"), Some("db".to_string()));
+
+    // Auth is off again, and the script and the empty PEL are back.
+    let mut c = client.get_multiplexed_async_connection().await.unwrap();
+    let exists: Vec<i64> = redis::cmd("SCRIPT")
+        .arg(&["EXISTS", &sha])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(exists, vec![1]);
+    let (count, ..): (i64, redis::Value, redis::Value, redis::Value) = redis::cmd("XPENDING")
+        .arg(&["stream", "grp"])
+        .query_async(&mut c)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    // A checkpoint can be restored again.
+    m.del("str");
+    m.restore(&checkpoint);
+    assert!(m.exists("str"));
+}
+
 // ── Dump ────────────────────────────────────────────────────────────
 
+#[tokio::test]
+async fn test_direct_dump_db() {
+    let m = Miniredis::run().await.unwrap();
+    let now = std::time::SystemTime::now();
+    m.set_time(now);
+    m.set("str", "hello");
+    m.set_ttl("str", std::time::Duration::from_secs(10));
+    m.push("list", &["b", "a"]);
+    m.set_add("set", &["y", "x"]);
+    m.hset("hash", "f", "v").unwrap();
+    m.zadd("zset", 2.0, "two").unwrap();
+    m.zadd("zset", 1.0, "one").unwrap();
+    m.xadd("stream", "1-1", &[("f", "v")]).unwrap();
+    m.xgroup_create("stream", "grp", "0").unwrap();
+    m.xreadgroup("stream", "grp", "alice", None).unwrap();
+    m.pfadd("hll", &["a", "b"]).unwrap();
+    m.db(1).set("other", "db");
+
+    let strings = |v: &[&str]| -> Vec<String> { v.iter().map(|s| s.to_string()).collect() };
+    let key = |key: &str, value| DumpedKey {
+        key: key.to_string(),
+        ttl: None,
+        value,
+    };
+    let entry = StreamEntry {
+        id: "1-1".to_string(),
+        values: vec![("f".to_string(), "v".to_string())],
+    };
+    let group = StreamGroup {
+        name: "grp".to_string(),
+        last_id: "1-1".to_string(),
+        pending: vec![PendingEntry {
+            id: "1-1".to_string(),
+            consumer: "alice".to_string(),
+            delivery_count: 1,
+            last_delivery: now,
+        }],
+        consumers: strings(&["alice"]),
+    };
+    assert_eq!(
+        m.dump_db(),
+        vec![
+            key(
+                "hash",
+                Value::Hash(vec![("f".to_string(), "v".to_string())])
+            ),
+            key("hll", Value::HyperLogLog(2)),
+            key("list", Value::List(strings(&["b", "a"]))),
+            key("set", Value::Set(strings(&["x", "y"]))),
+            DumpedKey {
+                key: "str".to_string(),
+                ttl: Some(std::time::Duration::from_secs(10)),
+                value: Value::String("hello".to_string()),
+            },
+            key(
+                "stream",
+                Value::Stream {
+                    entries: vec![entry],
+                    groups: vec![group],
+                }
+            ),
+            key(
+                "zset",
+                Value::SortedSet(vec![("one".to_string(), 1.0), ("two".to_string(), 2.0)])
+            ),
+        ]
+    );
+    assert_eq!(
+        m.db(1).dump_db(),
+        vec![key("other", Value::String("db".to_string()))]
+    );
+    assert!(m.db(2).dump_db().is_empty());
+}
+
 #[tokio::test]
 async fn test_direct_dump() {
     let m = Miniredis::run().await.unwrap();
     m.set("str", "hello");
     m.push("mylist", &["a", "b", "c"]);
     m.set_add("myset", &["x", "y"]);
-    m.hset("myhash", "f1", "v1");
-    m.zadd("myzset", 1.5, "member1");
+    m.hset("myhash", "f1", "v1").unwrap();
+    m.zadd("myzset", 1.5, "member1").unwrap();
 
     let dump = m.dump();
 
@@ -1,5 +1,7 @@
 use miniredis_rs::Miniredis;
 use redis::aio::MultiplexedConnection;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
 
 /// Spin up a server + connected client — equivalent to Go's `runWithClient(t)`.
 pub async fn start() -> (Miniredis, MultiplexedConnection) {
@@ -26,6 +28,28 @@ pub async fn start_two_clients() -> (Miniredis, MultiplexedConnection, Multiplex
     (m, c1, c2)
 }
 
+/// Send a command over a raw connection and return what the server
+/// answers (including any pushes that arrive in the meantime).
+#[allow(dead_code)]
+pub async fn raw_cmd(stream: &mut TcpStream, args: &[&str]) -> String {
+    let mut cmd = format!("*{}\r\n", args.len());
+    for arg in args {
+        cmd.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    stream.write_all(cmd.as_bytes()).await.unwrap();
+    raw_read(stream).await
+}
+
+/// Read whatever the server sent, or "" if nothing arrives.
+#[allow(dead_code)]
+pub async fn raw_read(stream: &mut TcpStream) -> String {
+    let mut buf = vec![0u8; 4096];
+    match tokio::time::timeout(std::time::Duration::from_millis(200), stream.read(&mut buf)).await {
+        Ok(n) => String::from_utf8_lossy(&buf[..n.unwrap()]).into_owned(),
+        Err(_) => String::new(),
+    }
+}
+
 // ── Assertion macros ─────────────────────────────────────────────────
 
 /// Execute a command and assert it returns "OK".
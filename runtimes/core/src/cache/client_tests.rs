@@ -1,6 +1,8 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
 
+use miniredis_rs::EvictionPolicy;
+
 use crate::cache::client::{Client, ListDirection, TtlOp};
 use crate::cache::error::Error;
 use crate::cache::miniredis::MiniredisServer;
@@ -17,7 +19,7 @@ fn new_test_pool() -> Client {
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().expect("failed to create runtime");
             let server = rt
-                .block_on(MiniredisServer::start())
+                .block_on(MiniredisServer::start(EvictionPolicy::AllKeysLru))
                 .expect("failed to start miniredis for tests");
             tx.send(server).expect("failed to send server");
             // Park forever to keep the runtime (and its server task) alive.
@@ -3,11 +3,13 @@ use std::sync::Arc;
 
 use anyhow::Context;
 use bb8_redis::redis;
+use miniredis_rs::EvictionPolicy;
 use redis::{ConnectionAddr, IntoConnectionInfo, RedisConnectionInfo, TlsCertificates};
 
 use crate::cache::client::Client;
 use crate::cache::miniredis::MiniredisServer;
 use crate::cache::noop::NoopCluster;
+use crate::encore::parser::meta::v1 as meta;
 use crate::encore::runtime::v1 as pb;
 use crate::names::EncoreName;
 use crate::secrets;
@@ -25,6 +27,7 @@ pub struct Manager {
 /// Configuration for creating a Manager.
 pub struct ManagerConfig<'a> {
     pub clusters: Vec<pb::RedisCluster>,
+    pub md: &'a meta::Data,
     pub creds: &'a pb::infrastructure::Credentials,
     pub secrets: &'a secrets::Manager,
     pub tracer: Tracer,
@@ -45,7 +48,7 @@ impl ManagerConfig<'_> {
             log::debug!("cache: starting in-process miniredis server");
             let server = self
                 .runtime
-                .block_on(MiniredisServer::start())
+                .block_on(MiniredisServer::start(eviction_policy(self.md)))
                 .context("failed to start miniredis server")?;
             let url = format!("redis://{}", server.addr());
             let client = redis::Client::open(url).context("failed to create miniredis client")?;
@@ -151,6 +154,15 @@ impl Cluster for ClusterImpl {
     }
 }
 
+/// The eviction policy for the miniredis server, which all clusters share:
+/// the one the app's first cache cluster declares, or allkeys-lru.
+fn eviction_policy(md: &meta::Data) -> EvictionPolicy {
+    md.cache_clusters
+        .iter()
+        .find_map(|c| EvictionPolicy::parse(&c.eviction_policy))
+        .unwrap_or(EvictionPolicy::AllKeysLru)
+}
+
 /// Builds cluster configurations from proto config.
 fn clusters_from_cfg(
     clusters: Vec<pb::RedisCluster>,
@@ -1,13 +1,17 @@
 use std::net::SocketAddr;
 use std::time::Duration;
 
-use miniredis_rs::Miniredis;
+use miniredis_rs::{EvictionPolicy, Miniredis};
 
-/// An in-process miniredis server with a background cleanup task
-/// that fast-forwards time and prunes excess keys (matching the
-/// behavior of the old Go miniredis-encore binary).
+/// Memory limit of the in-process server; keys are evicted following the
+/// cluster's eviction policy beyond it.
+const MAX_MEMORY: u64 = 100 * 1024 * 1024;
+
+/// An in-process miniredis server with a background task that
+/// fast-forwards time (matching the behavior of the old Go
+/// miniredis-encore binary).
 ///
-/// The server and cleanup task run as tokio tasks and will shut
+/// The server and the task run as tokio tasks and will shut
 /// down when the tokio runtime is dropped.
 pub struct MiniredisServer {
     server: Miniredis,
@@ -15,18 +19,23 @@ pub struct MiniredisServer {
 }
 
 impl MiniredisServer {
-    /// Start a new in-process miniredis server on a random port.
+    /// Start a new in-process miniredis server on a random port,
+    /// evicting keys following `eviction_policy` once it uses more
+    /// than 100 MiB.
     ///
-    /// Also spawns a background cleanup task that fast-forwards
-    /// time by 1s every second and prunes keys above 100 every 15s.
-    pub async fn start() -> anyhow::Result<Self> {
+    /// Also spawns a background task that fast-forwards
+    /// time by 1s every second.
+    pub async fn start(eviction_policy: EvictionPolicy) -> anyhow::Result<Self> {
         let server = Miniredis::run()
             .await
             .map_err(|e| anyhow::anyhow!("failed to start miniredis: {}", e))?;
+        server.set_maxmemory(MAX_MEMORY, eviction_policy);
+        // Nothing reads the command log of the dev cache.
+        server.disable_command_log();
         let addr = server.addr();
 
-        // Spawn the cleanup task on the current runtime.
-        tokio::spawn(cleanup_task(server.clone()));
+        // Spawn the fast-forward task on the current runtime.
+        tokio::spawn(fast_forward_task(server.clone()));
 
         Ok(Self { server, addr })
     }
@@ -42,25 +51,12 @@ impl MiniredisServer {
     }
 }
 
-/// Background task that fast-forwards miniredis time and periodically prunes
-/// excess keys, matching the Go binary's doCleanup behavior.
-async fn cleanup_task(server: Miniredis) {
+/// Background task that fast-forwards miniredis time so keys expire,
+/// matching the Go binary's doCleanup behavior.
+async fn fast_forward_task(server: Miniredis) {
     let mut interval = tokio::time::interval(Duration::from_secs(1));
-    let mut acc = Duration::ZERO;
     loop {
         interval.tick().await;
         server.fast_forward(Duration::from_secs(1));
-        acc += Duration::from_secs(1);
-        if acc >= Duration::from_secs(15) {
-            acc = Duration::ZERO;
-            // Prune to 100 keys.
-            let keys = server.keys();
-            if keys.len() > 100 {
-                let to_delete = keys.len() - 100;
-                for key in keys.iter().take(to_delete) {
-                    server.del(key);
-                }
-            }
-        }
     }
 }
@@ -386,6 +386,7 @@ impl Runtime {
 
         let cache = cache::ManagerConfig {
             clusters: resources.redis_clusters,
+            md: &md,
             creds: &creds,
             secrets: &secrets,
             tracer: tracer.clone(),